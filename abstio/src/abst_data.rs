@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use serde::{Deserialize, Serialize};
 
-use crate::CityName;
+use crate::{CityName, MapName};
 
 /// A list of all canonical data files for A/B Street that're uploaded somewhere. The file formats
 /// are tied to the latest version of the git repo. Players use the updater crate to sync these
@@ -139,6 +139,13 @@ impl Manifest {
         let path = path.strip_prefix(&crate::path("")).unwrap_or(path);
         self.entries.get(&format!("data/{}", path))
     }
+
+    /// Identifies the version of a map's data, to tell if something made for one copy of a map
+    /// matches another. None if the map isn't part of the canonical data.
+    pub fn map_version(&self, name: &MapName) -> Option<String> {
+        self.get_entry(&name.path())
+            .map(|entry| entry.checksum.clone())
+    }
 }
 
 /// Player-chosen groups of files to opt into downloading
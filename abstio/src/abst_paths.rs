@@ -264,6 +264,15 @@ impl MapName {
         ))
     }
 
+    /// Returns the filesystem path to this map's pathfinder, when it's stored separately from the
+    /// map. This isn't in the `maps` directory, so it isn't mistaken for another map.
+    pub fn pathfinder_path(&self) -> String {
+        path(format!(
+            "system/{}/{}/pathfinders/{}.bin",
+            self.city.country, self.city.city, self.map
+        ))
+    }
+
     /// Returns all maps from one city that're available locally.
     fn list_all_maps_in_city_locally(city: &CityName) -> Vec<MapName> {
         let mut names = Vec::new();
@@ -341,6 +350,13 @@ pub fn path_prebaked_results(name: &MapName, scenario_name: &str) -> String {
     ))
 }
 
+pub fn path_prebaked_reliability(name: &MapName, scenario_name: &str) -> String {
+    path(format!(
+        "system/{}/{}/prebaked_results/{}/{}_reliability.bin",
+        name.city.country, name.city.city, name.map, scenario_name
+    ))
+}
+
 pub fn path_scenario(name: &MapName, scenario_name: &str) -> String {
     // TODO Getting complicated. Sometimes we're trying to load, so we should look for .bin, then
     // .json. But when we're writing a custom scenario, we actually want to write a .bin.
@@ -367,6 +383,11 @@ pub fn path_all_scenarios(name: &MapName) -> String {
     ))
 }
 
+/// A translation of the tutorial, like "de" for German
+pub fn path_tutorial(language: &str) -> String {
+    path(format!("system/tutorials/{}.json", language))
+}
+
 /// Extract the map and scenario name from a path. Crashes if the input is strange.
 pub fn parse_scenario_path(path: &str) -> (MapName, String) {
     // TODO regex
@@ -431,6 +452,13 @@ pub fn path_all_saves(name: &MapName, edits_name: &str, run_name: &str) -> Strin
     ))
 }
 
+pub fn path_reliability(name: &MapName, scenario_name: &str, edits_name: &str) -> String {
+    path(format!(
+        "player/reliability/{}/{}/{}/{}/{}.bin",
+        name.city.country, name.city.city, name.map, scenario_name, edits_name
+    ))
+}
+
 pub fn path_trips(name: &MapName) -> String {
     path(format!(
         "player/routes/{}/{}/{}.json",
@@ -47,15 +47,71 @@ fn inner_slurp_file(path: &str) -> Result<Vec<u8>> {
     .with_context(|| path.to_string())
 }
 
+/// Transparently handles files written by `write_compressed_binary`.
 pub fn maybe_read_binary<T: DeserializeOwned>(path: String, timer: &mut Timer) -> Result<T> {
     if !path.ends_with(".bin") {
         panic!("read_binary needs {} to end with .bin", path);
     }
 
+    if is_compressed(&path) {
+        // The decoder doesn't necessarily read to the end of the file, which Timer's progress
+        // tracking expects, so just read everything at once. Deserializing from one buffer is
+        // much faster than through the decoder, which bincode reads a few bytes at a time.
+        timer.start(format!("decompress {}", path));
+        let result = || -> Result<T> {
+            let file = File::open(&path)?;
+            // Safety: see maybe_read_binary_mmap
+            let mmap = unsafe { memmap2::Mmap::map(file.file())? };
+            let bytes = zstd::stream::decode_all(&mmap[..])?;
+            Ok(bincode::deserialize(&bytes)?)
+        }()
+        .with_context(|| path.clone());
+        timer.stop(format!("decompress {}", path));
+        return result;
+    }
+
     timer.read_file(&path)?;
     bincode::deserialize_from(timer).map_err(|err| err.into())
 }
 
+/// Deserializes straight from a memory-mapped file, instead of reading it through a buffer first.
+/// The result is still fully deserialized into memory. Compressed files are handled like
+/// `maybe_read_binary`.
+pub fn maybe_read_binary_mmap<T: DeserializeOwned>(path: String, timer: &mut Timer) -> Result<T> {
+    if !path.ends_with(".bin") {
+        panic!("read_binary needs {} to end with .bin", path);
+    }
+    if is_compressed(&path) {
+        return maybe_read_binary(path, timer);
+    }
+
+    timer.start(format!("map {} into memory", path));
+    let result = || -> Result<T> {
+        let file = File::open(&path)?;
+        // Safety: nothing should modify the file while it's being read. If something does, the
+        // result is garbage, just like if the file were modified partway through a normal read.
+        let mmap = unsafe { memmap2::Mmap::map(file.file())? };
+        Ok(bincode::deserialize(&mmap[..])?)
+    }()
+    .with_context(|| path.clone());
+    timer.stop(format!("map {} into memory", path));
+    result
+}
+
+// zstd frames start with this
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+// Low levels are nearly as small as the defaults, but much faster to write. Big savestates are
+// dominated by the time to write bytes, not compress them.
+const ZSTD_LEVEL: i32 = 1;
+
+fn is_compressed(path: &str) -> bool {
+    let mut magic = [0; 4];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok()
+        && magic == ZSTD_MAGIC
+}
+
 // TODO Idea: Have a wrapper type DotJSON(...) and DotBin(...) to distinguish raw path strings
 fn maybe_write_json<T: Serialize>(path: &str, obj: &T) -> Result<()> {
     if !path.ends_with(".json") {
@@ -95,6 +151,36 @@ pub fn write_binary<T: Serialize>(path: String, obj: &T) {
     info!("Wrote {}", path);
 }
 
+fn maybe_write_compressed_binary<T: Serialize>(path: &str, obj: &T) -> Result<()> {
+    if !path.ends_with(".bin") {
+        panic!("write_compressed_binary needs {} to end with .bin", path);
+    }
+
+    fs_err::create_dir_all(std::path::Path::new(path).parent().unwrap())
+        .expect("Creating parent dir failed");
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = zstd::stream::write::Encoder::new(file, ZSTD_LEVEL)?;
+    // Compress on other threads while this one serializes
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+    encoder.multithread(workers as u32)?;
+    // bincode writes many tiny pieces, and each call into the encoder has overhead
+    let mut encoder = BufWriter::new(encoder);
+    bincode::serialize_into(&mut encoder, obj)?;
+    let encoder = encoder.into_inner().map_err(|err| err.into_error())?;
+    encoder.finish()?.flush()?;
+    Ok(())
+}
+
+/// Like `write_binary`, but compressed with zstd. `maybe_read_binary` detects this, so the file
+/// keeps the `.bin` extension. Compressed files can't be read on web.
+pub fn write_compressed_binary<T: Serialize>(path: String, obj: &T) {
+    if let Err(err) = maybe_write_compressed_binary(&path, obj) {
+        panic!("Can't write_compressed_binary({}): {}", path, err);
+    }
+    info!("Wrote {}", path);
+}
+
 pub fn write_raw(path: String, bytes: &[u8]) -> Result<()> {
     fs_err::create_dir_all(std::path::Path::new(&path).parent().unwrap())?;
 
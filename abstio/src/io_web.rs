@@ -115,6 +115,11 @@ pub fn maybe_read_binary<T: DeserializeOwned>(path: String, _: &mut Timer) -> Re
     }
 }
 
+/// There's no memory-mapping on web, and the files are already in memory anyway
+pub fn maybe_read_binary_mmap<T: DeserializeOwned>(path: String, timer: &mut Timer) -> Result<T> {
+    maybe_read_binary(path, timer)
+}
+
 pub fn write_json<T: Serialize>(path: String, obj: &T) {
     // Only save for data/player, for now
     if !path.starts_with(&path_player("")) {
@@ -131,6 +136,11 @@ pub fn write_binary<T: Serialize>(path: String, obj: &T) {
     write_raw(path, &abstutil::to_binary(obj)).unwrap();
 }
 
+/// There's no zstd on web, so this doesn't compress anything
+pub fn write_compressed_binary<T: Serialize>(path: String, obj: &T) {
+    write_binary(path, obj);
+}
+
 pub fn write_raw(path: String, bytes: &[u8]) -> Result<()> {
     // Only save for data/player, for now
     if !path.starts_with(&path_player("")) {
@@ -68,3 +68,35 @@ pub fn basename<I: AsRef<str>>(path: I) -> String {
 pub fn parent_path(path: &str) -> String {
     format!("{}", std::path::Path::new(path).parent().unwrap().display())
 }
+
+/// Start hashing with `fnv1a` from this
+pub const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// FNV-1a is simple and gives the same result everywhere, unlike std's default hasher, so use this
+/// for hashes that are saved or compared across runs.
+pub fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        // Reference values for 64-bit FNV-1a
+        assert_eq!(fnv1a(FNV_OFFSET, b""), FNV_OFFSET);
+        assert_eq!(fnv1a(FNV_OFFSET, b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(FNV_OFFSET, b"foobar"), 0x85944171f73967e8);
+        // Hashing can continue across calls
+        assert_eq!(
+            fnv1a(fnv1a(FNV_OFFSET, b"foo"), b"bar"),
+            fnv1a(FNV_OFFSET, b"foobar")
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
 use abstutil::MultiMap;
-use geom::Distance;
+use geom::{Distance, Duration, Time};
 use map_gui::tools::{CityPicker, Navigator};
 use map_gui::ID;
 use map_model::connectivity::WalkingOptions;
@@ -178,6 +178,12 @@ fn options_to_controls(ctx: &mut EventCtx, opts: &Options) -> Widget {
                     .map(|(label, speed)| Choice::new(label, speed))
                     .collect(),
             ));
+            rows.push(Toggle::switch(
+                ctx,
+                "Ride transit, using schedules around 8am",
+                None,
+                opts.transit_departure.is_some(),
+            ));
 
             rows.push(ColorLegend::row(ctx, Color::BLUE, "unwalkable roads"));
         }
@@ -195,6 +201,14 @@ pub fn options_from_controls(panel: &Panel) -> MovementOptions {
             walking_speed: panel
                 .maybe_dropdown_value("speed")
                 .unwrap_or_else(WalkingOptions::default_speed),
+            transit_departure: if panel
+                .maybe_is_checked("Ride transit, using schedules around 8am")
+                .unwrap_or(false)
+            {
+                Some(Time::START_OF_DAY + Duration::hours(8))
+            } else {
+                None
+            },
         })
     } else {
         MovementOptions::Biking
@@ -69,6 +69,10 @@ impl App {
                     "- road_thruput: {} bytes",
                     prettyprint_usize(serialized_size_bytes(&a.road_thruput))
                 );
+                println!(
+                    "- lane_person_thruput: {} bytes",
+                    prettyprint_usize(serialized_size_bytes(&a.lane_person_thruput))
+                );
                 println!(
                     "- intersection_thruput: {} bytes",
                     prettyprint_usize(serialized_size_bytes(&a.intersection_thruput))
@@ -522,7 +526,11 @@ impl map_gui::AppLike for App {
     }
 
     fn map_switched(&mut self, ctx: &mut EventCtx, map: Map, timer: &mut Timer) {
-        let sim = Sim::new(&map, self.primary.current_flags.sim_flags.opts.clone());
+        let opts = &self.primary.current_flags.sim_flags.opts;
+        let mut sim = Sim::new(&map, opts.clone());
+        if let Err(err) = sim.load_input_files(&map, opts, timer) {
+            warn!("Ignoring the simulation's input files: {}", err);
+        }
 
         CameraState::save(ctx.canvas, self.primary.map.get_name());
         self.primary = PerMap::map_loaded(
@@ -724,10 +732,12 @@ impl PerMap {
     /// Returns whatever was there
     pub fn clear_sim(&mut self) -> Sim {
         self.dirty_from_edits = false;
-        std::mem::replace(
-            &mut self.sim,
-            Sim::new(&self.map, self.current_flags.sim_flags.opts.clone()),
-        )
+        let opts = &self.current_flags.sim_flags.opts;
+        let mut sim = Sim::new(&self.map, opts.clone());
+        if let Err(err) = sim.load_input_files(&self.map, opts, &mut Timer::throwaway()) {
+            warn!("Ignoring the simulation's input files: {}", err);
+        }
+        std::mem::replace(&mut self.sim, sim)
     }
 
     pub fn canonical_point(&self, id: ID) -> Option<Pt2D> {
@@ -0,0 +1,309 @@
+//! Publish proposals to a community gallery, and browse and load what other people have shared.
+//! The protocol and migrating proposals to the current map live in
+//! `map_gui::tools::proposal_gallery`.
+
+use abstio::Manifest;
+use map_gui::tools::grey_out_map;
+use map_gui::tools::proposal_gallery::{
+    self, thumbnail_widget, Compatibility, GalleryEntry, Publication,
+};
+use widgetry::tools::{FutureLoader, PopupMsg};
+use widgetry::{
+    EventCtx, GfxCtx, Key, Line, Panel, SimpleState, State, Text, TextBox, TextExt, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::edit::apply_map_edits;
+use crate::sandbox::GameplayMode;
+
+pub struct PublishProposal;
+
+impl PublishProposal {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut txt = Text::new();
+        txt.add_line(Line(format!(
+            "You'll publish this proposal for {} anonymously, in the public domain",
+            map.get_name().describe()
+        )));
+        txt.add_line(Line("Anybody browsing the gallery can load it").secondary());
+
+        let panel = Panel::new_builder(Widget::col(vec![
+            Widget::row(vec![
+                Line("Publish to the gallery")
+                    .small_heading()
+                    .into_widget(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            txt.into_widget(ctx),
+            Widget::row(vec![
+                "Name:".text_widget(ctx).centered_vert(),
+                TextBox::default_widget(ctx, "name", map.get_edits().get_title()),
+            ]),
+            Widget::row(vec![
+                "Description:".text_widget(ctx).centered_vert(),
+                TextBox::default_widget(ctx, "description", String::new()),
+            ]),
+            thumbnail_widget(ctx, &proposal_gallery::make_thumbnail(map)),
+            Widget::row(vec![
+                ctx.style()
+                    .btn_solid_primary
+                    .text("Publish")
+                    .hotkey(Key::Enter)
+                    .build_def(ctx),
+                ctx.style().btn_plain.text("Cancel").build_def(ctx),
+            ]),
+        ]))
+        .build(ctx);
+        <dyn SimpleState<_>>::new_state(panel, Box::new(PublishProposal))
+    }
+}
+
+impl SimpleState<App> for PublishProposal {
+    fn on_click(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        x: &str,
+        panel: &mut Panel,
+    ) -> Transition {
+        match x {
+            "close" | "Cancel" => Transition::Pop,
+            "Publish" => {
+                let name = panel.text_box("name");
+                if name.is_empty() {
+                    return Transition::Push(PopupMsg::new_state(
+                        ctx,
+                        "Error",
+                        vec!["The proposal needs a name"],
+                    ));
+                }
+                let publication =
+                    Publication::new(&app.primary.map, name, panel.text_box("description"));
+                let url = app.opts.proposal_gallery_url.clone();
+
+                let (_, outer_progress_rx) = futures_channel::mpsc::channel(1);
+                let (_, inner_progress_rx) = futures_channel::mpsc::channel(1);
+                Transition::Replace(FutureLoader::<App, String>::new_state(
+                    ctx,
+                    Box::pin(async move {
+                        let id = proposal_gallery::publish(url, publication).await?;
+                        let wrapper: Box<dyn Send + FnOnce(&App) -> String> = Box::new(move |_| id);
+                        Ok(wrapper)
+                    }),
+                    outer_progress_rx,
+                    inner_progress_rx,
+                    "Publishing proposal",
+                    Box::new(|ctx, _, result| {
+                        Transition::Replace(match result {
+                            Ok(id) => {
+                                info!("Published proposal {}", id);
+                                PopupMsg::new_state(
+                                    ctx,
+                                    "Published",
+                                    vec!["Your proposal is in the gallery now"],
+                                )
+                            }
+                            Err(err) => PopupMsg::new_state(
+                                ctx,
+                                "Failure",
+                                vec![format!("Couldn't publish proposal: {}", err)],
+                            ),
+                        })
+                    }),
+                ))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+    }
+}
+
+pub struct BrowseGallery {
+    entries: Vec<(GalleryEntry, Compatibility)>,
+    mode: GameplayMode,
+}
+
+impl BrowseGallery {
+    /// Downloads the list of proposals for the current map, then shows them. Mode is just used
+    /// for `allows`.
+    pub fn new_state(ctx: &mut EventCtx, app: &App, mode: GameplayMode) -> Box<dyn State<App>> {
+        let url = app.opts.proposal_gallery_url.clone();
+        let map_name = app.primary.map.get_name().clone();
+        let (_, outer_progress_rx) = futures_channel::mpsc::channel(1);
+        let (_, inner_progress_rx) = futures_channel::mpsc::channel(1);
+        FutureLoader::<App, Vec<GalleryEntry>>::new_state(
+            ctx,
+            Box::pin(async move {
+                let entries = proposal_gallery::list(url, map_name).await?;
+                let wrapper: Box<dyn Send + FnOnce(&App) -> Vec<GalleryEntry>> =
+                    Box::new(move |_| entries);
+                Ok(wrapper)
+            }),
+            outer_progress_rx,
+            inner_progress_rx,
+            "Downloading the gallery",
+            Box::new(move |ctx, app, result| match result {
+                Ok(entries) => Transition::Replace(BrowseGallery::show(ctx, app, entries, mode)),
+                Err(err) => Transition::Replace(PopupMsg::new_state(
+                    ctx,
+                    "Couldn't browse the gallery",
+                    vec![err.to_string()],
+                )),
+            }),
+        )
+    }
+
+    fn show(
+        ctx: &mut EventCtx,
+        app: &App,
+        entries: Vec<GalleryEntry>,
+        mode: GameplayMode,
+    ) -> Box<dyn State<App>> {
+        let mut col = vec![Widget::row(vec![
+            Line("Community proposals").small_heading().into_widget(ctx),
+            ctx.style().btn_close_widget(ctx),
+        ])];
+        if entries.is_empty() {
+            col.push("Nobody has published a proposal for this map yet".text_widget(ctx));
+        }
+        let manifest = Manifest::load();
+        let entries: Vec<(GalleryEntry, Compatibility)> = entries
+            .into_iter()
+            .map(|entry| {
+                let compatibility = entry.check_compatibility(&app.primary.map, &manifest);
+                (entry, compatibility)
+            })
+            .collect();
+        for (idx, (entry, compatibility)) in entries.iter().enumerate() {
+            let (warning, loadable) = match compatibility {
+                Compatibility::Compatible => (None, true),
+                Compatibility::NeedsMigration(_) => (
+                    Some(
+                        "Made for a different version of this map; some changes may be lost"
+                            .to_string(),
+                    ),
+                    true,
+                ),
+                Compatibility::Incompatible(msg) => (Some(msg.clone()), false),
+            };
+            let mut details = vec![
+                Line(&entry.name).small_heading().into_widget(ctx),
+                Text::from(Line(&entry.description).secondary())
+                    .wrap_to_pct(ctx, 30)
+                    .into_widget(ctx),
+            ];
+            if let Some(warning) = warning {
+                details.push(Line(warning).fg(app.cs.signal_banned_turn).into_widget(ctx));
+            }
+            details.push(
+                ctx.style()
+                    .btn_outline
+                    .text("Load")
+                    .disabled(!loadable)
+                    .build_widget(ctx, format!("load {}", idx)),
+            );
+            col.push(
+                Widget::row(vec![
+                    thumbnail_widget(ctx, &entry.thumbnail),
+                    Widget::col(details),
+                ])
+                .padding(16)
+                .bg(app.cs.inner_panel_bg),
+            );
+        }
+
+        let panel = Panel::new_builder(Widget::col(col))
+            .exact_size_percent(50, 70)
+            .build(ctx);
+        <dyn SimpleState<_>>::new_state(panel, Box::new(BrowseGallery { entries, mode }))
+    }
+}
+
+impl SimpleState<App> for BrowseGallery {
+    fn on_click(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        x: &str,
+        _: &mut Panel,
+    ) -> Transition {
+        if x == "close" {
+            return Transition::Pop;
+        }
+        let idx = x["load ".len()..].parse::<usize>().unwrap();
+        let url = app.opts.proposal_gallery_url.clone();
+        let (entry, compatibility) = &self.entries[idx];
+        let id = entry.id.clone();
+        // Loading will try to migrate anyway, but explain why that was necessary
+        let mut notes = match compatibility {
+            Compatibility::Compatible => Vec::new(),
+            Compatibility::NeedsMigration(problems) => problems.clone(),
+            // The button is disabled
+            Compatibility::Incompatible(_) => unreachable!(),
+        };
+        let mode = self.mode.clone();
+
+        let (_, outer_progress_rx) = futures_channel::mpsc::channel(1);
+        let (_, inner_progress_rx) = futures_channel::mpsc::channel(1);
+        Transition::Push(FutureLoader::<App, Vec<u8>>::new_state(
+            ctx,
+            Box::pin(async move {
+                let bytes = proposal_gallery::download(url, id).await?;
+                let wrapper: Box<dyn Send + FnOnce(&App) -> Vec<u8>> = Box::new(move |_| bytes);
+                Ok(wrapper)
+            }),
+            outer_progress_rx,
+            inner_progress_rx,
+            "Downloading proposal",
+            Box::new(move |ctx, app, result| {
+                match result
+                    .and_then(|bytes| proposal_gallery::import_proposal(&app.primary.map, &bytes))
+                    .and_then(|imported| {
+                        if mode.allows(&imported.edits) {
+                            Ok(imported)
+                        } else {
+                            Err(anyhow!(
+                                "The current gameplay mode restricts edits. This proposal has a \
+                                 banned command."
+                            ))
+                        }
+                    }) {
+                    Ok(imported) => {
+                        let migration = imported.describe_migration();
+                        apply_map_edits(ctx, app, imported.edits);
+                        app.primary
+                            .sim
+                            .handle_live_edited_traffic_signals(&app.primary.map);
+                        // Leave the gallery too
+                        if migration.is_empty() {
+                            Transition::Multi(vec![Transition::Pop, Transition::Pop])
+                        } else {
+                            notes.extend(migration);
+                            Transition::Multi(vec![
+                                Transition::Pop,
+                                Transition::Replace(PopupMsg::new_state(
+                                    ctx,
+                                    "Proposal migrated",
+                                    notes,
+                                )),
+                            ])
+                        }
+                    }
+                    Err(err) => Transition::Replace(PopupMsg::new_state(
+                        ctx,
+                        "Couldn't load proposal",
+                        vec![err.to_string()],
+                    )),
+                }
+            }),
+        ))
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+    }
+}
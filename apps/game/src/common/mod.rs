@@ -10,12 +10,13 @@ use widgetry::{
 
 pub use self::route_sketcher::RouteSketcher;
 pub use self::select::RoadSelector;
-pub use self::warp::{warp_to_id, Warping};
+pub use self::warp::{warp_to_id, DebugWarp, Warping};
 use crate::app::App;
 use crate::app::Transition;
 use crate::info::{ContextualActions, InfoPanel, Tab};
 use crate::sandbox::TimeWarpScreen;
 
+pub mod gallery;
 mod route_sketcher;
 mod select;
 pub mod share;
@@ -382,9 +383,13 @@ pub fn color_for_trip_phase(app: &App, tpt: TripPhaseType) -> Color {
         TripPhaseType::Driving => app.cs.unzoomed_car,
         TripPhaseType::Walking => app.cs.unzoomed_pedestrian,
         TripPhaseType::Biking => app.cs.bike_trip,
-        TripPhaseType::Parking => app.cs.parking_trip,
-        TripPhaseType::WaitingForBus(_, _) => app.cs.bus_layer,
-        TripPhaseType::RidingBus(_, _, _) => app.cs.bus_trip,
+        TripPhaseType::Parking | TripPhaseType::Unloading => app.cs.parking_trip,
+        TripPhaseType::WaitingForBus(_, _) | TripPhaseType::WaitingForFerry(_, _) => {
+            app.cs.bus_layer
+        }
+        TripPhaseType::RidingBus(_, _, _) | TripPhaseType::RidingFerry(_, _) => app.cs.bus_trip,
+        TripPhaseType::WaitingForRidehail => app.cs.unzoomed_ridehail.alpha(0.5),
+        TripPhaseType::RidingRidehail(_) => app.cs.unzoomed_ridehail,
         TripPhaseType::Cancelled | TripPhaseType::Finished => unreachable!(),
         TripPhaseType::DelayedStart => Color::YELLOW,
     }
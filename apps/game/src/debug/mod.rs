@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use abstio::MapName;
 use abstutil::{Tags, Timer};
 use blockfinding::Perimeter;
-use geom::{ArrowCap, Circle, Distance, PolyLine, Pt2D};
+use geom::{ArrowCap, Circle, Distance, PolyLine, Pt2D, Time};
 use map_gui::colors::ColorSchemeChoice;
 use map_gui::load::MapLoader;
 use map_gui::options::OptionsPanel;
@@ -109,6 +109,14 @@ impl DebugMode {
                         .btn_outline
                         .text("pick a savestate to load")
                         .build_def(ctx),
+                    ctx.style()
+                        .btn_outline
+                        .text("resume from the latest sim state")
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_outline
+                        .text("schedule a checkpoint")
+                        .build_def(ctx),
                     ctx.style()
                         .btn_outline
                         .text("find bad traffic signals")
@@ -296,6 +304,36 @@ impl State<App> for DebugMode {
                     app.primary.current_selection = app.mouseover_debug_mode(ctx, self);
                     self.reset_info(ctx);
                 }
+                "resume from the latest sim state" => {
+                    if let Some(t) = ctx.loading_screen("load latest savestate", |ctx, timer| {
+                        let latest = app.primary.sim.find_latest_savestate();
+                        match latest
+                            .clone()
+                            .and_then(|path| Sim::load_savestate(path, timer).ok())
+                        {
+                            Some(new_sim) => {
+                                app.primary.sim = new_sim;
+                                app.recalculate_current_selection(ctx);
+                                None
+                            }
+                            None => Some(Transition::Push(PopupMsg::new_state(
+                                ctx,
+                                "Error",
+                                vec![format!("Couldn't load latest savestate {:?}", latest)],
+                            ))),
+                        }
+                    }) {
+                        return t;
+                    }
+                }
+                "schedule a checkpoint" => {
+                    return Transition::Push(PromptInput::new_state(
+                        ctx,
+                        "Save the simulation at what time? (like 7:30)",
+                        String::new(),
+                        Box::new(schedule_checkpoint),
+                    ));
+                }
                 "search OSM metadata" => {
                     return Transition::Push(PromptInput::new_state(
                         ctx,
@@ -513,6 +551,18 @@ impl ShowObject for DebugMode {
     }
 }
 
+fn schedule_checkpoint(input: String, ctx: &mut EventCtx, app: &mut App) -> Transition {
+    let msg = match Time::parse(&input) {
+        Ok(t) if t > app.primary.sim.time() => {
+            app.primary.sim.schedule_checkpoint(t);
+            format!("The simulation will be saved when it reaches {}", t)
+        }
+        Ok(t) => format!("It's already past {}", t),
+        Err(err) => format!("Bad time {}: {}", input, err),
+    };
+    Transition::Replace(PopupMsg::new_state(ctx, "Checkpoint", vec![msg]))
+}
+
 fn search_osm(filter: String, ctx: &mut EventCtx, app: &mut App) -> Transition {
     let mut num_matches = 0;
     let mut batch = GeomBatch::new();
@@ -1,13 +1,13 @@
 use std::collections::HashMap;
 
 use abstutil::{prettyprint_usize, Counter, Timer};
-use geom::{Duration, Polygon};
+use geom::{Distance, Duration, Polygon};
 use map_gui::colors::ColorSchemeChoice;
 use map_gui::tools::{cmp_count, ColorNetwork};
 use map_gui::AppLike;
 use map_model::{
     DirectedRoadID, Direction, PathConstraints, PathRequest, PathStepV2, Pathfinder, RoadID,
-    RoutingParams, NORMAL_LANE_THICKNESS,
+    RoutingParams, NORMAL_LANE_THICKNESS, SUMMER_DAY_OF_YEAR,
 };
 use synthpop::{TripEndpoint, TripMode};
 use widgetry::mapspace::ToggleZoomed;
@@ -52,26 +52,39 @@ impl RouteExplorer {
     }
 
     fn recalc_paths(&mut self, ctx: &mut EventCtx, app: &App) {
-        let (mode, params) = controls_to_params(&self.panel);
+        let (mode, params) = controls_to_params(&self.panel, app);
 
         if let Some((ref goal, _, ref mut preview)) = self.goal {
             *preview = Drawable::empty(ctx);
-            if let Some(polygon) = TripEndpoint::path_req(self.start, *goal, mode, &app.primary.map)
-                .and_then(|req| {
-                    Pathfinder::new_dijkstra(
-                        &app.primary.map,
-                        params,
-                        vec![req.constraints],
-                        &mut Timer::throwaway(),
-                    )
-                    .pathfind_v2(req, &app.primary.map)
-                })
-                .and_then(|path| path.into_v1(&app.primary.map).ok())
-                .and_then(|path| path.trace(&app.primary.map))
-                .map(|pl| pl.make_polygons(NORMAL_LANE_THICKNESS))
-            {
-                *preview = GeomBatch::from(vec![(Color::PURPLE, polygon)]).upload(ctx);
+            let route = |params: RoutingParams, thickness: Distance| {
+                TripEndpoint::path_req(self.start, *goal, mode, &app.primary.map)
+                    .and_then(|req| {
+                        Pathfinder::new_dijkstra(
+                            &app.primary.map,
+                            params,
+                            vec![req.constraints],
+                            &mut Timer::throwaway(),
+                        )
+                        .pathfind_v2(req, &app.primary.map)
+                    })
+                    .and_then(|path| path.into_v1(&app.primary.map).ok())
+                    .and_then(|path| path.trace(&app.primary.map))
+                    .map(|pl| pl.make_polygons(thickness))
+            };
+            let mut batch = GeomBatch::new();
+            // Underneath, show the route with the map's own parameters, to compare against.
+            if &params != app.primary.map.routing_params() {
+                if let Some(polygon) = route(
+                    app.primary.map.routing_params().clone(),
+                    NORMAL_LANE_THICKNESS * 2.0,
+                ) {
+                    batch.push(Color::grey(0.5).alpha(0.5), polygon);
+                }
+            }
+            if let Some(polygon) = route(params, NORMAL_LANE_THICKNESS) {
+                batch.push(Color::PURPLE, polygon);
             }
+            *preview = batch.upload(ctx);
         }
     }
 }
@@ -224,6 +237,38 @@ fn params_to_controls(ctx: &mut EventCtx, mode: TripMode, params: &RoutingParams
                 Duration::seconds(1.0),
             ),
         ]));
+        rows.push(Widget::row(vec![
+            "U-turn penalty:".text_widget(ctx).margin_right(20),
+            Spinner::widget(
+                ctx,
+                "u_turn_penalty",
+                (Duration::seconds(1.0), Duration::seconds(100.0)),
+                params.u_turn_penalty,
+                Duration::seconds(1.0),
+            ),
+        ]));
+    }
+    if mode == TripMode::Drive {
+        rows.push(Widget::row(vec![
+            "People in the car (for HOV lanes):"
+                .text_widget(ctx)
+                .margin_right(20),
+            Spinner::widget(ctx, "occupancy", (1, 8), params.occupancy, 1),
+        ]));
+    }
+    if mode == TripMode::Walk {
+        rows.push(Widget::row(vec![
+            "Avoid sidewalks in the summer sun:"
+                .text_widget(ctx)
+                .margin_right(20),
+            Spinner::f64_widget(
+                ctx,
+                "sun_exposure_penalty",
+                (1.0, 3.0),
+                params.sun_exposure_penalty,
+                0.1,
+            ),
+        ]));
     }
     if mode == TripMode::Bike {
         rows.push(Widget::row(vec![
@@ -278,26 +323,48 @@ fn params_to_controls(ctx: &mut EventCtx, mode: TripMode, params: &RoutingParams
                 0.1,
             ),
         ]));
+        rows.push(Widget::row(vec![
+            "Traffic stress penalty (per LTS level):"
+                .text_widget(ctx)
+                .margin_right(20),
+            Spinner::f64_widget(
+                ctx,
+                "traffic_stress_penalty",
+                (0.0, 3.0),
+                params.traffic_stress_penalty,
+                0.1,
+            ),
+        ]));
     }
     Widget::col(rows)
 }
 
-fn controls_to_params(panel: &Panel) -> (TripMode, RoutingParams) {
+fn controls_to_params(panel: &Panel, app: &App) -> (TripMode, RoutingParams) {
     let mut params = RoutingParams::default();
     if !panel.is_button_enabled("cars") {
         params.unprotected_turn_penalty = panel.spinner("unprotected_turn_penalty");
+        params.u_turn_penalty = panel.spinner("u_turn_penalty");
+        params.occupancy = panel.spinner("occupancy");
         return (TripMode::Drive, params);
     }
     if !panel.is_button_enabled("pedestrians") {
+        params.sun_exposure_penalty = panel.spinner::<RoundedF64>("sun_exposure_penalty").0;
+        if params.sun_exposure_penalty > 1.0 {
+            let map = &app.primary.map;
+            params.sidewalk_shade =
+                map.sidewalk_shade(&map.sun_position(SUMMER_DAY_OF_YEAR, app.primary.sim.time()));
+        }
         return (TripMode::Walk, params);
     }
     params.unprotected_turn_penalty = panel.spinner("unprotected_turn_penalty");
+    params.u_turn_penalty = panel.spinner("u_turn_penalty");
     params.bike_lane_penalty = panel.spinner::<RoundedF64>("bike_lane_penalty").0;
     params.bus_lane_penalty = panel.spinner::<RoundedF64>("bus_lane_penalty").0;
     params.driving_lane_penalty = panel.spinner::<RoundedF64>("driving_lane_penalty").0;
     params.avoid_steep_incline_penalty =
         panel.spinner::<RoundedF64>("avoid_steep_incline_penalty").0;
     params.avoid_high_stress = panel.spinner::<RoundedF64>("avoid_high_stress").0;
+    params.traffic_stress_penalty = panel.spinner::<RoundedF64>("traffic_stress_penalty").0;
     (TripMode::Bike, params)
 }
 
@@ -391,7 +458,7 @@ impl State<App> for AllRoutesExplorer {
                     ctx.loading_screen(
                         "calculate differential demand due to routing params",
                         |ctx, timer| {
-                            let (_, params) = controls_to_params(&self.panel);
+                            let (_, params) = controls_to_params(&self.panel, app);
                             let pathfinder = Pathfinder::new_ch(
                                 &app.primary.map,
                                 params,
@@ -1,7 +1,7 @@
 use crate::ID;
 use abstutil::{prettyprint_usize, Counter};
 use collisions::{CollisionDataset, Severity};
-use geom::{Circle, Distance, Duration, FindClosest, Time};
+use geom::{Circle, Distance, Duration, Time};
 use widgetry::mapspace::{DummyID, World};
 use widgetry::{
     Choice, Color, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, Slider,
@@ -9,6 +9,7 @@ use widgetry::{
 };
 
 use crate::app::{App, Transition};
+use crate::layer::collisions::snap_collisions;
 
 pub struct CollisionsViewer {
     data: CollisionDataset,
@@ -140,37 +141,22 @@ fn aggregated(
 ) -> World<DummyID> {
     let map = &app.primary.map;
 
-    // Match each collision to the nearest road and intersection
-    let mut closest: FindClosest<ID> = FindClosest::new();
-    for i in map.all_intersections() {
-        closest.add_polygon(ID::Intersection(i.id), &i.polygon);
-    }
-    for r in map.all_roads() {
-        closest.add(ID::Road(r.id), r.center_pts.points());
-    }
-
     // How many collisions occurred at each road and intersection?
     let mut per_road = Counter::new();
     let mut per_intersection = Counter::new();
     let mut unsnapped = 0;
-    for idx in indices {
-        let collision = &data.collisions[idx];
-        // Search up to 10m away
-        if let Some((id, _)) = closest.closest_pt(
-            collision.location.to_pt(map.get_gps_bounds()),
-            Distance::meters(10.0),
-        ) {
-            match id {
-                ID::Road(r) => {
-                    per_road.inc(r);
-                }
-                ID::Intersection(i) => {
-                    per_intersection.inc(i);
-                }
-                _ => unreachable!(),
+    for (id, _) in snap_collisions(map, indices.into_iter().map(|idx| &data.collisions[idx])) {
+        match id {
+            Some(ID::Road(r)) => {
+                per_road.inc(r);
+            }
+            Some(ID::Intersection(i)) => {
+                per_intersection.inc(i);
+            }
+            Some(_) => unreachable!(),
+            None => {
+                unsnapped += 1;
             }
-        } else {
-            unsnapped += 1;
         }
     }
     if unsnapped > 0 {
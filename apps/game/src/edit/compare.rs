@@ -0,0 +1,140 @@
+use geom::{Bounds, Distance, Polygon, Pt2D};
+use widgetry::{
+    Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome, Panel,
+    ScreenPt, Slider, State, Text, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+
+/// Ghosts the original cross-section of every edited road over the new one. Left of the swipe
+/// line, the original design covers the new one; right of it, only its outline remains.
+pub struct CompareDesigns {
+    panel: Panel,
+    old_lanes: Vec<(Polygon, Color)>,
+    outlines: Drawable,
+    swiped: Drawable,
+    /// The slider and the part of the map on screen when `swiped` was last drawn
+    swipe_key: Option<(f64, Pt2D, Pt2D)>,
+}
+
+impl CompareDesigns {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let old_lanes = old_lanes(app);
+        let mut outlines = GeomBatch::new();
+        for (polygon, _) in &old_lanes {
+            outlines.push(Color::BLACK, polygon.to_outline(Distance::meters(0.3)));
+        }
+
+        let edits_name = &app.primary.map.get_edits().edits_name;
+        let mut txt = Text::new();
+        txt.add_line(format!("Left of the line: before \"{}\"", edits_name));
+        txt.add_line(format!("Right of the line: after \"{}\"", edits_name));
+        txt.add_line(Line("The original lanes are outlined everywhere").secondary());
+
+        Box::new(CompareDesigns {
+            panel: Panel::new_builder(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Before and after").small_heading().into_widget(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                txt.into_widget(ctx),
+                Slider::area(ctx, 0.2 * ctx.canvas.window_width, 0.5, "swipe"),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            old_lanes,
+            outlines: ctx.upload(outlines),
+            swiped: Drawable::empty(ctx),
+            swipe_key: None,
+        })
+    }
+
+    fn redraw_swiped(&mut self, ctx: &mut EventCtx) {
+        let pct = self.panel.slider("swipe").get_percent();
+        let top_left = ctx.canvas.screen_to_map(ScreenPt::new(0.0, 0.0));
+        let bottom_right = ctx.canvas.screen_to_map(ScreenPt::new(
+            ctx.canvas.window_width,
+            ctx.canvas.window_height,
+        ));
+        let key = Some((pct, top_left, bottom_right));
+        if self.swipe_key == key {
+            return;
+        }
+        self.swipe_key = key;
+
+        let swipe_x = top_left.x() + pct * (bottom_right.x() - top_left.x());
+        let mut bounds = Bounds::new();
+        bounds.update(top_left);
+        bounds.update(Pt2D::new(swipe_x, bottom_right.y()));
+        let visible = bounds.get_rectangle();
+
+        let mut batch = GeomBatch::new();
+        for (polygon, color) in &self.old_lanes {
+            if let Ok(list) = polygon.intersection(&visible) {
+                for p in list {
+                    batch.push(color.alpha(0.9), p);
+                }
+            }
+        }
+        if let Ok(line) = geom::Line::new(
+            Pt2D::new(swipe_x, top_left.y()),
+            Pt2D::new(swipe_x, bottom_right.y()),
+        ) {
+            batch.push(
+                Color::WHITE,
+                line.make_polygons(Distance::meters(3.0 / ctx.canvas.cam_zoom)),
+            );
+        }
+        self.swiped = ctx.upload(batch);
+    }
+}
+
+impl State<App> for CompareDesigns {
+    fn event(&mut self, ctx: &mut EventCtx, _: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            }
+        }
+        self.redraw_swiped(ctx);
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        g.redraw(&self.swiped);
+        g.redraw(&self.outlines);
+        self.panel.draw(g);
+    }
+}
+
+/// The lanes of every road whose cross-section changed, laid out along the road's current center
+/// line the way they originally were
+fn old_lanes(app: &App) -> Vec<(Polygon, Color)> {
+    let map = &app.primary.map;
+    let mut lanes = Vec::new();
+    for (r, orig) in &map.get_edits().original_roads {
+        if map.get_r_edit(*r).lanes_ltr == orig.lanes_ltr {
+            continue;
+        }
+        let road = map.get_r(*r);
+        let total_width = orig.lanes_ltr.iter().map(|spec| spec.width).sum();
+        let mut width_so_far = Distance::ZERO;
+        for spec in &orig.lanes_ltr {
+            width_so_far += spec.width / 2.0;
+            if let Ok(pl) = road.center_pts.shift_from_center(total_width, width_so_far) {
+                lanes.push((
+                    pl.make_polygons(spec.width),
+                    app.cs.zoomed_road_surface(spec.lt, road.get_rank()),
+                ));
+            }
+            width_so_far += spec.width / 2.0;
+        }
+    }
+    lanes
+}
@@ -0,0 +1,196 @@
+use std::collections::BTreeSet;
+
+use geom::{Duration, Time};
+use map_gui::tools::{intersections_from_roads, ColorDiscrete};
+use map_model::{CommonEndpoint, CongestionCharge, EditCmd, RoadID};
+use widgetry::mapspace::ToggleZoomed;
+use widgetry::tools::PopupMsg;
+use widgetry::{
+    Color, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, Spinner, State, Text,
+    TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::{CommonState, RoadSelector};
+use crate::edit::apply_map_edits;
+
+/// Draw the cordon of a congestion charge and pick when and how much drivers pay to enter it
+pub struct CongestionChargeEditor {
+    panel: Panel,
+    selector: RoadSelector,
+    draw: ToggleZoomed,
+}
+
+impl CongestionChargeEditor {
+    pub fn new_state(ctx: &mut EventCtx, app: &mut App, start: RoadID) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let current = map.get_congestion_charge();
+        let members = match current {
+            Some(charge) => charge.roads.clone(),
+            None => map.dual_carriageway(start),
+        };
+        // Only daily charges can be edited here; anything else gets replaced by one
+        let (peak, off_peak, start_hour, end_hour) = match current {
+            Some(charge) if charge.schedule.len() == 3 => (
+                charge.schedule[1].1 as usize,
+                charge.schedule[0].1 as usize,
+                charge.schedule[1].0.get_hours(),
+                charge.schedule[2].0.get_hours(),
+            ),
+            _ => (10, 0, 7, 19),
+        };
+
+        let (draw, legend) = draw_cordon(ctx, app, &members);
+        let selector = RoadSelector::new(ctx, app, members);
+
+        Box::new(CongestionChargeEditor {
+            panel: Panel::new_builder(Widget::col(vec![
+                Line("Editing congestion charge")
+                    .small_heading()
+                    .into_widget(ctx),
+                Text::from(
+                    "Cars pay every time they drive into the cordon. Trips starting inside don't \
+                     pay to leave.",
+                )
+                .wrap_to_pct(ctx, 30)
+                .into_widget(ctx),
+                selector.make_controls(ctx).named("selector"),
+                legend,
+                Widget::row(vec![
+                    "Peak charge:".text_widget(ctx).centered_vert(),
+                    Spinner::widget(ctx, "peak", (1, 100), peak.max(1), 1),
+                    "from".text_widget(ctx).centered_vert(),
+                    Spinner::widget(ctx, "start hour", (0, 23), start_hour, 1),
+                    "until".text_widget(ctx).centered_vert(),
+                    Spinner::widget(ctx, "end hour", (1, 24), end_hour.max(1), 1),
+                ]),
+                Widget::row(vec![
+                    "Off-peak charge:".text_widget(ctx).centered_vert(),
+                    Spinner::widget(ctx, "off-peak", (0, 100), off_peak, 1),
+                ]),
+                Widget::custom_row(vec![
+                    ctx.style()
+                        .btn_solid_primary
+                        .text("Apply")
+                        .hotkey(Key::Enter)
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_solid_destructive
+                        .text("Remove congestion charge")
+                        .disabled(current.is_none())
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_plain
+                        .text("Cancel")
+                        .hotkey(Key::Escape)
+                        .build_def(ctx),
+                ])
+                .evenly_spaced(),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            selector,
+            draw,
+        })
+    }
+}
+
+impl State<App> for CongestionChargeEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "Apply" => {
+                    let start_hour: usize = self.panel.spinner("start hour");
+                    let end_hour: usize = self.panel.spinner("end hour");
+                    if self.selector.roads.is_empty() || start_hour >= end_hour {
+                        return Transition::Push(PopupMsg::new_state(
+                            ctx,
+                            "Invalid congestion charge",
+                            vec!["The cordon needs some roads, and the peak must end after it starts"],
+                        ));
+                    }
+                    let peak: usize = self.panel.spinner("peak");
+                    let off_peak: usize = self.panel.spinner("off-peak");
+                    let charge = CongestionCharge::daily(
+                        self.selector.roads.clone(),
+                        Time::START_OF_DAY + Duration::hours(start_hour),
+                        Time::START_OF_DAY + Duration::hours(end_hour),
+                        peak as f64,
+                        off_peak as f64,
+                    );
+                    set_charge(ctx, app, Some(charge));
+                    return Transition::Pop;
+                }
+                "Remove congestion charge" => {
+                    set_charge(ctx, app, None);
+                    return Transition::Pop;
+                }
+                "Cancel" => {
+                    return Transition::Pop;
+                }
+                x => {
+                    if self.selector.event(ctx, app, Some(x)) {
+                        let new_controls = self.selector.make_controls(ctx);
+                        self.panel.replace(ctx, "selector", new_controls);
+                        self.draw = draw_cordon(ctx, app, &self.selector.roads).0;
+                    }
+                }
+            },
+            _ => {
+                if self.selector.event(ctx, app, None) {
+                    let new_controls = self.selector.make_controls(ctx);
+                    self.panel.replace(ctx, "selector", new_controls);
+                    self.draw = draw_cordon(ctx, app, &self.selector.roads).0;
+                }
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.draw.draw(g);
+        self.panel.draw(g);
+        self.selector.draw(g, app, false);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn set_charge(ctx: &mut EventCtx, app: &mut App, new: Option<CongestionCharge>) {
+    let mut edits = app.primary.map.get_edits().clone();
+    edits.commands.push(EditCmd::ChangeCongestionCharge {
+        old: app.primary.map.get_congestion_charge().cloned(),
+        new,
+    });
+    apply_map_edits(ctx, app, edits);
+}
+
+fn draw_cordon(
+    ctx: &mut EventCtx,
+    app: &App,
+    members: &BTreeSet<RoadID>,
+) -> (ToggleZoomed, Widget) {
+    let mut colorer = ColorDiscrete::new(
+        app,
+        vec![
+            ("inside the cordon", Color::PURPLE),
+            ("charged entrance", Color::RED),
+        ],
+    );
+    let map = &app.primary.map;
+    for r in members {
+        let r = map.get_r(*r);
+        colorer.add_r(r.id, "inside the cordon");
+        for next in map.get_next_roads(r.id) {
+            if !members.contains(&next) {
+                if let CommonEndpoint::One(i) = r.common_endpoint(map.get_r(next)) {
+                    colorer.add_i(i, "charged entrance");
+                }
+            }
+        }
+    }
+    for i in intersections_from_roads(members, map) {
+        colorer.add_i(i, "inside the cordon");
+    }
+    colorer.build(ctx)
+}
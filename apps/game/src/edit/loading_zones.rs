@@ -0,0 +1,150 @@
+use map_gui::tools::ColorDiscrete;
+use map_model::{DirectedRoadID, Direction, EditCmd, LoadingZones, RoadID};
+use widgetry::mapspace::ToggleZoomed;
+use widgetry::{
+    Color, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, Spinner, State, Text,
+    TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+use crate::edit::apply_map_edits;
+
+/// Turn parking spots along one road into loading zone spaces for delivery vans
+pub struct LoadingZoneEditor {
+    panel: Panel,
+    /// The sides of the road with parking, and how many spaces fit there
+    sides: Vec<(DirectedRoadID, usize)>,
+    draw: ToggleZoomed,
+}
+
+impl LoadingZoneEditor {
+    pub fn new_state(ctx: &mut EventCtx, app: &mut App, r: RoadID) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let sides: Vec<(DirectedRoadID, usize)> = [Direction::Fwd, Direction::Back]
+            .into_iter()
+            .map(|dir| DirectedRoadID { road: r, dir })
+            .map(|dr| (dr, LoadingZones::max_spaces(map, dr)))
+            .filter(|(_, max)| *max > 0)
+            .collect();
+
+        let (draw, legend) = draw_loading_zones(ctx, app);
+        let mut col =
+            vec![
+            Line("Editing loading zones").small_heading().into_widget(ctx),
+            Text::from(
+                "Each space replaces a parking spot. Delivery vans that can't find a free space \
+                 double-park, blocking the lane while they unload.",
+            )
+            .wrap_to_pct(ctx, 30)
+            .into_widget(ctx),
+            legend,
+        ];
+        if sides.is_empty() {
+            col.push("This road has no parking to convert".text_widget(ctx));
+        }
+        for (dr, max) in &sides {
+            let current = map
+                .get_loading_zones()
+                .spaces
+                .get(dr)
+                .cloned()
+                .unwrap_or(0)
+                .min(*max);
+            col.push(Widget::row(vec![
+                format!(
+                    "Spaces on the side heading towards {}:",
+                    map.get_i(dr.dst_i(map))
+                        .name(app.opts.language.as_ref(), map)
+                )
+                .text_widget(ctx)
+                .centered_vert(),
+                Spinner::widget(ctx, side_name(*dr), (0, *max), current, 1),
+            ]));
+        }
+        col.push(
+            Widget::custom_row(vec![
+                ctx.style()
+                    .btn_solid_primary
+                    .text("Apply")
+                    .hotkey(Key::Enter)
+                    .disabled(sides.is_empty())
+                    .build_def(ctx),
+                ctx.style()
+                    .btn_plain
+                    .text("Cancel")
+                    .hotkey(Key::Escape)
+                    .build_def(ctx),
+            ])
+            .evenly_spaced(),
+        );
+
+        Box::new(LoadingZoneEditor {
+            panel: Panel::new_builder(Widget::col(col))
+                .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+                .build(ctx),
+            sides,
+            draw,
+        })
+    }
+
+    fn apply(&self, ctx: &mut EventCtx, app: &mut App) {
+        let map = &app.primary.map;
+        let old = map.get_loading_zones().clone();
+        let mut new = old.clone();
+        for (dr, _) in &self.sides {
+            let spaces: usize = self.panel.spinner(&side_name(*dr));
+            if spaces == 0 {
+                new.spaces.remove(dr);
+            } else {
+                new.spaces.insert(*dr, spaces);
+            }
+        }
+        if new == old {
+            return;
+        }
+
+        let mut edits = map.get_edits().clone();
+        edits
+            .commands
+            .push(EditCmd::ChangeLoadingZones { old, new });
+        apply_map_edits(ctx, app, edits);
+    }
+}
+
+impl State<App> for LoadingZoneEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "Apply" => {
+                    self.apply(ctx, app);
+                    return Transition::Pop;
+                }
+                "Cancel" => {
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.draw.draw(g);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn side_name(dr: DirectedRoadID) -> String {
+    format!("spaces {:?}", dr.dir)
+}
+
+fn draw_loading_zones(ctx: &mut EventCtx, app: &App) -> (ToggleZoomed, Widget) {
+    let mut colorer = ColorDiscrete::new(app, vec![("has a loading zone", Color::CYAN)]);
+    for dr in app.primary.map.get_loading_zones().spaces.keys() {
+        colorer.add_r(dr.road, "has a loading zone");
+    }
+    colorer.build(ctx)
+}
@@ -15,7 +15,7 @@ use widgetry::{
 };
 
 pub use self::roads::RoadEditor;
-pub use self::routes::RouteEditor;
+pub use self::routes::{RouteEditor, StopEditor};
 pub use self::stop_signs::StopSignEditor;
 pub use self::traffic_signals::TrafficSignalEditor;
 pub use self::validate::check_sidewalk_connectivity;
@@ -24,8 +24,12 @@ use crate::common::{tool_panel, CommonState, Warping};
 use crate::debug::DebugMode;
 use crate::sandbox::{GameplayMode, SandboxMode, TimeWarpScreen};
 
+mod compare;
+mod congestion_charge;
 mod crosswalks;
+mod loading_zones;
 mod multiple_roads;
+mod parking_pricing;
 mod roads;
 mod routes;
 mod stop_signs;
@@ -214,6 +218,8 @@ impl State<App> for EditMode {
                             Choice::string("save this proposal as..."),
                             // TODO Disable if empty edits
                             Choice::string("share proposal"),
+                            Choice::string("publish to the gallery")
+                                .active(!app.opts.proposal_gallery_url.is_empty()),
                             Choice::string("delete this proposal and remove all edits")
                                 .fg(ctx.style().text_destructive_color),
                         ],
@@ -292,6 +298,9 @@ impl State<App> for EditMode {
                                     ctx, app, "--dev",
                                 ))
                             }
+                            "publish to the gallery" => Transition::Replace(
+                                crate::common::gallery::PublishProposal::new_state(ctx, app),
+                            ),
                             "delete this proposal and remove all edits" => {
                                 abstio::delete_file(abstio::path_edits(
                                     app.primary.map.get_name(),
@@ -305,6 +314,9 @@ impl State<App> for EditMode {
                     ));
                 }
                 "load proposal" => {}
+                "compare before and after" => {
+                    return Transition::Push(compare::CompareDesigns::new_state(ctx, app));
+                }
                 "undo" => {
                     let mut edits = app.primary.map.get_edits().clone();
                     let maybe_id = cmd_to_id(&edits.commands.pop().unwrap());
@@ -581,7 +593,15 @@ impl LoadEdits {
         ];
         // widgetry can't toggle keyboard focus between two menus, so just use buttons for the less
         // common use case.
-        let mut proposals = vec![Line("Community proposals").small_heading().into_widget(ctx)];
+        let mut proposals = vec![
+            Line("Community proposals").small_heading().into_widget(ctx),
+            ctx.style()
+                .btn_outline
+                .text("Browse the online gallery")
+                .disabled(app.opts.proposal_gallery_url.is_empty())
+                .disabled_tooltip("Set a proposal gallery URL in the settings first")
+                .build_def(ctx),
+        ];
         // Up-front filter out proposals that definitely don't fit the current map
         for name in abstio::list_all_objects(abstio::path("system/proposals")) {
             let path = abstio::path(format!("system/proposals/{}.json", name));
@@ -626,6 +646,13 @@ impl State<App> for LoadEdits {
                         apply_map_edits(ctx, app, app.primary.map.new_edits());
                         Transition::Pop
                     }
+                    "Browse the online gallery" => {
+                        Transition::Replace(crate::common::gallery::BrowseGallery::new_state(
+                            ctx,
+                            app,
+                            self.mode.clone(),
+                        ))
+                    }
                     path => {
                         // TODO Kind of a hack. If it ends with .json, it's already a path.
                         // Otherwise it's a result from the menu.
@@ -795,6 +822,22 @@ pub fn apply_map_edits(ctx: &mut EventCtx, app: &mut App, edits: MapEdits) {
     });
 }
 
+/// Incidents in the simulation close and reopen roads by editing the map. Call this after stepping
+/// the simulation.
+pub fn apply_incident_edits(ctx: &mut EventCtx, app: &mut App) {
+    if let Some(edits) = app.primary.sim.edits_for_incidents(&app.primary.map) {
+        apply_map_edits(ctx, app, edits);
+        ctx.loading_screen("update the simulation for incidents", |_, timer| {
+            app.primary.map.recalculate_pathfinding_after_edits(timer);
+            app.primary
+                .sim
+                .handle_live_edited_traffic_signals(&app.primary.map);
+            app.primary.sim.handle_live_edits(&app.primary.map, timer);
+        });
+        app.primary.dirty_from_edits = true;
+    }
+}
+
 pub fn can_edit_lane(app: &App, l: LaneID) -> bool {
     let map = &app.primary.map;
     let lane = map.get_l(l);
@@ -896,6 +939,14 @@ fn make_changelist(ctx: &mut EventCtx, app: &App) -> Panel {
             ),
         ),
     ];
+    if !edits.original_roads.is_empty() {
+        col.push(
+            ctx.style()
+                .btn_outline
+                .text("compare before and after")
+                .build_def(ctx),
+        );
+    }
 
     if edits.commands.len() > 5 {
         col.push(format!("{} more...", edits.commands.len() - 5).text_widget(ctx));
@@ -939,7 +990,15 @@ fn cmd_to_id(cmd: &EditCmd) -> Option<ID> {
     match cmd {
         EditCmd::ChangeRoad { r, .. } => Some(ID::Road(*r)),
         EditCmd::ChangeIntersection { i, .. } => Some(ID::Intersection(*i)),
-        EditCmd::ChangeRouteSchedule { .. } => None,
+        EditCmd::ChangeRouteSchedule { .. }
+        | EditCmd::ChangeRouteBoarding { .. }
+        | EditCmd::ChangeRouteStops { .. }
+        | EditCmd::ChangeCongestionCharge { .. }
+        | EditCmd::ChangeParkingPricing { .. }
+        | EditCmd::ChangeLoadingZones { .. } => None,
+        EditCmd::ChangeStopBoarding { id, .. } | EditCmd::ChangeStopClosed { id, .. } => {
+            Some(ID::TransitStop(*id))
+        }
     }
 }
 
@@ -0,0 +1,199 @@
+use std::collections::BTreeSet;
+
+use geom::Duration;
+use map_gui::tools::ColorDiscrete;
+use map_model::{EditCmd, ParkingPolicy, RoadID};
+use widgetry::mapspace::ToggleZoomed;
+use widgetry::{
+    Color, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, Spinner, State, Text,
+    TextExt, Toggle, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::{CommonState, RoadSelector};
+use crate::edit::apply_map_edits;
+
+/// Set the price and time limit for parking along some roads, and optionally in the parking lots
+/// along them
+pub struct ParkingPricingEditor {
+    panel: Panel,
+    selector: RoadSelector,
+    draw: ToggleZoomed,
+}
+
+impl ParkingPricingEditor {
+    pub fn new_state(ctx: &mut EventCtx, app: &mut App, start: RoadID) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let members = map.dual_carriageway(start);
+        let (price, limit_hours) = match map.get_parking_pricing().blockfaces.get(&start) {
+            Some(policy) => (
+                policy.hourly_price as usize,
+                policy
+                    .max_stay
+                    .map(|d| (d.inner_seconds() / 3600.0) as usize)
+                    .unwrap_or(0),
+            ),
+            None => (2, 0),
+        };
+
+        let (draw, legend) = draw_pricing(ctx, app);
+        let selector = RoadSelector::new(ctx, app, members);
+
+        Box::new(ParkingPricingEditor {
+            panel: Panel::new_builder(Widget::col(vec![
+                Line("Editing parking pricing")
+                    .small_heading()
+                    .into_widget(ctx),
+                Text::from(
+                    "Drivers weigh the price against how far they have to walk, and avoid spots \
+                     with a time limit shorter than their stay.",
+                )
+                .wrap_to_pct(ctx, 30)
+                .into_widget(ctx),
+                selector.make_controls(ctx).named("selector"),
+                legend,
+                Widget::row(vec![
+                    "Price per hour:".text_widget(ctx).centered_vert(),
+                    Spinner::widget(ctx, "price", (0, 50), price, 1),
+                ]),
+                Widget::row(vec![
+                    "Time limit in hours (0 for none):"
+                        .text_widget(ctx)
+                        .centered_vert(),
+                    Spinner::widget(ctx, "time limit", (0, 24), limit_hours, 1),
+                ]),
+                Toggle::checkbox(ctx, "Also price parking lots along these roads", None, true),
+                Widget::custom_row(vec![
+                    ctx.style()
+                        .btn_solid_primary
+                        .text("Apply")
+                        .hotkey(Key::Enter)
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_solid_destructive
+                        .text("Make parking free")
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_plain
+                        .text("Cancel")
+                        .hotkey(Key::Escape)
+                        .build_def(ctx),
+                ])
+                .evenly_spaced(),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            selector,
+            draw,
+        })
+    }
+
+    fn set_policy(&self, ctx: &mut EventCtx, app: &mut App, policy: Option<ParkingPolicy>) {
+        let map = &app.primary.map;
+        let old = map.get_parking_pricing().clone();
+        let mut new = old.clone();
+        let roads = &self.selector.roads;
+        let include_lots = self
+            .panel
+            .is_checked("Also price parking lots along these roads");
+        let lots: BTreeSet<_> = map
+            .all_parking_lots()
+            .iter()
+            .filter(|pl| include_lots && roads.contains(&pl.driving_pos.lane().road))
+            .map(|pl| pl.id)
+            .collect();
+        for r in roads {
+            if let Some(policy) = policy {
+                new.blockfaces.insert(*r, policy);
+            } else {
+                new.blockfaces.remove(r);
+            }
+        }
+        for pl in lots {
+            if let Some(policy) = policy {
+                new.lots.insert(pl, policy);
+            } else {
+                new.lots.remove(&pl);
+            }
+        }
+
+        let mut edits = map.get_edits().clone();
+        edits
+            .commands
+            .push(EditCmd::ChangeParkingPricing { old, new });
+        apply_map_edits(ctx, app, edits);
+    }
+}
+
+impl State<App> for ParkingPricingEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "Apply" => {
+                    let price: usize = self.panel.spinner("price");
+                    let limit_hours: usize = self.panel.spinner("time limit");
+                    self.set_policy(
+                        ctx,
+                        app,
+                        Some(ParkingPolicy {
+                            hourly_price: price as f64,
+                            max_stay: if limit_hours == 0 {
+                                None
+                            } else {
+                                Some(Duration::hours(limit_hours))
+                            },
+                        }),
+                    );
+                    return Transition::Pop;
+                }
+                "Make parking free" => {
+                    self.set_policy(ctx, app, None);
+                    return Transition::Pop;
+                }
+                "Cancel" => {
+                    return Transition::Pop;
+                }
+                x => {
+                    if self.selector.event(ctx, app, Some(x)) {
+                        let new_controls = self.selector.make_controls(ctx);
+                        self.panel.replace(ctx, "selector", new_controls);
+                    }
+                }
+            },
+            _ => {
+                if self.selector.event(ctx, app, None) {
+                    let new_controls = self.selector.make_controls(ctx);
+                    self.panel.replace(ctx, "selector", new_controls);
+                }
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.draw.draw(g);
+        self.panel.draw(g);
+        self.selector.draw(g, app, false);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn draw_pricing(ctx: &mut EventCtx, app: &App) -> (ToggleZoomed, Widget) {
+    let mut colorer = ColorDiscrete::new(
+        app,
+        vec![
+            ("priced", Color::YELLOW),
+            ("priced with a time limit", Color::ORANGE),
+        ],
+    );
+    let map = &app.primary.map;
+    for (r, policy) in &map.get_parking_pricing().blockfaces {
+        if policy.max_stay.is_some() {
+            colorer.add_r(*r, "priced with a time limit");
+        } else {
+            colorer.add_r(*r, "priced");
+        }
+    }
+    colorer.build(ctx)
+}
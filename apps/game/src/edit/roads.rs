@@ -1,11 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use crate::ID;
 use geom::{Bounds, CornerRadii, Distance, Polygon, Pt2D, UnitFmt};
 use map_gui::render::{Renderable, OUTLINE_THICKNESS};
 use map_model::{
-    osm, BufferType, Direction, EditCmd, EditRoad, LaneID, LaneSpec, LaneType, MapEdits, Road,
-    RoadID,
+    osm, BufferType, Direction, EditCmd, EditRoad, HovLanes, LaneID, LaneSpec, LaneType, MapEdits,
+    Road, RoadID,
 };
 use widgetry::tools::PopupMsg;
 use widgetry::{
@@ -16,6 +16,9 @@ use widgetry::{
 
 use crate::app::{App, Transition};
 use crate::common::Warping;
+use crate::edit::congestion_charge::CongestionChargeEditor;
+use crate::edit::loading_zones::LoadingZoneEditor;
+use crate::edit::parking_pricing::ParkingPricingEditor;
 use crate::edit::zones::ZoneEditor;
 use crate::edit::{apply_map_edits, can_edit_lane, speed_limit_choices};
 
@@ -271,6 +274,28 @@ impl State<App> for RoadEditor {
                         ),
                     );
                 }
+                "Apply to both carriageways" => {
+                    let mut edits = self
+                        .compress_edits(app)
+                        .unwrap_or_else(|| app.primary.map.get_edits().clone());
+                    let cmds = app.primary.map.edit_dual_carriageway_cmds(
+                        self.r,
+                        &self.orig_road_state,
+                        &app.primary.map.get_r_edit(self.r),
+                    );
+                    let num_changed = cmds.len();
+                    edits.commands.extend(cmds);
+                    apply_map_edits(ctx, app, edits);
+                    app.primary.current_selection = None;
+                    return Transition::Replace(PopupMsg::new_state(
+                        ctx,
+                        "Dual carriageway",
+                        vec![format!(
+                            "Changed {} road segments on the rest of the dual carriageway to match",
+                            num_changed
+                        )],
+                    ));
+                }
                 _ => unreachable!(),
             }
         }
@@ -283,11 +308,28 @@ impl State<App> for RoadEditor {
                 } else if x == "delete lane" {
                     return self.modify_current_lane(ctx, app, None, |new, idx| {
                         new.lanes_ltr.remove(idx);
+                        if let Some(ref mut hov) = new.hov_lanes {
+                            hov.lane_removed(idx);
+                        }
                     });
                 } else if x == "flip direction" {
                     return self.modify_current_lane(ctx, app, Some(0), |new, idx| {
                         new.lanes_ltr[idx].dir = new.lanes_ltr[idx].dir.opposite();
                     });
+                } else if x == "toggle HOV lane" {
+                    return self.modify_current_lane(ctx, app, Some(0), |new, idx| {
+                        let hov = new.hov_lanes.get_or_insert_with(|| HovLanes {
+                            lanes: BTreeSet::new(),
+                            min_occupancy: 2,
+                            toll: None,
+                        });
+                        if !hov.lanes.remove(&idx) {
+                            hov.lanes.insert(idx);
+                        }
+                        if hov.lanes.is_empty() {
+                            new.hov_lanes = None;
+                        }
+                    });
                 } else if let Some(lt) = x.strip_prefix("change to ") {
                     let lt = if lt == "buffer" {
                         self.main_panel.persistent_split_value("change to buffer")
@@ -342,6 +384,9 @@ impl State<App> for RoadEditor {
                             .unwrap(),
                         app.primary.map.get_config().driving_side,
                     );
+                    if let Some(ref mut hov) = new.hov_lanes {
+                        hov.lane_inserted(idx);
+                    }
                     edits.commands.push(EditCmd::ChangeRoad {
                         r: self.r,
                         old,
@@ -361,6 +406,34 @@ impl State<App> for RoadEditor {
                         apply_map_edits(ctx, app, edits);
                     }
                     return Transition::Replace(ZoneEditor::new_state(ctx, app, self.r));
+                } else if x == "Congestion charge" {
+                    // Like the ZoneEditor, this edits many roads at once
+                    if let Some(edits) = self.compress_edits(app) {
+                        apply_map_edits(ctx, app, edits);
+                    }
+                    return Transition::Replace(CongestionChargeEditor::new_state(
+                        ctx, app, self.r,
+                    ));
+                } else if x == "Parking pricing" {
+                    if let Some(edits) = self.compress_edits(app) {
+                        apply_map_edits(ctx, app, edits);
+                    }
+                    return Transition::Replace(ParkingPricingEditor::new_state(ctx, app, self.r));
+                } else if x == "Loading zones" {
+                    if let Some(edits) = self.compress_edits(app) {
+                        apply_map_edits(ctx, app, edits);
+                    }
+                    return Transition::Replace(LoadingZoneEditor::new_state(ctx, app, self.r));
+                } else if x == "Remove slip lane" {
+                    let mut edits = app.primary.map.get_edits().clone();
+                    edits
+                        .commands
+                        .push(app.primary.map.remove_slip_lane_cmd(self.r));
+                    apply_map_edits(ctx, app, edits);
+                    self.redo_stack.clear();
+                    self.selected_lane = None;
+                    self.recalc_hovering(ctx, app);
+                    panels_need_recalc = true;
                 } else {
                     unreachable!()
                 }
@@ -446,6 +519,9 @@ impl State<App> for RoadEditor {
                         .push(app.primary.map.edit_road_cmd(self.r, |new| {
                             let spec = new.lanes_ltr.remove(old_idx);
                             new.lanes_ltr.insert(new_idx, spec);
+                            if let Some(ref mut hov) = new.hov_lanes {
+                                hov.lane_moved(old_idx, new_idx);
+                            }
                         }));
                     apply_map_edits(ctx, app, edits);
                     self.redo_stack.clear();
@@ -543,6 +619,17 @@ fn make_top_panel(
                 .disabled(current_state == orig_road_state)
                 .disabled_tooltip("You have to edit one road segment first, then you can apply the changes to more segments.")
                 .build_widget(ctx, "Apply to multiple road segments"),
+            if map.get_r(r).opposite_carriageway.is_empty() {
+                Widget::nothing()
+            } else {
+                ctx.style()
+                    .btn_plain
+                    .text("+ Apply to both carriageways")
+                    .label_color(Color::hex("#4CA7E9"), ControlState::Default)
+                    .disabled(current_state == orig_road_state)
+                    .disabled_tooltip("Edit this side of the road first, then copy the changes to the other side.")
+                    .build_widget(ctx, "Apply to both carriageways")
+            },
         ]),
         Widget::row(vec![
             ctx.style()
@@ -843,6 +930,19 @@ fn make_main_panel(
                     .hotkey(Key::F)
                     .build_def(ctx)
                     .centered_vert(),
+                if lane.is_driving() {
+                    ctx.style()
+                        .btn_plain
+                        .text(if road.hov_restriction(l).is_some() {
+                            "open to all vehicles"
+                        } else {
+                            "reserve for carpools"
+                        })
+                        .build_widget(ctx, "toggle HOV lane")
+                        .centered_vert()
+                } else {
+                    Widget::nothing()
+                },
                 Widget::row(vec![
                     Line("Width").secondary().into_widget(ctx).centered_vert(),
                     Widget::dropdown(ctx, "width preset", lane.width, width_choices(app, l)),
@@ -920,6 +1020,30 @@ fn make_main_panel(
             .text("Access restrictions")
             .build_def(ctx)
             .centered_vert(),
+        ctx.style()
+            .btn_outline
+            .text("Congestion charge")
+            .build_def(ctx)
+            .centered_vert(),
+        ctx.style()
+            .btn_outline
+            .text("Parking pricing")
+            .build_def(ctx)
+            .centered_vert(),
+        ctx.style()
+            .btn_outline
+            .text("Loading zones")
+            .build_def(ctx)
+            .centered_vert(),
+        if road.is_slip_lane() {
+            ctx.style()
+                .btn_outline
+                .text("Remove slip lane")
+                .build_def(ctx)
+                .centered_vert()
+        } else {
+            Widget::nothing()
+        },
     ]);
 
     Panel::new_builder(
@@ -1,8 +1,9 @@
 use geom::{Duration, Time};
-use map_model::{EditCmd, TransitRouteID};
+use map_model::{BoardingFeatures, EditCmd, TransitRouteID, TransitStopID};
+use widgetry::tools::PopupMsg;
 use widgetry::{
     EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, Spinner, State, TextExt,
-    VerticalAlignment, Widget,
+    Toggle, VerticalAlignment, Widget,
 };
 
 use crate::app::App;
@@ -18,7 +19,19 @@ impl RouteEditor {
     pub fn new_state(ctx: &mut EventCtx, app: &mut App, id: TransitRouteID) -> Box<dyn State<App>> {
         app.primary.current_selection = None;
 
-        let route = app.primary.map.get_tr(id);
+        let map = &app.primary.map;
+        let route = map.get_tr(id);
+        // Include stops skipped by an earlier detour, so they can be restored
+        let mut stop_checkboxes = Vec::new();
+        for (idx, ts) in all_stops(app, id).into_iter().enumerate() {
+            stop_checkboxes.push(Toggle::checkbox(
+                ctx,
+                &stop_label(idx, &map.get_ts(ts).name),
+                None,
+                route.stops.contains(&ts),
+            ));
+        }
+
         Box::new(RouteEditor {
             panel: Panel::new_builder(Widget::col(vec![
                 Widget::row(vec![
@@ -37,6 +50,10 @@ impl RouteEditor {
                         Duration::minutes(1),
                     ),
                 ]),
+                "At every stop:".text_widget(ctx),
+                boarding_checkboxes(ctx, route.boarding),
+                "Serve these stops, detouring around closed roads:".text_widget(ctx),
+                Widget::col(stop_checkboxes),
                 ctx.style()
                     .btn_solid_primary
                     .text("Apply")
@@ -68,12 +85,55 @@ impl State<App> for RouteEditor {
                         now += freq;
                     }
 
-                    let mut edits = app.primary.map.get_edits().clone();
+                    let map = &app.primary.map;
+                    let route = map.get_tr(self.route);
+                    let stops: Vec<TransitStopID> = all_stops(app, self.route)
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(idx, ts)| {
+                            self.panel
+                                .is_checked(&stop_label(*idx, &map.get_ts(*ts).name))
+                        })
+                        .map(|(_, ts)| ts)
+                        .collect();
+                    if stops != route.stops {
+                        let mut detoured = route.clone();
+                        detoured.stops = stops.clone();
+                        let problem = if stops.is_empty() {
+                            Some("The route has to serve at least one stop".to_string())
+                        } else {
+                            detoured.all_paths(map).err().map(|err| err.to_string())
+                        };
+                        if let Some(problem) = problem {
+                            return Transition::Push(PopupMsg::new_state(
+                                ctx,
+                                "Can't detour this route",
+                                vec![problem],
+                            ));
+                        }
+                    }
+
+                    let mut edits = map.get_edits().clone();
                     edits.commands.push(EditCmd::ChangeRouteSchedule {
                         id: self.route,
-                        old: app.primary.map.get_tr(self.route).spawn_times.clone(),
+                        old: route.spawn_times.clone(),
                         new: hourly_times,
                     });
+                    let boarding = read_boarding_checkboxes(&self.panel);
+                    if boarding != route.boarding {
+                        edits.commands.push(EditCmd::ChangeRouteBoarding {
+                            id: self.route,
+                            old: route.boarding,
+                            new: boarding,
+                        });
+                    }
+                    if stops != route.stops {
+                        edits.commands.push(EditCmd::ChangeRouteStops {
+                            id: self.route,
+                            old: route.stops.clone(),
+                            new: stops,
+                        });
+                    }
                     apply_map_edits(ctx, app, edits);
 
                     return Transition::Pop;
@@ -89,3 +149,113 @@ impl State<App> for RouteEditor {
         self.panel.draw(g);
     }
 }
+
+/// Edit the boarding features of one stop, affecting every route serving it, or close the stop.
+pub struct StopEditor {
+    panel: Panel,
+    stop: TransitStopID,
+}
+
+impl StopEditor {
+    pub fn new_state(ctx: &mut EventCtx, app: &mut App, id: TransitStopID) -> Box<dyn State<App>> {
+        app.primary.current_selection = None;
+
+        let stop = app.primary.map.get_ts(id);
+        Box::new(StopEditor {
+            panel: Panel::new_builder(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Stop editor").small_heading().into_widget(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                Line(&stop.name).into_widget(ctx),
+                "For every route here:".text_widget(ctx),
+                boarding_checkboxes(ctx, stop.boarding),
+                Toggle::checkbox(ctx, "closed, like for construction", None, stop.closed),
+                ctx.style()
+                    .btn_solid_primary
+                    .text("Apply")
+                    .hotkey(Key::Enter)
+                    .build_def(ctx),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            stop: id,
+        })
+    }
+}
+
+impl State<App> for StopEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "Apply" => {
+                    let stop = app.primary.map.get_ts(self.stop);
+                    let mut edits = app.primary.map.get_edits().clone();
+                    let boarding = read_boarding_checkboxes(&self.panel);
+                    if boarding != stop.boarding {
+                        edits.commands.push(EditCmd::ChangeStopBoarding {
+                            id: self.stop,
+                            old: stop.boarding,
+                            new: boarding,
+                        });
+                    }
+                    let closed = self.panel.is_checked("closed, like for construction");
+                    if closed != stop.closed {
+                        edits.commands.push(EditCmd::ChangeStopClosed {
+                            id: self.stop,
+                            old: stop.closed,
+                            new: closed,
+                        });
+                    }
+                    if edits.commands.len() != app.primary.map.get_edits().commands.len() {
+                        apply_map_edits(ctx, app, edits);
+                    }
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+    }
+}
+
+fn boarding_checkboxes(ctx: &mut EventCtx, features: BoardingFeatures) -> Widget {
+    Widget::col(vec![
+        Toggle::checkbox(ctx, "all-door boarding", None, features.all_door_boarding),
+        Toggle::checkbox(ctx, "off-board fares", None, features.off_board_fares),
+        Toggle::checkbox(ctx, "level boarding", None, features.level_boarding),
+    ])
+}
+
+fn read_boarding_checkboxes(panel: &Panel) -> BoardingFeatures {
+    BoardingFeatures {
+        all_door_boarding: panel.is_checked("all-door boarding"),
+        off_board_fares: panel.is_checked("off-board fares"),
+        level_boarding: panel.is_checked("level boarding"),
+    }
+}
+
+/// The stops a route served before any detours
+fn all_stops(app: &App, id: TransitRouteID) -> Vec<TransitStopID> {
+    let map = &app.primary.map;
+    map.get_edits()
+        .original_route_stops
+        .get(&id)
+        .cloned()
+        .unwrap_or_else(|| map.get_tr(id).stops.clone())
+}
+
+// Stop names along a route aren't always unique
+fn stop_label(idx: usize, name: &str) -> String {
+    format!("{}. {}", idx + 1, name)
+}
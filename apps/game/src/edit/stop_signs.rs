@@ -80,6 +80,22 @@ impl StopSignEditor {
                     .btn_outline
                     .text("convert to traffic signal")
                     .build_def(ctx),
+                ctx.style()
+                    .btn_outline
+                    .text(if app.primary.map.get_i(id).allow_u_turns {
+                        "ban U-turns"
+                    } else {
+                        "allow U-turns"
+                    })
+                    .build_def(ctx),
+                ctx.style()
+                    .btn_outline
+                    .text(if app.primary.map.get_i(id).protected_corners {
+                        "remove protected corners for bikes"
+                    } else {
+                        "protect bikes with corner islands"
+                    })
+                    .build_def(ctx),
             ]),
         ]))
         .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
@@ -160,6 +176,36 @@ impl SimpleState<App> for StopSignEditor {
                     self.mode.clone(),
                 ))
             }
+            "allow U-turns" | "ban U-turns" => {
+                let mut edits = app.primary.map.get_edits().clone();
+                edits
+                    .commands
+                    .push(app.primary.map.edit_intersection_cmd(self.id, |new| {
+                        new.allow_u_turns = !new.allow_u_turns;
+                    }));
+                apply_map_edits(ctx, app, edits);
+                Transition::Replace(StopSignEditor::new_state(
+                    ctx,
+                    app,
+                    self.id,
+                    self.mode.clone(),
+                ))
+            }
+            "protect bikes with corner islands" | "remove protected corners for bikes" => {
+                let mut edits = app.primary.map.get_edits().clone();
+                edits
+                    .commands
+                    .push(app.primary.map.edit_intersection_cmd(self.id, |new| {
+                        new.protected_corners = !new.protected_corners;
+                    }));
+                apply_map_edits(ctx, app, edits);
+                Transition::Replace(StopSignEditor::new_state(
+                    ctx,
+                    app,
+                    self.id,
+                    self.mode.clone(),
+                ))
+            }
             "Change crosswalks" => Transition::Replace(
                 super::crosswalks::CrosswalkEditor::new_state(ctx, app, self.id),
             ),
@@ -1,12 +1,14 @@
-use geom::Duration;
+use geom::{Distance, Duration};
 use map_gui::tools::FilePicker;
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, EditIntersectionControl, IntersectionID, StageType,
+    Actuation, ControlStopSign, ControlTrafficSignal, EditIntersectionControl, IntersectionID,
+    StageType, TransitPriority,
 };
+use maplit::btreeset;
 use widgetry::tools::{ChooseSomething, PopupMsg};
 use widgetry::{
-    Choice, DrawBaselayer, EventCtx, Key, Line, Panel, SimpleState, Spinner, State, Text, TextExt,
-    Widget,
+    Choice, DrawBaselayer, EventCtx, Key, Line, Panel, RoundedF64, SimpleState, Spinner, State,
+    Text, TextExt, Widget,
 };
 
 use crate::app::{App, Transition};
@@ -176,6 +178,238 @@ impl SimpleState<App> for ChangeDuration {
     }
 }
 
+pub struct EditActuation;
+
+impl EditActuation {
+    pub fn new_state(ctx: &mut EventCtx, signal: &ControlTrafficSignal) -> Box<dyn State<App>> {
+        let actuation = signal.actuation.clone().unwrap_or_else(Actuation::new);
+        let duration_spinner = |name: &str, value: Duration| {
+            Spinner::widget(
+                ctx,
+                name,
+                (Duration::seconds(1.0), Duration::minutes(5)),
+                value,
+                Duration::seconds(1.0),
+            )
+        };
+        let panel = Panel::new_builder(Widget::col(vec![
+            Widget::row(vec![
+                Line("Respond to detected vehicles")
+                    .small_heading()
+                    .into_widget(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            Text::from(
+                Line(
+                    "Actuated signals skip stages nobody is waiting for, and end a stage early \
+                     when nobody else arrives. Stage durations are ignored.",
+                )
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 30)
+            .into_widget(ctx),
+            Widget::row(vec![
+                "Minimum green:".text_widget(ctx).centered_vert(),
+                duration_spinner("min green", actuation.min_green),
+            ]),
+            Widget::row(vec![
+                "Maximum green, if others are waiting:"
+                    .text_widget(ctx)
+                    .centered_vert(),
+                duration_spinner("max green", actuation.max_green),
+            ]),
+            Widget::row(vec![
+                "Extend green while vehicles arrive by:"
+                    .text_widget(ctx)
+                    .centered_vert(),
+                duration_spinner("extension", actuation.extension),
+            ]),
+            Widget::row(vec![
+                "Detect vehicles this many meters from the stop line:"
+                    .text_widget(ctx)
+                    .centered_vert(),
+                Spinner::f64_widget(
+                    ctx,
+                    "detector length",
+                    (5.0, 150.0),
+                    actuation.detector_length.inner_meters(),
+                    5.0,
+                ),
+            ]),
+            Widget::row(vec![
+                ctx.style()
+                    .btn_solid_primary
+                    .text("Apply")
+                    .hotkey(Key::Enter)
+                    .build_def(ctx),
+                ctx.style()
+                    .btn_outline
+                    .text("Use fixed timing")
+                    .disabled(signal.actuation.is_none())
+                    .build_def(ctx),
+            ]),
+        ]))
+        .build(ctx);
+        <dyn SimpleState<_>>::new_state(panel, Box::new(EditActuation))
+    }
+}
+
+impl SimpleState<App> for EditActuation {
+    fn on_click(
+        &mut self,
+        _: &mut EventCtx,
+        _: &mut App,
+        x: &str,
+        panel: &mut Panel,
+    ) -> Transition {
+        let actuation = match x {
+            "close" => {
+                return Transition::Pop;
+            }
+            "Apply" => {
+                let min_green: Duration = panel.spinner("min green");
+                let max_green: Duration = panel.spinner("max green");
+                Some(Actuation {
+                    min_green,
+                    max_green: max_green.max(min_green),
+                    extension: panel.spinner("extension"),
+                    detector_length: Distance::meters(
+                        panel.spinner::<RoundedF64>("detector length").0,
+                    ),
+                })
+            }
+            "Use fixed timing" => None,
+            _ => unreachable!(),
+        };
+        Transition::Multi(vec![
+            Transition::Pop,
+            Transition::ModifyState(Box::new(move |state, ctx, app| {
+                let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+                editor.add_new_edit(ctx, app, 0, |ts| {
+                    ts.actuation = actuation.clone();
+                });
+            })),
+        ])
+    }
+
+    fn other_event(&mut self, ctx: &mut EventCtx, _: &mut App) -> Transition {
+        if ctx.normal_left_click() && ctx.canvas.get_cursor_in_screen_space().is_none() {
+            return Transition::Pop;
+        }
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::PreviousState
+    }
+}
+
+pub struct EditTransitPriority;
+
+impl EditTransitPriority {
+    pub fn new_state(ctx: &mut EventCtx, signal: &ControlTrafficSignal) -> Box<dyn State<App>> {
+        let priority = signal
+            .transit_priority
+            .clone()
+            .unwrap_or_else(TransitPriority::new);
+        let panel = Panel::new_builder(Widget::col(vec![
+            Widget::row(vec![
+                Line("Transit signal priority")
+                    .small_heading()
+                    .into_widget(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            Text::from(
+                Line(
+                    "When a bus or train approaches, stages that don't serve it are cut short, or \
+                     its green is held until it gets through.",
+                )
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 30)
+            .into_widget(ctx),
+            Widget::row(vec![
+                "Never cut a stage shorter than:"
+                    .text_widget(ctx)
+                    .centered_vert(),
+                Spinner::widget(
+                    ctx,
+                    "min green",
+                    (Duration::seconds(1.0), Duration::minutes(2)),
+                    priority.min_green,
+                    Duration::seconds(1.0),
+                ),
+            ]),
+            Widget::row(vec![
+                "Hold the green at most:".text_widget(ctx).centered_vert(),
+                Spinner::widget(
+                    ctx,
+                    "max extension",
+                    (Duration::ZERO, Duration::minutes(2)),
+                    priority.max_extension,
+                    Duration::seconds(1.0),
+                ),
+            ]),
+            Widget::row(vec![
+                ctx.style()
+                    .btn_solid_primary
+                    .text("Apply")
+                    .hotkey(Key::Enter)
+                    .build_def(ctx),
+                ctx.style()
+                    .btn_outline
+                    .text("No priority")
+                    .disabled(signal.transit_priority.is_none())
+                    .build_def(ctx),
+            ]),
+        ]))
+        .build(ctx);
+        <dyn SimpleState<_>>::new_state(panel, Box::new(EditTransitPriority))
+    }
+}
+
+impl SimpleState<App> for EditTransitPriority {
+    fn on_click(
+        &mut self,
+        _: &mut EventCtx,
+        _: &mut App,
+        x: &str,
+        panel: &mut Panel,
+    ) -> Transition {
+        let priority = match x {
+            "close" => {
+                return Transition::Pop;
+            }
+            "Apply" => Some(TransitPriority {
+                min_green: panel.spinner("min green"),
+                max_extension: panel.spinner("max extension"),
+            }),
+            "No priority" => None,
+            _ => unreachable!(),
+        };
+        Transition::Multi(vec![
+            Transition::Pop,
+            Transition::ModifyState(Box::new(move |state, ctx, app| {
+                let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+                editor.add_new_edit(ctx, app, 0, |ts| {
+                    ts.transit_priority = priority.clone();
+                });
+            })),
+        ])
+    }
+
+    fn other_event(&mut self, ctx: &mut EventCtx, _: &mut App) -> Transition {
+        if ctx.normal_left_click() && ctx.canvas.get_cursor_in_screen_space().is_none() {
+            return Transition::Pop;
+        }
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::PreviousState
+    }
+}
+
 pub fn edit_entire_signal(
     ctx: &mut EventCtx,
     app: &App,
@@ -190,10 +424,26 @@ pub fn edit_entire_signal(
         .turns
         .iter()
         .any(|t| t.between_sidewalks());
+    let has_bike_lanes = app
+        .primary
+        .map
+        .get_i(i)
+        .incoming_lanes
+        .iter()
+        .any(|l| app.primary.map.get_l(*l).is_biking());
+    let protected_corners = app.primary.map.get_i(i).protected_corners;
 
     let use_template = "use template";
     let all_walk = "add an all-walk stage at the end";
+    let bike_stage = "add a bike-only stage at the end";
+    let toggle_protected = if protected_corners {
+        "remove protected corners for bikes"
+    } else {
+        "protect bikes with corner islands"
+    };
     let major_minor_timing = "use timing pattern for a major/minor intersection";
+    let actuated = "respond to detected vehicles (actuated)";
+    let transit_priority = "give buses and trains priority";
     let stop_sign = "convert to stop signs";
     let close = "close intersection for construction";
     let reset = "reset to default";
@@ -209,7 +459,15 @@ pub fn edit_entire_signal(
     if has_sidewalks {
         choices.push(all_walk.to_string());
     }
+    if has_bike_lanes {
+        choices.push(bike_stage.to_string());
+        if mode.can_edit_roads() {
+            choices.push(toggle_protected.to_string());
+        }
+    }
     choices.push(major_minor_timing.to_string());
+    choices.push(actuated.to_string());
+    choices.push(transit_priority.to_string());
     // TODO Conflating stop signs and construction here
     if mode.can_edit_stop_signs() {
         choices.push(stop_sign.to_string());
@@ -258,6 +516,41 @@ pub fn edit_entire_signal(
                     }
                 })),
             ]),
+            x if x == bike_stage => Transition::Multi(vec![
+                Transition::Pop,
+                Transition::ModifyState(Box::new(move |state, ctx, app| {
+                    let mut new_signal = app.primary.map.get_traffic_signal(i).clone();
+                    if new_signal.add_bike_stage(&app.primary.map) {
+                        let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+                        editor.add_new_edit(ctx, app, 0, |ts| {
+                            *ts = new_signal.clone();
+                        });
+                    }
+                })),
+            ]),
+            x if x == toggle_protected => {
+                original.apply(app);
+
+                let mut edits = app.primary.map.get_edits().clone();
+                edits
+                    .commands
+                    .push(app.primary.map.edit_intersection_cmd(i, |new| {
+                        new.protected_corners = !new.protected_corners;
+                    }));
+                apply_map_edits(ctx, app, edits);
+                app.primary
+                    .sim
+                    .handle_live_edited_traffic_signals(&app.primary.map);
+                Transition::Multi(vec![
+                    Transition::Pop,
+                    Transition::Replace(TrafficSignalEditor::new_state(
+                        ctx,
+                        app,
+                        btreeset! {i},
+                        mode,
+                    )),
+                ])
+            }
             x if x == major_minor_timing => Transition::Replace(ChooseSomething::new_state(
                 ctx,
                 "Use what timing split?",
@@ -292,6 +585,14 @@ pub fn edit_entire_signal(
                     }
                 }),
             )),
+            x if x == transit_priority => Transition::Replace(EditTransitPriority::new_state(
+                ctx,
+                app.primary.map.get_traffic_signal(i),
+            )),
+            x if x == actuated => Transition::Replace(EditActuation::new_state(
+                ctx,
+                app.primary.map.get_traffic_signal(i),
+            )),
             x if x == stop_sign => {
                 original.apply(app);
 
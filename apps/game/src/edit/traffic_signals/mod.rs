@@ -3,7 +3,7 @@ use std::collections::{BTreeSet, VecDeque};
 use anyhow::Result;
 
 use abstutil::Timer;
-use geom::{Distance, Line, Polygon, Pt2D};
+use geom::{Distance, Line, Polygon, Pt2D, Speed};
 use map_gui::options::TrafficSignalStyle;
 use map_gui::render::{traffic_signal, DrawMovement, DrawOptions};
 use map_model::{
@@ -228,6 +228,15 @@ impl State<App> for TrafficSignalEditor {
                         self.members.clone(),
                     ));
                 }
+                "Coordinate a green wave" => {
+                    return Transition::Push(offsets::GreenWave::new_state(
+                        ctx,
+                        app,
+                        self.members.clone(),
+                        Vec::new(),
+                        Speed::miles_per_hour(25.0),
+                    ));
+                }
                 "Add a new stage" => {
                     self.add_new_edit(ctx, app, num_stages, |ts| {
                         ts.stages.push(Stage::new());
@@ -719,12 +728,17 @@ fn make_side_panel(
     for idx in 0..canonical_signal.stages.len() {
         let mut stack = GeomBatchStack::vertical(vec![
             Text::from(Line(format!(
-                "Stage {}: {}",
+                "Stage {}: {}{}",
                 idx + 1,
                 match canonical_signal.stages[idx].stage_type {
                     StageType::Fixed(d) => format!("{}", d),
                     StageType::Variable(min, _, _) => format!("{} (v)", min),
                 },
+                if canonical_signal.stages[idx].bikes_only {
+                    " (bikes)"
+                } else {
+                    ""
+                },
             )))
             .render(ctx),
             draw_multiple_signals(ctx, app, members, idx, &translations),
@@ -774,11 +788,18 @@ fn make_side_panel(
                 .hotkey(Key::E)
                 .build_def(ctx)
         } else {
-            ctx.style()
-                .btn_outline
-                .text("Tune offsets between signals")
-                .hotkey(Key::O)
-                .build_def(ctx)
+            Widget::col(vec![
+                ctx.style()
+                    .btn_outline
+                    .text("Tune offsets between signals")
+                    .hotkey(Key::O)
+                    .build_def(ctx),
+                ctx.style()
+                    .btn_outline
+                    .text("Coordinate a green wave")
+                    .hotkey(Key::G)
+                    .build_def(ctx),
+            ])
         },
     ]));
 
@@ -2,8 +2,9 @@ use std::collections::BTreeSet;
 
 use maplit::btreeset;
 
-use geom::{Distance, Duration};
+use geom::{Distance, Duration, Speed};
 use map_model::IntersectionID;
+use widgetry::tools::PopupMsg;
 use widgetry::{
     Color, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Panel, RewriteColor,
     SimpleState, Spinner, State, Text, TextExt, VerticalAlignment, Widget,
@@ -11,6 +12,7 @@ use widgetry::{
 
 use crate::app::{App, Transition};
 use crate::common::CommonState;
+use crate::edit::speed_limit_choices;
 use crate::edit::traffic_signals::fade_irrelevant;
 
 pub struct ShowAbsolute {
@@ -324,3 +326,168 @@ impl SimpleState<App> for TuneRelative {
         g.redraw(&self.labels);
     }
 }
+
+/// Pick signals in the order traffic passes through them, then calculate offsets for a green wave
+/// along that corridor.
+pub struct GreenWave {
+    members: BTreeSet<IntersectionID>,
+    corridor: Vec<IntersectionID>,
+    progression_speed: Speed,
+    labels: Drawable,
+}
+
+impl GreenWave {
+    pub fn new_state(
+        ctx: &mut EventCtx,
+        app: &App,
+        members: BTreeSet<IntersectionID>,
+        corridor: Vec<IntersectionID>,
+        progression_speed: Speed,
+    ) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut batch = fade_irrelevant(app, &members);
+        for pair in corridor.windows(2) {
+            if let Some((roads, _)) = map.simple_path_btwn(pair[0], pair[1]) {
+                for r in roads {
+                    batch.push(app.cs.route, map.get_r(r).get_thick_polygon());
+                }
+            }
+        }
+        for (idx, i) in corridor.iter().enumerate() {
+            batch.append(
+                Text::from(format!("{}", idx + 1))
+                    .bg(Color::PURPLE)
+                    .render_autocropped(ctx)
+                    .color(RewriteColor::ChangeAlpha(0.8))
+                    .scale(0.3)
+                    .centered_on(map.get_i(*i).polygon.center()),
+            );
+        }
+
+        let panel = Panel::new_builder(Widget::col(vec![
+            Widget::row(vec![
+                Line("Coordinate a green wave")
+                    .small_heading()
+                    .into_widget(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            if corridor.len() < 2 {
+                "Select signals in the order traffic passes through them".text_widget(ctx)
+            } else {
+                format!("{} signals in the corridor", corridor.len()).text_widget(ctx)
+            },
+            Widget::row(vec![
+                "Progression speed:".text_widget(ctx).centered_vert(),
+                Widget::dropdown(
+                    ctx,
+                    "progression speed",
+                    progression_speed,
+                    speed_limit_choices(app, Some(progression_speed)),
+                ),
+            ]),
+            Widget::row(vec![
+                ctx.style()
+                    .btn_outline
+                    .text("Start over")
+                    .disabled(corridor.is_empty())
+                    .build_def(ctx),
+                ctx.style()
+                    .btn_solid_primary
+                    .text("Update offsets")
+                    .hotkey(Key::Enter)
+                    .disabled(corridor.len() < 2)
+                    .build_def(ctx),
+            ]),
+        ]))
+        .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+        .build(ctx);
+        <dyn SimpleState<_>>::new_state(
+            panel,
+            Box::new(GreenWave {
+                members,
+                corridor,
+                progression_speed,
+                labels: ctx.upload(batch),
+            }),
+        )
+    }
+}
+
+impl SimpleState<App> for GreenWave {
+    fn on_click(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        x: &str,
+        _: &mut Panel,
+    ) -> Transition {
+        match x {
+            "close" => Transition::Pop,
+            "Start over" => Transition::Replace(GreenWave::new_state(
+                ctx,
+                app,
+                self.members.clone(),
+                Vec::new(),
+                self.progression_speed,
+            )),
+            "Update offsets" => match app
+                .primary
+                .map
+                .green_wave(&self.corridor, self.progression_speed)
+            {
+                Ok(signals) => {
+                    for ts in signals {
+                        app.primary.map.incremental_edit_traffic_signal(ts);
+                    }
+                    Transition::Replace(ShowAbsolute::new_state(ctx, app, self.members.clone()))
+                }
+                Err(err) => Transition::Push(PopupMsg::new_state(
+                    ctx,
+                    "Can't coordinate these signals",
+                    vec![err.to_string()],
+                )),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn panel_changed(
+        &mut self,
+        _: &mut EventCtx,
+        _: &mut App,
+        panel: &mut Panel,
+    ) -> Option<Transition> {
+        self.progression_speed = panel.dropdown_value("progression speed");
+        None
+    }
+
+    fn on_mouseover(&mut self, ctx: &mut EventCtx, app: &mut App) {
+        app.primary.current_selection = app.mouseover_unzoomed_intersections(ctx).filter(|id| {
+            let i = id.as_intersection();
+            self.members.contains(&i) && !self.corridor.contains(&i)
+        });
+    }
+
+    fn other_event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if let Some(i) = app.click_on_intersection(ctx, "add to the corridor") {
+            let mut corridor = self.corridor.clone();
+            corridor.push(i);
+            return Transition::Replace(GreenWave::new_state(
+                ctx,
+                app,
+                self.members.clone(),
+                corridor,
+                self.progression_speed,
+            ));
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        CommonState::draw_osd(g, app);
+
+        g.redraw(&self.labels);
+    }
+}
@@ -1,7 +1,6 @@
 use std::collections::BTreeSet;
 
 use enumset::EnumSet;
-use maplit::btreeset;
 
 use map_gui::tools::{checkbox_per_mode, intersections_from_roads, ColorDiscrete};
 use map_model::{AccessRestrictions, CommonEndpoint, PathConstraints, RoadID};
@@ -32,8 +31,8 @@ impl ZoneEditor {
         let members = if let Some(z) = start.get_zone(&app.primary.map) {
             z.members.clone()
         } else {
-            // Starting a new zone
-            btreeset! { start.id }
+            // Starting a new zone. Restrict both sides of a dual carriageway by default.
+            app.primary.map.dual_carriageway(start.id)
         };
         let allow_through_traffic = start
             .access_restrictions
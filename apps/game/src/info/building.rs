@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 
+use abstutil::prettyprint_usize;
 use geom::{Angle, Circle, Distance, Speed, Time};
 use map_model::{BuildingID, LaneID, OffstreetParking, Traversable, SIDEWALK_THICKNESS};
 use sim::{DrawPedestrianInput, PedestrianID, PersonID, TripResult, VehicleType};
@@ -29,6 +30,18 @@ fn info_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingI
     if app.opts.dev {
         kv.push(("OSM ID", format!("{}", b.orig_id.inner_id())));
     }
+    kv.push((
+        "Size",
+        format!(
+            "{} levels, {} tall, {} m² of floor space",
+            b.levels,
+            b.height.to_string(&app.opts.units),
+            prettyprint_usize(b.floor_area() as usize)
+        ),
+    ));
+    if let Some(land_use) = b.land_use {
+        kv.push(("Zoning", format!("{:?}", land_use)));
+    }
 
     let num_spots = b.num_parking_spots();
     if app.primary.sim.infinite_parking() {
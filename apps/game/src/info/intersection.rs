@@ -329,15 +329,21 @@ fn traffic_signal_body(ctx: &mut EventCtx, app: &App, id: IntersectionID) -> Wid
     }
 
     for (idx, stage) in signal.stages.iter().enumerate() {
+        let bikes_only = if stage.bikes_only {
+            " (bikes only)"
+        } else {
+            ""
+        };
         rows.push(
             match stage.stage_type {
-                StageType::Fixed(d) => Line(format!("Stage {}: {}", idx + 1, d)),
+                StageType::Fixed(d) => Line(format!("Stage {}: {}{}", idx + 1, d, bikes_only)),
                 StageType::Variable(min, delay, additional) => Line(format!(
-                    "Stage {}: {}, {}, {} (variable)",
+                    "Stage {}: {}, {}, {} (variable){}",
                     idx + 1,
                     min,
                     delay,
-                    additional
+                    additional,
+                    bikes_only
                 )),
             }
             .into_widget(ctx),
@@ -1,5 +1,5 @@
 use abstutil::prettyprint_usize;
-use map_model::{LaneID, PathConstraints};
+use map_model::{LaneID, PathConstraints, TrailType};
 use widgetry::{EventCtx, Line, LinePlot, PlotOptions, Series, Text, TextExt, Widget};
 
 use crate::app::App;
@@ -27,6 +27,11 @@ fn info_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
     if !l.is_walkable() {
         kv.push(("Type", l.lane_type.describe().to_string()));
     }
+    match r.trail {
+        Some(TrailType::Path) => kv.push(("Trail", "Off-road shared-use path".to_string())),
+        Some(TrailType::Track) => kv.push(("Trail", "Off-road track".to_string())),
+        None => {}
+    }
     if r.is_private() {
         let mut ban = Vec::new();
         for p in PathConstraints::all() {
@@ -40,16 +45,31 @@ fn info_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
     }
 
     if l.is_parking() {
+        let map = &app.primary.map;
+        let loading_spaces = map.get_loading_zones().spots_taken_from(map, l.id);
         kv.push((
             "Parking",
             format!(
                 "{} / {} spots available",
                 app.primary.sim.get_free_onstreet_spots(l.id).len(),
-                l.number_parking_spots(app.primary.map.get_config())
+                l.number_parking_spots(map.get_config()) - loading_spaces
             ),
         ));
+        if loading_spaces > 0 {
+            kv.push(("Loading zone", format!("{} spaces", loading_spaces)));
+        }
     } else {
         kv.push(("Speed limit", r.speed_limit.to_string(&app.opts.units)));
+        let dr = l.get_directed_parent();
+        if app.primary.sim.has_variable_speed_limit(dr) {
+            kv.push((
+                "Variable speed limit sign",
+                match app.primary.sim.get_posted_speed_limit(dr) {
+                    Some(speed) => format!("showing {}", speed.to_string(&app.opts.units)),
+                    None => "showing the normal limit".to_string(),
+                },
+            ));
+        }
     }
 
     kv.push(("Length", l.length().to_string(&app.opts.units)));
@@ -57,7 +77,9 @@ fn info_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
     rows.extend(make_table(ctx, kv));
 
     if l.is_parking() {
-        let capacity = l.number_parking_spots(app.primary.map.get_config());
+        let map = &app.primary.map;
+        let capacity = l.number_parking_spots(map.get_config())
+            - map.get_loading_zones().spots_taken_from(map, l.id);
         let mut series = vec![Series {
             label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
             color: app.cs.after_changes,
@@ -228,6 +250,25 @@ fn traffic_body(ctx: &mut EventCtx, app: &App, id: LaneID, opts: &DataOptions) -
         "Since midnight: {} commuters and vehicles crossed",
         prettyprint_usize(app.primary.sim.get_analytics().road_thruput.total_for(r))
     ));
+    txt.add_line(format!(
+        "Since midnight: {} people moved along this lane",
+        prettyprint_usize(
+            app.primary
+                .sim
+                .get_analytics()
+                .lane_person_thruput
+                .total_for(id)
+        )
+    ));
+    if let Some(hov) = app.primary.map.get_r(r).hov_restriction(id) {
+        txt.add_line(match hov.toll {
+            Some(toll) => format!(
+                "HOT lane: free for {}+ people per vehicle, otherwise {:.2} toll",
+                hov.min_occupancy, toll
+            ),
+            None => format!("HOV lane: {}+ people per vehicle", hov.min_occupancy),
+        });
+    }
     rows.push(txt.into_widget(ctx));
 
     rows.push(opts.to_controls(ctx, app));
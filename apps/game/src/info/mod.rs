@@ -21,7 +21,7 @@ use widgetry::{
 use crate::app::{App, Transition};
 use crate::common::{color_for_agent_type, Warping};
 use crate::debug::path_counter::PathCounter;
-use crate::edit::{EditMode, RouteEditor};
+use crate::edit::{EditMode, RouteEditor, StopEditor};
 use crate::layer::PANEL_PLACEMENT;
 use crate::sandbox::{dashboards, GameplayMode, SandboxMode, TimeWarpScreen};
 
@@ -607,6 +607,22 @@ impl InfoPanel {
                             )),
                         ])),
                     )
+                } else if action == "edit boarding at this stop" {
+                    if let Tab::TransitStop(id) = self.tab {
+                        (
+                            false,
+                            Some(Transition::Multi(vec![
+                                Transition::Push(EditMode::new_state(
+                                    ctx,
+                                    app,
+                                    ctx_actions.gameplay_mode(),
+                                )),
+                                Transition::Push(StopEditor::new_state(ctx, app, id)),
+                            ])),
+                        )
+                    } else {
+                        unreachable!()
+                    }
                 } else if action == "Explore demand across all traffic signals" {
                     (
                         false,
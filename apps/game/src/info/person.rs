@@ -147,8 +147,10 @@ fn trips_body(
                     ctx.prerender,
                     match trip.mode {
                         TripMode::Walk => "system/assets/meters/pedestrian.svg",
-                        TripMode::Bike => "system/assets/meters/bike.svg",
-                        TripMode::Drive => "system/assets/meters/car.svg",
+                        TripMode::Bike | TripMode::Micromobility => "system/assets/meters/bike.svg",
+                        TripMode::Drive | TripMode::Ridehail | TripMode::ParkAndRide => {
+                            "system/assets/meters/car.svg"
+                        }
                         TripMode::Transit => "system/assets/meters/bus.svg",
                     },
                 )
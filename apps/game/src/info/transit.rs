@@ -25,6 +25,13 @@ fn stop_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: TransitSt
     let sim = &app.primary.sim;
 
     rows.push(Line(&ts.name).into_widget(ctx));
+    if ts.closed {
+        rows.push(
+            Line("Closed. Vehicles pass by without stopping.")
+                .fg(Color::RED)
+                .into_widget(ctx),
+        );
+    }
 
     let all_arrivals = &sim.get_analytics().bus_arrivals;
     for r in app.primary.map.get_routes_serving_stop(id) {
@@ -94,6 +101,26 @@ fn stop_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: TransitSt
     }
     rows.push(txt.into_widget(ctx));
 
+    let mut features = Vec::new();
+    if ts.boarding.all_door_boarding {
+        features.push("all-door boarding");
+    }
+    if ts.boarding.off_board_fares {
+        features.push("off-board fares");
+    }
+    if ts.boarding.level_boarding {
+        features.push("level boarding");
+    }
+    if !features.is_empty() {
+        rows.push(format!("Has {}", features.join(", ")).text_widget(ctx));
+    }
+    rows.push(
+        ctx.style()
+            .btn_outline
+            .text("Edit stop")
+            .build_widget(ctx, "edit boarding at this stop"),
+    );
+
     // Draw where the bus/train stops
     details.draw_extra.zoomed.push(
         app.cs.bus_body.alpha(0.5),
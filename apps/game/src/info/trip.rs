@@ -5,7 +5,10 @@ use maplit::btreemap;
 use crate::ID;
 use geom::{Distance, Duration, Percent, Polygon, Pt2D, UnitFmt};
 use map_model::{Map, Path, PathStep, Traversable};
-use sim::{AgentID, Analytics, PersonID, Problem, TripID, TripInfo, TripPhase, TripPhaseType};
+use sim::{
+    AgentID, Analytics, EnergyModel, PersonID, Problem, TripEnergy, TripID, TripInfo, TripPhase,
+    TripPhaseType,
+};
 use synthpop::{TripEndpoint, TripMode};
 use widgetry::{
     Color, ControlState, DrawWithTooltips, EventCtx, GeomBatch, Line, LinePlot, PlotOptions,
@@ -333,17 +336,19 @@ pub fn finished(
             Line(trip.purpose.to_string()).secondary().into_widget(ctx),
         ]));
     }
-    col.push(describe_problems(
-        ctx,
-        if open_trips[&id].show_after {
-            app.primary.sim.get_analytics()
-        } else {
-            app.prebaked()
-        },
-        id,
-        &trip,
-        col_width,
-    ));
+    let analytics = if open_trips[&id].show_after {
+        app.primary.sim.get_analytics()
+    } else {
+        app.prebaked()
+    };
+    if let Some(exertion) = analytics.trip_exertion.get(&id) {
+        col.push(Widget::custom_row(vec![
+            Widget::custom_row(vec![Line("Energy").secondary().into_widget(ctx)])
+                .force_width_window_pct(ctx, col_width),
+            describe_energy(&EnergyModel::default_model().trip_energy(exertion)).text_widget(ctx),
+        ]));
+    }
+    col.push(describe_problems(ctx, analytics, id, &trip, col_width));
 
     col.push(make_trip_details(
         ctx,
@@ -390,6 +395,24 @@ pub fn cancelled(
     Widget::col(col)
 }
 
+fn describe_energy(energy: &TripEnergy) -> String {
+    let mut parts = Vec::new();
+    if energy.vehicle_kwh > 0.0 {
+        parts.push(format!(
+            "{:.1} kWh of fuel ({:.2} L)",
+            energy.vehicle_kwh,
+            energy.fuel_liters()
+        ));
+    }
+    if energy.kcal() > 0.0 {
+        parts.push(format!("{} kcal burned", energy.kcal().round()));
+    }
+    if parts.is_empty() {
+        return "None".to_string();
+    }
+    parts.join(", ")
+}
+
 fn describe_problems(
     ctx: &mut EventCtx,
     analytics: &Analytics,
@@ -400,6 +423,7 @@ fn describe_problems(
     match trip.mode {
         TripMode::Walk => {
             let mut arterial_intersection_crossings = 0;
+            let mut slip_lane_crossings = 0;
             let mut overcrowding = 0;
             let empty = Vec::new();
             for (_, problem) in analytics.problems_per_trip.get(&id).unwrap_or(&empty) {
@@ -407,6 +431,9 @@ fn describe_problems(
                     Problem::ArterialIntersectionCrossing(_) => {
                         arterial_intersection_crossings += 1;
                     }
+                    Problem::SlipLaneCrossing(_) => {
+                        slip_lane_crossings += 1;
+                    }
                     Problem::PedestrianOvercrowding(_) => {
                         overcrowding += 1;
                     }
@@ -423,6 +450,7 @@ fn describe_problems(
                 }
                 .secondary(),
             ]);
+            txt.add_line(Line(format!("{slip_lane_crossings} slip lanes crossed")).secondary());
             txt.add_line(Line(format!("{overcrowding} overcrowded sidewalks crossed")).secondary());
 
             Widget::custom_row(vec![
@@ -437,6 +465,7 @@ fn describe_problems(
         TripMode::Bike => {
             let mut count_complex_intersections = 0;
             let mut count_overtakes = 0;
+            let mut count_hooks = 0;
             let empty = Vec::new();
             for (_, problem) in analytics.problems_per_trip.get(&id).unwrap_or(&empty) {
                 match problem {
@@ -446,6 +475,9 @@ fn describe_problems(
                     Problem::OvertakeDesired(_) => {
                         count_overtakes += 1;
                     }
+                    Problem::HookConflict(_) => {
+                        count_hooks += 1;
+                    }
                     _ => {}
                 }
             }
@@ -468,6 +500,15 @@ fn describe_problems(
                 }
                 .secondary(),
             ]);
+            txt.add_appended(vec![
+                Line(count_hooks.to_string()),
+                if count_hooks == 1 {
+                    Line(" vehicle turned across their path")
+                } else {
+                    Line(" vehicles turned across their path")
+                }
+                .secondary(),
+            ]);
 
             Widget::custom_row(vec![
                 Line("Risk Exposure")
@@ -597,6 +638,29 @@ fn draw_problems(
                     (id, *time)
                 ));
             }
+            Problem::SlipLaneCrossing(t) => {
+                let t = map.get_t(*t);
+
+                let geom = t.geom.make_polygons(Distance::meters(10.0));
+                details.draw_extra.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(geom.center())
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.draw_extra.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(geom.center()),
+                );
+                details.tooltips.push((
+                    geom,
+                    Text::from(
+                        "Drivers turning through a slip lane often don't look for pedestrians.",
+                    ),
+                    (id, *time),
+                ));
+            }
             Problem::PedestrianOvercrowding(on) => {
                 let pt = on.get_polyline(map).middle();
                 details.draw_extra.unzoomed.append(
@@ -619,6 +683,30 @@ fn draw_problems(
                     (id, *time),
                 ));
             }
+            Problem::HookConflict(t) => {
+                let t = map.get_t(*t);
+
+                let geom = t.geom.make_polygons(Distance::meters(10.0));
+                details.draw_extra.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(geom.center())
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.draw_extra.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(geom.center()),
+                );
+                details.tooltips.push((
+                    geom,
+                    Text::from_multiline(vec![
+                        Line("A vehicle turned across this cyclist's path, just before or after them"),
+                        Line("Protected corners separate turning vehicles from cyclists"),
+                    ]),
+                    (id, *time),
+                ));
+            }
         }
     }
 }
@@ -714,14 +802,22 @@ fn make_timeline(
             GeomBatch::load_svg(
                 ctx.prerender,
                 match p.phase_type {
-                    TripPhaseType::Driving => "system/assets/timeline/driving.svg",
+                    TripPhaseType::Driving | TripPhaseType::RidingRidehail(_) => {
+                        "system/assets/timeline/driving.svg"
+                    }
                     TripPhaseType::Walking => "system/assets/timeline/walking.svg",
                     TripPhaseType::Biking => "system/assets/timeline/biking.svg",
-                    TripPhaseType::Parking => "system/assets/timeline/parking.svg",
-                    TripPhaseType::WaitingForBus(_, _) => {
+                    TripPhaseType::Parking | TripPhaseType::Unloading => {
+                        "system/assets/timeline/parking.svg"
+                    }
+                    TripPhaseType::WaitingForBus(_, _)
+                    | TripPhaseType::WaitingForFerry(_, _)
+                    | TripPhaseType::WaitingForRidehail => {
                         "system/assets/timeline/waiting_for_bus.svg"
                     }
-                    TripPhaseType::RidingBus(_, _, _) => "system/assets/timeline/riding_bus.svg",
+                    TripPhaseType::RidingBus(_, _, _) | TripPhaseType::RidingFerry(_, _) => {
+                        "system/assets/timeline/riding_bus.svg"
+                    }
                     TripPhaseType::Cancelled | TripPhaseType::Finished => unreachable!(),
                     TripPhaseType::DelayedStart => "system/assets/timeline/delayed_start.svg",
                 },
@@ -0,0 +1,123 @@
+use map_gui::tools::ColorNetwork;
+use sim::{EmissionsModel, ExposureSummary};
+use widgetry::mapspace::ToggleZoomed;
+use widgetry::tools::ColorLegend;
+use widgetry::{EventCtx, GfxCtx, Line, Panel, Text, Widget};
+
+use crate::app::App;
+use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
+
+/// Estimated exposure to traffic pollution per building, compared with the baseline before the
+/// proposal if it's available.
+pub struct AirQuality {
+    hour: usize,
+    draw: ToggleZoomed,
+    panel: Panel,
+}
+
+impl Layer for AirQuality {
+    fn name(&self) -> Option<&'static str> {
+        Some("air quality")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        // Throughput is only counted per hour, so don't bother recalculating more often
+        if app.primary.sim.time().get_hours() != self.hour {
+            *self = AirQuality::new(ctx, app);
+        }
+        <dyn Layer>::simple_event(ctx, &mut self.panel)
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        self.draw.draw(g);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw.unzoomed);
+    }
+}
+
+impl AirQuality {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> AirQuality {
+        let map = &app.primary.map;
+        let now = app.primary.sim.time();
+        let model = EmissionsModel::default_model();
+
+        let exposure = model.building_exposure(
+            map,
+            &model.road_emissions(map, app.primary.sim.get_analytics(), now),
+        );
+        let after = ExposureSummary::new(map, &exposure);
+
+        let mut colorer = ColorNetwork::new(app);
+        let max = exposure.values().cloned().fold(0.0, f64::max);
+        if max > 0.0 {
+            for (b, value) in &exposure {
+                colorer.add_b(*b, app.cs.good_to_bad_red.eval(value / max));
+            }
+        }
+
+        let mut txt = Text::new();
+        txt.add_line(format!(
+            "Average resident exposure: {:.1}",
+            after.mean_per_resident
+        ));
+        txt.add_line(format!(
+            "Average at {} schools: {:.1}",
+            after.num_schools, after.mean_at_schools
+        ));
+        if app.has_prebaked().is_some() {
+            let before = ExposureSummary::new(
+                map,
+                &model.building_exposure(map, &model.road_emissions(map, app.prebaked(), now)),
+            );
+            txt.add_line(Line("Before the proposal").secondary());
+            txt.add_line(format!(
+                "Average resident exposure: {:.1} ({})",
+                before.mean_per_resident,
+                pct_change(before.mean_per_resident, after.mean_per_resident)
+            ));
+            txt.add_line(format!(
+                "Average at schools: {:.1} ({})",
+                before.mean_at_schools,
+                pct_change(before.mean_at_schools, after.mean_at_schools)
+            ));
+        }
+
+        let panel = Panel::new_builder(Widget::col(vec![
+            header(ctx, "Air quality"),
+            Text::from(
+                Line(
+                    "A rough index of traffic pollution reaching each building since midnight, \
+                     for comparing proposals",
+                )
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .into_widget(ctx),
+            txt.into_widget(ctx),
+            ColorLegend::gradient(ctx, &app.cs.good_to_bad_red, vec!["lowest", "highest"]),
+        ]))
+        .aligned_pair(PANEL_PLACEMENT)
+        .build(ctx);
+
+        AirQuality {
+            hour: now.get_hours(),
+            draw: colorer.build(ctx),
+            panel,
+        }
+    }
+}
+
+/// How the proposal changed things, relative to before
+fn pct_change(before: f64, after: f64) -> String {
+    if before == 0.0 {
+        return "no change".to_string();
+    }
+    let pct = 100.0 * (after - before) / before;
+    if pct.abs() < 0.05 {
+        "no change".to_string()
+    } else if pct > 0.0 {
+        format!("now {:.1}% worse", pct)
+    } else {
+        format!("now {:.1}% better", -pct)
+    }
+}
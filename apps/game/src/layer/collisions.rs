@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+
+use abstutil::prettyprint_usize;
+use collisions::{Collision, CollisionDataset, Mode, Severity};
+use geom::{Distance, FindClosest};
+use map_gui::tools::ColorNetwork;
+use map_model::{IntersectionID, Map, RoadID};
+use widgetry::mapspace::ToggleZoomed;
+use widgetry::tools::ColorLegend;
+use widgetry::{EventCtx, GfxCtx, Line, Panel, Text, Widget};
+
+use crate::app::App;
+use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
+use crate::ID;
+
+// Collisions further than this from any road or intersection are ignored
+const SNAP_DISTANCE: Distance = Distance::const_meters(10.0);
+
+/// Real-world collisions imported for the city, counted per road and intersection, so hotspots
+/// can be compared with proposed edits.
+pub struct Collisions {
+    per_road: BTreeMap<RoadID, CollisionStats>,
+    per_intersection: BTreeMap<IntersectionID, CollisionStats>,
+    draw: ToggleZoomed,
+    panel: Panel,
+    tooltip: Option<Text>,
+}
+
+impl Layer for Collisions {
+    fn name(&self) -> Option<&'static str> {
+        Some("collisions")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        if ctx.redo_mouseover() {
+            self.tooltip = match app.mouseover_unzoomed_roads_and_intersections(ctx) {
+                Some(ID::Road(r)) => self.per_road.get(&r).map(|stats| stats.describe()),
+                Some(ID::Intersection(i)) => {
+                    self.per_intersection.get(&i).map(|stats| stats.describe())
+                }
+                _ => None,
+            };
+        }
+        <dyn Layer>::simple_event(ctx, &mut self.panel)
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        self.draw.draw(g);
+        if let Some(ref txt) = self.tooltip {
+            g.draw_mouse_tooltip(txt.clone());
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw.unzoomed);
+    }
+}
+
+impl Collisions {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Collisions {
+        let map = &app.primary.map;
+        let path = map.get_city_name().input_path("collisions.bin");
+        let maybe_data = ctx.loading_screen("load collision data", |_, timer| {
+            abstio::maybe_read_binary::<CollisionDataset>(path.clone(), timer)
+        });
+
+        let mut per_road: BTreeMap<RoadID, CollisionStats> = BTreeMap::new();
+        let mut per_intersection: BTreeMap<IntersectionID, CollisionStats> = BTreeMap::new();
+        let mut total = CollisionStats::default();
+        let mut unsnapped = 0;
+        let mut txt = Text::new();
+        match maybe_data {
+            Ok(data) => {
+                for (id, collision) in snap_collisions(map, data.collisions.iter()) {
+                    match id {
+                        Some(ID::Road(r)) => per_road.entry(r).or_default().add(collision),
+                        Some(ID::Intersection(i)) => {
+                            per_intersection.entry(i).or_default().add(collision)
+                        }
+                        _ => {
+                            unsnapped += 1;
+                            continue;
+                        }
+                    }
+                    total.add(collision);
+                }
+                if !data.source_url.is_empty() {
+                    txt.add_line(Line(format!("Source: {}", data.source_url)).secondary());
+                }
+            }
+            Err(_) => {
+                txt.add_line(format!("No collision data imported for {}", path));
+                txt.add_line(
+                    Line("Import a CSV file with: cli import-collisions --input=... --map=...")
+                        .secondary(),
+                );
+            }
+        }
+
+        let mut colorer = ColorNetwork::new(app);
+        let max = per_road
+            .values()
+            .chain(per_intersection.values())
+            .map(|stats| stats.total())
+            .max()
+            .unwrap_or(0);
+        if max > 0 {
+            let scale = |stats: &CollisionStats| (stats.total() as f64) / (max as f64);
+            for (r, stats) in &per_road {
+                colorer.add_r(*r, app.cs.good_to_bad_red.eval(scale(stats)));
+            }
+            for (i, stats) in &per_intersection {
+                colorer.add_i(*i, app.cs.good_to_bad_red.eval(scale(stats)));
+            }
+        }
+
+        if total.total() > 0 {
+            txt.add_appended(vec![
+                Line(prettyprint_usize(total.total())),
+                Line(" collisions near roads"),
+            ]);
+            txt.add_line(format!(
+                "{} fatal, {} serious, {} slight",
+                prettyprint_usize(total.fatal),
+                prettyprint_usize(total.serious),
+                prettyprint_usize(total.slight)
+            ));
+            txt.add_line(format!(
+                "{} involved pedestrians, {} involved cyclists",
+                prettyprint_usize(total.pedestrians),
+                prettyprint_usize(total.cyclists)
+            ));
+            if unsnapped > 0 {
+                txt.add_line(
+                    Line(format!(
+                        "{} too far from any road",
+                        prettyprint_usize(unsnapped)
+                    ))
+                    .secondary(),
+                );
+            }
+
+            let (_, edited_roads) = map.get_edits().changed_lanes(map);
+            let mut on_edited = CollisionStats::default();
+            for r in &edited_roads {
+                if let Some(stats) = per_road.get(r) {
+                    on_edited.merge(stats);
+                }
+            }
+            if !edited_roads.is_empty() {
+                txt.add_line(format!(
+                    "{} collisions ({} fatal or serious) happened on the {} edited roads",
+                    prettyprint_usize(on_edited.total()),
+                    prettyprint_usize(on_edited.fatal + on_edited.serious),
+                    prettyprint_usize(edited_roads.len())
+                ));
+            }
+
+            txt.add_line(Line("Worst roads").small_heading());
+            let mut ranked: Vec<(&RoadID, &CollisionStats)> = per_road.iter().collect();
+            ranked.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total()));
+            for (r, stats) in ranked.into_iter().take(5) {
+                txt.add_line(format!(
+                    "{}: {}",
+                    map.get_r(*r).get_name(app.opts.language.as_ref()),
+                    prettyprint_usize(stats.total())
+                ));
+            }
+        }
+
+        let panel = Panel::new_builder(Widget::col(vec![
+            header(ctx, "Collisions"),
+            txt.into_widget(ctx),
+            ColorLegend::gradient(
+                ctx,
+                &app.cs.good_to_bad_red,
+                vec!["0".to_string(), prettyprint_usize(max)],
+            ),
+        ]))
+        .aligned_pair(PANEL_PLACEMENT)
+        .build(ctx);
+
+        Collisions {
+            per_road,
+            per_intersection,
+            draw: colorer.build(ctx),
+            panel,
+            tooltip: None,
+        }
+    }
+}
+
+/// Match each collision to the nearest road or intersection, if one's close enough
+pub fn snap_collisions<'a, I: Iterator<Item = &'a Collision>>(
+    map: &Map,
+    collisions: I,
+) -> Vec<(Option<ID>, &'a Collision)> {
+    let mut closest: FindClosest<ID> = FindClosest::new();
+    for i in map.all_intersections() {
+        closest.add_polygon(ID::Intersection(i.id), &i.polygon);
+    }
+    for r in map.all_roads() {
+        closest.add(ID::Road(r.id), r.center_pts.points());
+    }
+    collisions
+        .map(|c| {
+            let id = closest
+                .closest_pt(c.location.to_pt(map.get_gps_bounds()), SNAP_DISTANCE)
+                .map(|(id, _)| id);
+            (id, c)
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct CollisionStats {
+    slight: usize,
+    serious: usize,
+    fatal: usize,
+    pedestrians: usize,
+    cyclists: usize,
+}
+
+impl CollisionStats {
+    fn add(&mut self, c: &Collision) {
+        match c.severity {
+            Severity::Slight => self.slight += 1,
+            Severity::Serious => self.serious += 1,
+            Severity::Fatal => self.fatal += 1,
+        }
+        match c.mode {
+            Some(Mode::Pedestrian) => self.pedestrians += 1,
+            Some(Mode::Bike) => self.cyclists += 1,
+            Some(Mode::Vehicle) | None => {}
+        }
+    }
+
+    fn merge(&mut self, other: &CollisionStats) {
+        self.slight += other.slight;
+        self.serious += other.serious;
+        self.fatal += other.fatal;
+        self.pedestrians += other.pedestrians;
+        self.cyclists += other.cyclists;
+    }
+
+    fn total(&self) -> usize {
+        self.slight + self.serious + self.fatal
+    }
+
+    fn describe(&self) -> Text {
+        let mut txt = Text::from(format!("{} collisions", prettyprint_usize(self.total())));
+        txt.add_line(
+            Line(format!(
+                "{} fatal, {} serious, {} slight",
+                self.fatal, self.serious, self.slight
+            ))
+            .secondary(),
+        );
+        if self.pedestrians > 0 || self.cyclists > 0 {
+            txt.add_line(
+                Line(format!(
+                    "{} involved pedestrians, {} involved cyclists",
+                    self.pedestrians, self.cyclists
+                ))
+                .secondary(),
+            );
+        }
+        txt
+    }
+}
@@ -0,0 +1,196 @@
+use geom::{Distance, Duration, Time};
+use map_model::LaneID;
+use widgetry::tools::ColorLegend;
+use widgetry::{
+    Drawable, EventCtx, GeomBatch, GfxCtx, Line, Outcome, Panel, Text, TextExt, Widget,
+};
+
+use crate::app::App;
+use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
+use crate::ID;
+
+// Speeds are averaged over stretches of lane this long
+const BIN_LENGTH: Distance = Distance::const_meters(10.0);
+// A lane is slow where vehicles average less than this fraction of the speed limit
+const SLOW_FRACTION: f64 = 0.5;
+
+/// Vehicle speeds along every lane, in short stretches, to see exactly where traffic starts to
+/// slow down.
+pub struct LaneSpeeds {
+    time: Time,
+    draw: Drawable,
+    panel: Panel,
+    tooltip: Option<Text>,
+}
+
+impl Layer for LaneSpeeds {
+    fn name(&self) -> Option<&'static str> {
+        Some("lane speeds")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        let now = app.primary.sim.time();
+        if now < self.time || now - self.time >= Duration::minutes(1) {
+            let mut new = LaneSpeeds::new(ctx, app);
+            new.panel.restore(ctx, &self.panel);
+            *self = new;
+        }
+
+        if ctx.redo_mouseover() {
+            self.tooltip = None;
+            if let (Some(ID::Road(r)), Some(speeds)) = (
+                app.mouseover_unzoomed_roads_and_intersections(ctx),
+                app.primary.sim.get_lane_speeds(),
+            ) {
+                let map = &app.primary.map;
+                let mut txt = Text::new();
+                for l in &map.get_r(r).lanes {
+                    if let Some((start, end)) = speeds.find_slowdown(l.id, map, SLOW_FRACTION) {
+                        txt.add_line(describe_slowdown(app, l.id, start, end));
+                    }
+                }
+                if !txt.is_empty() {
+                    self.tooltip = Some(txt);
+                }
+            }
+        }
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                "stop recording" => {
+                    app.primary.sim.stop_recording_lane_speeds();
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        g.redraw(&self.draw);
+        if let Some(ref txt) = self.tooltip {
+            g.draw_mouse_tooltip(txt.clone());
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw);
+    }
+}
+
+impl LaneSpeeds {
+    /// Starts recording speeds, if the sim isn't already
+    pub fn new(ctx: &mut EventCtx, app: &mut App) -> LaneSpeeds {
+        app.primary.sim.record_lane_speeds(BIN_LENGTH);
+        let map = &app.primary.map;
+        let speeds = app.primary.sim.get_lane_speeds().unwrap();
+
+        let mut batch = GeomBatch::new();
+        let mut slowdowns = Vec::new();
+        for l in speeds.bins.keys() {
+            let lane = map.get_l(*l);
+            let limit = map.get_parent(*l).speed_limit.inner_meters_per_second();
+            for (idx, speed) in speeds.average_speeds(*l).into_iter().enumerate() {
+                let speed = match speed {
+                    Some(speed) => speed,
+                    None => {
+                        continue;
+                    }
+                };
+                let start = speeds.bin_length * (idx as f64);
+                let end = (start + speeds.bin_length).min(lane.length());
+                if let Ok(slice) = lane.lane_center_pts.maybe_exact_slice(start, end) {
+                    let ratio = (speed.inner_meters_per_second() / limit).min(1.0);
+                    batch.push(
+                        app.cs.good_to_bad_red.eval(1.0 - ratio),
+                        slice.make_polygons(lane.width),
+                    );
+                }
+            }
+            if let Some((start, end)) = speeds.find_slowdown(*l, map, SLOW_FRACTION) {
+                slowdowns.push((end - start, *l, start, end));
+            }
+        }
+        slowdowns.sort_by_key(|(len, _, _, _)| std::cmp::Reverse(*len));
+
+        let mut col = vec![
+            header(ctx, "Lane speeds"),
+            Text::from(
+                Line(format!(
+                    "Average vehicle speeds along each lane, since {}",
+                    speeds.since.ampm_tostring()
+                ))
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .into_widget(ctx),
+            ColorLegend::gradient(ctx, &app.cs.good_to_bad_red, vec!["speed limit", "stopped"]),
+        ];
+        if slowdowns.is_empty() {
+            col.push("No slowdowns yet".text_widget(ctx));
+        } else {
+            let mut txt = Text::from(Line("Longest slowdowns").small_heading());
+            for (_, l, start, end) in slowdowns.into_iter().take(5) {
+                txt.add_line(format!(
+                    "{}: {}",
+                    map.get_parent(l).get_name(app.opts.language.as_ref()),
+                    describe_slowdown(app, l, start, end)
+                ));
+            }
+            col.push(txt.wrap_to_pct(ctx, 20).into_widget(ctx));
+        }
+        col.push(
+            ctx.style()
+                .btn_outline
+                .text("stop recording")
+                .build_def(ctx),
+        );
+
+        LaneSpeeds {
+            time: app.primary.sim.time(),
+            draw: ctx.upload(batch),
+            panel: Panel::new_builder(Widget::col(col))
+                .aligned_pair(PANEL_PLACEMENT)
+                .build(ctx),
+            tooltip: None,
+        }
+    }
+}
+
+/// Guesses what causes a slowdown from where it is along the lane
+fn describe_slowdown(app: &App, l: LaneID, start: Distance, end: Distance) -> String {
+    let map = &app.primary.map;
+    let lane = map.get_l(l);
+    let length = (end - start).to_string(&app.opts.units);
+    if end + BIN_LENGTH < lane.length() {
+        return format!(
+            "slow for {} in the middle of the lane, starting {} from the start",
+            length,
+            start.to_string(&app.opts.units)
+        );
+    }
+    let i = map.get_i(lane.dst_i);
+    let cause = if i.is_traffic_signal() {
+        "the traffic signal"
+    } else if i.is_stop_sign() {
+        "the stop sign"
+    } else if i.turns.iter().any(|t| t.turn_type.pedestrian_crossing()) {
+        "a pedestrian crossing"
+    } else if i
+        .outgoing_lanes
+        .iter()
+        .filter(|l| map.get_l(**l).is_driving())
+        .count()
+        < i.incoming_lanes
+            .iter()
+            .filter(|l| map.get_l(**l).is_driving())
+            .count()
+    {
+        "a merge"
+    } else {
+        "the intersection"
+    };
+    format!("queued for {} back from {}", length, cause)
+}
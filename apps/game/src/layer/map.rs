@@ -4,7 +4,7 @@ use crate::ID;
 use abstutil::{prettyprint_usize, Counter};
 use geom::{Distance, Time};
 use map_gui::tools::{ColorDiscrete, ColorNetwork};
-use map_model::{AmenityType, Direction, LaneType};
+use map_model::{AmenityType, Direction, LaneType, TrafficStress};
 use sim::AgentType;
 use widgetry::mapspace::ToggleZoomed;
 use widgetry::tools::ColorLegend;
@@ -347,4 +347,52 @@ impl Static {
             .into_widget(ctx),
         )
     }
+
+    pub fn traffic_stress(ctx: &mut EventCtx, app: &App) -> Static {
+        let colors = [
+            Color::hex("#1A9641"),
+            Color::hex("#A6D96A"),
+            Color::hex("#FDAE61"),
+            Color::hex("#D7191C"),
+        ];
+        let mut colorer = ColorDiscrete::new(
+            app,
+            TrafficStress::all()
+                .into_iter()
+                .zip(colors)
+                .map(|(stress, color)| (format!("LTS {}", stress.level()), color))
+                .collect(),
+        );
+        let mut lengths = Counter::new();
+        for r in app.primary.map.all_roads() {
+            // Show the worse direction
+            if let Some(stress) = [Direction::Fwd, Direction::Back]
+                .into_iter()
+                .filter_map(|dir| r.traffic_stress_for_bikes(&app.primary.map, dir))
+                .max()
+            {
+                colorer.add_r(r.id, format!("LTS {}", stress.level()));
+                lengths.add(stress, r.length().inner_meters() as usize);
+            }
+        }
+
+        let total = lengths.sum().max(1);
+        let mut txt = Text::new();
+        for stress in TrafficStress::all() {
+            txt.add_line(format!(
+                "LTS {} ({}): {}% of roads",
+                stress.level(),
+                stress.describe(),
+                100 * lengths.get(stress) / total
+            ));
+        }
+
+        Static::new(
+            ctx,
+            colorer,
+            "traffic stress",
+            "Level of traffic stress for cycling".to_string(),
+            txt.into_widget(ctx),
+        )
+    }
 }
@@ -1,3 +1,4 @@
+use geom::Duration;
 use map_gui::tools::{grey_out_map, HeatmapOptions};
 use sim::AgentType;
 use widgetry::{
@@ -8,14 +9,20 @@ use widgetry::{
 use crate::app::{App, Transition};
 use crate::sandbox::dashboards;
 
+mod air_quality;
+pub mod collisions;
 pub mod elevation;
 pub mod favorites;
+mod lane_speeds;
 pub mod map;
+mod noise;
 mod pandemic;
 mod parking;
 mod population;
 mod problems;
 mod problems_diff;
+mod screenlines;
+mod shade;
 pub mod traffic;
 pub mod transit;
 
@@ -116,6 +123,7 @@ impl PickLayer {
                     btn("map edits", Key::E),
                     btn("parking occupancy", Key::P),
                     btn("transit network", Key::U),
+                    btn("transit coverage", Key::W),
                     btn("population map", Key::X),
                     btn("no sidewalks", Key::S),
                     btn("favorite buildings", Key::F),
@@ -136,6 +144,13 @@ impl PickLayer {
                     btn("blackholes", Key::L),
                     btn("problem map", Key::K),
                     btn("high stress", Key::H),
+                    btn("traffic stress", Key::Num2),
+                    btn("air quality", Key::Q),
+                    btn("road noise", Key::Num1),
+                    btn("collisions", Key::Num3),
+                    btn("screenlines", Key::Num4),
+                    btn("lane speeds", Key::Num5),
+                    btn("shade", Key::I),
                     if app.primary.sim.get_pandemic_model().is_some() {
                         btn("pandemic model", Key::Y)
                     } else {
@@ -157,111 +172,194 @@ impl PickLayer {
                 .build(ctx),
         })
     }
+
+    /// The name of every layer `activate` understands
+    pub fn names(app: &App) -> Vec<&'static str> {
+        let mut names = vec![
+            "None",
+            "delay",
+            "throughput",
+            "traffic jams",
+            "cycling activity",
+            "pedestrian crowding",
+            "map edits",
+            "parking occupancy",
+            "transit network",
+            "transit coverage",
+            "population map",
+            "no sidewalks",
+            "favorite buildings",
+            "amenities",
+            "backpressure",
+            "steep streets",
+            "elevation",
+            "parking efficiency",
+            "blackholes",
+            "problem map",
+            "high stress",
+            "traffic stress",
+            "air quality",
+            "road noise",
+            "collisions",
+            "screenlines",
+            "lane speeds",
+            "shade",
+            "traffic signal demand",
+            "commuter patterns",
+        ];
+        if app.primary.sim.get_pandemic_model().is_some() {
+            names.push("pandemic model");
+        }
+        names
+    }
+
+    /// Switches to the layer with this name. A few of these are really dashboards; they're
+    /// returned instead, for the caller to open.
+    pub fn activate(ctx: &mut EventCtx, app: &mut App, name: &str) -> Option<Box<dyn State<App>>> {
+        match name {
+            "None" => {
+                app.primary.layer = None;
+            }
+            "amenities" => {
+                app.primary.layer = Some(Box::new(map::Static::amenities(ctx, app)));
+            }
+            "backpressure" => {
+                app.primary.layer = Some(Box::new(traffic::Backpressure::new(ctx, app)));
+            }
+            "cycling activity" => {
+                app.primary.layer = Some(Box::new(map::BikeActivity::new(ctx, app)));
+            }
+            "delay" => {
+                app.primary.layer = Some(Box::new(traffic::Delay::new(ctx, app)));
+            }
+            "pedestrian crowding" => {
+                app.primary.layer = Some(Box::new(traffic::PedestrianCrowding::new(ctx, app)));
+            }
+            "steep streets" => {
+                app.primary.layer = Some(Box::new(elevation::SteepStreets::new(ctx, app)));
+            }
+            "elevation" => {
+                app.primary.layer = Some(Box::new(elevation::ElevationContours::new(ctx, app)));
+            }
+            "map edits" => {
+                app.primary.layer = Some(Box::new(map::Static::edits(ctx, app)));
+            }
+            "no sidewalks" => {
+                app.primary.layer = Some(Box::new(map::Static::no_sidewalks(ctx, app)));
+            }
+            "high stress" => {
+                app.primary.layer = Some(Box::new(map::Static::high_stress(ctx, app)));
+            }
+            "traffic stress" => {
+                app.primary.layer = Some(Box::new(map::Static::traffic_stress(ctx, app)));
+            }
+            "air quality" => {
+                app.primary.layer = Some(Box::new(air_quality::AirQuality::new(ctx, app)));
+            }
+            "road noise" => {
+                app.primary.layer = Some(Box::new(noise::Noise::new(ctx, app)));
+            }
+            "collisions" => {
+                app.primary.layer = Some(Box::new(collisions::Collisions::new(ctx, app)));
+            }
+            "screenlines" => {
+                app.primary.layer = Some(Box::new(screenlines::Screenlines::new(ctx, app)));
+            }
+            "lane speeds" => {
+                app.primary.layer = Some(Box::new(lane_speeds::LaneSpeeds::new(ctx, app)));
+            }
+            "shade" => {
+                app.primary.layer = Some(Box::new(shade::Shade::new(
+                    ctx,
+                    app,
+                    map_model::SUMMER_DAY_OF_YEAR,
+                )));
+            }
+            "favorite buildings" => {
+                app.primary.layer = Some(Box::new(favorites::ShowFavorites::new(ctx, app)));
+            }
+            "pandemic model" => {
+                app.primary.layer = Some(Box::new(pandemic::Pandemic::new(
+                    ctx,
+                    app,
+                    pandemic::Options {
+                        heatmap: Some(HeatmapOptions::new()),
+                        state: pandemic::Seir::Infected,
+                    },
+                )));
+            }
+            "blackholes" => {
+                app.primary.layer = Some(Box::new(map::Static::blackholes(ctx, app)));
+            }
+            "parking occupancy" => {
+                app.primary.layer = Some(Box::new(parking::Occupancy::new(
+                    ctx, app, true, true, true, false, true,
+                )));
+            }
+            "parking efficiency" => {
+                app.primary.layer = Some(Box::new(parking::Efficiency::new(ctx, app)));
+            }
+            "population map" => {
+                app.primary.layer = Some(Box::new(population::PopulationMap::new(
+                    ctx,
+                    app,
+                    population::Options {
+                        heatmap: Some(HeatmapOptions::new()),
+                    },
+                )));
+            }
+            "problem map" => {
+                app.primary.layer = Some(Box::new(problems::ProblemMap::new(
+                    ctx,
+                    app,
+                    problems::Options::new(app),
+                )));
+            }
+            "throughput" => {
+                app.primary.layer = Some(Box::new(traffic::Throughput::new(
+                    ctx,
+                    app,
+                    AgentType::all().into_iter().collect(),
+                )));
+            }
+            "traffic jams" => {
+                app.primary.layer = Some(Box::new(traffic::TrafficJams::new(ctx, app)));
+            }
+            "transit network" => {
+                app.primary.layer = Some(Box::new(transit::TransitNetwork::new(
+                    ctx, app, false, true, true,
+                )));
+            }
+            "transit coverage" => {
+                app.primary.layer = Some(Box::new(transit::TransitCoverage::new(
+                    ctx,
+                    app,
+                    Duration::minutes(10),
+                    Duration::minutes(15),
+                )));
+            }
+            "traffic signal demand" => {
+                return Some(dashboards::TrafficSignalDemand::new_state(ctx, app));
+            }
+            "commuter patterns" => {
+                return Some(dashboards::CommuterPatterns::new_state(ctx, app));
+            }
+            _ => unreachable!(),
+        }
+        None
+    }
 }
 
 impl State<App> for PickLayer {
     fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
         match self.panel.event(ctx) {
-            Outcome::Clicked(x) => match x.as_ref() {
-                "close" => {}
-                "None" => {
-                    app.primary.layer = None;
-                }
-                "amenities" => {
-                    app.primary.layer = Some(Box::new(map::Static::amenities(ctx, app)));
-                }
-                "backpressure" => {
-                    app.primary.layer = Some(Box::new(traffic::Backpressure::new(ctx, app)));
-                }
-                "cycling activity" => {
-                    app.primary.layer = Some(Box::new(map::BikeActivity::new(ctx, app)));
-                }
-                "delay" => {
-                    app.primary.layer = Some(Box::new(traffic::Delay::new(ctx, app)));
-                }
-                "pedestrian crowding" => {
-                    app.primary.layer = Some(Box::new(traffic::PedestrianCrowding::new(ctx, app)));
-                }
-                "steep streets" => {
-                    app.primary.layer = Some(Box::new(elevation::SteepStreets::new(ctx, app)));
-                }
-                "elevation" => {
-                    app.primary.layer = Some(Box::new(elevation::ElevationContours::new(ctx, app)));
-                }
-                "map edits" => {
-                    app.primary.layer = Some(Box::new(map::Static::edits(ctx, app)));
-                }
-                "no sidewalks" => {
-                    app.primary.layer = Some(Box::new(map::Static::no_sidewalks(ctx, app)));
+            Outcome::Clicked(x) => {
+                if x != "close" {
+                    if let Some(state) = PickLayer::activate(ctx, app, &x) {
+                        return Transition::Replace(state);
+                    }
                 }
-                "high stress" => {
-                    app.primary.layer = Some(Box::new(map::Static::high_stress(ctx, app)));
-                }
-                "favorite buildings" => {
-                    app.primary.layer = Some(Box::new(favorites::ShowFavorites::new(ctx, app)));
-                }
-                "pandemic model" => {
-                    app.primary.layer = Some(Box::new(pandemic::Pandemic::new(
-                        ctx,
-                        app,
-                        pandemic::Options {
-                            heatmap: Some(HeatmapOptions::new()),
-                            state: pandemic::Seir::Infected,
-                        },
-                    )));
-                }
-                "blackholes" => {
-                    app.primary.layer = Some(Box::new(map::Static::blackholes(ctx, app)));
-                }
-                "parking occupancy" => {
-                    app.primary.layer = Some(Box::new(parking::Occupancy::new(
-                        ctx, app, true, true, true, false, true,
-                    )));
-                }
-                "parking efficiency" => {
-                    app.primary.layer = Some(Box::new(parking::Efficiency::new(ctx, app)));
-                }
-                "population map" => {
-                    app.primary.layer = Some(Box::new(population::PopulationMap::new(
-                        ctx,
-                        app,
-                        population::Options {
-                            heatmap: Some(HeatmapOptions::new()),
-                        },
-                    )));
-                }
-                "problem map" => {
-                    app.primary.layer = Some(Box::new(problems::ProblemMap::new(
-                        ctx,
-                        app,
-                        problems::Options::new(app),
-                    )));
-                }
-                "throughput" => {
-                    app.primary.layer = Some(Box::new(traffic::Throughput::new(
-                        ctx,
-                        app,
-                        AgentType::all().into_iter().collect(),
-                    )));
-                }
-                "traffic jams" => {
-                    app.primary.layer = Some(Box::new(traffic::TrafficJams::new(ctx, app)));
-                }
-                "transit network" => {
-                    app.primary.layer = Some(Box::new(transit::TransitNetwork::new(
-                        ctx, app, false, true, true,
-                    )));
-                }
-                "traffic signal demand" => {
-                    return Transition::Replace(dashboards::TrafficSignalDemand::new_state(
-                        ctx, app,
-                    ));
-                }
-                "commuter patterns" => {
-                    return Transition::Replace(dashboards::CommuterPatterns::new_state(ctx, app));
-                }
-                _ => unreachable!(),
-            },
+            }
             _ => {
                 if self.panel.clicked_outside(ctx) {
                     return Transition::Pop;
@@ -0,0 +1,127 @@
+use abstutil::prettyprint_usize;
+use map_gui::tools::ColorNetwork;
+use sim::{NoiseModel, NoiseSummary};
+use widgetry::mapspace::ToggleZoomed;
+use widgetry::tools::ColorLegend;
+use widgetry::{EventCtx, GfxCtx, Line, Panel, Text, Widget};
+
+use crate::app::App;
+use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
+
+// The color scale goes from this level...
+const QUIET_DB: f64 = 45.0;
+// ... to this one
+const LOUD_DB: f64 = 75.0;
+
+/// Estimated road traffic noise along each road and at each building, compared with the baseline
+/// before the proposal if it's available.
+pub struct Noise {
+    hour: usize,
+    draw: ToggleZoomed,
+    panel: Panel,
+}
+
+impl Layer for Noise {
+    fn name(&self) -> Option<&'static str> {
+        Some("road noise")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        // Throughput is only counted per hour, so don't bother recalculating more often
+        if app.primary.sim.time().get_hours() != self.hour {
+            *self = Noise::new(ctx, app);
+        }
+        <dyn Layer>::simple_event(ctx, &mut self.panel)
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        self.draw.draw(g);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw.unzoomed);
+    }
+}
+
+impl Noise {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Noise {
+        let map = &app.primary.map;
+        let now = app.primary.sim.time();
+        let model = NoiseModel::default_model();
+
+        let emissions = model.road_emissions(map, app.primary.sim.get_analytics(), now);
+        let exposure = model.building_exposure(map, &emissions);
+        let after = NoiseSummary::new(map, &exposure);
+
+        let scale = |db: f64| ((db - QUIET_DB) / (LOUD_DB - QUIET_DB)).max(0.0).min(1.0);
+        let mut colorer = ColorNetwork::new(app);
+        for (r, emission) in &emissions {
+            colorer.add_r(
+                *r,
+                app.cs
+                    .good_to_bad_red
+                    .eval(scale(NoiseModel::level_beside_road(*emission))),
+            );
+        }
+        for (b, db) in &exposure {
+            colorer.add_b(*b, app.cs.good_to_bad_red.eval(scale(*db)));
+        }
+
+        let mut txt = Text::new();
+        describe(&mut txt, &after);
+        if app.has_prebaked().is_some() {
+            let before = NoiseSummary::new(
+                map,
+                &model.building_exposure(map, &model.road_emissions(map, app.prebaked(), now)),
+            );
+            txt.add_line(Line("Before the proposal").secondary());
+            describe(&mut txt, &before);
+            let change = after.mean_per_resident - before.mean_per_resident;
+            if change.abs() >= 0.05 {
+                txt.add_line(format!(
+                    "Homes are now {:.1} dB {} on average",
+                    change.abs(),
+                    if change > 0.0 { "louder" } else { "quieter" }
+                ));
+            }
+        }
+
+        let panel = Panel::new_builder(Widget::col(vec![
+            header(ctx, "Road noise"),
+            Text::from(
+                Line(
+                    "A rough estimate of traffic noise since midnight, from simulated volumes \
+                     and speeds, for comparing proposals",
+                )
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .into_widget(ctx),
+            txt.into_widget(ctx),
+            ColorLegend::gradient(
+                ctx,
+                &app.cs.good_to_bad_red,
+                vec![format!("{} dB", QUIET_DB), format!("{} dB+", LOUD_DB)],
+            ),
+        ]))
+        .aligned_pair(PANEL_PLACEMENT)
+        .build(ctx);
+
+        Noise {
+            hour: now.get_hours(),
+            draw: colorer.build(ctx),
+            panel,
+        }
+    }
+}
+
+fn describe(txt: &mut Text, summary: &NoiseSummary) {
+    txt.add_line(format!(
+        "Average at home: {:.1} dB",
+        summary.mean_per_resident
+    ));
+    txt.add_line(format!(
+        "{} of {} residents above 55 dB, {} above 65 dB",
+        prettyprint_usize(summary.residents_above_55),
+        prettyprint_usize(summary.residents),
+        prettyprint_usize(summary.residents_above_65)
+    ));
+}
@@ -264,7 +264,9 @@ fn export_raw_problems(app: &App) -> Result<String> {
                     Traversable::Lane(l) => map.get_r(l.road).orig_id.to_string(),
                     Traversable::Turn(t) => map.get_i(t.parent).orig_id.to_string(),
                 },
-                Problem::ArterialIntersectionCrossing(t) => map.get_i(t.parent).orig_id.to_string(),
+                Problem::ArterialIntersectionCrossing(t)
+                | Problem::SlipLaneCrossing(t)
+                | Problem::HookConflict(t) => map.get_i(t.parent).orig_id.to_string(),
             };
             writeln!(
                 out,
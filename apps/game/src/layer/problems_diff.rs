@@ -132,7 +132,9 @@ impl RelativeProblemMap {
                             }
                         }
                     }
-                    Problem::ArterialIntersectionCrossing(t) => {
+                    Problem::ArterialIntersectionCrossing(t)
+                    | Problem::SlipLaneCrossing(t)
+                    | Problem::HookConflict(t) => {
                         intersections.inc(t.parent);
                     }
                 }
@@ -0,0 +1,295 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use abstutil::prettyprint_usize;
+use geom::{Distance, Polygon, Time};
+use sim::{AgentType, Screenline, ScreenlineShape};
+use widgetry::tools::{Lasso, PolyLineLasso, PopupMsg};
+use widgetry::{
+    Color, DrawBaselayer, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Panel, State, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
+
+/// Count everybody crossing lines drawn across roads, or entering and leaving an area
+pub struct Screenlines {
+    time: Time,
+    num_screenlines: usize,
+    draw: Drawable,
+    panel: Panel,
+}
+
+impl Layer for Screenlines {
+    fn name(&self) -> Option<&'static str> {
+        Some("screenlines")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time
+            || app.primary.sim.get_screenlines().len() != self.num_screenlines
+        {
+            let mut new = Screenlines::new(ctx, app);
+            new.panel.restore(ctx, &self.panel);
+            *self = new;
+        }
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                "draw a screenline" => {
+                    return Some(LayerOutcome::Transition(Transition::Push(
+                        DrawScreenline::new_state(ctx, false),
+                    )));
+                }
+                "draw a cordon" => {
+                    return Some(LayerOutcome::Transition(Transition::Push(
+                        DrawScreenline::new_state(ctx, true),
+                    )));
+                }
+                "clear" => {
+                    app.primary.sim.clear_screenlines();
+                }
+                "Export to CSV" => {
+                    return Some(LayerOutcome::Transition(Transition::Push(
+                        match export_counts(app) {
+                            Ok(path) => PopupMsg::new_state(
+                                ctx,
+                                "Data exported",
+                                vec![format!("Data exported to {path}")],
+                            ),
+                            Err(err) => {
+                                PopupMsg::new_state(ctx, "Export failed", vec![err.to_string()])
+                            }
+                        },
+                    )));
+                }
+                _ => unreachable!(),
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        g.redraw(&self.draw);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw);
+    }
+}
+
+impl Screenlines {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Screenlines {
+        let screenlines = app.primary.sim.get_screenlines();
+
+        let mut batch = GeomBatch::new();
+        let mut col = vec![
+            header(ctx, "Screenlines"),
+            Text::from(
+                Line(
+                    "Count everybody crossing a line drawn across roads, or entering and leaving \
+                     an area, from when it's drawn",
+                )
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .into_widget(ctx),
+            Widget::row(vec![
+                ctx.style()
+                    .btn_outline
+                    .text("draw a screenline")
+                    .build_def(ctx),
+                ctx.style().btn_outline.text("draw a cordon").build_def(ctx),
+            ]),
+        ];
+
+        let mut txt = Text::new();
+        for s in screenlines {
+            let color = if matches!(s.shape, ScreenlineShape::Cordon(_)) {
+                Color::BLUE
+            } else {
+                Color::RED
+            };
+            batch.push(color.alpha(0.8), outline(s));
+
+            txt.add_line(Line(&s.name).small_heading());
+            txt.add_line(
+                Line(format!(
+                    "Across {} roads, since {}",
+                    s.roads.len(),
+                    s.since.ampm_tostring()
+                ))
+                .secondary(),
+            );
+            for first in [true, false] {
+                let total = s.total(first);
+                txt.add_line(format!(
+                    "{}: {} agents, {} people",
+                    s.direction_name(first),
+                    prettyprint_usize(total.agents),
+                    prettyprint_usize(total.people)
+                ));
+                for agent_type in AgentType::all() {
+                    let agents: usize = s
+                        .counts
+                        .iter()
+                        .filter(|((dir, t, _), _)| *dir == first && *t == agent_type)
+                        .map(|(_, count)| count.agents)
+                        .sum();
+                    if agents > 0 {
+                        txt.add_line(
+                            Line(format!(
+                                "  {}: {}",
+                                agent_type.plural_noun(),
+                                prettyprint_usize(agents)
+                            ))
+                            .secondary(),
+                        );
+                    }
+                }
+            }
+        }
+        if screenlines.is_empty() {
+            col.push("No screenlines yet".text_widget(ctx));
+        } else {
+            col.push(txt.into_widget(ctx));
+            col.push(Widget::row(vec![
+                ctx.style().btn_outline.text("Export to CSV").build_def(ctx),
+                ctx.style().btn_outline.text("clear").build_def(ctx),
+            ]));
+        }
+
+        Screenlines {
+            time: app.primary.sim.time(),
+            num_screenlines: screenlines.len(),
+            draw: ctx.upload(batch),
+            panel: Panel::new_builder(Widget::col(col))
+                .aligned_pair(PANEL_PLACEMENT)
+                .build(ctx),
+        }
+    }
+}
+
+fn outline(s: &Screenline) -> Polygon {
+    match s.shape {
+        ScreenlineShape::Line(ref pl) => pl.make_polygons(Distance::meters(5.0)),
+        ScreenlineShape::Cordon(ref polygon) => polygon.to_outline(Distance::meters(5.0)),
+    }
+}
+
+fn export_counts(app: &App) -> Result<String> {
+    let path = format!(
+        "screenlines_{}_{}.csv",
+        app.primary.map.get_name().as_filename(),
+        app.primary.sim.time().as_filename()
+    );
+    let mut out = String::new();
+    writeln!(out, "screenline,direction,hour,mode,agents,people")?;
+    for s in app.primary.sim.get_screenlines() {
+        for ((first, agent_type, hour), count) in &s.counts {
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                s.name,
+                s.direction_name(*first),
+                hour,
+                agent_type.noun(),
+                count.agents,
+                count.people
+            )?;
+        }
+    }
+    abstio::write_file(path, out)
+}
+
+enum Drawing {
+    Line(PolyLineLasso),
+    Cordon(Lasso),
+}
+
+struct DrawScreenline {
+    drawing: Drawing,
+    panel: Panel,
+}
+
+impl DrawScreenline {
+    fn new_state(ctx: &mut EventCtx, cordon: bool) -> Box<dyn State<App>> {
+        let (drawing, instructions) = if cordon {
+            (
+                Drawing::Cordon(Lasso::new(Distance::meters(1.0))),
+                "Click and drag to draw around an area",
+            )
+        } else {
+            (
+                Drawing::Line(PolyLineLasso::new()),
+                "Click and drag to draw a line across some roads",
+            )
+        };
+        Box::new(DrawScreenline {
+            drawing,
+            panel: Panel::new_builder(Widget::col(vec![
+                instructions.text_widget(ctx),
+                ctx.style()
+                    .btn_outline
+                    .text("cancel")
+                    .hotkey(Key::Escape)
+                    .build_def(ctx),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for DrawScreenline {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "cancel" => {
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let map = &app.primary.map;
+        let now = app.primary.sim.time();
+        let idx = app.primary.sim.get_screenlines().len() + 1;
+        let screenline = match self.drawing {
+            Drawing::Line(ref mut lasso) => lasso
+                .event(ctx)
+                .map(|pl| Screenline::along(format!("Screenline {}", idx), pl, map, now)),
+            Drawing::Cordon(ref mut lasso) => lasso
+                .event(ctx)
+                .map(|polygon| Screenline::cordon(format!("Cordon {}", idx), polygon, map, now)),
+        };
+        if let Some(screenline) = screenline {
+            if screenline.roads.is_empty() {
+                return Transition::Replace(PopupMsg::new_state(
+                    ctx,
+                    "Nothing to count",
+                    vec!["That doesn't cross any roads"],
+                ));
+            }
+            app.primary.sim.add_screenline(screenline);
+            return Transition::Pop;
+        }
+
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::PreviousState
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        match self.drawing {
+            Drawing::Line(ref lasso) => lasso.draw(g),
+            Drawing::Cordon(ref lasso) => lasso.draw(g),
+        }
+    }
+}
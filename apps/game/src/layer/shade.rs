@@ -0,0 +1,123 @@
+use geom::{Duration, Time};
+use map_gui::tools::ColorNetwork;
+use widgetry::mapspace::ToggleZoomed;
+use widgetry::tools::ColorLegend;
+use widgetry::{Color, EventCtx, GfxCtx, Line, Outcome, Panel, Spinner, Text, TextExt, Widget};
+
+use crate::app::App;
+use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
+
+// Shadows barely move over a few minutes
+const RECALCULATE_EVERY: Duration = Duration::const_seconds(15.0 * 60.0);
+
+/// How much of each sidewalk is shaded by buildings and street trees, at the current time of day.
+pub struct Shade {
+    time: Time,
+    day_of_year: usize,
+    draw: ToggleZoomed,
+    panel: Panel,
+}
+
+impl Layer for Shade {
+    fn name(&self) -> Option<&'static str> {
+        Some("shade")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        if time_bucket(app.primary.sim.time()) != time_bucket(self.time) {
+            *self = Shade::new(ctx, app, self.day_of_year);
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => {
+                *self = Shade::new(ctx, app, self.panel.spinner("day of year"));
+            }
+            _ => {}
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        self.draw.draw(g);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw.unzoomed);
+    }
+}
+
+impl Shade {
+    pub fn new(ctx: &mut EventCtx, app: &App, day_of_year: usize) -> Shade {
+        let map = &app.primary.map;
+        let now = app.primary.sim.time();
+        let sun = map.sun_position(day_of_year, now);
+
+        let mut colorer = ColorNetwork::new(app);
+        for shadow in map.shadows(&sun) {
+            colorer.draw.zoomed.push(Color::BLACK.alpha(0.3), shadow);
+        }
+        let shade = map.sidewalk_shade(&sun);
+        let mut total_length = 0.0;
+        let mut shaded_length = 0.0;
+        for (l, pct) in &shade {
+            let length = map.get_l(*l).length().inner_meters();
+            total_length += length;
+            shaded_length += pct * length;
+            // Sunny sidewalks are the uncomfortable ones
+            colorer.add_l(*l, app.cs.good_to_bad_red.eval(1.0 - pct));
+        }
+
+        let mut txt = Text::new();
+        if sun.is_up() {
+            txt.add_line(format!(
+                "Sun {:.0}° above the horizon at {}",
+                sun.elevation_degrees,
+                now.ampm_tostring()
+            ));
+            if total_length > 0.0 {
+                txt.add_line(format!(
+                    "{:.0}% of sidewalks are shaded",
+                    100.0 * shaded_length / total_length
+                ));
+            }
+        } else {
+            txt.add_line(format!("The sun is down at {}", now.ampm_tostring()));
+        }
+
+        let panel = Panel::new_builder(Widget::col(vec![
+            header(ctx, "Shade"),
+            Text::from(
+                Line(
+                    "Shadows of buildings and street trees. Times are local solar time, so the \
+                     sun is highest at noon.",
+                )
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .into_widget(ctx),
+            Widget::row(vec![
+                "Day of the year:".text_widget(ctx).centered_vert(),
+                Spinner::widget(ctx, "day of year", (1, 365), day_of_year, 1),
+            ]),
+            txt.into_widget(ctx),
+            ColorLegend::gradient(ctx, &app.cs.good_to_bad_red, vec!["shaded", "sunny"]),
+        ]))
+        .aligned_pair(PANEL_PLACEMENT)
+        .build(ctx);
+
+        Shade {
+            time: now,
+            day_of_year,
+            draw: colorer.build(ctx),
+            panel,
+        }
+    }
+}
+
+fn time_bucket(time: Time) -> usize {
+    (time.inner_seconds() / RECALCULATE_EVERY.inner_seconds()) as usize
+}
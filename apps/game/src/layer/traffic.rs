@@ -8,7 +8,7 @@ use abstutil::{prettyprint_usize, Counter};
 use geom::{Circle, Distance, Duration, Percent, Polygon, Pt2D, Time};
 use map_gui::tools::ColorNetwork;
 use map_model::{IntersectionID, Map, Traversable};
-use sim::{AgentType, VehicleType};
+use sim::{crowded_walking_speed, AgentType, VehicleType};
 use widgetry::mapspace::ToggleZoomed;
 use widgetry::mapspace::{DummyID, World};
 use widgetry::tools::{ColorLegend, DivergingScale, PopupMsg};
@@ -688,6 +688,14 @@ impl PedestrianCrowding {
             // Round up, so we don't show 0 density
             (x * 10.0).ceil() / 10.0
         }
+        fn crowding_tooltip(density: f64) -> Text {
+            let mut txt = Text::from(format!("{density} people / m²"));
+            txt.add_line(format!(
+                "People walk at {}% of their usual speed",
+                (100.0 * crowded_walking_speed(density)).round()
+            ));
+            txt
+        }
 
         let (roads, intersections) = app.primary.sim.get_pedestrian_density(map);
         let mut max_density: f64 = 0.0;
@@ -706,7 +714,7 @@ impl PedestrianCrowding {
                 .hitbox(map.get_r(r).get_thick_polygon())
                 .draw_color_unzoomed(*color)
                 .invisibly_hoverable()
-                .tooltip(Text::from(format!("{density} people / m²")))
+                .tooltip(crowding_tooltip(density))
                 .build(ctx);
         }
         for (i, density) in intersections {
@@ -723,7 +731,7 @@ impl PedestrianCrowding {
                 .hitbox(map.get_i(i).polygon.clone())
                 .draw_color_unzoomed(*color)
                 .invisibly_hoverable()
-                .tooltip(Text::from(format!("{density} people / m²")))
+                .tooltip(crowding_tooltip(density))
                 .build(ctx);
         }
         world.initialize_hover(ctx);
@@ -1,7 +1,14 @@
+use std::collections::HashSet;
+
+use abstutil::prettyprint_usize;
+use geom::Duration;
 use map_gui::tools::ColorDiscrete;
-use map_model::{PathConstraints, PathStep};
+use map_model::connectivity::{Spot, WalkingOptions};
+use map_model::{BuildingID, Map, PathConstraints, PathStep, TransitStopID};
 use widgetry::mapspace::ToggleZoomed;
-use widgetry::{EventCtx, GfxCtx, Outcome, Panel, Toggle, Widget};
+use widgetry::{
+    Color, EventCtx, GfxCtx, Line, Outcome, Panel, Spinner, Text, TextExt, Toggle, Widget,
+};
 
 use crate::app::App;
 use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
@@ -111,3 +118,215 @@ impl TransitNetwork {
         TransitNetwork { panel, draw }
     }
 }
+
+/// What share of residents live within a short walk of frequent transit, before and after the
+/// proposed route changes?
+pub struct TransitCoverage {
+    panel: Panel,
+    draw: ToggleZoomed,
+}
+
+impl Layer for TransitCoverage {
+    fn name(&self) -> Option<&'static str> {
+        Some("transit coverage")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => {
+                let mut new = TransitCoverage::new(
+                    ctx,
+                    app,
+                    self.panel.spinner("max walking time"),
+                    self.panel.spinner("max headway"),
+                );
+                new.panel.restore(ctx, &self.panel);
+                *self = new;
+            }
+            _ => {}
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        self.draw.draw(g);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw.unzoomed);
+    }
+}
+
+impl TransitCoverage {
+    pub fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        max_walk: Duration,
+        max_headway: Duration,
+    ) -> TransitCoverage {
+        let map = &app.primary.map;
+        let before = covered_bldgs(map, max_walk, max_headway, true);
+        let after = covered_bldgs(map, max_walk, max_headway, false);
+
+        let mut colorer = ColorDiscrete::new(
+            app,
+            vec![
+                ("covered", app.cs.bus_layer),
+                ("gained coverage", Color::GREEN),
+                ("lost coverage", Color::RED),
+            ],
+        );
+        let mut total = 0;
+        let mut residents_before = 0;
+        let mut residents_after = 0;
+        for b in map.all_buildings() {
+            let residents = b.bldg_type.num_residents();
+            total += residents;
+            match (before.contains(&b.id), after.contains(&b.id)) {
+                (true, true) => {
+                    colorer.add_b(b.id, "covered");
+                }
+                (false, true) => {
+                    colorer.add_b(b.id, "gained coverage");
+                }
+                (true, false) => {
+                    colorer.add_b(b.id, "lost coverage");
+                }
+                (false, false) => {}
+            }
+            if before.contains(&b.id) {
+                residents_before += residents;
+            }
+            if after.contains(&b.id) {
+                residents_after += residents;
+            }
+        }
+        for ts in map.all_transit_stops().keys() {
+            if stop_is_frequent(map, *ts, max_headway, false) {
+                colorer.add_ts(*ts, "covered");
+            }
+        }
+        let (draw, legend) = colorer.build(ctx);
+
+        let pct = |x: usize| {
+            if total == 0 {
+                0.0
+            } else {
+                100.0 * (x as f64) / (total as f64)
+            }
+        };
+        let mut txt = Text::new();
+        txt.add_line(format!(
+            "Residents covered before edits: {} ({:.1}%)",
+            prettyprint_usize(residents_before),
+            pct(residents_before)
+        ));
+        txt.add_line(format!(
+            "Residents covered after edits: {} ({:.1}%)",
+            prettyprint_usize(residents_after),
+            pct(residents_after)
+        ));
+        txt.add_line(Line(format!("Out of {} residents", prettyprint_usize(total))).secondary());
+
+        let panel = Panel::new_builder(Widget::col(vec![
+            header(ctx, "Walking access to transit"),
+            Widget::row(vec![
+                "Max walking time".text_widget(ctx).centered_vert(),
+                Spinner::widget(
+                    ctx,
+                    "max walking time",
+                    (Duration::minutes(1), Duration::minutes(30)),
+                    max_walk,
+                    Duration::minutes(1),
+                ),
+            ]),
+            Widget::row(vec![
+                "Stops served at least every"
+                    .text_widget(ctx)
+                    .centered_vert(),
+                Spinner::widget(
+                    ctx,
+                    "max headway",
+                    (Duration::minutes(1), Duration::hours(2)),
+                    max_headway,
+                    Duration::minutes(5),
+                ),
+            ]),
+            txt.into_widget(ctx),
+            legend,
+        ]))
+        .aligned_pair(PANEL_PLACEMENT)
+        .build(ctx);
+
+        TransitCoverage { panel, draw }
+    }
+}
+
+/// The buildings within walking distance of a frequently served stop. `before` uses the schedules
+/// imported from GTFS, ignoring edits.
+fn covered_bldgs(
+    map: &Map,
+    max_walk: Duration,
+    max_headway: Duration,
+    before: bool,
+) -> HashSet<BuildingID> {
+    let starts: Vec<Spot> = map
+        .all_transit_stops()
+        .values()
+        .filter(|ts| stop_is_frequent(map, ts.id, max_headway, before))
+        .map(|ts| Spot::DirectedRoad(map.get_l(ts.sidewalk_pos.lane()).get_directed_parent()))
+        .collect();
+    if starts.is_empty() {
+        return HashSet::new();
+    }
+    map_model::connectivity::all_walking_costs_from(
+        map,
+        starts,
+        max_walk,
+        WalkingOptions::default(),
+    )
+    .into_keys()
+    .collect()
+}
+
+/// During its busiest hour, do all the routes serving this stop together depart at least every
+/// `max_headway`? Closed stops are never frequent.
+fn stop_is_frequent(map: &Map, ts: TransitStopID, max_headway: Duration, before: bool) -> bool {
+    let edits = map.get_edits();
+    let mut closed = map.get_ts(ts).closed;
+    if before {
+        if let Some(orig) = edits.original_stop_closed.get(&ts) {
+            closed = *orig;
+        }
+    }
+    if closed {
+        return false;
+    }
+
+    let mut per_hour = vec![0; 24];
+    for tr in map.all_transit_routes() {
+        let (stops, times) = if before {
+            (
+                edits.original_route_stops.get(&tr.id).unwrap_or(&tr.stops),
+                &tr.orig_spawn_times,
+            )
+        } else {
+            (&tr.stops, &tr.spawn_times)
+        };
+        if !stops.contains(&ts) {
+            continue;
+        }
+        for t in times {
+            let hour = (t.inner_seconds() / 3600.0) as usize;
+            if let Some(cnt) = per_hour.get_mut(hour) {
+                *cnt += 1;
+            }
+        }
+    }
+    let busiest = per_hour.into_iter().max().unwrap_or(0);
+    busiest > 0 && max_headway * (busiest as f64) >= Duration::hours(1)
+}
@@ -239,6 +239,8 @@ fn run(mut settings: Settings) {
     if let Some(n) = args.tutorial {
         setup.initialize_tutorial = true;
         setup.mode = Mode::Gameplay(sandbox::GameplayMode::Tutorial(
+            MapName::from_path(&setup.flags.sim_flags.load)
+                .unwrap_or_else(|| MapName::seattle("montlake")),
             sandbox::TutorialPointer::new(n - 1, 0),
         ));
     }
@@ -301,7 +303,7 @@ fn setup_app(ctx: &mut EventCtx, mut setup: Setup) -> (App, Vec<Box<dyn State<Ap
         GameplayMode::PlayScenario(_, _, _)
         | GameplayMode::FixTrafficSignals
         | GameplayMode::OptimizeCommute(_, _)
-        | GameplayMode::Tutorial(_),
+        | GameplayMode::Tutorial(_, _),
     ) = setup.mode
     {
         setup.opts.color_scheme = map_gui::colors::ColorSchemeChoice::NightMode;
@@ -368,7 +370,11 @@ fn setup_app(ctx: &mut EventCtx, mut setup: Setup) -> (App, Vec<Box<dyn State<Ap
         // work on the web.
         let primary = ctx.loading_screen("load map", |ctx, timer| {
             assert!(setup.flags.sim_flags.scenario_modifiers.is_empty());
-            let (map, sim, _) = setup.flags.sim_flags.load_synchronously(timer);
+            let (map, sim, _) = setup
+                .flags
+                .sim_flags
+                .load_synchronously(timer)
+                .unwrap_or_else(|err| panic!("Can't load {}: {}", setup.flags.sim_flags.load, err));
             PerMap::map_loaded(map, sim, setup.flags.clone(), &setup.opts, &cs, ctx, timer)
         });
         assert!(secondary.is_none());
@@ -506,7 +512,7 @@ fn finish_app_setup(
     }
 
     if setup.initialize_tutorial {
-        crate::sandbox::gameplay::Tutorial::initialize(ctx, app);
+        crate::sandbox::gameplay::Tutorial::initialize(ctx, app).unwrap();
     }
 
     if title {
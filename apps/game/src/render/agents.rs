@@ -1,12 +1,12 @@
 use std::borrow::Borrow;
 use std::collections::HashMap;
 
-use geom::{Circle, Pt2D, QuadTree, Time};
+use geom::{Circle, Distance, Polygon, Pt2D, QuadTree, Ring, Time};
 use map_gui::colors::ColorScheme;
-use map_gui::options::Options;
+use map_gui::options::{AgentStyle, Options};
 use map_model::{Map, Traversable};
 use sim::{AgentID, Sim, UnzoomedAgent, VehicleType};
-use widgetry::{Color, Drawable, GeomBatch, GfxCtx, Panel, Prerender};
+use widgetry::{Color, Drawable, GeomBatch, GfxCtx, Panel, Prerender, RewriteColor};
 
 use crate::render::{
     draw_vehicle, unzoomed_agent_radius, DrawPedCrowd, DrawPedestrian, GameRenderable,
@@ -19,9 +19,15 @@ pub struct AgentCache {
     // This time applies to agents_per_on. unzoomed has its own possibly separate Time!
     time: Option<Time>,
     agents_per_on: HashMap<Traversable, Vec<Box<dyn GameRenderable>>>,
-    // when either of (time, unzoomed agent filters) change, recalculate (a quadtree of all agents,
-    // draw all agents)
-    unzoomed: Option<(Time, UnzoomedAgents, QuadTree<AgentID>, Drawable)>,
+    // when any of (time, unzoomed agent filters, style) change, recalculate (a quadtree of all
+    // agents, draw all agents)
+    unzoomed: Option<(
+        Time,
+        UnzoomedAgents,
+        (AgentStyle, bool),
+        QuadTree<AgentID>,
+        Drawable,
+    )>,
 }
 
 impl AgentCache {
@@ -77,7 +83,7 @@ impl AgentCache {
         self.agents_per_on.insert(on, list);
     }
 
-    /// If the sim time has changed or the unzoomed agent filters have been modified, recalculate
+    /// If the sim time, the unzoomed agent filters, or the agent style have changed, recalculate
     /// the quadtree and drawable for all unzoomed agents.
     pub fn calculate_unzoomed_agents<P: AsRef<Prerender>>(
         &mut self,
@@ -85,11 +91,13 @@ impl AgentCache {
         map: &Map,
         sim: &Sim,
         cs: &ColorScheme,
+        opts: &Options,
     ) -> &QuadTree<AgentID> {
         let now = sim.time();
+        let style = (opts.agent_style, opts.scale_agents_by_occupancy);
         let mut recalc = true;
-        if let Some((time, ref orig_agents, _, _)) = self.unzoomed {
-            if now == time && self.unzoomed_agents == orig_agents.clone() {
+        if let Some((time, ref orig_agents, orig_style, _, _)) = self.unzoomed {
+            if now == time && self.unzoomed_agents == orig_agents.clone() && style == orig_style {
                 recalc = false;
             }
         }
@@ -99,14 +107,8 @@ impl AgentCache {
 
             let mut batch = GeomBatch::new();
             let mut quadtree = QuadTree::builder();
-            // It's quite silly to produce triangles for the same circle over and over again. ;)
-            let car_circle = Circle::new(
-                Pt2D::new(0.0, 0.0),
-                unzoomed_agent_radius(Some(VehicleType::Car)),
-            )
-            .to_polygon();
-            let ped_circle =
-                Circle::new(Pt2D::new(0.0, 0.0), unzoomed_agent_radius(None)).to_polygon();
+            // It's quite silly to produce triangles for the same shape over and over again. ;)
+            let shapes = UnzoomedShapes::new(prerender.as_ref(), opts.agent_style);
 
             for agent in sim.get_unzoomed_agents(map) {
                 if let Some(mut color) = self.unzoomed_agents.color(&agent, cs) {
@@ -120,22 +122,31 @@ impl AgentCache {
                         color = color.tint(0.5);
                     }
 
-                    let circle = if agent.id.to_vehicle_type().is_some() {
-                        car_circle.translate(agent.pos.x(), agent.pos.y())
-                    } else {
-                        ped_circle.translate(agent.pos.x(), agent.pos.y())
-                    };
-                    quadtree.add_with_box(agent.id, circle.get_bounds());
-                    batch.push(color, circle);
+                    let mut shape = shapes
+                        .get(agent.id.to_vehicle_type())
+                        .clone()
+                        .color(RewriteColor::Change(Color::WHITE, color));
+                    if opts.scale_agents_by_occupancy && agent.occupancy > 1 {
+                        shape = shape.scale(occupancy_scale(agent.occupancy));
+                    }
+                    let shape = shape.centered_on(agent.pos);
+                    quadtree.add_with_box(agent.id, shape.get_bounds());
+                    batch.append(shape);
                 }
             }
 
             let draw = prerender.as_ref().upload(batch);
 
-            self.unzoomed = Some((now, self.unzoomed_agents.clone(), quadtree.build(), draw));
+            self.unzoomed = Some((
+                now,
+                self.unzoomed_agents.clone(),
+                style,
+                quadtree.build(),
+                draw,
+            ));
         }
 
-        &self.unzoomed.as_ref().unwrap().2
+        &self.unzoomed.as_ref().unwrap().3
     }
 
     pub fn draw_unzoomed_agents(
@@ -146,8 +157,8 @@ impl AgentCache {
         cs: &ColorScheme,
         opts: &Options,
     ) {
-        self.calculate_unzoomed_agents(g, map, sim, cs);
-        g.redraw(&self.unzoomed.as_ref().unwrap().3);
+        self.calculate_unzoomed_agents(g, map, sim, cs, opts);
+        g.redraw(&self.unzoomed.as_ref().unwrap().4);
 
         if opts.debug_all_agents {
             let mut cnt = 0;
@@ -236,3 +247,95 @@ impl UnzoomedAgents {
         self.peds = panel.is_checked("Walk");
     }
 }
+
+/// The shape drawn for each type of unzoomed agent, centered at the origin. The body is white, so
+/// it can be recolored per agent.
+struct UnzoomedShapes {
+    car: GeomBatch,
+    bike: GeomBatch,
+    transit: GeomBatch,
+    ped: GeomBatch,
+}
+
+impl UnzoomedShapes {
+    fn new(prerender: &Prerender, style: AgentStyle) -> UnzoomedShapes {
+        let vehicle_radius = unzoomed_agent_radius(Some(VehicleType::Car));
+        let ped_radius = unzoomed_agent_radius(None);
+        let circle = |radius| {
+            GeomBatch::from(vec![(
+                Color::WHITE,
+                Circle::new(Pt2D::new(0.0, 0.0), radius).to_polygon(),
+            )])
+        };
+        let icon = |filename: &str, radius: Distance| {
+            GeomBatch::load_svg(prerender, filename)
+                .color(RewriteColor::ChangeAll(Color::WHITE))
+                .autocrop()
+                .scale_to_fit_width(2.0 * radius.inner_meters())
+        };
+
+        match style {
+            AgentStyle::Circles => UnzoomedShapes {
+                car: circle(vehicle_radius),
+                bike: circle(vehicle_radius),
+                transit: circle(vehicle_radius),
+                ped: circle(ped_radius),
+            },
+            AgentStyle::Shapes => {
+                let square = Polygon::rectangle_centered(
+                    Pt2D::new(0.0, 0.0),
+                    1.6 * vehicle_radius,
+                    1.6 * vehicle_radius,
+                );
+                let r = vehicle_radius.inner_meters();
+                let triangle = Ring::must_new(vec![
+                    Pt2D::new(0.0, -r),
+                    Pt2D::new(r, r),
+                    Pt2D::new(-r, r),
+                    Pt2D::new(0.0, -r),
+                ])
+                .into_polygon();
+                // Transit vehicles are a bigger diamond, with an outline to stand out even if the
+                // fill color is hard to distinguish
+                let r = 1.3 * r;
+                let diamond = Ring::must_new(vec![
+                    Pt2D::new(0.0, -r),
+                    Pt2D::new(r, 0.0),
+                    Pt2D::new(0.0, r),
+                    Pt2D::new(-r, 0.0),
+                    Pt2D::new(0.0, -r),
+                ])
+                .into_polygon();
+                let mut transit = GeomBatch::new();
+                transit.push(Color::BLACK, diamond.to_outline(0.2 * vehicle_radius));
+                transit.push(Color::WHITE, diamond);
+                UnzoomedShapes {
+                    car: GeomBatch::from(vec![(Color::WHITE, square)]),
+                    bike: GeomBatch::from(vec![(Color::WHITE, triangle)]),
+                    transit,
+                    ped: circle(ped_radius),
+                }
+            }
+            AgentStyle::Icons => UnzoomedShapes {
+                car: icon("system/assets/meters/car.svg", vehicle_radius),
+                bike: icon("system/assets/meters/bike.svg", vehicle_radius),
+                transit: icon("system/assets/meters/bus.svg", 1.3 * vehicle_radius),
+                ped: icon("system/assets/meters/pedestrian.svg", ped_radius),
+            },
+        }
+    }
+
+    fn get(&self, vt: Option<VehicleType>) -> &GeomBatch {
+        match vt {
+            Some(VehicleType::Car) => &self.car,
+            Some(VehicleType::Bike) => &self.bike,
+            Some(VehicleType::Bus) | Some(VehicleType::Train) => &self.transit,
+            None => &self.ped,
+        }
+    }
+}
+
+/// Grow with the number of people, but not so fast that a full bus covers up the whole street
+fn occupancy_scale(occupancy: usize) -> f64 {
+    (occupancy as f64).sqrt().min(3.0)
+}
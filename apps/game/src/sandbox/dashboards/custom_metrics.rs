@@ -0,0 +1,89 @@
+use abstutil::prettyprint_usize;
+use widgetry::{EventCtx, GfxCtx, Line, Outcome, Panel, State, Text, Widget};
+
+use crate::app::{App, Transition};
+use crate::sandbox::dashboards::DashTab;
+
+/// Shows whatever custom metrics were registered with the simulation, compared against the
+/// baseline when it measured the same thing
+pub struct CustomMetrics {
+    panel: Panel,
+}
+
+impl CustomMetrics {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let now = app.primary.sim.time();
+        let results = &app.primary.sim.get_analytics().custom_metric_results;
+        let baseline = app
+            .has_prebaked()
+            .map(|_| &app.prebaked().custom_metric_results);
+
+        let mut txt = Text::new();
+        if results.is_empty() {
+            txt.add_line("No custom metrics registered");
+        }
+        for (name, result) in results {
+            txt.add_line(Line(name).small_heading());
+            let before = baseline.and_then(|b| b.get(name));
+            for (label, value) in &result.latest {
+                match before.and_then(|b| b.value_at(label, now)) {
+                    Some(value1) => {
+                        txt.add_line(format!(
+                            "{}: {} (baseline {}, {:+.1})",
+                            label,
+                            fmt_value(*value),
+                            fmt_value(value1),
+                            value - value1
+                        ));
+                    }
+                    None => {
+                        txt.add_line(format!("{}: {}", label, fmt_value(*value)));
+                    }
+                }
+            }
+            txt.add_line("");
+        }
+        if baseline.is_some() && !results.is_empty() {
+            txt.add_line(
+                Line("Baseline values are from the end of the last full hour").secondary(),
+            );
+        }
+
+        Box::new(CustomMetrics {
+            panel: Panel::new_builder(Widget::col(vec![
+                DashTab::CustomMetrics.picker(ctx, app),
+                txt.into_widget(ctx).section(ctx),
+            ]))
+            .exact_size_percent(90, 90)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for CustomMetrics {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => DashTab::CustomMetrics
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
+
+// Most metrics are counts
+fn fmt_value(x: f64) -> String {
+    if x.fract() == 0.0 && x >= 0.0 {
+        prettyprint_usize(x as usize)
+    } else {
+        format!("{:.2}", x)
+    }
+}
@@ -0,0 +1,177 @@
+use abstutil::{prettyprint_usize, Counter};
+use geom::{Duration, Time};
+use map_model::BuildingID;
+use sim::Analytics;
+use widgetry::{
+    EventCtx, GfxCtx, Line, LinePlot, Outcome, Panel, PlotOptions, Series, State, Text, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::sandbox::dashboards::DashTab;
+
+/// How often delivery vans double-park instead of using a loading zone, and how much traffic gets
+/// stuck behind them
+pub struct Deliveries {
+    panel: Panel,
+}
+
+impl Deliveries {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let now = app.primary.sim.time();
+        let analytics = app.primary.sim.get_analytics();
+        let baseline = app.has_prebaked().map(|_| app.prebaked());
+        let map = &app.primary.map;
+
+        let mut txt = Text::new();
+        let after = Summary::new(analytics, now);
+        after.describe(&mut txt);
+        if let Some(baseline) = baseline {
+            txt.add_line("");
+            txt.add_line(format!(
+                "Before \"{}\":",
+                app.primary.map.get_edits().edits_name
+            ));
+            Summary::new(baseline, now).describe(&mut txt);
+        }
+
+        let zones = map.get_loading_zones();
+        txt.add_line("");
+        txt.add_line(format!(
+            "{} loading zone spaces along {} curbs",
+            prettyprint_usize(zones.spaces.values().sum()),
+            prettyprint_usize(zones.spaces.len())
+        ));
+
+        if !after.worst_stops.is_empty() {
+            txt.add_line("");
+            txt.add_line(Line("Where vans double-park most").small_heading());
+            for (b, cnt) in &after.worst_stops {
+                txt.add_line(format!(
+                    "{}: {} times",
+                    map.get_b(*b).address,
+                    prettyprint_usize(*cnt)
+                ));
+            }
+        }
+
+        let mut series = vec![Series {
+            label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
+            color: app.cs.after_changes,
+            pts: cumulative_double_parking(analytics, now),
+        }];
+        if let Some(baseline) = baseline {
+            series.push(Series {
+                label: format!("Before \"{}\"", app.primary.map.get_edits().edits_name),
+                color: app.cs.before_changes.alpha(0.5),
+                pts: cumulative_double_parking(baseline, now),
+            });
+        }
+
+        Box::new(Deliveries {
+            panel: Panel::new_builder(Widget::col(vec![
+                DashTab::Deliveries.picker(ctx, app),
+                txt.into_widget(ctx).section(ctx),
+                Line("Vans double-parked so far")
+                    .small_heading()
+                    .into_widget(ctx),
+                LinePlot::new_widget(
+                    ctx,
+                    "double-parked vans",
+                    series,
+                    PlotOptions::fixed(),
+                    app.opts.units,
+                )
+                .section(ctx),
+            ]))
+            .exact_size_percent(90, 90)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for Deliveries {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => DashTab::Deliveries
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
+
+struct Summary {
+    stops: usize,
+    double_parked: usize,
+    /// How long double-parked vans blocked lanes in total
+    lane_blocked: Duration,
+    /// Counting the vehicles queued behind each double-parked van when it stopped
+    queued: usize,
+    worst_stops: Vec<(BuildingID, usize)>,
+}
+
+impl Summary {
+    fn new(analytics: &Analytics, until: Time) -> Summary {
+        let mut summary = Summary {
+            stops: 0,
+            double_parked: 0,
+            lane_blocked: Duration::ZERO,
+            queued: 0,
+            worst_stops: Vec::new(),
+        };
+        let mut per_bldg = Counter::new();
+        for (t, b, blocked, queued, double_parked) in &analytics.delivery_stops {
+            if *t > until {
+                break;
+            }
+            summary.stops += 1;
+            if *double_parked {
+                summary.double_parked += 1;
+                summary.lane_blocked += *blocked;
+                summary.queued += *queued;
+                per_bldg.inc(*b);
+            }
+        }
+        summary.worst_stops = per_bldg.highest_n(5);
+        summary
+    }
+
+    fn describe(&self, txt: &mut Text) {
+        txt.add_line(format!(
+            "{} delivery stops: {} double-parked, and {} in a loading zone",
+            prettyprint_usize(self.stops),
+            prettyprint_usize(self.double_parked),
+            prettyprint_usize(self.stops - self.double_parked)
+        ));
+        txt.add_line(format!(
+            "Double-parked vans blocked lanes for {} in total, with {} vehicles queued behind them \
+             when they stopped",
+            self.lane_blocked,
+            prettyprint_usize(self.queued)
+        ));
+    }
+}
+
+fn cumulative_double_parking(analytics: &Analytics, until: Time) -> Vec<(Time, usize)> {
+    let mut pts = vec![(Time::START_OF_DAY, 0)];
+    let mut cnt = 0;
+    for (t, _, _, _, double_parked) in &analytics.delivery_stops {
+        if *t > until {
+            break;
+        }
+        if *double_parked {
+            cnt += 1;
+            pts.push((*t, cnt));
+        }
+    }
+    pts.push((until, cnt));
+    pts
+}
@@ -0,0 +1,172 @@
+use abstutil::prettyprint_usize;
+use geom::{Duration, Time};
+use map_model::BuildingID;
+use sim::Analytics;
+use widgetry::{EventCtx, GfxCtx, Line, Outcome, Panel, State, Text, Widget};
+
+use crate::app::{App, Transition};
+use crate::sandbox::dashboards::DashTab;
+
+/// How long fire engines and ambulances take to reach emergencies, and whether edits slow them
+/// down
+pub struct EmergencyResponse {
+    panel: Panel,
+}
+
+impl EmergencyResponse {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let now = app.primary.sim.time();
+        let map = &app.primary.map;
+        let baseline = app.has_prebaked().map(|_| app.prebaked());
+
+        let mut txt = Text::new();
+        if app.primary.sim.get_emergency_calls().is_empty() {
+            txt.add_line("No emergency calls were scheduled for this simulation");
+        }
+        let after = Summary::new(app.primary.sim.get_analytics(), now);
+        after.describe(&mut txt);
+        if let Some(baseline) = baseline {
+            let before = Summary::new(baseline, now);
+            txt.add_line("");
+            txt.add_line(format!(
+                "Before \"{}\":",
+                app.primary.map.get_edits().edits_name
+            ));
+            before.describe(&mut txt);
+
+            // The same calls happen in both runs, so compare each building's responses
+            let mut changes: Vec<(BuildingID, Duration)> = Vec::new();
+            let mut newly_unreachable = 0;
+            for (b, response1) in &before.responses {
+                match (response1, after.response_to(*b)) {
+                    (Some(dt1), Some(Some(dt2))) => {
+                        changes.push((*b, dt2 - *dt1));
+                    }
+                    (Some(_), Some(None)) => {
+                        newly_unreachable += 1;
+                    }
+                    _ => {}
+                }
+            }
+            txt.add_line("");
+            txt.add_line(Line("Changes").small_heading());
+            txt.add_line(format!(
+                "{} responses got faster, {} got slower",
+                prettyprint_usize(
+                    changes
+                        .iter()
+                        .filter(|(_, dt)| *dt < Duration::ZERO)
+                        .count()
+                ),
+                prettyprint_usize(
+                    changes
+                        .iter()
+                        .filter(|(_, dt)| *dt > Duration::ZERO)
+                        .count()
+                )
+            ));
+            if newly_unreachable > 0 {
+                txt.add_line(
+                    Line(format!(
+                        "{} emergencies can't be reached anymore",
+                        prettyprint_usize(newly_unreachable)
+                    ))
+                    .fg(ctx.style().text_destructive_color),
+                );
+            }
+            changes.sort_by_key(|(_, dt)| *dt);
+            changes.reverse();
+            for (b, dt) in changes.into_iter().take(5) {
+                if dt > Duration::ZERO {
+                    txt.add_line(format!("{}: {} slower", map.get_b(b).address, dt));
+                }
+            }
+        } else if let Some((b, dt)) = after
+            .responses
+            .iter()
+            .filter_map(|(b, dt)| Some((*b, (*dt)?)))
+            .max_by_key(|(_, dt)| *dt)
+        {
+            txt.add_line(format!(
+                "The slowest response was {} to {}",
+                dt,
+                map.get_b(b).address
+            ));
+        }
+
+        Box::new(EmergencyResponse {
+            panel: Panel::new_builder(Widget::col(vec![
+                DashTab::EmergencyResponse.picker(ctx, app),
+                txt.into_widget(ctx).section(ctx),
+            ]))
+            .exact_size_percent(90, 90)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for EmergencyResponse {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => DashTab::EmergencyResponse
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
+
+struct Summary {
+    /// In the order vehicles arrived. None if the building couldn't be reached.
+    responses: Vec<(BuildingID, Option<Duration>)>,
+}
+
+impl Summary {
+    fn new(analytics: &Analytics, until: Time) -> Summary {
+        Summary {
+            responses: analytics
+                .emergency_responses
+                .iter()
+                .take_while(|(t, _, _)| *t <= until)
+                .map(|(_, b, dt)| (*b, *dt))
+                .collect(),
+        }
+    }
+
+    fn response_to(&self, b: BuildingID) -> Option<Option<Duration>> {
+        self.responses
+            .iter()
+            .find(|(b2, _)| *b2 == b)
+            .map(|(_, dt)| *dt)
+    }
+
+    fn describe(&self, txt: &mut Text) {
+        let mut times: Vec<Duration> = self.responses.iter().filter_map(|(_, dt)| *dt).collect();
+        times.sort();
+        let unreachable = self.responses.len() - times.len();
+        txt.add_line(format!(
+            "{} emergency vehicles arrived, and {} couldn't reach the emergency at all",
+            prettyprint_usize(times.len()),
+            prettyprint_usize(unreachable)
+        ));
+        if times.is_empty() {
+            return;
+        }
+        let total = times.iter().fold(Duration::ZERO, |sum, dt| sum + *dt);
+        txt.add_line(format!(
+            "Average response time {}, median {}, 90th percentile {}, slowest {}",
+            Duration::seconds(total.inner_seconds() / (times.len() as f64)),
+            times[times.len() / 2],
+            times[(times.len() * 9 / 10).min(times.len() - 1)],
+            times.last().unwrap()
+        ));
+    }
+}
@@ -0,0 +1,336 @@
+use abstutil::prettyprint_usize;
+use geom::Time;
+use map_model::RoadID;
+use sim::{
+    Analytics, CyclingStats, Emissions, EnergyModel, FleetMix, HealthImpact, HealthImpactModel,
+    TripEnergy,
+};
+use synthpop::TripMode;
+use widgetry::{
+    EventCtx, GfxCtx, Line, LinePlot, Outcome, Panel, PlotOptions, Series, State, Text, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::sandbox::dashboards::DashTab;
+
+/// The fuel used and pollution emitted by cars, the calories people burn walking and cycling, and
+/// how that changes people's health
+pub struct Energy {
+    panel: Panel,
+}
+
+impl Energy {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let now = app.primary.sim.time();
+        let model = EnergyModel::default_model();
+        let fleet = FleetMix::default_mix();
+        let analytics = app.primary.sim.get_analytics();
+        let baseline = app.has_prebaked().map(|_| app.prebaked());
+
+        let mut txt = Text::new();
+        txt.add_line(
+            Line("A rough estimate from the distance, speed and climbing of finished trips")
+                .secondary(),
+        );
+        txt.add_line("");
+        let after = Summary::new(&model, &fleet, analytics, now);
+        after.describe(app, &mut txt);
+        if let Some(baseline) = baseline {
+            txt.add_line("");
+            txt.add_line(format!(
+                "Before \"{}\":",
+                app.primary.map.get_edits().edits_name
+            ));
+            let before = Summary::new(&model, &fleet, baseline, now);
+            before.describe(app, &mut txt);
+
+            txt.add_line("");
+            txt.add_line(Line("Emissions").small_heading());
+            describe_emissions_change(app, &fleet, &before, &after, baseline, now, &mut txt);
+
+            txt.add_line("");
+            txt.add_line(Line("Health impact").small_heading());
+            let health = HealthImpactModel::default_model();
+            describe_health(
+                &health,
+                &health.assess(&app.primary.sim, baseline, now),
+                &mut txt,
+            );
+        }
+
+        let mut fuel_series = vec![Series {
+            label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
+            color: app.cs.after_changes,
+            pts: cumulative(&model, analytics, now, |e| e.fuel_liters()),
+        }];
+        let mut kcal_series = vec![Series {
+            label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
+            color: app.cs.after_changes,
+            pts: cumulative(&model, analytics, now, |e| e.kcal()),
+        }];
+        let mut co2_series = vec![Series {
+            label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
+            color: app.cs.after_changes,
+            pts: cumulative_co2(&fleet, analytics, now),
+        }];
+        if let Some(baseline) = baseline {
+            fuel_series.push(Series {
+                label: format!("Before \"{}\"", app.primary.map.get_edits().edits_name),
+                color: app.cs.before_changes.alpha(0.5),
+                pts: cumulative(&model, baseline, now, |e| e.fuel_liters()),
+            });
+            kcal_series.push(Series {
+                label: format!("Before \"{}\"", app.primary.map.get_edits().edits_name),
+                color: app.cs.before_changes.alpha(0.5),
+                pts: cumulative(&model, baseline, now, |e| e.kcal()),
+            });
+            co2_series.push(Series {
+                label: format!("Before \"{}\"", app.primary.map.get_edits().edits_name),
+                color: app.cs.before_changes.alpha(0.5),
+                pts: cumulative_co2(&fleet, baseline, now),
+            });
+        }
+
+        Box::new(Energy {
+            panel: Panel::new_builder(Widget::col(vec![
+                DashTab::Energy.picker(ctx, app),
+                txt.into_widget(ctx).section(ctx),
+                Line("Liters of fuel burned so far")
+                    .small_heading()
+                    .into_widget(ctx),
+                LinePlot::new_widget(
+                    ctx,
+                    "fuel",
+                    fuel_series,
+                    PlotOptions::fixed(),
+                    app.opts.units,
+                )
+                .section(ctx),
+                Line("Kilograms of CO2 emitted so far")
+                    .small_heading()
+                    .into_widget(ctx),
+                LinePlot::new_widget(ctx, "co2", co2_series, PlotOptions::fixed(), app.opts.units)
+                    .section(ctx),
+                Line("Calories burned walking and cycling so far")
+                    .small_heading()
+                    .into_widget(ctx),
+                LinePlot::new_widget(
+                    ctx,
+                    "calories",
+                    kcal_series,
+                    PlotOptions::fixed(),
+                    app.opts.units,
+                )
+                .section(ctx),
+            ]))
+            .exact_size_percent(90, 90)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for Energy {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => DashTab::Energy.transition(ctx, app, &self.panel).unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
+
+struct Summary {
+    /// Per mode, in the order of `TripMode::all`
+    per_mode: Vec<(TripMode, usize, TripEnergy)>,
+    total: TripEnergy,
+    emissions: Emissions,
+    bikes: CyclingStats,
+    ebikes: CyclingStats,
+}
+
+impl Summary {
+    fn new(model: &EnergyModel, fleet: &FleetMix, analytics: &Analytics, until: Time) -> Summary {
+        let mut per_mode: Vec<(TripMode, usize, TripEnergy)> = TripMode::all()
+            .into_iter()
+            .map(|mode| (mode, 0, TripEnergy::default()))
+            .collect();
+        let mut total = TripEnergy::default();
+        for (_, _, mode, energy) in model.finished_trips(analytics, until) {
+            let entry = per_mode.iter_mut().find(|(m, _, _)| *m == mode).unwrap();
+            entry.1 += 1;
+            entry.2.add(&energy);
+            total.add(&energy);
+        }
+        let (bikes, ebikes) = analytics.cycling_stats(until);
+        Summary {
+            per_mode,
+            total,
+            emissions: fleet.total(analytics, until),
+            bikes,
+            ebikes,
+        }
+    }
+
+    fn describe(&self, app: &App, txt: &mut Text) {
+        txt.add_line(format!(
+            "{:.0} L of fuel ({:.0} kWh) burned driving",
+            self.total.fuel_liters(),
+            self.total.vehicle_kwh
+        ));
+        txt.add_line(format!(
+            "{} kg of CO2, {:.0} g of NOx and {:.0} g of particulates emitted driving",
+            prettyprint_usize(self.emissions.co2_kg().round() as usize),
+            self.emissions.nox_grams,
+            self.emissions.pm_grams
+        ));
+        txt.add_line(format!(
+            "{} kcal burned walking, and {} kcal cycling",
+            prettyprint_usize(self.total.walking_kcal.round() as usize),
+            prettyprint_usize(self.total.biking_kcal.round() as usize)
+        ));
+        for (mode, trips, energy) in &self.per_mode {
+            if *trips == 0 {
+                continue;
+            }
+            let n = *trips as f64;
+            txt.add_line(format!(
+                "{} {} trips, each using {:.2} L of fuel and burning {:.0} kcal on average",
+                prettyprint_usize(*trips),
+                mode.noun().to_lowercase(),
+                energy.fuel_liters() / n,
+                energy.kcal() / n
+            ));
+        }
+        for (name, stats) in [("bikes", &self.bikes), ("e-bikes", &self.ebikes)] {
+            if let Some(speed) = stats.average_speed() {
+                txt.add_line(format!(
+                    "{} trips on {} rode {} in total, at {} on average",
+                    prettyprint_usize(stats.trips),
+                    name,
+                    stats.distance.to_string(&app.opts.units),
+                    speed.to_string(&app.opts.units)
+                ));
+            }
+        }
+    }
+}
+
+fn describe_emissions_change(
+    app: &App,
+    fleet: &FleetMix,
+    before: &Summary,
+    after: &Summary,
+    baseline: &Analytics,
+    now: Time,
+    txt: &mut Text,
+) {
+    let pct = |x1: f64, x2: f64| {
+        if x1 == 0.0 {
+            0.0
+        } else {
+            100.0 * (x2 - x1) / x1
+        }
+    };
+    txt.add_line(format!(
+        "CO2 {:+.1}%, NOx {:+.1}%, particulates {:+.1}%",
+        pct(before.emissions.co2_grams, after.emissions.co2_grams),
+        pct(before.emissions.nox_grams, after.emissions.nox_grams),
+        pct(before.emissions.pm_grams, after.emissions.pm_grams)
+    ));
+
+    // Where did traffic shift to?
+    let roads_before = fleet.road_emissions(baseline, now);
+    let roads_after = fleet.road_emissions(app.primary.sim.get_analytics(), now);
+    let mut changes: Vec<(RoadID, f64)> = roads_after
+        .iter()
+        .map(|(r, e)| {
+            let co2_before = roads_before.get(r).map(|e| e.co2_grams).unwrap_or(0.0);
+            (*r, e.co2_kg() - co2_before / 1000.0)
+        })
+        .filter(|(_, dx)| *dx > 0.0)
+        .collect();
+    changes.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    for (r, dx) in changes.into_iter().take(3) {
+        txt.add_line(format!(
+            "{:.1} kg more CO2 along {}",
+            dx,
+            app.primary
+                .map
+                .get_r(r)
+                .get_name(app.opts.language.as_ref())
+        ));
+    }
+}
+
+fn describe_health(model: &HealthImpactModel, impact: &HealthImpact, txt: &mut Text) {
+    txt.add_line(
+        Line(format!(
+            "Following the WHO's HEAT method, if the day repeats {} days a week all year",
+            model.days_per_week
+        ))
+        .secondary(),
+    );
+    txt.add_line(format!(
+        "Walking: {} minutes per week, before {}",
+        prettyprint_usize(impact.walking_minutes_after.round() as usize),
+        prettyprint_usize(impact.walking_minutes_before.round() as usize)
+    ));
+    txt.add_line(format!(
+        "Cycling: {} minutes per week, before {}",
+        prettyprint_usize(impact.cycling_minutes_after.round() as usize),
+        prettyprint_usize(impact.cycling_minutes_before.round() as usize)
+    ));
+    txt.add_line(format!(
+        "{} people are more active, and {} less active",
+        prettyprint_usize(impact.more_active),
+        prettyprint_usize(impact.less_active)
+    ));
+    if impact.deaths_prevented_per_year >= 0.0 {
+        txt.add_line(format!(
+            "{:.2} early deaths prevented per year, worth {} per year",
+            impact.deaths_prevented_per_year,
+            prettyprint_usize(impact.value_per_year.round() as usize)
+        ));
+    } else {
+        txt.add_line(format!(
+            "{:.2} more early deaths per year, costing {} per year",
+            -impact.deaths_prevented_per_year,
+            prettyprint_usize(-impact.value_per_year.round() as usize)
+        ));
+    }
+}
+
+fn cumulative_co2(fleet: &FleetMix, analytics: &Analytics, until: Time) -> Vec<(Time, usize)> {
+    let mut pts = vec![(Time::START_OF_DAY, 0)];
+    let mut sum = 0.0;
+    for (t, _, emissions) in fleet.finished_trips(analytics, until) {
+        sum += emissions.co2_kg();
+        pts.push((t, sum.round() as usize));
+    }
+    pts.push((until, sum.round() as usize));
+    pts
+}
+
+// The plots can only show whole numbers
+fn cumulative<F: Fn(&TripEnergy) -> f64>(
+    model: &EnergyModel,
+    analytics: &Analytics,
+    until: Time,
+    value: F,
+) -> Vec<(Time, usize)> {
+    let mut pts = vec![(Time::START_OF_DAY, 0)];
+    let mut sum = 0.0;
+    for (t, _, _, energy) in model.finished_trips(analytics, until) {
+        sum += value(&energy);
+        pts.push((t, sum.round() as usize));
+    }
+    pts.push((until, sum.round() as usize));
+    pts
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+
+use abstutil::prettyprint_usize;
+use geom::Time;
+use map_model::BuildingID;
+use sim::Analytics;
+use widgetry::{
+    EventCtx, GfxCtx, Line, LinePlot, Outcome, Panel, PlotOptions, Series, State, Text, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::sandbox::dashboards::DashTab;
+
+/// How shared bikes and e-scooters get used, and where they pile up or run out without anybody
+/// rebalancing them
+pub struct Micromobility {
+    panel: Panel,
+}
+
+impl Micromobility {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let now = app.primary.sim.time();
+        let analytics = app.primary.sim.get_analytics();
+        let baseline = app.has_prebaked().map(|_| app.prebaked());
+        let map = &app.primary.map;
+
+        let mut txt = Text::new();
+        let after = Summary::new(analytics, now);
+        after.describe(&mut txt);
+        if let Some(baseline) = baseline {
+            txt.add_line("");
+            txt.add_line(format!(
+                "Before \"{}\":",
+                app.primary.map.get_edits().edits_name
+            ));
+            Summary::new(baseline, now).describe(&mut txt);
+        }
+
+        let mut drained: Vec<(BuildingID, isize)> = after
+            .net_per_bldg
+            .iter()
+            .filter(|(_, net)| **net < 0)
+            .map(|(b, net)| (*b, *net))
+            .collect();
+        drained.sort_by_key(|(_, net)| *net);
+        if !drained.is_empty() {
+            txt.add_line("");
+            txt.add_line(Line("Where vehicles run out").small_heading());
+            for (b, net) in drained.into_iter().take(5) {
+                txt.add_line(format!(
+                    "{}: {} fewer than at the start of the day",
+                    map.get_b(b).address,
+                    prettyprint_usize(net.unsigned_abs())
+                ));
+            }
+        }
+        let mut piled_up: Vec<(BuildingID, isize)> = after
+            .net_per_bldg
+            .iter()
+            .filter(|(_, net)| **net > 0)
+            .map(|(b, net)| (*b, *net))
+            .collect();
+        piled_up.sort_by_key(|(_, net)| -*net);
+        if !piled_up.is_empty() {
+            txt.add_line("");
+            txt.add_line(Line("Where vehicles pile up").small_heading());
+            for (b, net) in piled_up.into_iter().take(5) {
+                txt.add_line(format!(
+                    "{}: {} more than at the start of the day",
+                    map.get_b(b).address,
+                    prettyprint_usize(net as usize)
+                ));
+            }
+        }
+
+        let mut series = vec![Series {
+            label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
+            color: app.cs.after_changes,
+            pts: cumulative_unavailable(analytics, now),
+        }];
+        if let Some(baseline) = baseline {
+            series.push(Series {
+                label: format!("Before \"{}\"", app.primary.map.get_edits().edits_name),
+                color: app.cs.before_changes.alpha(0.5),
+                pts: cumulative_unavailable(baseline, now),
+            });
+        }
+
+        Box::new(Micromobility {
+            panel: Panel::new_builder(Widget::col(vec![
+                DashTab::Micromobility.picker(ctx, app),
+                txt.into_widget(ctx).section(ctx),
+                Line("Trips finding no vehicle nearby so far")
+                    .small_heading()
+                    .into_widget(ctx),
+                LinePlot::new_widget(
+                    ctx,
+                    "no vehicle nearby",
+                    series,
+                    PlotOptions::fixed(),
+                    app.opts.units,
+                )
+                .section(ctx),
+            ]))
+            .exact_size_percent(90, 90)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for Micromobility {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => DashTab::Micromobility
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
+
+struct Summary {
+    taken: usize,
+    left: usize,
+    unavailable: usize,
+    /// Vehicles left minus vehicles taken outside each building
+    net_per_bldg: BTreeMap<BuildingID, isize>,
+}
+
+impl Summary {
+    fn new(analytics: &Analytics, until: Time) -> Summary {
+        let mut summary = Summary {
+            taken: 0,
+            left: 0,
+            unavailable: 0,
+            net_per_bldg: BTreeMap::new(),
+        };
+        for (t, b, taken) in &analytics.shared_vehicle_moves {
+            if *t > until {
+                break;
+            }
+            let net = summary.net_per_bldg.entry(*b).or_insert(0);
+            if *taken {
+                summary.taken += 1;
+                *net -= 1;
+            } else {
+                summary.left += 1;
+                *net += 1;
+            }
+        }
+        summary.net_per_bldg.retain(|_, net| *net != 0);
+        summary.unavailable = analytics
+            .no_shared_vehicle
+            .iter()
+            .take_while(|(t, _)| *t <= until)
+            .count();
+        summary
+    }
+
+    fn describe(&self, txt: &mut Text) {
+        txt.add_line(format!(
+            "{} shared bikes and scooters picked up, and {} left at the end of a ride",
+            prettyprint_usize(self.taken),
+            prettyprint_usize(self.left)
+        ));
+        txt.add_line(format!(
+            "{} trips found no vehicle nearby and walked instead",
+            prettyprint_usize(self.unavailable)
+        ));
+        let to_move: isize = self.net_per_bldg.values().filter(|net| **net > 0).sum();
+        txt.add_line(format!(
+            "Putting every vehicle back where it started means moving {} of them, from {} places",
+            prettyprint_usize(to_move as usize),
+            prettyprint_usize(self.net_per_bldg.values().filter(|net| **net > 0).count())
+        ));
+    }
+}
+
+fn cumulative_unavailable(analytics: &Analytics, until: Time) -> Vec<(Time, usize)> {
+    let mut pts = vec![(Time::START_OF_DAY, 0)];
+    let mut cnt = 0;
+    for (t, _) in &analytics.no_shared_vehicle {
+        if *t > until {
+            break;
+        }
+        cnt += 1;
+        pts.push((*t, cnt));
+    }
+    pts.push((until, cnt));
+    pts
+}
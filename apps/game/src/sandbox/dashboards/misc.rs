@@ -1,6 +1,9 @@
+use std::collections::BTreeMap;
+
 use abstutil::{prettyprint_usize, Counter};
-use geom::Time;
+use geom::{Duration, Time};
 use map_model::TransitRouteID;
+use sim::{Analytics, CarID};
 use widgetry::{
     Autocomplete, EventCtx, GfxCtx, Image, Line, LinePlot, Outcome, Panel, PlotOptions, Series,
     State, TextExt, Widget,
@@ -138,6 +141,12 @@ impl TransitRoutes {
             }
         }
 
+        let now = app.primary.sim.time();
+        let signal_delays = signal_delay_per_route(app.primary.sim.get_analytics(), now);
+        let baseline_signal_delays = app
+            .has_prebaked()
+            .map(|_| signal_delay_per_route(app.prebaked(), now));
+
         // Sort descending by count, but ascending by name. Hence the funny negation.
         let mut routes: Vec<(isize, isize, isize, String, TransitRouteID)> = Vec::new();
         for r in app.primary.map.all_transit_routes() {
@@ -174,18 +183,35 @@ impl TransitRoutes {
                 routes
                     .into_iter()
                     .map(|(boardings, alightings, waiting, name, id)| {
+                        let delay = signal_delays.get(&id).cloned().unwrap_or(Duration::ZERO);
+                        let mut txt = format!(
+                            "{} boardings, {} alightings, {} currently waiting, {} delayed at \
+                             signals",
+                            prettyprint_usize(-boardings as usize),
+                            prettyprint_usize(-alightings as usize),
+                            prettyprint_usize(-waiting as usize),
+                            delay.to_string(&app.opts.units)
+                        );
+                        if let Some(ref baseline) = baseline_signal_delays {
+                            let before = baseline.get(&id).cloned().unwrap_or(Duration::ZERO);
+                            if delay < before {
+                                txt.push_str(&format!(
+                                    " ({} saved)",
+                                    (before - delay).to_string(&app.opts.units)
+                                ));
+                            } else if delay > before {
+                                txt.push_str(&format!(
+                                    " ({} worse)",
+                                    (delay - before).to_string(&app.opts.units)
+                                ));
+                            }
+                        }
                         Widget::row(vec![
                             ctx.style()
                                 .btn_outline
                                 .text(name)
                                 .build_widget(ctx, id.to_string()),
-                            format!(
-                                "{} boardings, {} alightings, {} currently waiting",
-                                prettyprint_usize(-boardings as usize),
-                                prettyprint_usize(-alightings as usize),
-                                prettyprint_usize(-waiting as usize)
-                            )
-                            .text_widget(ctx),
+                            txt.text_widget(ctx),
                         ])
                     })
                     .collect(),
@@ -251,3 +277,26 @@ impl State<App> for TransitRoutes {
         self.panel.draw(g);
     }
 }
+
+/// How long each route's vehicles have waited at traffic signals, up to some time
+fn signal_delay_per_route(
+    analytics: &Analytics,
+    until: Time,
+) -> BTreeMap<TransitRouteID, Duration> {
+    // Delays are recorded per vehicle, so figure out which route each one serves
+    let route_per_vehicle: BTreeMap<CarID, TransitRouteID> = analytics
+        .bus_arrivals
+        .iter()
+        .map(|(_, car, route, _)| (*car, *route))
+        .collect();
+    let mut delays = BTreeMap::new();
+    for (t, car, _, delay) in &analytics.transit_signal_delays {
+        if *t > until {
+            break;
+        }
+        if let Some(route) = route_per_vehicle.get(car) {
+            *delays.entry(*route).or_insert(Duration::ZERO) += *delay;
+        }
+    }
+    delays
+}
@@ -7,12 +7,21 @@ use crate::app::App;
 use crate::app::Transition;
 
 mod commuter;
+mod custom_metrics;
+mod deliveries;
+mod emergency;
+mod energy;
 mod generic_trip_table;
+mod micromobility;
 mod misc;
 mod mode_shift;
 mod parking_overhead;
+mod parking_prices;
+mod reliability;
+mod report;
 mod risks;
 mod selector;
+mod tolls;
 mod traffic_signals;
 mod travel_times;
 mod trip_problems;
@@ -30,10 +39,19 @@ pub enum DashTab {
     CommuterPatterns,
     TrafficSignals,
     ModeShift,
+    Tolls,
+    ParkingPrices,
+    Deliveries,
+    Energy,
+    Micromobility,
+    EmergencyResponse,
+    CustomMetrics,
+    TravelTimeReliability,
 }
 
 impl DashTab {
-    pub fn picker(self, ctx: &EventCtx, app: &App) -> Widget {
+    /// Every dashboard that makes sense right now
+    pub fn choices(app: &App) -> Vec<Choice<DashTab>> {
         let mut choices = vec![
             Choice::new("Trip Table", DashTab::TripTable),
             Choice::new("Travel Times", DashTab::TravelTimes),
@@ -44,15 +62,27 @@ impl DashTab {
             Choice::new("Commuter Patterns", DashTab::CommuterPatterns),
             Choice::new("Traffic Signal Demand", DashTab::TrafficSignals),
             Choice::new("Mode shift (experimental)", DashTab::ModeShift),
+            Choice::new("Tolls", DashTab::Tolls),
+            Choice::new("Parking Prices", DashTab::ParkingPrices),
+            Choice::new("Deliveries", DashTab::Deliveries),
+            Choice::new("Energy, Emissions & Health", DashTab::Energy),
+            Choice::new("Shared Bikes & Scooters", DashTab::Micromobility),
+            Choice::new("Emergency Response", DashTab::EmergencyResponse),
+            Choice::new("Custom Metrics", DashTab::CustomMetrics),
+            Choice::new("Travel Time Reliability", DashTab::TravelTimeReliability),
         ];
         if app.has_prebaked().is_none() {
             choices.remove(1);
             choices.remove(1);
         }
+        choices
+    }
+
+    pub fn picker(self, ctx: &EventCtx, app: &App) -> Widget {
         Widget::row(vec![
             Image::from_path("system/assets/meters/trip_histogram.svg").into_widget(ctx),
             Line("Data").big_heading_plain().into_widget(ctx),
-            Widget::dropdown(ctx, "tab", self, choices),
+            Widget::dropdown(ctx, "tab", self, DashTab::choices(app)),
             format!("By {}", app.primary.sim.time().ampm_tostring())
                 .text_widget(ctx)
                 .centered_vert(),
@@ -73,6 +103,16 @@ impl DashTab {
             DashTab::CommuterPatterns => CommuterPatterns::new_state(ctx, app),
             DashTab::TrafficSignals => TrafficSignalDemand::new_state(ctx, app),
             DashTab::ModeShift => mode_shift::ModeShift::new_state(ctx, app),
+            DashTab::Tolls => tolls::Tolls::new_state(ctx, app),
+            DashTab::ParkingPrices => parking_prices::ParkingPrices::new_state(ctx, app),
+            DashTab::Deliveries => deliveries::Deliveries::new_state(ctx, app),
+            DashTab::Energy => energy::Energy::new_state(ctx, app),
+            DashTab::Micromobility => micromobility::Micromobility::new_state(ctx, app),
+            DashTab::EmergencyResponse => emergency::EmergencyResponse::new_state(ctx, app),
+            DashTab::CustomMetrics => custom_metrics::CustomMetrics::new_state(ctx, app),
+            DashTab::TravelTimeReliability => {
+                reliability::TravelTimeReliability::new_state(ctx, app)
+            }
         }
     }
 
@@ -0,0 +1,123 @@
+use abstutil::prettyprint_usize;
+use map_model::{Map, ParkingPolicy};
+use sim::ParkingSpot;
+use widgetry::{EventCtx, GfxCtx, Line, Outcome, Panel, State, Text, Widget};
+
+use crate::app::{App, Transition};
+use crate::sandbox::dashboards::DashTab;
+
+/// How full on-street and lot parking is right now, grouped by price and time limit
+pub struct ParkingPrices {
+    panel: Panel,
+}
+
+impl ParkingPrices {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let (filled, available) = app.primary.sim.get_all_parking_spots();
+
+        // Each policy, with how many spots are (filled, available)
+        let mut tiers: Vec<(Option<ParkingPolicy>, usize, usize)> = Vec::new();
+        let mut count = |spot: ParkingSpot, is_filled: bool| {
+            let policy = match policy_for(map, spot) {
+                Some(policy) => policy,
+                // Parking in buildings isn't priced
+                None => return,
+            };
+            let idx = match tiers.iter().position(|(p, _, _)| *p == policy) {
+                Some(idx) => idx,
+                None => {
+                    tiers.push((policy, 0, 0));
+                    tiers.len() - 1
+                }
+            };
+            if is_filled {
+                tiers[idx].1 += 1;
+            } else {
+                tiers[idx].2 += 1;
+            }
+        };
+        for spot in filled {
+            count(spot, true);
+        }
+        for spot in available {
+            count(spot, false);
+        }
+        tiers.sort_by(|a, b| {
+            let price = |p: &Option<ParkingPolicy>| p.map(|p| p.hourly_price).unwrap_or(0.0);
+            price(&a.0)
+                .partial_cmp(&price(&b.0))
+                .unwrap()
+                .then_with(|| {
+                    a.0.and_then(|p| p.max_stay)
+                        .cmp(&b.0.and_then(|p| p.max_stay))
+                })
+        });
+
+        let mut txt = Text::new();
+        if map.get_parking_pricing().is_empty() {
+            txt.add_line("All parking is free. Edit a road to set parking prices.");
+        }
+        for (policy, filled, available) in tiers {
+            let total = filled + available;
+            txt.add_line(Line(describe(policy)).small_heading());
+            txt.add_line(format!(
+                "{} of {} spots taken ({}%)",
+                prettyprint_usize(filled),
+                prettyprint_usize(total),
+                if total == 0 { 0 } else { 100 * filled / total }
+            ));
+        }
+
+        Box::new(ParkingPrices {
+            panel: Panel::new_builder(Widget::col(vec![
+                DashTab::ParkingPrices.picker(ctx, app),
+                txt.into_widget(ctx).section(ctx),
+            ]))
+            .exact_size_percent(90, 90)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for ParkingPrices {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => DashTab::ParkingPrices
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
+
+/// None for parking in buildings, Some(None) for free parking
+fn policy_for(map: &Map, spot: ParkingSpot) -> Option<Option<ParkingPolicy>> {
+    let pricing = map.get_parking_pricing();
+    match spot {
+        ParkingSpot::Onstreet(l, _) => Some(pricing.blockfaces.get(&l.road).cloned()),
+        ParkingSpot::Lot(pl, _) => Some(pricing.lots.get(&pl).cloned()),
+        ParkingSpot::Offstreet(_, _) => None,
+    }
+}
+
+fn describe(policy: Option<ParkingPolicy>) -> String {
+    match policy {
+        None => "Free".to_string(),
+        Some(policy) => {
+            let mut label = format!("{:.2} per hour", policy.hourly_price);
+            if let Some(max) = policy.max_stay {
+                label.push_str(&format!(", {} limit", max));
+            }
+            label
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use abstutil::{prettyprint_usize, Timer};
+use geom::Duration;
+use sim::{Reliability, ReliabilityStats};
+use synthpop::TripEndpoint;
+use widgetry::{EventCtx, GfxCtx, Line, Outcome, Panel, State, Text, Widget};
+
+use crate::app::{App, Transition};
+use crate::sandbox::dashboards::DashTab;
+
+/// Compares how reliable travel times are before and after edits, using results from running the
+/// scenario several times with the CLI's `measure-reliability` command
+pub struct TravelTimeReliability {
+    panel: Panel,
+}
+
+impl TravelTimeReliability {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut txt = Text::new();
+        match app.has_prebaked() {
+            Some((map_name, scenario_name)) => {
+                let edits_name = &map.get_edits().edits_name;
+                let baseline: Option<Reliability> = abstio::maybe_read_binary(
+                    abstio::path_prebaked_reliability(map_name, scenario_name),
+                    &mut Timer::throwaway(),
+                )
+                .ok();
+                let current: Option<Reliability> = if map.get_edits().commands.is_empty() {
+                    baseline.clone()
+                } else {
+                    abstio::maybe_read_binary(
+                        abstio::path_reliability(map_name, scenario_name, edits_name),
+                        &mut Timer::throwaway(),
+                    )
+                    .ok()
+                };
+
+                match (baseline, current) {
+                    (Some(baseline), Some(current)) => {
+                        txt.add_line(Line("Before").small_heading());
+                        describe(&mut txt, &baseline);
+                        if !map.get_edits().commands.is_empty() {
+                            txt.add_line("");
+                            txt.add_line(Line(format!("After \"{}\"", edits_name)).small_heading());
+                            describe(&mut txt, &current);
+                            txt.add_line("");
+                            compare(&mut txt, app, &baseline, &current);
+                        }
+                    }
+                    (baseline, _) => {
+                        txt.add_line(
+                            "Reliability is measured by running the scenario several times, \
+                             which takes a while, so it has to be done ahead of time.",
+                        );
+                        if baseline.is_none() {
+                            txt.add_line(format!(
+                                "For the baseline, run: cli measure-reliability --scenario={}",
+                                abstio::path_scenario(map_name, scenario_name)
+                            ));
+                        }
+                        txt.add_line(format!(
+                            "For these edits, run: cli measure-reliability --scenario={} \
+                             --edits={}",
+                            abstio::path_scenario(map_name, scenario_name),
+                            abstio::path_edits(map_name, edits_name)
+                        ));
+                    }
+                }
+            }
+            None => {
+                txt.add_line("There are no baseline results for this scenario to compare with");
+            }
+        }
+
+        Box::new(TravelTimeReliability {
+            panel: Panel::new_builder(Widget::col(vec![
+                DashTab::TravelTimeReliability.picker(ctx, app),
+                txt.into_widget(ctx).section(ctx),
+            ]))
+            .exact_size_percent(90, 90)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for TravelTimeReliability {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => DashTab::TravelTimeReliability
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
+
+fn describe(txt: &mut Text, reliability: &Reliability) {
+    txt.add_line(format!(
+        "Over {} runs, {} origin/destination pairs and {} roads",
+        reliability.runs,
+        prettyprint_usize(reliability.od_pairs.len()),
+        prettyprint_usize(reliability.roads.len())
+    ));
+    txt.add_line(format!(
+        "Trips: median planning time index {}, median buffer time index {}",
+        fmt_index(median(
+            reliability
+                .od_pairs
+                .values()
+                .filter_map(|s| s.planning_time_index())
+        )),
+        fmt_index(median(
+            reliability
+                .od_pairs
+                .values()
+                .filter_map(|s| s.buffer_time_index())
+        ))
+    ));
+    txt.add_line(format!(
+        "Roads: median planning time index {}",
+        fmt_index(median(
+            reliability
+                .roads
+                .values()
+                .filter_map(|s| s.planning_time_index())
+        ))
+    ));
+}
+
+fn compare(txt: &mut Text, app: &App, before: &Reliability, after: &Reliability) {
+    let map = &app.primary.map;
+    txt.add_line(Line("Changes").small_heading());
+
+    // Only compare pairs with enough samples in both, or the 95th percentile is meaningless
+    let enough = |s: &ReliabilityStats| s.samples.len() >= before.runs.min(after.runs);
+    let mut od_changes: Vec<(Duration, TripEndpoint, TripEndpoint)> = Vec::new();
+    for (key, stats1) in &before.od_pairs {
+        if let Some(stats2) = after.od_pairs.get(key) {
+            if enough(stats1) && enough(stats2) {
+                od_changes.push((stats2.p95() - stats1.p95(), key.0, key.1));
+            }
+        }
+    }
+    txt.add_line(format!(
+        "The 95th percentile travel time got faster for {} origin/destination pairs, and slower \
+         for {}",
+        prettyprint_usize(
+            od_changes
+                .iter()
+                .filter(|(dt, _, _)| *dt < Duration::ZERO)
+                .count()
+        ),
+        prettyprint_usize(
+            od_changes
+                .iter()
+                .filter(|(dt, _, _)| *dt > Duration::ZERO)
+                .count()
+        )
+    ));
+    od_changes.sort_by_key(|(dt, _, _)| std::cmp::Reverse(*dt));
+    for (dt, from, to) in od_changes.into_iter().take(5) {
+        if dt > Duration::ZERO {
+            txt.add_line(format!(
+                "{} to {}: {} slower",
+                describe_endpoint(app, from),
+                describe_endpoint(app, to),
+                dt
+            ));
+        }
+    }
+
+    txt.add_line("");
+    let mut road_changes = Vec::new();
+    for (r, stats1) in &before.roads {
+        if let Some(stats2) = after.roads.get(r) {
+            if let (Some(pti1), Some(pti2)) =
+                (stats1.planning_time_index(), stats2.planning_time_index())
+            {
+                road_changes.push((pti2 - pti1, *r));
+            }
+        }
+    }
+    road_changes.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+    let worse: Vec<_> = road_changes
+        .into_iter()
+        .filter(|(change, _)| *change > 0.1)
+        .take(5)
+        .collect();
+    if worse.is_empty() {
+        txt.add_line("No road became much less reliable");
+    } else {
+        txt.add_line("Roads that became less reliable:");
+        for (change, r) in worse {
+            txt.add_line(format!(
+                "{}: planning time index up by {:.2}",
+                map.get_r(r).get_name(app.opts.language.as_ref()),
+                change
+            ));
+        }
+    }
+}
+
+fn describe_endpoint(app: &App, endpoint: TripEndpoint) -> String {
+    match endpoint {
+        TripEndpoint::Building(b) => app.primary.map.get_b(b).address.clone(),
+        TripEndpoint::Border(i) => format!("off-map via {}", i),
+        TripEndpoint::SuddenlyAppear(_) => "somewhere on the map".to_string(),
+    }
+}
+
+fn median<I: Iterator<Item = f64>>(values: I) -> Option<f64> {
+    let mut values: Vec<f64> = values.collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    Some(values[values.len() / 2])
+}
+
+fn fmt_index(x: Option<f64>) -> String {
+    match x {
+        Some(x) => format!("{:.2}", x),
+        None => "unknown".to_string(),
+    }
+}
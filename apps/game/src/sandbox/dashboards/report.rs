@@ -0,0 +1,540 @@
+//! A standalone HTML report summarizing how a proposal changes the simulation, for sharing with
+//! people who won't open A/B Street themselves. Everything is inlined, including the map and
+//! charts as SVG, so the file can be emailed around or attached to a planning document.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use abstutil::prettyprint_usize;
+use geom::{Duration, Polygon, Pt2D, Time};
+use map_gui::tools::color_for_mode;
+use sim::{Analytics, EnergyModel, FleetMix, ProblemType};
+use synthpop::TripMode;
+
+use crate::app::App;
+
+const MAP_WIDTH: f64 = 800.0;
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 240.0;
+// Travel time changes are grouped into these buckets, in minutes
+const BUCKETS: [f64; 7] = [-10.0, -5.0, -1.0, 1.0, 5.0, 10.0, f64::MAX];
+
+/// Writes the report and returns the path to it
+pub fn generate_report(app: &App) -> Result<String> {
+    let map = &app.primary.map;
+    let edits = map.get_edits();
+    let now = app.primary.sim.time();
+    let after = app.primary.sim.get_analytics();
+    let before = app.has_prebaked().map(|_| app.prebaked());
+
+    let path = format!(
+        "report_{}_{}_{}.html",
+        map.get_name().as_filename(),
+        edits.edits_name.replace(' ', "_"),
+        now.as_filename()
+    );
+
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html><head><meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(&edits.edits_name))?;
+    writeln!(
+        out,
+        "<style>body {{ font-family: sans-serif; max-width: 900px; margin: auto; }} table {{ \
+         border-collapse: collapse; }} td, th {{ border: 1px solid #ccc; padding: 4px 8px; \
+         text-align: right; }} th:first-child, td:first-child {{ text-align: left; }} .notes {{ \
+         color: #555; }}</style>"
+    )?;
+    writeln!(out, "</head><body>")?;
+
+    writeln!(out, "<h1>{}</h1>", escape(&edits.edits_name))?;
+    writeln!(
+        out,
+        "<p>{}, simulated until {}</p>",
+        escape(&map.get_name().describe()),
+        now.ampm_tostring()
+    )?;
+    for line in &edits.proposal_description {
+        writeln!(out, "<p>{}</p>", escape(line))?;
+    }
+    if let Some(ref link) = edits.proposal_link {
+        writeln!(out, "<p><a href=\"{0}\">{0}</a></p>", escape(link))?;
+    }
+
+    writeln!(out, "<h2>Map edits</h2>")?;
+    draw_edits(app, &mut out)?;
+    writeln!(out, "<ul>")?;
+    for cmd in &edits.commands {
+        let (summary, details) = cmd.describe(map);
+        writeln!(out, "<li>{}", escape(&summary))?;
+        if !details.is_empty() {
+            writeln!(out, ": {}", escape(&details.join(", ")))?;
+        }
+        writeln!(out, "</li>")?;
+    }
+    writeln!(out, "</ul>")?;
+
+    writeln!(out, "<h2>Key metrics</h2>")?;
+    if before.is_none() {
+        writeln!(
+            out,
+            "<p class=\"notes\">No baseline without the edits has been simulated yet, so only \
+             the results with the edits are shown.</p>"
+        )?;
+    }
+    metrics_table(after, before, now, &mut out)?;
+
+    if let Some(before) = before {
+        writeln!(out, "<h2>Travel times</h2>")?;
+        travel_time_changes(after, before, now, &mut out)?;
+    }
+
+    writeln!(out, "<h2>Finished trips over time</h2>")?;
+    finished_trips_chart(app, after, before, now, &mut out)?;
+
+    writeln!(out, "<h2>Methodology</h2>")?;
+    writeln!(out, "<div class=\"notes\">")?;
+    writeln!(
+        out,
+        "<p>These results come from an agent-based simulation in A/B Street. Every person in \
+         the scenario follows a schedule of trips, choosing a route on the edited map, and \
+         interacts with other traffic along the way. The baseline is the same scenario with \
+         the same random seed, simulated on the map without any edits.</p>"
+    )?;
+    writeln!(
+        out,
+        "<p>Travel times only compare trips that finished successfully both before and after \
+         the edits. Cancelled trips couldn't find any route, or got stuck.</p>"
+    )?;
+    writeln!(
+        out,
+        "<p>Fuel, emissions and calories are rough estimates from the distance, speed and \
+         climbing of finished trips, using a default European car fleet. They're meant for \
+         comparing proposals against each other, not as absolute predictions.</p>"
+    )?;
+    writeln!(
+        out,
+        "<p>The scenario, map data and model all simplify reality. Treat small differences as \
+         noise, and check surprising results in the simulation itself.</p>"
+    )?;
+    writeln!(out, "</div>")?;
+
+    writeln!(out, "</body></html>")?;
+    abstio::write_file(path, out)
+}
+
+/// Every road and intersection in grey, edited ones in red
+fn draw_edits(app: &App, out: &mut String) -> Result<()> {
+    let map = &app.primary.map;
+    let bounds = map.get_bounds();
+    let scale = MAP_WIDTH / bounds.width();
+    let height = bounds.height() * scale;
+    let to_svg = |pt: Pt2D| {
+        (
+            (pt.x() - bounds.min_x) * scale,
+            (pt.y() - bounds.min_y) * scale,
+        )
+    };
+
+    let (_, changed_roads) = map.get_edits().changed_lanes(map);
+    let changed_intersections = &map.get_edits().original_intersections;
+
+    writeln!(
+        out,
+        "<svg width=\"{}\" height=\"{:.0}\" viewBox=\"0 0 {} {:.0}\" \
+         xmlns=\"http://www.w3.org/2000/svg\">",
+        MAP_WIDTH, height, MAP_WIDTH, height
+    )?;
+    for r in map.all_roads() {
+        let color = if changed_roads.contains(&r.id) {
+            "#e03030"
+        } else {
+            "#bbbbbb"
+        };
+        svg_polygon(&r.get_thick_polygon(), color, &to_svg, out)?;
+    }
+    for i in map.all_intersections() {
+        let color = if changed_intersections.contains_key(&i.id) {
+            "#e03030"
+        } else {
+            "#bbbbbb"
+        };
+        svg_polygon(&i.polygon, color, &to_svg, out)?;
+    }
+    writeln!(out, "</svg>")?;
+    Ok(())
+}
+
+fn svg_polygon<F: Fn(Pt2D) -> (f64, f64)>(
+    polygon: &Polygon,
+    color: &str,
+    to_svg: &F,
+    out: &mut String,
+) -> Result<()> {
+    let mut d = String::new();
+    for (idx, pt) in polygon.get_outer_ring().points().iter().enumerate() {
+        let (x, y) = to_svg(*pt);
+        write!(d, "{}{:.1} {:.1} ", if idx == 0 { "M" } else { "L" }, x, y)?;
+    }
+    writeln!(out, "<path d=\"{}Z\" fill=\"{}\"/>", d, color)?;
+    Ok(())
+}
+
+struct ModeSummary {
+    finished: usize,
+    cancelled: usize,
+    total_time: Duration,
+}
+
+fn summarize(analytics: &Analytics, now: Time) -> BTreeMap<TripMode, ModeSummary> {
+    let mut per_mode: BTreeMap<TripMode, ModeSummary> = BTreeMap::new();
+    for (t, _, mode, maybe_dt) in &analytics.finished_trips {
+        if *t > now {
+            break;
+        }
+        let summary = per_mode.entry(*mode).or_insert_with(|| ModeSummary {
+            finished: 0,
+            cancelled: 0,
+            total_time: Duration::ZERO,
+        });
+        if let Some(dt) = maybe_dt {
+            summary.finished += 1;
+            summary.total_time += *dt;
+        } else {
+            summary.cancelled += 1;
+        }
+    }
+    per_mode
+}
+
+fn metrics_table(
+    after: &Analytics,
+    before: Option<&Analytics>,
+    now: Time,
+    out: &mut String,
+) -> Result<()> {
+    let mut rows: Vec<(String, f64, Option<f64>)> = Vec::new();
+
+    let summary_after = summarize(after, now);
+    let summary_before = before.map(|a| summarize(a, now));
+    for mode in TripMode::all() {
+        let get = |summary: &BTreeMap<TripMode, ModeSummary>, f: &dyn Fn(&ModeSummary) -> f64| {
+            summary.get(&mode).map(f).unwrap_or(0.0)
+        };
+        let finished = |s: &ModeSummary| s.finished as f64;
+        let cancelled = |s: &ModeSummary| s.cancelled as f64;
+        let mean_minutes = |s: &ModeSummary| {
+            if s.finished == 0 {
+                0.0
+            } else {
+                s.total_time.inner_seconds() / 60.0 / (s.finished as f64)
+            }
+        };
+        if get(&summary_after, &finished) == 0.0
+            && summary_before
+                .as_ref()
+                .map(|s| get(s, &finished) == 0.0)
+                .unwrap_or(true)
+        {
+            continue;
+        }
+        let noun = mode.noun().to_lowercase();
+        rows.push((
+            format!("Finished {} trips", noun),
+            get(&summary_after, &finished),
+            summary_before.as_ref().map(|s| get(s, &finished)),
+        ));
+        rows.push((
+            format!("Cancelled {} trips", noun),
+            get(&summary_after, &cancelled),
+            summary_before.as_ref().map(|s| get(s, &cancelled)),
+        ));
+        rows.push((
+            format!("Average {} trip, minutes", noun),
+            get(&summary_after, &mean_minutes),
+            summary_before.as_ref().map(|s| get(s, &mean_minutes)),
+        ));
+    }
+
+    let count_problems = |analytics: &Analytics, problem_type: ProblemType| -> f64 {
+        analytics
+            .problems_per_trip
+            .values()
+            .map(|problems| problem_type.count(problems))
+            .sum::<usize>() as f64
+    };
+    for problem_type in ProblemType::all() {
+        rows.push((
+            format!("Trips' {}", problem_type.name()),
+            count_problems(after, problem_type),
+            before.map(|a| count_problems(a, problem_type)),
+        ));
+    }
+
+    let model = EnergyModel::default_model();
+    let fleet = FleetMix::default_mix();
+    let fuel = |a: &Analytics| {
+        model
+            .finished_trips(a, now)
+            .iter()
+            .map(|(_, _, _, e)| e.fuel_liters())
+            .sum::<f64>()
+    };
+    let kcal = |a: &Analytics| {
+        model
+            .finished_trips(a, now)
+            .iter()
+            .map(|(_, _, _, e)| e.kcal())
+            .sum::<f64>()
+    };
+    let co2 = |a: &Analytics| fleet.total(a, now).co2_kg();
+    rows.push((
+        "Fuel burned, liters".to_string(),
+        fuel(after),
+        before.map(fuel),
+    ));
+    rows.push(("CO2 emitted, kg".to_string(), co2(after), before.map(co2)));
+    rows.push((
+        "Calories burned walking and cycling, kcal".to_string(),
+        kcal(after),
+        before.map(kcal),
+    ));
+
+    writeln!(out, "<table>")?;
+    if before.is_some() {
+        writeln!(
+            out,
+            "<tr><th></th><th>Before</th><th>After</th><th>Change</th></tr>"
+        )?;
+    } else {
+        writeln!(out, "<tr><th></th><th>After</th></tr>")?;
+    }
+    for (name, after, maybe_before) in rows {
+        if let Some(before) = maybe_before {
+            let change = if before == 0.0 {
+                String::new()
+            } else {
+                format!("{:+.1}%", 100.0 * (after - before) / before)
+            };
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&name),
+                fmt_value(before),
+                fmt_value(after),
+                change
+            )?;
+        } else {
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                escape(&name),
+                fmt_value(after)
+            )?;
+        }
+    }
+    writeln!(out, "</table>")?;
+
+    Ok(())
+}
+
+/// A histogram of how much faster or slower each trip got
+fn travel_time_changes(
+    after: &Analytics,
+    before: &Analytics,
+    now: Time,
+    out: &mut String,
+) -> Result<()> {
+    let mut counts = vec![0; BUCKETS.len()];
+    let mut saved = Duration::ZERO;
+    let mut lost = Duration::ZERO;
+    for (_, dt_before, dt_after, _) in after.both_finished_trips(now, before) {
+        let minutes = (dt_after - dt_before).inner_seconds() / 60.0;
+        let idx = BUCKETS.iter().position(|max| minutes < *max).unwrap();
+        counts[idx] += 1;
+        if dt_after < dt_before {
+            saved += dt_before - dt_after;
+        } else {
+            lost += dt_after - dt_before;
+        }
+    }
+    writeln!(
+        out,
+        "<p>Altogether, faster trips saved {:.0} hours, and slower trips lost {:.0} hours.</p>",
+        saved.inner_seconds() / 3600.0,
+        lost.inner_seconds() / 3600.0
+    )?;
+
+    let labels = [
+        "10+ min faster",
+        "5-10 min faster",
+        "1-5 min faster",
+        "about the same",
+        "1-5 min slower",
+        "5-10 min slower",
+        "10+ min slower",
+    ];
+    let max = counts.iter().max().cloned().unwrap_or(0).max(1) as f64;
+    let bar_width = CHART_WIDTH / (counts.len() as f64);
+    writeln!(
+        out,
+        "<svg width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" \
+         xmlns=\"http://www.w3.org/2000/svg\" font-size=\"11\">",
+        CHART_WIDTH,
+        CHART_HEIGHT + 40.0
+    )?;
+    for (idx, count) in counts.into_iter().enumerate() {
+        let height = CHART_HEIGHT * (count as f64) / max;
+        let x = (idx as f64) * bar_width;
+        let color = if idx < 3 {
+            "#2a9d4a"
+        } else if idx == 3 {
+            "#999999"
+        } else {
+            "#e03030"
+        };
+        writeln!(
+            out,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>",
+            x + 4.0,
+            CHART_HEIGHT - height,
+            bar_width - 8.0,
+            height,
+            color
+        )?;
+        writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            x + bar_width / 2.0,
+            CHART_HEIGHT + 15.0,
+            labels[idx]
+        )?;
+        writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            x + bar_width / 2.0,
+            CHART_HEIGHT + 30.0,
+            prettyprint_usize(count)
+        )?;
+    }
+    writeln!(out, "</svg>")?;
+    Ok(())
+}
+
+/// Cumulative finished trips per mode, with the baseline dashed
+fn finished_trips_chart(
+    app: &App,
+    after: &Analytics,
+    before: Option<&Analytics>,
+    now: Time,
+    out: &mut String,
+) -> Result<()> {
+    let cumulative = |analytics: &Analytics| {
+        let mut per_mode: BTreeMap<TripMode, Vec<(Time, usize)>> = BTreeMap::new();
+        for (t, _, mode, maybe_dt) in &analytics.finished_trips {
+            if *t > now {
+                break;
+            }
+            if maybe_dt.is_none() {
+                continue;
+            }
+            let pts = per_mode.entry(*mode).or_default();
+            let n = pts.last().map(|(_, n)| *n).unwrap_or(0);
+            pts.push((*t, n + 1));
+        }
+        per_mode
+    };
+    let series_after = cumulative(after);
+    let series_before = before.map(cumulative);
+    let max = series_after
+        .values()
+        .chain(series_before.iter().flat_map(|s| s.values()))
+        .filter_map(|pts| pts.last().map(|(_, n)| *n))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let end = (now - Time::START_OF_DAY).inner_seconds().max(1.0);
+
+    writeln!(
+        out,
+        "<svg width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" \
+         xmlns=\"http://www.w3.org/2000/svg\" font-size=\"11\">",
+        CHART_WIDTH + 140.0,
+        CHART_HEIGHT + 20.0
+    )?;
+    writeln!(
+        out,
+        "<text x=\"0\" y=\"{}\">{}</text><text x=\"{}\" y=\"{}\" \
+         text-anchor=\"end\">{}</text><text x=\"0\" y=\"10\">{}</text>",
+        CHART_HEIGHT + 15.0,
+        Time::START_OF_DAY.ampm_tostring(),
+        CHART_WIDTH,
+        CHART_HEIGHT + 15.0,
+        now.ampm_tostring(),
+        prettyprint_usize(max as usize)
+    )?;
+    let mut legend_y = 20.0;
+    for mode in TripMode::all() {
+        let color = color_for_mode(app, mode).as_hex();
+        let mut drew = false;
+        for (series, dashed) in [(Some(&series_after), false), (series_before.as_ref(), true)] {
+            if let Some(pts) = series.and_then(|s| s.get(&mode)) {
+                let mut d = String::new();
+                for (idx, (t, n)) in pts.iter().enumerate() {
+                    let x = CHART_WIDTH * (*t - Time::START_OF_DAY).inner_seconds() / end;
+                    let y = CHART_HEIGHT * (1.0 - (*n as f64) / max);
+                    write!(d, "{}{:.1} {:.1} ", if idx == 0 { "M" } else { "L" }, x, y)?;
+                }
+                writeln!(
+                    out,
+                    "<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"{}/>",
+                    d,
+                    color,
+                    if dashed {
+                        " stroke-dasharray=\"4 3\" opacity=\"0.6\""
+                    } else {
+                        ""
+                    }
+                )?;
+                drew = true;
+            }
+        }
+        if drew {
+            writeln!(
+                out,
+                "<text x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>",
+                CHART_WIDTH + 10.0,
+                legend_y,
+                color,
+                mode.noun()
+            )?;
+            legend_y += 15.0;
+        }
+    }
+    writeln!(out, "</svg>")?;
+    if before.is_some() {
+        writeln!(
+            out,
+            "<p class=\"notes\">Solid lines are with the edits, dashed lines are the \
+             baseline.</p>"
+        )?;
+    }
+    Ok(())
+}
+
+fn fmt_value(x: f64) -> String {
+    if x.abs() >= 100.0 {
+        prettyprint_usize(x.round() as usize)
+    } else {
+        format!("{:.1}", x)
+    }
+}
+
+fn escape(x: &str) -> String {
+    x.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
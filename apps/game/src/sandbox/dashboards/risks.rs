@@ -77,6 +77,18 @@ impl RiskSummaries {
                             ),
                         ])
                         .section(ctx),
+                        Widget::col(vec![
+                            Line("Slip lane crossings")
+                                .small_heading()
+                                .into_widget(ctx)
+                                .centered_horiz(),
+                            problem_matrix(
+                                ctx,
+                                app,
+                                ped_filter.trip_problems(app, ProblemType::SlipLaneCrossing),
+                            ),
+                        ])
+                        .section(ctx),
                         Widget::col(vec![
                             Line("Overcrowded sidewalks")
                                 .small_heading()
@@ -134,6 +146,18 @@ impl RiskSummaries {
                             ),
                         ])
                         .section(ctx),
+                        Widget::col(vec![
+                            Line("Vehicles turning across cyclists")
+                                .small_heading()
+                                .into_widget(ctx)
+                                .centered_horiz(),
+                            problem_matrix(
+                                ctx,
+                                app,
+                                bike_filter.trip_problems(app, ProblemType::HookConflict),
+                            ),
+                        ])
+                        .section(ctx),
                     ],
                 )
                 .margin_above(30),
@@ -0,0 +1,159 @@
+use std::collections::BTreeSet;
+
+use abstutil::prettyprint_usize;
+use geom::Time;
+use map_model::RoadID;
+use sim::{AgentType, Analytics};
+use widgetry::{
+    EventCtx, GfxCtx, Line, LinePlot, Outcome, Panel, PlotOptions, Series, State, Text, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::sandbox::dashboards::DashTab;
+
+/// Revenue from HOT lanes and the congestion charge, and how traffic in the cordon changed
+pub struct Tolls {
+    panel: Panel,
+}
+
+impl Tolls {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let now = app.primary.sim.time();
+        let analytics = app.primary.sim.get_analytics();
+        let baseline = app.has_prebaked().map(|_| app.prebaked());
+
+        let mut txt = Text::new();
+        let (revenue, payments) = total_revenue(analytics, now);
+        txt.add_line(format!(
+            "{:.2} collected from {} tolls so far",
+            revenue,
+            prettyprint_usize(payments)
+        ));
+        if let Some(baseline) = baseline {
+            txt.add_line(format!(
+                "{:.2} by this time before \"{}\"",
+                total_revenue(baseline, now).0,
+                app.primary.map.get_edits().edits_name
+            ));
+        }
+
+        if let Some(charge) = app.primary.map.get_congestion_charge() {
+            txt.add_line("");
+            txt.add_line(Line("Congestion charge").small_heading());
+            txt.add_line(format!(
+                "Entering the cordon of {} roads costs {:.2} right now, and {:.2} at peak",
+                prettyprint_usize(charge.roads.len()),
+                charge.toll_at(now),
+                charge.peak_toll()
+            ));
+            let after = cordon_traffic(analytics, &charge.roads, now);
+            let before = baseline.map(|baseline| cordon_traffic(baseline, &charge.roads, now));
+            for (idx, agent_type) in AgentType::all().into_iter().enumerate() {
+                let mut line = format!(
+                    "{} {} on roads in the cordon",
+                    prettyprint_usize(after[idx]),
+                    agent_type.plural_noun()
+                );
+                if let Some(ref before) = before {
+                    line.push_str(&format!(" ({} before)", prettyprint_usize(before[idx])));
+                }
+                txt.add_line(line);
+            }
+        }
+
+        let mut series = vec![Series {
+            label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
+            color: app.cs.after_changes,
+            pts: cumulative_revenue(analytics, now),
+        }];
+        if let Some(baseline) = baseline {
+            series.push(Series {
+                label: format!("Before \"{}\"", app.primary.map.get_edits().edits_name),
+                color: app.cs.before_changes.alpha(0.5),
+                pts: cumulative_revenue(baseline, now),
+            });
+        }
+
+        Box::new(Tolls {
+            panel: Panel::new_builder(Widget::col(vec![
+                DashTab::Tolls.picker(ctx, app),
+                txt.into_widget(ctx).section(ctx),
+                Line("Total revenue").small_heading().into_widget(ctx),
+                LinePlot::new_widget(
+                    ctx,
+                    "toll revenue",
+                    series,
+                    PlotOptions::fixed(),
+                    app.opts.units,
+                )
+                .section(ctx),
+            ]))
+            .exact_size_percent(90, 90)
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for Tolls {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => DashTab::Tolls.transition(ctx, app, &self.panel).unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
+
+/// (revenue, number of payments) up to some time
+fn total_revenue(analytics: &Analytics, until: Time) -> (f64, usize) {
+    let mut revenue = 0.0;
+    let mut payments = 0;
+    for (t, _, _, toll) in &analytics.tolls_paid {
+        if *t > until {
+            break;
+        }
+        revenue += *toll;
+        payments += 1;
+    }
+    (revenue, payments)
+}
+
+// The plots can only show whole numbers
+fn cumulative_revenue(analytics: &Analytics, until: Time) -> Vec<(Time, usize)> {
+    let mut pts = vec![(Time::START_OF_DAY, 0)];
+    let mut revenue = 0.0;
+    for (t, _, _, toll) in &analytics.tolls_paid {
+        if *t > until {
+            break;
+        }
+        revenue += *toll;
+        pts.push((*t, revenue.round() as usize));
+    }
+    pts.push((until, revenue.round() as usize));
+    pts
+}
+
+/// How many agents of each type, in the order of `AgentType::all`, crossed roads in the cordon
+fn cordon_traffic(analytics: &Analytics, roads: &BTreeSet<RoadID>, until: Time) -> Vec<usize> {
+    AgentType::all()
+        .into_iter()
+        .map(|agent_type| {
+            analytics
+                .road_thruput
+                .counts
+                .iter()
+                .filter(|((r, a, hour), _)| {
+                    *a == agent_type && *hour <= until.get_hours() && roads.contains(r)
+                })
+                .map(|(_, cnt)| *cnt)
+                .sum()
+        })
+        .collect()
+}
@@ -14,6 +14,7 @@ use widgetry::{
     Panel, State, Text, TextExt, Toggle, Widget,
 };
 
+use super::report::generate_report;
 use super::trip_problems::{problem_matrix, TripProblemFilter};
 use crate::app::{App, Transition};
 use crate::sandbox::dashboards::generic_trip_table::open_trip_transition;
@@ -42,11 +43,11 @@ impl TravelTimes {
         }
 
         filters.push(
-            ctx.style()
-                .btn_plain
-                .text("Export to CSV")
-                .build_def(ctx)
-                .align_bottom(),
+            Widget::col(vec![
+                ctx.style().btn_plain.text("Export to CSV").build_def(ctx),
+                ctx.style().btn_plain.text("Generate report").build_def(ctx),
+            ])
+            .align_bottom(),
         );
 
         Panel::new_builder(Widget::col(vec![
@@ -125,6 +126,18 @@ impl State<App> for TravelTimes {
                         }
                     });
                 }
+                "Generate report" => {
+                    return Transition::Push(match generate_report(app) {
+                        Ok(path) => PopupMsg::new_state(
+                            ctx,
+                            "Report generated",
+                            vec![format!("Report written to {}", path)],
+                        ),
+                        Err(err) => {
+                            PopupMsg::new_state(ctx, "Report failed", vec![err.to_string()])
+                        }
+                    });
+                }
                 "close" => Transition::Pop,
                 _ => unreachable!(),
             },
@@ -42,8 +42,8 @@ pub enum GameplayMode {
     // Map name, scenario name, background traffic
     Actdev(MapName, String, bool),
 
-    // current
-    Tutorial(TutorialPointer),
+    // Map name, current
+    Tutorial(MapName, TutorialPointer),
 }
 
 pub trait GameplayState: downcast_rs::Downcast {
@@ -98,7 +98,7 @@ impl GameplayMode {
             GameplayMode::PlayScenario(ref name, _, _) => name.clone(),
             GameplayMode::FixTrafficSignals => MapName::seattle("downtown"),
             GameplayMode::OptimizeCommute(_, _) => MapName::seattle("montlake"),
-            GameplayMode::Tutorial(_) => MapName::seattle("montlake"),
+            GameplayMode::Tutorial(ref name, _) => name.clone(),
             GameplayMode::Actdev(ref name, _, _) => name.clone(),
         }
     }
@@ -112,7 +112,7 @@ impl GameplayMode {
                 return LoadScenario::Scenario(s);
             }
             GameplayMode::PlayScenario(_, ref scenario, _) => scenario.to_string(),
-            GameplayMode::Tutorial(current) => {
+            GameplayMode::Tutorial(_, current) => {
                 return match Tutorial::scenario(app, *current) {
                     Some(generator) => {
                         LoadScenario::Scenario(generator.generate(map, &mut rng, timer))
@@ -177,7 +177,10 @@ impl GameplayMode {
     pub fn allows(&self, edits: &MapEdits) -> bool {
         for cmd in &edits.commands {
             match cmd {
-                EditCmd::ChangeRoad { .. } => {
+                EditCmd::ChangeRoad { .. }
+                | EditCmd::ChangeCongestionCharge { .. }
+                | EditCmd::ChangeParkingPricing { .. }
+                | EditCmd::ChangeLoadingZones { .. } => {
                     if !self.can_edit_roads() {
                         return false;
                     }
@@ -198,8 +201,20 @@ impl GameplayMode {
                     if old.crosswalks != new.crosswalks && !self.can_edit_stop_signs() {
                         return false;
                     }
+                    // Permitting U-turns changes the turns available, like a lane edit would
+                    if old.allow_u_turns != new.allow_u_turns && !self.can_edit_roads() {
+                        return false;
+                    }
+                    // So does rebuilding the corners for protected bike crossings
+                    if old.protected_corners != new.protected_corners && !self.can_edit_roads() {
+                        return false;
+                    }
                 }
-                EditCmd::ChangeRouteSchedule { .. } => {}
+                EditCmd::ChangeRouteSchedule { .. }
+                | EditCmd::ChangeRouteBoarding { .. }
+                | EditCmd::ChangeStopBoarding { .. }
+                | EditCmd::ChangeRouteStops { .. }
+                | EditCmd::ChangeStopClosed { .. } => {}
             }
         }
         true
@@ -219,7 +234,7 @@ impl GameplayMode {
             GameplayMode::OptimizeCommute(p, goal) => {
                 commute::OptimizeCommute::new_state(ctx, app, *p, *goal)
             }
-            GameplayMode::Tutorial(current) => Tutorial::make_gameplay(ctx, app, *current),
+            GameplayMode::Tutorial(_, current) => Tutorial::make_gameplay(ctx, app, *current),
             GameplayMode::Actdev(_, ref scenario, bg_traffic) => {
                 actdev::Actdev::new_state(ctx, scenario.clone(), *bg_traffic)
             }
@@ -5,12 +5,13 @@ use maplit::btreeset;
 use abstutil::prettyprint_usize;
 use geom::{Duration, Time};
 use map_gui::tools::{checkbox_per_mode, grey_out_map, CityPicker};
+use map_model::AmenityType;
 use sim::SlidingWindow;
-use synthpop::{ScenarioModifier, TripMode};
+use synthpop::{ScenarioModifier, TripMode, WorkplaceFilter};
 use widgetry::tools::{ChooseSomething, PopupMsg, URLManager};
 use widgetry::{
     lctrl, Choice, Color, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, LinePlot, Outcome,
-    Panel, PlotOptions, Series, SimpleState, Slider, Spinner, State, Text, TextExt,
+    Panel, PlotOptions, Series, SimpleState, Slider, Spinner, State, Text, TextExt, Toggle,
     VerticalAlignment, Widget,
 };
 
@@ -258,6 +259,26 @@ impl EditScenarioModifiers {
                 .text("Add extra new trips")
                 .build_def(ctx),
         );
+        rows.push(
+            ctx.style()
+                .btn_outline
+                .text("Work from home")
+                .build_def(ctx),
+        );
+        rows.push(Widget::row(vec![
+            Spinner::widget(ctx, "pct_avoid_charge", (1, 100), 20_usize, 1),
+            ctx.style()
+                .btn_outline
+                .text("% of drivers use transit to avoid the congestion charge")
+                .build_def(ctx),
+        ]));
+        rows.push(Widget::row(vec![
+            Spinner::widget(ctx, "pct_ebikes", (1, 100), 20_usize, 1),
+            ctx.style()
+                .btn_outline
+                .text("% of bikes are electric")
+                .build_def(ctx),
+        ]));
         rows.push(Widget::row(vec![
             Spinner::widget(ctx, "repeat_days", (2, 14), 2, 1),
             ctx.style()
@@ -333,6 +354,14 @@ impl State<App> for EditScenarioModifiers {
                         self.modifiers.clone(),
                     ));
                 }
+                "Work from home" => {
+                    return Transition::Push(WorkFromHome::new_state(
+                        ctx,
+                        app,
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                    ));
+                }
                 "Add extra new trips" => {
                     return Transition::Push(ChooseSomething::new_state(
                         ctx,
@@ -368,6 +397,28 @@ impl State<App> for EditScenarioModifiers {
                         self.modifiers.clone(),
                     ));
                 }
+                "% of drivers use transit to avoid the congestion charge" => {
+                    self.modifiers
+                        .push(ScenarioModifier::AvoidCongestionCharge {
+                            pct_ppl: self.panel.spinner("pct_avoid_charge"),
+                            to_mode: TripMode::Transit,
+                        });
+                    return Transition::Replace(EditScenarioModifiers::new_state(
+                        ctx,
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                    ));
+                }
+                "% of bikes are electric" => {
+                    self.modifiers.push(ScenarioModifier::ElectricBikes {
+                        pct_bikes: self.panel.spinner("pct_ebikes"),
+                    });
+                    return Transition::Replace(EditScenarioModifiers::new_state(
+                        ctx,
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                    ));
+                }
                 "Repeat schedule multiple days with +/- 10 minutes of noise" => {
                     self.modifiers.push(ScenarioModifier::RepeatDaysNoise {
                         days: self.panel.spinner("repeat_days_noise"),
@@ -570,6 +621,107 @@ impl State<App> for ChangeMode {
     }
 }
 
+struct WorkFromHome {
+    panel: Panel,
+    scenario_name: String,
+    modifiers: Vec<ScenarioModifier>,
+}
+
+impl WorkFromHome {
+    fn new_state(
+        ctx: &mut EventCtx,
+        app: &App,
+        scenario_name: String,
+        modifiers: Vec<ScenarioModifier>,
+    ) -> Box<dyn State<App>> {
+        let mut sectors = vec![Choice::new("anywhere", None)];
+        for t in app.primary.map.get_available_amenity_types() {
+            sectors.push(Choice::new(t.to_string(), Some(t)));
+        }
+        Box::new(WorkFromHome {
+            scenario_name,
+            modifiers,
+            panel: Panel::new_builder(Widget::col(vec![
+                Line("Work from home").small_heading().into_widget(ctx),
+                Widget::row(vec![
+                    "Percent of commuters who stay home:"
+                        .text_widget(ctx)
+                        .centered_vert(),
+                    Spinner::widget(ctx, "pct_ppl", (1, 100), 30_usize, 1),
+                ]),
+                Widget::row(vec![
+                    "Working at:".text_widget(ctx).centered_vert(),
+                    Widget::dropdown(ctx, "sector", None::<AmenityType>, sectors),
+                ]),
+                Toggle::checkbox(
+                    ctx,
+                    "cancel the trips at the last minute, instead of not planning them",
+                    None,
+                    false,
+                ),
+                Widget::row(vec![
+                    ctx.style()
+                        .btn_solid_primary
+                        .text("Apply")
+                        .hotkey(Key::Enter)
+                        .build_def(ctx),
+                    ctx.style()
+                        .btn_solid_destructive
+                        .text("Discard changes")
+                        .hotkey(Key::Escape)
+                        .build_def(ctx),
+                ])
+                .centered(),
+            ]))
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for WorkFromHome {
+    fn event(&mut self, ctx: &mut EventCtx, _: &mut App) -> Transition {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "Discard changes" => {
+                    return Transition::Pop;
+                }
+                "Apply" => {
+                    let workplaces = match self
+                        .panel
+                        .dropdown_value::<Option<AmenityType>, _>("sector")
+                    {
+                        Some(t) => WorkplaceFilter::Sectors(btreeset! { t }),
+                        None => WorkplaceFilter::Anywhere,
+                    };
+                    let mut mods = self.modifiers.clone();
+                    mods.push(ScenarioModifier::WorkFromHome {
+                        pct_ppl: self.panel.spinner("pct_ppl"),
+                        workplaces,
+                        cancel: self.panel.is_checked(
+                            "cancel the trips at the last minute, instead of not planning them",
+                        ),
+                    });
+                    return Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::Replace(EditScenarioModifiers::new_state(
+                            ctx,
+                            self.scenario_name.clone(),
+                            mods,
+                        )),
+                    ]);
+                }
+                _ => unreachable!(),
+            }
+        }
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
+
 pub struct DepartureSummary {
     first_trip: Time,
 }
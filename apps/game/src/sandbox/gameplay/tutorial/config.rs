@@ -0,0 +1,273 @@
+//! The tutorial's text and the places it visits are data, not code. Places are found by querying
+//! the loaded map, so the tutorial works in any imported city, and the text can be translated
+//! without touching the code. Each stage's task is still checked by the code.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use abstutil::Timer;
+use geom::{Duration, Pt2D};
+use map_model::{osm, BuildingID, IntersectionID, LaneType, Map, OriginalRoad, RoadID};
+use widgetry::{EventCtx, Key, Line, Text};
+
+use super::Task;
+use crate::ID;
+
+/// Every stage of the tutorial, in order, and where on the map they happen
+#[derive(Serialize, Deserialize)]
+pub struct TutorialConfig {
+    pub stages: Vec<StageConfig>,
+    /// For each place, the queries to try in order. The first one matching something on the
+    /// loaded map wins, so list specific OSM objects first and generic fallbacks last.
+    pub places: BTreeMap<Place, Vec<FeatureQuery>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StageConfig {
+    pub task: Task,
+    /// Describes the task, like "Inspect objects"
+    pub title: String,
+    /// Move the camera here when the stage starts
+    #[serde(default)]
+    pub warp_to: Option<Place>,
+    /// Defaults to 4.0
+    #[serde(default)]
+    pub zoom: Option<f64>,
+    /// Shown before the task starts
+    pub messages: Vec<MessageConfig>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MessageConfig {
+    /// Some words in braces are replaced: `{Space}`, `{LeftArrow}`, and `{RightArrow}` become
+    /// hotkeys, and `{goal}` becomes how much the fix-the-bike-lane stage has to speed up trips.
+    pub lines: Vec<String>,
+    #[serde(default)]
+    pub arrow: Option<ArrowTarget>,
+    /// The path to an SVG shown above the text
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub left_aligned: bool,
+}
+
+/// Something on the screen a message can point at
+#[derive(Serialize, Deserialize)]
+pub enum ArrowTarget {
+    /// A button in the tool panel, like "settings"
+    ToolPanel(String),
+    /// A button in the time controls, like "pause"
+    TimePanel(String),
+    WholeTimePanel,
+    /// A button by the minimap, like "change layers"
+    Minimap(String),
+    /// A button in the tutorial's own panel, like "edit map"
+    TutorialPanel(String),
+    /// The car the player has to follow
+    EscortCar,
+}
+
+/// The places the tutorial needs to find on the map
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Place {
+    /// Where the camera starts
+    Start,
+    /// The building the player has to find first
+    FireStation,
+    /// Where to look while following the car
+    EscortView,
+    /// A road where the car to follow appears
+    EscortStart,
+    /// A building the car to follow drives to. Its parking fills up first.
+    EscortGoal,
+    /// An intersection where some extra traffic appears while following the car
+    Traffic,
+    /// A border intersection where cars and bikes enter to compete for the same lane
+    BikeLaneBorder,
+    /// A building the cars and bikes go to
+    BikeLaneGoal,
+    /// Where to look while the cars and bikes compete
+    BikeLaneView,
+}
+
+/// A way of finding something on the map
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum FeatureQuery {
+    /// A building from an OSM way
+    OsmBuilding(i64),
+    /// An intersection from an OSM node
+    OsmIntersection(i64),
+    /// A road from the segment of an OSM way between two nodes
+    OsmRoad { way: i64, i1: i64, i2: i64 },
+    /// The building tagged with this amenity, like "fire_station", closest to the map's center
+    Amenity(String),
+    /// The building closest to the map's center that cars can reach
+    CentralBuilding,
+    /// The intersection closest to the map's center, not counting borders
+    CentralIntersection,
+    /// The road with a driving lane closest to the map's center
+    CentralRoad,
+    /// The border intersection closest to the map's center where cars can enter
+    CentralBorder,
+}
+
+impl TutorialConfig {
+    /// Uses a translation to the player's language, if there is one
+    pub fn load(language: Option<&String>) -> TutorialConfig {
+        if let Some(lang) = language {
+            match abstio::maybe_read_json(abstio::path_tutorial(lang), &mut Timer::throwaway()) {
+                Ok(config) => {
+                    return config;
+                }
+                Err(err) => {
+                    warn!("No tutorial in {}, using English: {}", lang, err);
+                }
+            }
+        }
+        TutorialConfig::english()
+    }
+
+    /// The tutorial bundled with the game
+    pub fn english() -> TutorialConfig {
+        serde_json::from_str(include_str!("en.json")).unwrap()
+    }
+
+    pub fn find(&self, map: &Map, place: Place) -> Result<ID> {
+        for query in self.places.get(&place).into_iter().flatten() {
+            if let Some(id) = query.find(map) {
+                return Ok(id);
+            }
+        }
+        bail!(
+            "Nothing on {} matches the tutorial's {:?}",
+            map.get_name().describe(),
+            place
+        )
+    }
+
+    pub fn find_b(&self, map: &Map, place: Place) -> Result<BuildingID> {
+        match self.find(map, place)? {
+            ID::Building(b) => Ok(b),
+            x => bail!(
+                "The tutorial's {:?} should be a building, not {:?}",
+                place,
+                x
+            ),
+        }
+    }
+
+    pub fn find_i(&self, map: &Map, place: Place) -> Result<IntersectionID> {
+        match self.find(map, place)? {
+            ID::Intersection(i) => Ok(i),
+            x => bail!(
+                "The tutorial's {:?} should be an intersection, not {:?}",
+                place,
+                x
+            ),
+        }
+    }
+
+    pub fn find_r(&self, map: &Map, place: Place) -> Result<RoadID> {
+        match self.find(map, place)? {
+            ID::Road(r) => Ok(r),
+            x => bail!("The tutorial's {:?} should be a road, not {:?}", place, x),
+        }
+    }
+}
+
+impl FeatureQuery {
+    fn find(&self, map: &Map) -> Option<ID> {
+        let center = map.get_bounds().center();
+        match self {
+            FeatureQuery::OsmBuilding(id) => map
+                .find_b_by_osm_id(osm::OsmID::Way(osm::WayID(*id)))
+                .map(ID::Building),
+            FeatureQuery::OsmIntersection(id) => map
+                .find_i_by_osm_id(osm::NodeID(*id))
+                .ok()
+                .map(ID::Intersection),
+            FeatureQuery::OsmRoad { way, i1, i2 } => map
+                .find_r_by_osm_id(OriginalRoad::new(*way, (*i1, *i2)))
+                .ok()
+                .map(ID::Road),
+            FeatureQuery::Amenity(amenity) => closest(
+                center,
+                map.all_buildings()
+                    .iter()
+                    .filter(|b| {
+                        b.osm_tags.is("amenity", amenity)
+                            || b.amenities.iter().any(|a| &a.amenity_type == amenity)
+                    })
+                    .map(|b| (ID::Building(b.id), b.label_center)),
+            ),
+            FeatureQuery::CentralBuilding => closest(
+                center,
+                map.all_buildings()
+                    .iter()
+                    .filter(|b| b.driving_connection(map).is_some())
+                    .map(|b| (ID::Building(b.id), b.label_center)),
+            ),
+            FeatureQuery::CentralIntersection => closest(
+                center,
+                map.all_intersections()
+                    .iter()
+                    .filter(|i| !i.is_border())
+                    .map(|i| (ID::Intersection(i.id), i.polygon.center())),
+            ),
+            FeatureQuery::CentralRoad => closest(
+                center,
+                map.all_roads()
+                    .iter()
+                    .filter(|r| r.lanes.iter().any(|l| l.lane_type == LaneType::Driving))
+                    .map(|r| (ID::Road(r.id), r.center_pts.middle())),
+            ),
+            FeatureQuery::CentralBorder => closest(
+                center,
+                map.all_intersections()
+                    .iter()
+                    .filter(|i| {
+                        i.is_incoming_border()
+                            && i.roads.iter().any(|r| map.get_r(*r).is_driveable())
+                    })
+                    .map(|i| (ID::Intersection(i.id), i.polygon.center())),
+            ),
+        }
+    }
+}
+
+fn closest(center: Pt2D, candidates: impl Iterator<Item = (ID, Pt2D)>) -> Option<ID> {
+    candidates
+        .min_by_key(|(_, pt)| pt.dist_to(center))
+        .map(|(id, _)| id)
+}
+
+impl MessageConfig {
+    pub fn make_text(&self, ctx: &EventCtx, goal: Duration) -> Text {
+        let hotkey_color = ctx.style().text_hotkey_color;
+        let mut txt = Text::new();
+        for line in &self.lines {
+            txt.add_line("");
+            let mut rest = line.as_str();
+            while let Some(start) = rest.find('{') {
+                let end = match rest[start..].find('}') {
+                    Some(len) => start + len,
+                    None => break,
+                };
+                txt.append(Line(&rest[..start]));
+                let word = &rest[start + 1..end];
+                match word {
+                    "Space" => txt.append(Line(Key::Space.describe()).fg(hotkey_color)),
+                    "LeftArrow" => txt.append(Line(Key::LeftArrow.describe()).fg(hotkey_color)),
+                    "RightArrow" => txt.append(Line(Key::RightArrow.describe()).fg(hotkey_color)),
+                    "goal" => txt.append(Line(goal.to_string())),
+                    _ => txt.append(Line(&rest[start..=end])),
+                }
+                rest = &rest[end + 1..];
+            }
+            txt.append(Line(rest));
+        }
+        txt
+    }
+}
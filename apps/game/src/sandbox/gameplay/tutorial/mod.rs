@@ -1,20 +1,28 @@
 use std::collections::BTreeSet;
 
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
 use crate::ID;
 use abstio::MapName;
 use abstutil::Timer;
 use geom::{ArrowCap, Distance, Duration, PolyLine, Pt2D, Time};
 use map_gui::load::MapLoader;
 use map_gui::tools::Minimap;
-use map_model::{osm, BuildingID, Map, OriginalRoad, Position};
-use sim::{AgentID, BorderSpawnOverTime, CarID, ScenarioGenerator, SpawnOverTime, VehicleType};
+use map_model::{BuildingID, IntersectionID, LaneType, Map, Position, RoadID};
+use sim::{
+    AgentID, Analytics, BorderSpawnOverTime, CarID, ScenarioGenerator, Sim, SpawnOverTime,
+    VehicleType,
+};
 use synthpop::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose};
 use widgetry::tools::PopupMsg;
 use widgetry::{
     hotkeys, lctrl, Color, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Image, Key, Line,
-    Outcome, Panel, ScreenPt, State, Text, TextExt, VerticalAlignment, Widget,
+    Outcome, Panel, State, Text, TextExt, VerticalAlignment, Widget,
 };
 
+use self::config::{ArrowTarget, Place, TutorialConfig};
+
 use crate::app::{App, Transition};
 use crate::challenges::cutscene::CutsceneBuilder;
 use crate::common::{tool_panel, Warping};
@@ -25,6 +33,8 @@ use crate::sandbox::{
     SandboxMode, TimePanel,
 };
 
+mod config;
+
 const ESCORT: CarID = CarID {
     id: 0,
     vehicle_type: VehicleType::Car,
@@ -53,20 +63,28 @@ impl TutorialPointer {
 }
 
 impl Tutorial {
-    /// Launches the tutorial gameplay along with its cutscene
+    /// Launches the tutorial gameplay along with its cutscene, on whatever map is loaded
     pub fn start(ctx: &mut EventCtx, app: &mut App) -> Box<dyn State<App>> {
+        let name = app.primary.map.get_name().clone();
         MapLoader::new_state(
             ctx,
             app,
-            MapName::seattle("montlake"),
+            name,
             Box::new(|ctx, app| {
-                Tutorial::initialize(ctx, app);
+                if let Err(err) = Tutorial::initialize(ctx, app) {
+                    return Transition::Replace(PopupMsg::new_state(
+                        ctx,
+                        "The tutorial can't run on this map",
+                        vec![err.to_string()],
+                    ));
+                }
 
                 Transition::Multi(vec![
                     Transition::Pop,
                     Transition::Push(SandboxMode::simple_new(
                         app,
                         GameplayMode::Tutorial(
+                            app.primary.map.get_name().clone(),
                             app.session
                                 .tutorial
                                 .as_ref()
@@ -80,12 +98,13 @@ impl Tutorial {
         )
     }
 
-    /// Idempotent. This must be called before `make_gameplay` or `scenario`. The current map must
-    /// be montlake.
-    pub fn initialize(ctx: &mut EventCtx, app: &mut App) {
-        if app.session.tutorial.is_none() {
-            app.session.tutorial = Some(TutorialState::new(ctx, app));
+    /// Idempotent while the same map is loaded. This must be called before `make_gameplay` or
+    /// `scenario`. Fails if the tutorial can't find some place it needs on the current map.
+    pub fn initialize(ctx: &mut EventCtx, app: &mut App) -> Result<()> {
+        if app.session.tutorial.as_ref().map(|tut| &tut.map) != Some(app.primary.map.get_name()) {
+            app.session.tutorial = Some(TutorialState::new(ctx, app)?);
         }
+        Ok(())
     }
 
     pub fn make_gameplay(
@@ -147,7 +166,7 @@ impl Tutorial {
                 "edit map" => {
                     // TODO Ideally this would be an inactive button in message states
                     if self.msg_panel.is_none() {
-                        let mode = GameplayMode::Tutorial(tut.current);
+                        let mode = GameplayMode::Tutorial(tut.map.clone(), tut.current);
                         return Some(Transition::Push(EditMode::new_state(ctx, app, mode)));
                     }
                 }
@@ -275,6 +294,14 @@ impl Tutorial {
             }
         } else if tut.interaction() == Task::FixBikes {
             if app.primary.sim.is_done() {
+                if app.has_prebaked().is_none() {
+                    // Only some maps have prebaked results, so simulate the same trips without the
+                    // player's edits to compare against
+                    let baseline = ctx.loading_screen("simulate without your edits", |_, timer| {
+                        simulate_without_edits(app, tut, timer)
+                    });
+                    app.set_prebaked(Some(baseline));
+                }
                 let mut before = Duration::ZERO;
                 let mut after = Duration::ZERO;
                 for (_, b, a, _) in app
@@ -366,7 +393,8 @@ impl GameplayState for Tutorial {
         // The arrows get screwy when window size changes.
         let window_dims = (ctx.canvas.window_width, ctx.canvas.window_height);
         if window_dims != tut.window_dims {
-            tut.stages = TutorialState::new(ctx, app).stages;
+            // The map and config haven't changed, so this worked before
+            tut.stages = TutorialState::new(ctx, app).unwrap().stages;
             tut.window_dims = window_dims;
         }
 
@@ -459,8 +487,8 @@ impl GameplayState for Tutorial {
     }
 }
 
-#[derive(PartialEq, PartialOrd, Clone, Copy)]
-enum Task {
+#[derive(PartialEq, PartialOrd, Clone, Copy, Serialize, Deserialize)]
+pub enum Task {
     Nil,
     Camera,
     InspectObjects,
@@ -544,26 +572,12 @@ impl Task {
         };
         Text::from(simple)
     }
-
-    fn label(self) -> &'static str {
-        match self {
-            Task::Nil => unreachable!(),
-            Task::Camera => "Moving the drone",
-            Task::InspectObjects => "Interacting with objects",
-            Task::TimeControls => "Passing the time",
-            Task::PauseResume => "Pausing/resuming",
-            Task::Escort => "Following people",
-            Task::LowParking => "Exploring map layers",
-            Task::WatchBikes => "Observing a problem",
-            Task::FixBikes => "Editing lanes",
-            Task::Done => "Tutorial complete!",
-        }
-    }
 }
 
 struct Stage {
     messages: Vec<Message>,
     task: Task,
+    title: String,
     warp_to: Option<(ID, f64)>,
     custom_spawn: Option<Box<dyn Fn(&mut App)>>,
     make_scenario: Option<ScenarioGenerator>,
@@ -573,45 +587,15 @@ struct Message {
     txt: Text,
     aligned: HorizontalAlignment,
     arrow: Option<Box<dyn Fn(&GfxCtx, &App) -> Pt2D>>,
-    icon: Option<&'static str>,
-}
-
-impl Message {
-    fn new(txt: Text) -> Message {
-        Message {
-            txt,
-            aligned: HorizontalAlignment::Center,
-            arrow: None,
-            icon: None,
-        }
-    }
-
-    fn arrow(mut self, pt: ScreenPt) -> Message {
-        self.arrow = Some(Box::new(move |_, _| pt.to_pt()));
-        self
-    }
-
-    fn dynamic_arrow(mut self, arrow: Box<dyn Fn(&GfxCtx, &App) -> Pt2D>) -> Message {
-        self.arrow = Some(arrow);
-        self
-    }
-
-    fn icon(mut self, path: &'static str) -> Message {
-        self.icon = Some(path);
-        self
-    }
-
-    fn left_aligned(mut self) -> Message {
-        self.aligned = HorizontalAlignment::Left;
-        self
-    }
+    icon: Option<String>,
 }
 
 impl Stage {
-    fn new(task: Task) -> Stage {
+    fn new(task: Task, title: String) -> Stage {
         Stage {
             messages: Vec::new(),
             task,
+            title,
             warp_to: None,
             custom_spawn: None,
             make_scenario: None,
@@ -645,6 +629,7 @@ impl Stage {
 pub struct TutorialState {
     stages: Vec<Stage>,
     pub current: TutorialPointer,
+    map: MapName,
 
     window_dims: (f64, f64),
 
@@ -668,7 +653,41 @@ pub struct TutorialState {
     fire_station: BuildingID,
 }
 
-fn make_bike_lane_scenario(map: &Map) -> ScenarioGenerator {
+/// Runs the current stage's scenario on the unedited map, returning the scenario name and results
+fn simulate_without_edits(
+    app: &App,
+    tut: &TutorialState,
+    timer: &mut Timer,
+) -> (MapName, String, Analytics) {
+    let map = app
+        .primary
+        .unedited_map
+        .as_ref()
+        .or_else(|| app.secondary.as_ref().map(|secondary| &secondary.map))
+        .unwrap_or(&app.primary.map);
+    let flags = &app.primary.current_flags.sim_flags;
+    let scenario =
+        tut.stage()
+            .make_scenario
+            .clone()
+            .unwrap()
+            .generate(map, &mut flags.make_rng(), timer);
+    let mut sim = Sim::new(map, flags.opts.clone());
+    sim.instantiate(&scenario, map, &mut flags.make_rng(), timer);
+    sim.timed_step(
+        map,
+        sim.get_end_of_day() - Time::START_OF_DAY,
+        &mut None,
+        timer,
+    );
+    (
+        tut.map.clone(),
+        scenario.scenario_name,
+        sim.get_analytics().clone(),
+    )
+}
+
+fn make_bike_lane_scenario(config: &TutorialConfig, map: &Map) -> Result<ScenarioGenerator> {
     let mut s = ScenarioGenerator::empty("car vs bike contention");
     s.border_spawn_over_time.push(BorderSpawnOverTime {
         num_peds: 0,
@@ -677,17 +696,97 @@ fn make_bike_lane_scenario(map: &Map) -> ScenarioGenerator {
         percent_use_transit: 0.0,
         start_time: Time::START_OF_DAY,
         stop_time: Time::START_OF_DAY + Duration::seconds(10.0),
-        start_from_border: map.find_i_by_osm_id(osm::NodeID(3005680098)).unwrap(),
+        start_from_border: config.find_i(map, Place::BikeLaneBorder)?,
         goal: Some(TripEndpoint::Building(
-            map.find_b_by_osm_id(bldg(217699501)).unwrap(),
+            config.find_b(map, Place::BikeLaneGoal)?,
         )),
     });
-    s
+    Ok(s)
+}
+
+fn low_parking_scenario() -> ScenarioGenerator {
+    // TODO Actually, we ideally just want a bunch of parked cars, not all these trips
+    ScenarioGenerator {
+        scenario_name: "low parking".to_string(),
+        only_seed_buses: Some(BTreeSet::new()),
+        spawn_over_time: vec![SpawnOverTime {
+            num_agents: 1000,
+            start_time: Time::START_OF_DAY,
+            stop_time: Time::START_OF_DAY + Duration::hours(3),
+            goal: None,
+            percent_driving: 1.0,
+            percent_biking: 0.0,
+            percent_use_transit: 0.0,
+        }],
+        border_spawn_over_time: Vec::new(),
+    }
+}
+
+// Seed a specific target car, and fill up the target building's private parking to force the
+// target to park on-street.
+fn spawn_escort(app: &mut App, start: RoadID, goal_bldg: BuildingID, traffic: IntersectionID) {
+    let map = &app.primary.map;
+    let start_lane = map
+        .get_r(start)
+        .lanes
+        .iter()
+        .find(|l| l.lane_type == LaneType::Driving)
+        .unwrap()
+        .id;
+    let spawn_by_goal_bldg = {
+        let pos = map.get_b(goal_bldg).driving_connection(map).unwrap().0;
+        Position::new(pos.lane(), Distance::ZERO)
+    };
+
+    let mut scenario = Scenario::empty(map, "prank");
+    scenario.people.push(PersonSpec {
+        orig_id: None,
+        trips: vec![IndividTrip::new(
+            Time::START_OF_DAY,
+            TripPurpose::Shopping,
+            TripEndpoint::SuddenlyAppear(Position::new(
+                start_lane,
+                map.get_l(start_lane).length() * 0.8,
+            )),
+            TripEndpoint::Building(goal_bldg),
+            TripMode::Drive,
+        )],
+    });
+    // Will definitely get there first
+    for _ in 0..map.get_b(goal_bldg).num_parking_spots() {
+        scenario.people.push(PersonSpec {
+            orig_id: None,
+            trips: vec![IndividTrip::new(
+                Time::START_OF_DAY,
+                TripPurpose::Shopping,
+                TripEndpoint::SuddenlyAppear(spawn_by_goal_bldg),
+                TripEndpoint::Building(goal_bldg),
+                TripMode::Drive,
+            )],
+        });
+    }
+    let mut rng = app.primary.current_flags.sim_flags.make_rng();
+    app.primary
+        .sim
+        .instantiate(&scenario, map, &mut rng, &mut Timer::new("spawn trip"));
+    app.primary.sim.tiny_step(map, &mut app.primary.sim_cb);
+
+    // And add some noise
+    spawn_agents_around(traffic, app);
+}
+
+/// Points at a widget that doesn't move
+fn fixed_arrow(panel: &Panel, name: &str) -> Result<Box<dyn Fn(&GfxCtx, &App) -> Pt2D>> {
+    if !panel.has_widget(name) {
+        bail!("The tutorial points at {}, which doesn't exist", name);
+    }
+    let pt = panel.center_of(name);
+    Ok(Box::new(move |_, _| pt.to_pt()))
 }
 
 fn transition(app: &mut App, tut: &mut TutorialState) -> Transition {
     tut.reset_state();
-    let mode = GameplayMode::Tutorial(tut.current);
+    let mode = GameplayMode::Tutorial(tut.map.clone(), tut.current);
     Transition::Replace(SandboxMode::simple_new(app, mode))
 }
 
@@ -775,7 +874,7 @@ impl TutorialState {
                         Line(format!(
                             "Task {}: {}",
                             self.current.stage + 1,
-                            self.stage().task.label()
+                            self.stage().title
                         ))
                         .small_heading(),
                     )
@@ -833,11 +932,11 @@ impl TutorialState {
             msg_panel: if let Some(msg) = self.message() {
                 let mut col = vec![{
                     let mut txt = Text::new();
-                    txt.add_line(Line(self.stage().task.label()).small_heading());
+                    txt.add_line(Line(&self.stage().title).small_heading());
                     txt.add_line("");
                     txt.into_widget(ctx)
                 }];
-                if let Some(icon) = msg.icon {
+                if let Some(ref icon) = msg.icon {
                     col.push(Image::from_path(icon).dims(30.0).into_widget(ctx));
                 }
                 col.push(msg.txt.clone().wrap_to_pct(ctx, 30).into_widget(ctx));
@@ -880,10 +979,13 @@ impl TutorialState {
         })
     }
 
-    fn new(ctx: &mut EventCtx, app: &App) -> TutorialState {
+    fn new(ctx: &mut EventCtx, app: &App) -> Result<TutorialState> {
+        let config = TutorialConfig::load(app.opts.language.as_ref());
+        let map = &app.primary.map;
         let mut state = TutorialState {
             stages: Vec::new(),
             current: TutorialPointer::new(0, 0),
+            map: map.get_name().clone(),
             window_dims: (ctx.canvas.window_width, ctx.canvas.window_height),
 
             inspected_bike_lane: false,
@@ -898,7 +1000,7 @@ impl TutorialState {
             parking_found: false,
             score_delivered: false,
 
-            fire_station: app.primary.map.find_b_by_osm_id(bldg(731238736)).unwrap(),
+            fire_station: config.find_b(map, Place::FireStation)?,
         };
 
         let tool_panel = tool_panel(ctx);
@@ -909,361 +1011,90 @@ impl TutorialState {
         let minimap = Minimap::new(ctx, app, MinimapController);
         ctx.canvas.cam_zoom = orig_zoom;
 
-        let map = &app.primary.map;
-
-        state.stages.push(
-            Stage::new(Task::Camera)
-                .warp_to(
-                    ID::Intersection(map.find_i_by_osm_id(osm::NodeID(53096945)).unwrap()),
-                    None,
-                )
-                .msg(Message::new(Text::from_multiline(vec![
-                    "Let's start by piloting your fancy new drone.",
-                    "",
-                    "- Click and drag to pan around the map",
-                    "- Use your scroll wheel or touchpad to zoom in and out.",
-                ])))
-                .msg(
-                    Message::new(Text::from(
-                        "If the controls feel wrong, try adjusting the settings.",
-                    ))
-                    .arrow(tool_panel.center_of("settings")),
-                )
-                .msg(Message::new(Text::from_multiline(vec![
-                    "Let's try the drone ou--",
-                    "",
-                    "WHOA, THERE'S A FIRE STATION ON FIRE!",
-                    "GO CLICK ON IT, QUICK!",
-                ])))
-                .msg(Message::new(Text::from_multiline(vec![
-                    "Hint:",
-                    "- Look around for an unusually red building",
-                    "- You have to zoom in to interact with anything on the map.",
-                ]))),
-        );
-
-        state.stages.push(
-            Stage::new(Task::InspectObjects)
-                .msg(Message::new(Text::from(
-                    "What, no fire? Er, sorry about that. Just a little joke we like to play on \
-                     the new recruits.",
-                )))
-                .msg(Message::new(Text::from_multiline(vec![
-                    "Now, let's learn how to inspect and interact with objects in the map.",
-                    "",
-                    "Find one of each:",
-                    "[ ] bike lane",
-                    "[ ] building",
-                    "[ ] intersection with stop sign",
-                    "[ ] intersection on the map border",
-                    "- Hint: You have to zoom in before you can select anything.",
-                ]))),
-        );
-
-        state.stages.push(
-            Stage::new(Task::TimeControls)
-                .warp_to(
-                    ID::Intersection(map.find_i_by_osm_id(osm::NodeID(53096945)).unwrap()),
-                    Some(6.5),
-                )
-                .msg(
-                    Message::new(Text::from_multiline(vec![
-                        "Inspection complete!",
-                        "",
-                        "You'll work day and night, watching traffic patterns unfold.",
-                    ]))
-                    .arrow(time.panel.center_of_panel()),
-                )
-                .msg(
-                    Message::new({
-                        let mut txt = Text::from(Line("You can pause or resume time"));
-                        txt.add_line("");
-                        txt.add_line("Hint: Press ");
-                        txt.append(Line(Key::Space.describe()).fg(ctx.style().text_hotkey_color));
-                        txt.append(Line(" to pause/resume"));
-                        txt
-                    })
-                    .arrow(time.panel.center_of("pause"))
-                    .icon("system/assets/speed/pause.svg"),
-                )
-                .msg(
-                    Message::new({
-                        let mut txt = Text::from(Line("Speed things up"));
-                        txt.add_line("");
-                        txt.add_line("Hint: Press ");
-                        txt.append(
-                            Line(Key::LeftArrow.describe()).fg(ctx.style().text_hotkey_color),
-                        );
-                        txt.append(Line(" to slow down, "));
-                        txt.append(
-                            Line(Key::RightArrow.describe()).fg(ctx.style().text_hotkey_color),
-                        );
-                        txt.append(Line(" to speed up"));
-                        txt
-                    })
-                    .arrow(time.panel.center_of("30x speed"))
-                    .icon("system/assets/speed/triangle.svg"),
-                )
-                .msg(
-                    Message::new(Text::from("Advance time by certain amounts"))
-                        .arrow(time.panel.center_of("step forwards")),
-                )
-                .msg(
-                    Message::new(Text::from("And jump to the beginning of the day"))
-                        .arrow(time.panel.center_of("reset to midnight"))
-                        .icon("system/assets/speed/reset.svg"),
-                )
-                .msg(Message::new(Text::from(
-                    "Let's try these controls out. Wait until 5pm or later.",
-                ))),
-        );
-
-        state.stages.push(
-            Stage::new(Task::PauseResume)
-                .msg(Message::new(Text::from(
-                    "Whew, that took a while! (Hopefully not though...)",
-                )))
-                .msg(
-                    Message::new(Text::from_multiline(vec![
-                        "You might've figured it out already,",
-                        "But you'll be pausing/resuming time VERY frequently",
-                    ]))
-                    .arrow(time.panel.center_of("pause"))
-                    .icon("system/assets/speed/pause.svg"),
-                )
-                .msg(Message::new(Text::from(
-                    "Just reassure me and pause/resume time a few times, alright?",
-                ))),
-        );
-
-        state.stages.push(
-            Stage::new(Task::Escort)
-                // Don't center on where the agents are, be a little offset
-                .warp_to(
-                    ID::Building(map.find_b_by_osm_id(bldg(217700459)).unwrap()),
-                    Some(8.0),
-                )
-                .custom_spawn(Box::new(move |app| {
-                    // Seed a specific target car, and fill up the target building's private
-                    // parking to force the target to park on-street.
-                    let map = &app.primary.map;
-                    let goal_bldg = map.find_b_by_osm_id(bldg(217701875)).unwrap();
-                    let start_lane = {
-                        let r = map.get_r(
-                            map.find_r_by_osm_id(OriginalRoad::new(36952952, (53128049, 53101726)))
-                                .unwrap(),
-                        );
-                        assert_eq!(r.lanes.len(), 6);
-                        r.lanes[2].id
-                    };
-                    let spawn_by_goal_bldg = {
-                        let pos = map.get_b(goal_bldg).driving_connection(map).unwrap().0;
-                        Position::new(pos.lane(), Distance::ZERO)
-                    };
-
-                    let mut scenario = Scenario::empty(map, "prank");
-                    scenario.people.push(PersonSpec {
-                        orig_id: None,
-                        trips: vec![IndividTrip::new(
-                            Time::START_OF_DAY,
-                            TripPurpose::Shopping,
-                            TripEndpoint::SuddenlyAppear(Position::new(
-                                start_lane,
-                                map.get_l(start_lane).length() * 0.8,
-                            )),
-                            TripEndpoint::Building(goal_bldg),
-                            TripMode::Drive,
-                        )],
-                    });
-                    // Will definitely get there first
-                    for _ in 0..map.get_b(goal_bldg).num_parking_spots() {
-                        scenario.people.push(PersonSpec {
-                            orig_id: None,
-                            trips: vec![IndividTrip::new(
-                                Time::START_OF_DAY,
-                                TripPurpose::Shopping,
-                                TripEndpoint::SuddenlyAppear(spawn_by_goal_bldg),
-                                TripEndpoint::Building(goal_bldg),
-                                TripMode::Drive,
-                            )],
+        let escort_start = config.find_r(map, Place::EscortStart)?;
+        let escort_goal = config.find_b(map, Place::EscortGoal)?;
+        let traffic = config.find_i(map, Place::Traffic)?;
+        let bike_lane_scenario = make_bike_lane_scenario(&config, map)?;
+
+        // The tutorial's own panel depends on how many stages there are, so arrows pointing at it
+        // have to wait
+        let mut point_at_top_right = Vec::new();
+        for stage_config in &config.stages {
+            let mut stage = Stage::new(stage_config.task, stage_config.title.clone());
+            if let Some(place) = stage_config.warp_to {
+                stage = stage.warp_to(config.find(map, place)?, stage_config.zoom);
+            }
+            for msg in &stage_config.messages {
+                let arrow = match msg.arrow {
+                    None => None,
+                    Some(ArrowTarget::ToolPanel(ref name)) => Some(fixed_arrow(&tool_panel, name)?),
+                    Some(ArrowTarget::TimePanel(ref name)) => Some(fixed_arrow(&time.panel, name)?),
+                    Some(ArrowTarget::WholeTimePanel) => {
+                        let pt = time.panel.center_of_panel();
+                        let arrow: Box<dyn Fn(&GfxCtx, &App) -> Pt2D> =
+                            Box::new(move |_, _| pt.to_pt());
+                        Some(arrow)
+                    }
+                    Some(ArrowTarget::Minimap(ref name)) => {
+                        Some(fixed_arrow(minimap.get_panel(), name)?)
+                    }
+                    Some(ArrowTarget::TutorialPanel(ref name)) => {
+                        point_at_top_right.push((
+                            state.stages.len(),
+                            stage.messages.len(),
+                            name.clone(),
+                        ));
+                        None
+                    }
+                    Some(ArrowTarget::EscortCar) => {
+                        let arrow: Box<dyn Fn(&GfxCtx, &App) -> Pt2D> = Box::new(|g, app| {
+                            g.canvas
+                                .map_to_screen(
+                                    app.primary
+                                        .sim
+                                        .canonical_pt_for_agent(
+                                            AgentID::Car(ESCORT),
+                                            &app.primary.map,
+                                        )
+                                        .unwrap(),
+                                )
+                                .to_pt()
                         });
+                        Some(arrow)
                     }
-                    let mut rng = app.primary.current_flags.sim_flags.make_rng();
-                    app.primary.sim.instantiate(
-                        &scenario,
-                        map,
-                        &mut rng,
-                        &mut Timer::new("spawn trip"),
-                    );
-                    app.primary.sim.tiny_step(map, &mut app.primary.sim_cb);
-
-                    // And add some noise
-                    spawn_agents_around(
-                        app.primary
-                            .map
-                            .find_i_by_osm_id(osm::NodeID(53101726))
-                            .unwrap(),
-                        app,
-                    );
-                }))
-                .msg(Message::new(Text::from(
-                    "Alright alright, no need to wear out your spacebar.",
-                )))
-                .msg(Message::new(Text::from_multiline(vec![
-                    "Oh look, some people appeared!",
-                    "We've got pedestrians, bikes, and cars moving around now.",
-                ])))
-                .msg(
-                    Message::new(Text::from_multiline(vec![
-                        "Why don't you follow this car to their destination,",
-                        "see where they park, and then play a little... prank?",
-                    ]))
-                    .dynamic_arrow(Box::new(|g, app| {
-                        g.canvas
-                            .map_to_screen(
-                                app.primary
-                                    .sim
-                                    .canonical_pt_for_agent(AgentID::Car(ESCORT), &app.primary.map)
-                                    .unwrap(),
-                            )
-                            .to_pt()
-                    }))
-                    .left_aligned(),
-                )
-                .msg(
-                    Message::new(Text::from_multiline(vec![
-                        "You don't have to manually chase them; just click to follow.",
-                        "",
-                        "(If you do lose track of them, just reset)",
-                    ]))
-                    .arrow(time.panel.center_of("reset to midnight"))
-                    .icon("system/assets/speed/reset.svg"),
-                ),
-        );
-
-        state.stages.push(
-            Stage::new(Task::LowParking)
-                // TODO Actually, we ideally just want a bunch of parked cars, not all these trips
-                .scenario(ScenarioGenerator {
-                    scenario_name: "low parking".to_string(),
-                    only_seed_buses: Some(BTreeSet::new()),
-                    spawn_over_time: vec![SpawnOverTime {
-                        num_agents: 1000,
-                        start_time: Time::START_OF_DAY,
-                        stop_time: Time::START_OF_DAY + Duration::hours(3),
-                        goal: None,
-                        percent_driving: 1.0,
-                        percent_biking: 0.0,
-                        percent_use_transit: 0.0,
-                    }],
-                    border_spawn_over_time: Vec::new(),
-                })
-                .msg(
-                    Message::new(Text::from_multiline(vec![
-                        "What an immature prank. You should re-evaluate your life decisions.",
-                        "",
-                        "The map is quite large, so to help you orient, the minimap shows you an \
-                         overview of all activity. You can click and drag it just like the normal \
-                         map.",
-                    ]))
-                    .arrow(minimap.get_panel().center_of("minimap"))
-                    .left_aligned(),
-                )
-                .msg(
-                    Message::new(Text::from_multiline(vec![
-                        "You can apply different layers to the map, to find things like:",
-                        "",
-                        "- roads with high traffic",
-                        "- bus stops",
-                        "- how much parking is filled up",
-                    ]))
-                    .arrow(minimap.get_panel().center_of("change layers"))
-                    .icon("system/assets/tools/layers.svg")
-                    .left_aligned(),
-                )
-                .msg(Message::new(Text::from_multiline(vec![
-                    "Let's try these out.",
-                    "There are lots of cars parked everywhere. Can you find a road that's almost \
-                     out of parking spots?",
-                ]))),
-        );
-
-        let bike_lane_scenario = make_bike_lane_scenario(map);
-        let bike_lane_focus_pt = map.find_b_by_osm_id(bldg(217699496)).unwrap();
-
-        state.stages.push(
-            Stage::new(Task::WatchBikes)
-                .warp_to(ID::Building(bike_lane_focus_pt), None)
-                .scenario(bike_lane_scenario.clone())
-                .msg(Message::new(Text::from_multiline(vec![
-                    "Well done!",
-                    "",
-                    "Something's about to happen over here. Follow along and figure out what the \
-                     problem is, at whatever speed you'd like.",
-                ]))),
-        );
+                };
+                stage = stage.msg(Message {
+                    txt: msg.make_text(ctx, CAR_BIKE_CONTENTION_GOAL),
+                    aligned: if msg.left_aligned {
+                        HorizontalAlignment::Left
+                    } else {
+                        HorizontalAlignment::Center
+                    },
+                    arrow,
+                    icon: msg.icon.clone(),
+                });
+            }
+
+            stage = match stage_config.task {
+                Task::Escort => stage.custom_spawn(Box::new(move |app| {
+                    spawn_escort(app, escort_start, escort_goal, traffic)
+                })),
+                Task::LowParking => stage.scenario(low_parking_scenario()),
+                Task::WatchBikes | Task::FixBikes => stage.scenario(bike_lane_scenario.clone()),
+                _ => stage,
+            };
+            state.stages.push(stage);
+        }
+        if state.stages.is_empty() {
+            bail!("The tutorial doesn't have any stages");
+        }
 
         let top_right = state.make_top_right(ctx, true);
-        state.stages.push(
-            Stage::new(Task::FixBikes)
-                .scenario(bike_lane_scenario)
-                .warp_to(ID::Building(bike_lane_focus_pt), None)
-                .msg(Message::new(Text::from_multiline(vec![
-                    "Looks like lots of cars and bikes trying to go to a house by the playfield.",
-                    "",
-                    "When lots of cars and bikes share the same lane, cars are delayed (assuming \
-                     there's no room to pass) and the cyclist probably feels unsafe too.",
-                ])))
-                .msg(Message::new(Text::from(
-                    "Luckily, you have the power to modify lanes! What if you could transform the \
-                     parking lanes that aren't being used much into bike lanes?",
-                )))
-                .msg(
-                    Message::new(Text::from(
-                        "To edit lanes, click 'edit map' and then select a lane.",
-                    ))
-                    .arrow(top_right.center_of("edit map")),
-                )
-                .msg(Message::new(Text::from_multiline(vec![
-                    "When you finish making edits, time will jump to the beginning of the next \
-                     day. You can't make most changes in the middle of the day.",
-                    "",
-                    "Seattleites are really boring; they follow the exact same schedule everyday. \
-                     They're also stubborn, so even if you try to influence their decision \
-                     whether to drive, walk, bike, or take a bus, they'll do the same thing. For \
-                     now, you're just trying to make things better, assuming people stick to \
-                     their routine.",
-                ])))
-                .msg(
-                    // TODO Deliberately vague with the measurement.
-                    Message::new(Text::from_multiline(vec![
-                        format!(
-                            "So adjust lanes and speed up the slowest trip by at least {}.",
-                            CAR_BIKE_CONTENTION_GOAL
-                        ),
-                        "".to_string(),
-                        "You can explore results as trips finish. When everyone's finished, \
-                         you'll get your final score."
-                            .to_string(),
-                    ]))
-                    .arrow(minimap.get_panel().center_of("more data")),
-                ),
-        );
-
-        state.stages.push(
-            Stage::new(Task::Done).msg(Message::new(Text::from_multiline(vec![
-                "You're ready for the hard stuff now.",
-                "",
-                "- Try out some challenges",
-                "- Explore larger parts of Seattle in the sandbox, and try out any ideas you've \
-                 got.",
-                "- Check out community proposals, and submit your own",
-                "",
-                "Go have the appropriate amount of fun!",
-            ]))),
-        );
+        for (stage, msg, name) in point_at_top_right {
+            state.stages[stage].messages[msg].arrow = Some(fixed_arrow(&top_right, &name)?);
+        }
 
-        state
+        Ok(state)
 
         // TODO Multi-modal trips -- including parking. (Cars per bldg, ownership)
         // TODO Explain the finished trip data
@@ -1273,7 +1104,7 @@ impl TutorialState {
     }
 
     pub fn scenarios_to_prebake(map: &Map) -> Vec<ScenarioGenerator> {
-        vec![make_bike_lane_scenario(map)]
+        vec![make_bike_lane_scenario(&TutorialConfig::english(), map).unwrap()]
     }
 }
 
@@ -1409,8 +1240,3 @@ fn intro_story(ctx: &mut EventCtx) -> Box<dyn State<App>> {
             }),
         )
 }
-
-// Assumes ways
-fn bldg(id: i64) -> osm::OsmID {
-    osm::OsmID::Way(osm::WayID(id))
-}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use geom::{Circle, Distance, Duration, Pt2D};
+use widgetry::{
+    Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, State,
+    Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::Warping;
+use crate::ID;
+
+// An intersection counts as gridlocked once somebody's been waiting there this long
+const GRIDLOCK_THRESHOLD: Duration = Duration::const_seconds(120.0);
+// An individual agent counts as stuck once they've been blocked this long
+const STUCK_AGENT_THRESHOLD: Duration = Duration::const_seconds(300.0);
+// Agents are bucketed into square cells this wide when looking for dense areas
+const DENSITY_CELL_SIZE: f64 = 100.0;
+// Don't report two hotspots closer than this
+const MIN_SPACING: Distance = Distance::const_meters(200.0);
+const MAX_HOTSPOTS: usize = 10;
+
+/// "Show me the action": find the most congested or unusual places in the running simulation and
+/// offer a one-click camera jump to each of them. Useful for demos and for finding problems on an
+/// unfamiliar map.
+pub struct Highlights {
+    panel: Panel,
+    hotspots: Vec<Hotspot>,
+    draw: Drawable,
+}
+
+struct Hotspot {
+    pt: Pt2D,
+    id: Option<ID>,
+    description: String,
+}
+
+impl Highlights {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let hotspots = find_hotspots(app);
+
+        let mut batch = GeomBatch::new();
+        for h in &hotspots {
+            let circle = Circle::new(h.pt, MIN_SPACING / 2.0);
+            batch.push(Color::RED.alpha(0.3), circle.to_polygon());
+            if let Ok(outline) = circle.to_outline(Distance::meters(5.0)) {
+                batch.push(Color::RED, outline);
+            }
+        }
+
+        let mut col = vec![Widget::row(vec![
+            Line("Show me the action").small_heading().into_widget(ctx),
+            ctx.style().btn_close_widget(ctx),
+        ])];
+        if hotspots.is_empty() {
+            col.push("Nothing interesting is happening right now".text_widget(ctx));
+        } else {
+            col.push(
+                Text::from(
+                    Line(format!(
+                        "The {} busiest or most unusual places right now",
+                        hotspots.len()
+                    ))
+                    .secondary(),
+                )
+                .into_widget(ctx),
+            );
+            for (idx, h) in hotspots.iter().enumerate() {
+                col.push(
+                    ctx.style()
+                        .btn_outline
+                        .text(format!("{}) {}", idx + 1, h.description))
+                        .build_widget(ctx, format!("hotspot {}", idx)),
+                );
+            }
+        }
+
+        Box::new(Highlights {
+            panel: Panel::new_builder(Widget::col(col))
+                .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+                .build(ctx),
+            hotspots,
+            draw: ctx.upload(batch),
+        })
+    }
+}
+
+impl State<App> for Highlights {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            if x == "close" {
+                return Transition::Pop;
+            }
+            let idx = x["hotspot ".len()..].parse::<usize>().unwrap();
+            let hotspot = &self.hotspots[idx];
+            // Replace this state, so that the warp can open an info panel in the sandbox
+            return Transition::Replace(Warping::new_state(
+                ctx,
+                hotspot.pt,
+                Some(10.0),
+                hotspot.id.clone(),
+                &mut app.primary,
+            ));
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+    }
+}
+
+/// Ranks places worth looking at, most interesting first. Gridlock is reported before agents stuck
+/// for a long time, which are reported before areas that're just densely packed.
+fn find_hotspots(app: &App) -> Vec<Hotspot> {
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+    let now = sim.time();
+    let mut candidates = Vec::new();
+
+    // delayed_intersections is already sorted by the earliest waiting, so the likely root cause
+    // of gridlock comes first
+    for (i, since) in sim.delayed_intersections(GRIDLOCK_THRESHOLD) {
+        candidates.push(Hotspot {
+            pt: map.get_i(i).polygon.center(),
+            id: Some(ID::Intersection(i)),
+            description: format!(
+                "Gridlock at {} for {}",
+                map.get_i(i).name(app.opts.language.as_ref(), map),
+                now - since
+            ),
+        });
+    }
+
+    let mut stuck: Vec<_> = sim
+        .get_blocked_by_graph(map)
+        .into_iter()
+        .filter(|(_, (delay, _))| *delay >= STUCK_AGENT_THRESHOLD)
+        .collect();
+    stuck.sort_by_key(|(_, (delay, _))| std::cmp::Reverse(*delay));
+    for (agent, (delay, _)) in stuck {
+        if let Some(pt) = sim.canonical_pt_for_agent(agent, map) {
+            candidates.push(Hotspot {
+                pt,
+                id: Some(ID::from_agent(agent)),
+                description: format!("{} stuck for {}", agent, delay),
+            });
+        }
+    }
+
+    for (pt, count) in densest_areas(app) {
+        candidates.push(Hotspot {
+            pt,
+            id: None,
+            description: format!("{} agents crowded together", count),
+        });
+    }
+
+    let mut hotspots: Vec<Hotspot> = Vec::new();
+    for candidate in candidates {
+        if hotspots.len() == MAX_HOTSPOTS {
+            break;
+        }
+        if hotspots
+            .iter()
+            .all(|h| h.pt.dist_to(candidate.pt) >= MIN_SPACING)
+        {
+            hotspots.push(candidate);
+        }
+    }
+    hotspots
+}
+
+/// Bucket every agent into a grid, returning the center of each non-trivial cell and the number of
+/// agents in it. The densest cells come first.
+fn densest_areas(app: &App) -> Vec<(Pt2D, usize)> {
+    let mut cells: HashMap<(i64, i64), (usize, f64, f64)> = HashMap::new();
+    for a in app.primary.sim.get_unzoomed_agents(&app.primary.map) {
+        let key = (
+            (a.pos.x() / DENSITY_CELL_SIZE).floor() as i64,
+            (a.pos.y() / DENSITY_CELL_SIZE).floor() as i64,
+        );
+        let entry = cells.entry(key).or_insert((0, 0.0, 0.0));
+        entry.0 += 1;
+        entry.1 += a.pos.x();
+        entry.2 += a.pos.y();
+    }
+
+    let mut results: Vec<(Pt2D, usize)> = cells
+        .into_values()
+        // A handful of agents isn't worth calling out
+        .filter(|(count, _, _)| *count >= 10)
+        .map(|(count, sum_x, sum_y)| {
+            // Jump to the centroid of the agents, not the corner of the cell
+            (Pt2D::new(sum_x / count as f64, sum_y / count as f64), count)
+        })
+        .collect();
+    results.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    results
+}
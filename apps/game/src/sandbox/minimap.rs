@@ -9,6 +9,7 @@ use crate::app::App;
 use crate::app::Transition;
 use crate::common::Warping;
 use crate::layer::PickLayer;
+use crate::sandbox::highlights::Highlights;
 
 pub struct MinimapController;
 
@@ -94,6 +95,7 @@ impl MinimapControls<App> for MinimapController {
                 return Some(Transition::Push(PickLayer::pick(ctx, app)));
             }
             "more data" => Some(Transition::Push(app.session.dash_tab.launch(ctx, app))),
+            "show me the action" => Some(Transition::Push(Highlights::new_state(ctx, app))),
             _ => unreachable!(),
         }
     }
@@ -330,8 +332,12 @@ fn make_tool_panel(ctx: &mut EventCtx, app: &App) -> Widget {
             .hotkey(Key::K)
             .build_widget(ctx, "search"),
         buttons
+            .clone()
             .image_path("system/assets/meters/trip_histogram.svg")
             .hotkey(Key::Q)
             .build_widget(ctx, "more data"),
+        buttons
+            .image_path("system/assets/tools/fire.svg")
+            .build_widget(ctx, "show me the action"),
     ])
 }
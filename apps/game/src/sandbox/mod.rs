@@ -1,20 +1,26 @@
+use std::collections::BTreeSet;
+
 use anyhow::Result;
 use maplit::btreeset;
 
-use geom::{Circle, Distance, Time};
+use geom::{Circle, Distance, Duration, Polygon, Pt2D, Time};
 use map_gui::colors::ColorSchemeChoice;
 use map_gui::load::MapLoader;
 use map_gui::options::OptionsPanel;
 use map_gui::tools::Minimap;
 use map_gui::AppLike;
-use sim::Analytics;
+use map_model::LaneID;
+use sim::{Analytics, Incident, PickupDropoffZone, VariableSpeedLimits};
 use synthpop::Scenario;
-use widgetry::tools::{ChooseSomething, FileLoader, FutureLoader, URLManager};
-use widgetry::{lctrl, Choice, EventCtx, GfxCtx, Key, Outcome, Panel, State, UpdateType};
+use widgetry::tools::{ChooseSomething, FileLoader, FutureLoader, PopupMsg, URLManager};
+use widgetry::{
+    lctrl, Choice, EventCtx, GeomBatch, GfxCtx, Key, Outcome, Panel, State, UpdateType,
+};
 
 pub use self::gameplay::{spawn_agents_around, GameplayMode, TutorialPointer, TutorialState};
 pub use self::minimap::MinimapController;
 use self::misc_tools::{RoutePreview, TrafficRecorder};
+use self::palette::CommandPalette;
 pub use self::speed::{SpeedSetting, TimePanel};
 pub use self::time_warp::TimeWarpScreen;
 use crate::app::{App, Transition};
@@ -32,8 +38,11 @@ use crate::ID;
 
 pub mod dashboards;
 pub mod gameplay;
+mod highlights;
 mod minimap;
 mod misc_tools;
+mod palette;
+mod right_of_way;
 mod speed;
 mod time_warp;
 mod turn_explorer;
@@ -149,6 +158,13 @@ impl State<App> for SandboxMode {
         if app.opts.dev && ctx.input.pressed(lctrl(Key::D)) {
             return Transition::Push(DebugMode::new_state(ctx, app));
         }
+        if ctx.input.pressed(lctrl(Key::P)) {
+            return Transition::Push(CommandPalette::new_state(
+                ctx,
+                app,
+                self.gameplay_mode.clone(),
+            ));
+        }
 
         if let Some(ref mut m) = self.controls.minimap {
             if let Some(t) = m.event(ctx, app) {
@@ -224,6 +240,7 @@ impl State<App> for SandboxMode {
         if let Some(ref l) = app.primary.layer {
             l.draw(g, app);
         }
+        draw_ferries(g, app);
 
         if !app.opts.minimal_controls {
             if let Some(ref c) = self.controls.common {
@@ -257,6 +274,68 @@ impl State<App> for SandboxMode {
     }
 }
 
+fn draw_ferries(g: &mut GfxCtx, app: &App) {
+    let mut batch = GeomBatch::new();
+    for (_, pt, angle) in app.primary.sim.get_ferries(&app.primary.map) {
+        batch.append(
+            GeomBatch::from(vec![(
+                app.cs.bus_body,
+                Polygon::rectangle_centered(
+                    Pt2D::new(0.0, 0.0),
+                    Distance::meters(30.0),
+                    Distance::meters(10.0),
+                ),
+            )])
+            .centered_on(pt)
+            .rotate(angle),
+        );
+    }
+    batch.draw(g);
+}
+
+/// Close a lane or its whole road for a while, starting right now
+fn schedule_incident(ctx: &mut EventCtx, l: LaneID) -> Transition {
+    let mut choices = Vec::new();
+    for (whole_road, what) in [(false, "this lane"), (true, "the whole road")] {
+        for minutes in [15, 30, 60, 120] {
+            choices.push(Choice::new(
+                format!("close {} for {} minutes", what, minutes),
+                (whole_road, Duration::minutes(minutes)),
+            ));
+        }
+    }
+    Transition::Push(ChooseSomething::new_state(
+        ctx,
+        "What happens here?",
+        choices,
+        Box::new(move |(whole_road, duration), ctx, app| {
+            let now = app.primary.sim.time();
+            let mut incident = Incident {
+                description: format!("incident on {}", l.road),
+                start: now,
+                end: now + duration,
+                roads: BTreeSet::new(),
+                lanes: BTreeSet::new(),
+            };
+            if whole_road {
+                incident.roads.insert(l.road);
+            } else {
+                incident.lanes.insert(l);
+            }
+            match app
+                .primary
+                .sim
+                .schedule_incident(&app.primary.map, incident)
+            {
+                Ok(()) => Transition::Pop,
+                Err(err) => {
+                    Transition::Replace(PopupMsg::new_state(ctx, "Error", vec![err.to_string()]))
+                }
+            }
+        }),
+    ))
+}
+
 pub fn maybe_exit_sandbox(ctx: &mut EventCtx) -> Transition {
     Transition::Push(ChooseSomething::new_state(
         ctx,
@@ -320,6 +399,18 @@ impl ContextualActions for Actions {
                     {
                         actions.push((Key::E, "edit stop sign".to_string()));
                     }
+                    if !app.primary.map.get_i(i).is_border() {
+                        actions.push((Key::Z, "explore right-of-way".to_string()));
+                        if app
+                            .primary
+                            .sim
+                            .is_dont_block_the_box_enforced(i, &app.primary.map)
+                        {
+                            actions.push((Key::B, "allow blocking the box".to_string()));
+                        } else {
+                            actions.push((Key::B, "enforce don't block the box".to_string()));
+                        }
+                    }
                     if app.opts.dev && app.primary.sim.num_recorded_trips().is_none() {
                         actions.push((Key::R, "record traffic here".to_string()));
                     }
@@ -330,6 +421,19 @@ impl ContextualActions for Actions {
                     }
                     if self.gameplay.can_edit_roads() && can_edit_lane(app, l) {
                         actions.push((Key::E, "edit lane".to_string()));
+                        actions.push((Key::I, "schedule an incident here".to_string()));
+                    }
+                    let lane = app.primary.map.get_l(l);
+                    if lane.lane_type.is_for_moving_vehicles() {
+                        if app
+                            .primary
+                            .sim
+                            .has_variable_speed_limit(lane.get_directed_parent())
+                        {
+                            actions.push((Key::V, "remove variable speed limit sign".to_string()));
+                        } else {
+                            actions.push((Key::V, "add variable speed limit sign".to_string()));
+                        }
                     }
                 }
                 ID::Building(b) => {
@@ -338,13 +442,18 @@ impl ContextualActions for Actions {
                     } else {
                         actions.push((Key::F, "add this building to favorites".to_string()));
                     }
+                    if app.primary.sim.get_pickup_dropoff_zone(b).is_some() {
+                        actions.push((Key::P, "remove pickup/dropoff zone".to_string()));
+                    } else {
+                        actions.push((Key::P, "add pickup/dropoff zone".to_string()));
+                    }
                 }
                 _ => {}
             }
         }
         actions.extend(match self.gameplay {
             GameplayMode::Freeform(_) => gameplay::freeform::actions(app, id),
-            GameplayMode::Tutorial(_) => gameplay::tutorial::actions(app, id),
+            GameplayMode::Tutorial(_, _) => gameplay::tutorial::actions(app, id),
             _ => Vec::new(),
         });
         actions
@@ -379,9 +488,35 @@ impl ContextualActions for Actions {
             (ID::Intersection(i), "record traffic here") => {
                 Transition::Push(TrafficRecorder::new_state(ctx, btreeset! {i}))
             }
+            (ID::Intersection(i), "allow blocking the box") => {
+                app.primary.sim.set_dont_block_the_box(i, false);
+                Transition::Keep
+            }
+            (ID::Intersection(i), "enforce don't block the box") => {
+                app.primary.sim.set_dont_block_the_box(i, true);
+                Transition::Keep
+            }
+            (ID::Intersection(i), "explore right-of-way") => {
+                Transition::Push(right_of_way::RightOfWay::new_state(ctx, app, i))
+            }
             (ID::Lane(l), "explore turns from this lane") => {
                 Transition::Push(turn_explorer::TurnExplorer::new_state(ctx, app, l))
             }
+            (ID::Lane(l), "add variable speed limit sign") => {
+                // The sign reacts to traffic on its own road. Longer corridors can be set up with
+                // SimOptions.
+                let dr = app.primary.map.get_l(l).get_directed_parent();
+                app.primary
+                    .sim
+                    .add_variable_speed_limits(VariableSpeedLimits::default_policy(vec![dr]));
+                Transition::Keep
+            }
+            (ID::Lane(l), "remove variable speed limit sign") => {
+                let dr = app.primary.map.get_l(l).get_directed_parent();
+                app.primary.sim.remove_variable_speed_limit(dr);
+                Transition::Keep
+            }
+            (ID::Lane(l), "schedule an incident here") => schedule_incident(ctx, l),
             (ID::Lane(l), "edit lane") => Transition::Multi(vec![
                 Transition::Push(EditMode::new_state(ctx, app, self.gameplay.clone())),
                 Transition::Push(RoadEditor::new_state(ctx, app, l)),
@@ -396,6 +531,16 @@ impl ContextualActions for Actions {
                 app.primary.layer = Some(Box::new(ShowFavorites::new(ctx, app)));
                 Transition::Keep
             }
+            (ID::Building(b), "add pickup/dropoff zone") => {
+                app.primary
+                    .sim
+                    .add_pickup_dropoff_zone(PickupDropoffZone::new(b));
+                Transition::Keep
+            }
+            (ID::Building(b), "remove pickup/dropoff zone") => {
+                app.primary.sim.remove_pickup_dropoff_zone(b);
+                Transition::Keep
+            }
             (_, "follow (run the simulation)") => {
                 *close_panel = false;
                 Transition::ModifyState(Box::new(|state, ctx, app| {
@@ -416,7 +561,7 @@ impl ContextualActions for Actions {
             }
             (id, action) => match self.gameplay {
                 GameplayMode::Freeform(_) => gameplay::freeform::execute(ctx, app, id, action),
-                GameplayMode::Tutorial(_) => gameplay::tutorial::execute(ctx, app, id, action),
+                GameplayMode::Tutorial(_, _) => gameplay::tutorial::execute(ctx, app, id, action),
                 _ => unreachable!(),
             },
         }
@@ -675,7 +820,7 @@ fn mouseover_unzoomed_agent_circle(ctx: &mut EventCtx, app: &mut App) {
         .primary
         .agents
         .borrow_mut()
-        .calculate_unzoomed_agents(ctx, &app.primary.map, &app.primary.sim, &app.cs)
+        .calculate_unzoomed_agents(ctx, &app.primary.map, &app.primary.sim, &app.cs, &app.opts)
         .query_bbox(Circle::new(cursor, Distance::meters(3.0)).get_bounds())
     {
         if let Some(pt) = app.primary.sim.canonical_pt_for_agent(id, &app.primary.map) {
@@ -0,0 +1,297 @@
+use geom::Time;
+use map_gui::options::OptionsPanel;
+use map_gui::tools::{grey_out_map, Navigator};
+use widgetry::{
+    Autocomplete, DrawBaselayer, EventCtx, GfxCtx, Key, Line, Outcome, Panel, State, Text, TextBox,
+    Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::{warp_to_id, DebugWarp};
+use crate::debug::DebugMode;
+use crate::layer::PickLayer;
+use crate::sandbox::dashboards::DashTab;
+use crate::sandbox::time_warp::JumpToTime;
+use crate::sandbox::{GameplayMode, TimeWarpScreen};
+
+#[derive(Clone)]
+enum Command {
+    Dashboard(DashTab),
+    Layer(&'static str),
+    JumpToTime,
+    SearchStreet,
+    WarpToID,
+    Settings,
+    ToggleDevMode,
+    DebugMode,
+    Console,
+}
+
+/// Finds any of the tools scattered around sandbox mode by name
+pub struct CommandPalette {
+    panel: Panel,
+    commands: Vec<Command>,
+    gameplay: GameplayMode,
+}
+
+impl CommandPalette {
+    pub fn new_state(ctx: &mut EventCtx, app: &App, gameplay: GameplayMode) -> Box<dyn State<App>> {
+        let mut named = Vec::new();
+        for choice in DashTab::choices(app) {
+            named.push((
+                format!("open dashboard: {}", choice.label),
+                Command::Dashboard(choice.data),
+            ));
+        }
+        for name in PickLayer::names(app) {
+            if name == "None" {
+                named.push(("hide the layer".to_string(), Command::Layer(name)));
+            } else {
+                named.push((format!("show layer: {}", name), Command::Layer(name)));
+            }
+        }
+        named.push(("jump to time".to_string(), Command::JumpToTime));
+        named.push(("search for a street".to_string(), Command::SearchStreet));
+        named.push(("warp to an object by ID".to_string(), Command::WarpToID));
+        named.push(("settings".to_string(), Command::Settings));
+        named.push((
+            if app.opts.dev {
+                "turn off dev mode"
+            } else {
+                "turn on dev mode"
+            }
+            .to_string(),
+            Command::ToggleDevMode,
+        ));
+        if app.opts.dev {
+            named.push(("debug mode".to_string(), Command::DebugMode));
+        }
+        named.push(("debug console".to_string(), Command::Console));
+
+        let (names, commands): (Vec<String>, Vec<Command>) = named.into_iter().unzip();
+        Box::new(CommandPalette {
+            panel: Panel::new_builder(Widget::col(vec![
+                Widget::row(vec![
+                    Line("What do you want to do?")
+                        .small_heading()
+                        .into_widget(ctx),
+                    ctx.style().btn_close_widget(ctx),
+                ]),
+                Autocomplete::new_widget(
+                    ctx,
+                    names
+                        .into_iter()
+                        .enumerate()
+                        .map(|(idx, name)| (name, idx))
+                        .collect(),
+                    10,
+                )
+                .named("command"),
+            ]))
+            .build(ctx),
+            commands,
+            gameplay,
+        })
+    }
+
+    fn run(&self, ctx: &mut EventCtx, app: &mut App, cmd: Command) -> Transition {
+        match cmd {
+            Command::Dashboard(tab) => {
+                app.session.dash_tab = tab;
+                Transition::Replace(tab.launch(ctx, app))
+            }
+            Command::Layer(name) => match PickLayer::activate(ctx, app, name) {
+                Some(state) => Transition::Replace(state),
+                None => Transition::Pop,
+            },
+            Command::JumpToTime => {
+                Transition::Replace(JumpToTime::new_state(ctx, app, Some(self.gameplay.clone())))
+            }
+            Command::SearchStreet => Transition::Replace(Navigator::new_state(ctx, app)),
+            Command::WarpToID => Transition::Replace(DebugWarp::new_state(ctx)),
+            Command::Settings => Transition::Replace(OptionsPanel::new_state(ctx, app)),
+            Command::ToggleDevMode => {
+                app.opts.dev = !app.opts.dev;
+                Transition::Pop
+            }
+            Command::DebugMode => Transition::Replace(DebugMode::new_state(ctx, app)),
+            Command::Console => Transition::Replace(DebugConsole::new_state(ctx)),
+        }
+    }
+}
+
+impl State<App> for CommandPalette {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            }
+        }
+        if let Some(choices) = self.panel.autocomplete_done::<usize>("command") {
+            // Anything that matches more than one command is ambiguous
+            if choices.len() != 1 {
+                return Transition::Pop;
+            }
+            let cmd = self.commands[choices[0]].clone();
+            return self.run(ctx, app, cmd);
+        }
+
+        if self.panel.clicked_outside(ctx) {
+            return Transition::Pop;
+        }
+
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::PreviousState
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
+
+const CONSOLE_HELP: [&str; 5] = [
+    "goto r42 -- warp to road 42, or anything else warping by ID understands",
+    "time 18:30:00 -- run the simulation until then",
+    "layer delay -- toggle a layer",
+    "layers -- list all layers",
+    "dev -- toggle dev mode",
+];
+
+/// Evaluates short commands, for people who know exactly what they want
+pub struct DebugConsole {
+    panel: Panel,
+    history: Vec<String>,
+}
+
+impl DebugConsole {
+    pub fn new_state(ctx: &mut EventCtx) -> Box<dyn State<App>> {
+        let mut console = DebugConsole {
+            panel: Panel::empty(ctx),
+            history: CONSOLE_HELP.iter().map(|x| x.to_string()).collect(),
+        };
+        console.recreate_panel(ctx);
+        Box::new(console)
+    }
+
+    fn recreate_panel(&mut self, ctx: &mut EventCtx) {
+        let mut txt = Text::new();
+        // Only the most recent output fits
+        for line in self.history.iter().rev().take(15).rev() {
+            txt.add_line(line);
+        }
+        self.panel = Panel::new_builder(Widget::col(vec![
+            Widget::row(vec![
+                Line("Debug console").small_heading().into_widget(ctx),
+                ctx.style().btn_close_widget(ctx),
+            ]),
+            txt.into_widget(ctx),
+            TextBox::default_widget(ctx, "input", String::new()),
+            ctx.style()
+                .btn_outline
+                .text("run")
+                .hotkey(Key::Enter)
+                .build_def(ctx),
+        ]))
+        .build(ctx);
+    }
+
+    /// Either leaves the console by returning a transition, or leaves some output in the history
+    fn evaluate(&mut self, ctx: &mut EventCtx, app: &mut App, line: &str) -> Option<Transition> {
+        self.history.push(format!("> {}", line));
+        let (cmd, arg) = match line.split_once(' ') {
+            Some((cmd, arg)) => (cmd, arg.trim()),
+            None => (line, ""),
+        };
+        match cmd {
+            "goto" => {
+                return Some(warp_to_id(ctx, app, arg));
+            }
+            "time" => match Time::parse(arg) {
+                Ok(t) if t > app.primary.sim.time() => {
+                    return Some(Transition::Replace(TimeWarpScreen::new_state(
+                        ctx, app, t, None,
+                    )));
+                }
+                Ok(_) => {
+                    self.history
+                        .push("The simulation can only jump forwards".to_string());
+                }
+                Err(err) => {
+                    self.history.push(format!("Bad time: {}", err));
+                }
+            },
+            "layer" => {
+                if !PickLayer::names(app).into_iter().any(|name| name == arg) {
+                    self.history
+                        .push(format!("No layer called {}; try \"layers\"", arg));
+                } else if app.primary.layer.as_ref().and_then(|l| l.name()) == Some(arg) {
+                    app.primary.layer = None;
+                    self.history.push(format!("Hid {}", arg));
+                } else if let Some(state) = PickLayer::activate(ctx, app, arg) {
+                    return Some(Transition::Replace(state));
+                } else {
+                    self.history.push(format!("Showing {}", arg));
+                }
+            }
+            "layers" => {
+                self.history.push(PickLayer::names(app).join(", "));
+            }
+            "dev" => {
+                app.opts.dev = !app.opts.dev;
+                self.history.push(format!(
+                    "Dev mode is {}",
+                    if app.opts.dev { "on" } else { "off" }
+                ));
+            }
+            "help" => {
+                self.history
+                    .extend(CONSOLE_HELP.iter().map(|x| x.to_string()));
+            }
+            _ => {
+                self.history
+                    .push(format!("Unknown command {}; try \"help\"", cmd));
+            }
+        }
+        None
+    }
+}
+
+impl State<App> for DebugConsole {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "run" => {
+                    let line = self.panel.text_box("input");
+                    let line = line.trim();
+                    if !line.is_empty() {
+                        if let Some(t) = self.evaluate(ctx, app, line) {
+                            return t;
+                        }
+                        self.recreate_panel(ctx);
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        Transition::Keep
+    }
+
+    // Keep the map visible, so toggling layers shows something
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::PreviousState
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+    }
+}
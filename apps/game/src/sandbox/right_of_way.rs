@@ -0,0 +1,255 @@
+use geom::{ArrowCap, Distance};
+use map_gui::render::{DrawOptions, BIG_ARROW_THICKNESS};
+use map_gui::AppLike;
+use map_model::{IntersectionID, Movement, TurnPriority};
+use widgetry::tools::ColorLegend;
+use widgetry::{
+    Color, DrawBaselayer, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Panel, State, Text, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+
+/// Shows which movements through an intersection must yield to which, according to the same stop
+/// sign and traffic signal rules the simulation uses.
+pub struct RightOfWay {
+    i: IntersectionID,
+    // 0 means all movements, otherwise one particular movement
+    idx: usize,
+    // Only for traffic signals
+    stage: usize,
+    panel: Panel,
+    draw: Drawable,
+}
+
+impl RightOfWay {
+    pub fn new_state(ctx: &mut EventCtx, app: &App, i: IntersectionID) -> Box<dyn State<App>> {
+        let stage = if app.primary.map.maybe_get_traffic_signal(i).is_some() {
+            app.primary.sim.current_stage_and_remaining_time(i).0
+        } else {
+            0
+        };
+        let mut state = RightOfWay {
+            i,
+            idx: 0,
+            stage,
+            panel: Panel::empty(ctx),
+            draw: Drawable::empty(ctx),
+        };
+        state.recalculate(ctx, app);
+        Box::new(state)
+    }
+
+    fn recalculate(&mut self, ctx: &mut EventCtx, app: &App) {
+        self.panel = self.make_panel(ctx, app);
+
+        let movements: Vec<&Movement> = app.map().get_i(self.i).movements.values().collect();
+        let mut batch = GeomBatch::new();
+        if self.idx == 0 {
+            for m in &movements {
+                let color = match self.priority(app, m) {
+                    TurnPriority::Protected => PROTECTED,
+                    TurnPriority::Yield => YIELD,
+                    TurnPriority::Banned => BANNED,
+                };
+                batch.push(
+                    color.alpha(0.8),
+                    m.geom.make_arrow(BIG_ARROW_THICKNESS, ArrowCap::Triangle),
+                );
+            }
+        } else {
+            let current = movements[self.idx - 1];
+            let our_priority = self.priority(app, current);
+            for m in &movements {
+                if m.id == current.id || !current.conflicts_with(m) {
+                    continue;
+                }
+                let color = match relationship(our_priority, self.priority(app, m)) {
+                    Some(color) => color,
+                    None => {
+                        continue;
+                    }
+                };
+                batch.extend(
+                    color,
+                    m.geom.dashed_arrow(
+                        BIG_ARROW_THICKNESS,
+                        Distance::meters(1.0),
+                        Distance::meters(0.5),
+                        ArrowCap::Triangle,
+                    ),
+                );
+            }
+            batch.push(
+                CURRENT_MOVEMENT,
+                current
+                    .geom
+                    .make_arrow(BIG_ARROW_THICKNESS, ArrowCap::Triangle),
+            );
+        }
+        self.draw = ctx.upload(batch);
+    }
+
+    fn priority(&self, app: &App, m: &Movement) -> TurnPriority {
+        let map = app.map();
+        if let Some(signal) = map.maybe_get_traffic_signal(self.i) {
+            if signal.is_banned_turn_on_red(self.stage, m.id, map.get_i(self.i)) {
+                return TurnPriority::Banned;
+            }
+            signal.stages[self.stage].get_priority_of_movement(m.id)
+        } else if let Some(sign) = map.maybe_get_stop_sign(self.i) {
+            sign.get_priority(m.members[0], map)
+        } else {
+            TurnPriority::Protected
+        }
+    }
+
+    fn make_panel(&self, ctx: &mut EventCtx, app: &App) -> Panel {
+        let map = app.map();
+        let num_movements = map.get_i(self.i).movements.len();
+
+        let mut col = vec![Widget::row(vec![
+            Text::from(
+                Line(format!(
+                    "Right-of-way at {}",
+                    map.get_i(self.i).name(app.opts().language.as_ref(), map)
+                ))
+                .small_heading(),
+            )
+            .into_widget(ctx),
+            Widget::vert_separator(ctx, 50.0),
+            ctx.style()
+                .btn_prev()
+                .disabled(self.idx == 0)
+                .hotkey(Key::LeftArrow)
+                .build_widget(ctx, "previous movement"),
+            Text::from(Line(format!("{}/{}", self.idx, num_movements)).secondary())
+                .into_widget(ctx)
+                .centered_vert(),
+            ctx.style()
+                .btn_next()
+                .disabled(self.idx == num_movements)
+                .hotkey(Key::RightArrow)
+                .build_widget(ctx, "next movement"),
+            ctx.style().btn_close_widget(ctx),
+        ])];
+
+        if let Some(signal) = map.maybe_get_traffic_signal(self.i) {
+            col.push(Widget::row(vec![
+                ctx.style()
+                    .btn_prev()
+                    .disabled(self.stage == 0)
+                    .hotkey(Key::UpArrow)
+                    .build_widget(ctx, "previous stage"),
+                Text::from(Line(format!(
+                    "Stage {}/{}",
+                    self.stage + 1,
+                    signal.stages.len()
+                )))
+                .into_widget(ctx)
+                .centered_vert(),
+                ctx.style()
+                    .btn_next()
+                    .disabled(self.stage == signal.stages.len() - 1)
+                    .hotkey(Key::DownArrow)
+                    .build_widget(ctx, "next stage"),
+            ]));
+        }
+
+        if self.idx == 0 {
+            col.push(ColorLegend::row(ctx, PROTECTED, "has priority"));
+            col.push(ColorLegend::row(ctx, YIELD, "must yield"));
+            if map.maybe_get_traffic_signal(self.i).is_some() {
+                col.push(ColorLegend::row(ctx, BANNED, "not allowed this stage"));
+            }
+        } else {
+            col.push(ColorLegend::row(ctx, CURRENT_MOVEMENT, "current movement"));
+            col.push(ColorLegend::row(
+                ctx,
+                YIELDS_TO_US,
+                "yields to this movement",
+            ));
+            col.push(ColorLegend::row(
+                ctx,
+                WE_YIELD,
+                "this movement yields to it",
+            ));
+            col.push(ColorLegend::row(
+                ctx,
+                FIRST_COME,
+                "equal priority; first come, first served",
+            ));
+        }
+
+        Panel::new_builder(Widget::col(col))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx)
+    }
+}
+
+impl State<App> for RightOfWay {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        if let Outcome::Clicked(x) = self.panel.event(ctx) {
+            match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "previous movement" => {
+                    self.idx -= 1;
+                }
+                "next movement" => {
+                    self.idx += 1;
+                }
+                "previous stage" => {
+                    self.stage -= 1;
+                }
+                "next stage" => {
+                    self.stage += 1;
+                }
+                _ => unreachable!(),
+            }
+            self.recalculate(ctx, app);
+        }
+
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        let mut opts = DrawOptions::new();
+        opts.suppress_traffic_signal_details.push(self.i);
+        app.draw_with_opts(g, opts);
+
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+    }
+}
+
+/// How does a conflicting movement relate to ours? None if it can't happen at the same time.
+fn relationship(ours: TurnPriority, theirs: TurnPriority) -> Option<Color> {
+    if ours == TurnPriority::Banned || theirs == TurnPriority::Banned {
+        return None;
+    }
+    if ours == theirs {
+        Some(FIRST_COME)
+    } else if ours == TurnPriority::Protected {
+        Some(YIELDS_TO_US)
+    } else {
+        Some(WE_YIELD)
+    }
+}
+
+// Since this is extremely localized and probably changing, not going to put this in ColorScheme.
+const PROTECTED: Color = Color::GREEN;
+const YIELD: Color = Color::YELLOW;
+const BANNED: Color = Color::RED;
+
+const CURRENT_MOVEMENT: Color = Color::BLUE;
+const YIELDS_TO_US: Color = Color::GREEN.alpha(0.8);
+const WE_YIELD: Color = Color::RED.alpha(0.8);
+const FIRST_COME: Color = Color::YELLOW.alpha(0.8);
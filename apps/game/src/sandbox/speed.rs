@@ -494,6 +494,7 @@ impl TimePanel {
                     Duration::seconds(0.033),
                     &mut app.primary.sim_cb,
                 );
+                crate::edit::apply_incident_edits(ctx, app);
                 app.recalculate_current_selection(ctx);
             }
         }
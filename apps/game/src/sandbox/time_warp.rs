@@ -284,6 +284,7 @@ impl State<App> for TimeWarpScreen {
                 Duration::seconds(0.033),
                 &mut app.primary.sim_cb,
             );
+            crate::edit::apply_incident_edits(ctx, app);
             #[allow(clippy::never_loop)]
             for (t, maybe_i, alert) in app.primary.sim.clear_alerts() {
                 // TODO Just the first :(
@@ -272,7 +272,7 @@ fn find_cells(
     let mut visited = BTreeSet::new();
 
     for start in interior_roads {
-        if visited.contains(start) || map.get_r(*start).modal_filter.is_some() {
+        if visited.contains(start) || filter_dist(map, *start).is_some() {
             continue;
         }
         let start = *start;
@@ -300,7 +300,11 @@ fn find_cells(
     }
 
     // Filtered roads right along the perimeter have a tiny cell
-    for (road, filter) in map.all_roads_with_modal_filter() {
+    for road in map.all_roads() {
+        let dist = match filter_dist(map, road.id) {
+            Some(dist) => dist,
+            None => continue,
+        };
         if borders.contains(&road.src_i) {
             let mut cell = Cell {
                 roads: BTreeMap::new(),
@@ -310,7 +314,7 @@ fn find_cells(
                 road.id,
                 DistanceInterval {
                     start: Distance::ZERO,
-                    end: filter.dist,
+                    end: dist,
                 },
             );
             cells.push(cell);
@@ -323,7 +327,7 @@ fn find_cells(
             cell.roads.insert(
                 road.id,
                 DistanceInterval {
-                    start: filter.dist,
+                    start: dist,
                     end: road.length(),
                 },
             );
@@ -346,7 +350,7 @@ fn floodfill(
     let mut queue = vec![start];
 
     // The caller should handle this case
-    assert!(map.get_r(start).modal_filter.is_none());
+    assert!(filter_dist(map, start).is_none());
     assert!(crate::is_driveable(map.get_r(start), map));
 
     while !queue.is_empty() {
@@ -379,7 +383,7 @@ fn floodfill(
                         continue;
                     }
                 }
-                if let Some(ref filter) = map.get_r(*next).modal_filter {
+                if let Some(dist) = filter_dist(map, *next) {
                     // Which ends of the filtered road have we reached?
                     let mut visited_start = next_road.src_i == i;
                     let mut visited_end = next_road.dst_i == i;
@@ -395,15 +399,11 @@ fn floodfill(
                     visited_roads.insert(
                         *next,
                         DistanceInterval {
-                            start: if visited_start {
-                                Distance::ZERO
-                            } else {
-                                filter.dist
-                            },
+                            start: if visited_start { Distance::ZERO } else { dist },
                             end: if visited_end {
                                 next_road.length()
                             } else {
-                                filter.dist
+                                dist
                             },
                         },
                     );
@@ -429,3 +429,25 @@ fn floodfill(
         borders: cell_borders,
     }
 }
+
+/// Where a modal filter cuts this road into two cells. Both sides of a dual carriageway are one
+/// street, so a filter on either side cuts all of them at the same point.
+fn filter_dist(map: &Map, r: RoadID) -> Option<Distance> {
+    let road = map.get_r(r);
+    if let Some(ref filter) = road.modal_filter {
+        return Some(filter.dist);
+    }
+    for other in map.dual_carriageway(r) {
+        let other = map.get_r(other);
+        if let Some(ref filter) = other.modal_filter {
+            let pt = other.center_pts.must_dist_along(filter.dist).0;
+            if let Some((dist, _)) = road
+                .center_pts
+                .dist_along_of_point(road.center_pts.project_pt(pt))
+            {
+                return Some(dist);
+            }
+        }
+    }
+    None
+}
@@ -37,6 +37,9 @@ pub fn render_poi_icons(ctx: &EventCtx, map: &Map) -> Drawable {
         let (name, icon) = match extra.kind {
             ExtraPOIType::LondonUndergroundStation(ref name) => (name, &tfl),
             ExtraPOIType::NationalRailStation(ref name) => (name, &national_rail),
+            ExtraPOIType::Tree { .. } => {
+                continue;
+            }
         };
         batch.append(icon.clone().centered_on(extra.pt));
         batch.append(
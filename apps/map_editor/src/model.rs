@@ -544,6 +544,7 @@ impl Model {
                 public_garage_name: None,
                 num_parking_spots: 0,
                 amenities: Vec::new(),
+                land_use: None,
             },
         );
         self.bldg_added(ctx, id);
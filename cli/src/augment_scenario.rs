@@ -5,14 +5,22 @@ use rand_xorshift::XorShiftRng;
 use abstutil::{prettyprint_usize, Timer};
 use geom::{Distance, Duration, FindClosest};
 use map_model::{AmenityType, BuildingID, Map};
-use synthpop::{IndividTrip, Scenario, ScenarioModifier, TripEndpoint, TripMode, TripPurpose};
+use synthpop::{
+    DeliveryDemand, GatewayDemand, IndividTrip, MicromobilityStation, Scenario, ScenarioModifier,
+    TripEndpoint, TripMode, TripPurpose,
+};
 
+#[allow(clippy::too_many_arguments)]
 pub fn run(
     input_scenario: String,
     should_add_return_trips: bool,
     should_add_lunch_trips: bool,
     modifiers: Vec<ScenarioModifier>,
     should_delete_cancelled_trips: bool,
+    gateway_demand: Option<String>,
+    delivery_demand: Option<String>,
+    micromobility: Option<String>,
+    should_add_park_and_ride: bool,
     rng_seed: u64,
 ) {
     let mut rng = XorShiftRng::seed_from_u64(rng_seed);
@@ -27,6 +35,41 @@ pub fn run(
     if should_add_lunch_trips {
         add_lunch_trips(&mut scenario, &map, &mut rng, &mut timer);
     }
+    if let Some(path) = gateway_demand {
+        let demand: GatewayDemand = abstio::read_json(path, &mut timer);
+        let people = demand.generate(&map, &mut rng);
+        println!(
+            "Added {} people crossing the map boundary",
+            prettyprint_usize(people.len())
+        );
+        scenario.people.extend(people);
+    }
+    if let Some(path) = delivery_demand {
+        let demand: DeliveryDemand = abstio::read_json(path, &mut timer);
+        let people = demand.generate(&map, &mut rng);
+        println!(
+            "Added {} vans and people collecting parcels",
+            prettyprint_usize(people.len())
+        );
+        scenario.people.extend(people);
+    }
+    if let Some(path) = micromobility {
+        let stations: Vec<MicromobilityStation> = abstio::read_json(path, &mut timer);
+        println!(
+            "Added {} shared vehicles at {} places",
+            prettyprint_usize(stations.iter().map(|s| s.vehicles).sum()),
+            prettyprint_usize(stations.len())
+        );
+        scenario.micromobility.extend(stations);
+    }
+    if should_add_park_and_ride {
+        let lots = synthpop::park_and_ride_candidates(&map);
+        println!(
+            "Marked {} parking lots as park-and-ride",
+            prettyprint_usize(lots.len())
+        );
+        scenario.park_and_ride.extend(lots);
+    }
 
     for m in modifiers {
         scenario = m.apply(&map, scenario, &mut rng);
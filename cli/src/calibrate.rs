@@ -0,0 +1,298 @@
+//! Compare a scenario against observed traffic counts, and optionally scale how many people make
+//! trips until the simulated volumes match better.
+//!
+//! The counts are a CSV file with `latitude`, `longitude`, `hour` (0-23) and `count` columns, plus
+//! an optional `mode` column: `car` (the default, counting cars and buses), `bike`, or
+//! `pedestrian`. Each count gets matched to the nearest road, and counts both directions.
+//!
+//! Fit is measured with the GEH statistic, the usual check for traffic models. A count with a GEH
+//! under 5 is considered a good match, and models are typically expected to match at least 85%
+//! of counts.
+//!
+//! Each adjustment scales the people whose first trip departs in each hour, by the ratio of
+//! observed to simulated traffic at that hour. This is crude; it can't fix where people travel,
+//! only how many do. Routing isn't adjusted.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::Deserialize;
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Distance, Duration, FindClosest, LonLat};
+use map_model::{Map, RoadID};
+use sim::{AgentType, AlertHandler, Analytics, Sim, SimFlags, SimOptions};
+use synthpop::Scenario;
+
+// Counts further than this from any road are ignored
+const SNAP_DISTANCE: Distance = Distance::const_meters(20.0);
+// Don't scale demand too much in one step, since volumes on congested roads don't respond
+// linearly
+const MAX_SCALE_PER_ITERATION: f64 = 2.0;
+
+struct ObservedCount {
+    road: RoadID,
+    hour: usize,
+    agent_types: BTreeSet<AgentType>,
+    count: usize,
+}
+
+#[derive(Deserialize)]
+struct CsvRecord {
+    latitude: f64,
+    longitude: f64,
+    hour: usize,
+    count: usize,
+    mode: Option<String>,
+}
+
+pub fn run(
+    scenario_path: String,
+    counts_path: String,
+    iterations: usize,
+    output: Option<String>,
+    rng_seed: u64,
+) -> Result<()> {
+    let mut timer = Timer::new("calibrate scenario");
+    let mut scenario: Scenario = abstio::must_read_object(scenario_path, &mut timer);
+    let mut map = Map::load_synchronously(scenario.map_name.path(), &mut timer);
+    let observed = import_counts(&map, &counts_path)?;
+    if observed.is_empty() {
+        bail!("None of the counts in {} are near a road", counts_path);
+    }
+    println!(
+        "Comparing against {} counts on {} roads",
+        prettyprint_usize(observed.len()),
+        prettyprint_usize(
+            observed
+                .iter()
+                .map(|c| c.road)
+                .collect::<BTreeSet<_>>()
+                .len()
+        )
+    );
+
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let mut best: Option<(f64, Scenario, Vec<usize>)> = None;
+    // One extra run to check the last adjustment
+    for iteration in 0..=iterations {
+        let analytics = simulate(&mut map, &scenario, &mut timer);
+        let simulated: Vec<usize> = observed
+            .iter()
+            .map(|c| simulated_count(&analytics, c))
+            .collect();
+        let fit = describe_fit(&observed, &simulated);
+        println!(
+            "Iteration {}: {} people, {}",
+            iteration,
+            prettyprint_usize(scenario.people.len()),
+            fit.1
+        );
+        if best.as_ref().map(|(x, _, _)| fit.0 > *x).unwrap_or(true) {
+            best = Some((fit.0, scenario.clone(), simulated.clone()));
+        }
+        if iteration != iterations {
+            scale_demand(&mut scenario, &observed, &simulated, &mut rng);
+        }
+    }
+
+    let (pct_good, mut best_scenario, simulated) = best.unwrap();
+    if iterations > 0 {
+        best_scenario.scenario_name = format!("{}_calibrated", best_scenario.scenario_name);
+        println!(
+            "Saving the best fit, with {:.1}% of counts matching, as {}",
+            pct_good,
+            abstio::path_scenario(&best_scenario.map_name, &best_scenario.scenario_name)
+        );
+        best_scenario.save();
+    }
+    if let Some(path) = output {
+        let mut out = String::new();
+        writeln!(out, "road,osm_way_id,hour,observed,simulated,geh")?;
+        for (c, sim_count) in observed.iter().zip(simulated) {
+            writeln!(
+                out,
+                "{},{},{},{},{},{:.2}",
+                c.road.0,
+                map.get_r(c.road).orig_id.osm_way_id.0,
+                c.hour,
+                c.count,
+                sim_count,
+                geh(sim_count as f64, c.count as f64)
+            )?;
+        }
+        println!("Wrote the comparison to {}", abstio::write_file(path, out)?);
+    }
+    Ok(())
+}
+
+/// The GEH statistic comparing a modelled hourly volume with an observed one
+fn geh(modelled: f64, observed: f64) -> f64 {
+    if modelled + observed == 0.0 {
+        return 0.0;
+    }
+    (2.0 * (modelled - observed).powi(2) / (modelled + observed)).sqrt()
+}
+
+fn import_counts(map: &Map, path: &str) -> Result<Vec<ObservedCount>> {
+    let mut closest: FindClosest<RoadID> = FindClosest::new();
+    for r in map.all_roads() {
+        closest.add(r.id, r.center_pts.points());
+    }
+
+    let mut counts = Vec::new();
+    let mut unsnapped = 0;
+    for rec in csv::Reader::from_reader(fs_err::File::open(path)?).deserialize() {
+        let rec: CsvRecord = match rec {
+            Ok(rec) => rec,
+            Err(err) => {
+                warn!("Skipping row: {}", err);
+                continue;
+            }
+        };
+        if rec.hour > 23 {
+            warn!("Skipping count with hour {}", rec.hour);
+            continue;
+        }
+        let agent_types = match rec.mode.as_ref().map(|x| x.to_lowercase()).as_deref() {
+            None | Some("") | Some("car") | Some("vehicle") => {
+                vec![AgentType::Car, AgentType::Bus]
+            }
+            Some("bike") | Some("bicycle") => vec![AgentType::Bike],
+            Some("pedestrian") | Some("walk") => vec![AgentType::Pedestrian],
+            Some(x) => {
+                warn!("Unknown mode {}", x);
+                continue;
+            }
+        };
+        let pt = LonLat::new(rec.longitude, rec.latitude).to_pt(map.get_gps_bounds());
+        match closest.closest_pt(pt, SNAP_DISTANCE) {
+            Some((road, _)) => counts.push(ObservedCount {
+                road,
+                hour: rec.hour,
+                agent_types: agent_types.into_iter().collect(),
+                count: rec.count,
+            }),
+            None => {
+                unsnapped += 1;
+            }
+        }
+    }
+    if unsnapped > 0 {
+        warn!(
+            "{} counts are too far from any road",
+            prettyprint_usize(unsnapped)
+        );
+    }
+    Ok(counts)
+}
+
+fn simulate(map: &mut Map, scenario: &Scenario, timer: &mut Timer) -> Analytics {
+    let mut opts = SimOptions::new("calibration");
+    opts.alerts = AlertHandler::Silence;
+    let mut sim = Sim::new(map, opts);
+    // Every iteration needs the same rng seed, so only the demand changes
+    let mut rng = SimFlags::for_test("calibration").make_rng();
+    sim.instantiate(scenario, map, &mut rng, timer);
+    sim.timed_step(map, Duration::hours(24), &mut None, timer);
+    sim.get_analytics().clone()
+}
+
+fn simulated_count(analytics: &Analytics, c: &ObservedCount) -> usize {
+    c.agent_types
+        .iter()
+        .map(|agent_type| {
+            analytics
+                .road_thruput
+                .counts
+                .get(&(c.road, *agent_type, c.hour))
+                .cloned()
+                .unwrap_or(0)
+        })
+        .sum()
+}
+
+/// Returns the percent of counts with a GEH under 5, along with a summary
+fn describe_fit(observed: &[ObservedCount], simulated: &[usize]) -> (f64, String) {
+    let gehs: Vec<f64> = observed
+        .iter()
+        .zip(simulated)
+        .map(|(c, s)| geh(*s as f64, c.count as f64))
+        .collect();
+    let good = gehs.iter().filter(|x| **x < 5.0).count();
+    let pct_good = 100.0 * (good as f64) / (gehs.len() as f64);
+    let mean_geh = gehs.iter().sum::<f64>() / (gehs.len() as f64);
+    let total_observed: usize = observed.iter().map(|c| c.count).sum();
+    let total_simulated: usize = simulated.iter().sum();
+    (
+        pct_good,
+        format!(
+            "{:.1}% of counts have GEH < 5, mean GEH {:.2}, {} observed vs {} simulated",
+            pct_good,
+            mean_geh,
+            prettyprint_usize(total_observed),
+            prettyprint_usize(total_simulated)
+        ),
+    )
+}
+
+/// Removes or duplicates people, depending on how their first trip's departure hour compares
+fn scale_demand(
+    scenario: &mut Scenario,
+    observed: &[ObservedCount],
+    simulated: &[usize],
+    rng: &mut XorShiftRng,
+) {
+    let mut per_hour: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+    for (c, s) in observed.iter().zip(simulated) {
+        let entry = per_hour.entry(c.hour).or_insert((0, 0));
+        entry.0 += c.count;
+        entry.1 += *s;
+    }
+    let scale: BTreeMap<usize, f64> = per_hour
+        .into_iter()
+        .filter(|(_, (_, s))| *s > 0)
+        .map(|(hour, (o, s))| {
+            let ratio = (o as f64) / (s as f64);
+            (
+                hour,
+                ratio.clamp(1.0 / MAX_SCALE_PER_ITERATION, MAX_SCALE_PER_ITERATION),
+            )
+        })
+        .collect();
+
+    let mut people = Vec::new();
+    for person in scenario.people.drain(..) {
+        let ratio = person
+            .trips
+            .first()
+            .and_then(|trip| scale.get(&trip.depart.get_hours()))
+            .cloned()
+            .unwrap_or(1.0);
+        let mut copies = ratio.floor() as usize;
+        if rng.gen_bool(ratio.fract()) {
+            copies += 1;
+        }
+        for _ in 0..copies {
+            people.push(person.clone());
+        }
+    }
+    scenario.people = people;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geh() {
+        assert_eq!(geh(0.0, 0.0), 0.0);
+        assert_eq!(geh(100.0, 100.0), 0.0);
+        // The usual calibration target is below 5
+        assert!((geh(150.0, 100.0) - 4.47).abs() < 0.01);
+        assert_eq!(geh(150.0, 100.0), geh(100.0, 150.0));
+    }
+}
@@ -0,0 +1,680 @@
+//! Export a map, and optionally a scenario, to other traffic simulators, so results can be
+//! cross-validated.
+//!
+//! For SUMO, this writes the plain XML files that netconvert reads: `<prefix>.nod.xml`,
+//! `<prefix>.edg.xml`, `<prefix>.con.xml` for the lane connections, and `<prefix>.tll.xml` for
+//! traffic signals. If netconvert is installed, it then builds `<prefix>.net.xml`, working out
+//! junction shapes and right-of-way. A scenario becomes `<prefix>.rou.xml`. Pedestrian crossings
+//! aren't represented, and variable traffic signal stages use their minimum duration.
+//!
+//! For MATSim, this writes `<prefix>_network.xml` and `<prefix>_plans.xml`. Only directed roads
+//! that some vehicle can use become links.
+//!
+//! Both formats use the map's own coordinates in meters, with the Y axis flipped to point north.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::process::Command;
+
+use anyhow::{bail, Result};
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Pt2D, Time};
+use map_model::{
+    osm, DirectedRoadID, Direction, DrivingSide, Intersection, IntersectionControl, IntersectionID,
+    LaneID, LaneType, Map, Turn, TurnPriority, TurnType,
+};
+use synthpop::{Scenario, TripEndpoint, TripMode};
+
+pub fn run(map: String, format: String, scenario: Option<String>, prefix: String) -> Result<()> {
+    let mut timer = Timer::new("export network");
+    let map = Map::load_synchronously(map, &mut timer);
+    let scenario: Option<Scenario> =
+        scenario.map(|path| abstio::must_read_object(path, &mut timer));
+
+    match format.as_ref() {
+        "sumo" => {
+            let net = Network::new(&map);
+            let (connections, tl_logics) = net.sumo_connections(&map);
+            for (suffix, contents) in [
+                ("nod", sumo_nodes(&map)),
+                ("edg", net.sumo_edges(&map)),
+                ("con", connections),
+                ("tll", tl_logics),
+            ] {
+                println!(
+                    "Wrote {}",
+                    abstio::write_file(format!("{}.{}.xml", prefix, suffix), contents)?
+                );
+            }
+            if let Some(scenario) = scenario {
+                println!(
+                    "Wrote {}",
+                    abstio::write_file(
+                        format!("{}.rou.xml", prefix),
+                        sumo_routes(&net, &map, &scenario)
+                    )?
+                );
+            }
+            run_netconvert(&map, &prefix)?;
+        }
+        "matsim" => {
+            println!(
+                "Wrote {}",
+                abstio::write_file(format!("{}_network.xml", prefix), matsim_network(&map))?
+            );
+            if let Some(scenario) = scenario {
+                println!(
+                    "Wrote {}",
+                    abstio::write_file(
+                        format!("{}_plans.xml", prefix),
+                        matsim_plans(&map, &scenario)
+                    )?
+                );
+            }
+        }
+        x => bail!("Unknown format {}; use sumo or matsim", x),
+    }
+    Ok(())
+}
+
+/// Builds `<prefix>.net.xml` from the plain files, if netconvert is installed
+fn run_netconvert(map: &Map, prefix: &str) -> Result<()> {
+    let mut args = vec![
+        format!("--node-files={}.nod.xml", prefix),
+        format!("--edge-files={}.edg.xml", prefix),
+        format!("--connection-files={}.con.xml", prefix),
+        format!("--tllogic-files={}.tll.xml", prefix),
+        format!("--output-file={}.net.xml", prefix),
+    ];
+    if map.get_config().driving_side == DrivingSide::Left {
+        args.push("--lefthand".to_string());
+    }
+    match Command::new("netconvert").args(&args).status() {
+        Ok(status) if status.success() => {
+            println!("Wrote {}.net.xml", prefix);
+            Ok(())
+        }
+        Ok(status) => bail!("netconvert failed: {}", status),
+        Err(err) => {
+            println!(
+                "Couldn't run netconvert ({}). To build the SUMO network, run:\nnetconvert {}",
+                err,
+                args.join(" ")
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Which lanes are represented in SUMO, and what they're called
+struct Network {
+    /// Rightmost lane first, as SUMO numbers them
+    edges: Vec<(DirectedRoadID, Vec<LaneID>)>,
+    lane_ids: HashMap<LaneID, String>,
+}
+
+impl Network {
+    fn new(map: &Map) -> Network {
+        let mut net = Network {
+            edges: Vec::new(),
+            lane_ids: HashMap::new(),
+        };
+        for road in map.all_roads() {
+            for dir in [Direction::Fwd, Direction::Back] {
+                // Lanes are listed left-to-right along the road's forwards direction
+                let mut lanes: Vec<LaneID> = road
+                    .lanes
+                    .iter()
+                    .filter(|l| l.dir == dir && sumo_vclasses(l.lane_type).is_some())
+                    .map(|l| l.id)
+                    .collect();
+                if lanes.is_empty() {
+                    continue;
+                }
+                if dir == Direction::Fwd {
+                    lanes.reverse();
+                }
+                let dr = DirectedRoadID { road: road.id, dir };
+                for (idx, l) in lanes.iter().enumerate() {
+                    net.lane_ids.insert(*l, format!("{}_{}", edge_id(dr), idx));
+                }
+                net.edges.push((dr, lanes));
+            }
+        }
+        net
+    }
+
+    fn sumo_edges(&self, map: &Map) -> String {
+        let mut out = String::new();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(out, "<edges>").unwrap();
+        for (dr, lanes) in &self.edges {
+            let road = map.get_r(dr.road);
+            let mut center = road.center_pts.points().clone();
+            if dr.dir == Direction::Back {
+                center.reverse();
+            }
+            // Two-way roads have lanes on either side of the center line. One-way roads are
+            // centered on it.
+            let two_way = self
+                .edges
+                .iter()
+                .any(|(other, _)| other.road == dr.road && other.dir != dr.dir);
+            writeln!(
+                out,
+                r#"    <edge id="{}" from="{}" to="{}" priority="{}" numLanes="{}" speed="{:.2}" name="{}" shape="{}" spreadType="{}">"#,
+                edge_id(*dr),
+                junction_id(dr.src_i(map)),
+                junction_id(dr.dst_i(map)),
+                edge_priority(road.get_rank()),
+                lanes.len(),
+                road.speed_limit.inner_meters_per_second(),
+                escape(&road.get_name(None)),
+                center
+                    .into_iter()
+                    .map(|pt| xy(map, pt))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                if two_way { "right" } else { "center" }
+            )
+            .unwrap();
+            for (idx, l) in lanes.iter().enumerate() {
+                let lane = map.get_l(*l);
+                writeln!(
+                    out,
+                    r#"        <lane index="{}" allow="{}" width="{:.2}"/>"#,
+                    idx,
+                    sumo_vclasses(lane.lane_type).unwrap(),
+                    lane.width.inner_meters(),
+                )
+                .unwrap();
+            }
+            writeln!(out, "    </edge>").unwrap();
+        }
+        writeln!(out, "</edges>").unwrap();
+        out
+    }
+
+    /// Returns the connections between lanes, and the traffic signal programs. netconvert works
+    /// out which connections conflict and who yields.
+    fn sumo_connections(&self, map: &Map) -> (String, String) {
+        let mut connections = String::new();
+        writeln!(connections, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(connections, "<connections>").unwrap();
+        let mut tl_logics = String::new();
+        writeln!(tl_logics, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(tl_logics, "<tlLogics>").unwrap();
+
+        for i in map.all_intersections() {
+            // In the order traffic signals index them
+            let turns: Vec<_> = i
+                .turns
+                .iter()
+                .filter(|t| {
+                    matches!(
+                        t.turn_type,
+                        TurnType::Straight | TurnType::Right | TurnType::Left | TurnType::UTurn
+                    ) && self.lane_ids.contains_key(&t.id.src)
+                        && self.lane_ids.contains_key(&t.id.dst)
+                })
+                .collect();
+            let connection_attrs = |t: &Turn| {
+                format!(
+                    r#"from="{}" to="{}" fromLane="{}" toLane="{}""#,
+                    edge_id(map.get_l(t.id.src).get_directed_parent()),
+                    edge_id(map.get_l(t.id.dst).get_directed_parent()),
+                    lane_index(&self.lane_ids[&t.id.src]),
+                    lane_index(&self.lane_ids[&t.id.dst]),
+                )
+            };
+            for t in &turns {
+                writeln!(connections, "    <connection {}/>", connection_attrs(t)).unwrap();
+            }
+
+            if junction_type(i, map) != "traffic_light" {
+                continue;
+            }
+            let signal = map.get_traffic_signal(i.id);
+            writeln!(
+                tl_logics,
+                r#"    <tlLogic id="{}" type="static" programID="0" offset="{:.2}">"#,
+                junction_id(i.id),
+                signal.offset.inner_seconds()
+            )
+            .unwrap();
+            for stage in &signal.stages {
+                let state: String = turns
+                    .iter()
+                    .map(
+                        |t| match stage.get_priority_of_movement(i.turn_to_movement(t.id).0) {
+                            TurnPriority::Protected => 'G',
+                            TurnPriority::Yield => 'g',
+                            TurnPriority::Banned => 'r',
+                        },
+                    )
+                    .collect();
+                writeln!(
+                    tl_logics,
+                    r#"        <phase duration="{:.2}" state="{}"/>"#,
+                    stage.stage_type.simple_duration().inner_seconds(),
+                    state
+                )
+                .unwrap();
+            }
+            writeln!(tl_logics, "    </tlLogic>").unwrap();
+            for (link_idx, t) in turns.into_iter().enumerate() {
+                writeln!(
+                    tl_logics,
+                    r#"    <connection {} tl="{}" linkIndex="{}"/>"#,
+                    connection_attrs(t),
+                    junction_id(i.id),
+                    link_idx
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(connections, "</connections>").unwrap();
+        writeln!(tl_logics, "</tlLogics>").unwrap();
+        (connections, tl_logics)
+    }
+}
+
+fn sumo_nodes(map: &Map) -> String {
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(out, "<nodes>").unwrap();
+    for i in map.all_intersections() {
+        let center = i.polygon.center();
+        let junction_type = junction_type(i, map);
+        let tl = if junction_type == "traffic_light" {
+            format!(r#" tl="{}""#, junction_id(i.id))
+        } else {
+            String::new()
+        };
+        writeln!(
+            out,
+            r#"    <node id="{}" x="{:.2}" y="{:.2}" type="{}"{}/>"#,
+            junction_id(i.id),
+            center.x(),
+            map.get_bounds().max_y - center.y(),
+            junction_type,
+            tl
+        )
+        .unwrap();
+    }
+    writeln!(out, "</nodes>").unwrap();
+    out
+}
+
+fn junction_type(i: &Intersection, map: &Map) -> &'static str {
+    if i.is_border() || i.is_closed() {
+        return "dead_end";
+    }
+    match i.control {
+        IntersectionControl::Signalled => "traffic_light",
+        IntersectionControl::Signed => {
+            if map.get_stop_sign(i.id).roads.values().all(|r| r.must_stop) {
+                "allway_stop"
+            } else {
+                "priority_stop"
+            }
+        }
+        _ => "priority",
+    }
+}
+
+/// netconvert gives way to the edge with the higher priority at unsignalized junctions
+fn edge_priority(rank: osm::RoadRank) -> usize {
+    match rank {
+        osm::RoadRank::Local => 1,
+        osm::RoadRank::Arterial => 2,
+        osm::RoadRank::Highway => 3,
+    }
+}
+
+/// The SUMO vehicle classes that can use a lane, or None if it shouldn't be exported
+fn sumo_vclasses(lt: LaneType) -> Option<&'static str> {
+    match lt {
+        LaneType::Driving => Some("passenger bus truck delivery motorcycle taxi emergency bicycle"),
+        LaneType::Bus => Some("bus taxi emergency"),
+        LaneType::Biking => Some("bicycle"),
+        LaneType::Sidewalk | LaneType::Footway => Some("pedestrian"),
+        LaneType::Shoulder | LaneType::SharedUse => Some("pedestrian bicycle"),
+        LaneType::LightRail => Some("tram"),
+        // Parked cars, buffers, turn lanes, and construction don't carry traffic
+        _ => None,
+    }
+}
+
+fn sumo_routes(net: &Network, map: &Map, scenario: &Scenario) -> String {
+    // SUMO requires departures in order
+    let mut trips = Vec::new();
+    let mut skipped = 0;
+    for (person_idx, person) in scenario.people.iter().enumerate() {
+        for (trip_idx, trip) in person.trips.iter().enumerate() {
+            if trip.cancelled {
+                continue;
+            }
+            let req = match TripEndpoint::path_req(trip.origin, trip.destination, trip.mode, map) {
+                Some(req) => req,
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let (from, to) = match (
+                net.lane_ids.get(&req.start.lane()),
+                net.lane_ids.get(&req.end.lane()),
+            ) {
+                (Some(_), Some(_)) => (
+                    edge_id(map.get_l(req.start.lane()).get_directed_parent()),
+                    edge_id(map.get_l(req.end.lane()).get_directed_parent()),
+                ),
+                _ => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let id = format!("p{}_{}", person_idx, trip_idx);
+            let depart = seconds(trip.depart);
+            let xml = match trip.mode {
+                TripMode::Drive | TripMode::Bike => format!(
+                    r#"    <trip id="{}" type="{}" depart="{:.2}" from="{}" to="{}"/>"#,
+                    id,
+                    if trip.mode == TripMode::Drive {
+                        "car"
+                    } else {
+                        "bike"
+                    },
+                    depart,
+                    from,
+                    to
+                ),
+                TripMode::Walk => format!(
+                    "    <person id=\"{}\" depart=\"{:.2}\">\n        <walk from=\"{}\" to=\"{}\"/>\n    </person>",
+                    id, depart, from, to
+                ),
+                TripMode::Transit => format!(
+                    "    <person id=\"{}\" depart=\"{:.2}\">\n        <personTrip from=\"{}\" to=\"{}\" modes=\"public\"/>\n    </person>",
+                    id, depart, from, to
+                ),
+                TripMode::Ridehail => format!(
+                    "    <person id=\"{}\" depart=\"{:.2}\">\n        <personTrip from=\"{}\" to=\"{}\" modes=\"taxi\"/>\n    </person>",
+                    id, depart, from, to
+                ),
+                TripMode::Micromobility => format!(
+                    "    <person id=\"{}\" depart=\"{:.2}\">\n        <personTrip from=\"{}\" to=\"{}\" modes=\"bicycle\"/>\n    </person>",
+                    id, depart, from, to
+                ),
+                TripMode::ParkAndRide => format!(
+                    "    <person id=\"{}\" depart=\"{:.2}\">\n        <personTrip from=\"{}\" to=\"{}\" modes=\"car public\"/>\n    </person>",
+                    id, depart, from, to
+                ),
+            };
+            trips.push((trip.depart, xml));
+        }
+    }
+    if skipped > 0 {
+        warn!(
+            "Skipped {} trips with endpoints that couldn't be matched to the network",
+            prettyprint_usize(skipped)
+        );
+    }
+    trips.sort_by_key(|(depart, _)| *depart);
+
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(out, "<routes>").unwrap();
+    writeln!(out, r#"    <vType id="car" vClass="passenger"/>"#).unwrap();
+    writeln!(out, r#"    <vType id="bike" vClass="bicycle"/>"#).unwrap();
+    for (_, xml) in trips {
+        writeln!(out, "{}", xml).unwrap();
+    }
+    writeln!(out, "</routes>").unwrap();
+    out
+}
+
+fn matsim_network(map: &Map) -> String {
+    let bounds = map.get_bounds();
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        out,
+        r#"<!DOCTYPE network SYSTEM "http://www.matsim.org/files/dtd/network_v2.dtd">"#
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r#"<network name="{}">"#,
+        escape(&map.get_name().describe())
+    )
+    .unwrap();
+
+    writeln!(out, "    <nodes>").unwrap();
+    for i in map.all_intersections() {
+        let center = i.polygon.center();
+        writeln!(
+            out,
+            r#"        <node id="{}" x="{:.2}" y="{:.2}"/>"#,
+            junction_id(i.id),
+            center.x(),
+            bounds.max_y - center.y()
+        )
+        .unwrap();
+    }
+    writeln!(out, "    </nodes>").unwrap();
+
+    writeln!(
+        out,
+        r#"    <links capperiod="01:00:00" effectivecellsize="7.5" effectivelanewidth="3.75">"#
+    )
+    .unwrap();
+    for road in map.all_roads() {
+        for dir in [Direction::Fwd, Direction::Back] {
+            let lanes: Vec<LaneType> = road
+                .lanes
+                .iter()
+                .filter(|l| l.dir == dir)
+                .map(|l| l.lane_type)
+                .collect();
+            let num_driving = lanes
+                .iter()
+                .filter(|lt| matches!(lt, LaneType::Driving | LaneType::Bus))
+                .count();
+            let mut modes = Vec::new();
+            if lanes.contains(&LaneType::Driving) {
+                modes.push("car");
+            }
+            if lanes.contains(&LaneType::Driving) || lanes.contains(&LaneType::Biking) {
+                modes.push("bike");
+            }
+            if num_driving > 0 {
+                modes.push("pt");
+            }
+            if modes.is_empty() {
+                continue;
+            }
+
+            let dr = DirectedRoadID { road: road.id, dir };
+            let permlanes = num_driving.max(1);
+            // A typical saturation flow per lane
+            let capacity = 1800 * permlanes;
+            writeln!(
+                out,
+                r#"        <link id="{}" from="{}" to="{}" length="{:.2}" freespeed="{:.2}" capacity="{}" permlanes="{}" oneway="1" modes="{}"/>"#,
+                edge_id(dr),
+                junction_id(dr.src_i(map)),
+                junction_id(dr.dst_i(map)),
+                road.length().inner_meters(),
+                road.speed_limit.inner_meters_per_second(),
+                capacity,
+                permlanes,
+                modes.join(",")
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "    </links>").unwrap();
+    writeln!(out, "</network>").unwrap();
+    out
+}
+
+fn matsim_plans(map: &Map, scenario: &Scenario) -> String {
+    let activity = |endpt: TripEndpoint, activity_type: String, end_time: Option<Time>| {
+        let mut xml = format!(
+            r#"            <activity type="{}" x="{:.2}" y="{:.2}""#,
+            activity_type,
+            endpt.pt(map).x(),
+            map.get_bounds().max_y - endpt.pt(map).y()
+        );
+        if let Some(t) = end_time {
+            let secs = seconds(t).round() as usize;
+            write!(
+                xml,
+                r#" end_time="{:02}:{:02}:{:02}""#,
+                secs / 3600,
+                (secs % 3600) / 60,
+                secs % 60
+            )
+            .unwrap();
+        }
+        xml.push_str("/>");
+        xml
+    };
+    let activity_type = |endpt: TripEndpoint, purpose: String| {
+        if matches!(endpt, TripEndpoint::Border(_)) {
+            "outside".to_string()
+        } else {
+            purpose.replace(' ', "_")
+        }
+    };
+
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        out,
+        r#"<!DOCTYPE population SYSTEM "http://www.matsim.org/files/dtd/population_v6.dtd">"#
+    )
+    .unwrap();
+    writeln!(out, "<population>").unwrap();
+    for (person_idx, person) in scenario.people.iter().enumerate() {
+        let trips: Vec<_> = person.trips.iter().filter(|t| !t.cancelled).collect();
+        if trips.is_empty() {
+            continue;
+        }
+        writeln!(out, r#"    <person id="p{}">"#, person_idx).unwrap();
+        writeln!(out, r#"        <plan selected="yes">"#).unwrap();
+        // Assume people start the day at home
+        writeln!(
+            out,
+            "{}",
+            activity(
+                trips[0].origin,
+                activity_type(trips[0].origin, "home".to_string()),
+                Some(trips[0].depart)
+            )
+        )
+        .unwrap();
+        for (idx, trip) in trips.iter().enumerate() {
+            let mode = match trip.mode {
+                TripMode::Walk => "walk",
+                TripMode::Bike => "bike",
+                TripMode::Transit => "pt",
+                TripMode::Drive => "car",
+                TripMode::Ridehail => "taxi",
+                TripMode::Micromobility => "bike",
+                // Only the first leg
+                TripMode::ParkAndRide => "car",
+            };
+            writeln!(out, r#"            <leg mode="{}"/>"#, mode).unwrap();
+            writeln!(
+                out,
+                "{}",
+                activity(
+                    trip.destination,
+                    activity_type(trip.destination, trip.purpose.to_string()),
+                    trips.get(idx + 1).map(|next| next.depart)
+                )
+            )
+            .unwrap();
+        }
+        writeln!(out, "        </plan>").unwrap();
+        writeln!(out, "    </person>").unwrap();
+    }
+    writeln!(out, "</population>").unwrap();
+    out
+}
+
+fn edge_id(dr: DirectedRoadID) -> String {
+    format!(
+        "r{}{}",
+        dr.road.0,
+        if dr.dir == Direction::Fwd { "f" } else { "b" }
+    )
+}
+
+fn junction_id(i: IntersectionID) -> String {
+    format!("i{}", i.0)
+}
+
+fn lane_index(lane_id: &str) -> &str {
+    lane_id.rsplit('_').next().unwrap()
+}
+
+/// Flip the Y axis, so north is up
+fn xy(map: &Map, pt: Pt2D) -> String {
+    format!("{:.2},{:.2}", pt.x(), map.get_bounds().max_y - pt.y())
+}
+
+fn seconds(t: Time) -> f64 {
+    (t - Time::START_OF_DAY).inner_seconds()
+}
+
+fn escape(x: &str) -> String {
+    x.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map_model::RoadID;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape(r#"Pike & "Pine" <St>"#),
+            "Pike &amp; &quot;Pine&quot; &lt;St&gt;"
+        );
+    }
+
+    #[test]
+    fn test_edge_and_lane_ids() {
+        let fwd = DirectedRoadID {
+            road: RoadID(12),
+            dir: Direction::Fwd,
+        };
+        let back = DirectedRoadID {
+            road: RoadID(12),
+            dir: Direction::Back,
+        };
+        assert_eq!(edge_id(fwd), "r12f");
+        assert_eq!(edge_id(back), "r12b");
+        assert_eq!(lane_index("r12f_3"), "3");
+        assert_eq!(lane_index(&format!("{}_0", edge_id(back))), "0");
+    }
+
+    #[test]
+    fn test_sumo_vclasses() {
+        assert!(sumo_vclasses(LaneType::Driving)
+            .unwrap()
+            .contains("passenger"));
+        assert_eq!(sumo_vclasses(LaneType::Biking), Some("bicycle"));
+        assert_eq!(sumo_vclasses(LaneType::Parking), None);
+        assert_eq!(sumo_vclasses(LaneType::Construction), None);
+    }
+}
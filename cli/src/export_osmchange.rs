@@ -0,0 +1,121 @@
+//! Export map edits as an OsmChange file, for mappers to review in JOSM and upload to
+//! OpenStreetMap. Edited ways are fetched from the OSM API first, so the file is based on their
+//! latest version. Edits without a tagging equivalent are only logged.
+
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use fs_err::File;
+
+use abstutil::{Tags, Timer};
+use map_model::{Map, MapEdits};
+
+pub async fn run(map: String, edits: String, output: String) -> Result<()> {
+    let mut timer = Timer::new("export OsmChange");
+    let mut map = Map::load_synchronously(map, &mut timer);
+    let edits = MapEdits::load_from_file(&map, edits, &mut timer)?;
+    map.must_apply_edits(edits, &mut timer);
+
+    let change = map.edits_to_osm_change();
+    for x in &change.unsupported {
+        warn!("Not exported: {}", x);
+    }
+    if change.modify_ways.is_empty() && change.new_restrictions.is_empty() {
+        bail!("None of the edits can be expressed in OSM");
+    }
+
+    let mut modified_ways = Vec::new();
+    for (way, tag_changes) in &change.modify_ways {
+        let url = format!("https://api.openstreetmap.org/api/0.6/way/{}", way.0);
+        let resp = abstio::http_get(url).await?;
+        let mut tree = match xmltree::Element::parse(resp.as_slice())?.take_child("way") {
+            Some(x) => x,
+            None => bail!("OSM API response for {} has no way", way),
+        };
+        let mut osm_tags = Tags::empty();
+        let mut other_children = Vec::new();
+        for node in tree.children.drain(..) {
+            if let Some(elem) = node.as_element() {
+                if elem.name == "tag" {
+                    osm_tags.insert(elem.attributes["k"].clone(), elem.attributes["v"].clone());
+                    continue;
+                }
+            }
+            other_children.push(node);
+        }
+
+        for (k, v) in tag_changes {
+            match v {
+                Some(v) => osm_tags.insert(k, v),
+                None => {
+                    osm_tags.remove(k);
+                }
+            }
+        }
+
+        tree.children = other_children;
+        for (k, v) in osm_tags.inner() {
+            let mut new_elem = xmltree::Element::new("tag");
+            new_elem.attributes.insert("k".to_string(), k.to_string());
+            new_elem.attributes.insert("v".to_string(), v.to_string());
+            tree.children.push(xmltree::XMLNode::Element(new_elem));
+        }
+
+        tree.attributes.remove("timestamp");
+        tree.attributes.remove("changeset");
+        tree.attributes.remove("user");
+        tree.attributes.remove("uid");
+        tree.attributes.remove("visible");
+
+        let mut bytes: Vec<u8> = Vec::new();
+        tree.write(&mut bytes)?;
+        let out = String::from_utf8(bytes)?;
+        let stripped = out.trim_start_matches("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        modified_ways.push(stripped.to_string());
+    }
+
+    let mut f = File::create(&output)?;
+    writeln!(f, "<osmChange version=\"0.6\" generator=\"abst\">")?;
+    if !modified_ways.is_empty() {
+        writeln!(f, "<modify>")?;
+        for w in modified_ways {
+            writeln!(f, "  {}", w)?;
+        }
+        writeln!(f, "</modify>")?;
+    }
+    if !change.new_restrictions.is_empty() {
+        writeln!(f, "<create>")?;
+        // New objects get negative IDs until they're uploaded
+        for (idx, tr) in change.new_restrictions.iter().enumerate() {
+            writeln!(f, "  <relation id=\"-{}\" version=\"0\">", idx + 1)?;
+            writeln!(
+                f,
+                "    <member type=\"way\" ref=\"{}\" role=\"from\"/>",
+                tr.from.0
+            )?;
+            writeln!(
+                f,
+                "    <member type=\"node\" ref=\"{}\" role=\"via\"/>",
+                tr.via.0
+            )?;
+            writeln!(
+                f,
+                "    <member type=\"way\" ref=\"{}\" role=\"to\"/>",
+                tr.to.0
+            )?;
+            writeln!(f, "    <tag k=\"type\" v=\"restriction\"/>")?;
+            writeln!(f, "    <tag k=\"restriction\" v=\"{}\"/>", tr.restriction)?;
+            writeln!(f, "  </relation>")?;
+        }
+        writeln!(f, "</create>")?;
+    }
+    writeln!(f, "</osmChange>")?;
+    info!(
+        "Wrote {}, modifying {} ways and adding {} turn restrictions. {} edits weren't exported.",
+        output,
+        change.modify_ways.len(),
+        change.new_restrictions.len(),
+        change.unsupported.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,140 @@
+//! Simulate a scenario with hazards like floods closing roads for part of the day, and compare
+//! against the same scenario without them. Reports the buildings that get cut off and how much
+//! longer trips take.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, Result};
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Duration, Time};
+use map_model::{BuildingID, Hazard, Map, MapEdits};
+use sim::{AlertHandler, Sim, SimFlags, SimOptions, TripID};
+use synthpop::Scenario;
+
+pub fn run(scenario_path: String, hazards_path: String) -> Result<()> {
+    let mut timer = Timer::new("simulate hazards");
+    let scenario: Scenario = abstio::must_read_object(scenario_path, &mut timer);
+    let mut map = Map::load_synchronously(scenario.map_name.path(), &mut timer);
+    let hazards = Hazard::load_geojson(&map, &hazards_path)?;
+    if hazards.is_empty() {
+        bail!("{} has no hazard polygons", hazards_path);
+    }
+
+    let already_stranded = map.stranded_buildings();
+    let (baseline, _) = simulate(&mut map, &scenario, &[], &mut timer);
+    let (with_hazards, stranded) = simulate(&mut map, &scenario, &hazards, &mut timer);
+
+    let newly_stranded = stranded.difference(&already_stranded).count();
+    let residents: usize = stranded
+        .difference(&already_stranded)
+        .map(|b| map.get_b(*b).num_residents())
+        .sum();
+    println!(
+        "{} buildings, with {} residents, were cut off from the road network at some point",
+        prettyprint_usize(newly_stranded),
+        prettyprint_usize(residents)
+    );
+
+    let mut num_delayed = 0;
+    let mut num_faster = 0;
+    let mut total_delay = Duration::ZERO;
+    let mut num_newly_cancelled = 0;
+    for (trip, before) in &baseline {
+        let before = match before {
+            Some(dt) => *dt,
+            None => {
+                continue;
+            }
+        };
+        match with_hazards.get(trip).cloned().flatten() {
+            Some(after) => {
+                if after > before {
+                    num_delayed += 1;
+                    total_delay += after - before;
+                } else if after < before {
+                    num_faster += 1;
+                }
+            }
+            None => {
+                num_newly_cancelled += 1;
+            }
+        }
+    }
+    println!(
+        "{} trips took longer, by {} in total. {} were faster.",
+        prettyprint_usize(num_delayed),
+        total_delay,
+        prettyprint_usize(num_faster)
+    );
+    if num_delayed > 0 {
+        println!(
+            "The average delayed trip took {} longer",
+            Duration::seconds(total_delay.inner_seconds() / (num_delayed as f64))
+        );
+    }
+    println!(
+        "{} trips didn't finish or were cancelled because of the hazards",
+        prettyprint_usize(num_newly_cancelled)
+    );
+    Ok(())
+}
+
+/// Simulates the whole day, closing and reopening roads as hazards start and end. Returns the
+/// duration of every trip (`None` if it was cancelled), and all buildings stranded at any point.
+fn simulate(
+    map: &mut Map,
+    scenario: &Scenario,
+    hazards: &[Hazard],
+    timer: &mut Timer,
+) -> (BTreeMap<TripID, Option<Duration>>, BTreeSet<BuildingID>) {
+    let mut opts = SimOptions::new("hazards");
+    opts.alerts = AlertHandler::Silence;
+    let mut sim = Sim::new(map, opts);
+    // Both runs need the same rng seed
+    let mut rng = SimFlags::for_test("hazards").make_rng();
+    sim.instantiate(scenario, map, &mut rng, timer);
+
+    let original_edits = map.get_edits().clone();
+    let mut changes: Vec<Time> = hazards.iter().flat_map(|h| [h.start, h.end]).collect();
+    changes.sort();
+    changes.dedup();
+    // Like prebaking, run a few hours past the end of the day
+    let end = sim.get_end_of_day() + Duration::hours(3);
+
+    let mut stranded = BTreeSet::new();
+    for time in changes {
+        if time > end {
+            break;
+        }
+        if time > sim.time() {
+            sim.timed_step(map, time - sim.time(), &mut None, timer);
+        }
+        let closed = map.roads_closed_by_hazards(hazards, time);
+        info!("At {}, {} roads are closed", time, closed.len());
+        let edits = map.close_roads_for_hazards(&original_edits, &closed);
+        update_map(map, &mut sim, edits, timer);
+        stranded.extend(map.stranded_buildings());
+    }
+    if end > sim.time() {
+        sim.timed_step(map, end - sim.time(), &mut None, timer);
+    }
+    if map.get_edits() != &original_edits {
+        update_map(map, &mut sim, original_edits, timer);
+    }
+
+    let trips = sim
+        .get_analytics()
+        .finished_trips
+        .iter()
+        .map(|(_, trip, _, maybe_dt)| (*trip, *maybe_dt))
+        .collect();
+    (trips, stranded)
+}
+
+fn update_map(map: &mut Map, sim: &mut Sim, edits: MapEdits, timer: &mut Timer) {
+    map.must_apply_edits(edits, timer);
+    map.recalculate_pathfinding_after_edits(timer);
+    sim.handle_live_edited_traffic_signals(map);
+    sim.handle_live_edits(map, timer);
+}
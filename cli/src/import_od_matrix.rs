@@ -0,0 +1,145 @@
+//! Import an origin-destination matrix, like the output of a travel demand model, as a scenario.
+//!
+//! Zones are polygons in a GeoJSON file, each with a property naming it. The matrix is a CSV or
+//! JSON file. Each record counts trips from one zone to another, departing during some window, by
+//! mode:
+//!
+//! ```text
+//! origin,destination,departure_start,departure_end,walk,bike,transit,drive
+//! zone1,zone2,07:00,08:00,12,3,40,105
+//! zone2,zone1,16:30,17:30,10,3,38,98
+//! ```
+//!
+//! Times are `HH:MM` or `HH:MM:SS` since midnight. A JSON matrix is a list of objects with the same
+//! fields. Each trip becomes a person who just takes that one trip, starting and ending somewhere
+//! in the zones, or at a border if the zone is partly or completely outside the map.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use serde::Deserialize;
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Duration, Polygon, Time};
+use map_model::Map;
+use popdat::od::{IncludeZonePolicy, ZoneTrips};
+use synthpop::{Scenario, TripMode};
+
+pub fn run(
+    map: String,
+    zones: String,
+    zone_property: String,
+    matrix: String,
+    scenario_name: String,
+    rng_seed: u64,
+) -> Result<()> {
+    let mut timer = Timer::new("import OD matrix");
+    let map = Map::load_synchronously(map, &mut timer);
+    let zones = parse_zones(&map, zones, &zone_property)?;
+    let matrix = parse_matrix(matrix, &mut timer)?;
+
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let mut s = Scenario::empty(&map, &scenario_name);
+    // Include all buses/trains
+    s.only_seed_buses = None;
+    s.people = popdat::od::disaggregate_trips(
+        &map,
+        zones,
+        matrix,
+        IncludeZonePolicy::AllowRemote,
+        &mut rng,
+        &mut timer,
+    );
+    println!("Imported {} people", prettyprint_usize(s.people.len()));
+    s.save();
+    Ok(())
+}
+
+fn parse_zones(map: &Map, path: String, property: &str) -> Result<HashMap<String, Polygon>> {
+    let mut zones = HashMap::new();
+    // Zones far away from the map still matter for trips passing through
+    let require_in_bounds = false;
+    for (polygon, tags) in Polygon::from_geojson_bytes(
+        &abstio::slurp_file(path)?,
+        map.get_gps_bounds(),
+        require_in_bounds,
+    )? {
+        match tags.get(property) {
+            Some(id) => {
+                zones.insert(id.to_string(), polygon);
+            }
+            None => bail!("A zone is missing {}: {:?}", property, tags),
+        }
+    }
+    Ok(zones)
+}
+
+fn parse_matrix(path: String, timer: &mut Timer) -> Result<Vec<ZoneTrips>> {
+    let records: Vec<Record> = if path.ends_with(".json") {
+        abstio::maybe_read_json(path, timer)?
+    } else {
+        let mut records = Vec::new();
+        for rec in csv::Reader::from_reader(fs_err::File::open(path)?).deserialize() {
+            records.push(rec?);
+        }
+        records
+    };
+
+    let mut matrix = Vec::new();
+    for rec in records {
+        let depart_after = parse_time(&rec.departure_start)?;
+        let depart_before = parse_time(&rec.departure_end)?;
+        if depart_before < depart_after {
+            bail!(
+                "Departure window from {} to {} ends before it starts",
+                rec.origin,
+                rec.destination
+            );
+        }
+        for (mode, number_trips) in [
+            (TripMode::Walk, rec.walk),
+            (TripMode::Bike, rec.bike),
+            (TripMode::Transit, rec.transit),
+            (TripMode::Drive, rec.drive),
+        ] {
+            if number_trips > 0 {
+                matrix.push(ZoneTrips {
+                    origin_zone: rec.origin.clone(),
+                    destination_zone: rec.destination.clone(),
+                    mode,
+                    number_trips,
+                    depart_after,
+                    depart_before,
+                });
+            }
+        }
+    }
+    Ok(matrix)
+}
+
+fn parse_time(input: &str) -> Result<Time> {
+    let input = if input.matches(':').count() == 1 {
+        format!("{}:00", input)
+    } else {
+        input.to_string()
+    };
+    Ok(Time::START_OF_DAY + Duration::parse(&input)?)
+}
+
+#[derive(Deserialize)]
+struct Record {
+    origin: String,
+    destination: String,
+    departure_start: String,
+    departure_end: String,
+    #[serde(default)]
+    walk: usize,
+    #[serde(default)]
+    bike: usize,
+    #[serde(default)]
+    transit: usize,
+    #[serde(default)]
+    drive: usize,
+}
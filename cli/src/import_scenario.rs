@@ -2,7 +2,7 @@ use serde::Deserialize;
 
 use abstutil::{prettyprint_usize, Timer};
 use map_model::Map;
-use synthpop::{ExternalPerson, Scenario};
+use synthpop::{ExternalPerson, GatewayDemand, Scenario};
 
 pub fn run(input: String, map: String, skip_problems: bool) {
     let mut timer = Timer::new("import traffic demand data");
@@ -24,6 +24,20 @@ pub fn run(input: String, map: String, skip_problems: bool) {
     s.save();
 }
 
+pub fn derive_gateway_demand(input: String, map: String, output: String) {
+    let mut timer = Timer::new("derive gateway demand");
+    let map = Map::load_synchronously(map, &mut timer);
+    let input: Input = abstio::read_json(input, &mut timer);
+
+    let demand = GatewayDemand::from_regional_model(&map, &input.people);
+    println!(
+        "Found traffic at {} gateways, with {}% passing through",
+        prettyprint_usize(demand.gateways.len()),
+        (demand.through_traffic * 100.0).round()
+    );
+    abstio::write_json(output, &demand);
+}
+
 #[derive(Deserialize)]
 struct Input {
     scenario_name: String,
@@ -5,10 +5,16 @@
 extern crate log;
 
 mod augment_scenario;
+mod calibrate;
 mod clip_osm;
+mod export_network;
+mod export_osmchange;
 mod generate_houses;
+mod hazard_impacts;
 mod import_grid2demand;
+mod import_od_matrix;
 mod import_scenario;
+mod monte_carlo;
 mod one_step_import;
 
 use std::io::Write;
@@ -19,7 +25,7 @@ use fs_err::File;
 use importer::Job;
 use structopt::StructOpt;
 
-use abstutil::Timer;
+use abstutil::{prettyprint_usize, Timer};
 
 #[derive(StructOpt)]
 #[structopt(name = "abcli", about = "The A/B Street multi-tool")]
@@ -62,6 +68,22 @@ enum Command {
         /// Delete cancelled trips, and delete people with no remaining trips.
         #[structopt(long)]
         delete_cancelled_trips: bool,
+        /// A JSON file with hourly volumes of trips entering and leaving the map through border
+        /// intersections. Adds a person for each of these trips.
+        #[structopt(long)]
+        add_gateway_demand: Option<String>,
+        /// A JSON file describing home deliveries by van, with optional parcel lockers and other
+        /// pickup points. Adds a person for each van round and each trip to collect a parcel.
+        #[structopt(long)]
+        add_delivery_demand: Option<String>,
+        /// A JSON list of bike share stations and free-floating e-scooters. Trips using the
+        /// micromobility mode ride these.
+        #[structopt(long)]
+        add_micromobility: Option<String>,
+        /// Mark big parking lots next to transit stops as park-and-ride lots, for trips using the
+        /// park-and-ride mode.
+        #[structopt(long)]
+        add_park_and_ride: bool,
         /// A seed for generating random numbers
         #[structopt(long, default_value = "42")]
         rng_seed: u64,
@@ -88,6 +110,40 @@ enum Command {
         #[structopt(long)]
         map: String,
     },
+    /// Import a CSV file of collisions, with one point per row. See `collisions::import_csv` for
+    /// the columns. The collisions layer and viewer read the result.
+    ImportCollisions {
+        /// The path to a CSV file
+        #[structopt(long)]
+        input: String,
+        /// The path to a map in the city the collisions happened in
+        #[structopt(long)]
+        map: String,
+        /// Where the data came from, to credit it
+        #[structopt(long, default_value = "")]
+        source_url: String,
+    },
+    /// Compare a scenario against observed hourly traffic counts with the GEH statistic, and
+    /// optionally scale the number of people to match them better. See cli/src/calibrate.rs for
+    /// the CSV format.
+    Calibrate {
+        /// The path to a scenario file
+        #[structopt(long)]
+        scenario: String,
+        /// The path to a CSV file with observed counts
+        #[structopt(long)]
+        counts: String,
+        /// How many times to adjust the scenario and simulate again. The best fit gets saved as a
+        /// new scenario, with "_calibrated" appended to the name.
+        #[structopt(long, default_value = "0")]
+        iterations: usize,
+        /// The path to write a CSV file comparing each count with the best fit
+        #[structopt(long)]
+        output: Option<String>,
+        /// A seed for generating random numbers
+        #[structopt(long, default_value = "42")]
+        rng_seed: u64,
+    },
     /// Import a JSON scenario in the
     /// https://a-b-street.github.io/docs/tech/dev/formats/scenarios.html format
     ImportScenario {
@@ -102,6 +158,220 @@ enum Command {
         #[structopt(long)]
         skip_problems: bool,
     },
+    /// Import an origin-destination matrix of trips between zones, by mode and departure window.
+    /// See cli/src/import_od_matrix.rs for the format.
+    ImportODMatrix {
+        /// The path to a map to generate trips for
+        #[structopt(long)]
+        map: String,
+        /// The path to a GeoJSON file with zone polygons
+        #[structopt(long)]
+        zones: String,
+        /// The property of each zone polygon with its ID, as used in the matrix
+        #[structopt(long, default_value = "id")]
+        zone_property: String,
+        /// The path to a CSV or JSON file with the matrix
+        #[structopt(long)]
+        matrix: String,
+        /// The name of the scenario to save
+        #[structopt(long, default_value = "od_matrix")]
+        scenario_name: String,
+        /// A seed for generating random numbers
+        #[structopt(long, default_value = "42")]
+        rng_seed: u64,
+    },
+    /// Count the trips in a JSON scenario (in the same format as ImportScenario, usually from a
+    /// regional travel demand model) that enter or leave the map, and write hourly volumes per
+    /// border intersection. These can be added to a scenario with AugmentScenario.
+    DeriveGatewayDemand {
+        /// The path to a JSON scenario file
+        #[structopt(long)]
+        input: String,
+        /// The path to a map matching the scenario data
+        #[structopt(long)]
+        map: String,
+        /// The path to write the gateway volumes
+        #[structopt(long)]
+        output: String,
+    },
+    /// Export a map, and optionally a scenario, to SUMO or MATSim. See cli/src/export_network.rs
+    /// for what's included.
+    ExportNetwork {
+        /// The path to a map to export
+        #[structopt(long)]
+        map: String,
+        /// Either sumo or matsim
+        #[structopt(long)]
+        format: String,
+        /// The path to a scenario for the same map, to also export as trips or plans
+        #[structopt(long)]
+        scenario: Option<String>,
+        /// The output files start with this path
+        #[structopt(long)]
+        output: String,
+    },
+    /// Export all roads, lanes, intersections, and buildings of a map, plus what any edits change,
+    /// as GeoJSON with attributes.
+    ExportGeoJSON {
+        /// The path to a map to export
+        #[structopt(long)]
+        map: String,
+        /// The path to edits for the same map, to apply first
+        #[structopt(long)]
+        edits: Option<String>,
+        /// The path to write the GeoJSON file
+        #[structopt(long)]
+        output: String,
+    },
+    /// Simulate a scenario for the whole day, then export vehicle volumes, average speeds, and
+    /// delays per road and vehicle type in 15 minute bins, as CSV.
+    ExportLinkStats {
+        /// The path to a scenario file
+        #[structopt(long)]
+        scenario: String,
+        /// The path to edits for the scenario's map, to apply first
+        #[structopt(long)]
+        edits: Option<String>,
+        /// The path to write the CSV file
+        #[structopt(long)]
+        output: String,
+    },
+    /// Export map edits as an OsmChange file, with tag changes on the original OSM ways, so they
+    /// can be reviewed and uploaded to OpenStreetMap. See cli/src/export_osmchange.rs.
+    ExportOsmChange {
+        /// The path to a map
+        #[structopt(long)]
+        map: String,
+        /// The path to edits for the same map
+        #[structopt(long)]
+        edits: String,
+        /// The path to write the .osc file
+        #[structopt(long)]
+        output: String,
+    },
+    /// Transfer map edits to a map re-imported from newer OSM data. Commands referring to roads or
+    /// intersections that no longer exist are moved onto whatever replaced them, and the ones that
+    /// can't be transferred are reported.
+    MigrateEdits {
+        /// The path to the re-imported map
+        #[structopt(long)]
+        map: String,
+        /// The path to edits made for an older version of the map
+        #[structopt(long)]
+        edits: String,
+        /// The path to write the migrated edits
+        #[structopt(long)]
+        output: String,
+    },
+    /// Split a map into balanced parts with few roads between them, as groundwork for simulating
+    /// a huge region across multiple processes. Writes the partitioning as JSON, plus a GeoJSON
+    /// file for checking it visually.
+    PartitionMap {
+        /// The path to a map
+        #[structopt(long)]
+        map: String,
+        /// How many parts to split the map into
+        #[structopt(long)]
+        num_parts: usize,
+        /// The path to write the JSON file. The GeoJSON file gets the same name, ending in
+        /// .geojson instead.
+        #[structopt(long)]
+        output: String,
+    },
+    /// Simulate a scenario with hazards like floods closing roads for part of the day, and report
+    /// stranded buildings and delayed trips compared to the same scenario without them.
+    HazardImpacts {
+        /// The path to a scenario file
+        #[structopt(long)]
+        scenario: String,
+        /// The path to a GeoJSON file with hazard polygons. Each may have `start` and `end`
+        /// properties, like "7:30", to close roads only during that time.
+        #[structopt(long)]
+        hazards: String,
+    },
+    /// Simulate a scenario many times with different random seeds, departure times, and some
+    /// people switching modes, and write a report on how much the results vary between runs. See
+    /// cli/src/monte_carlo.rs.
+    MonteCarlo {
+        /// The path to a scenario file
+        #[structopt(long)]
+        scenario: String,
+        /// The path to edits for the scenario's map, to apply first
+        #[structopt(long)]
+        edits: Option<String>,
+        /// How many times to run the scenario
+        #[structopt(long, default_value = "20")]
+        runs: usize,
+        /// Shift each person's departures by up to this many minutes earlier or later
+        #[structopt(long, default_value = "10")]
+        departure_noise_minutes: usize,
+        /// The percent of people who switch to a random mode in each run
+        #[structopt(long, default_value = "5")]
+        mode_noise_pct: usize,
+        /// The seed for the first run. Each run after increments it.
+        #[structopt(long, default_value = "42")]
+        rng_seed: u64,
+        /// The path to write the Markdown report
+        #[structopt(long)]
+        output: String,
+    },
+    /// Simulate a scenario several times with different random seeds and slightly shifted
+    /// departures, and save travel time reliability measures per origin/destination pair and per
+    /// road. Without edits, this is saved alongside the prebaked results as the baseline.
+    MeasureReliability {
+        /// The path to a scenario file
+        #[structopt(long)]
+        scenario: String,
+        /// The path to edits for the scenario's map, to apply first
+        #[structopt(long)]
+        edits: Option<String>,
+        /// How many times to run the scenario
+        #[structopt(long, default_value = "10")]
+        runs: usize,
+        /// Shift each person's departures by up to this many minutes earlier or later
+        #[structopt(long, default_value = "5")]
+        jitter_minutes: usize,
+    },
+    /// Runs a scenario with the much faster, coarser fast-forward model, and saves the analytics.
+    /// Compare the results against other fast-forward runs, not the normal simulation.
+    SimulateFastForward {
+        /// The path to a scenario file
+        #[structopt(long)]
+        scenario: String,
+        /// The path to edits for the scenario's map, to apply first
+        #[structopt(long)]
+        edits: Option<String>,
+        /// The path to write the analytics to, ending in .bin
+        #[structopt(long)]
+        output: String,
+    },
+    /// Splits a map into parts like `partition-map` and runs a scenario, estimating how much faster
+    /// the simulation could be if the parts ran in parallel. This only estimates; the simulation
+    /// itself isn't parallelized yet, and still runs on one thread.
+    AnalyzeParallelism {
+        /// The path to a scenario file
+        #[structopt(long)]
+        scenario: String,
+        /// How many parts to split the map into
+        #[structopt(long, default_value = "8")]
+        num_parts: usize,
+    },
+    /// Compares two logs written by `run_scenario --determinism-log`, from different runs or
+    /// machines, and reports where the simulations first diverged.
+    CompareDeterminism {
+        #[structopt()]
+        first: String,
+        #[structopt()]
+        second: String,
+    },
+    /// Imports a one-shot A/B Street map from a SUMO .net.xml file, instead of OSM. See
+    /// importer/src/sumo.rs for what's included.
+    ImportSUMO {
+        #[structopt()]
+        input: String,
+        #[structopt(flatten)]
+        opts: map_model::RawToMapOptions,
+    },
     /// Transform a JSON map that's been manually edited into the binary format suitable for
     /// simulation.
     ImportJSONMap {
@@ -118,6 +388,14 @@ enum Command {
         #[structopt()]
         map: String,
     },
+    /// Rewrites a Map so its pathfinder is stored in a separate file, only read the first time
+    /// something pathfinds. This makes huge maps much faster to load, but the pathfinder takes the
+    /// same memory once read, and the result can't be used on web.
+    SplitPathfinder {
+        /// The path to a map. The map is modified in-place.
+        #[structopt()]
+        map: String,
+    },
     /// Procedurally generates houses along empty residential roads of a map
     GenerateHouses {
         /// The path to a map to generate houses for
@@ -142,6 +420,10 @@ enum Command {
         /// The path to a GeoJSON file with one boundary polygon
         #[structopt()]
         input: String,
+        /// Pick the smallest pre-built city extract from download.bbbike.org instead. These are
+        /// much smaller than Geofabrik's regions.
+        #[structopt(long)]
+        bbbike: bool,
     },
     /// Imports a one-shot A/B Street map from a GeoJSON boundary in a single command.
     OneStepImport {
@@ -155,6 +437,10 @@ enum Command {
         /// Use Geofabrik to grab OSM input if true, or Overpass if false. Overpass is faster.
         #[structopt(long)]
         use_geofabrik: bool,
+        /// Use a pre-built city extract from BBBike to grab OSM input. This overrides
+        /// --use-geofabrik, and is a much smaller download for cities that BBBike covers.
+        #[structopt(long)]
+        use_bbbike: bool,
         /// Use osmium to clip osm.pbf files. Faster, but requires an external system dependency.
         /// Falls back to something built-in and slower.
         #[structopt(long)]
@@ -166,10 +452,39 @@ enum Command {
         /// Downgrade crosswalks not matching a `highway=crossing` OSM node into unmarked crossings.
         #[structopt(long)]
         filter_crosswalks: bool,
+        /// Don't import off-road paths and tracks as trails for walking and cycling.
+        #[structopt(long)]
+        skip_trails: bool,
+        /// How to guess on-street parking for roads without parking tags in OSM: never,
+        /// conservative, or aggressive.
+        #[structopt(long, default_value = "never")]
+        infer_parking: convert_osm::ParkingInference,
+        /// The path to a GeoJSON file of zoning or land-use parcels, to decide who lives and works
+        /// in each building.
+        #[structopt(long)]
+        zoning: Option<String>,
+        /// Which property of each parcel in --zoning holds the zoning code or land-use category
+        #[structopt(long, default_value = "zoning")]
+        zoning_property: String,
+        /// The path to a CSV file of signal timing sheets, to use instead of guessing. See
+        /// convert_osm/src/signal_timing.rs for the format.
+        #[structopt(long)]
+        signal_timing: Option<String>,
+        /// The path to a JSON file of rules for keeping and rewriting OSM tags. See
+        /// convert_osm/src/tag_filter.rs for the format.
+        #[structopt(long)]
+        tag_filter: Option<String>,
+        /// Download global 30m elevation tiles covering the boundary, so roads get an incline.
+        #[structopt(long)]
+        elevation: bool,
         /// Generate a simple travel demand model based on 2011 UK commuting data. This will only
         /// work if the boundary is in the UK.
         #[structopt(long)]
         create_uk_travel_demand_model: bool,
+        /// Generate a scenario by placing residents according to a WorldPop or GHSL population
+        /// GeoTIFF in EPSG:4326. This works anywhere.
+        #[structopt(long)]
+        population_raster: Option<String>,
         #[structopt(flatten)]
         opts: map_model::RawToMapOptions,
     },
@@ -189,10 +504,36 @@ enum Command {
         /// Downgrade crosswalks not matching a `highway=crossing` OSM node into unmarked crossings.
         #[structopt(long)]
         filter_crosswalks: bool,
+        /// Don't import off-road paths and tracks as trails for walking and cycling.
+        #[structopt(long)]
+        skip_trails: bool,
+        /// How to guess on-street parking for roads without parking tags in OSM: never,
+        /// conservative, or aggressive.
+        #[structopt(long, default_value = "never")]
+        infer_parking: convert_osm::ParkingInference,
+        /// The path to a GeoJSON file of zoning or land-use parcels, to decide who lives and works
+        /// in each building.
+        #[structopt(long)]
+        zoning: Option<String>,
+        /// Which property of each parcel in --zoning holds the zoning code or land-use category
+        #[structopt(long, default_value = "zoning")]
+        zoning_property: String,
+        /// The path to a CSV file of signal timing sheets, to use instead of guessing. See
+        /// convert_osm/src/signal_timing.rs for the format.
+        #[structopt(long)]
+        signal_timing: Option<String>,
+        /// The path to a JSON file of rules for keeping and rewriting OSM tags. See
+        /// convert_osm/src/tag_filter.rs for the format.
+        #[structopt(long)]
+        tag_filter: Option<String>,
         /// Generate a simple travel demand model based on 2011 UK commuting data. This will only
         /// work if the boundary is in the UK.
         #[structopt(long)]
         create_uk_travel_demand_model: bool,
+        /// Generate a scenario by placing residents according to a WorldPop or GHSL population
+        /// GeoTIFF in EPSG:4326. This works anywhere.
+        #[structopt(long)]
+        population_raster: Option<String>,
         #[structopt(flatten)]
         opts: map_model::RawToMapOptions,
     },
@@ -258,6 +599,10 @@ async fn main() -> Result<()> {
             add_lunch_trips,
             scenario_modifiers,
             delete_cancelled_trips,
+            add_gateway_demand,
+            add_delivery_demand,
+            add_micromobility,
+            add_park_and_ride,
             rng_seed,
         } => augment_scenario::run(
             input_scenario,
@@ -265,6 +610,10 @@ async fn main() -> Result<()> {
             add_lunch_trips,
             scenario_modifiers,
             delete_cancelled_trips,
+            add_gateway_demand,
+            add_delivery_demand,
+            add_micromobility,
+            add_park_and_ride,
             rng_seed,
         ),
         Command::ClipOSM {
@@ -273,42 +622,149 @@ async fn main() -> Result<()> {
             out_path,
         } => clip_osm::run(pbf_path, clip_path, out_path)?,
         Command::ImportGrid2Demand { input, map } => import_grid2demand::run(input, map)?,
+        Command::ImportCollisions {
+            input,
+            map,
+            source_url,
+        } => import_collisions(input, map, source_url)?,
+        Command::Calibrate {
+            scenario,
+            counts,
+            iterations,
+            output,
+            rng_seed,
+        } => calibrate::run(scenario, counts, iterations, output, rng_seed)?,
         Command::ImportScenario {
             input,
             map,
             skip_problems,
         } => import_scenario::run(input, map, skip_problems),
+        Command::ImportODMatrix {
+            map,
+            zones,
+            zone_property,
+            matrix,
+            scenario_name,
+            rng_seed,
+        } => import_od_matrix::run(map, zones, zone_property, matrix, scenario_name, rng_seed)?,
+        Command::DeriveGatewayDemand { input, map, output } => {
+            import_scenario::derive_gateway_demand(input, map, output)
+        }
+        Command::ExportNetwork {
+            map,
+            format,
+            scenario,
+            output,
+        } => export_network::run(map, format, scenario, output)?,
+        Command::ExportGeoJSON { map, edits, output } => export_geojson(map, edits, output)?,
+        Command::ExportLinkStats {
+            scenario,
+            edits,
+            output,
+        } => export_link_stats(scenario, edits, output)?,
+        Command::ExportOsmChange { map, edits, output } => {
+            export_osmchange::run(map, edits, output).await?
+        }
+        Command::MigrateEdits { map, edits, output } => migrate_edits(map, edits, output)?,
+        Command::PartitionMap {
+            map,
+            num_parts,
+            output,
+        } => partition_map(map, num_parts, output)?,
+        Command::HazardImpacts { scenario, hazards } => hazard_impacts::run(scenario, hazards)?,
+        Command::MonteCarlo {
+            scenario,
+            edits,
+            runs,
+            departure_noise_minutes,
+            mode_noise_pct,
+            rng_seed,
+            output,
+        } => monte_carlo::run(
+            scenario,
+            edits,
+            runs,
+            departure_noise_minutes,
+            mode_noise_pct,
+            rng_seed,
+            output,
+        )?,
+        Command::MeasureReliability {
+            scenario,
+            edits,
+            runs,
+            jitter_minutes,
+        } => measure_reliability(scenario, edits, runs, jitter_minutes)?,
+        Command::SimulateFastForward {
+            scenario,
+            edits,
+            output,
+        } => simulate_fast_forward(scenario, edits, output)?,
+        Command::AnalyzeParallelism {
+            scenario,
+            num_parts,
+        } => analyze_parallelism(scenario, num_parts)?,
+        Command::CompareDeterminism { first, second } => compare_determinism(first, second)?,
+        Command::ImportSUMO { input, opts } => importer::sumo::oneshot(input, opts)?,
         Command::ImportJSONMap { input, output } => import_json_map(input, output),
         Command::MinifyMap { map } => minify_map(map),
+        Command::SplitPathfinder { map } => split_pathfinder(map),
         Command::GenerateHouses {
             map,
             num_required,
             rng_seed,
             output,
         } => generate_houses::run(map, num_required, rng_seed, output),
-        Command::PickGeofabrik { input } => {
-            println!("{}", importer::pick_geofabrik(input).await?.0)
+        Command::PickGeofabrik { input, bbbike } => {
+            if bbbike {
+                println!("{}", importer::pick_bbbike(input).await?.0)
+            } else {
+                println!("{}", importer::pick_geofabrik(input).await?.0)
+            }
         }
         Command::OneStepImport {
             geojson_path,
             map_name,
             use_geofabrik,
+            use_bbbike,
             use_osmium,
             inferred_sidewalks,
             filter_crosswalks,
+            skip_trails,
+            infer_parking,
+            zoning,
+            zoning_property,
+            signal_timing,
+            tag_filter,
+            elevation,
             create_uk_travel_demand_model,
+            population_raster,
             opts,
         } => {
             let mut options = convert_osm::Options::default();
             options.map_config.inferred_sidewalks = inferred_sidewalks;
             options.filter_crosswalks = filter_crosswalks;
+            options.include_trails = !skip_trails;
+            options.infer_onstreet_parking = infer_parking;
+            options.zoning = zoning.map(|path| convert_osm::ZoningInput {
+                path,
+                property: zoning_property,
+            });
+            options.signal_timing = signal_timing;
+            options.tag_filter = tag_filter;
+            if elevation {
+                options.elevation_dem_tiles =
+                    Some(abstio::path_shared_input("elevation/copernicus/"));
+            }
             one_step_import::run(
                 geojson_path,
                 map_name,
                 use_geofabrik,
+                use_bbbike,
                 use_osmium,
                 options,
                 create_uk_travel_demand_model,
+                population_raster.map(|path| importer::PopulationRaster { path }),
                 opts,
             )
             .await?
@@ -318,17 +774,33 @@ async fn main() -> Result<()> {
             clip_path,
             inferred_sidewalks,
             filter_crosswalks,
+            skip_trails,
+            infer_parking,
+            zoning,
+            zoning_property,
+            signal_timing,
+            tag_filter,
             create_uk_travel_demand_model,
+            population_raster,
             opts,
         } => {
             let mut options = convert_osm::Options::default();
             options.map_config.inferred_sidewalks = inferred_sidewalks;
             options.filter_crosswalks = filter_crosswalks;
+            options.include_trails = !skip_trails;
+            options.infer_onstreet_parking = infer_parking;
+            options.zoning = zoning.map(|path| convert_osm::ZoningInput {
+                path,
+                property: zoning_property,
+            });
+            options.signal_timing = signal_timing;
+            options.tag_filter = tag_filter;
             importer::oneshot(
                 osm_input,
                 clip_path,
                 options,
                 create_uk_travel_demand_model,
+                population_raster.map(|path| importer::PopulationRaster { path }),
                 opts,
             )
             .await
@@ -392,6 +864,90 @@ fn random_scenario(rng_seed: u64, map: String, scenario_name: String) {
     );
 }
 
+fn simulate_fast_forward(scenario: String, edits: Option<String>, output: String) -> Result<()> {
+    let mut timer = Timer::new("simulate fast-forward");
+    let scenario: synthpop::Scenario = abstio::must_read_object(scenario, &mut timer);
+    let mut map = map_model::Map::load_synchronously(scenario.map_name.path(), &mut timer);
+    if let Some(path) = edits {
+        let edits = map_model::MapEdits::load_from_file(&map, path, &mut timer)?;
+        map.must_apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+    }
+
+    let mut rng = sim::SimFlags::for_test("fast_forward").make_rng();
+    let mut sim = sim::FastForwardSim::new(&map, &scenario, &mut rng);
+    // Like prebaking, run a few hours past the end of the day
+    sim.run_until(
+        &map,
+        sim.get_end_of_day() + geom::Duration::hours(3),
+        &mut timer,
+    );
+    abstio::write_binary(output, sim.get_analytics());
+    println!(
+        "{:?}",
+        sim::PrebakeSummary::from_analytics(sim.get_analytics(), &scenario)
+    );
+    Ok(())
+}
+
+fn analyze_parallelism(scenario: String, num_parts: usize) -> Result<()> {
+    let mut timer = Timer::new("analyze parallelism");
+    let scenario: synthpop::Scenario = abstio::must_read_object(scenario, &mut timer);
+    let map = map_model::Map::load_synchronously(scenario.map_name.path(), &mut timer);
+    let partitioning = std::sync::Arc::new(map.partition(num_parts)?);
+    let (weights, _) = partitioning.summarize(&map);
+    for (part, weight) in weights.into_iter().enumerate() {
+        println!("Part {} has weight {}", part, prettyprint_usize(weight));
+    }
+    println!(
+        "{} roads cross between parts",
+        prettyprint_usize(partitioning.boundary_roads.len())
+    );
+    match sim::ParallelismEstimate::lookahead(&map, &partitioning) {
+        Some(dt) => println!("Parts could run ahead of each other by {}", dt),
+        None => println!("There's only one part"),
+    }
+
+    let mut opts = sim::SimOptions::new("analyze_parallelism");
+    opts.alerts = sim::AlertHandler::Silence;
+    let mut sim = sim::Sim::new(&map, opts);
+    sim.add_custom_metric(Box::new(sim::ParallelismEstimate::new(&map, partitioning)));
+    let mut rng = sim::SimFlags::for_test("analyze_parallelism").make_rng();
+    sim.instantiate(&scenario, &map, &mut rng, &mut timer);
+    sim.timed_step(
+        &map,
+        sim.get_end_of_day() - geom::Time::START_OF_DAY + geom::Duration::hours(3),
+        &mut None,
+        &mut timer,
+    );
+    for metric in sim.get_analytics().custom_metrics() {
+        for (label, value) in metric.values() {
+            println!("{}: {:.2}", label, value);
+        }
+    }
+    Ok(())
+}
+
+fn compare_determinism(first: String, second: String) -> Result<()> {
+    let mut timer = Timer::throwaway();
+    let log1: sim::DeterminismLog = abstio::maybe_read_json(first, &mut timer)?;
+    let log2: sim::DeterminismLog = abstio::maybe_read_json(second, &mut timer)?;
+    match log1.compare(&log2) {
+        Some(divergence) => {
+            println!("{}", divergence);
+        }
+        None => {
+            let n = log1.samples.len().min(log2.samples.len());
+            if n == 0 {
+                println!("There aren't any samples to compare");
+            } else {
+                println!("Both runs match through {}", log1.samples[n - 1].time);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn import_json_map(input: String, output: String) {
     // TODO This can't handle the output of dump_map! What?!
     let mut map: map_model::Map = abstio::read_json(input, &mut Timer::throwaway());
@@ -399,6 +955,172 @@ fn import_json_map(input: String, output: String) {
     abstio::write_binary(output, &map);
 }
 
+fn import_collisions(input: String, map: String, source_url: String) -> Result<()> {
+    let mut timer = Timer::new("import collisions");
+    let map = map_model::Map::load_synchronously(map, &mut timer);
+    let data = collisions::import_csv(&input, &source_url)?;
+    let path = map.get_city_name().input_path("collisions.bin");
+    println!(
+        "Imported {} collisions to {}",
+        prettyprint_usize(data.collisions.len()),
+        path
+    );
+    abstio::write_binary(path, &data);
+    Ok(())
+}
+
+fn export_geojson(map: String, edits: Option<String>, output: String) -> Result<()> {
+    let mut timer = Timer::new("export GeoJSON");
+    let mut map = map_model::Map::load_synchronously(map, &mut timer);
+    if let Some(path) = edits {
+        let edits = map_model::MapEdits::load_from_file(&map, path, &mut timer)?;
+        map.must_apply_edits(edits, &mut timer);
+    }
+    abstio::write_json(output, &map.export_layers());
+    Ok(())
+}
+
+fn measure_reliability(
+    scenario: String,
+    edits: Option<String>,
+    runs: usize,
+    jitter_minutes: usize,
+) -> Result<()> {
+    let mut timer = Timer::new("measure travel time reliability");
+    let scenario: synthpop::Scenario = abstio::must_read_object(scenario, &mut timer);
+    let mut map = map_model::Map::load_synchronously(scenario.map_name.path(), &mut timer);
+    let has_edits = edits.is_some();
+    if let Some(path) = edits {
+        let edits = map_model::MapEdits::load_from_file(&map, path, &mut timer)?;
+        map.must_apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+    }
+
+    let reliability = sim::Reliability::measure(
+        &map,
+        &scenario,
+        runs,
+        geom::Duration::minutes(jitter_minutes),
+        &mut timer,
+    );
+    let path = if has_edits {
+        abstio::path_reliability(
+            &scenario.map_name,
+            &scenario.scenario_name,
+            &reliability.edits_name,
+        )
+    } else {
+        abstio::path_prebaked_reliability(&scenario.map_name, &scenario.scenario_name)
+    };
+    abstio::write_binary(path.clone(), &reliability);
+    println!(
+        "Measured {} origin/destination pairs and {} roads over {} runs, saved to {}",
+        prettyprint_usize(reliability.od_pairs.len()),
+        prettyprint_usize(reliability.roads.len()),
+        runs,
+        path
+    );
+    Ok(())
+}
+
+fn export_link_stats(scenario: String, edits: Option<String>, output: String) -> Result<()> {
+    let mut timer = Timer::new("export link stats");
+    let scenario: synthpop::Scenario = abstio::must_read_object(scenario, &mut timer);
+    let mut map = map_model::Map::load_synchronously(scenario.map_name.path(), &mut timer);
+    if let Some(path) = edits {
+        let edits = map_model::MapEdits::load_from_file(&map, path, &mut timer)?;
+        map.must_apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+    }
+
+    let mut opts = sim::SimOptions::new("link_stats");
+    opts.alerts = sim::AlertHandler::Silence;
+    let mut sim = sim::Sim::new(&map, opts);
+    let mut rng = sim::SimFlags::for_test("link_stats").make_rng();
+    sim.instantiate(&scenario, &map, &mut rng, &mut timer);
+    // Like prebaking, run a few hours past the end of the day
+    sim.timed_step(
+        &map,
+        sim.get_end_of_day() - geom::Time::START_OF_DAY + geom::Duration::hours(3),
+        &mut None,
+        &mut timer,
+    );
+
+    let mut f = File::create(&output)?;
+    writeln!(
+        f,
+        "road,osm_way_id,vehicle_type,start_seconds,volume,traversals,average_speed_mps,average_delay_seconds"
+    )?;
+    for ((r, agent_type, bin), stats) in &sim.get_analytics().link_stats {
+        writeln!(
+            f,
+            "{},{},{},{},{},{},{},{}",
+            r.0,
+            map.get_r(*r).orig_id.osm_way_id.0,
+            agent_type.noun().to_lowercase(),
+            (*bin as f64) * sim::LINK_STATS_BIN.inner_seconds(),
+            stats.volume,
+            stats.traversals,
+            stats
+                .average_speed()
+                .map(|s| format!("{:.2}", s.inner_meters_per_second()))
+                .unwrap_or_default(),
+            stats
+                .average_delay()
+                .map(|d| format!("{:.1}", d.inner_seconds()))
+                .unwrap_or_default()
+        )?;
+    }
+    println!("Wrote {}", output);
+    Ok(())
+}
+
+fn migrate_edits(map: String, edits: String, output: String) -> Result<()> {
+    let mut timer = Timer::new("migrate edits");
+    let map = map_model::Map::load_synchronously(map, &mut timer);
+    let (perma, upgraded) =
+        map_model::PermanentMapEdits::from_json_bytes(&abstio::slurp_file(edits)?, &map)?;
+    if upgraded {
+        println!("Upgraded the edits from an older format");
+    }
+    let (edits, report) = perma.into_edits_migrating(&map);
+    println!(
+        "The migrated edits have {} commands. {} were moved, and {} couldn't be transferred.",
+        prettyprint_usize(edits.commands.len()),
+        prettyprint_usize(report.re_anchored.len()),
+        prettyprint_usize(report.failed.len())
+    );
+    for msg in report.re_anchored {
+        println!("- Moved: {}", msg);
+    }
+    for msg in report.failed {
+        println!("- Couldn't transfer: {}", msg);
+    }
+    abstio::write_json(output, &edits.to_permanent(&map));
+    Ok(())
+}
+
+fn partition_map(map: String, num_parts: usize, output: String) -> Result<()> {
+    let mut timer = Timer::new("partition map");
+    let map = map_model::Map::load_synchronously(map, &mut timer);
+    let partitioning = map.partition(num_parts)?;
+    let (weights, cut) = partitioning.summarize(&map);
+    for (part, weight) in weights.into_iter().enumerate() {
+        println!("Part {} has weight {}", part, prettyprint_usize(weight));
+    }
+    println!(
+        "{} roads with {} lanes cross between parts",
+        prettyprint_usize(partitioning.boundary_roads.len()),
+        prettyprint_usize(cut)
+    );
+    abstio::write_json(
+        output.trim_end_matches(".json").to_string() + ".geojson",
+        &partitioning.to_geojson(&map),
+    );
+    abstio::write_json(output, &partitioning);
+    Ok(())
+}
+
 fn minify_map(path: String) {
     let mut timer = Timer::new("minify map");
     let mut map = map_model::Map::load_synchronously(path, &mut timer);
@@ -407,6 +1129,12 @@ fn minify_map(path: String) {
     map.save();
 }
 
+fn split_pathfinder(path: String) {
+    let mut timer = Timer::new("split pathfinder");
+    let mut map = map_model::Map::load_synchronously(path, &mut timer);
+    map.save_with_lazy_pathfinder();
+}
+
 fn regenerate_everything_externally() -> Result<()> {
     let path = "regenerate.sh";
     let mut f = File::create(path)?;
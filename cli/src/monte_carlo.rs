@@ -0,0 +1,223 @@
+//! Run the same scenario many times with different random seeds, and summarize how much the
+//! results vary. A single run is deterministic, so it's easy to read too much into a small
+//! difference between two runs. Each run here shifts departure times and switches some people to
+//! a random mode, on top of the usual randomness when the scenario is instantiated (like where
+//! cars are parked).
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Duration, Time};
+use map_model::Map;
+use sim::{AlertHandler, Sim, SimOptions};
+use synthpop::{Scenario, ScenarioModifier, TripMode};
+
+pub fn run(
+    scenario_path: String,
+    edits: Option<String>,
+    runs: usize,
+    departure_noise_minutes: usize,
+    mode_noise_pct: usize,
+    rng_seed: u64,
+    output: String,
+) -> Result<()> {
+    if runs == 0 {
+        bail!("Need at least one run");
+    }
+    let mut timer = Timer::new("monte carlo batch");
+    let scenario: Scenario = abstio::must_read_object(scenario_path, &mut timer);
+    let mut map = Map::load_synchronously(scenario.map_name.path(), &mut timer);
+    if let Some(path) = edits {
+        let edits = map_model::MapEdits::load_from_file(&map, path, &mut timer)?;
+        map.must_apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+    }
+    let modifiers = vec![
+        ScenarioModifier::DepartureNoise(Duration::minutes(departure_noise_minutes)),
+        ScenarioModifier::ModeNoise {
+            pct_ppl: mode_noise_pct,
+        },
+    ];
+
+    let mut results: Vec<(u64, Vec<(String, f64)>)> = Vec::new();
+    for run in 0..runs {
+        let seed = rng_seed + (run as u64);
+        timer.start(format!("run {}/{} with seed {}", run + 1, runs, seed));
+        let mut rng = XorShiftRng::seed_from_u64(seed);
+        let mut varied = scenario.clone();
+        for m in &modifiers {
+            varied = m.apply(&map, varied, &mut rng);
+        }
+
+        let mut opts = SimOptions::new("monte_carlo");
+        opts.alerts = AlertHandler::Silence;
+        let mut sim = Sim::new(&map, opts);
+        sim.instantiate(&varied, &map, &mut rng, &mut timer);
+        // Like prebaking, run a few hours past the end of the day
+        sim.timed_step(
+            &map,
+            sim.get_end_of_day() - Time::START_OF_DAY + Duration::hours(3),
+            &mut None,
+            &mut timer,
+        );
+        results.push((seed, metrics(&sim)));
+        timer.stop(format!("run {}/{} with seed {}", run + 1, runs, seed));
+    }
+
+    let report = make_report(&scenario, &map, &modifiers, &results)?;
+    println!("{}", report);
+    println!(
+        "Wrote the report to {}",
+        abstio::write_file(output, report)?
+    );
+    Ok(())
+}
+
+/// The metrics from one run, in a consistent order
+fn metrics(sim: &Sim) -> Vec<(String, f64)> {
+    let analytics = sim.get_analytics();
+    let mut times: Vec<Duration> = Vec::new();
+    let mut cancelled = 0;
+    let mut per_mode: BTreeMap<TripMode, usize> = BTreeMap::new();
+    for (_, _, mode, maybe_dt) in &analytics.finished_trips {
+        match maybe_dt {
+            Some(dt) => {
+                times.push(*dt);
+                *per_mode.entry(*mode).or_insert(0) += 1;
+            }
+            None => {
+                cancelled += 1;
+            }
+        }
+    }
+    times.sort();
+    let total: f64 = times.iter().map(|dt| dt.inner_seconds()).sum();
+
+    let mut result = vec![
+        ("finished trips".to_string(), times.len() as f64),
+        ("cancelled trips".to_string(), cancelled as f64),
+        ("unfinished trips".to_string(), sim.num_trips().1 as f64),
+        (
+            "mean trip time (s)".to_string(),
+            if times.is_empty() {
+                0.0
+            } else {
+                total / (times.len() as f64)
+            },
+        ),
+        (
+            "median trip time (s)".to_string(),
+            percentile(&times, 50.0).inner_seconds(),
+        ),
+        (
+            "90th percentile trip time (s)".to_string(),
+            percentile(&times, 90.0).inner_seconds(),
+        ),
+        ("total trip time (hours)".to_string(), total / 3600.0),
+    ];
+    for mode in TripMode::all() {
+        result.push((
+            format!("{} trips finished", mode.ongoing_verb()),
+            per_mode.get(&mode).cloned().unwrap_or(0) as f64,
+        ));
+    }
+    result
+}
+
+// Assumes times are sorted
+fn percentile(times: &[Duration], pct: f64) -> Duration {
+    if times.is_empty() {
+        return Duration::ZERO;
+    }
+    let idx = ((pct / 100.0) * (times.len() as f64)).ceil() as usize;
+    times[idx.max(1).min(times.len()) - 1]
+}
+
+fn make_report(
+    scenario: &Scenario,
+    map: &Map,
+    modifiers: &[ScenarioModifier],
+    results: &[(u64, Vec<(String, f64)>)],
+) -> Result<String> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "# {} runs of {} on {}, with edits \"{}\"",
+        results.len(),
+        scenario.scenario_name,
+        scenario.map_name.describe(),
+        map.get_edits().edits_name
+    )?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{} people. Each run varies the RNG seed, and:",
+        prettyprint_usize(scenario.people.len())
+    )?;
+    for m in modifiers {
+        writeln!(out, "- {}", m.describe())?;
+    }
+    writeln!(out)?;
+
+    writeln!(out, "## Distributions")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "| metric | mean | std dev | min | 5th pct | median | 95th pct | max |"
+    )?;
+    writeln!(out, "|---|---|---|---|---|---|---|---|")?;
+    for (idx, (name, _)) in results[0].1.iter().enumerate() {
+        let mut values: Vec<f64> = results.iter().map(|(_, m)| m[idx].1).collect();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let std_dev = (values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+        let pct = |p: f64| {
+            let rank = ((p / 100.0) * n).ceil() as usize;
+            values[rank.max(1).min(values.len()) - 1]
+        };
+        writeln!(
+            out,
+            "| {} | {:.1} | {:.1} | {:.1} | {:.1} | {:.1} | {:.1} | {:.1} |",
+            name,
+            mean,
+            std_dev,
+            values[0],
+            pct(5.0),
+            pct(50.0),
+            pct(95.0),
+            values[values.len() - 1]
+        )?;
+    }
+    writeln!(out)?;
+    if results.len() < 20 {
+        writeln!(
+            out,
+            "With only {} runs, the 5th and 95th percentiles are just the extreme runs.",
+            results.len()
+        )?;
+        writeln!(out)?;
+    }
+
+    writeln!(out, "## Each run")?;
+    writeln!(out)?;
+    write!(out, "| seed |")?;
+    for (name, _) in &results[0].1 {
+        write!(out, " {} |", name)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "|---|{}", "---|".repeat(results[0].1.len()))?;
+    for (seed, values) in results {
+        write!(out, "| {} |", seed)?;
+        for (_, value) in values {
+            write!(out, " {:.1} |", value)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(out)
+}
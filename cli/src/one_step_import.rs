@@ -7,9 +7,11 @@ pub async fn run(
     geojson_path: String,
     name: String,
     use_geofabrik: bool,
+    use_bbbike: bool,
     use_osmium: bool,
     options: convert_osm::Options,
     create_uk_travel_demand_model: bool,
+    population_raster: Option<importer::PopulationRaster>,
     opts: map_model::RawToMapOptions,
 ) -> Result<()> {
     if name.contains(' ') || name.is_empty() {
@@ -21,7 +23,7 @@ pub async fn run(
 
     let city = CityName::new("zz", "oneshot");
     let osm;
-    if !use_geofabrik {
+    if !use_geofabrik && !use_bbbike {
         println!("Downloading OSM data from Overpass...");
         osm = city.input_path(format!("osm/{}.osm", name));
 
@@ -41,8 +43,13 @@ pub async fn run(
         abstio::download_to_file("https://overpass-api.de/api/interpreter", Some(query), &osm)
             .await?;
     } else {
-        println!("Figuring out what Geofabrik file contains your boundary");
-        let (url, pbf) = importer::pick_geofabrik(geojson_path.clone()).await?;
+        let (url, pbf) = if use_bbbike {
+            println!("Figuring out what BBBike extract contains your boundary");
+            importer::pick_bbbike(geojson_path.clone()).await?
+        } else {
+            println!("Figuring out what Geofabrik file contains your boundary");
+            importer::pick_geofabrik(geojson_path.clone()).await?
+        };
         osm = city.input_path(format!("osm/{}.osm.pbf", name));
         fs_err::create_dir_all(std::path::Path::new(&pbf).parent().unwrap())
             .expect("Creating parent dir failed");
@@ -70,6 +77,11 @@ pub async fn run(
         }
     }
 
+    if let Some(ref dir) = options.elevation_dem_tiles {
+        println!("Downloading elevation data");
+        importer::download_dem_tiles(&geojson_path, dir).await?;
+    }
+
     // Import!
     println!("Running importer");
     importer::oneshot(
@@ -77,6 +89,7 @@ pub async fn run(
         Some(geojson_path),
         options,
         create_uk_travel_demand_model,
+        population_raster,
         opts,
     )
     .await;
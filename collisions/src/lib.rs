@@ -4,6 +4,7 @@
 #[macro_use]
 extern crate log;
 
+use anyhow::Result;
 use geom::{Duration, LonLat};
 use kml::ExtraShapes;
 use serde::{Deserialize, Serialize};
@@ -27,6 +28,8 @@ pub struct Collision {
     pub time: Duration,
     /// The severity reported in the original data source.
     pub severity: Severity,
+    /// The most vulnerable kind of road user involved, if the data source says.
+    pub mode: Option<Mode>,
     /* TODO Many more interesting and common things: the date, the number of
      * people/vehicles/bikes/casualties, road/weather/alcohol/speeding conditions possibly
      * influencing the event, etc. */
@@ -42,6 +45,14 @@ pub enum Severity {
     Fatal,
 }
 
+/// Who was involved in a collision. If a cyclist was hit by a car, this is `Bike`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    Pedestrian,
+    Bike,
+    Vehicle,
+}
+
 /// Import data from the UK STATS19 dataset. See https://github.com/ropensci/stats19. Any parsing
 /// errors will skip the row and log a warning.
 pub fn import_stats19(input: ExtraShapes, source_url: &str) -> CollisionDataset {
@@ -75,6 +86,8 @@ pub fn import_stats19(input: ExtraShapes, source_url: &str) -> CollisionDataset
             location: shape.points[0],
             time,
             severity,
+            // The accidents table doesn't say; that's in the separate casualties table
+            mode: None,
         });
     }
     data
@@ -115,15 +128,102 @@ pub fn import_seattle(input: ExtraShapes, source_url: &str) -> CollisionDataset
                 continue;
             }
         };
+        let count = |key: &str| {
+            shape
+                .attributes
+                .get(key)
+                .and_then(|x| x.parse::<usize>().ok())
+                .unwrap_or(0)
+        };
+        let mode = if count("PEDCOUNT") > 0 {
+            Mode::Pedestrian
+        } else if count("PEDCYLCOUNT") > 0 {
+            Mode::Bike
+        } else {
+            Mode::Vehicle
+        };
         data.collisions.push(Collision {
             location: shape.points[0],
             time,
             severity,
+            mode: Some(mode),
         });
     }
     data
 }
 
+/// Import a generic CSV file, with one collision per row. The columns are:
+///
+/// - `latitude` and `longitude`
+/// - `severity`: slight, serious, or fatal
+/// - `mode` (optional): pedestrian, bike, or vehicle -- the most vulnerable road user involved
+/// - `time` (optional): the local time of day, like `17:30`. Midnight if it's missing.
+///
+/// Any parsing errors will skip the row and log a warning.
+pub fn import_csv(path: &str, source_url: &str) -> Result<CollisionDataset> {
+    let mut data = CollisionDataset {
+        source_url: source_url.to_string(),
+        collisions: Vec::new(),
+    };
+    for rec in csv::Reader::from_reader(fs_err::File::open(path)?).deserialize() {
+        let rec: CsvRecord = match rec {
+            Ok(rec) => rec,
+            Err(err) => {
+                warn!("Skipping row: {}", err);
+                continue;
+            }
+        };
+        let severity = match rec.severity.to_lowercase().as_ref() {
+            "slight" | "minor" => Severity::Slight,
+            "serious" | "severe" => Severity::Serious,
+            "fatal" | "killed" => Severity::Fatal,
+            x => {
+                warn!("Unknown severity {}", x);
+                continue;
+            }
+        };
+        let mode = match rec.mode.as_ref().map(|x| x.to_lowercase()) {
+            None => None,
+            Some(x) => match x.as_ref() {
+                "" => None,
+                "pedestrian" | "walk" => Some(Mode::Pedestrian),
+                "bike" | "bicycle" | "cyclist" => Some(Mode::Bike),
+                "vehicle" | "car" | "motorcycle" | "bus" | "truck" => Some(Mode::Vehicle),
+                x => {
+                    warn!("Unknown mode {}", x);
+                    continue;
+                }
+            },
+        };
+        let time = match rec.time.as_ref().filter(|x| !x.is_empty()) {
+            None => Duration::ZERO,
+            Some(x) => match Duration::parse(&format!("{}:00", x)) {
+                Ok(time) => time,
+                Err(err) => {
+                    warn!("Couldn't parse time: {}", err);
+                    continue;
+                }
+            },
+        };
+        data.collisions.push(Collision {
+            location: LonLat::new(rec.longitude, rec.latitude),
+            time,
+            severity,
+            mode,
+        });
+    }
+    Ok(data)
+}
+
+#[derive(Deserialize)]
+struct CsvRecord {
+    latitude: f64,
+    longitude: f64,
+    severity: String,
+    mode: Option<String>,
+    time: Option<String>,
+}
+
 // INCDTTM is something like "11/12/2019 7:30:00 AM"
 fn parse_incdttm(x: &str) -> Option<Duration> {
     let parts = x.split(' ').collect::<Vec<_>>();
@@ -1,16 +1,93 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::BufReader;
+use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use elevation::GeoTiffElevation;
 use fs_err::File;
-use geom::Distance;
+use geom::{Distance, GPSBounds, LonLat};
+use osm2streets::IntersectionID;
 
 use abstutil::Timer;
 use raw_map::RawMap;
 
 pub fn add_data(map: &mut RawMap, path: &str, timer: &mut Timer) -> Result<()> {
-    // Get intersection points from road endpoints, to reduce the number of elevation lookups
+    let intersection_points = intersection_points(map).into_iter().collect();
+    timer.start("lookup elevation");
+    lookup(map, path, intersection_points)?;
+    timer.stop("lookup elevation");
+
+    calculate_inclines(map);
+    Ok(())
+}
+
+/// Like `add_data`, but uses a directory of global DEM tiles, as named by `dem_tiles`. Tiles that
+/// don't exist are skipped; Copernicus doesn't publish tiles covering only ocean.
+pub fn add_data_from_tiles(map: &mut RawMap, dir: &str, timer: &mut Timer) -> Result<()> {
+    let mut points_per_tile: BTreeMap<String, Vec<(IntersectionID, LonLat)>> = BTreeMap::new();
+    for (i, gps) in intersection_points(map) {
+        points_per_tile
+            .entry(dem_tile_name(gps.x(), gps.y()))
+            .or_insert_with(Vec::new)
+            .push((i, gps));
+    }
+
+    let mut found_any = false;
+    timer.start_iter("lookup elevation from DEM tiles", points_per_tile.len());
+    for (tile, points) in points_per_tile {
+        timer.next();
+        let path = format!("{}/{}.tif", dir.trim_end_matches('/'), tile);
+        if !Path::new(&path).exists() {
+            warn!(
+                "Missing DEM tile {}, skipping {} intersections",
+                path,
+                points.len()
+            );
+            continue;
+        }
+        found_any = true;
+        lookup(map, &path, points)?;
+    }
+    if !found_any {
+        bail!("None of the DEM tiles covering this map exist in {}", dir);
+    }
+
+    calculate_inclines(map);
+    Ok(())
+}
+
+/// Returns the name and download URL of every 1x1 degree Copernicus GLO-30 DEM tile covering
+/// these bounds. The tiles are GeoTIFFs in EPSG:4326.
+pub fn dem_tiles(bounds: &GPSBounds) -> Vec<(String, String)> {
+    let mut tiles = Vec::new();
+    for lat in (bounds.min_lat.floor() as i64)..=(bounds.max_lat.floor() as i64) {
+        for lon in (bounds.min_lon.floor() as i64)..=(bounds.max_lon.floor() as i64) {
+            let name = dem_tile_name(lon as f64, lat as f64);
+            let url = format!(
+                "https://copernicus-dem-30m.s3.amazonaws.com/{}/{}.tif",
+                name, name
+            );
+            tiles.push((name, url));
+        }
+    }
+    tiles
+}
+
+fn dem_tile_name(lon: f64, lat: f64) -> String {
+    // Tiles are named by their southwest corner
+    let lat = lat.floor() as i64;
+    let lon = lon.floor() as i64;
+    format!(
+        "Copernicus_DSM_COG_10_{}{:02}_00_{}{:03}_00_DEM",
+        if lat < 0 { 'S' } else { 'N' },
+        lat.abs(),
+        if lon < 0 { 'W' } else { 'E' },
+        lon.abs()
+    )
+}
+
+// Get intersection points from road endpoints, to reduce the number of elevation lookups
+fn intersection_points(map: &RawMap) -> HashMap<IntersectionID, LonLat> {
     let mut intersection_points = HashMap::new();
     for r in map.streets.roads.values() {
         for (i, pt) in [
@@ -20,13 +97,12 @@ pub fn add_data(map: &mut RawMap, path: &str, timer: &mut Timer) -> Result<()> {
             intersection_points.insert(i, pt.to_gps(&map.streets.gps_bounds));
         }
     }
+    intersection_points
+}
 
-    // TODO Download the file if needed?
+fn lookup(map: &mut RawMap, path: &str, points: Vec<(IntersectionID, LonLat)>) -> Result<()> {
     let mut elevation = GeoTiffElevation::new(BufReader::new(File::open(path)?));
-
-    timer.start_iter("lookup elevation", intersection_points.len());
-    for (i, gps) in intersection_points {
-        timer.next();
+    for (i, gps) in points {
         if let Some(height) = elevation.get_height_for_lon_lat(gps.x() as f32, gps.y() as f32) {
             if height < 0.0 {
                 continue;
@@ -35,7 +111,10 @@ pub fn add_data(map: &mut RawMap, path: &str, timer: &mut Timer) -> Result<()> {
                 .insert(i, Distance::meters(height.into()));
         }
     }
+    Ok(())
+}
 
+fn calculate_inclines(map: &mut RawMap) {
     // Calculate the incline for each road here, before the road gets trimmed for intersection
     // geometry. If we did this after trimming, we'd miss some of the horizontal distance.
     for road in map.streets.roads.values() {
@@ -59,6 +138,4 @@ pub fn add_data(map: &mut RawMap, path: &str, timer: &mut Timer) -> Result<()> {
             );
         }
     }
-
-    Ok(())
 }
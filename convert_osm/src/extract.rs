@@ -1,12 +1,14 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
 
 use abstutil::{MultiMap, Tags, Timer};
 use geom::{Distance, FindClosest, GPSBounds, HashablePt2D, LonLat, Polygon, Pt2D, Ring};
 use osm2streets::osm::{OsmID, RelationID, WayID};
-use osm2streets::{osm, NamePerLanguage};
+use osm2streets::{osm, Direction, DrivingSide, LaneSpec, LaneType, NamePerLanguage};
 use raw_map::{
-    Amenity, AreaType, CrossingType, ExtraPOI, ExtraPOIType, RawArea, RawBuilding, RawMap,
-    RawParkingLot,
+    Amenity, AreaType, BarrierType, CrossingType, ExtraPOI, ExtraPOIType, RawArea, RawBuilding,
+    RawFerryRoute, RawMap, RawParkingLot, RawSite, RawTransitRoute, RawTransitStop, TrailType,
 };
 
 use crate::Options;
@@ -18,10 +20,24 @@ pub struct Extract {
     pub doc: streets_reader::osm_reader::Document,
     pub bus_routes_on_roads: MultiMap<WayID, String>,
     /// Crossings located at these points, which should be on a Road's center line
-    pub crossing_nodes: HashSet<(HashablePt2D, CrossingType)>,
-    /// Some kind of barrier nodes at these points.
-    pub barrier_nodes: Vec<(osm::NodeID, HashablePt2D)>,
+    pub crossing_nodes: HashMap<HashablePt2D, CrossingNode>,
+    /// Barriers that restrict some kind of traffic at these points
+    pub barrier_nodes: Vec<(osm::NodeID, HashablePt2D, BarrierType)>,
     pub extra_pois: Vec<ExtraPOI>,
+    /// Tram and light rail routes mapped in OSM, with their stops
+    pub rail_routes: Vec<(RawTransitRoute, Vec<RawTransitStop>)>,
+    pub ferry_routes: Vec<RawFerryRoute>,
+    /// Ways imported as trails, with `include_trails`
+    pub trails: HashMap<WayID, TrailType>,
+}
+
+#[derive(Clone, Copy)]
+pub struct CrossingNode {
+    pub kind: CrossingType,
+    /// Unmarked crossings don't give pedestrians priority
+    pub marked: bool,
+    /// Only found as a point along a `footway=crossing` way, so it might not be on a road at all
+    pub from_way: bool,
 }
 
 pub fn extract_osm(
@@ -30,7 +46,7 @@ pub fn extract_osm(
     clip_pts: Option<Vec<LonLat>>,
     opts: &Options,
     timer: &mut Timer,
-) -> Extract {
+) -> Result<Extract> {
     let osm_input_bytes = fs_err::read(osm_input_path).unwrap();
     let mut doc = streets_reader::osm_reader::Document::read(
         &osm_input_bytes,
@@ -55,12 +71,24 @@ pub fn extract_osm(
 
     streets_reader::detect_country_code(&mut map.streets);
 
+    if let Some(ref path) = opts.tag_filter {
+        timer.start("filter OSM tags");
+        crate::tag_filter::TagFilter::load(path, timer)
+            .map_err(|err| anyhow!("Can't load tag filter {}: {}", path, err))?
+            .apply(&mut doc);
+        timer.stop("filter OSM tags");
+    }
+
     let mut out = OsmExtract::new();
     let mut amenity_points = Vec::new();
     let mut bus_routes_on_roads: MultiMap<WayID, String> = MultiMap::new();
-    let mut crossing_nodes = HashSet::new();
+    // Tags describing the crossing at each point, from the node itself and any crossing way
+    // through it
+    let mut crossing_tags: HashMap<HashablePt2D, (Tags, bool)> = HashMap::new();
     let mut barrier_nodes = Vec::new();
     let mut extra_pois = Vec::new();
+    let mut rail_routes = Vec::new();
+    let mut ferry_routes = Vec::new();
 
     timer.start_iter("processing OSM nodes", doc.nodes.len());
     for (id, node) in &doc.nodes {
@@ -70,18 +98,21 @@ pub fn extract_osm(
             amenity_points.push((node.pt, amenity));
         }
         if node.tags.is(osm::HIGHWAY, "crossing") {
-            // TODO Look for crossing:signals:* too.
-            // https://wiki.openstreetmap.org/wiki/Tag:crossing=traffic%20signals?uselang=en
-            let kind = if node.tags.is("crossing", "traffic_signals") {
-                CrossingType::Signalized
-            } else {
-                CrossingType::Unsignalized
-            };
-            crossing_nodes.insert((node.pt.to_hashable(), kind));
+            crossing_tags.insert(node.pt.to_hashable(), (node.tags.clone(), false));
         }
-        // TODO Any kind of barrier?
-        if node.tags.is("barrier", "bollard") {
-            barrier_nodes.push((*id, node.pt.to_hashable()));
+        let red_turn_tags = Tags::new(
+            node.tags
+                .inner()
+                .iter()
+                .filter(|(k, _)| k.starts_with("red_turn:"))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        );
+        if !red_turn_tags.is_empty() {
+            map.red_turn_tags.insert(*id, red_turn_tags);
+        }
+        if let Some(barrier) = get_barrier_type(&node.tags) {
+            barrier_nodes.push((*id, node.pt.to_hashable(), barrier));
         }
 
         if node.tags.is("railway", "station") {
@@ -103,17 +134,71 @@ pub fn extract_osm(
                 }
             }
         }
+        if node.tags.is("natural", "tree") {
+            // Most trees aren't measured, so guess a typical mature street tree
+            let height = parse_meters(node.tags.get("height")).unwrap_or(Distance::meters(10.0));
+            let crown_diameter =
+                parse_meters(node.tags.get("diameter_crown")).unwrap_or(Distance::meters(6.0));
+            extra_pois.push(ExtraPOI {
+                pt: node.pt,
+                kind: ExtraPOIType::Tree {
+                    height,
+                    crown_diameter,
+                },
+            });
+        }
     }
 
     let mut coastline_groups: Vec<(WayID, Vec<Pt2D>)> = Vec::new();
     let mut memorial_areas: Vec<Polygon> = Vec::new();
     let mut amenity_areas: Vec<(Polygon, Amenity)> = Vec::new();
+    let mut trails = HashMap::new();
     timer.start_iter("processing OSM ways", doc.ways.len());
     for (id, way) in &mut doc.ways {
         timer.next();
         let id = *id;
 
-        if out.handle_way(id, &way, &opts.map_config) {
+        if way.tags.is("footway", "crossing") || way.tags.is("cycleway", "crossing") {
+            for pt in &way.pts {
+                let (tags, _) = crossing_tags
+                    .entry(pt.to_hashable())
+                    .or_insert_with(|| (Tags::empty(), true));
+                // Tags on the node win
+                for key in CROSSING_KEYS {
+                    if let Some(value) = way.tags.get(key) {
+                        if !tags.contains_key(key) {
+                            tags.insert(key, value.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        if way.tags.contains_key(osm::HIGHWAY) {
+            crate::parking::normalize_parking_tags(&mut way.tags, opts.infer_onstreet_parking);
+            normalize_turn_lanes(&mut way.tags);
+        }
+
+        let trail = if opts.include_trails {
+            get_trail_type(&way.tags)
+        } else {
+            None
+        };
+        let is_road = if let Some(trail) = trail {
+            // Only hand the trail to osm2streets as a cycleway. The tags kept for the road are the
+            // original ones.
+            let cycleway_tags = trail_as_cycleway(&way.tags);
+            let original_tags = std::mem::replace(&mut way.tags, cycleway_tags);
+            let is_road = out.handle_way(id, &way, &opts.map_config);
+            way.tags = original_tags;
+            if is_road {
+                trails.insert(id, trail);
+            }
+            is_road
+        } else {
+            out.handle_way(id, &way, &opts.map_config)
+        };
+        if is_road {
             continue;
         } else if way.tags.is(osm::HIGHWAY, "service") {
             // If we got here, is_road didn't interpret it as a normal road
@@ -132,6 +217,15 @@ pub fn extract_osm(
             continue;
         };
 
+        if is_site(&way.tags) && polygon.area() >= MIN_SITE_AREA_SQ_METERS {
+            map.sites.push(RawSite {
+                osm_id: OsmID::Way(id),
+                polygon: polygon.clone(),
+                osm_tags: way.tags.clone(),
+                aisles: Vec::new(),
+            });
+        }
+
         if is_bldg(&way.tags) {
             map.buildings.insert(
                 OsmID::Way(id),
@@ -140,6 +234,7 @@ pub fn extract_osm(
                     public_garage_name: None,
                     num_parking_spots: 0,
                     amenities: get_bldg_amenities(&way.tags),
+                    land_use: None,
                     osm_tags: way.tags.clone(),
                 },
             );
@@ -175,6 +270,21 @@ pub fn extract_osm(
         timer.next();
         let id = *id;
 
+        if rel.tags.is("type", "multipolygon") && is_site(&rel.tags) {
+            for polygon in
+                glue_multipolygon(id, doc.get_multipolygon_members(id, rel), Some(&boundary))
+            {
+                if polygon.area() >= MIN_SITE_AREA_SQ_METERS {
+                    map.sites.push(RawSite {
+                        osm_id: OsmID::Relation(id),
+                        polygon,
+                        osm_tags: rel.tags.clone(),
+                        aisles: Vec::new(),
+                    });
+                }
+            }
+        }
+
         if out.handle_relation(id, rel) {
             continue;
         } else if let Some(area_type) = get_area_type(&rel.tags) {
@@ -201,6 +311,7 @@ pub fn extract_osm(
                                 public_garage_name: None,
                                 num_parking_spots: 0,
                                 amenities: get_bldg_amenities(&rel.tags),
+                                land_use: None,
                                 osm_tags: rel.tags.clone(),
                             },
                         );
@@ -238,6 +349,18 @@ pub fn extract_osm(
                     }
                 }
             }
+        } else if crate::rail::is_rail_route(&rel.tags) {
+            if let Some(route) =
+                crate::rail::extract_rail_route(map, &doc, id, &rel.tags, &rel.members)
+            {
+                rail_routes.push(route);
+            }
+        } else if crate::ferry::is_ferry_route(&rel.tags) {
+            if let Some(route) =
+                crate::ferry::extract_ferry_route(map, &doc, id, &rel.tags, &rel.members)
+            {
+                ferry_routes.push(route);
+            }
         } else if rel.tags.is("type", "route") && rel.tags.is("route", "bus") {
             if let Some(name) = rel.tags.get("name") {
                 for (role, member) in &rel.members {
@@ -318,16 +441,208 @@ pub fn extract_osm(
     }
     timer.stop("find service roads crossing parking lots");
 
-    Extract {
+    timer.start("find service roads inside large sites");
+    find_site_aisles(map, &mut out.roads);
+    timer.stop("find service roads inside large sites");
+
+    Ok(Extract {
         osm: out,
         doc,
         bus_routes_on_roads,
-        crossing_nodes,
+        crossing_nodes: crossing_tags
+            .into_iter()
+            .filter_map(|(pt, (tags, from_way))| {
+                let (kind, marked) = get_crossing_type(&tags)?;
+                Some((
+                    pt,
+                    CrossingNode {
+                        kind,
+                        marked,
+                        from_way,
+                    },
+                ))
+            })
+            .collect(),
         barrier_nodes,
         extra_pois,
+        rail_routes,
+        ferry_routes,
+        trails,
+    })
+}
+
+/// map_model reads `turn:lanes:forward` (or `turn:lanes`) for forwards lanes and
+/// `turn:lanes:backward` for backwards lanes. Clean up the values and make the direction explicit,
+/// so that's all it needs to understand.
+fn normalize_turn_lanes(tags: &mut Tags) {
+    // On a oneway=-1 road, the unqualified tag describes the only direction of travel, which is
+    // backwards relative to the way
+    if tags.is("oneway", "-1") && !tags.contains_key("turn:lanes:backward") {
+        if let Some(value) = tags.remove("turn:lanes") {
+            tags.insert("turn:lanes:backward", value);
+        }
+    }
+
+    for key in ["turn:lanes", "turn:lanes:forward", "turn:lanes:backward"] {
+        if let Some(value) = tags.get(key) {
+            // Values are sometimes written with stray whitespace or capitals, like
+            // "Left | through;right"
+            let cleaned = value
+                .split('|')
+                .map(|lane| {
+                    lane.split(';')
+                        .map(|x| x.trim().to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(";")
+                })
+                .collect::<Vec<_>>()
+                .join("|");
+            if cleaned != *value {
+                tags.insert(key, cleaned);
+            }
+        }
     }
 }
 
+/// Paths through parks and along old railways often have no bike tags, so they'd only be
+/// imported as footways. Is this one of them, open to both walking and cycling?
+fn get_trail_type(tags: &Tags) -> Option<TrailType> {
+    let trail = if tags.is(osm::HIGHWAY, "path") {
+        TrailType::Path
+    } else if tags.is(osm::HIGHWAY, "track") {
+        TrailType::Track
+    } else {
+        return None;
+    };
+    if tags.is("area", "yes")
+        || tags.is_any("bicycle", vec!["no", "dismount"])
+        || tags.is("foot", "no")
+    {
+        return None;
+    }
+    // Farm and forestry tracks are often private
+    if tags.is_any("access", vec!["no", "private"])
+        && !tags.is_any("bicycle", vec!["yes", "designated", "permissive"])
+    {
+        return None;
+    }
+    Some(trail)
+}
+
+/// The tags osm2streets needs to accept a trail as a road. Its lanes come from `trail_lanes`.
+fn trail_as_cycleway(tags: &Tags) -> Tags {
+    let mut tags = tags.clone();
+    tags.insert(osm::HIGHWAY, "cycleway");
+    if !tags.contains_key("foot") {
+        tags.insert("foot", "yes");
+    }
+    tags
+}
+
+/// osm2streets has no lane type for trails, and would only make bike lanes for them. Lay one out
+/// here instead: a bike lane each way, unless cycling is one-way, and a path along the outside
+/// that people walk on in both directions.
+pub fn trail_lanes(tags: &Tags, driving_side: DrivingSide) -> Vec<LaneSpec> {
+    let spec = |lt, dir| LaneSpec {
+        lt,
+        dir,
+        width: LaneSpec::typical_lane_widths(lt, "cycleway")[0].0,
+        allowed_turns: Default::default(),
+    };
+    let oneway = tags.is("oneway", "yes") || tags.is("oneway:bicycle", "yes");
+    // Listed for driving on the right, where forwards lanes are on the right
+    let mut lanes = Vec::new();
+    if !oneway {
+        lanes.push(spec(LaneType::Biking, Direction::Back));
+    }
+    lanes.push(spec(LaneType::Biking, Direction::Fwd));
+    lanes.push(spec(LaneType::Sidewalk, Direction::Fwd));
+    if driving_side == DrivingSide::Left {
+        lanes.reverse();
+    }
+    lanes
+}
+
+const CROSSING_KEYS: [&str; 4] = [
+    "crossing",
+    "crossing:markings",
+    "crossing:signals",
+    "crossing_ref",
+];
+
+/// Is the crossing signalized, and is it marked? None if crossing isn't allowed here. Untagged
+/// crossings are assumed to be marked.
+fn get_crossing_type(tags: &Tags) -> Option<(CrossingType, bool)> {
+    if tags.is("crossing", "no") {
+        return None;
+    }
+    let kind = if tags.is("crossing", "traffic_signals") || tags.is("crossing:signals", "yes") {
+        CrossingType::Signalized
+    } else {
+        CrossingType::Unsignalized
+    };
+    let marked = !(tags.is("crossing", "unmarked") || tags.is("crossing:markings", "no"));
+    Some((kind, marked))
+}
+
+/// Only barriers that stop some traffic matter; open gates, kerbs, and fences crossing footpaths
+/// are ignored.
+fn get_barrier_type(tags: &Tags) -> Option<BarrierType> {
+    let lets_buses_through = tags.is_any("bus", vec!["yes", "designated"])
+        || tags.is_any("psv", vec!["yes", "designated"]);
+    match tags.get("barrier")?.as_str() {
+        "bus_trap" | "sump_buster" => Some(BarrierType::BusGate),
+        "bollard" | "block" | "planter" | "cycle_barrier" => {
+            if tags.is_any("motor_vehicle", vec!["yes", "designated"]) {
+                None
+            } else if lets_buses_through {
+                Some(BarrierType::BusGate)
+            } else {
+                Some(BarrierType::WalkCycleOnly)
+            }
+        }
+        "gate" | "lift_gate" | "swing_gate" => {
+            if tags.is_any("access", vec!["no", "private"])
+                || tags.is_any("motor_vehicle", vec!["no", "private"])
+            {
+                if lets_buses_through {
+                    Some(BarrierType::BusGate)
+                } else {
+                    Some(BarrierType::PrivateGate)
+                }
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Like `12` or `12 m`. Other units are rare for trees, so they're ignored.
+fn parse_meters(raw: Option<&String>) -> Option<Distance> {
+    let value = raw?
+        .trim()
+        .trim_end_matches('m')
+        .trim()
+        .parse::<f64>()
+        .ok()?;
+    if value > 0.0 {
+        Some(Distance::meters(value))
+    } else {
+        None
+    }
+}
+
+// Smaller hospitals, schools, and so on don't have much of an internal road network
+const MIN_SITE_AREA_SQ_METERS: f64 = 20_000.0;
+
+fn is_site(tags: &Tags) -> bool {
+    tags.is_any(
+        "amenity",
+        vec!["hospital", "university", "college", "school"],
+    ) || tags.is("shop", "mall")
+}
+
 fn is_bldg(tags: &Tags) -> bool {
     // Sorry, the towers at Gasworks don't count. :)
     tags.contains_key("building") && !tags.contains_key("abandoned:man_made")
@@ -434,3 +749,91 @@ fn service_road_crosses_parking_lot(map: &RawMap, pts: &[Pt2D], candidates: Vec<
     }
     false
 }
+
+// Service roads entirely inside a large site are its internal aisles, not part of the public
+// network. Service roads crossing the site's boundary stay, since they're how the site connects to
+// everything else.
+fn find_site_aisles(map: &mut RawMap, roads: &mut Vec<(WayID, Vec<Pt2D>, Tags)>) {
+    if map.sites.is_empty() {
+        return;
+    }
+    let mut keep_roads = Vec::new();
+    for (id, pts, osm_tags) in roads.drain(..) {
+        if osm_tags.is(osm::HIGHWAY, "service") {
+            if let Some(site) = map
+                .sites
+                .iter_mut()
+                .find(|site| pts.iter().all(|pt| site.polygon.contains_pt(*pt)))
+            {
+                site.aisles.push((id, pts));
+                continue;
+            }
+        }
+        keep_roads.push((id, pts, osm_tags));
+    }
+    roads.extend(keep_roads);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crossing(kv: &[(&str, &str)]) -> Option<(CrossingType, bool)> {
+        get_crossing_type(&Tags::new(
+            kv.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        ))
+    }
+
+    #[test]
+    fn test_get_crossing_type() {
+        assert_eq!(crossing(&[]), Some((CrossingType::Unsignalized, true)));
+        assert_eq!(
+            crossing(&[("crossing", "traffic_signals")]),
+            Some((CrossingType::Signalized, true))
+        );
+        assert_eq!(
+            crossing(&[("crossing:signals", "yes"), ("crossing:markings", "no")]),
+            Some((CrossingType::Signalized, false))
+        );
+        assert_eq!(
+            crossing(&[("crossing", "unmarked")]),
+            Some((CrossingType::Unsignalized, false))
+        );
+        assert_eq!(crossing(&[("crossing", "no")]), None);
+    }
+
+    #[test]
+    fn test_trail_lanes() {
+        let lanes = |kv: &[(&str, &str)], driving_side| {
+            let tags = Tags::new(
+                kv.iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            );
+            trail_lanes(&tags, driving_side)
+                .into_iter()
+                .map(|spec| (spec.lt, spec.dir))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            lanes(&[("highway", "path")], DrivingSide::Right),
+            vec![
+                (LaneType::Biking, Direction::Back),
+                (LaneType::Biking, Direction::Fwd),
+                (LaneType::Sidewalk, Direction::Fwd),
+            ]
+        );
+        assert_eq!(
+            lanes(
+                &[("highway", "track"), ("oneway:bicycle", "yes")],
+                DrivingSide::Left
+            ),
+            vec![
+                (LaneType::Sidewalk, Direction::Fwd),
+                (LaneType::Biking, Direction::Fwd),
+            ]
+        );
+    }
+}
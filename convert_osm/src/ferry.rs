@@ -0,0 +1,95 @@
+//! Ferries are mapped in OSM as `route=ferry` relations
+//! (<https://wiki.openstreetmap.org/wiki/Tag:route%3Dferry>). Turn those into ferry routes,
+//! following the water between terminals.
+
+use abstutil::Tags;
+use geom::{PolyLine, Pt2D};
+use osm2streets::osm::{OsmID, RelationID};
+use raw_map::{RawFerryRoute, RawMap};
+use streets_reader::osm_reader::Document;
+
+use crate::rail::{glue_way, parse_interval, spawn_times_every};
+
+pub fn is_ferry_route(tags: &Tags) -> bool {
+    tags.is("type", "route") && tags.is("route", "ferry")
+}
+
+/// Returns None if the route doesn't connect at least two terminals inside the map.
+pub fn extract_ferry_route(
+    map: &RawMap,
+    doc: &Document,
+    id: RelationID,
+    tags: &Tags,
+    members: &[(String, OsmID)],
+) -> Option<RawFerryRoute> {
+    let boundary = &map.streets.boundary_polygon;
+    let name = tags
+        .get("name")
+        .cloned()
+        .unwrap_or_else(|| format!("ferry {}", id.0));
+
+    let mut pts: Vec<Pt2D> = Vec::new();
+    for (role, member) in members {
+        if let OsmID::Way(w) = member {
+            if !role.is_empty() {
+                continue;
+            }
+            if let Some(way) = doc.ways.get(w) {
+                glue_way(&mut pts, way.pts.clone());
+            }
+        }
+    }
+    pts.dedup();
+    let shape = PolyLine::new(pts).ok()?;
+
+    // Terminals are usually stop or platform nodes. Many relations only have the water part, so
+    // fall back to where it reaches the shore.
+    let mut terminals = Vec::new();
+    for (role, member) in members {
+        if let OsmID::Node(n) = member {
+            if !role.starts_with("stop") && !role.starts_with("platform") {
+                continue;
+            }
+            if let Some(node) = doc.nodes.get(n) {
+                let terminal_name = node
+                    .tags
+                    .get("name")
+                    .cloned()
+                    .unwrap_or_else(|| name.clone());
+                terminals.push((terminal_name, node.pt));
+            }
+        }
+    }
+    if terminals.is_empty() {
+        terminals.push((name.clone(), shape.first_pt()));
+        terminals.push((name.clone(), shape.last_pt()));
+    }
+    terminals.retain(|(_, pt)| boundary.contains_pt(*pt));
+    terminals.dedup_by(|a, b| a.1 == b.1);
+
+    // Put the terminals in order along the water
+    let mut with_dist = Vec::new();
+    for (terminal_name, pt) in terminals {
+        let (dist, _) = shape.dist_along_of_point(shape.project_pt(pt))?;
+        with_dist.push((dist, terminal_name, pt));
+    }
+    with_dist.sort_by_key(|(dist, _, _)| *dist);
+    if with_dist.len() < 2 {
+        return None;
+    }
+
+    Some(RawFerryRoute {
+        osm_id: OsmID::Relation(id),
+        name,
+        shape,
+        terminals: with_dist
+            .into_iter()
+            .map(|(_, terminal_name, pt)| (terminal_name, pt))
+            .collect(),
+        spawn_times: tags
+            .get("interval")
+            .and_then(|x| parse_interval(x))
+            .map(spawn_times_every)
+            .unwrap_or_default(),
+    })
+}
@@ -5,7 +5,7 @@ use fs_err::File;
 use serde::Deserialize;
 
 use abstutil::MultiMap;
-use geom::{LonLat, PolyLine, Pt2D};
+use geom::{Duration, LonLat, PolyLine, Pt2D, Time};
 use kml::{ExtraShape, ExtraShapes};
 use raw_map::{RawMap, RawTransitRoute, RawTransitStop, RawTransitType};
 
@@ -18,8 +18,8 @@ pub fn import(map: &mut RawMap) -> Result<()> {
         // See https://developers.google.com/transit/gtfs/reference#routestxt
         let route_type = match rec.route_type {
             3 => RawTransitType::Bus,
-            // These aren't distinguished in the map model yet. Trams and streetcars might
-            // particularly mess up...  or just fail to snap to a road later.
+            // Trams, subways, and rail all run on light rail tracks in the map model. Their stops
+            // snap to the nearest track.
             0 | 1 | 2 => RawTransitType::Train,
             _ => continue,
         };
@@ -34,9 +34,13 @@ pub fn import(map: &mut RawMap) -> Result<()> {
             shape: PolyLine::dummy(),
             stops: Vec::new(),
             route_type,
+            spawn_times: Vec::new(),
         });
     }
 
+    // The schedule is for one typical weekday
+    let weekday_services = load_weekday_services(map)?;
+
     // Map route_id to shape_id
     let mut route_to_shapes = MultiMap::new();
     // Map (route_id, shape_id) to trip_id
@@ -45,6 +49,11 @@ pub fn import(map: &mut RawMap) -> Result<()> {
         .deserialize()
     {
         let rec: Trip = rec?;
+        if let Some(ref services) = weekday_services {
+            if !services.contains(&rec.service_id) {
+                continue;
+            }
+        }
         route_to_shapes.insert(rec.route_id.clone(), rec.shape_id.clone());
         route_and_shape_to_trips.insert((rec.route_id, rec.shape_id), rec.trip_id);
     }
@@ -105,39 +114,68 @@ pub fn import(map: &mut RawMap) -> Result<()> {
     }
     map.transit_routes = transit_routes;
 
-    // For now, every route uses exactly one trip ID, and there's no schedule. Just pick an
-    // arbitrary trip per route.
+    // Every route uses the stops of one arbitrary trip with the chosen shape. All trips with that
+    // shape contribute to the schedule.
     let mut route_to_trip = HashMap::new();
     for (route_id, shape_id) in &route_to_shape {
         let trips = route_and_shape_to_trips.get((route_id.clone(), shape_id.clone()));
-        if let Some(trip_id) = trips.into_iter().next() {
+        if let Some(trip_id) = trips.iter().next() {
             route_to_trip.insert(route_id.clone(), trip_id);
         }
     }
 
-    // Scrape the trip ID -> (stop ID, sequence number)
+    // Scrape the trip ID -> (stop ID, sequence number), and when each trip leaves its first stop
     let mut trip_to_stops: HashMap<TripID, Vec<(StopID, usize)>> = HashMap::new();
+    let mut trip_departures: HashMap<TripID, (usize, Time)> = HashMap::new();
     for rec in
         csv::Reader::from_reader(File::open(map.name.city.input_path("gtfs/stop_times.txt"))?)
             .deserialize()
     {
         let rec: StopTime = rec?;
+        if let Some(time) = parse_time(&rec.departure_time) {
+            let entry = trip_departures
+                .entry(rec.trip_id.clone())
+                .or_insert((rec.stop_sequence, time));
+            if rec.stop_sequence < entry.0 {
+                *entry = (rec.stop_sequence, time);
+            }
+        }
         trip_to_stops
             .entry(rec.trip_id)
             .or_insert_with(Vec::new)
             .push((rec.stop_id, rec.stop_sequence));
     }
+    let trip_frequencies = load_frequencies(map)?;
 
-    // Assign the stops for every route
+    // Assign the stops and schedule for every route
     let mut stop_ids = HashSet::new();
     for route in &mut map.transit_routes {
-        let trip_id = route_to_trip[&RouteID(route.gtfs_id.clone())];
-        let mut stops = trip_to_stops.remove(&trip_id).unwrap_or_else(Vec::new);
+        let route_id = RouteID(route.gtfs_id.clone());
+        let trip_id = route_to_trip[&route_id];
+        let mut stops = trip_to_stops.remove(trip_id).unwrap_or_else(Vec::new);
         stops.sort_by_key(|(_, seq)| *seq);
         for (stop_id, _) in stops {
             route.stops.push(stop_id.0.clone());
             stop_ids.insert(stop_id);
         }
+
+        let shape_id = route_to_shape[&route_id].clone();
+        for trip_id in route_and_shape_to_trips.get((route_id, shape_id)) {
+            if let Some(frequencies) = trip_frequencies.get(trip_id) {
+                // Trips defined by headways instead of exact times
+                for freq in frequencies {
+                    let mut time = freq.start;
+                    while time < freq.end {
+                        route.spawn_times.push(time);
+                        time = time + freq.headway;
+                    }
+                }
+            } else if let Some((_, time)) = trip_departures.get(trip_id) {
+                route.spawn_times.push(*time);
+            }
+        }
+        route.spawn_times.sort();
+        route.spawn_times.dedup();
     }
 
     // Scrape stop metadata
@@ -199,11 +237,15 @@ struct Route {
     route_type: usize,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+struct ServiceID(String);
+
 #[derive(Deserialize)]
 struct Trip {
     route_id: RouteID,
     shape_id: ShapeID,
     trip_id: TripID,
+    service_id: ServiceID,
 }
 
 #[derive(Deserialize)]
@@ -227,6 +269,158 @@ struct StopTime {
     trip_id: TripID,
     stop_id: StopID,
     stop_sequence: usize,
+    // Only required for the first and last stop of a trip
+    #[serde(default)]
+    departure_time: String,
+}
+
+#[derive(Deserialize)]
+struct Calendar {
+    service_id: ServiceID,
+    tuesday: usize,
+}
+
+#[derive(Deserialize)]
+struct CalendarDate {
+    service_id: ServiceID,
+    /// YYYYMMDD
+    date: String,
+    /// 1 if the service runs that day, 2 if it doesn't
+    exception_type: usize,
+}
+
+#[derive(Deserialize)]
+struct Frequency {
+    trip_id: TripID,
+    start_time: String,
+    end_time: String,
+    headway_secs: usize,
+}
+
+struct Headway {
+    start: Time,
+    end: Time,
+    headway: Duration,
+}
+
+/// GTFS times are HH:MM:SS since the start of the service day, and may exceed 24 hours for trips
+/// running past midnight.
+fn parse_time(raw: &str) -> Option<Time> {
+    let parts: Vec<&str> = raw.trim().split(':').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let hours = parts[0].parse::<usize>().ok()?;
+    let minutes = parts[1].parse::<usize>().ok()?;
+    let seconds = parts[2].parse::<usize>().ok()?;
+    Some(Time::START_OF_DAY + Duration::seconds((hours * 3600 + minutes * 60 + seconds) as f64))
+}
+
+/// Returns the services running on a typical weekday (Tuesday), or None if there's no calendar
+/// at all, and every trip should be used.
+///
+/// Services in calendar.txt say which days they usually run. Some feeds list services only in
+/// calendar_dates.txt, with every date they run; use the ones running on the Tuesday with the most
+/// of them, so one-off specials on other dates don't count.
+fn load_weekday_services(map: &RawMap) -> Result<Option<HashSet<ServiceID>>> {
+    let calendar_path = map.name.city.input_path("gtfs/calendar.txt");
+    let dates_path = map.name.city.input_path("gtfs/calendar_dates.txt");
+    if !abstio::file_exists(&calendar_path) && !abstio::file_exists(&dates_path) {
+        return Ok(None);
+    }
+
+    let mut services = HashSet::new();
+    let mut in_calendar = HashSet::new();
+    if abstio::file_exists(&calendar_path) {
+        for rec in csv::Reader::from_reader(File::open(calendar_path)?).deserialize() {
+            let rec: Calendar = rec?;
+            if rec.tuesday == 1 {
+                services.insert(rec.service_id.clone());
+            }
+            in_calendar.insert(rec.service_id);
+        }
+    }
+
+    if abstio::file_exists(&dates_path) {
+        let mut per_tuesday: BTreeMap<String, HashSet<ServiceID>> = BTreeMap::new();
+        for rec in csv::Reader::from_reader(File::open(dates_path)?).deserialize() {
+            let rec: CalendarDate = rec?;
+            if rec.exception_type == 1
+                && !in_calendar.contains(&rec.service_id)
+                && is_tuesday(&rec.date)
+            {
+                per_tuesday
+                    .entry(rec.date)
+                    .or_insert_with(HashSet::new)
+                    .insert(rec.service_id);
+            }
+        }
+        // Ties go to the earliest date
+        let mut best: Option<HashSet<ServiceID>> = None;
+        for date_services in per_tuesday.into_values() {
+            if best
+                .as_ref()
+                .map(|x| date_services.len() > x.len())
+                .unwrap_or(true)
+            {
+                best = Some(date_services);
+            }
+        }
+        services.extend(best.unwrap_or_default());
+    }
+
+    if services.is_empty() {
+        warn!("No GTFS services run on a Tuesday, so using every trip");
+        return Ok(None);
+    }
+    Ok(Some(services))
+}
+
+/// Is a YYYYMMDD date a Tuesday?
+fn is_tuesday(date: &str) -> bool {
+    if date.len() != 8 || !date.is_ascii() {
+        return false;
+    }
+    let (y, m, d) = match (
+        date[0..4].parse::<usize>(),
+        date[4..6].parse::<usize>(),
+        date[6..8].parse::<usize>(),
+    ) {
+        (Ok(y), Ok(m), Ok(d)) if (1..=12).contains(&m) => (y, m, d),
+        _ => {
+            return false;
+        }
+    };
+    // Sakamoto's method, with 0 for Sunday
+    let offsets = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let y = if m < 3 { y - 1 } else { y };
+    (y + y / 4 - y / 100 + y / 400 + offsets[m - 1] + d) % 7 == 2
+}
+
+/// frequencies.txt is optional, describing trips that repeat on a headway.
+fn load_frequencies(map: &RawMap) -> Result<HashMap<TripID, Vec<Headway>>> {
+    let mut results: HashMap<TripID, Vec<Headway>> = HashMap::new();
+    let path = map.name.city.input_path("gtfs/frequencies.txt");
+    if !abstio::file_exists(&path) {
+        return Ok(results);
+    }
+    for rec in csv::Reader::from_reader(File::open(path)?).deserialize() {
+        let rec: Frequency = rec?;
+        if let (Some(start), Some(end)) = (parse_time(&rec.start_time), parse_time(&rec.end_time)) {
+            if rec.headway_secs == 0 {
+                continue;
+            }
+            results
+                .entry(rec.trip_id)
+                .or_insert_with(Vec::new)
+                .push(Headway {
+                    start,
+                    end,
+                    headway: Duration::seconds(rec.headway_secs as f64),
+                });
+        }
+    }
+    Ok(results)
 }
 
 fn dump_kml(map: &RawMap) {
@@ -260,3 +454,17 @@ fn dump_kml(map: &RawMap) {
         &ExtraShapes { shapes },
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_tuesday() {
+        assert!(is_tuesday("20240102"));
+        assert!(is_tuesday("20240305"));
+        assert!(!is_tuesday("20240229"));
+        assert!(!is_tuesday("20240101"));
+        assert!(!is_tuesday("2024-01-02"));
+    }
+}
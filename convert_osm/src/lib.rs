@@ -3,24 +3,39 @@ extern crate log;
 
 use std::collections::{HashMap, HashSet};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 use abstio::MapName;
 use abstutil::{Tags, Timer};
 use geom::{Distance, HashablePt2D, LonLat, PolyLine, Polygon};
 use osm2streets::{osm, MapConfig, Road, RoadID};
-use raw_map::{CrossingType, ExtraRoadData, RawMap};
+use raw_map::{BarrierType, ExtraRoadData, RawMap, RawTransitType};
+
+use crate::extract::CrossingNode;
 
 mod elevation;
 mod extract;
+mod ferry;
 mod gtfs;
 mod parking;
+mod rail;
+mod signal_timing;
+mod tag_filter;
+mod zoning;
+
+pub use elevation::dem_tiles;
+pub use tag_filter::TagFilter;
+pub use zoning::ZoningInput;
 
 /// Configures the creation of a `RawMap` from OSM and other input data.
+#[derive(Debug)]
 pub struct Options {
     pub map_config: MapConfig,
 
     pub onstreet_parking: OnstreetParking,
+    /// If a road has no on-street parking tags at all, maybe guess some based on its type and
+    /// width.
+    pub infer_onstreet_parking: ParkingInference,
     pub public_offstreet_parking: PublicOffstreetParking,
     pub private_offstreet_parking: PrivateOffstreetParking,
     /// If provided, read polygons from this GeoJSON file and add them to the RawMap as buildings.
@@ -29,8 +44,23 @@ pub struct Options {
     pub gtfs_url: Option<String>,
     /// Path to a GeoTIFF file in EPSG:4326 to use for elevation data
     pub elevation_geotiff: Option<String>,
+    /// If `elevation_geotiff` isn't set, use a directory of global DEM tiles, as described by
+    /// `dem_tiles`, for elevation data. These cover anywhere, at a coarse resolution.
+    pub elevation_dem_tiles: Option<String>,
     /// Only include crosswalks that match a `highway=crossing` OSM node.
     pub filter_crosswalks: bool,
+    /// Import off-road `highway=path` and `highway=track` trails as shared-use paths for people
+    /// walking and cycling. This is on by default, but off for the built-in maps until their
+    /// scenarios and tests are regenerated.
+    pub include_trails: bool,
+    /// Attach land-use categories from official zoning or parcel data to buildings
+    pub zoning: Option<ZoningInput>,
+    /// Use signal timing sheets from this CSV file, instead of guessing. See
+    /// convert_osm/src/signal_timing.rs for the format.
+    pub signal_timing: Option<String>,
+    /// Rewrite OSM tags using the rules in this JSON file before importing anything. See
+    /// convert_osm/src/tag_filter.rs for the format.
+    pub tag_filter: Option<String>,
 }
 
 impl Options {
@@ -38,18 +68,25 @@ impl Options {
         Self {
             map_config: MapConfig::default(),
             onstreet_parking: OnstreetParking::JustOSM,
+            infer_onstreet_parking: ParkingInference::Never,
             public_offstreet_parking: PublicOffstreetParking::None,
             private_offstreet_parking: PrivateOffstreetParking::FixedPerBldg(1),
             extra_buildings: None,
             gtfs_url: None,
             elevation_geotiff: None,
+            elevation_dem_tiles: None,
             filter_crosswalks: false,
+            include_trails: true,
+            zoning: None,
+            signal_timing: None,
+            tag_filter: None,
         }
     }
 }
 
 /// What roads will have on-street parking lanes? Data from
 /// <https://wiki.openstreetmap.org/wiki/Key:parking:lane> is always used if available.
+#[derive(Debug)]
 pub enum OnstreetParking {
     /// If not tagged, there won't be parking.
     JustOSM,
@@ -58,7 +95,45 @@ pub enum OnstreetParking {
     Blockface(String),
 }
 
+/// How aggressively to guess on-street parking for roads that have no parking tags in OSM?
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParkingInference {
+    /// Untagged roads have no parking.
+    Never,
+    /// Only residential streets get parking, on one side if they're tagged as narrow.
+    Conservative,
+    /// Most minor roads get parking on both sides, unless they're tagged as very narrow.
+    Aggressive,
+}
+
+impl std::str::FromStr for ParkingInference {
+    type Err = anyhow::Error;
+
+    fn from_str(x: &str) -> Result<Self> {
+        match x {
+            "never" => Ok(ParkingInference::Never),
+            "conservative" => Ok(ParkingInference::Conservative),
+            "aggressive" => Ok(ParkingInference::Aggressive),
+            _ => bail!(
+                "Unknown ParkingInference {}; use never, conservative, or aggressive",
+                x
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for ParkingInference {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParkingInference::Never => write!(f, "never"),
+            ParkingInference::Conservative => write!(f, "conservative"),
+            ParkingInference::Aggressive => write!(f, "aggressive"),
+        }
+    }
+}
+
 /// How many spots are available in public parking garages?
+#[derive(Debug)]
 pub enum PublicOffstreetParking {
     None,
     /// Pull data from
@@ -69,19 +144,20 @@ pub enum PublicOffstreetParking {
 
 /// If a building doesn't have anything from public_offstreet_parking and isn't tagged as a garage
 /// in OSM, how many private spots should it have?
+#[derive(Debug)]
 pub enum PrivateOffstreetParking {
     FixedPerBldg(usize),
     // TODO Based on the number of residents?
 }
 
-/// Create a RawMap from OSM and other input data.
+/// Create a RawMap from OSM and other input data. Fails if the tag filter can't be loaded.
 pub fn convert(
     osm_input_path: String,
     name: MapName,
     clip_path: Option<String>,
     opts: Options,
     timer: &mut Timer,
-) -> RawMap {
+) -> Result<RawMap> {
     timer.start("create RawMap from input data");
 
     let mut map = RawMap::blank(name);
@@ -91,7 +167,7 @@ pub fn convert(
 
     let clip_pts = clip_path.map(|path| LonLat::read_geojson_polygon(&path).unwrap());
     timer.start("extract all from OSM");
-    let extract = extract::extract_osm(&mut map, &osm_input_path, clip_pts, &opts, timer);
+    let extract = extract::extract_osm(&mut map, &osm_input_path, clip_pts, &opts, timer)?;
     timer.stop("extract all from OSM");
     let pt_to_road =
         streets_reader::split_ways::split_up_roads(&mut map.streets, extract.osm, timer);
@@ -107,8 +183,14 @@ pub fn convert(
     for i in map.streets.intersections.keys() {
         map.elevation_per_intersection.insert(*i, Distance::ZERO);
     }
-    for r in map.streets.roads.keys() {
-        map.extra_road_data.insert(*r, ExtraRoadData::default());
+    for (id, r) in &map.streets.roads {
+        let mut extra = ExtraRoadData::default();
+        extra.trail = r
+            .osm_ids
+            .get(0)
+            .and_then(|w| extract.trails.get(w))
+            .cloned();
+        map.extra_road_data.insert(*id, extra);
     }
 
     // Remember OSM tags for all roads. Do this before apply_parking, which looks at tags
@@ -126,6 +208,25 @@ pub fn convert(
     }
     timer.stop("preserve OSM tags");
 
+    let driving_side = map.streets.config.driving_side;
+    let trail_lanes: Vec<_> = map
+        .extra_road_data
+        .iter()
+        .filter(|(_, extra)| extra.trail.is_some())
+        .filter_map(|(r, _)| {
+            let tags = map.road_to_osm_tags(*r)?;
+            Some((*r, extract::trail_lanes(tags, driving_side)))
+        })
+        .collect();
+    for (r, lanes) in trail_lanes {
+        let road = map.streets.roads.get_mut(&r).unwrap();
+        road.lane_specs_ltr = lanes;
+        road.update_center_line(driving_side);
+        let (i1, i2) = (road.src_i, road.dst_i);
+        map.streets.update_i(i1);
+        map.streets.update_i(i2);
+    }
+
     parking::apply_parking(&mut map, &opts, timer);
 
     timer.start("use barrier and crossing nodes");
@@ -143,14 +244,48 @@ pub fn convert(
             error!("No elevation data: {}", err);
         }
         timer.stop("add elevation data");
+    } else if let Some(ref dir) = opts.elevation_dem_tiles {
+        timer.start("add elevation data from DEM tiles");
+        if let Err(err) = elevation::add_data_from_tiles(&mut map, dir, timer) {
+            error!("No elevation data: {}", err);
+        }
+        timer.stop("add elevation data from DEM tiles");
     }
     if let Some(ref path) = opts.extra_buildings {
         add_extra_buildings(&mut map, path).unwrap();
     }
+    if let Some(ref input) = opts.zoning {
+        timer.start("add zoning data");
+        if let Err(err) = zoning::apply(&mut map, input, timer) {
+            error!("No zoning data: {}", err);
+        }
+        timer.stop("add zoning data");
+    }
+    if let Some(ref path) = opts.signal_timing {
+        timer.start("add signal timing");
+        if let Err(err) = signal_timing::apply(&mut map, path) {
+            error!("No signal timing data: {}", err);
+        }
+        timer.stop("add signal timing");
+    }
 
     if opts.gtfs_url.is_some() {
         gtfs::import(&mut map).unwrap();
     }
+    // GTFS has real schedules, so only fall back to OSM rail routes when it has none
+    if !map
+        .transit_routes
+        .iter()
+        .any(|r| r.route_type == RawTransitType::Train)
+    {
+        for (route, stops) in extract.rail_routes {
+            for stop in stops {
+                map.transit_stops.insert(stop.gtfs_id.clone(), stop);
+            }
+            map.transit_routes.push(route);
+        }
+    }
+    map.ferry_routes = extract.ferry_routes;
 
     timer.start("Add census data");
     if let Err(err) = add_census(&mut map) {
@@ -164,7 +299,7 @@ pub fn convert(
 
     timer.stop("create RawMap from input data");
 
-    map
+    Ok(map)
 }
 
 fn add_extra_buildings(map: &mut RawMap, path: &str) -> Result<()> {
@@ -184,6 +319,7 @@ fn add_extra_buildings(map: &mut RawMap, path: &str) -> Result<()> {
                 public_garage_name: None,
                 num_parking_spots: 1,
                 amenities: Vec::new(),
+                land_use: None,
             },
         );
         // We could use new_osm_way_id, but faster to just assume we're the only place introducing
@@ -285,7 +421,7 @@ fn clip_map(map: &mut RawMap, timer: &mut Timer) {
 
 fn use_barrier_nodes(
     map: &mut RawMap,
-    barrier_nodes: Vec<(osm::NodeID, HashablePt2D)>,
+    barrier_nodes: Vec<(osm::NodeID, HashablePt2D, BarrierType)>,
     pt_to_road: &HashMap<HashablePt2D, RoadID>,
 ) {
     // An OSM node likely only maps to one intersection
@@ -296,7 +432,7 @@ fn use_barrier_nodes(
         }
     }
 
-    for (node, pt) in barrier_nodes {
+    for (node, pt, barrier) in barrier_nodes {
         // Many barriers are on footpaths or roads that we don't retain
         if let Some(road) = pt_to_road.get(&pt).and_then(|r| map.streets.roads.get(r)) {
             // Filters on roads that're already car-free are redundant
@@ -305,17 +441,26 @@ fn use_barrier_nodes(
                     .get_mut(&road.id)
                     .unwrap()
                     .barrier_nodes
-                    .push(pt.to_pt2d());
+                    .push((pt.to_pt2d(), barrier));
             }
         } else if let Some(i) = node_to_intersection.get(&node) {
             let roads = &map.streets.intersections[i].roads;
             if roads.len() == 2 {
-                // Arbitrarily put the barrier on one of the roads
+                // A gate where a driveway meets a street only restricts the driveway. Otherwise,
+                // arbitrarily put the barrier on one of the roads.
+                let r = if barrier == BarrierType::PrivateGate {
+                    *roads
+                        .iter()
+                        .find(|r| is_service_road(map, **r))
+                        .unwrap_or(&roads[0])
+                } else {
+                    roads[0]
+                };
                 map.extra_road_data
-                    .get_mut(&roads[0])
+                    .get_mut(&r)
                     .unwrap()
                     .barrier_nodes
-                    .push(pt.to_pt2d());
+                    .push((pt.to_pt2d(), barrier));
             } else {
                 // TODO Look for real examples at non-2-way intersections to understand what to do.
                 // If there's a barrier in the middle of a 4-way, does that disconnect all
@@ -329,25 +474,70 @@ fn use_barrier_nodes(
     }
 }
 
+fn is_service_road(map: &RawMap, r: RoadID) -> bool {
+    map.road_to_osm_tags(r)
+        .map(|tags| tags.is(osm::HIGHWAY, "service"))
+        .unwrap_or(false)
+}
+
+/// Crossings farther than this from both ends of a road are mid-block
+const MAX_CROSSING_DIST_FROM_END: Distance = Distance::const_meters(20.0);
+
+/// Is this crossing near the start (true) or end (false) of the road, or mid-block (None)?
+fn crossing_near_end(road: &Road, pt: HashablePt2D) -> Option<bool> {
+    let (dist, _) = road.reference_line.dist_along_of_point(pt.to_pt2d())?;
+    let len = road.reference_line.length();
+    let at_start = dist <= len / 2.0;
+    let dist_to_end = if at_start { dist } else { len - dist };
+    if dist_to_end <= MAX_CROSSING_DIST_FROM_END {
+        Some(at_start)
+    } else {
+        None
+    }
+}
+
+/// Records every crossing along the roads it's on. An unmarked crossing mapped near an
+/// intersection means there's no crosswalk there, so pedestrians cross it as an unmarked crossing
+/// instead. `filter_crosswalks` goes further, keeping only crosswalks with a marked crossing mapped.
+///
+/// Mid-block crossings only become `Road::crossings`, which the LTN tool uses. Pedestrians in the
+/// simulation still only cross roads at intersections.
 fn use_crossing_nodes(
     map: &mut RawMap,
-    crossing_nodes: &HashSet<(HashablePt2D, CrossingType)>,
+    crossing_nodes: &HashMap<HashablePt2D, CrossingNode>,
     pt_to_road: &HashMap<HashablePt2D, RoadID>,
 ) {
-    for (pt, kind) in crossing_nodes {
+    for (pt, crossing) in crossing_nodes {
         // Some crossings are on footpaths or roads that we don't retain
-        if let Some(road) = pt_to_road
-            .get(pt)
-            .and_then(|r| map.extra_road_data.get_mut(r))
-        {
-            road.crossing_nodes.push((pt.to_pt2d(), *kind));
+        let road = match pt_to_road.get(pt).and_then(|r| map.streets.roads.get(r)) {
+            Some(road) => road,
+            None => continue,
+        };
+        // Points along a crossing way may just be where it meets a sidewalk
+        if crossing.from_way && !road.is_driveable() {
+            continue;
+        }
+        let near_end = crossing_near_end(road, *pt);
+        let data = map.extra_road_data.get_mut(&road.id).unwrap();
+        data.crossing_nodes.push((pt.to_pt2d(), crossing.kind));
+
+        if !crossing.marked {
+            match near_end {
+                Some(true) => {
+                    data.crosswalk_backward = false;
+                }
+                Some(false) => {
+                    data.crosswalk_forward = false;
+                }
+                None => {}
+            }
         }
     }
 }
 
 fn filter_crosswalks(
     map: &mut RawMap,
-    crosswalks: HashSet<(HashablePt2D, CrossingType)>,
+    crossing_nodes: HashMap<HashablePt2D, CrossingNode>,
     pt_to_road: HashMap<HashablePt2D, RoadID>,
     timer: &mut Timer,
 ) {
@@ -358,30 +548,34 @@ fn filter_crosswalks(
         road.crosswalk_backward = false;
     }
 
-    // Match each crosswalk node to a road
-    timer.start_iter("filter crosswalks", crosswalks.len());
-    for (pt, _) in crosswalks {
+    // Match each marked crossing to a road
+    timer.start_iter("filter crosswalks", crossing_nodes.len());
+    for (pt, crossing) in crossing_nodes {
         timer.next();
+        if !crossing.marked {
+            continue;
+        }
         // Some crossing nodes are outside the map boundary or otherwise not on a road that we
         // retained
         if let Some(road) = pt_to_road.get(&pt).and_then(|r| map.streets.roads.get(r)) {
-            // Crossings aren't right at an intersection. Where is this point along the center
-            // line?
-            if let Some((dist, _)) = road.reference_line.dist_along_of_point(pt.to_pt2d()) {
-                let pct = dist / road.reference_line.length();
-                // Don't throw away any crossings. If it occurs in the first half of the road, snap
-                // to the first intersection. If there's a mid-block crossing mapped, that'll
-                // likely not be correctly interpreted, unless an intersection is there anyway.
-                let data = map.extra_road_data.get_mut(&road.id).unwrap();
-                if pct <= 0.5 {
+            if crossing.from_way && !road.is_driveable() {
+                continue;
+            }
+            // Crossings aren't right at an intersection. Snap ones close enough to the nearest
+            // intersection. Mid-block crossings are kept as crossing nodes along the road instead.
+            let data = map.extra_road_data.get_mut(&road.id).unwrap();
+            match crossing_near_end(road, pt) {
+                Some(true) => {
                     data.crosswalk_backward = true;
-                } else {
+                }
+                Some(false) => {
                     data.crosswalk_forward = true;
                 }
-
-                // TODO Some crosswalks incorrectly snap to the intersection near a short service
-                // road, which later gets trimmed. So the crosswalk effectively disappears.
+                None => {}
             }
+
+            // TODO Some crosswalks incorrectly snap to the intersection near a short service
+            // road, which later gets trimmed. So the crosswalk effectively disappears.
         }
     }
 }
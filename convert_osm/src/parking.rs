@@ -4,7 +4,9 @@ use kml::ExtraShapes;
 use osm2streets::{osm, RoadID};
 use raw_map::RawMap;
 
-use crate::{OnstreetParking, Options, PrivateOffstreetParking, PublicOffstreetParking};
+use crate::{
+    OnstreetParking, Options, ParkingInference, PrivateOffstreetParking, PublicOffstreetParking,
+};
 
 // Just used for matching hints to different sides of a road.
 const DIRECTED_ROAD_THICKNESS: Distance = Distance::const_meters(2.5);
@@ -25,6 +27,88 @@ pub fn apply_parking(map: &mut RawMap, opts: &Options, timer: &mut Timer) {
     apply_private_offstreet_parking(map, &opts.private_offstreet_parking);
 }
 
+/// osm2streets only understands the older `parking:lane:*` scheme. Translate the newer
+/// `parking:both/left/right` tags (<https://wiki.openstreetmap.org/wiki/Street_parking>) into it,
+/// and for roads with no parking tags at all, maybe guess.
+pub fn normalize_parking_tags(tags: &mut Tags, inference: ParkingInference) {
+    for side in ["both", "left", "right"] {
+        let old_key = format!("parking:lane:{}", side);
+        if tags.contains_key(&old_key) {
+            continue;
+        }
+        let value = match tags.get(&format!("parking:{}", side)) {
+            Some(x) => x.clone(),
+            None => {
+                continue;
+            }
+        };
+        let translated = match value.as_ref() {
+            "lane" | "street_side" | "on_kerb" | "half_on_kerb" | "shoulder" | "yes" => {
+                match tags.get(&format!("parking:{}:orientation", side)) {
+                    Some(x) if x == "diagonal" || x == "perpendicular" => x.clone(),
+                    _ => "parallel".to_string(),
+                }
+            }
+            // Parking mapped as a separate area doesn't belong to the road
+            "no" | "separate" => "no_parking".to_string(),
+            _ => {
+                continue;
+            }
+        };
+        tags.insert(old_key, translated);
+    }
+
+    if unknown_parking(tags) {
+        if let Some(key) = infer_parking(tags, inference) {
+            tags.insert(key, "parallel");
+        }
+    }
+}
+
+/// Returns the `parking:lane:*` key to tag, if any.
+fn infer_parking(tags: &Tags, inference: ParkingInference) -> Option<&'static str> {
+    let highway_types = match inference {
+        ParkingInference::Never => {
+            return None;
+        }
+        ParkingInference::Conservative => vec!["residential", "living_street"],
+        ParkingInference::Aggressive => vec![
+            "residential",
+            "living_street",
+            "unclassified",
+            "tertiary",
+            "secondary",
+        ],
+    };
+    if !tags.is_any(osm::HIGHWAY, highway_types)
+        || tags.is("area", "yes")
+        || tags.is_any("access", vec!["no", "private"])
+    {
+        return None;
+    }
+
+    // The minimum carriageway width for parking on both sides, and on one side
+    let (both_sides, one_side) = match inference {
+        ParkingInference::Conservative => (Distance::meters(9.0), Distance::meters(7.0)),
+        _ => (Distance::meters(7.5), Distance::meters(5.5)),
+    };
+    match tags.get("width").and_then(|x| parse_width(x)) {
+        Some(width) if width >= both_sides => Some("parking:lane:both"),
+        Some(width) if width >= one_side => Some("parking:lane:right"),
+        Some(_) => None,
+        // Without a width, the residential street is probably a typical one with parking
+        None => Some("parking:lane:both"),
+    }
+}
+
+fn parse_width(x: &str) -> Option<Distance> {
+    x.trim_end_matches('m')
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .map(Distance::meters)
+}
+
 fn unknown_parking(tags: &Tags) -> bool {
     !tags.contains_key("parking:lane:left")
         && !tags.contains_key("parking:lane:right")
@@ -0,0 +1,147 @@
+//! Tram and light rail lines are often mapped in OSM
+//! (<https://wiki.openstreetmap.org/wiki/Public_transport>) even where no GTFS feed is
+//! available. Turn those route relations into transit routes and stops.
+//!
+//! This is only part of tram support. There's no tram lane type, since osm2streets decides lane
+//! types, so these routes become trains running on its light rail lanes. The simulation treats
+//! them like any other train. Where osm2streets doesn't make a light rail lane, like trams sharing
+//! a lane with cars, the route instead runs on the driving lanes, like a bus.
+
+use abstutil::Tags;
+use geom::{Duration, PolyLine, Pt2D, Time};
+use osm2streets::osm::{OsmID, RelationID};
+use raw_map::{RawMap, RawTransitRoute, RawTransitStop, RawTransitType};
+use streets_reader::osm_reader::Document;
+
+/// Is this relation a tram or light rail route?
+pub fn is_rail_route(tags: &Tags) -> bool {
+    tags.is("type", "route") && tags.is_any("route", vec!["tram", "light_rail"])
+}
+
+/// Returns None if the route has no alignment or no stops inside the map.
+pub fn extract_rail_route(
+    map: &RawMap,
+    doc: &Document,
+    id: RelationID,
+    tags: &Tags,
+    members: &[(String, OsmID)],
+) -> Option<(RawTransitRoute, Vec<RawTransitStop>)> {
+    let boundary = &map.streets.boundary_polygon;
+    let route_name = tags
+        .get("name")
+        .cloned()
+        .unwrap_or_else(|| format!("route {}", id.0));
+
+    // Ways with no role are the alignment, in order
+    let mut pts: Vec<Pt2D> = Vec::new();
+    for (role, member) in members {
+        if let OsmID::Way(w) = member {
+            if !role.is_empty() {
+                continue;
+            }
+            if let Some(way) = doc.ways.get(w) {
+                glue_way(&mut pts, way.pts.clone());
+            }
+        }
+    }
+    pts.dedup();
+    let shape = PolyLine::new(pts).ok()?;
+
+    // Prefer stop positions on the track, but fall back to platforms
+    let mut stops = Vec::new();
+    for roles in [
+        vec!["stop", "stop_entry_only", "stop_exit_only"],
+        vec!["platform", "platform_entry_only", "platform_exit_only"],
+    ] {
+        for (role, member) in members {
+            if let OsmID::Node(n) = member {
+                if !roles.contains(&role.as_str()) {
+                    continue;
+                }
+                if let Some(node) = doc.nodes.get(n) {
+                    if boundary.contains_pt(node.pt) {
+                        stops.push(RawTransitStop {
+                            gtfs_id: format!("osm_node_{}", n.0),
+                            position: node.pt,
+                            name: node
+                                .tags
+                                .get("name")
+                                .cloned()
+                                .unwrap_or_else(|| route_name.clone()),
+                        });
+                    }
+                }
+            }
+        }
+        if !stops.is_empty() {
+            break;
+        }
+    }
+    // Consecutive stop_entry_only / stop_exit_only pairs may share a node
+    stops.dedup_by(|a, b| a.gtfs_id == b.gtfs_id);
+    if stops.is_empty() {
+        return None;
+    }
+
+    let route = RawTransitRoute {
+        long_name: route_name.clone(),
+        short_name: tags.get("ref").cloned().unwrap_or(route_name),
+        gtfs_id: format!("osm_relation_{}", id.0),
+        shape,
+        stops: stops.iter().map(|s| s.gtfs_id.clone()).collect(),
+        route_type: RawTransitType::Train,
+        spawn_times: tags
+            .get("interval")
+            .and_then(|x| parse_interval(x))
+            .map(spawn_times_every)
+            .unwrap_or_default(),
+    };
+    Some((route, stops))
+}
+
+/// Ways in a route relation are ordered, but each one may point either way.
+pub(crate) fn glue_way(pts: &mut Vec<Pt2D>, mut way_pts: Vec<Pt2D>) {
+    if let Some(last) = pts.last().cloned() {
+        if way_pts.last() == Some(&last) {
+            way_pts.reverse();
+        } else if way_pts[0] != last && (pts[0] == way_pts[0] || Some(&pts[0]) == way_pts.last()) {
+            // Only the first way has been added so far and it's backwards
+            pts.reverse();
+            if way_pts.last() == pts.last() {
+                way_pts.reverse();
+            }
+        }
+    }
+    // If there's a gap, just jump over it. The shape is only used to find where vehicles enter
+    // and leave the map.
+    pts.extend(way_pts);
+}
+
+/// `interval` is tagged in minutes, or as `HH:MM` or `HH:MM:SS`.
+pub(crate) fn parse_interval(raw: &str) -> Option<Duration> {
+    let parts: Vec<f64> = raw
+        .trim()
+        .split(':')
+        .map(|x| x.parse::<f64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let interval = match parts.as_slice() {
+        [minutes] => Duration::seconds(minutes * 60.0),
+        [hours, minutes] => Duration::seconds(hours * 3600.0 + minutes * 60.0),
+        [hours, minutes, seconds] => Duration::seconds(hours * 3600.0 + minutes * 60.0 + seconds),
+        _ => {
+            return None;
+        }
+    };
+    if interval <= Duration::ZERO {
+        return None;
+    }
+    Some(interval)
+}
+
+pub(crate) fn spawn_times_every(interval: Duration) -> Vec<Time> {
+    let end_of_day = Time::START_OF_DAY + Duration::hours(24);
+    (0..)
+        .map(|i| Time::START_OF_DAY + (i as f64) * interval)
+        .take_while(|t| *t < end_of_day)
+        .collect()
+}
@@ -0,0 +1,241 @@
+//! Many cities publish timing sheets for their traffic signals. Use these as the signal programs,
+//! instead of the heuristic defaults.
+//!
+//! The input is a CSV file with one row per stage of each signal. Signals are matched to the
+//! closest signalled intersection by location. Exports from Synchro (UTDF) and most city sheets
+//! can be flattened into this format:
+//!
+//! - `latitude` and `longitude` of the intersection
+//! - `plan`: optional; only the first plan listed for each signal is used
+//! - `stage`: the order of the stage in the cycle, starting at 1
+//! - `green`, `yellow`, and `all_red`: seconds; the stage lasts for the sum
+//! - `offset`: optional, in seconds, relative to a central clock
+//! - `protected` and `permitted`: space-separated movements, like `NBL SBT`. Directions are the
+//!   way traffic approaches, then `L`, `T`, or `R` for left, through, or right.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+
+use geom::{Angle, Distance, Duration, LonLat, Pt2D};
+use osm2streets::{osm, Direction, IntersectionControl, IntersectionID, LaneType};
+use raw_map::{RawMap, RawSignalProgram, RawSignalStage};
+
+// Timing sheets locate signals roughly; don't match anything farther away than this
+const MAX_SNAP_DIST: Distance = Distance::const_meters(50.0);
+
+#[derive(Deserialize)]
+struct Record {
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    plan: String,
+    stage: usize,
+    green: f64,
+    #[serde(default)]
+    yellow: f64,
+    #[serde(default)]
+    all_red: f64,
+    #[serde(default)]
+    offset: f64,
+    protected: String,
+    #[serde(default)]
+    permitted: String,
+}
+
+pub fn apply(map: &mut RawMap, path: &str) -> Result<()> {
+    // Group stages by location, keeping the first plan for each
+    let mut per_signal: BTreeMap<String, Vec<Record>> = BTreeMap::new();
+    for rec in csv::Reader::from_reader(fs_err::File::open(path)?).deserialize() {
+        let rec: Record = rec?;
+        let records = per_signal
+            .entry(format!("{},{}", rec.longitude, rec.latitude))
+            .or_insert_with(Vec::new);
+        if records.is_empty() || records[0].plan == rec.plan {
+            records.push(rec);
+        }
+    }
+
+    let signals: BTreeMap<IntersectionID, Pt2D> = intersection_points(map)
+        .into_iter()
+        .filter(|(i, _)| map.streets.intersections[i].control == IntersectionControl::Signalled)
+        .collect();
+
+    let mut num_matches = 0;
+    let num_signals = per_signal.len();
+    for (_, mut records) in per_signal {
+        let pt =
+            LonLat::new(records[0].longitude, records[0].latitude).to_pt(&map.streets.gps_bounds);
+        let closest = signals
+            .iter()
+            .map(|(i, i_pt)| (*i, i_pt.dist_to(pt)))
+            .min_by_key(|(_, dist)| *dist);
+        let i = match closest {
+            Some((i, dist)) if dist <= MAX_SNAP_DIST => i,
+            _ => {
+                warn!(
+                    "No signalled intersection near {}",
+                    pt.to_gps(&map.streets.gps_bounds)
+                );
+                continue;
+            }
+        };
+        let osm_id = match map.streets.intersections[&i].osm_ids.get(0) {
+            Some(id) => *id,
+            None => {
+                continue;
+            }
+        };
+
+        records.sort_by_key(|rec| rec.stage);
+        match make_program(map, i, &records) {
+            Ok(program) => {
+                map.traffic_signal_programs.insert(osm_id, program);
+                num_matches += 1;
+            }
+            Err(err) => {
+                warn!("Can't use the timing sheet for {}: {}", osm_id, err);
+            }
+        }
+    }
+    info!(
+        "Matched {} of {} signal timing sheets to intersections",
+        num_matches, num_signals
+    );
+    Ok(())
+}
+
+fn make_program(map: &RawMap, i: IntersectionID, records: &[Record]) -> Result<RawSignalProgram> {
+    let approaches = Approaches::new(map, i);
+    let mut stages = Vec::new();
+    for rec in records {
+        let mut stage = RawSignalStage {
+            duration: Duration::seconds(rec.green + rec.yellow + rec.all_red),
+            protected: Vec::new(),
+            permitted: Vec::new(),
+        };
+        for code in rec.protected.split_whitespace() {
+            stage.protected.push(approaches.movement(code)?);
+        }
+        for code in rec.permitted.split_whitespace() {
+            stage.permitted.push(approaches.movement(code)?);
+        }
+        stages.push(stage);
+    }
+    if stages.is_empty() {
+        bail!("no stages");
+    }
+    Ok(RawSignalProgram {
+        offset: Duration::seconds(records[0].offset),
+        stages,
+    })
+}
+
+/// The ways meeting at one intersection, with the heading of traffic entering and leaving along
+/// each.
+struct Approaches {
+    incoming: Vec<(osm::WayID, Angle)>,
+    outgoing: Vec<(osm::WayID, Angle)>,
+}
+
+impl Approaches {
+    fn new(map: &RawMap, i: IntersectionID) -> Approaches {
+        let mut incoming = Vec::new();
+        let mut outgoing = Vec::new();
+        for r in &map.streets.intersections[&i].roads {
+            let road = &map.streets.roads[r];
+            let way = match road.osm_ids.get(0) {
+                Some(id) => *id,
+                None => {
+                    continue;
+                }
+            };
+            let has_lanes = |dir: Direction| {
+                road.lane_specs_ltr.iter().any(|spec| {
+                    spec.dir == dir && matches!(spec.lt, LaneType::Driving | LaneType::Bus)
+                })
+            };
+            // Traffic moving forwards along the road enters at its end
+            let (towards, away, heading) = if road.dst_i == i {
+                (
+                    Direction::Fwd,
+                    Direction::Back,
+                    road.reference_line.last_line().angle(),
+                )
+            } else {
+                (
+                    Direction::Back,
+                    Direction::Fwd,
+                    road.reference_line.first_line().angle().opposite(),
+                )
+            };
+            if has_lanes(towards) {
+                incoming.push((way, heading));
+            }
+            if has_lanes(away) {
+                outgoing.push((way, heading.opposite()));
+            }
+        }
+        Approaches { incoming, outgoing }
+    }
+
+    /// Resolves a code like "NBL" into (from way, to way)
+    fn movement(&self, code: &str) -> Result<(osm::WayID, osm::WayID)> {
+        if code.len() != 3 || !code.is_ascii() {
+            bail!("weird movement {}", code);
+        }
+        // Note Y inversion, as usual
+        let heading = Angle::degrees(match &code[0..2] {
+            "EB" => 0.0,
+            "SB" => 90.0,
+            "WB" => 180.0,
+            "NB" => 270.0,
+            _ => bail!("weird direction in movement {}", code),
+        });
+        // Counter-clockwise rotation is positive
+        let ideal_turn = match &code[2..] {
+            "T" => 0.0,
+            "L" => 90.0,
+            "R" => -90.0,
+            _ => bail!("weird turn in movement {}", code),
+        };
+
+        let (from, from_angle) = self
+            .incoming
+            .iter()
+            .map(|(way, angle)| {
+                (
+                    *way,
+                    *angle,
+                    heading.simple_shortest_rotation_towards(*angle).abs(),
+                )
+            })
+            .filter(|(_, _, diff)| *diff <= 45.0)
+            .min_by_key(|(_, _, diff)| (diff * 100.0) as usize)
+            .map(|(way, angle, _)| (way, angle))
+            .ok_or_else(|| anyhow!("nothing approaches heading {}", &code[0..2]))?;
+        let to = self
+            .outgoing
+            .iter()
+            .filter(|(way, _)| *way != from)
+            .map(|(way, angle)| {
+                let turn = from_angle.simple_shortest_rotation_towards(*angle);
+                (*way, (turn - ideal_turn).abs())
+            })
+            .filter(|(_, diff)| *diff <= 60.0)
+            .min_by_key(|(_, diff)| (diff * 100.0) as usize)
+            .map(|(way, _)| way)
+            .ok_or_else(|| anyhow!("nowhere to go for {}", code))?;
+        Ok((from, to))
+    }
+}
+
+fn intersection_points(map: &RawMap) -> BTreeMap<IntersectionID, Pt2D> {
+    let mut points = BTreeMap::new();
+    for r in map.streets.roads.values() {
+        points.insert(r.src_i, r.reference_line.first_pt());
+        points.insert(r.dst_i, r.reference_line.last_pt());
+    }
+    points
+}
@@ -0,0 +1,136 @@
+//! Regional OSM tagging sometimes uses values that the usual rules don't understand, or maps
+//! objects that shouldn't be imported. Like osmosis' `--tag-filter`, a JSON file per import can
+//! accept or reject values and rewrite others, before anything else reads the tags. For example:
+//!
+//! ```json
+//! {
+//!   "accept": { "railway": ["rail", "light_rail", "tram"] },
+//!   "reject": { "highway": ["proposed", "abandoned"], "amenity": ["bench"] },
+//!   "rename": { "highway": { "residential_link": "residential" } },
+//!   "unknown_highway": "unclassified"
+//! }
+//! ```
+//!
+//! Renaming happens first, then unknown roads are classified, then values are accepted or
+//! rejected. Filtering out a value only removes that one tag, so a way rejected as a road can still
+//! become a building or an area.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use abstutil::{Tags, Timer};
+use osm2streets::osm;
+use streets_reader::osm_reader::Document;
+
+/// Values of `highway` on ways that importing already understands
+const KNOWN_HIGHWAYS: [&str; 29] = [
+    "motorway",
+    "motorway_link",
+    "trunk",
+    "trunk_link",
+    "primary",
+    "primary_link",
+    "secondary",
+    "secondary_link",
+    "tertiary",
+    "tertiary_link",
+    "unclassified",
+    "residential",
+    "living_street",
+    "service",
+    "road",
+    "busway",
+    "bus_guideway",
+    "pedestrian",
+    "footway",
+    "path",
+    "cycleway",
+    "steps",
+    "track",
+    "bridleway",
+    "corridor",
+    "platform",
+    "construction",
+    "proposed",
+    "abandoned",
+];
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TagFilter {
+    /// Per key, only these values are kept. Other values of the key are removed.
+    #[serde(default)]
+    pub accept: BTreeMap<String, BTreeSet<String>>,
+    /// Per key, these values are removed.
+    #[serde(default)]
+    pub reject: BTreeMap<String, BTreeSet<String>>,
+    /// Per key, replace some values with others.
+    #[serde(default)]
+    pub rename: BTreeMap<String, BTreeMap<String, String>>,
+    /// Import ways with a `highway` value that isn't otherwise understood as this road class,
+    /// remembering the original in `abst:highway`. Without this, they're usually skipped.
+    #[serde(default)]
+    pub unknown_highway: Option<String>,
+}
+
+impl TagFilter {
+    pub fn load(path: &str, timer: &mut Timer) -> Result<TagFilter> {
+        abstio::maybe_read_json(path.to_string(), timer)
+    }
+
+    /// Rewrites the tags of every node, way, and relation in place
+    pub fn apply(&self, doc: &mut Document) {
+        let mut changed = 0;
+        for node in doc.nodes.values_mut() {
+            if self.retag(&mut node.tags) {
+                changed += 1;
+            }
+        }
+        for way in doc.ways.values_mut() {
+            let mut way_changed = self.retag(&mut way.tags);
+            if let Some(ref class) = self.unknown_highway {
+                if let Some(value) = way.tags.get(osm::HIGHWAY).cloned() {
+                    if !KNOWN_HIGHWAYS.contains(&value.as_str()) {
+                        way.tags.insert("abst:highway", value);
+                        way.tags.insert(osm::HIGHWAY, class.clone());
+                        way_changed = true;
+                    }
+                }
+            }
+            if way_changed {
+                changed += 1;
+            }
+        }
+        for rel in doc.relations.values_mut() {
+            if self.retag(&mut rel.tags) {
+                changed += 1;
+            }
+        }
+        info!("The tag filter changed {} OSM objects", changed);
+    }
+
+    /// Applies everything except classifying unknown roads. True if anything changed.
+    fn retag(&self, tags: &mut Tags) -> bool {
+        let mut changed = false;
+        for (key, renames) in &self.rename {
+            if let Some(value) = tags.get(key).and_then(|v| renames.get(v)).cloned() {
+                tags.insert(key.clone(), value);
+                changed = true;
+            }
+        }
+        for (key, values) in &self.accept {
+            if tags.get(key).map(|v| !values.contains(v)).unwrap_or(false) {
+                tags.remove(key);
+                changed = true;
+            }
+        }
+        for (key, values) in &self.reject {
+            if tags.get(key).map(|v| values.contains(v)).unwrap_or(false) {
+                tags.remove(key);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,61 @@
+//! Cities publish zoning or land-use maps as parcel polygons, each with a category. Attach these
+//! to buildings, so the number of residents and workers -- and so the trips generated -- match
+//! official data instead of guesses from OSM tags.
+
+use anyhow::Result;
+
+use abstutil::Timer;
+use geom::{FindClosest, Polygon};
+use osm2streets::osm::OsmID;
+use raw_map::{LandUse, RawMap};
+
+/// Where to find zoning data
+#[derive(Debug)]
+pub struct ZoningInput {
+    /// A GeoJSON file of parcel polygons. Convert shapefiles first, with something like
+    /// `ogr2ogr -f GeoJSON -t_srs EPSG:4326 parcels.geojson parcels.shp`.
+    pub path: String,
+    /// Which property holds the zoning code or land-use description. These are interpreted by
+    /// `LandUse::parse`.
+    pub property: String,
+}
+
+pub fn apply(map: &mut RawMap, input: &ZoningInput, timer: &mut Timer) -> Result<()> {
+    let require_in_bounds = false;
+    let parcels: Vec<(Polygon, LandUse)> = Polygon::from_geojson_bytes(
+        &abstio::slurp_file(&input.path)?,
+        &map.streets.gps_bounds,
+        require_in_bounds,
+    )?
+    .into_iter()
+    .filter_map(|(polygon, props)| {
+        let land_use = LandUse::parse(props.get(&input.property)?)?;
+        Some((polygon, land_use))
+    })
+    .collect();
+
+    let mut closest_bldg: FindClosest<OsmID> = FindClosest::new();
+    for (id, b) in &map.buildings {
+        closest_bldg.add_polygon(*id, &b.polygon);
+    }
+
+    let mut num_matches = 0;
+    timer.start_iter("match buildings to parcels", parcels.len());
+    for (parcel, land_use) in parcels {
+        timer.next();
+        for id in closest_bldg.all_points_inside(&parcel) {
+            let b = map.buildings.get_mut(&id).unwrap();
+            // A building overlapping several parcels belongs to the one containing its center
+            if b.land_use.is_none() && parcel.contains_pt(b.polygon.center()) {
+                b.land_use = Some(land_use);
+                num_matches += 1;
+            }
+        }
+    }
+    info!(
+        "Matched {} of {} buildings to a zoning parcel",
+        num_matches,
+        map.buildings.len()
+    );
+    Ok(())
+}
@@ -9,13 +9,19 @@
 //! it's now 01:01:00.0
 //! > curl http://localhost:1234/data/get-road-thruput
 //! ... huge JSON blob
+//!
+//! To follow the simulation live, connect a WebSocket client to ws://localhost:1234/stream. See
+//! stream.rs for details.
+//!
+//! Every endpoint and the shape of its payloads is described by /api/get-schema; see schema.rs.
+//! Prometheus can scrape /metrics to monitor long runs.
 
 #[macro_use]
 extern crate anyhow;
 #[macro_use]
 extern crate log;
 
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::net::IpAddr;
 use std::sync::RwLock;
 
@@ -27,17 +33,24 @@ use serde::{Deserialize, Serialize};
 use structopt::StructOpt;
 
 use abstio::MapName;
-use abstutil::{serialize_btreemap, Timer};
-use geom::{Distance, Duration, FindClosest, LonLat, Time};
+use abstutil::{deserialize_btreemap, serialize_btreemap, Timer};
+use geom::{Distance, Duration, FindClosest, LonLat, Polygon, Speed, Time};
+use map_model::connectivity::{self, Spot, WalkingOptions};
 use map_model::{
-    CompressedMovementID, ControlTrafficSignal, EditIntersectionControl, IntersectionID, Map,
-    MovementID, PermanentMapEdits, RoadID, TurnID,
+    BuildingID, CompressedMovementID, CongestionCharge, ControlTrafficSignal, EditCmd,
+    EditIntersectionControl, IntersectionID, LaneID, LaneType, Map, MovementID, ParkingPolicy,
+    PathConstraints, PermanentEditCmd, PermanentMapEdits, RoadID, StageType, TurnID,
 };
 use sim::{
-    AgentID, AgentType, DelayCause, PersonID, Sim, SimFlags, SimOptions, TripID, VehicleType,
+    AgentID, AgentType, DelayCause, Incident, PersonID, Sim, SimFlags, SimOptions, TripID,
+    VehicleType,
 };
 use synthpop::{ExternalPerson, Scenario, ScenarioModifier, TripMode};
 
+mod metrics;
+mod schema;
+mod stream;
+
 lazy_static::lazy_static! {
     static ref MAP: RwLock<Map> = RwLock::new(Map::blank());
     static ref SIM: RwLock<Sim> = RwLock::new(Sim::new(&Map::blank(), SimOptions::new("tmp")));
@@ -90,7 +103,11 @@ async fn main() {
             load.scenario = path;
         }
 
-        let (map, sim) = load.setup(&mut Timer::new("setup headless"));
+        let (map, mut sim) = load
+            .setup(&mut Timer::new("setup headless"))
+            .unwrap_or_else(|err| panic!("Can't set up the simulation: {}", err));
+        metrics::update(&sim, &map);
+        stream::publish(&mut sim, &map);
         *MAP.write().unwrap() = map;
         *SIM.write().unwrap() = sim;
     }
@@ -114,27 +131,42 @@ async fn serve_req(req: Request<Body>) -> Result<Response<Body>, hyper::Error> {
             .query_pairs()
             .map(|(k, v)| (k.to_string(), v.to_string()))
             .collect();
+    // Scraped often, and mustn't wait for the sim to finish running
+    if path == "/metrics" {
+        return Ok(Response::builder()
+            .header(hyper::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(metrics::render()))
+            .unwrap());
+    }
+    if path == "/stream" {
+        info!("Handling {}", path);
+        return Ok(stream::connect(req, &params));
+    }
     let body = hyper::body::to_bytes(req).await?.to_vec();
     info!("Handling {}", path);
-    Ok(
-        match handle_command(
-            &path,
-            &params,
-            &body,
-            &mut SIM.write().unwrap(),
-            &mut MAP.write().unwrap(),
-            &mut LOAD.write().unwrap(),
-        ) {
-            Ok(resp) => Response::new(Body::from(resp)),
-            Err(err) => {
-                error!("{}: {}", path, err);
-                Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(Body::from(format!("Bad command {}: {}", path, err)))
-                    .unwrap()
-            }
-        },
-    )
+    let mut sim = SIM.write().unwrap();
+    let mut map = MAP.write().unwrap();
+    let result = handle_command(
+        &path,
+        &params,
+        &body,
+        &mut sim,
+        &mut map,
+        &mut LOAD.write().unwrap(),
+    );
+    // Monitoring and clients following the simulation should see the effects of any command
+    metrics::update(&sim, &map);
+    stream::publish(&mut sim, &map);
+    Ok(match result {
+        Ok(resp) => Response::new(Body::from(resp)),
+        Err(err) => {
+            error!("{}: {}", path, err);
+            Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(format!("Bad command {}: {}", path, err)))
+                .unwrap()
+        }
+    })
 }
 
 fn handle_command(
@@ -154,7 +186,7 @@ fn handle_command(
     match path {
         // Controlling the simulation
         "/sim/reset" => {
-            let (new_map, new_sim) = load.setup(&mut Timer::new("reset sim"));
+            let (new_map, new_sim) = load.setup(&mut Timer::new("reset sim"))?;
             *map = new_map;
             *sim = new_sim;
             Ok("sim reloaded".to_string())
@@ -167,7 +199,7 @@ fn handle_command(
             load.edits = args.edits;
 
             // Also reset
-            let (new_map, new_sim) = load.setup(&mut Timer::new("reset sim"));
+            let (new_map, new_sim) = load.setup(&mut Timer::new("reset sim"))?;
             *map = new_map;
             *sim = new_sim;
 
@@ -179,17 +211,71 @@ fn handle_command(
             *sim = Sim::new(&map, SimOptions::default());
             Ok("map changed, blank simulation".to_string())
         }
+        "/sim/save" => Ok(sim.save()),
+        "/sim/schedule-checkpoint" => {
+            let t = Time::parse(get("t")?)?;
+            if t <= sim.time() {
+                bail!("{} is in the past", t);
+            }
+            sim.schedule_checkpoint(t);
+            Ok(format!("the sim will be saved at {}", t))
+        }
+        "/sim/resume" => {
+            // Without a path, use the latest savestate for the current run
+            let path = match params.get("path") {
+                Some(path) => path.clone(),
+                None => sim
+                    .find_latest_savestate()
+                    .ok_or_else(|| anyhow!("no savestates for the current run"))?,
+            };
+            if !path.starts_with(&abstio::path_player("saves/")) || !abstio::file_exists(&path) {
+                bail!("{} isn't a savestate", path);
+            }
+            let mut flags = SimFlags::for_test("headless");
+            flags.load = path.clone();
+            let (new_map, new_sim, _) = flags.load_synchronously(&mut Timer::new("resume sim"))?;
+            *map = new_map;
+            *sim = new_sim;
+            Ok(format!("resumed from {} at {}", path, sim.time()))
+        }
         "/sim/get-time" => Ok(sim.time().to_string()),
         "/sim/goto-time" => {
             let t = Time::parse(get("t")?)?;
             if t <= sim.time() {
                 bail!("{} is in the past. call /sim/reset first?", t)
             } else {
-                let dt = t - sim.time();
-                sim.timed_step(map, dt, &mut None, &mut Timer::new("goto-time"));
-                Ok(format!("it's now {}", t))
+                let mut timer = Timer::new("goto-time");
+                // Pause regularly to update metrics and for anybody following along. The sim also
+                // stops early whenever an incident starts or ends.
+                while sim.time() < t {
+                    let streaming = stream::is_active();
+                    let real_time_limit = if streaming {
+                        stream::PUBLISH_EVERY
+                    } else {
+                        metrics::UPDATE_EVERY
+                    };
+                    sim.time_limited_step(map, t - sim.time(), real_time_limit, &mut None);
+                    metrics::update(sim, map);
+                    if streaming {
+                        stream::publish(sim, map);
+                    }
+                    if let Some(edits) = sim.edits_for_incidents(map) {
+                        map.must_apply_edits(edits, &mut timer);
+                        map.recalculate_pathfinding_after_edits(&mut timer);
+                        sim.handle_live_edited_traffic_signals(map);
+                        sim.handle_live_edits(map, &mut timer);
+                    }
+                }
+                Ok(format!("it's now {}", sim.time()))
             }
         }
+        "/sim/schedule-incident" => {
+            let incident: Incident = abstutil::from_json(body)?;
+            let description = incident.description.clone();
+            sim.schedule_incident(map, incident)?;
+            Ok(format!("{} scheduled", description))
+        }
+        "/sim/get-incidents" => Ok(abstutil::to_json(sim.get_incidents())),
         "/sim/new-person" => {
             let input: ExternalPerson = abstutil::from_json(body)?;
             for trip in &input.trips {
@@ -235,6 +321,20 @@ fn handle_command(
 
             Ok(format!("{} has been updated", id))
         }
+        "/traffic-signals/green-wave" => {
+            let args: GreenWave = abstutil::from_json(body)?;
+            let signals = map.green_wave(&args.corridor, args.progression_speed)?;
+            let offsets: BTreeMap<IntersectionID, Duration> =
+                signals.iter().map(|ts| (ts.id, ts.offset)).collect();
+
+            let mut edits = map.get_edits().clone();
+            edits.commands.extend(map.green_wave_edits(signals));
+            map.must_apply_edits(edits, &mut Timer::throwaway());
+            map.recalculate_pathfinding_after_edits(&mut Timer::throwaway());
+            sim.handle_live_edited_traffic_signals(map);
+
+            Ok(abstutil::to_json(&offsets))
+        }
         "/traffic-signals/get-delays" => {
             let i = map.get_i(IntersectionID(get("id")?.parse::<usize>()?));
             let t1 = Time::parse(get("t1")?)?;
@@ -329,19 +429,7 @@ fn handle_command(
             Ok(abstutil::to_json(&trips))
         }
         "/data/get-agent-positions" => Ok(abstutil::to_json(&AgentPositions {
-            agents: sim
-                .get_unzoomed_agents(map)
-                .into_iter()
-                .chain(sim.get_unzoomed_transit_riders(map))
-                .map(|a| AgentPosition {
-                    id: a.id,
-                    trip: sim.agent_to_trip(a.id),
-                    person: a.person,
-                    vehicle_type: a.id.to_vehicle_type(),
-                    pos: a.pos.to_gps(map.get_gps_bounds()),
-                    distance_crossed: sim.agent_properties(map, a.id).dist_crossed,
-                })
-                .collect(),
+            agents: agent_positions(sim, map),
         })),
         "/data/get-road-thruput" => Ok(abstutil::to_json(&RoadThroughput {
             counts: sim
@@ -385,6 +473,67 @@ fn handle_command(
                 .collect();
             Ok(abstutil::to_json(&results))
         }
+        // Connectivity
+        "/connectivity/get-scc" => {
+            let constraints = parse_constraints(get("constraints")?)?;
+            let (main, disconnected) = connectivity::find_scc(map, constraints);
+            if params.get("format").map(|x| x.as_str()) == Some("geojson") {
+                let gps_bounds = Some(map.get_gps_bounds());
+                let mut pairs = Vec::new();
+                for l in main.iter().chain(disconnected.iter()) {
+                    let mut props = serde_json::Map::new();
+                    props.insert("id".to_string(), l.0.into());
+                    props.insert("connected".to_string(), main.contains(l).into());
+                    pairs.push((map.get_l(*l).lane_center_pts.to_geojson(gps_bounds), props));
+                }
+                Ok(abstutil::to_json(
+                    &geom::geometries_with_properties_to_geojson(pairs),
+                ))
+            } else {
+                Ok(abstutil::to_json(&StronglyConnectedComponents {
+                    main: main.into_iter().collect(),
+                    disconnected: disconnected.into_iter().collect(),
+                }))
+            }
+        }
+        "/connectivity/costs-from" => {
+            let args: CostsFrom = abstutil::from_json(body)?;
+            let costs = match args.constraints {
+                PathConstraints::Pedestrian => {
+                    let mut opts = WalkingOptions::default();
+                    if let Some(speed) = args.walking_speed {
+                        opts.walking_speed = speed;
+                    }
+                    opts.transit_departure = args.transit_departure;
+                    connectivity::all_walking_costs_from(map, args.starts, args.time_limit, opts)
+                }
+                PathConstraints::Car | PathConstraints::Bike => {
+                    connectivity::all_vehicle_costs_from(
+                        map,
+                        args.starts,
+                        args.time_limit,
+                        args.constraints,
+                    )
+                }
+                x => bail!("costs can't be calculated for {:?}", x),
+            };
+            let costs: BTreeMap<BuildingID, Duration> = costs.into_iter().collect();
+            if params.get("format").map(|x| x.as_str()) == Some("geojson") {
+                let gps_bounds = Some(map.get_gps_bounds());
+                let mut pairs = Vec::new();
+                for (b, cost) in costs {
+                    let mut props = serde_json::Map::new();
+                    props.insert("id".to_string(), b.0.into());
+                    props.insert("cost_seconds".to_string(), cost.inner_seconds().into());
+                    pairs.push((map.get_b(b).polygon.to_geojson(gps_bounds), props));
+                }
+                Ok(abstutil::to_json(
+                    &geom::geometries_with_properties_to_geojson(pairs),
+                ))
+            } else {
+                Ok(abstutil::to_json(&costs))
+            }
+        }
         // Controlling the map
         "/map/get-edits" => {
             let mut edits = map.get_edits().clone();
@@ -392,6 +541,120 @@ fn handle_command(
             edits.compress(map);
             Ok(abstutil::to_json(&edits.to_permanent(map)))
         }
+        "/map/set-congestion-charge" => {
+            // The body is GeoJSON with the cordon's boundary
+            let require_in_bounds = false;
+            let polygon =
+                Polygon::from_geojson_bytes(body, map.get_gps_bounds(), require_in_bounds)?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| anyhow!("no polygon in the body"))?
+                    .0;
+            let off_peak = match params.get("off_peak") {
+                Some(x) => x.parse::<f64>()?,
+                None => 0.0,
+            };
+            let schedule = vec![
+                (Time::START_OF_DAY, off_peak),
+                (Time::parse(get("start")?)?, get("peak")?.parse::<f64>()?),
+                (Time::parse(get("end")?)?, off_peak),
+            ];
+            let charge = CongestionCharge::from_polygon(map, &polygon, schedule);
+            let num_roads = charge.roads.len();
+
+            let mut edits = map.get_edits().clone();
+            edits.commands.push(EditCmd::ChangeCongestionCharge {
+                old: map.get_congestion_charge().cloned(),
+                new: Some(charge),
+            });
+            map.must_apply_edits(edits, &mut Timer::throwaway());
+            map.recalculate_pathfinding_after_edits(&mut Timer::throwaway());
+
+            Ok(format!("Congestion charge covers {} roads", num_roads))
+        }
+        "/map/set-parking-pricing" => {
+            // The body is GeoJSON with the area to price
+            let require_in_bounds = false;
+            let polygon =
+                Polygon::from_geojson_bytes(body, map.get_gps_bounds(), require_in_bounds)?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| anyhow!("no polygon in the body"))?
+                    .0;
+            let policy = ParkingPolicy {
+                hourly_price: get("hourly_price")?.parse::<f64>()?,
+                max_stay: match params.get("max_stay") {
+                    Some(x) => Some(Duration::parse(x)?),
+                    None => None,
+                },
+            };
+
+            let old = map.get_parking_pricing().clone();
+            let mut new = old.clone();
+            for r in map.all_roads() {
+                if polygon.contains_pt(r.center_pts.middle()) {
+                    new.blockfaces.insert(r.id, policy);
+                }
+            }
+            for pl in map.all_parking_lots() {
+                if polygon.contains_pt(pl.polygon.center()) {
+                    new.lots.insert(pl.id, policy);
+                }
+            }
+            let msg = format!(
+                "Now {} blockfaces and {} parking lots are priced",
+                new.blockfaces.len(),
+                new.lots.len()
+            );
+
+            let mut edits = map.get_edits().clone();
+            edits
+                .commands
+                .push(EditCmd::ChangeParkingPricing { old, new });
+            map.must_apply_edits(edits, &mut Timer::throwaway());
+
+            Ok(msg)
+        }
+        "/map/validate-edits" => {
+            let requests: Vec<EditRequest> = abstutil::from_json(body)?;
+            let orig_edits = map.get_edits().clone();
+            let result = try_edits(map, requests);
+            map.must_apply_edits(orig_edits, &mut Timer::throwaway());
+            Ok(abstutil::to_json(&result?))
+        }
+        "/map/apply-edits" => {
+            let requests: Vec<EditRequest> = abstutil::from_json(body)?;
+            let force = params.get("force").map(|x| x == "true").unwrap_or(false);
+            let orig_edits = map.get_edits().clone();
+            let mut report = match try_edits(map, requests) {
+                Ok(report) => report,
+                Err(err) => {
+                    map.must_apply_edits(orig_edits, &mut Timer::throwaway());
+                    return Err(err);
+                }
+            };
+            if !report.newly_disconnected.is_empty() && !force {
+                map.must_apply_edits(orig_edits, &mut Timer::throwaway());
+                bail!(
+                    "these edits disconnect {} lanes from the rest of the map. Pass force=true to \
+                     apply anyway",
+                    report
+                        .newly_disconnected
+                        .values()
+                        .map(|x| x.len())
+                        .sum::<usize>()
+                );
+            }
+
+            let mut timer = Timer::new("apply edits from the headless API");
+            let started = std::time::Instant::now();
+            map.recalculate_pathfinding_after_edits(&mut timer);
+            report.pathfinding_recalculated =
+                Some(Duration::seconds(started.elapsed().as_secs_f64()));
+            sim.handle_live_edited_traffic_signals(map);
+            sim.handle_live_edits(map, &mut timer);
+            Ok(abstutil::to_json(&report))
+        }
         "/map/get-edit-road-command" => {
             let r = RoadID(get("id")?.parse::<usize>()?);
             Ok(abstutil::to_json(
@@ -415,13 +678,14 @@ fn handle_command(
                 None => bail!("No road within {} of {}", threshold, pt),
             }
         }
+        "/api/get-schema" => schema::describe_api(),
         _ => Err(anyhow!("Unknown command")),
     }
 }
 
 // TODO I think specifying the API with protobufs or similar will be a better idea.
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct FinishedTrip {
     id: TripID,
     person: PersonID,
@@ -430,24 +694,30 @@ struct FinishedTrip {
     mode: TripMode,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct Delays {
-    #[serde(serialize_with = "serialize_btreemap")]
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
     per_direction: BTreeMap<MovementID, Vec<Duration>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct Throughput {
-    #[serde(serialize_with = "serialize_btreemap")]
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
     per_direction: BTreeMap<MovementID, usize>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct AgentPositions {
     agents: Vec<AgentPosition>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 struct AgentPosition {
     /// The agent's ID
     id: AgentID,
@@ -473,13 +743,13 @@ struct AgentPosition {
     distance_crossed: Distance,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct RoadThroughput {
     // (road, agent type, hour since midnight, throughput for that one hour period)
     counts: Vec<(RoadID, AgentType, usize, usize)>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct TrafficSignalState {
     current_stage_idx: usize,
     remaining_time: Duration,
@@ -488,15 +758,165 @@ struct TrafficSignalState {
     waiting: Vec<(AgentID, TurnID, Time)>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct BlockedByGraph {
     /// Each entry indicates that some agent has been stuck in one place for some amount of time,
     /// due to being blocked by another agent or because they're waiting at an intersection. Unless
     /// the agent is a bus, then the TripID and PersonID will also be filled out.
-    #[serde(serialize_with = "serialize_btreemap")]
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
     blocked_by: BTreeMap<AgentID, (Duration, DelayCause, Option<TripID>, Option<PersonID>)>,
 }
 
+#[derive(Serialize, Deserialize)]
+struct StronglyConnectedComponents {
+    /// Lanes in the largest component, where everything can reach everything else
+    main: BTreeSet<LaneID>,
+    /// All other lanes usable by the constraints, which can't reach or be reached from the main
+    /// component
+    disconnected: BTreeSet<LaneID>,
+}
+
+#[derive(Deserialize)]
+struct CostsFrom {
+    /// Buildings, borders, or roads to start from. The cost is from the closest one.
+    starts: Vec<Spot>,
+    /// Buildings further away than this aren't included
+    time_limit: Duration,
+    /// Pedestrian, Bike, or Car
+    constraints: PathConstraints,
+    /// When walking, the speed in meters per second. Defaults to about 3mph.
+    #[serde(default)]
+    walking_speed: Option<Speed>,
+    /// When walking, also ride transit departing around this time
+    #[serde(default)]
+    transit_departure: Option<Time>,
+}
+
+/// A change to the map, simpler than the commands in saved edits
+#[derive(Deserialize)]
+enum EditRequest {
+    /// Change one lane's type. Lanes are numbered from the left side of the road.
+    ChangeLaneType {
+        road: RoadID,
+        lane: usize,
+        lane_type: LaneType,
+    },
+    /// Close every lane except sidewalks for construction
+    CloseRoad { road: RoadID },
+    /// In meters per second
+    ChangeSpeedLimit { road: RoadID, speed_limit: Speed },
+    /// Run each stage of a traffic signal for a fixed duration, and optionally change the offset
+    ChangeSignalTiming {
+        intersection: IntersectionID,
+        stage_durations: Vec<Duration>,
+        #[serde(default)]
+        offset: Option<Duration>,
+    },
+    /// Any command in the same format as saved edits, like from `/map/get-edit-road-command`
+    Command(PermanentEditCmd),
+}
+
+impl EditRequest {
+    fn into_cmd(self, map: &Map) -> Result<EditCmd> {
+        match self {
+            EditRequest::ChangeLaneType {
+                road,
+                lane,
+                lane_type,
+            } => {
+                let num_lanes = map
+                    .maybe_get_r(road)
+                    .ok_or_else(|| anyhow!("{} doesn't exist", road))?
+                    .lanes
+                    .len();
+                if lane >= num_lanes {
+                    bail!("{} only has {} lanes", road, num_lanes);
+                }
+                Ok(map.edit_road_cmd(road, |new| {
+                    new.lanes_ltr[lane].lt = lane_type;
+                }))
+            }
+            EditRequest::CloseRoad { road } => {
+                if map.maybe_get_r(road).is_none() {
+                    bail!("{} doesn't exist", road);
+                }
+                Ok(map.edit_road_cmd(road, |new| {
+                    for spec in &mut new.lanes_ltr {
+                        if !spec.lt.is_walkable() {
+                            spec.lt = LaneType::Construction;
+                        }
+                    }
+                }))
+            }
+            EditRequest::ChangeSpeedLimit { road, speed_limit } => {
+                if map.maybe_get_r(road).is_none() {
+                    bail!("{} doesn't exist", road);
+                }
+                if speed_limit <= Speed::ZERO {
+                    bail!("the speed limit must be positive");
+                }
+                Ok(map.edit_road_cmd(road, |new| {
+                    new.speed_limit = speed_limit;
+                }))
+            }
+            EditRequest::ChangeSignalTiming {
+                intersection,
+                stage_durations,
+                offset,
+            } => {
+                let mut ts = map
+                    .maybe_get_traffic_signal(intersection)
+                    .ok_or_else(|| anyhow!("{} isn't a traffic signal", intersection))?
+                    .clone();
+                if stage_durations.len() != ts.stages.len() {
+                    bail!(
+                        "{} has {} stages, but {} durations were given",
+                        intersection,
+                        ts.stages.len(),
+                        stage_durations.len()
+                    );
+                }
+                for (stage, dt) in ts.stages.iter_mut().zip(stage_durations) {
+                    stage.stage_type = StageType::Fixed(dt);
+                }
+                if let Some(offset) = offset {
+                    ts.offset = offset;
+                }
+                ts.validate(map.get_i(intersection))?;
+                Ok(map.edit_intersection_cmd(intersection, |new| {
+                    new.control = EditIntersectionControl::TrafficSignal(ts.export(map));
+                }))
+            }
+            EditRequest::Command(cmd) => cmd.into_cmd(map),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EditReport {
+    num_commands: usize,
+    /// Lanes that can't reach or be reached from the rest of the map anymore, for pedestrians,
+    /// cars, and bikes
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    newly_disconnected: BTreeMap<PathConstraints, BTreeSet<LaneID>>,
+    /// Only set when the edits are applied. How long recalculating pathfinding took.
+    pathfinding_recalculated: Option<Duration>,
+}
+
+#[derive(Deserialize)]
+struct GreenWave {
+    /// Traffic signals, in the order vehicles pass through them
+    corridor: Vec<IntersectionID>,
+    /// In meters per second
+    progression_speed: Speed,
+}
+
 #[derive(Deserialize)]
 struct LoadSim {
     scenario: String,
@@ -510,7 +930,7 @@ struct LoadSim {
 }
 
 impl LoadSim {
-    fn setup(&self, timer: &mut Timer) -> (Map, Sim) {
+    fn setup(&self, timer: &mut Timer) -> Result<(Map, Sim)> {
         let mut scenario: Scenario = abstio::must_read_object(self.scenario.clone(), timer);
 
         let mut map = Map::load_synchronously(scenario.map_name.path(), timer);
@@ -526,12 +946,78 @@ impl LoadSim {
         }
 
         let mut sim = Sim::new(&map, self.opts.clone());
+        sim.load_input_files(&map, &self.opts, timer)?;
         sim.instantiate(&scenario, &map, &mut rng, timer);
 
-        (map, sim)
+        Ok((map, sim))
     }
 }
 
+/// Applies each request on top of the current edits, without recalculating pathfinding. The map
+/// is left with the new edits, even if something fails.
+fn try_edits(map: &mut Map, requests: Vec<EditRequest>) -> Result<EditReport> {
+    let constraints = vec![
+        PathConstraints::Pedestrian,
+        PathConstraints::Car,
+        PathConstraints::Bike,
+    ];
+    let disconnected_before: Vec<HashSet<LaneID>> = constraints
+        .iter()
+        .map(|c| connectivity::find_scc(map, *c).1)
+        .collect();
+
+    let num_commands = requests.len();
+    for (idx, req) in requests.into_iter().enumerate() {
+        // Later requests may edit the same road or intersection, so each one starts from the
+        // previous
+        let cmd = req
+            .into_cmd(map)
+            .map_err(|err| anyhow!("edit #{}: {}", idx + 1, err))?;
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(cmd);
+        map.try_apply_edits(edits, &mut Timer::throwaway());
+    }
+
+    let mut newly_disconnected = BTreeMap::new();
+    for (c, before) in constraints.into_iter().zip(disconnected_before) {
+        let lanes: BTreeSet<LaneID> = connectivity::find_scc(map, c)
+            .1
+            .into_iter()
+            .filter(|l| !before.contains(l))
+            .collect();
+        if !lanes.is_empty() {
+            newly_disconnected.insert(c, lanes);
+        }
+    }
+    Ok(EditReport {
+        num_commands,
+        newly_disconnected,
+        pathfinding_recalculated: None,
+    })
+}
+
+fn parse_constraints(x: &str) -> Result<PathConstraints> {
+    PathConstraints::all()
+        .into_iter()
+        .find(|c| format!("{:?}", c) == x)
+        .ok_or_else(|| anyhow!("unknown constraints {}", x))
+}
+
+fn agent_positions(sim: &Sim, map: &Map) -> Vec<AgentPosition> {
+    sim.get_unzoomed_agents(map)
+        .into_iter()
+        .chain(sim.get_unzoomed_transit_riders(map))
+        .map(|a| AgentPosition {
+            id: a.id,
+            trip: sim.agent_to_trip(a.id),
+            person: a.person,
+            vehicle_type: a.id.to_vehicle_type(),
+            pos: a.pos.to_gps(map.get_gps_bounds()),
+            distance_crossed: sim.agent_properties(map, a.id).dist_crossed,
+        })
+        .collect()
+}
+
 fn export_geometry(map: &Map, i: IntersectionID) -> geojson::GeoJson {
     let mut pairs = Vec::new();
 
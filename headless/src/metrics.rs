@@ -0,0 +1,193 @@
+//! Serves `/metrics` in the Prometheus text format, so operators can monitor many headless
+//! simulations with standard tooling. The simulation is locked while it runs, so `/metrics` never
+//! looks at it directly. Instead, a snapshot is updated after every command and regularly while
+//! `/sim/goto-time` runs.
+
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use geom::{Duration, Time};
+use map_model::Map;
+use sim::{AgentType, Sim};
+
+/// While the simulation runs, the snapshot is updated this often in real time
+pub const UPDATE_EVERY: Duration = Duration::const_seconds(1.0);
+
+lazy_static::lazy_static! {
+    static ref SNAPSHOT: Mutex<Option<Snapshot>> = Mutex::new(None);
+}
+
+struct Snapshot {
+    updated_at: Instant,
+    map: String,
+    edits: String,
+    sim_time: Time,
+    active_agents: Vec<(AgentType, usize)>,
+    finished_trips: usize,
+    unfinished_trips: usize,
+    steps: usize,
+    events: usize,
+    /// Since the previous snapshot
+    events_per_second: f64,
+}
+
+pub fn update(sim: &Sim, map: &Map) {
+    let now = Instant::now();
+    let mut snapshot = SNAPSHOT.lock().unwrap();
+    let events_per_second = match *snapshot {
+        // If the sim was reset, the count started over
+        Some(ref prev) if sim.num_events() >= prev.events && now > prev.updated_at => {
+            ((sim.num_events() - prev.events) as f64) / (now - prev.updated_at).as_secs_f64()
+        }
+        _ => 0.0,
+    };
+    let agents = sim.num_agents();
+    let (finished_trips, unfinished_trips) = sim.num_trips();
+    *snapshot = Some(Snapshot {
+        updated_at: now,
+        map: map.get_name().as_filename(),
+        edits: map.get_edits().edits_name.clone(),
+        sim_time: sim.time(),
+        active_agents: AgentType::all()
+            .into_iter()
+            .map(|a| (a, agents.get(a)))
+            .collect(),
+        finished_trips,
+        unfinished_trips,
+        steps: sim.step_count(),
+        events: sim.num_events(),
+        events_per_second,
+    });
+}
+
+pub fn render() -> String {
+    let mut out = String::new();
+    // Writing to a String can't fail
+    render_all(&mut out).unwrap();
+    out
+}
+
+fn render_all(out: &mut String) -> std::fmt::Result {
+    if let Some(ref snapshot) = *SNAPSHOT.lock().unwrap() {
+        snapshot.render(out)?;
+    }
+    if let Some(bytes) = resident_memory_bytes() {
+        metric(
+            out,
+            "process_resident_memory_bytes",
+            "gauge",
+            "Resident memory size",
+        )?;
+        writeln!(out, "process_resident_memory_bytes {}", bytes)?;
+    }
+    Ok(())
+}
+
+impl Snapshot {
+    fn render(&self, out: &mut String) -> std::fmt::Result {
+        metric(
+            out,
+            "abst_info",
+            "gauge",
+            "The map and edits being simulated",
+        )?;
+        writeln!(
+            out,
+            "abst_info{{map=\"{}\",edits=\"{}\"}} 1",
+            escape(&self.map),
+            escape(&self.edits)
+        )?;
+
+        metric(
+            out,
+            "abst_sim_time_seconds",
+            "gauge",
+            "Simulated time since midnight",
+        )?;
+        writeln!(
+            out,
+            "abst_sim_time_seconds {}",
+            (self.sim_time - Time::START_OF_DAY).inner_seconds()
+        )?;
+
+        metric(
+            out,
+            "abst_active_agents",
+            "gauge",
+            "Agents currently in the simulation",
+        )?;
+        for (agent_type, count) in &self.active_agents {
+            writeln!(
+                out,
+                "abst_active_agents{{type=\"{:?}\"}} {}",
+                agent_type, count
+            )?;
+        }
+
+        metric(
+            out,
+            "abst_trips_finished_total",
+            "counter",
+            "Trips finished or cancelled",
+        )?;
+        writeln!(out, "abst_trips_finished_total {}", self.finished_trips)?;
+        metric(
+            out,
+            "abst_trips_unfinished",
+            "gauge",
+            "Trips not finished yet, including ones that haven't started",
+        )?;
+        writeln!(out, "abst_trips_unfinished {}", self.unfinished_trips)?;
+
+        metric(
+            out,
+            "abst_sim_steps_total",
+            "counter",
+            "Times the simulation advanced",
+        )?;
+        writeln!(out, "abst_sim_steps_total {}", self.steps)?;
+        metric(out, "abst_events_total", "counter", "Simulation events")?;
+        writeln!(out, "abst_events_total {}", self.events)?;
+        metric(
+            out,
+            "abst_events_per_second",
+            "gauge",
+            "Simulation events per real second, between the last two updates",
+        )?;
+        writeln!(out, "abst_events_per_second {}", self.events_per_second)?;
+
+        metric(
+            out,
+            "abst_metrics_age_seconds",
+            "gauge",
+            "How long ago these metrics were updated",
+        )?;
+        writeln!(
+            out,
+            "abst_metrics_age_seconds {}",
+            self.updated_at.elapsed().as_secs_f64()
+        )
+    }
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str) -> std::fmt::Result {
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} {}", name, kind)
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Only supported on Linux
+fn resident_memory_bytes() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    // Like "VmRSS:     12345 kB"
+    let kb = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+    Some(kb * 1024)
+}
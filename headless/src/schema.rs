@@ -0,0 +1,289 @@
+//! A machine-readable description of the API, so client libraries in other languages can be
+//! generated instead of guessing the shape of each response. `/api/get-schema` returns JSON with:
+//!
+//! - `endpoints`: the query parameters, request body, and response of every endpoint
+//! - `types`: every named type used by those payloads, in the format of
+//!   [serde-reflection](https://github.com/zefchain/serde-reflection). serde-generate can turn
+//!   this into Python, TypeScript, and other languages.
+//!
+//! The types are traced from the same serde definitions the server uses, so they can't drift from
+//! the real payloads. The list of endpoints has to be kept in sync with `handle_command` by hand.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_reflection::{Format, Registry, Tracer, TracerConfig};
+
+use geom::Duration;
+use map_model::connectivity::Spot;
+use map_model::{
+    ControlTrafficSignal, Direction, IntersectionID, LaneType, PathConstraints, PermanentEditCmd,
+    PermanentMapEdits, StageType, Traversable,
+};
+use sim::{
+    AgentID, AgentType, AlertLocation, DelayCause, Event, Incident, TripID, TripPhaseType,
+    VehicleType,
+};
+use synthpop::{ExternalPerson, ExternalTripEndpoint, ScenarioModifier, TripMode};
+
+use crate::stream::StreamMessage;
+use crate::{
+    AgentPositions, BlockedByGraph, CostsFrom, Delays, EditReport, EditRequest, FinishedTrip,
+    GreenWave, LoadSim, RoadThroughput, StronglyConnectedComponents, Throughput,
+    TrafficSignalState,
+};
+
+#[derive(Serialize)]
+struct ApiSchema {
+    endpoints: BTreeMap<&'static str, Endpoint>,
+    types: Registry,
+}
+
+#[derive(Serialize)]
+struct Endpoint {
+    /// Optional parameters end with a "?"
+    params: Vec<&'static str>,
+    /// If there's a body, the request should be a POST
+    body: Option<Payload>,
+    response: Payload,
+}
+
+#[derive(Serialize)]
+enum Payload {
+    /// Usually a message describing what happened
+    Text,
+    GeoJson,
+    Json(Format),
+    /// The connection is upgraded to a WebSocket, sending JSON messages
+    WebSocket(Format),
+}
+
+pub fn describe_api() -> Result<String> {
+    let mut t = Tracer::new(TracerConfig::default());
+    let mut endpoints: BTreeMap<&'static str, Endpoint> = BTreeMap::new();
+    let mut add =
+        |path: &'static str, params: &[&'static str], body: Option<Payload>, response: Payload| {
+            endpoints.insert(
+                path,
+                Endpoint {
+                    params: params.to_vec(),
+                    body,
+                    response,
+                },
+            );
+        };
+
+    // Controlling the simulation
+    add("/sim/reset", &[], None, Payload::Text);
+    add(
+        "/sim/load",
+        &[],
+        Some(json::<LoadSim>(&mut t)?),
+        Payload::Text,
+    );
+    add("/sim/load-blank", &["map"], None, Payload::Text);
+    add("/sim/save", &[], None, Payload::Text);
+    add("/sim/schedule-checkpoint", &["t"], None, Payload::Text);
+    add("/sim/resume", &["path?"], None, Payload::Text);
+    add("/sim/get-time", &[], None, Payload::Text);
+    add("/sim/goto-time", &["t"], None, Payload::Text);
+    add(
+        "/sim/schedule-incident",
+        &[],
+        Some(json::<Incident>(&mut t)?),
+        Payload::Text,
+    );
+    add(
+        "/sim/get-incidents",
+        &[],
+        None,
+        json::<Vec<Incident>>(&mut t)?,
+    );
+    add(
+        "/sim/new-person",
+        &[],
+        Some(json::<ExternalPerson>(&mut t)?),
+        Payload::Text,
+    );
+    add(
+        "/stream",
+        &["every_ms?"],
+        None,
+        Payload::WebSocket(trace::<StreamMessage<'static>>(&mut t)?),
+    );
+
+    // Traffic signals
+    add(
+        "/traffic-signals/get",
+        &["id"],
+        None,
+        json::<ControlTrafficSignal>(&mut t)?,
+    );
+    add(
+        "/traffic-signals/set",
+        &[],
+        Some(json::<ControlTrafficSignal>(&mut t)?),
+        Payload::Text,
+    );
+    add(
+        "/traffic-signals/green-wave",
+        &[],
+        Some(json::<GreenWave>(&mut t)?),
+        json::<BTreeMap<IntersectionID, Duration>>(&mut t)?,
+    );
+    add(
+        "/traffic-signals/get-delays",
+        &["id", "t1", "t2"],
+        None,
+        json::<Delays>(&mut t)?,
+    );
+    add(
+        "/traffic-signals/get-cumulative-thruput",
+        &["id"],
+        None,
+        json::<Throughput>(&mut t)?,
+    );
+    add(
+        "/traffic-signals/get-all-current-state",
+        &[],
+        None,
+        json::<BTreeMap<IntersectionID, TrafficSignalState>>(&mut t)?,
+    );
+
+    // Querying data
+    add(
+        "/data/get-finished-trips",
+        &[],
+        None,
+        json::<Vec<FinishedTrip>>(&mut t)?,
+    );
+    add(
+        "/data/get-agent-positions",
+        &[],
+        None,
+        json::<AgentPositions>(&mut t)?,
+    );
+    add(
+        "/data/get-road-thruput",
+        &[],
+        None,
+        json::<RoadThroughput>(&mut t)?,
+    );
+    add(
+        "/data/get-blocked-by-graph",
+        &[],
+        None,
+        json::<BlockedByGraph>(&mut t)?,
+    );
+    // The number of seconds
+    add("/data/trip-time-lower-bound", &["id"], None, Payload::Text);
+    add(
+        "/data/all-trip-time-lower-bounds",
+        &[],
+        None,
+        json::<BTreeMap<TripID, Duration>>(&mut t)?,
+    );
+
+    // Connectivity. With format=geojson, these return GeoJSON instead.
+    add(
+        "/connectivity/get-scc",
+        &["constraints", "format?"],
+        None,
+        json::<StronglyConnectedComponents>(&mut t)?,
+    );
+    add(
+        "/connectivity/costs-from",
+        &["format?"],
+        Some(json::<CostsFrom>(&mut t)?),
+        json::<BTreeMap<map_model::BuildingID, Duration>>(&mut t)?,
+    );
+
+    // Controlling the map
+    add(
+        "/map/get-edits",
+        &[],
+        None,
+        json::<PermanentMapEdits>(&mut t)?,
+    );
+    add(
+        "/map/set-congestion-charge",
+        &["start", "end", "peak", "off_peak?"],
+        Some(Payload::GeoJson),
+        Payload::Text,
+    );
+    add(
+        "/map/set-parking-pricing",
+        &["hourly_price", "max_stay?"],
+        Some(Payload::GeoJson),
+        Payload::Text,
+    );
+    add(
+        "/map/validate-edits",
+        &[],
+        Some(json::<Vec<EditRequest>>(&mut t)?),
+        json::<EditReport>(&mut t)?,
+    );
+    add(
+        "/map/apply-edits",
+        &["force?"],
+        Some(json::<Vec<EditRequest>>(&mut t)?),
+        json::<EditReport>(&mut t)?,
+    );
+    add(
+        "/map/get-edit-road-command",
+        &["id"],
+        None,
+        json::<PermanentEditCmd>(&mut t)?,
+    );
+    add(
+        "/map/get-intersection-geometry",
+        &["id"],
+        None,
+        Payload::GeoJson,
+    );
+    add("/map/get-all-geometry", &[], None, Payload::GeoJson);
+    add(
+        "/map/get-nearest-road",
+        &["lon", "lat", "threshold_meters"],
+        None,
+        Payload::Text,
+    );
+
+    add("/api/get-schema", &[], None, Payload::Text);
+    // In the Prometheus text format
+    add("/metrics", &[], None, Payload::Text);
+
+    // Tracing a type only finds the first variant of enums nested inside it, so trace all of
+    // those directly
+    trace::<AgentID>(&mut t)?;
+    trace::<AgentType>(&mut t)?;
+    trace::<AlertLocation>(&mut t)?;
+    trace::<DelayCause>(&mut t)?;
+    trace::<Direction>(&mut t)?;
+    trace::<Event>(&mut t)?;
+    trace::<ExternalTripEndpoint>(&mut t)?;
+    trace::<LaneType>(&mut t)?;
+    trace::<PathConstraints>(&mut t)?;
+    trace::<ScenarioModifier>(&mut t)?;
+    trace::<Spot>(&mut t)?;
+    trace::<StageType>(&mut t)?;
+    trace::<Traversable>(&mut t)?;
+    trace::<TripMode>(&mut t)?;
+    trace::<TripPhaseType>(&mut t)?;
+    trace::<VehicleType>(&mut t)?;
+
+    Ok(abstutil::to_json(&ApiSchema {
+        endpoints,
+        types: t.registry()?,
+    }))
+}
+
+fn trace<T: DeserializeOwned>(tracer: &mut Tracer) -> Result<Format> {
+    Ok(tracer.trace_simple_type::<T>()?.0)
+}
+
+fn json<T: DeserializeOwned>(tracer: &mut Tracer) -> Result<Payload> {
+    Ok(Payload::Json(trace::<T>(tracer)?))
+}
@@ -0,0 +1,241 @@
+//! Streams the simulation's state over a WebSocket, so external dashboards or visualizations can
+//! follow a running simulation. Connect to `/stream?every_ms=500` with any WebSocket client. While
+//! `/sim/goto-time` runs, the simulation pauses regularly to publish its state. Each client
+//! receives a JSON message at most as often as it asked for, with agent positions, aggregate
+//! metrics, and every event since its previous message.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use futures::{SinkExt, StreamExt};
+use hyper::header::{HeaderValue, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE};
+use hyper::upgrade::Upgraded;
+use hyper::{Body, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::{Message, Role};
+use tokio_tungstenite::WebSocketStream;
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Duration, Time};
+use map_model::Map;
+use sim::{AgentType, Event, Sim};
+
+use crate::AgentPosition;
+
+/// While clients are connected, the simulation publishes its state this often in real time.
+/// Clients asking for more frequent messages get them this often instead.
+pub const PUBLISH_EVERY: Duration = Duration::const_seconds(0.1);
+
+lazy_static::lazy_static! {
+    static ref FRAMES: broadcast::Sender<Arc<Frame>> = broadcast::channel(64).0;
+    /// The simulation is locked while it runs, so clients connecting then start from the last frame
+    /// published
+    static ref LATEST: Mutex<Option<Arc<Frame>>> = Mutex::new(None);
+}
+
+/// The simulation's state at one moment
+struct Frame {
+    time: Time,
+    agents: Vec<AgentPosition>,
+    /// Everything that happened since the previous frame
+    events: Vec<(Time, Event)>,
+    metrics: Metrics,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Metrics {
+    /// How many agents of each type are currently moving
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    active_agents: BTreeMap<AgentType, usize>,
+    finished_trips: usize,
+    unfinished_trips: usize,
+}
+
+/// What each client receives. Frames are merged when a client asks for messages less often than
+/// they're published.
+#[derive(Serialize, Deserialize)]
+pub struct StreamMessage<'a> {
+    time: Time,
+    agents: Cow<'a, [AgentPosition]>,
+    events: Cow<'a, [(Time, Event)]>,
+    metrics: Cow<'a, Metrics>,
+}
+
+impl Frame {
+    fn new(sim: &Sim, map: &Map, events: Vec<(Time, Event)>) -> Frame {
+        let (finished_trips, unfinished_trips) = sim.num_trips();
+        Frame {
+            time: sim.time(),
+            agents: crate::agent_positions(sim, map),
+            events,
+            metrics: Metrics {
+                active_agents: sim.num_agents().consume(),
+                finished_trips,
+                unfinished_trips,
+            },
+        }
+    }
+}
+
+/// True if any client is following the simulation
+pub fn is_active() -> bool {
+    FRAMES.receiver_count() > 0
+}
+
+/// Sends the current state of the simulation to every client, and remembers it for clients
+/// connecting later. Events are only recorded while somebody's connected, starting from the first
+/// publish after they connect.
+///
+/// Frames are expensive to build on large maps, so with nobody connected, this does nothing once
+/// there's some frame to start from. Clients connecting while the simulation is idle get a fresh
+/// one from `connect`, and if it's running, it publishes again shortly.
+pub fn publish(sim: &mut Sim, map: &Map) {
+    let active = is_active();
+    sim.stream_events(active);
+    if !active {
+        let mut latest = LATEST.lock().unwrap();
+        if latest.is_none() {
+            *latest = Some(Arc::new(Frame::new(sim, map, Vec::new())));
+        }
+        return;
+    }
+    let events = sim
+        .take_streamed_events()
+        .into_iter()
+        // Paths are huge, and only used internally for parking replanning
+        .filter(|(_, ev)| !matches!(ev, Event::PathAmended(_)))
+        .collect();
+    let frame = Arc::new(Frame::new(sim, map, events));
+    *LATEST.lock().unwrap() = Some(frame.clone());
+    // This only fails if every client disconnected since is_active
+    let _ = FRAMES.send(frame);
+}
+
+/// Upgrades a request to a WebSocket connection, then streams to it until the client leaves.
+/// This doesn't wait for the simulation, so clients can connect while it runs.
+pub fn connect(req: Request<Body>, params: &HashMap<String, String>) -> Response<Body> {
+    let every_ms = match params.get("every_ms").map(|x| x.parse::<u64>()) {
+        Some(Ok(x)) => x,
+        Some(Err(err)) => {
+            return bad_request(format!("bad every_ms: {}", err));
+        }
+        None => 1000,
+    };
+    let every = std::time::Duration::from_millis(every_ms).max(std::time::Duration::from_secs_f64(
+        PUBLISH_EVERY.inner_seconds(),
+    ));
+    let accept = match req.headers().get(SEC_WEBSOCKET_KEY) {
+        Some(key) => derive_accept_key(key.as_bytes()),
+        None => {
+            return bad_request("/stream needs a WebSocket connection".to_string());
+        }
+    };
+
+    // Subscribe before responding, so nothing published in between is missed
+    let frames = FRAMES.subscribe();
+    // Don't wait if the simulation is busy
+    let initial = match (crate::SIM.try_read(), crate::MAP.try_read()) {
+        (Ok(sim), Ok(map)) => Arc::new(Frame::new(&sim, &map, Vec::new())),
+        _ => match LATEST.lock().unwrap().clone() {
+            Some(frame) => frame,
+            None => {
+                return bad_request("the simulation isn't set up yet".to_string());
+            }
+        },
+    };
+    tokio::spawn(async move {
+        match hyper::upgrade::on(req).await {
+            Ok(upgraded) => {
+                info!("A client started following the simulation");
+                stream(upgraded, frames, initial, every).await;
+                info!("A client stopped following the simulation");
+            }
+            Err(err) => {
+                error!("Couldn't upgrade /stream to a WebSocket: {}", err);
+            }
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(CONNECTION, HeaderValue::from_static("upgrade"))
+        .header(UPGRADE, HeaderValue::from_static("websocket"))
+        .header(SEC_WEBSOCKET_ACCEPT, accept)
+        .body(Body::empty())
+        .unwrap()
+}
+
+async fn stream(
+    upgraded: Upgraded,
+    mut frames: broadcast::Receiver<Arc<Frame>>,
+    initial: Arc<Frame>,
+    every: std::time::Duration,
+) {
+    let mut ws = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+    let mut latest = initial;
+    // Events from frames not sent yet. The initial frame's events happened before connecting.
+    let mut events: Vec<(Time, Event)> = Vec::new();
+    let mut unsent = false;
+    if send(&mut ws, &latest, &events).await.is_err() {
+        return;
+    }
+    let mut next_send = Instant::now() + every;
+
+    loop {
+        tokio::select! {
+            msg = ws.next() => match msg {
+                // Clients don't need to send anything, and tungstenite answers pings itself
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            frame = frames.recv() => match frame {
+                Ok(frame) => {
+                    events.extend(frame.events.iter().cloned());
+                    latest = frame;
+                    unsent = true;
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("A client following the simulation fell behind, and missed {} frames", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            // Send the latest state once it's time, even if nothing new is published for a while
+            _ = tokio::time::sleep_until(next_send), if unsent => {
+                if send(&mut ws, &latest, &events).await.is_err() {
+                    break;
+                }
+                events.clear();
+                unsent = false;
+                next_send = Instant::now() + every;
+            }
+        }
+    }
+}
+
+async fn send(
+    ws: &mut WebSocketStream<Upgraded>,
+    frame: &Frame,
+    events: &[(Time, Event)],
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    let msg = StreamMessage {
+        time: frame.time,
+        agents: Cow::Borrowed(&frame.agents),
+        events: Cow::Borrowed(events),
+        metrics: Cow::Borrowed(&frame.metrics),
+    };
+    ws.send(Message::Text(abstutil::to_json_terse(&msg))).await
+}
+
+fn bad_request(msg: String) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .body(Body::from(msg))
+        .unwrap()
+}
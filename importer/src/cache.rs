@@ -0,0 +1,81 @@
+//! Re-running the importer after tweaking one option shouldn't redo every expensive stage. Each
+//! cached stage records a hash of everything that went into producing its output in a sidecar
+//! `.inputs_hash` file. A stage only reruns if its output is missing or the hash changed.
+
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::Result;
+
+use abstutil::{fnv1a, FNV_OFFSET};
+
+/// Describes all of the inputs to one stage of the import. The hash is saved, so it has to be the
+/// same for every build of the importer.
+pub struct StageInputs {
+    hash: u64,
+}
+
+impl Default for StageInputs {
+    fn default() -> StageInputs {
+        StageInputs::new()
+    }
+}
+
+impl StageInputs {
+    pub fn new() -> StageInputs {
+        StageInputs { hash: FNV_OFFSET }
+    }
+
+    /// Any setting that affects the stage, like a URL or a description of the options used.
+    pub fn value<S: AsRef<str>>(mut self, value: S) -> StageInputs {
+        self.hash = fnv1a(self.hash, value.as_ref().as_bytes());
+        // Separate values, so ("ab", "c") and ("a", "bc") differ
+        self.hash = fnv1a(self.hash, &[0]);
+        self
+    }
+
+    /// The contents of an input file. A missing file hashes differently than an empty one.
+    pub fn file<S: AsRef<str>>(mut self, path: S) -> Result<StageInputs> {
+        let path = path.as_ref();
+        self.hash = fnv1a(self.hash, path.as_bytes());
+        if !Path::new(path).exists() {
+            self.hash = fnv1a(self.hash, &[0]);
+            return Ok(self);
+        }
+        self.hash = fnv1a(self.hash, &[1]);
+        let mut file = fs_err::File::open(path)?;
+        let mut buffer = vec![0; 1024 * 1024];
+        loop {
+            let n = file.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            self.hash = fnv1a(self.hash, &buffer[..n]);
+        }
+        Ok(self)
+    }
+
+    fn finish(&self) -> String {
+        format!("{:016x}", self.hash)
+    }
+
+    /// Is the output of this stage present and produced from exactly these inputs?
+    pub fn is_fresh(&self, output: &str) -> bool {
+        if !Path::new(output).exists() {
+            return false;
+        }
+        match fs_err::read_to_string(sidecar_path(output)) {
+            Ok(previous) => previous.trim() == self.finish(),
+            Err(_) => false,
+        }
+    }
+
+    /// Call after successfully producing the output of this stage.
+    pub fn record(&self, output: &str) {
+        fs_err::write(sidecar_path(output), self.finish()).unwrap();
+    }
+}
+
+fn sidecar_path(output: &str) -> String {
+    format!("{}.inputs_hash", output.trim_end_matches('/'))
+}
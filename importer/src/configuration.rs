@@ -7,6 +7,14 @@ pub struct ImporterConfiguration {
     pub unzip: String,
     pub gunzip: String,
     pub gunzip_args: String,
+    /// Rerun every stage of the import, even if its inputs haven't changed. Not read from the
+    /// config file; set from the command line.
+    #[serde(skip)]
+    pub ignore_cache: bool,
+    /// Overrides how on-street parking is guessed for every map. Not read from the config file;
+    /// set from the command line.
+    #[serde(skip)]
+    pub infer_parking: Option<convert_osm::ParkingInference>,
 }
 
 impl Default for ImporterConfiguration {
@@ -16,6 +24,8 @@ impl Default for ImporterConfiguration {
             unzip: String::from("unzip"),
             gunzip: String::from("gunzip"),
             gunzip_args: String::from(""),
+            ignore_cache: false,
+            infer_parking: None,
         }
     }
 }
@@ -16,15 +16,19 @@ use abstutil::Timer;
 use map_model::RawToMapOptions;
 
 pub use self::configuration::ImporterConfiguration;
-pub use self::pick_geofabrik::pick_geofabrik;
-pub use utils::osmium;
+pub use self::pick_geofabrik::{pick_bbbike, pick_geofabrik};
+pub use self::population_raster::PopulationRaster;
+pub use utils::{download_dem_tiles, osmium};
 
 mod berlin;
+mod cache;
 mod configuration;
 mod map_config;
 mod pick_geofabrik;
+mod population_raster;
 mod seattle;
 mod soundcast;
+pub mod sumo;
 mod uk;
 mod utils;
 
@@ -51,6 +55,7 @@ pub async fn oneshot(
     clip: Option<String>,
     options: convert_osm::Options,
     create_uk_travel_demand_model: bool,
+    population_raster: Option<PopulationRaster>,
     opts: RawToMapOptions,
 ) {
     let mut timer = abstutil::Timer::new("oneshot");
@@ -62,10 +67,11 @@ pub async fn oneshot(
         clip,
         options,
         &mut timer,
-    );
+    )
+    .unwrap();
     // Often helpful to save intermediate representation in case user wants to load into map_editor
     raw.save();
-    let map = map_model::Map::create_from_raw(raw, opts, &mut timer);
+    let mut map = map_model::Map::create_from_raw(raw, opts, &mut timer);
     timer.start("save map");
     map.save();
     timer.stop("save map");
@@ -77,6 +83,11 @@ pub async fn oneshot(
             .unwrap();
         timer.stop("generating UK travel demand model");
     }
+    if let Some(input) = population_raster {
+        timer.start("generating scenario from population raster");
+        population_raster::generate_scenario(&map, &input, &mut timer).unwrap();
+        timer.stop("generating scenario from population raster");
+    }
 
     println!("{} has been created", map.get_name().path());
 }
@@ -104,6 +115,16 @@ pub struct Job {
     #[structopt()]
     pub only_map: Option<String>,
 
+    /// Rerun every stage, even if its inputs haven't changed since the last import. Use this
+    /// after changing the importer code itself.
+    #[structopt(long)]
+    pub ignore_cache: bool,
+
+    /// How to guess on-street parking for roads without parking tags in OSM: never,
+    /// conservative, or aggressive. If not specified, never.
+    #[structopt(long)]
+    pub infer_parking: Option<convert_osm::ParkingInference>,
+
     #[structopt(flatten)]
     pub opts: RawToMapOptions,
 }
@@ -117,6 +138,8 @@ impl Job {
             scenario: false,
             city_overview: false,
             only_map: None,
+            ignore_cache: false,
+            infer_parking: None,
             opts: RawToMapOptions::default(),
         };
         // Only some maps run extra tasks
@@ -157,6 +180,12 @@ impl Job {
         if self.city_overview {
             flags.push("--city-overview".to_string());
         }
+        if self.ignore_cache {
+            flags.push("--ignore-cache".to_string());
+        }
+        if let Some(ref x) = self.infer_parking {
+            flags.push(format!("--infer-parking={}", x));
+        }
         if let Some(ref name) = self.only_map {
             flags.push(name.clone());
         }
@@ -171,7 +200,11 @@ impl Job {
             std::process::exit(1);
         }
 
-        let config = ImporterConfiguration::load();
+        let mut config = ImporterConfiguration::load();
+        config.ignore_cache = self.ignore_cache;
+        if self.infer_parking.is_some() {
+            config.infer_parking = self.infer_parking;
+        }
 
         timer.start(format!("import {}", self.city.describe()));
         let names = if let Some(n) = self.only_map {
@@ -21,7 +21,13 @@ pub fn config_for_map(name: &MapName) -> convert_osm::Options {
             } else {
                 Distance::meters(8.0)
             },
-            turn_on_red: name.city.country == "us" && name.city.city != "nyc",
+            // Tagged intersections and roads can override this
+            turn_on_red: match name.city.country.as_ref() {
+                "us" => name.city.city != "nyc",
+                // Except on the island of Montreal
+                "ca" => name.city.city != "montreal",
+                _ => false,
+            },
             include_railroads: match name.city.city.as_ref() {
                 "phoenix" | "seattle" | "tucson" => false,
                 _ => {
@@ -35,12 +41,20 @@ pub fn config_for_map(name: &MapName) -> convert_osm::Options {
             },
         },
         filter_crosswalks: false,
+        include_trails: false,
+        zoning: None,
+        signal_timing: None,
+        // Regional tagging quirks can be handled without code changes
+        tag_filter: Some(name.city.input_path("tag_filter.json"))
+            .filter(|path| abstio::file_exists(path)),
         onstreet_parking: match name.city.city.as_ref() {
             "seattle" => {
                 convert_osm::OnstreetParking::Blockface(name.city.input_path("blockface.bin"))
             }
             _ => convert_osm::OnstreetParking::JustOSM,
         },
+        // Guessing changes existing maps, so only do it when asked with --infer-parking
+        infer_onstreet_parking: convert_osm::ParkingInference::Never,
         public_offstreet_parking: if name.city == CityName::seattle() {
             convert_osm::PublicOffstreetParking::Gis(name.city.input_path("offstreet_parking.bin"))
         } else {
@@ -77,7 +91,7 @@ pub fn config_for_map(name: &MapName) -> convert_osm::Options {
         } else {
             None
         },
-        // We only have a few elevation sources working
+        // We only have a few high-resolution elevation sources working
         elevation_geotiff: if name.city == CityName::new("us", "seattle") {
             Some("data/input/shared/elevation/king_county_2016_lidar.tif".to_string())
         } else if name.city.country == "gb" {
@@ -87,5 +101,7 @@ pub fn config_for_map(name: &MapName) -> convert_osm::Options {
         } else {
             None
         },
+        // Everywhere else falls back to global 30m tiles
+        elevation_dem_tiles: Some(abstio::path_shared_input("elevation/copernicus/")),
     }
 }
@@ -1,7 +1,7 @@
 use std::convert::TryInto;
 
 use anyhow::{bail, Result};
-use geo::{Area, Contains};
+use geo::{Area, BoundingRect, Contains};
 use geojson::GeoJson;
 
 use abstutil::Timer;
@@ -34,6 +34,92 @@ pub async fn pick_geofabrik(input: String) -> Result<(String, String)> {
     Ok((url, local))
 }
 
+/// Given the path to a GeoJSON boundary polygon, return the URL of the smallest pre-built BBBike
+/// city extract that completely covers the boundary, and the path to where the local copy should
+/// go. BBBike extracts are city-sized, so they're much smaller downloads than Geofabrik's regions.
+///
+/// If no pre-built city covers the boundary, the error explains how to request a custom extract.
+pub async fn pick_bbbike(input: String) -> Result<(String, String)> {
+    let boundary = load_boundary(input)?;
+
+    let path = abstio::path_shared_input("bbbike-cities.csv");
+    if !abstio::file_exists(&path) {
+        let url = "https://raw.githubusercontent.com/wosch/bbbike-world/world/etc/cities.csv";
+        info!("Downloading {}", url);
+        abstio::download_to_file(url, None, &path).await?;
+    }
+    let cities = parse_bbbike_cities(&String::from_utf8(abstio::slurp_file(path)?)?);
+    info!("Searching {} BBBike cities", cities.len());
+
+    // The areas are in square degrees, so don't round them
+    let best = cities
+        .into_iter()
+        .filter(|(_, rect)| rect.to_polygon().contains(&boundary))
+        .min_by(|(_, a), (_, b)| a.unsigned_area().partial_cmp(&b.unsigned_area()).unwrap());
+    let city = match best {
+        Some((city, _)) => city,
+        None => bail!(
+            "No pre-built BBBike extract covers your boundary. Request a custom one at {}",
+            bbbike_custom_extract_url(&boundary)?
+        ),
+    };
+
+    let url = format!("https://download.bbbike.org/osm/bbbike/{city}/{city}.osm.pbf");
+    let local = abstio::path_shared_input(format!("bbbike/{city}.osm.pbf"));
+    Ok((url, local))
+}
+
+/// BBBike's city list is colon-separated. The first field is the name used in download URLs, and
+/// one of the fields is the bounding box, formatted as `lon1,lat1 lon2,lat2`. Lines that don't
+/// match this are skipped.
+fn parse_bbbike_cities(raw: &str) -> Vec<(String, geo::Rect)> {
+    let mut cities = Vec::new();
+    for line in raw.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields[0].is_empty() {
+            continue;
+        }
+        if let Some(rect) = fields[1..].iter().find_map(|f| parse_bbox(f)) {
+            cities.push((fields[0].to_string(), rect));
+        }
+    }
+    cities
+}
+
+fn parse_bbox(field: &str) -> Option<geo::Rect> {
+    let mut pts = Vec::new();
+    for pair in field.split_whitespace() {
+        let (x, y) = pair.split_once(',')?;
+        pts.push(geo::Coord {
+            x: x.parse::<f64>().ok()?,
+            y: y.parse::<f64>().ok()?,
+        });
+    }
+    if pts.len() != 2 {
+        return None;
+    }
+    Some(geo::Rect::new(pts[0], pts[1]))
+}
+
+/// BBBike's extract service generates arbitrary extracts, but asynchronously -- the user has to
+/// submit the form with their email and wait for a download link. So just point them there.
+fn bbbike_custom_extract_url(boundary: &geo::Polygon) -> Result<String> {
+    let rect = match boundary.bounding_rect() {
+        Some(rect) => rect,
+        None => bail!("Boundary is empty"),
+    };
+    Ok(format!(
+        "https://extract.bbbike.org/?sw_lng={}&sw_lat={}&ne_lng={}&ne_lat={}&format=osm.pbf",
+        rect.min().x,
+        rect.min().y,
+        rect.max().x,
+        rect.max().y
+    ))
+}
+
 fn load_boundary(path: String) -> Result<geo::Polygon> {
     let gj: GeoJson = abstio::maybe_read_json(path, &mut Timer::throwaway())?;
     let mut features = match gj {
@@ -0,0 +1,120 @@
+//! Census data is only wired up for a few countries. WorldPop (<https://www.worldpop.org>) and
+//! GHSL (<https://human-settlement.emergency.copernicus.eu>) publish global rasters estimating how
+//! many people live in each small cell, so use one of those to place residents anywhere.
+
+use std::collections::BTreeMap;
+use std::io::BufReader;
+
+use anyhow::Result;
+use elevation::GeoTiffElevation;
+use fs_err::File;
+use georaster::geotiff::GeoTiffReader;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use abstutil::{prettyprint_usize, Timer};
+use map_model::{BuildingID, Map};
+
+/// Where to find a population raster
+pub struct PopulationRaster {
+    /// A GeoTIFF in EPSG:4326, where each pixel holds the number of people living there, like
+    /// WorldPop's "ppp" files
+    pub path: String,
+}
+
+/// Spread the population of each raster cell over the residential buildings inside it, then
+/// generate a scenario with schedules for everybody, saved as "population".
+pub fn generate_scenario(map: &Map, input: &PopulationRaster, timer: &mut Timer) -> Result<()> {
+    // Where the pixel grid starts and how big each pixel is in degrees. The pixel height is
+    // usually negative, since rows go from north to south.
+    let (origin, pixel_size) = {
+        let reader = GeoTiffReader::open(BufReader::new(File::open(&input.path)?))?;
+        match (reader.origin(), reader.pixel_size()) {
+            (Some(origin), Some(pixel_size)) if pixel_size[0] != 0.0 && pixel_size[1] != 0.0 => {
+                (origin, pixel_size)
+            }
+            _ => bail!("{} isn't georeferenced", input.path),
+        }
+    };
+
+    // Identify each cell by its column and row
+    let mut bldgs_per_cell: BTreeMap<(i64, i64), Vec<BuildingID>> = BTreeMap::new();
+    for b in map.all_buildings() {
+        if !b.bldg_type.has_residents() {
+            continue;
+        }
+        let gps = b.label_center.to_gps(map.get_gps_bounds());
+        let cell = (
+            ((gps.x() - origin[0]) / pixel_size[0]).floor() as i64,
+            ((gps.y() - origin[1]) / pixel_size[1]).floor() as i64,
+        );
+        bldgs_per_cell
+            .entry(cell)
+            .or_insert_with(Vec::new)
+            .push(b.id);
+    }
+
+    let mut raster = GeoTiffElevation::new(BufReader::new(File::open(&input.path)?));
+    let mut rng = XorShiftRng::seed_from_u64(42);
+    let mut homes = Vec::new();
+    let mut total = 0.0;
+    timer.start_iter("sample population raster", bldgs_per_cell.len());
+    for ((x, y), bldgs) in bldgs_per_cell {
+        timer.next();
+        // Sample the middle of the cell
+        let lon = origin[0] + ((x as f64) + 0.5) * pixel_size[0];
+        let lat = origin[1] + ((y as f64) + 0.5) * pixel_size[1];
+        // Rasters use a large negative number for cells with no data
+        let population = match raster.get_height_for_lon_lat(lon as f32, lat as f32) {
+            Some(x) if x > 0.0 => f64::from(x),
+            _ => {
+                continue;
+            }
+        };
+        total += population;
+        homes.extend(distribute_by_floor_area(population, bldgs, map, &mut rng));
+    }
+    info!(
+        "Placed about {} residents from {}",
+        prettyprint_usize(total as usize),
+        input.path
+    );
+
+    let scenario = popdat::generate_scenario_for_homes(
+        "population",
+        homes,
+        popdat::Config::default(),
+        map,
+        &mut rng,
+    );
+    scenario.save();
+    Ok(())
+}
+
+/// Bigger buildings get more people. The raster's estimates are fractional, so round randomly to
+/// keep the expected total.
+fn distribute_by_floor_area(
+    population: f64,
+    bldgs: Vec<BuildingID>,
+    map: &Map,
+    rng: &mut XorShiftRng,
+) -> Vec<(BuildingID, usize)> {
+    let total_area: f64 = bldgs.iter().map(|b| map.get_b(*b).floor_area()).sum();
+    let num_bldgs = bldgs.len() as f64;
+    let mut results = Vec::new();
+    for b in bldgs {
+        let share = if total_area > 0.0 {
+            population * map.get_b(b).floor_area() / total_area
+        } else {
+            population / num_bldgs
+        };
+        let mut n = share.floor() as usize;
+        if rng.gen_bool(share.fract()) {
+            n += 1;
+        }
+        if n > 0 {
+            results.push((b, n));
+        }
+    }
+    results
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use abstutil::{prettyprint_usize, MultiMap, Timer};
 use geom::PolyLine;
@@ -202,8 +202,10 @@ fn clip_trips(
                 borders.for_mode(orig.mode),
                 match orig.mode {
                     TripMode::Walk | TripMode::Transit => PathConstraints::Pedestrian,
-                    TripMode::Drive => PathConstraints::Car,
-                    TripMode::Bike => PathConstraints::Bike,
+                    TripMode::Drive | TripMode::Ridehail | TripMode::ParkAndRide => {
+                        PathConstraints::Car
+                    }
+                    TripMode::Bike | TripMode::Micromobility => PathConstraints::Bike,
                 },
                 maybe_huge_map.as_ref(),
                 only_passthrough_trips,
@@ -293,6 +295,9 @@ pub fn make_scenario(
         map_name: map.get_name().clone(),
         people,
         only_seed_buses: None,
+        micromobility: Vec::new(),
+        park_and_ride: BTreeSet::new(),
+        ebike_share: 0.0,
     }
     .remove_weird_schedules(true)
 }
@@ -0,0 +1,486 @@
+//! Import a SUMO network (.net.xml) directly, instead of starting from OSM. Some cities have
+//! carefully calibrated SUMO networks, and this lets them be explored and edited here.
+//!
+//! SUMO edges are one-way, so edges running in opposite directions between the same junctions
+//! become one road. Lanes are classified by the vehicle classes they allow. Turns that SUMO has no
+//! connection for become turn restrictions, and the first program of each traffic light is kept.
+//! Internal edges, crossings, and walking areas are ignored; A/B Street generates its own.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::Result;
+use roxmltree::Node;
+
+use abstio::MapName;
+use abstutil::{Tags, Timer};
+use geom::{Distance, Duration, GPSBounds, LonLat, PolyLine, Pt2D};
+use map_model::RawToMapOptions;
+use osm2streets::{
+    osm, Direction, DrivingSide, IntersectionControl, IntersectionKind, LaneSpec, LaneType,
+    RestrictionType, Road,
+};
+use raw_map::{ExtraRoadData, RawMap, RawSignalProgram, RawSignalStage};
+
+/// Transforms a .net.xml file to a map in one step.
+pub fn oneshot(path: String, opts: RawToMapOptions) -> Result<()> {
+    let mut timer = Timer::new("import SUMO network");
+    let name = abstutil::basename(&path);
+    let name = name.trim_end_matches(".net");
+    let raw = convert(&path, MapName::new("zz", "oneshot", name), &mut timer)?;
+    // Often helpful to save intermediate representation in case user wants to load into map_editor
+    raw.save();
+    let mut map = map_model::Map::create_from_raw(raw, opts, &mut timer);
+    timer.start("save map");
+    map.save();
+    timer.stop("save map");
+    println!("{} has been created", map.get_name().path());
+    Ok(())
+}
+
+struct Edge<'a> {
+    id: &'a str,
+    from: &'a str,
+    to: &'a str,
+    node: Node<'a, 'a>,
+    /// Index 0 is the rightmost lane
+    lanes: Vec<(LaneType, Distance)>,
+}
+
+/// SUMO coordinates to map coordinates
+struct Projection {
+    conv: [f64; 4],
+    geo: [f64; 4],
+    gps_bounds: GPSBounds,
+}
+
+impl Projection {
+    fn new(conv: [f64; 4], geo: [f64; 4], gps_bounds: GPSBounds) -> Result<Projection> {
+        // pt() divides by these
+        if conv[2] - conv[0] <= 0.0 || conv[3] - conv[1] <= 0.0 {
+            bail!("convBoundary {:?} has no area", conv);
+        }
+        Ok(Projection {
+            conv,
+            geo,
+            gps_bounds,
+        })
+    }
+
+    fn pt(&self, x: f64, y: f64) -> Pt2D {
+        let lerp = |v: f64, idx: usize| {
+            let pct = (v - self.conv[idx]) / (self.conv[idx + 2] - self.conv[idx]);
+            self.geo[idx] + pct * (self.geo[idx + 2] - self.geo[idx])
+        };
+        LonLat::new(lerp(x, 0), lerp(y, 1)).to_pt(&self.gps_bounds)
+    }
+
+    fn shape(&self, input: &str) -> Result<Vec<Pt2D>> {
+        let mut pts = Vec::new();
+        for pair in input.split_whitespace() {
+            let xy = parse_floats(pair)?;
+            if xy.len() < 2 {
+                bail!("Bad point {}", pair);
+            }
+            pts.push(self.pt(xy[0], xy[1]));
+        }
+        Ok(pts)
+    }
+}
+
+fn convert(path: &str, name: MapName, timer: &mut Timer) -> Result<RawMap> {
+    timer.start("parse XML");
+    let text = fs_err::read_to_string(path)?;
+    let doc = roxmltree::Document::parse(&text)?;
+    timer.stop("parse XML");
+    let net = doc.root_element();
+
+    let mut map = RawMap::blank(name);
+    if net.attribute("lefthand") == Some("true") {
+        map.streets.config.driving_side = DrivingSide::Left;
+    }
+
+    let location = net
+        .children()
+        .find(|n| n.has_tag_name("location"))
+        .ok_or_else(|| anyhow!("No <location>"))?;
+    let conv = parse_boundary(attr(location, "convBoundary")?)?;
+    // Without a projection, the coordinates are just meters. Put them near the equator, where a
+    // degree is about the same distance both ways.
+    let geo = if location.attribute("projParameter") == Some("!") {
+        let meters_per_degree = 111_320.0;
+        [
+            0.0,
+            0.0,
+            (conv[2] - conv[0]) / meters_per_degree,
+            (conv[3] - conv[1]) / meters_per_degree,
+        ]
+    } else {
+        parse_boundary(attr(location, "origBoundary")?)?
+    };
+    let mut gps_bounds = GPSBounds::new();
+    gps_bounds.update(LonLat::new(geo[0], geo[1]));
+    gps_bounds.update(LonLat::new(geo[2], geo[3]));
+    map.streets.gps_bounds = gps_bounds.clone();
+    map.streets.boundary_polygon = gps_bounds.to_bounds().get_rectangle();
+    let proj = Projection::new(conv, geo, gps_bounds)?;
+
+    let mut edges: Vec<Edge> = Vec::new();
+    for node in net.children().filter(|n| n.has_tag_name("edge")) {
+        // Internal edges, crossings, walking areas, and connectors
+        if node.attribute("function").is_some() {
+            continue;
+        }
+        let mut lanes: Vec<(usize, LaneType, Distance)> = Vec::new();
+        for lane in node.children().filter(|n| n.has_tag_name("lane")) {
+            let lt = match lane_type(lane.attribute("allow"), lane.attribute("disallow")) {
+                Some(lt) => lt,
+                None => {
+                    continue;
+                }
+            };
+            let width = match lane.attribute("width") {
+                Some(w) => Distance::meters(w.parse()?),
+                None => Distance::meters(3.2),
+            };
+            lanes.push((attr(lane, "index")?.parse()?, lt, width));
+        }
+        if lanes.is_empty() {
+            continue;
+        }
+        lanes.sort_by_key(|(idx, _, _)| *idx);
+        edges.push(Edge {
+            id: attr(node, "id")?,
+            from: attr(node, "from")?,
+            to: attr(node, "to")?,
+            node,
+            lanes: lanes.into_iter().map(|(_, lt, w)| (lt, w)).collect(),
+        });
+    }
+
+    // How many roads will each junction have? Dead ends with one road are borders.
+    let mut roads_per_junction: HashMap<&str, HashSet<(&str, &str)>> = HashMap::new();
+    for e in &edges {
+        let pair = if e.from < e.to {
+            (e.from, e.to)
+        } else {
+            (e.to, e.from)
+        };
+        roads_per_junction.entry(e.from).or_default().insert(pair);
+        roads_per_junction.entry(e.to).or_default().insert(pair);
+    }
+
+    let mut junctions: HashMap<&str, (osm2streets::IntersectionID, osm::NodeID)> = HashMap::new();
+    for node in net.children().filter(|n| n.has_tag_name("junction")) {
+        let id = attr(node, "id")?;
+        let num_roads = match roads_per_junction.get(id) {
+            Some(roads) => roads.len(),
+            None => {
+                continue;
+            }
+        };
+        let junction_type = node.attribute("type").unwrap_or("priority");
+        if junction_type == "internal" {
+            continue;
+        }
+        let pt = proj.pt(attr(node, "x")?.parse()?, attr(node, "y")?.parse()?);
+        let kind = if num_roads == 1 {
+            IntersectionKind::MapEdge
+        } else {
+            IntersectionKind::Intersection
+        };
+        let control = if junction_type.starts_with("traffic_light") {
+            IntersectionControl::Signalled
+        } else if junction_type == "allway_stop" || junction_type == "priority_stop" {
+            IntersectionControl::Signed
+        } else {
+            IntersectionControl::Uncontrolled
+        };
+        // There are no real OSM IDs, but signal programs and edits are keyed by them
+        let osm_id = osm::NodeID(junctions.len() as i64 + 1);
+        let i = map
+            .streets
+            .insert_intersection(vec![osm_id], pt, kind, control);
+        map.elevation_per_intersection.insert(i, Distance::ZERO);
+        junctions.insert(id, (i, osm_id));
+    }
+
+    // Pair up edges going opposite ways, preferring SUMO's convention of "X" and "-X"
+    let mut edge_idx: HashMap<&str, usize> = HashMap::new();
+    for (idx, e) in edges.iter().enumerate() {
+        edge_idx.insert(e.id, idx);
+    }
+    let mut used = vec![false; edges.len()];
+    // Per edge, the way and road it became, and the junction it ends at
+    let mut ways: HashMap<&str, (osm::WayID, osm2streets::RoadID, osm2streets::IntersectionID)> =
+        HashMap::new();
+    for idx in 0..edges.len() {
+        if used[idx] {
+            continue;
+        }
+        used[idx] = true;
+        let fwd = &edges[idx];
+        let (i1, i2) = match (junctions.get(fwd.from), junctions.get(fwd.to)) {
+            (Some(i1), Some(i2)) => (*i1, *i2),
+            _ => {
+                warn!("Edge {} has an unknown junction", fwd.id);
+                continue;
+            }
+        };
+        if i1 == i2 {
+            warn!("Skipping loop edge {}", fwd.id);
+            continue;
+        }
+        let reverse_id = match fwd.id.strip_prefix('-') {
+            Some(x) => x.to_string(),
+            None => format!("-{}", fwd.id),
+        };
+        let back_idx = edge_idx
+            .get(reverse_id.as_str())
+            .cloned()
+            .filter(|b| !used[*b] && edges[*b].from == fwd.to && edges[*b].to == fwd.from)
+            .or_else(|| {
+                (0..edges.len())
+                    .find(|b| !used[*b] && edges[*b].from == fwd.to && edges[*b].to == fwd.from)
+            });
+        if let Some(b) = back_idx {
+            used[b] = true;
+        }
+        let back = back_idx.map(|b| &edges[b]);
+
+        let reference_line = match fwd.node.attribute("shape") {
+            Some(shape) => proj.shape(shape)?,
+            None => vec![
+                map.streets.intersections[&i1.0].polygon.center(),
+                map.streets.intersections[&i2.0].polygon.center(),
+            ],
+        };
+        let reference_line = match PolyLine::deduping_new(reference_line) {
+            Ok(pl) => pl,
+            Err(err) => {
+                warn!("Edge {} has a bad shape: {}", fwd.id, err);
+                continue;
+            }
+        };
+
+        // Lanes ordered left-to-right along the forwards edge
+        let spec = |(lt, width): &(LaneType, Distance), dir: Direction| LaneSpec {
+            lt: *lt,
+            dir,
+            width: *width,
+            allowed_turns: Default::default(),
+        };
+        let fwd_lanes = fwd.lanes.iter().rev().map(|l| spec(l, Direction::Fwd));
+        let back_lanes: Vec<LaneSpec> = back
+            .map(|e| e.lanes.iter().map(|l| spec(l, Direction::Back)).collect())
+            .unwrap_or_default();
+        let lane_specs_ltr: Vec<LaneSpec> = match map.streets.config.driving_side {
+            DrivingSide::Right => back_lanes.into_iter().chain(fwd_lanes).collect(),
+            DrivingSide::Left => fwd_lanes.chain(back_lanes).collect(),
+        };
+
+        let way = osm::WayID(ways.len() as i64 + 1);
+        let tags = road_tags(fwd, back, &lane_specs_ltr);
+        let id = map.streets.next_road_id();
+        let mut road = Road::new(
+            id,
+            vec![way],
+            i1.0,
+            i2.0,
+            reference_line,
+            tags.clone(),
+            &map.streets.config,
+        );
+        road.lane_specs_ltr = lane_specs_ltr;
+        road.update_center_line(map.streets.config.driving_side);
+        map.streets.insert_road(road);
+        map.osm_tags.insert(way, tags);
+        map.extra_road_data.insert(id, ExtraRoadData::default());
+        ways.insert(fwd.id, (way, id, i2.0));
+        if let Some(back) = back {
+            ways.insert(back.id, (way, id, i1.0));
+        }
+    }
+
+    // Where can each edge turn, and which connections does each signal control?
+    let mut connections: BTreeMap<
+        (osm2streets::RoadID, osm2streets::IntersectionID),
+        HashSet<osm2streets::RoadID>,
+    > = BTreeMap::new();
+    let mut signal_links: HashMap<&str, BTreeMap<usize, (osm::WayID, osm::WayID)>> = HashMap::new();
+    for node in net.children().filter(|n| n.has_tag_name("connection")) {
+        let (from, to) = match (ways.get(attr(node, "from")?), ways.get(attr(node, "to")?)) {
+            (Some(from), Some(to)) => (*from, *to),
+            // Connections to internal edges
+            _ => {
+                continue;
+            }
+        };
+        connections
+            .entry((from.1, from.2))
+            .or_default()
+            .insert(to.1);
+        if let (Some(tl), Some(link)) = (node.attribute("tl"), node.attribute("linkIndex")) {
+            signal_links
+                .entry(tl)
+                .or_default()
+                .insert(link.parse()?, (from.0, to.0));
+        }
+    }
+    for ((from, i), allowed) in connections {
+        let banned: Vec<osm2streets::RoadID> = map.streets.intersections[&i]
+            .roads
+            .iter()
+            .filter(|to| **to != from && !allowed.contains(to))
+            .cloned()
+            .collect();
+        let road = map.streets.roads.get_mut(&from).unwrap();
+        for to in banned {
+            road.turn_restrictions.push((RestrictionType::BanTurns, to));
+        }
+    }
+
+    for node in net.children().filter(|n| n.has_tag_name("tlLogic")) {
+        let id = attr(node, "id")?;
+        let osm_id = match junctions.get(id) {
+            Some((_, osm_id)) => *osm_id,
+            None => {
+                continue;
+            }
+        };
+        // Only keep the first program
+        if map.traffic_signal_programs.contains_key(&osm_id) {
+            continue;
+        }
+        let links = signal_links.remove(id).unwrap_or_default();
+        let offset = Duration::seconds(node.attribute("offset").unwrap_or("0").parse()?);
+        let mut stages: Vec<RawSignalStage> = Vec::new();
+        // Yellow and all-red phases before the first green belong to the last stage
+        let mut leftover = Duration::ZERO;
+        for phase in node.children().filter(|n| n.has_tag_name("phase")) {
+            let duration = Duration::seconds(attr(phase, "duration")?.parse()?);
+            let mut stage = RawSignalStage {
+                duration,
+                protected: Vec::new(),
+                permitted: Vec::new(),
+            };
+            for (idx, c) in attr(phase, "state")?.chars().enumerate() {
+                if let Some(movement) = links.get(&idx) {
+                    match c {
+                        'G' => stage.protected.push(*movement),
+                        'g' => stage.permitted.push(*movement),
+                        _ => {}
+                    }
+                }
+            }
+            if stage.protected.is_empty() && stage.permitted.is_empty() {
+                match stages.last_mut() {
+                    Some(last) => {
+                        last.duration += duration;
+                    }
+                    None => {
+                        leftover += duration;
+                    }
+                }
+            } else {
+                stages.push(stage);
+            }
+        }
+        if let Some(last) = stages.last_mut() {
+            last.duration += leftover;
+            map.traffic_signal_programs
+                .insert(osm_id, RawSignalProgram { offset, stages });
+        }
+    }
+
+    Ok(map)
+}
+
+/// None if the lane doesn't carry anything
+fn lane_type(allow: Option<&str>, disallow: Option<&str>) -> Option<LaneType> {
+    if disallow == Some("all") {
+        return None;
+    }
+    let allow: HashSet<&str> = allow.unwrap_or("").split_whitespace().collect();
+    let disallow: HashSet<&str> = disallow.unwrap_or("").split_whitespace().collect();
+    let allowed = |class: &str| {
+        if allow.is_empty() {
+            !disallow.contains(class)
+        } else {
+            allow.contains(class)
+        }
+    };
+    Some(if allowed("passenger") {
+        LaneType::Driving
+    } else if allowed("bus") {
+        LaneType::Bus
+    } else if allowed("bicycle") && allowed("pedestrian") {
+        LaneType::SharedUse
+    } else if allowed("bicycle") {
+        LaneType::Biking
+    } else if allowed("pedestrian") {
+        LaneType::Sidewalk
+    } else if allowed("tram") || allowed("rail_urban") {
+        LaneType::LightRail
+    } else {
+        return None;
+    })
+}
+
+/// There's no OSM data, but a few tags are still used for classifying and naming roads
+fn road_tags(fwd: &Edge, back: Option<&Edge>, lanes: &[LaneSpec]) -> Tags {
+    let mut tags = Tags::empty();
+    let highway = fwd
+        .node
+        .attribute("type")
+        .and_then(|t| t.strip_prefix("highway."))
+        .map(|t| t.to_string());
+    if lanes.iter().all(|l| l.lt == LaneType::LightRail) {
+        tags.insert("railway", "light_rail");
+    } else if let Some(highway) = highway {
+        tags.insert(osm::HIGHWAY, highway);
+    } else if lanes.iter().any(|l| l.lt.is_for_moving_vehicles()) {
+        tags.insert(osm::HIGHWAY, "unclassified");
+    } else if lanes.iter().any(|l| l.lt == LaneType::Biking) {
+        tags.insert(osm::HIGHWAY, "cycleway");
+    } else {
+        tags.insert(osm::HIGHWAY, "footway");
+    }
+    if let Some(name) = fwd.node.attribute("name") {
+        tags.insert("name", name);
+    }
+    if back.is_none() {
+        tags.insert("oneway", "yes");
+    }
+    // The fastest lane, in km/h
+    let speed = fwd
+        .node
+        .children()
+        .filter(|n| n.has_tag_name("lane"))
+        .filter_map(|n| n.attribute("speed").and_then(|s| s.parse::<f64>().ok()))
+        .fold(0.0, f64::max);
+    if speed > 0.0 {
+        tags.insert("maxspeed", (speed * 3.6).round().to_string());
+    }
+    tags
+}
+
+fn attr<'a>(node: Node<'a, '_>, key: &str) -> Result<&'a str> {
+    node.attribute(key)
+        .ok_or_else(|| anyhow!("<{}> missing {}", node.tag_name().name(), key))
+}
+
+fn parse_floats(input: &str) -> Result<Vec<f64>> {
+    let mut results = Vec::new();
+    for x in input.split(',') {
+        results.push(x.parse()?);
+    }
+    Ok(results)
+}
+
+fn parse_boundary(input: &str) -> Result<[f64; 4]> {
+    let x = parse_floats(input)?;
+    if x.len() != 4 {
+        bail!("Bad boundary {}", input);
+    }
+    Ok([x[0], x[1], x[2], x[3]])
+}
@@ -6,6 +6,7 @@ use abstutil::{must_run_cmd, Timer};
 use map_model::RawToMapOptions;
 use raw_map::RawMap;
 
+use crate::cache::StageInputs;
 use crate::configuration::ImporterConfiguration;
 
 /// If the output file doesn't already exist, downloads the URL into that location. Automatically
@@ -85,16 +86,49 @@ pub async fn download_kml(
     fs_err::rename(tmp, output.replace(".bin", ".kml")).unwrap();
 }
 
+/// Downloads every global DEM tile covering a boundary polygon into a directory, skipping tiles
+/// that already exist. Some tiles are expected to be missing upstream -- Copernicus doesn't publish
+/// tiles that'd only cover ocean -- so failed downloads just produce a warning.
+pub async fn download_dem_tiles(boundary_polygon: &str, dir: &str) -> anyhow::Result<()> {
+    let bounds = geom::GPSBounds::from(geom::LonLat::read_geojson_polygon(boundary_polygon)?);
+    fs_err::create_dir_all(dir)?;
+    for (name, url) in convert_osm::dem_tiles(&bounds) {
+        let output = format!("{}/{}.tif", dir.trim_end_matches('/'), name);
+        if Path::new(&output).exists() {
+            println!("- {} already exists", output);
+            continue;
+        }
+        println!("- Missing {}, so downloading {}", output, url);
+        let tmp = format!("{output}_TMP");
+        match abstio::download_to_file(&url, None, &tmp).await {
+            Ok(()) => {
+                fs_err::rename(tmp, output)?;
+            }
+            Err(err) => {
+                warn!("Couldn't download DEM tile {}: {}", url, err);
+                // Don't leave a partial file around
+                let _ = fs_err::remove_file(tmp);
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Uses osmium to clip the input .osm.xml or osm.pbf against a polygon and produce some output pbf
-/// file. Skips if the output exists.
+/// file. Skips if the output was already produced from the same input and polygon.
 pub fn osmium(
     input: String,
     clipping_polygon: String,
     output: String,
     config: &ImporterConfiguration,
 ) {
-    if Path::new(&output).exists() {
-        println!("- {} already exists", output);
+    let inputs = StageInputs::new()
+        .file(&input)
+        .unwrap()
+        .file(&clipping_polygon)
+        .unwrap();
+    if !config.ignore_cache && inputs.is_fresh(&output) {
+        println!("- {} is up-to-date", output);
         return;
     }
     // Create the output directory if needed
@@ -114,8 +148,11 @@ pub fn osmium(
             .arg(output)
             .arg("-f")
             // Smaller files without author, timestamp, version
-            .arg("pbf,add_metadata=false"),
+            .arg("pbf,add_metadata=false")
+            // The output may be stale
+            .arg("--overwrite"),
     );
+    inputs.record(&output);
 }
 
 /// Creates a RawMap from OSM and other input data.
@@ -127,7 +164,10 @@ pub async fn osm_to_raw(
     if name.city == CityName::seattle() {
         crate::seattle::input(config, timer).await;
     }
-    let opts = crate::map_config::config_for_map(&name);
+    let mut opts = crate::map_config::config_for_map(&name);
+    if let Some(x) = config.infer_parking {
+        opts.infer_onstreet_parking = x;
+    }
     if let Some(ref url) = opts.gtfs_url {
         download(config, name.city.input_path("gtfs/"), url).await;
     }
@@ -140,30 +180,104 @@ pub async fn osm_to_raw(
         .await
         .unwrap();
     download(config, local_osm_file.clone(), &osm_url).await;
+    if opts.elevation_geotiff.is_none() {
+        if let Some(ref dir) = opts.elevation_dem_tiles {
+            download_dem_tiles(&boundary_polygon, dir).await.unwrap();
+        }
+    }
 
+    let clipped_osm = name.city.input_path(format!("osm/{}.osm.pbf", name.map));
     osmium(
         local_osm_file,
         boundary_polygon.clone(),
-        name.city.input_path(format!("osm/{}.osm.pbf", name.map)),
+        clipped_osm.clone(),
         config,
     );
 
+    let output = abstio::path_raw_map(&name);
+    let inputs = raw_map_inputs(&name, &clipped_osm, &boundary_polygon, &opts).unwrap();
+    if !config.ignore_cache && inputs.is_fresh(&output) {
+        println!("- {} is up-to-date", output);
+        return abstio::read_binary(output, timer);
+    }
+
     let map = convert_osm::convert(
-        name.city.input_path(format!("osm/{}.osm.pbf", name.map)),
+        clipped_osm,
         name.clone(),
         Some(boundary_polygon),
         opts,
         timer,
-    );
+    )
+    .unwrap();
     map.save();
+    inputs.record(&output);
     map
 }
 
+/// Everything that affects the RawMap produced by convert_osm
+fn raw_map_inputs(
+    name: &MapName,
+    clipped_osm: &str,
+    boundary_polygon: &str,
+    opts: &convert_osm::Options,
+) -> anyhow::Result<StageInputs> {
+    let mut inputs = StageInputs::new()
+        .file(clipped_osm)?
+        .file(boundary_polygon)?
+        .value(format!("{:?}", opts));
+    // Some options refer to extra input files by path
+    if let convert_osm::OnstreetParking::Blockface(ref path) = opts.onstreet_parking {
+        inputs = inputs.file(path)?;
+    }
+    if let convert_osm::PublicOffstreetParking::Gis(ref path) = opts.public_offstreet_parking {
+        inputs = inputs.file(path)?;
+    }
+    if let Some(ref zoning) = opts.zoning {
+        inputs = inputs.file(&zoning.path)?;
+    }
+    // The URL is part of the options, but the feed behind it may have changed since
+    if opts.gtfs_url.is_some() {
+        for file in [
+            "routes",
+            "trips",
+            "shapes",
+            "stop_times",
+            "stops",
+            "calendar",
+            "calendar_dates",
+            "frequencies",
+        ] {
+            inputs = inputs.file(name.city.input_path(format!("gtfs/{}.txt", file)))?;
+        }
+    }
+    for path in [
+        &opts.extra_buildings,
+        &opts.elevation_geotiff,
+        &opts.signal_timing,
+        &opts.tag_filter,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        inputs = inputs.file(path)?;
+    }
+    if opts.elevation_geotiff.is_none() {
+        if let Some(ref dir) = opts.elevation_dem_tiles {
+            let bounds =
+                geom::GPSBounds::from(geom::LonLat::read_geojson_polygon(boundary_polygon)?);
+            for (name, _) in convert_osm::dem_tiles(&bounds) {
+                inputs = inputs.file(format!("{}/{}.tif", dir.trim_end_matches('/'), name))?;
+            }
+        }
+    }
+    Ok(inputs)
+}
+
 /// Converts a RawMap to a Map.
 pub fn raw_to_map(name: &MapName, opts: RawToMapOptions, timer: &mut Timer) -> map_model::Map {
     timer.start(format!("Raw->Map for {}", name.describe()));
     let raw: RawMap = abstio::read_binary(abstio::path_raw_map(name), timer);
-    let map = map_model::Map::create_from_raw(raw, opts, timer);
+    let mut map = map_model::Map::create_from_raw(raw, opts, timer);
     timer.start("save map");
     map.save();
     timer.stop("save map");
@@ -132,6 +132,9 @@ pub struct ColorScheme {
     pub unzoomed_car: Color,
     pub unzoomed_bike: Color,
     pub unzoomed_bus: Color,
+    pub unzoomed_ridehail: Color,
+    pub unzoomed_micromobility: Color,
+    pub unzoomed_park_and_ride: Color,
     pub unzoomed_pedestrian: Color,
 
     // Agents
@@ -257,6 +260,9 @@ impl ColorScheme {
             unzoomed_car: hex("#FE5f55"),
             unzoomed_bike: hex("#90BE6D"),
             unzoomed_bus: hex("#FFD166"),
+            unzoomed_ridehail: hex("#9B5DE5"),
+            unzoomed_micromobility: hex("#00BBF9"),
+            unzoomed_park_and_ride: hex("#F3722C"),
             unzoomed_pedestrian: hex("#457B9D"),
 
             // Agents
@@ -4,7 +4,7 @@ use abstutil::Timer;
 use geom::{Duration, UnitFmt};
 use widgetry::{
     CanvasSettings, Choice, EventCtx, GeomBatch, GfxCtx, Key, Line, Outcome, Panel, Spinner, State,
-    TextExt, Toggle, Widget,
+    TextBox, TextExt, Toggle, Widget,
 };
 
 use crate::colors::ColorSchemeChoice;
@@ -40,11 +40,18 @@ pub struct Options {
     pub show_stop_signs: bool,
     /// Draw crosswalks and unmarked crossings.
     pub show_crosswalks: bool,
+    /// Paint lanes and crosswalks following local conventions: sharrows, bus lane stencils,
+    /// colored surfacing for bike and bus lanes, and the country's zebra style.
+    pub detailed_lane_markings: bool,
     /// If true, draw an icon for traffic signals both when zoomed and unzoomed. If false, color
     /// the intersection when unzoomed and render the signal's current state when zoomed.
     pub show_traffic_signal_icon: bool,
     /// If true, modify several basemap features to de-emphasize them: border intersections
     pub simplify_basemap: bool,
+    /// How to draw agents when unzoomed
+    pub agent_style: AgentStyle,
+    /// When unzoomed, draw cars and transit vehicles carrying more people bigger
+    pub scale_agents_by_occupancy: bool,
 
     /// When making a screen recording, enable this option to hide some UI elements
     pub minimal_controls: bool,
@@ -63,6 +70,9 @@ pub struct Options {
     pub language: Option<String>,
     /// How to render geometric units
     pub units: UnitFmt,
+    /// Where proposals are published to and browsed from. There's no public gallery service, so
+    /// this is empty by default, which hides the gallery.
+    pub proposal_gallery_url: String,
 }
 
 impl Options {
@@ -93,8 +103,11 @@ impl Options {
             show_building_outlines: true,
             show_stop_signs: true,
             show_crosswalks: true,
+            detailed_lane_markings: true,
             show_traffic_signal_icon: false,
             simplify_basemap: false,
+            agent_style: AgentStyle::Circles,
+            scale_agents_by_occupancy: false,
 
             time_increment: Duration::minutes(10),
             dont_draw_time_warp: false,
@@ -108,6 +121,7 @@ impl Options {
                 // TODO Should default be based on the map?
                 metric: false,
             },
+            proposal_gallery_url: String::new(),
         }
     }
 }
@@ -130,6 +144,18 @@ pub enum CameraAngle {
     Abstract,
 }
 
+/// Ways of drawing unzoomed agents. Colors alone can be hard to tell apart for colorblind players
+/// or on a projector, so some styles also vary the shape.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum AgentStyle {
+    /// A circle, colored by mode
+    Circles,
+    /// A different shape for each mode. Transit vehicles are outlined.
+    Shapes,
+    /// An icon of each mode
+    Icons,
+}
+
 pub struct OptionsPanel {
     panel: Panel,
 }
@@ -226,6 +252,25 @@ impl OptionsPanel {
                             ],
                         ),
                     ]),
+                    Widget::row(vec![
+                        "Unzoomed agents:".text_widget(ctx),
+                        Widget::dropdown(
+                            ctx,
+                            "Agent style",
+                            app.opts().agent_style,
+                            vec![
+                                Choice::new("Circles colored by mode", AgentStyle::Circles),
+                                Choice::new("Shapes by mode", AgentStyle::Shapes),
+                                Choice::new("Icons by mode", AgentStyle::Icons),
+                            ],
+                        ),
+                    ]),
+                    Toggle::checkbox(
+                        ctx,
+                        "Draw vehicles carrying more people bigger",
+                        None,
+                        app.opts().scale_agents_by_occupancy,
+                    ),
                     Widget::row(vec![
                         "Color scheme:".text_widget(ctx),
                         Widget::dropdown(
@@ -268,6 +313,12 @@ impl OptionsPanel {
                         }
                         Widget::dropdown(ctx, "language", default, choices)
                     }]),
+                    Toggle::checkbox(
+                        ctx,
+                        "Use local styles for lane markings",
+                        None,
+                        app.opts().detailed_lane_markings,
+                    ),
                     Toggle::choice(
                         ctx,
                         "metric / imperial units",
@@ -288,6 +339,14 @@ impl OptionsPanel {
                         None,
                         app.opts().debug_all_agents,
                     ),
+                    Widget::row(vec![
+                        "Proposal gallery URL".text_widget(ctx).centered_vert(),
+                        TextBox::default_widget(
+                            ctx,
+                            "proposal gallery URL",
+                            app.opts().proposal_gallery_url.clone(),
+                        ),
+                    ]),
                 ])
                 .bg(app.cs().inner_panel_bg)
                 .padding(8),
@@ -316,6 +375,11 @@ impl<A: AppLike> State<A> for OptionsPanel {
                     opts.debug_all_agents = self
                         .panel
                         .is_checked("Draw all agents to debug geometry (Slow!)");
+                    opts.proposal_gallery_url = self
+                        .panel
+                        .text_box("proposal gallery URL")
+                        .trim()
+                        .to_string();
 
                     ctx.canvas.settings.invert_scroll = self
                         .panel
@@ -383,6 +447,23 @@ impl<A: AppLike> State<A> for OptionsPanel {
                     }
 
                     opts.units.metric = self.panel.is_checked("metric / imperial units");
+                    // The agent cache notices these changed by itself
+                    opts.agent_style = self.panel.dropdown_value("Agent style");
+                    opts.scale_agents_by_occupancy = self
+                        .panel
+                        .is_checked("Draw vehicles carrying more people bigger");
+
+                    let detailed_lane_markings =
+                        self.panel.is_checked("Use local styles for lane markings");
+                    if detailed_lane_markings != opts.detailed_lane_markings {
+                        opts.detailed_lane_markings = detailed_lane_markings;
+                        for r in &mut app.mut_draw_map().roads {
+                            r.clear_rendering();
+                        }
+                        for i in &mut app.mut_draw_map().intersections {
+                            i.clear_rendering();
+                        }
+                    }
 
                     let language = self.panel.dropdown_value("language");
                     if language != opts.language {
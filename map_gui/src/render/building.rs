@@ -1,7 +1,7 @@
 use std::cell::RefCell;
 
 use geom::{Angle, Bounds, Distance, Line, Polygon, Pt2D, Ring, Tessellation};
-use map_model::{Building, BuildingID, Map, OffstreetParking};
+use map_model::{Building, BuildingID, Map, OffstreetParking, BUILDING_HEIGHT_PER_LEVEL};
 use widgetry::{Color, Drawable, EventCtx, GeomBatch, GfxCtx, Line, Text};
 
 use crate::colors::ColorScheme;
@@ -74,10 +74,11 @@ impl DrawBuilding {
                     CameraAngle::TopDown | CameraAngle::Abstract => unreachable!(),
                 };
 
-                let bldg_height_per_level = 3.5;
+                let bldg_height_per_level = BUILDING_HEIGHT_PER_LEVEL.inner_meters();
                 // In downtown areas, really tall buildings look kind of ridculous next to
                 // everything else. So we artificially compress the number of levels a bit.
-                let bldg_rendered_meters = bldg_height_per_level * bldg.levels.powf(0.8);
+                let levels = bldg.height.inner_meters() / bldg_height_per_level;
+                let bldg_rendered_meters = bldg_height_per_level * levels.powf(0.8);
                 let height = Distance::meters(bldg_rendered_meters);
 
                 let map_bounds = map.get_gps_bounds().to_bounds();
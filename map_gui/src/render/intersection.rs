@@ -11,6 +11,7 @@ use map_model::{
 use widgetry::{Color, Drawable, GeomBatch, GfxCtx, Prerender, RewriteColor, Text};
 
 use crate::colors::ColorScheme;
+use crate::render::markings::ZebraStyle;
 use crate::render::{traffic_signal, DrawOptions, Renderable, OUTLINE_THICKNESS};
 use crate::{AppLike, ID};
 
@@ -70,15 +71,21 @@ impl DrawIntersection {
             }
         }
 
+        let zebra = ZebraStyle::for_app(app);
         for turn in &i.turns {
             if !app.opts().show_crosswalks {
                 break;
             }
             if turn.turn_type.pedestrian_crossing() {
-                make_crosswalk(&mut default_geom, turn, map, app.cs());
+                make_crosswalk(&mut default_geom, turn, map, app.cs(), zebra);
             }
         }
 
+        default_geom.extend(
+            app.cs().zoomed_road_surface(LaneType::Sidewalk, rank),
+            i.corner_islands(map),
+        );
+
         if i.is_private(map) {
             if let Some(color) = app.cs().private_road {
                 default_geom.push(color.alpha(0.5), i.polygon.clone());
@@ -547,7 +554,13 @@ fn make_octagon(center: Pt2D, radius: Distance, facing: Angle) -> Polygon {
 }
 
 /// Draws both zebra crosswalks and unmarked crossings
-pub fn make_crosswalk(batch: &mut GeomBatch, turn: &Turn, map: &Map, cs: &ColorScheme) {
+pub fn make_crosswalk(
+    batch: &mut GeomBatch,
+    turn: &Turn,
+    map: &Map,
+    cs: &ColorScheme,
+    zebra: ZebraStyle,
+) {
     if turn.turn_type == TurnType::UnmarkedCrossing {
         make_unmarked_crossing(batch, turn, map, cs);
         return;
@@ -562,7 +575,6 @@ pub fn make_crosswalk(batch: &mut GeomBatch, turn: &Turn, map: &Map, cs: &ColorS
     // Start at least width out to not hit sidewalk corners. Also account for the thickness of the
     // crosswalk line itself. Center the lines inside these two boundaries.
     let boundary = width;
-    let tile_every = width * 0.6;
     let line = if let Some(l) = turn.crosswalk_line() {
         l
     } else {
@@ -570,6 +582,14 @@ pub fn make_crosswalk(batch: &mut GeomBatch, turn: &Turn, map: &Map, cs: &ColorS
     };
 
     const CROSSWALK_LINE_THICKNESS: Distance = Distance::const_meters(0.15);
+    // Continental and ladder styles use single thick bars instead of thin pairs
+    const CROSSWALK_BAR_THICKNESS: Distance = Distance::const_meters(0.4);
+    let (tile_every, bar_thickness) = match zebra {
+        ZebraStyle::DoubleBars => (width * 0.6, CROSSWALK_LINE_THICKNESS),
+        ZebraStyle::Continental | ZebraStyle::Ladder => {
+            (CROSSWALK_BAR_THICKNESS * 2.0, CROSSWALK_BAR_THICKNESS)
+        }
+    };
 
     let available_length = line.length() - (boundary * 2.0);
     if available_length > Distance::ZERO {
@@ -584,22 +604,40 @@ pub fn make_crosswalk(batch: &mut GeomBatch, turn: &Turn, map: &Map, cs: &ColorS
             let pt2 = pt1.project_away(Distance::meters(1.0), line.angle());
             batch.push(
                 cs.general_road_marking,
-                perp_line(Line::must_new(pt1, pt2), width).make_polygons(CROSSWALK_LINE_THICKNESS),
+                perp_line(Line::must_new(pt1, pt2), width).make_polygons(bar_thickness),
             );
 
-            // Actually every line is a double
-            let pt3 = line
-                .dist_along(dist_along + 2.0 * CROSSWALK_LINE_THICKNESS)
-                .expect(&err);
-            let pt4 = pt3.project_away(Distance::meters(1.0), line.angle());
-            batch.push(
-                cs.general_road_marking,
-                perp_line(Line::must_new(pt3, pt4), width).make_polygons(CROSSWALK_LINE_THICKNESS),
-            );
+            if zebra == ZebraStyle::DoubleBars {
+                // Actually every line is a double
+                let pt3 = line
+                    .dist_along(dist_along + 2.0 * CROSSWALK_LINE_THICKNESS)
+                    .expect(&err);
+                let pt4 = pt3.project_away(Distance::meters(1.0), line.angle());
+                batch.push(
+                    cs.general_road_marking,
+                    perp_line(Line::must_new(pt3, pt4), width)
+                        .make_polygons(CROSSWALK_LINE_THICKNESS),
+                );
+            }
 
             dist_along += tile_every;
         }
     }
+
+    if zebra == ZebraStyle::Ladder {
+        // The rails of the ladder run along both edges of the crossing
+        if let Ok(slice) = line.slice(boundary, line.length() - boundary) {
+            for rail in [
+                slice.shift_left(width / 2.0),
+                slice.shift_right(width / 2.0),
+            ] {
+                batch.push(
+                    cs.general_road_marking,
+                    rail.make_polygons(CROSSWALK_LINE_THICKNESS),
+                );
+            }
+        }
+    }
 }
 
 fn make_rainbow_crosswalk(batch: &mut GeomBatch, turn: &Turn, map: &Map) -> bool {
@@ -9,6 +9,7 @@ use geom::{
 use map_model::{BufferType, Direction, DrivingSide, Lane, LaneID, LaneType, Map, Road, TurnID};
 use widgetry::{Color, Drawable, GeomBatch, GfxCtx, Prerender, RewriteColor};
 
+use crate::render::markings::{bus_stencils, hov_diamonds, sharrows, MarkingStyle};
 use crate::render::{DrawOptions, Renderable, OUTLINE_THICKNESS};
 use crate::{AppLike, ID};
 
@@ -36,10 +37,17 @@ impl DrawLane {
         let road = map.get_r(lane.id.road);
         let rank = road.get_rank();
         let mut batch = GeomBatch::new();
+        let style = if app.opts().detailed_lane_markings {
+            MarkingStyle::for_map(map)
+        } else {
+            MarkingStyle::default_style()
+        };
 
         if !lane.is_light_rail() {
             batch.push(
-                app.cs().zoomed_road_surface(lane.lane_type, rank),
+                style
+                    .lane_surface(lane.lane_type)
+                    .unwrap_or_else(|| app.cs().zoomed_road_surface(lane.lane_type, rank)),
                 self.polygon.clone(),
             );
         }
@@ -70,6 +78,12 @@ impl DrawLane {
                 batch.extend(general_road_marking, calculate_driving_lines(lane, road));
                 batch.extend(general_road_marking, calculate_turn_markings(map, lane));
                 batch.extend(general_road_marking, calculate_one_way_markings(lane, road));
+                if road.hov_restriction(lane.id).is_some() {
+                    batch.extend(general_road_marking, hov_diamonds(lane));
+                }
+                if app.opts().detailed_lane_markings {
+                    batch.append(sharrows(prerender, map, lane, road, general_road_marking));
+                }
             }
             LaneType::Bus => {
                 batch.extend(general_road_marking, calculate_driving_lines(lane, road));
@@ -86,6 +100,7 @@ impl DrawLane {
                             .rotate(angle.shortest_rotation_towards(Angle::degrees(-90.0))),
                     );
                 }
+                batch.append(bus_stencils(prerender, &style, lane, general_road_marking));
             }
             LaneType::Biking => {
                 for (pt, angle) in lane
@@ -3,7 +3,8 @@ use std::collections::HashMap;
 use abstutil::Timer;
 use geom::{Bounds, Distance, QuadTree, Tessellation};
 use map_model::{
-    AreaID, BuildingID, IntersectionID, LaneID, Map, ParkingLotID, Road, RoadID, TransitStopID,
+    osm, AreaID, BuildingID, IntersectionID, LaneID, Map, ParkingLotID, Road, RoadID,
+    TransitStopID, NORMAL_LANE_THICKNESS,
 };
 use widgetry::{Color, Drawable, EventCtx, Fill, GeomBatch};
 
@@ -90,6 +91,15 @@ impl DrawMap {
             timer.next();
             areas.push(DrawArea::new(ctx, a, cs, &mut all_areas));
         }
+        // The internal roads of large sites aren't part of the road network, but still show them
+        for site in map.all_sites() {
+            for aisle in &site.aisles {
+                all_areas.push(
+                    cs.unzoomed_road_surface(osm::RoadRank::Local),
+                    aisle.thicken_tessellation(NORMAL_LANE_THICKNESS / 2.0),
+                );
+            }
+        }
         timer.start("upload all areas");
         let draw_all_areas = all_areas.upload(ctx);
         timer.stop("upload all areas");
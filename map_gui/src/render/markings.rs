@@ -0,0 +1,223 @@
+//! Lanes and crossings are painted differently around the world. When detailed lane markings are
+//! enabled, use the local conventions, so that edited streets look like the designs being
+//! proposed.
+
+use geom::{Angle, Distance, PolyLine, Polygon, Pt2D};
+use map_model::{Direction, DrivingSide, Lane, LaneType, Map, Road};
+use widgetry::{Color, GeomBatch, Line, Prerender, RewriteColor, Text};
+
+use crate::AppLike;
+
+pub struct MarkingStyle {
+    /// Overrides the color scheme's surface for bike lanes
+    pub bike_lane_surface: Option<Color>,
+    /// Overrides the color scheme's surface for bus lanes
+    pub bus_lane_surface: Option<Color>,
+    /// Painted on bus lanes. None means to only use the icon.
+    pub bus_stencil: Option<&'static str>,
+    pub zebra: ZebraStyle,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ZebraStyle {
+    /// Pairs of thin bars, the original A/B Street style
+    DoubleBars,
+    /// Thick bars parallel to the direction of travel, used in most of Europe
+    Continental,
+    /// Thick bars between two transverse lines, common in North America
+    Ladder,
+}
+
+impl ZebraStyle {
+    /// The local style when detailed lane markings are enabled, otherwise the original style
+    pub fn for_app(app: &dyn AppLike) -> ZebraStyle {
+        if app.opts().detailed_lane_markings {
+            MarkingStyle::for_map(app.map()).zebra
+        } else {
+            ZebraStyle::DoubleBars
+        }
+    }
+}
+
+impl MarkingStyle {
+    pub fn for_map(map: &Map) -> MarkingStyle {
+        let red_surface = Color::rgb(170, 60, 50);
+        let green_surface = Color::rgb(15, 125, 75);
+        match map.get_name().city.country.as_ref() {
+            "us" | "ca" => MarkingStyle {
+                bike_lane_surface: Some(green_surface),
+                bus_lane_surface: Some(red_surface),
+                bus_stencil: Some("BUS ONLY"),
+                zebra: ZebraStyle::Ladder,
+            },
+            "gb" | "ie" => MarkingStyle {
+                bike_lane_surface: Some(green_surface),
+                bus_lane_surface: Some(red_surface),
+                bus_stencil: Some("BUS LANE"),
+                zebra: ZebraStyle::Continental,
+            },
+            // Red asphalt for cycle paths
+            "nl" | "be" | "de" | "at" | "ch" => MarkingStyle {
+                bike_lane_surface: Some(red_surface),
+                bus_lane_surface: None,
+                bus_stencil: Some("BUS"),
+                zebra: ZebraStyle::Continental,
+            },
+            "" | "zz" => MarkingStyle::default_style(),
+            _ => MarkingStyle {
+                zebra: ZebraStyle::Continental,
+                ..MarkingStyle::default_style()
+            },
+        }
+    }
+
+    /// Don't override anything from the color scheme.
+    pub fn default_style() -> MarkingStyle {
+        MarkingStyle {
+            bike_lane_surface: None,
+            bus_lane_surface: None,
+            bus_stencil: None,
+            zebra: ZebraStyle::DoubleBars,
+        }
+    }
+
+    pub fn lane_surface(&self, lt: LaneType) -> Option<Color> {
+        match lt {
+            LaneType::Biking => self.bike_lane_surface,
+            LaneType::Bus => self.bus_lane_surface,
+            _ => None,
+        }
+    }
+}
+
+/// Is this a driving lane next to the curb (or parking, or a buffer), where a cyclist sharing the
+/// lane would ride?
+fn is_curbside_driving_lane(lane: &Lane, road: &Road, driving_side: DrivingSide) -> bool {
+    // Lanes are ordered left-to-right. Which neighbor is towards the curb?
+    let curb_is_right = (lane.dir == Direction::Fwd) == (driving_side == DrivingSide::Right);
+    let neighbor = if curb_is_right {
+        road.lanes.get(lane.id.offset + 1)
+    } else if lane.id.offset == 0 {
+        None
+    } else {
+        road.lanes.get(lane.id.offset - 1)
+    };
+    match neighbor {
+        Some(l) => !(l.lane_type == LaneType::Driving && l.dir == lane.dir),
+        None => true,
+    }
+}
+
+fn road_has_sharrows(road: &Road) -> bool {
+    [
+        "cycleway",
+        "cycleway:both",
+        "cycleway:left",
+        "cycleway:right",
+    ]
+    .into_iter()
+    .any(|k| road.osm_tags.is(k, "shared_lane"))
+}
+
+/// Draw shared lane markings (a bike with two chevrons) on the curbside driving lane of roads
+/// tagged as having them.
+pub fn sharrows<P: AsRef<Prerender>>(
+    prerender: &P,
+    map: &Map,
+    lane: &Lane,
+    road: &Road,
+    color: Color,
+) -> GeomBatch {
+    let mut batch = GeomBatch::new();
+    if lane.lane_type != LaneType::Driving
+        || !road_has_sharrows(road)
+        || !is_curbside_driving_lane(lane, road, map.get_config().driving_side)
+    {
+        return batch;
+    }
+
+    for (pt, angle) in lane
+        .lane_center_pts
+        .step_along(Distance::meters(40.0), Distance::meters(10.0))
+    {
+        batch.append(
+            GeomBatch::load_svg(prerender, "system/assets/meters/bike.svg")
+                .color(RewriteColor::ChangeAll(color))
+                .scale(0.05)
+                .centered_on(pt)
+                .rotate(angle.shortest_rotation_towards(Angle::degrees(-90.0))),
+        );
+        for dist in [Distance::meters(1.5), Distance::meters(2.2)] {
+            batch.push(
+                color,
+                chevron(pt.project_away(dist, angle), angle, lane.width),
+            );
+        }
+    }
+    batch
+}
+
+fn chevron(apex: Pt2D, angle: Angle, lane_width: Distance) -> Polygon {
+    let arm = (lane_width * 0.25).min(Distance::meters(1.0));
+    PolyLine::must_new(vec![
+        apex.project_away(arm, angle.rotate_degs(135.0)),
+        apex,
+        apex.project_away(arm, angle.rotate_degs(-135.0)),
+    ])
+    .make_polygons(Distance::meters(0.2))
+}
+
+/// Outline diamonds along a lane reserved for high-occupancy vehicles.
+pub fn hov_diamonds(lane: &Lane) -> Vec<Polygon> {
+    let half_length = (lane.width * 0.8).min(Distance::meters(3.0));
+    let half_width = lane.width * 0.25;
+    let mut results = Vec::new();
+    for (pt, angle) in lane
+        .lane_center_pts
+        .step_along(Distance::meters(40.0), Distance::meters(15.0))
+    {
+        let front = pt.project_away(half_length, angle);
+        results.push(
+            PolyLine::must_new(vec![
+                front,
+                pt.project_away(half_width, angle.rotate_degs(-90.0)),
+                pt.project_away(half_length, angle.rotate_degs(180.0)),
+                pt.project_away(half_width, angle.rotate_degs(90.0)),
+                front,
+            ])
+            .make_polygons(Distance::meters(0.25)),
+        );
+    }
+    results
+}
+
+/// Paint words on a bus lane, between the bus icons.
+pub fn bus_stencils<P: AsRef<Prerender>>(
+    prerender: &P,
+    style: &MarkingStyle,
+    lane: &Lane,
+    color: Color,
+) -> GeomBatch {
+    let mut batch = GeomBatch::new();
+    let word = match style.bus_stencil {
+        Some(word) => word,
+        None => {
+            return batch;
+        }
+    };
+    let stencil = Text::from(Line(word).fg(color)).render_autocropped(prerender.as_ref());
+    // The icons are placed every 30m starting 5m in, so go in between them
+    for (pt, angle) in lane
+        .lane_center_pts
+        .step_along(Distance::meters(30.0), Distance::meters(20.0))
+    {
+        batch.append(
+            stencil
+                .clone()
+                .scale_to_fit_width((lane.width * 0.8).inner_meters())
+                .centered_on(pt)
+                .rotate_around_batch_center(angle.shortest_rotation_towards(Angle::degrees(-90.0))),
+        );
+    }
+    batch
+}
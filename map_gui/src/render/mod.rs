@@ -16,6 +16,7 @@ mod building;
 mod intersection;
 mod lane;
 mod map;
+mod markings;
 mod parking_lot;
 mod road;
 pub mod traffic_signal;
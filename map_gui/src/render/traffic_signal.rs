@@ -8,6 +8,7 @@ use widgetry::{Color, GeomBatch, Line, Prerender, RewriteColor, Text};
 
 use crate::options::TrafficSignalStyle;
 use crate::render::intersection::make_crosswalk;
+use crate::render::markings::ZebraStyle;
 use crate::render::BIG_ARROW_THICKNESS;
 use crate::AppLike;
 
@@ -152,6 +153,7 @@ pub fn draw_signal_stage(
                         app.map().get_t(i.movements[m].members[0]),
                         app.map(),
                         app.cs(),
+                        ZebraStyle::for_app(app),
                     );
                 } else {
                     batch.push(
@@ -171,7 +173,7 @@ pub fn draw_signal_stage(
                 if turn.between_sidewalks() {
                     continue;
                 }
-                match stage.get_priority_of_turn(turn.id, i) {
+                match stage.get_priority_of_turn(turn.id, app.map()) {
                     TurnPriority::Protected => {
                         batch.push(
                             app.cs().signal_protected_turn,
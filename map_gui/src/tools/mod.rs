@@ -46,6 +46,7 @@ mod labels;
 mod minimap;
 mod navigate;
 mod polygon;
+pub mod proposal_gallery;
 mod title_screen;
 mod trip_files;
 mod ui;
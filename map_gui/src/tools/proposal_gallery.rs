@@ -0,0 +1,278 @@
+//! Exchange proposals with other people through a community gallery, instead of emailing JSON
+//! files around. The gallery service lives at `proposal_gallery_url` in the settings, and the API
+//! is small:
+//!
+//! - POST `/publish` with a `Publication` returns the new proposal's ID
+//! - GET `/list?map=...` returns a list of `GalleryEntry` for one map
+//! - GET `/get?id=...` returns the `PermanentMapEdits` of one proposal
+//!
+//! Maps are regenerated from newer OSM data over time, and the format of edits changes, so a
+//! proposal may have been made for a different version of the map. Before downloading,
+//! `check_compatibility` warns about this, and `import_proposal` upgrades the format, moves
+//! commands onto roads and intersections that replaced the original ones, and skips the commands
+//! that no longer apply.
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use abstio::{Manifest, MapName};
+use geom::{Distance, Polygon};
+use map_model::{osm, Map, MapEdits, MigrationReport, PermanentMapEdits};
+use widgetry::{EventCtx, GeomBatch, TextExt, Widget};
+
+const THUMBNAIL_SIZE: f64 = 200.0;
+
+/// What's sent to the gallery when publishing
+#[derive(Serialize, Deserialize)]
+pub struct Publication {
+    pub name: String,
+    pub description: String,
+    pub map: MapName,
+    /// The checksum of the map's data, from `Manifest::map_version`. None if the map isn't part
+    /// of the canonical data, like one imported by the player.
+    pub map_version: Option<String>,
+    /// An SVG of the map, highlighting what the proposal changes
+    pub thumbnail: String,
+    pub edits: PermanentMapEdits,
+}
+
+/// One proposal listed in the gallery. The edits are downloaded separately, once chosen.
+#[derive(Serialize, Deserialize)]
+pub struct GalleryEntry {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub map: MapName,
+    /// Proposals published before these were tracked don't have them
+    #[serde(default)]
+    pub map_version: Option<String>,
+    /// The gallery copies this from the published edits
+    #[serde(default)]
+    pub edits_version: Option<usize>,
+    pub thumbnail: String,
+}
+
+/// How a proposal in the gallery relates to the map it would be loaded into
+pub enum Compatibility {
+    /// Made for exactly this version of the map
+    Compatible,
+    /// Made for a different or unknown version of the map. Loading it will try to migrate, but
+    /// some changes might be lost. The messages describe why.
+    NeedsMigration(Vec<String>),
+    /// Can't be loaded at all
+    Incompatible(String),
+}
+
+impl Publication {
+    pub fn new(map: &Map, name: String, description: String) -> Publication {
+        let mut edits = map.get_edits().to_permanent(map);
+        edits.edits_name = name.clone();
+        Publication {
+            name,
+            description,
+            map: map.get_name().clone(),
+            map_version: Manifest::load().map_version(map.get_name()),
+            thumbnail: make_thumbnail(map),
+            edits,
+        }
+    }
+}
+
+impl GalleryEntry {
+    /// Pass in `Manifest::load()`, to compare the versions of the map
+    pub fn check_compatibility(&self, map: &Map, manifest: &Manifest) -> Compatibility {
+        if &self.map != map.get_name() {
+            return Compatibility::Incompatible(format!(
+                "This proposal is for {}, not {}",
+                self.map.describe(),
+                map.get_name().describe()
+            ));
+        }
+
+        let mut problems = Vec::new();
+        match self.edits_version {
+            Some(v) if v > PermanentMapEdits::CURRENT_VERSION => {
+                return Compatibility::Incompatible(
+                    "This proposal was made with a newer version of A/B Street. Update to load it."
+                        .to_string(),
+                );
+            }
+            Some(v) if v < PermanentMapEdits::CURRENT_VERSION => {
+                problems.push("This proposal was made with an older version of A/B Street".into());
+            }
+            Some(_) => {}
+            None => {
+                problems.push("The gallery doesn't know what version made this proposal".into());
+            }
+        }
+        match (
+            self.map_version.as_ref(),
+            manifest.map_version(map.get_name()),
+        ) {
+            (Some(theirs), Some(ours)) if theirs == &ours => {}
+            (Some(_), Some(_)) => {
+                problems.push("This proposal was made for a different version of the map".into());
+            }
+            _ => {
+                problems
+                    .push("The map this proposal was made for might be a different version".into());
+            }
+        }
+
+        if problems.is_empty() {
+            Compatibility::Compatible
+        } else {
+            Compatibility::NeedsMigration(problems)
+        }
+    }
+}
+
+/// A proposal downloaded from the gallery, migrated to the current map
+pub struct ImportedProposal {
+    pub edits: MapEdits,
+    /// True if the proposal was saved in an older format and had to be upgraded
+    pub upgraded_format: bool,
+    /// Which commands were moved onto different roads or intersections, and which couldn't be
+    /// transferred to this map
+    pub migration: MigrationReport,
+}
+
+impl ImportedProposal {
+    /// Describes what was lost or changed during migration, if anything
+    pub fn describe_migration(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.upgraded_format {
+            lines.push("This proposal was upgraded from an older format.".to_string());
+        }
+        if !self.migration.re_anchored.is_empty() {
+            lines.push(format!(
+                "{} changes were moved onto the roads or intersections that replaced them:",
+                self.migration.re_anchored.len()
+            ));
+            lines.extend(
+                self.migration
+                    .re_anchored
+                    .iter()
+                    .map(|x| format!("- {}", x)),
+            );
+        }
+        if !self.migration.failed.is_empty() {
+            lines.push(format!(
+                "{} changes don't apply to this version of the map, and were skipped:",
+                self.migration.failed.len()
+            ));
+            lines.extend(self.migration.failed.iter().map(|err| format!("- {}", err)));
+        }
+        lines
+    }
+}
+
+/// Parses a proposal from `download`, upgrading old formats and skipping commands that
+/// no longer match the map. Fails if nothing applies to this map.
+pub fn import_proposal(map: &Map, bytes: &[u8]) -> Result<ImportedProposal> {
+    let (perma, upgraded_format) = PermanentMapEdits::from_json_bytes(bytes, map)?;
+    if map.get_name().city != perma.map_name.city {
+        bail!(
+            "This proposal is for {:?}, but this map is {:?}",
+            perma.map_name.city,
+            map.get_name().city
+        );
+    }
+    let (edits, migration) = perma.into_edits_migrating(map);
+    if edits.commands.is_empty() && !migration.failed.is_empty() {
+        bail!(
+            "None of the changes in this proposal apply to this version of the map. The first \
+             problem: {}",
+            migration.failed[0]
+        );
+    }
+    Ok(ImportedProposal {
+        edits,
+        upgraded_format,
+        migration,
+    })
+}
+
+/// Returns the new proposal's ID
+pub async fn publish(gallery_url: String, publication: Publication) -> Result<String> {
+    abstio::http_post(
+        format!("{}/publish", gallery_url),
+        abstutil::to_json(&publication),
+    )
+    .await
+}
+
+/// Lists the proposals published for one map
+pub async fn list(gallery_url: String, map: MapName) -> Result<Vec<GalleryEntry>> {
+    let bytes = abstio::http_get(format!("{}/list?map={}", gallery_url, map.as_filename())).await?;
+    let mut entries = abstutil::from_json::<Vec<GalleryEntry>>(&bytes)?;
+    // The service should only send proposals for this map, but double-check
+    entries.retain(|entry| entry.map == map);
+    Ok(entries)
+}
+
+/// Downloads the raw edits of one proposal. Pass them to `import_proposal`.
+pub async fn download(gallery_url: String, id: String) -> Result<Vec<u8>> {
+    abstio::http_get(format!("{}/get?id={}", gallery_url, id)).await
+}
+
+/// A small SVG of the map's boundary and major roads, with edited roads and intersections in red
+pub fn make_thumbnail(map: &Map) -> String {
+    let bounds = map.get_bounds();
+    let scale = THUMBNAIL_SIZE / bounds.width().max(bounds.height());
+    // The stroke width, in map space
+    let thickness = Distance::meters(1.5 / scale);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0:.0}\" height=\"{1:.0}\" \
+         viewBox=\"0 0 {0:.0} {1:.0}\">",
+        bounds.width() * scale,
+        bounds.height() * scale
+    );
+    let mut add = |polygon: &Polygon, color: &str| {
+        svg.push_str("<path d=\"");
+        for (idx, pt) in polygon.get_outer_ring().points().iter().enumerate() {
+            let cmd = if idx == 0 { "M" } else { "L" };
+            write!(
+                svg,
+                "{}{:.1} {:.1} ",
+                cmd,
+                (pt.x() - bounds.min_x) * scale,
+                (pt.y() - bounds.min_y) * scale
+            )
+            .unwrap();
+        }
+        write!(svg, "Z\" fill=\"{}\"/>", color).unwrap();
+    };
+
+    add(map.get_boundary_polygon(), "#EEEEEE");
+    for r in map.all_roads() {
+        if r.get_rank() != osm::RoadRank::Local {
+            add(&r.center_pts.make_polygons(thickness), "#999999");
+        }
+    }
+    let edits = map.get_edits();
+    for r in edits.original_roads.keys() {
+        add(
+            &map.get_r(*r).center_pts.make_polygons(thickness * 2.0),
+            "#FF0000",
+        );
+    }
+    for i in edits.original_intersections.keys() {
+        add(&map.get_i(*i).polygon, "#FF0000");
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+pub fn thumbnail_widget(ctx: &EventCtx, svg: &str) -> Widget {
+    match GeomBatch::parse_svg_bytes(svg.as_bytes()) {
+        Ok(batch) => batch.scale_to_fit_width(THUMBNAIL_SIZE).into_widget(ctx),
+        Err(err) => {
+            warn!("Bad thumbnail: {}", err);
+            "(no preview)".text_widget(ctx)
+        }
+    }
+}
@@ -259,6 +259,9 @@ pub fn color_for_mode(app: &dyn AppLike, m: TripMode) -> Color {
         TripMode::Bike => app.cs().unzoomed_bike,
         TripMode::Transit => app.cs().unzoomed_bus,
         TripMode::Drive => app.cs().unzoomed_car,
+        TripMode::Ridehail => app.cs().unzoomed_ridehail,
+        TripMode::Micromobility => app.cs().unzoomed_micromobility,
+        TripMode::ParkAndRide => app.cs().unzoomed_park_and_ride,
     }
 }
 
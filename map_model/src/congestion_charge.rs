@@ -0,0 +1,78 @@
+//! A congestion charge tolls drivers entering a cordon, like in London or Stockholm. The charge
+//! can vary through the day. It's part of the map edits, so the pathfinder can account for it and
+//! proposals can be compared against the same demand.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Duration, Polygon, Time};
+
+use crate::{Map, RoadID};
+
+/// Cars pay the charge every time they cross into the cordon. Starting a trip inside is free.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CongestionCharge {
+    /// The roads inside the cordon
+    pub roads: BTreeSet<RoadID>,
+    /// Sorted by time. Each charge lasts until the next one starts. Before the first, entering is
+    /// free.
+    pub schedule: Vec<(Time, f64)>,
+}
+
+impl CongestionCharge {
+    /// Charges `peak` between `start` and `end` every day, and `off_peak` otherwise
+    pub fn daily(
+        roads: BTreeSet<RoadID>,
+        start: Time,
+        end: Time,
+        peak: f64,
+        off_peak: f64,
+    ) -> Self {
+        CongestionCharge {
+            roads,
+            schedule: vec![
+                (Time::START_OF_DAY, off_peak),
+                (start, peak),
+                (end, off_peak),
+            ],
+        }
+    }
+
+    /// All roads whose center lies in the polygon are inside the cordon
+    pub fn from_polygon(map: &Map, polygon: &Polygon, schedule: Vec<(Time, f64)>) -> Self {
+        CongestionCharge {
+            roads: map
+                .all_roads()
+                .iter()
+                .filter(|r| polygon.contains_pt(r.center_pts.middle()))
+                .map(|r| r.id)
+                .collect(),
+            schedule,
+        }
+    }
+
+    /// How much entering the cordon costs at some time. Multi-day simulations repeat the schedule.
+    pub fn toll_at(&self, time: Time) -> f64 {
+        let time = Time::START_OF_DAY
+            + Duration::seconds(time.inner_seconds() % Duration::hours(24).inner_seconds());
+        self.schedule
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= time)
+            .map(|(_, toll)| *toll)
+            .unwrap_or(0.0)
+    }
+
+    pub fn peak_toll(&self) -> f64 {
+        self.schedule
+            .iter()
+            .map(|(_, toll)| *toll)
+            .fold(0.0, f64::max)
+    }
+
+    /// Does moving from one road to the next cross into the cordon?
+    pub fn charges_entering(&self, from: RoadID, to: RoadID) -> bool {
+        !self.roads.contains(&from) && self.roads.contains(&to)
+    }
+}
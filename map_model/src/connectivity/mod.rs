@@ -8,6 +8,7 @@ use petgraph::graphmap::DiGraphMap;
 use abstutil::PriorityQueueItem;
 use geom::Duration;
 
+pub(crate) use self::walking::FrequencyBasedTransit;
 pub use self::walking::{all_walking_costs_from, WalkingOptions};
 pub use crate::pathfind::{vehicle_cost, WalkingNode};
 use crate::{BuildingID, DirectedRoadID, IntersectionID, LaneID, Map, PathConstraints};
@@ -1,17 +1,21 @@
 use std::collections::{BinaryHeap, HashMap, HashSet};
 
 use abstutil::{MultiMap, PriorityQueueItem};
-use geom::{Duration, Speed};
+use geom::{Duration, Speed, Time};
 
 use crate::connectivity::Spot;
 use crate::pathfind::{zone_cost, WalkingNode};
-use crate::{BuildingID, Lane, LaneType, Map, PathConstraints, PathStep};
+use crate::{BuildingID, Lane, LaneID, LaneType, Map, PathConstraints, PathStep, TransitStopID};
 
 #[derive(Clone)]
 pub struct WalkingOptions {
     /// If true, allow walking on shoulders.
     pub allow_shoulders: bool,
     pub walking_speed: Speed,
+    /// If set, also ride transit, departing around this time. No vehicles are simulated; waiting
+    /// for a route costs half of its headway, and riding costs the free-flow driving time plus
+    /// dwelling at each stop.
+    pub transit_departure: Option<Time>,
 }
 
 impl WalkingOptions {
@@ -19,6 +23,7 @@ impl WalkingOptions {
         WalkingOptions {
             allow_shoulders: true,
             walking_speed: WalkingOptions::default_speed(),
+            transit_departure: None,
         }
     }
 
@@ -111,6 +116,10 @@ pub fn all_walking_costs_from(
         sidewalk_to_bldgs.insert(b.sidewalk(), b.id);
     }
 
+    let transit = opts
+        .transit_departure
+        .map(|time| map.get_pathfinder().frequency_based_transit(map, time));
+
     let mut results = HashMap::new();
 
     let mut visited_nodes = HashSet::new();
@@ -125,7 +134,45 @@ pub fn all_walking_costs_from(
 
         let (r, is_dst_i) = match current.value {
             WalkingNode::SidewalkEndpoint(r, is_dst_i) => (r, is_dst_i),
-            _ => unreachable!(),
+            WalkingNode::RideTransit(ts) => {
+                // Board every route serving this stop, and get off anywhere downstream
+                for (wait, rides) in transit.as_ref().unwrap().boardings(ts) {
+                    for (ts2, ride) in rides {
+                        let cost = current.cost + *wait + *ride;
+                        if cost > time_limit {
+                            continue;
+                        }
+                        let pos = map.get_ts(*ts2).sidewalk_pos;
+                        let lane = map.get_l(pos.lane());
+                        for b in sidewalk_to_bldgs.get(lane.id) {
+                            let dist =
+                                (map.get_b(*b).sidewalk_pos.dist_along() - pos.dist_along()).abs();
+                            insert_min(
+                                &mut results,
+                                *b,
+                                cost + dist / opts.walking_speed,
+                                time_limit,
+                            );
+                        }
+                        let dr = lane.get_directed_parent();
+                        queue.push(PriorityQueueItem {
+                            cost: cost + pos.dist_along() / opts.walking_speed,
+                            value: WalkingNode::SidewalkEndpoint(dr, false),
+                        });
+                        queue.push(PriorityQueueItem {
+                            cost: cost + (lane.length() - pos.dist_along()) / opts.walking_speed,
+                            value: WalkingNode::SidewalkEndpoint(dr, true),
+                        });
+                        // Transfer to another route here
+                        queue.push(PriorityQueueItem {
+                            cost,
+                            value: WalkingNode::RideTransit(*ts2),
+                        });
+                    }
+                }
+                continue;
+            }
+            WalkingNode::LeaveMap(_) => unreachable!(),
         };
         let lane = map.get_l(r.must_get_sidewalk(map));
         // Cross the lane
@@ -155,8 +202,20 @@ pub fn all_walking_costs_from(
                         bldg_dist_along
                     };
                     let bldg_cost = current.cost + dist_to_bldg / speed;
-                    if bldg_cost <= time_limit {
-                        results.insert(*b, bldg_cost);
+                    insert_min(&mut results, *b, bldg_cost, time_limit);
+                }
+                if let Some(ref transit) = transit {
+                    for ts in transit.stops_per_sidewalk.get(lane.id) {
+                        let stop_dist_along = map.get_ts(*ts).sidewalk_pos.dist_along();
+                        let dist_to_stop = if is_dst_i {
+                            sidewalk_len - stop_dist_along
+                        } else {
+                            stop_dist_along
+                        };
+                        queue.push(PriorityQueueItem {
+                            cost: current.cost + dist_to_stop / speed,
+                            value: WalkingNode::RideTransit(*ts),
+                        });
                     }
                 }
 
@@ -190,3 +249,93 @@ pub fn all_walking_costs_from(
 
     results
 }
+
+fn insert_min(
+    results: &mut HashMap<BuildingID, Duration>,
+    b: BuildingID,
+    cost: Duration,
+    time_limit: Duration,
+) {
+    if cost > time_limit {
+        return;
+    }
+    let entry = results.entry(b).or_insert(cost);
+    if cost < *entry {
+        *entry = cost;
+    }
+}
+
+/// A quick approximation of transit for planning, using only the schedules. This is the classic
+/// frequency-based assignment, where the expected wait for a route is half of its headway. Only
+/// isochrones use this; the route planner still finds paths riding the simulated vehicles.
+pub(crate) struct FrequencyBasedTransit {
+    stops_per_sidewalk: MultiMap<LaneID, TransitStopID>,
+    /// For each stop, every route that can be boarded there: the expected wait, and how long it
+    /// takes to ride to each later stop
+    boardings: HashMap<TransitStopID, Vec<(Duration, Vec<(TransitStopID, Duration)>)>>,
+}
+
+impl FrequencyBasedTransit {
+    pub(crate) fn new(map: &Map, departure: Time) -> FrequencyBasedTransit {
+        let mut stops_per_sidewalk = MultiMap::new();
+        for ts in map.all_transit_stops().values() {
+            stops_per_sidewalk.insert(ts.sidewalk_pos.lane(), ts.id);
+        }
+
+        let mut boardings: HashMap<TransitStopID, Vec<_>> = HashMap::new();
+        for route in map.all_transit_routes() {
+            let headway = match route.headway_at(departure) {
+                Some(x) => x,
+                None => {
+                    continue;
+                }
+            };
+            let paths = match route.all_paths(map) {
+                Ok(paths) => paths,
+                Err(_) => {
+                    continue;
+                }
+            };
+            // How long after leaving the first stop does the vehicle reach each stop? Entry i of
+            // paths leads to stop i.
+            let mut offsets = vec![Duration::ZERO];
+            for (idx, path) in paths.iter().enumerate().take(route.stops.len()).skip(1) {
+                let stop = map.get_ts(route.stops[idx - 1]);
+                let dwell = if stop.closed {
+                    Duration::ZERO
+                } else {
+                    route.boarding.union(stop.boarding).dwell_time(1, 1)
+                };
+                offsets.push(offsets[idx - 1] + dwell + path.estimate_duration(map, None));
+            }
+            for (idx1, ts1) in route.stops.iter().enumerate() {
+                if map.get_ts(*ts1).closed {
+                    continue;
+                }
+                let rides: Vec<(TransitStopID, Duration)> = route
+                    .stops
+                    .iter()
+                    .enumerate()
+                    .skip(idx1 + 1)
+                    .filter(|(_, ts2)| !map.get_ts(**ts2).closed)
+                    .map(|(idx2, ts2)| (*ts2, offsets[idx2] - offsets[idx1]))
+                    .collect();
+                if !rides.is_empty() {
+                    boardings
+                        .entry(*ts1)
+                        .or_insert_with(Vec::new)
+                        .push((headway / 2.0, rides));
+                }
+            }
+        }
+
+        FrequencyBasedTransit {
+            stops_per_sidewalk,
+            boardings,
+        }
+    }
+
+    fn boardings(&self, ts: TransitStopID) -> &[(Duration, Vec<(TransitStopID, Duration)>)] {
+        self.boardings.get(&ts).map(|x| x.as_slice()).unwrap_or(&[])
+    }
+}
@@ -4,11 +4,11 @@ use abstutil::Timer;
 use geom::{Distance, HashablePt2D, Line};
 use osm2streets::{osm, InputRoad};
 
-use crate::make::{match_points_to_lanes, snap_driveway, trim_path};
+use crate::make::{driveway_query_pt, match_points_to_lanes, snap_driveway, trim_path};
 use crate::{
     connectivity, BuildingID, ControlStopSign, ControlTrafficSignal, EditCmd, EditEffects,
     EditIntersectionControl, IntersectionControl, IntersectionID, LaneSpec, Map, MapEdits,
-    Movement, ParkingLotID, PathConstraints, Pathfinder, RoadID, Zone,
+    Movement, ParkingLotID, PathConstraints, Pathfinder, Position, RoadID, Zone,
 };
 
 impl Map {
@@ -110,7 +110,13 @@ impl Map {
             for id in &effects.changed_roads {
                 let stops = self.get_r(*id).transit_stops.clone();
                 for s in stops {
-                    let sidewalk_pos = self.get_ts(s).sidewalk_pos;
+                    let ts = self.get_ts(s);
+                    let sidewalk_pos = ts.sidewalk_pos;
+                    if ts.is_train_stop {
+                        // Tracks are often on a different road than the platform's sidewalk, and
+                        // editing them is disallowed anyway
+                        continue;
+                    }
                     // Must exist, because we aren't allowed to orphan a bus stop.
                     let driving_lane = self
                         .get_r(*id)
@@ -122,6 +128,30 @@ impl Map {
                     self.transit_stops.get_mut(&s).unwrap().driving_pos = driving_pos;
                 }
             }
+
+            // Ferry terminals just need some sidewalk on the same road
+            for idx in 0..self.ferry_terminals.len() {
+                let terminal = &self.ferry_terminals[idx];
+                let road = self.get_r(terminal.sidewalk_pos.lane().road);
+                if !effects.changed_roads.contains(&road.id) {
+                    continue;
+                }
+                if road
+                    .lanes
+                    .get(terminal.sidewalk_pos.lane().offset)
+                    .map(|l| l.is_walkable())
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+                if let Some(lane) = road.lanes.iter().find(|l| l.is_walkable()) {
+                    let pl = &lane.lane_center_pts;
+                    if let Some((dist, _)) = pl.dist_along_of_point(pl.project_pt(terminal.pt)) {
+                        let pos = Position::new(lane.id, dist);
+                        self.ferry_terminals[idx].sidewalk_pos = pos;
+                    }
+                }
+            }
         }
 
         new_edits.update_derived(self);
@@ -150,6 +180,7 @@ impl Map {
             .extend(more_changed_intersections);
 
         self.recalculate_road_to_buildings();
+        self.recalculate_has_hov_lanes();
 
         effects
     }
@@ -161,6 +192,7 @@ impl Map {
             return;
         }
 
+        self.take_lazy_pathfinder();
         let mut pathfinder = std::mem::replace(&mut self.pathfinder, Pathfinder::empty());
         pathfinder.apply_edits(self, timer);
         self.pathfinder = pathfinder;
@@ -205,6 +237,19 @@ impl EditCmd {
                 road.crossings = new.crossings.clone();
                 road.turn_restrictions = new.turn_restrictions.clone();
                 road.complicated_turn_restrictions = new.complicated_turn_restrictions.clone();
+                road.hov_lanes = new.hov_lanes.clone();
+                // Whoever inserts, removes, or moves lanes should shift the offsets with
+                // HovLanes::lane_inserted and friends. Lane edits might still leave the
+                // reservation pointing at lanes that're gone or aren't for driving anymore.
+                if let Some(ref mut hov) = road.hov_lanes {
+                    let lanes = &road.lanes;
+                    hov.lanes.retain(|offset| {
+                        lanes.get(*offset).map(|l| l.is_driving()).unwrap_or(false)
+                    });
+                    if hov.lanes.is_empty() {
+                        road.hov_lanes = None;
+                    }
+                }
 
                 effects.changed_roads.insert(road.id);
                 // TODO If lanes_ltr didn't change, can we skip some of this?
@@ -236,6 +281,18 @@ impl EditCmd {
                     return;
                 }
                 map.intersections[i.0].modal_filter = new.modal_filter.clone();
+                map.intersections[i.0].allow_u_turns = new.allow_u_turns;
+                map.intersections[i.0].protected_corners = new.protected_corners;
+                let u_turns_changed = old.allow_u_turns != new.allow_u_turns
+                    && old.control != EditIntersectionControl::Closed
+                    && new.control != EditIntersectionControl::Closed;
+                // The turns stay the same, but bikes take a different path through them
+                let corners_changed = old.protected_corners != new.protected_corners
+                    && old.control != EditIntersectionControl::Closed
+                    && new.control != EditIntersectionControl::Closed;
+                if u_turns_changed || corners_changed {
+                    recalculate_turns(*i, map, effects);
+                }
 
                 map.stop_signs.remove(i);
                 map.traffic_signals.remove(i);
@@ -250,10 +307,16 @@ impl EditCmd {
                         if old.control == EditIntersectionControl::Closed {
                             recalculate_turns(*i, map, effects);
                         }
-                        map.traffic_signals.insert(
-                            *i,
-                            ControlTrafficSignal::import(raw_ts.clone(), *i, map).unwrap(),
-                        );
+                        let ts = match ControlTrafficSignal::import(raw_ts.clone(), *i, map) {
+                            Ok(ts) => ts,
+                            // The movements changed, so the old timing can't be used anymore
+                            Err(err) if u_turns_changed => {
+                                warn!("Resetting traffic signal at {}: {}", i, err);
+                                ControlTrafficSignal::new(map, *i)
+                            }
+                            Err(err) => panic!("{}", err),
+                        };
+                        map.traffic_signals.insert(*i, ts);
                     }
                     EditIntersectionControl::Closed => {
                         map.intersections[i.0].control = IntersectionControl::Construction;
@@ -273,6 +336,27 @@ impl EditCmd {
             EditCmd::ChangeRouteSchedule { id, new, .. } => {
                 map.transit_routes[id.0].spawn_times = new.clone();
             }
+            EditCmd::ChangeRouteBoarding { id, new, .. } => {
+                map.transit_routes[id.0].boarding = *new;
+            }
+            EditCmd::ChangeStopBoarding { id, new, .. } => {
+                map.transit_stops.get_mut(id).unwrap().boarding = *new;
+            }
+            EditCmd::ChangeRouteStops { id, new, .. } => {
+                map.transit_routes[id.0].stops = new.clone();
+            }
+            EditCmd::ChangeStopClosed { id, new, .. } => {
+                map.transit_stops.get_mut(id).unwrap().closed = *new;
+            }
+            EditCmd::ChangeCongestionCharge { new, .. } => {
+                map.congestion_charge = new.clone();
+            }
+            EditCmd::ChangeParkingPricing { new, .. } => {
+                map.parking_pricing = new.clone();
+            }
+            EditCmd::ChangeLoadingZones { new, .. } => {
+                map.loading_zones = new.clone();
+            }
         }
     }
 
@@ -293,6 +377,35 @@ impl EditCmd {
                 old: new,
                 new: old,
             },
+            EditCmd::ChangeRouteBoarding { id, old, new } => EditCmd::ChangeRouteBoarding {
+                id,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeStopBoarding { id, old, new } => EditCmd::ChangeStopBoarding {
+                id,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeRouteStops { id, old, new } => EditCmd::ChangeRouteStops {
+                id,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeStopClosed { id, old, new } => EditCmd::ChangeStopClosed {
+                id,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeCongestionCharge { old, new } => {
+                EditCmd::ChangeCongestionCharge { old: new, new: old }
+            }
+            EditCmd::ChangeParkingPricing { old, new } => {
+                EditCmd::ChangeParkingPricing { old: new, new: old }
+            }
+            EditCmd::ChangeLoadingZones { old, new } => {
+                EditCmd::ChangeLoadingZones { old: new, new: old }
+            }
         }
     }
 }
@@ -484,16 +597,17 @@ fn recalculate_intersection_polygon(
 /// Recalculate the driveways of some buildings after map edits.
 fn fix_building_driveways(map: &mut Map, input: Vec<BuildingID>, effects: &mut EditEffects) {
     // TODO Copying from make/buildings.rs
-    let mut center_per_bldg: BTreeMap<BuildingID, HashablePt2D> = BTreeMap::new();
+    let mut center_per_bldg: BTreeMap<BuildingID, (HashablePt2D, HashablePt2D)> = BTreeMap::new();
     let mut query: HashSet<HashablePt2D> = HashSet::new();
     for id in input {
-        let center = map.get_b(id).polygon.center().to_hashable();
-        center_per_bldg.insert(id, center);
-        query.insert(center);
+        let center = map.get_b(id).polygon.center();
+        let query_pt = driveway_query_pt(map, center).to_hashable();
+        center_per_bldg.insert(id, (center.to_hashable(), query_pt));
+        query.insert(query_pt);
     }
 
     let sidewalk_buffer = Distance::meters(7.5);
-    let mut sidewalk_pts = match_points_to_lanes(
+    let sidewalk_pts = match_points_to_lanes(
         map,
         query,
         |l| l.is_walkable(),
@@ -504,8 +618,9 @@ fn fix_building_driveways(map: &mut Map, input: Vec<BuildingID>, effects: &mut E
         &mut Timer::throwaway(),
     );
 
-    for (id, bldg_center) in center_per_bldg {
-        match sidewalk_pts.remove(&bldg_center).and_then(|pos| {
+    for (id, (bldg_center, query_pt)) in center_per_bldg {
+        // Several buildings in a site can share the same query point
+        match sidewalk_pts.get(&query_pt).cloned().and_then(|pos| {
             Line::new(bldg_center.to_pt2d(), pos.pt(map))
                 .map(|l| (pos, trim_path(&map.get_b(id).polygon, l)))
                 .ok()
@@ -0,0 +1,280 @@
+//! When a map is re-imported from newer OSM data, ways get split or merged differently and
+//! intersections get consolidated, so `PermanentEditCmd`s referring to the old `OriginalRoad` or
+//! OSM node may not match anything. Edited roads and intersections are saved with their geometry
+//! as anchors, so these commands can be moved onto whatever now occupies the same place. Edits
+//! saved before anchors existed can still follow a road split along the same OSM way.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, LonLat, PolyLine};
+
+use crate::edits::{EditCmd, EditRoad, MapEdits};
+use crate::{osm, Crossing, IntersectionID, Map, OriginalRoad, RoadFilter, RoadID};
+
+/// How far apart the old and new center lines can be, to consider them the same road
+const ROAD_THRESHOLD: Distance = Distance::const_meters(5.0);
+/// How far an intersection can drift and still be considered the same
+const INTERSECTION_THRESHOLD: Distance = Distance::const_meters(20.0);
+/// How different the direction of the old and new roads can be, in degrees
+const ANGLE_THRESHOLD: f64 = 30.0;
+
+/// Where the edited objects were when the edits were saved
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct EditAnchors {
+    /// The center line of each edited road
+    pub roads: Vec<(OriginalRoad, Vec<LonLat>)>,
+    /// The center of each edited intersection
+    pub intersections: Vec<(osm::NodeID, LonLat)>,
+}
+
+/// What happened to edits that didn't match the current map directly
+#[derive(Default)]
+pub struct MigrationReport {
+    /// Commands moved onto different roads or intersections
+    pub re_anchored: Vec<String>,
+    /// Commands that couldn't be transferred at all, and why
+    pub failed: Vec<String>,
+}
+
+impl EditAnchors {
+    pub fn new(edits: &MapEdits, map: &Map) -> EditAnchors {
+        let gps_bounds = map.get_gps_bounds();
+        let mut anchors = EditAnchors::default();
+        for r in edits.original_roads.keys() {
+            let road = map.get_r(*r);
+            anchors.roads.push((
+                road.orig_id,
+                gps_bounds.convert_back(road.center_pts.points()),
+            ));
+        }
+        for i in edits.original_intersections.keys() {
+            let intersection = map.get_i(*i);
+            anchors.intersections.push((
+                intersection.orig_id,
+                intersection.polygon.center().to_gps(gps_bounds),
+            ));
+        }
+        anchors
+    }
+
+    /// Finds the roads that now cover an edited road, after `find_r_by_osm_id` failed. The roads
+    /// returned all point the same direction as the original.
+    pub(crate) fn find_roads(&self, orig: OriginalRoad, map: &Map) -> Result<Vec<RoadID>> {
+        if let Some(anchor) = self.road_line(orig, map) {
+            let roads: Vec<RoadID> = map
+                .all_roads()
+                .iter()
+                .filter(|r| overlaps(&anchor, &r.center_pts) || overlaps(&r.center_pts, &anchor))
+                .map(|r| r.id)
+                .collect();
+            if !roads.is_empty() {
+                return Ok(roads);
+            }
+        }
+        follow_way(orig, map)
+    }
+
+    /// Where an edited road was, if the edits say
+    fn road_line(&self, orig: OriginalRoad, map: &Map) -> Option<PolyLine> {
+        self.roads
+            .iter()
+            .find(|(r, _)| *r == orig)
+            .and_then(|(_, pts)| PolyLine::new(map.get_gps_bounds().convert(pts)).ok())
+    }
+
+    /// Finds the intersection now at the same place as an edited one, after `find_i_by_osm_id`
+    /// failed
+    pub(crate) fn find_intersection(&self, orig: osm::NodeID, map: &Map) -> Result<IntersectionID> {
+        let pt = match self.intersections.iter().find(|(i, _)| *i == orig) {
+            Some((_, gps)) => map.localise_lon_lat_to_map(*gps),
+            None => bail!("{} is gone, and the edits don't say where it was", orig),
+        };
+        let i = map.find_i_by_pt2d(pt)?;
+        if map.get_i(i).polygon.center().dist_to(pt) > INTERSECTION_THRESHOLD {
+            bail!("{} is gone, and no intersection is at the same place", orig);
+        }
+        Ok(i)
+    }
+}
+
+/// True if the middle of `road` lies along `anchor`, pointing the same way
+fn overlaps(anchor: &PolyLine, road: &PolyLine) -> bool {
+    let (middle, angle) = road.must_dist_along(road.length() / 2.0);
+    let projected = anchor.project_pt(middle);
+    if projected.dist_to(middle) > ROAD_THRESHOLD {
+        return false;
+    }
+    match anchor.dist_along_of_point(projected) {
+        Some((_, anchor_angle)) => anchor_angle.approx_eq(angle, ANGLE_THRESHOLD),
+        None => false,
+    }
+}
+
+/// Without geometry, follow roads from the same OSM way between the original endpoints. This
+/// handles ways split by new intersections.
+fn follow_way(orig: OriginalRoad, map: &Map) -> Result<Vec<RoadID>> {
+    let mut current = map
+        .find_i_by_osm_id(orig.i1)
+        .map_err(|_| anyhow!("{} is gone, and so is the start of it", orig))?;
+    let mut roads = Vec::new();
+    while map.get_i(current).orig_id != orig.i2 {
+        let next = map.get_i(current).roads.iter().find(|r| {
+            let road = map.get_r(**r);
+            road.orig_id.osm_way_id == orig.osm_way_id
+                && road.src_i == current
+                && !roads.contains(*r)
+        });
+        match next {
+            Some(r) => {
+                roads.push(*r);
+                current = map.get_r(*r).dst_i;
+            }
+            None => bail!(
+                "{} is gone, and the same way doesn't connect its ends",
+                orig
+            ),
+        }
+    }
+    Ok(roads)
+}
+
+/// Moves a distance along the original road onto one of the roads replacing it. None if the
+/// original road's geometry is unknown, or the position isn't along this piece.
+fn move_dist(anchor: Option<&PolyLine>, dist: Distance, road: &PolyLine) -> Option<Distance> {
+    let (pt, _) = anchor?.dist_along(dist).ok()?;
+    let projected = road.project_pt(pt);
+    if projected.dist_to(pt) > ROAD_THRESHOLD {
+        return None;
+    }
+    let (dist, _) = road.dist_along_of_point(projected)?;
+    if dist == Distance::ZERO || dist == road.length() {
+        return None;
+    }
+    Some(dist)
+}
+
+/// Keeps the crossings and modal filter that lie along one piece of a split road, at their new
+/// distances
+fn move_onto(edit: &EditRoad, anchor: Option<&PolyLine>, road: &PolyLine) -> EditRoad {
+    let mut moved = edit.clone();
+    moved.crossings = edit
+        .crossings
+        .iter()
+        .filter_map(|crossing| {
+            move_dist(anchor, crossing.dist, road).map(|dist| Crossing {
+                kind: crossing.kind,
+                dist,
+            })
+        })
+        .collect();
+    moved.modal_filter = edit.modal_filter.as_ref().and_then(|filter| {
+        move_dist(anchor, filter.dist, road).map(|dist| RoadFilter::new(dist, filter.filter_type))
+    });
+    moved
+}
+
+/// Moves a `ChangeRoad` command onto the roads that now cover the original one. Crossings and
+/// modal filters are re-projected onto whichever piece they fall along.
+pub(crate) fn migrate_road(
+    orig: OriginalRoad,
+    new: EditRoad,
+    old: EditRoad,
+    anchors: &EditAnchors,
+    map: &Map,
+    report: &mut MigrationReport,
+) -> Result<Vec<EditCmd>> {
+    let anchor = anchors.road_line(orig, map);
+    let mut cmds = Vec::new();
+    let mut num_crossings = 0;
+    let mut has_filter = false;
+    for r in anchors.find_roads(orig, map)? {
+        let road = map.get_r(r);
+        // Like into_cmd, don't guess what edits mean when the lanes changed
+        if road.lanes.len() != old.lanes_ltr.len() {
+            report.failed.push(format!(
+                "{} is now partly {}, but it has {} lanes instead of {}",
+                orig,
+                road.orig_id,
+                road.lanes.len(),
+                old.lanes_ltr.len()
+            ));
+            continue;
+        }
+        let moved = move_onto(&new, anchor.as_ref(), &road.center_pts);
+        num_crossings += moved.crossings.len();
+        has_filter |= moved.modal_filter.is_some();
+        cmds.push(EditCmd::ChangeRoad {
+            r,
+            new: moved,
+            old: move_onto(&old, anchor.as_ref(), &road.center_pts),
+        });
+    }
+    if cmds.is_empty() {
+        bail!(
+            "{} is gone, and no road with the same lanes replaced it",
+            orig
+        );
+    }
+    if num_crossings < new.crossings.len() {
+        report.failed.push(format!(
+            "{} of the crossings on {} don't lie along the roads replacing it",
+            new.crossings.len() - num_crossings,
+            orig
+        ));
+    }
+    if new.modal_filter.is_some() && !has_filter {
+        report.failed.push(format!(
+            "The modal filter on {} doesn't lie along the roads replacing it",
+            orig
+        ));
+    }
+    report.re_anchored.push(format!(
+        "{} moved onto {}",
+        orig,
+        cmds.iter()
+            .map(|cmd| match cmd {
+                EditCmd::ChangeRoad { r, .. } => map.get_r(*r).orig_id.to_string(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    Ok(cmds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::Pt2D;
+
+    fn line(x1: f64, y1: f64, x2: f64, y2: f64) -> PolyLine {
+        PolyLine::must_new(vec![Pt2D::new(x1, y1), Pt2D::new(x2, y2)])
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let anchor = line(0.0, 0.0, 100.0, 0.0);
+        // Half of a split road, drawn slightly differently
+        assert!(overlaps(&anchor, &line(50.0, 2.0, 100.0, 2.0)));
+        // Pointing the other way
+        assert!(!overlaps(&anchor, &line(100.0, 2.0, 50.0, 2.0)));
+        // A parallel street
+        assert!(!overlaps(&anchor, &line(0.0, 20.0, 100.0, 20.0)));
+    }
+
+    #[test]
+    fn test_move_dist() {
+        let anchor = line(0.0, 0.0, 100.0, 0.0);
+        let piece = line(50.0, 2.0, 100.0, 2.0);
+        let moved = move_dist(Some(&anchor), Distance::meters(60.0), &piece).unwrap();
+        assert!((moved.inner_meters() - 10.0).abs() < 0.01);
+        // Along the other piece
+        assert_eq!(
+            move_dist(Some(&anchor), Distance::meters(20.0), &piece),
+            None
+        );
+        // Without the old geometry, nothing can be moved
+        assert_eq!(move_dist(None, Distance::meters(60.0), &piece), None);
+    }
+}
@@ -11,15 +11,20 @@ use abstutil::Timer;
 use geom::{Speed, Time};
 use osm2streets::{get_lane_specs_ltr, RestrictionType};
 
-pub use self::perma::PermanentMapEdits;
+pub use self::migrate::{EditAnchors, MigrationReport};
+pub use self::osm_change::{NewTurnRestriction, OsmChange};
+pub use self::perma::{PermanentEditCmd, PermanentMapEdits};
 use crate::{
-    AccessRestrictions, ControlStopSign, ControlTrafficSignal, Crossing, DiagonalFilter,
-    IntersectionControl, IntersectionID, LaneID, LaneSpec, Map, MapConfig, ParkingLotID, Road,
-    RoadFilter, RoadID, TransitRouteID, TurnID, TurnType,
+    AccessRestrictions, BoardingFeatures, CongestionCharge, ControlStopSign, ControlTrafficSignal,
+    Crossing, DiagonalFilter, Direction, DrivingSide, HovLanes, IntersectionControl,
+    IntersectionID, LaneID, LaneSpec, LaneType, LoadingZones, Map, MapConfig, ParkingLotID,
+    ParkingPricing, Road, RoadFilter, RoadID, TransitRouteID, TransitStopID, TurnID, TurnType,
 };
 
 mod apply;
 mod compat;
+mod migrate;
+mod osm_change;
 mod perma;
 pub mod perma_traffic_signal;
 
@@ -36,6 +41,10 @@ pub struct MapEdits {
     pub original_roads: BTreeMap<RoadID, EditRoad>,
     pub original_intersections: BTreeMap<IntersectionID, EditIntersection>,
     pub changed_routes: BTreeSet<TransitRouteID>,
+    pub original_route_boarding: BTreeMap<TransitRouteID, BoardingFeatures>,
+    pub original_stop_boarding: BTreeMap<TransitStopID, BoardingFeatures>,
+    pub original_route_stops: BTreeMap<TransitRouteID, Vec<TransitStopID>>,
+    pub original_stop_closed: BTreeMap<TransitStopID, bool>,
 
     /// Some edits are included in the game by default, in data/system/proposals, as "community
     /// proposals." They require a description and may have a link to a write-up.
@@ -60,6 +69,39 @@ pub enum EditCmd {
         old: Vec<Time>,
         new: Vec<Time>,
     },
+    ChangeRouteBoarding {
+        id: TransitRouteID,
+        old: BoardingFeatures,
+        new: BoardingFeatures,
+    },
+    ChangeStopBoarding {
+        id: TransitStopID,
+        old: BoardingFeatures,
+        new: BoardingFeatures,
+    },
+    /// Detour a route by changing the stops it serves. Vehicles find their own way between stops.
+    ChangeRouteStops {
+        id: TransitRouteID,
+        old: Vec<TransitStopID>,
+        new: Vec<TransitStopID>,
+    },
+    ChangeStopClosed {
+        id: TransitStopID,
+        old: bool,
+        new: bool,
+    },
+    ChangeCongestionCharge {
+        old: Option<CongestionCharge>,
+        new: Option<CongestionCharge>,
+    },
+    ChangeParkingPricing {
+        old: ParkingPricing,
+        new: ParkingPricing,
+    },
+    ChangeLoadingZones {
+        old: LoadingZones,
+        new: LoadingZones,
+    },
 }
 
 pub struct EditEffects {
@@ -82,6 +124,8 @@ pub struct EditRoad {
     pub crossings: Vec<Crossing>,
     pub turn_restrictions: Vec<(RestrictionType, RoadID)>,
     pub complicated_turn_restrictions: Vec<(RoadID, RoadID)>,
+    #[serde(default)]
+    pub hov_lanes: Option<HovLanes>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -91,6 +135,10 @@ pub struct EditIntersection {
     /// This must contain all crossing turns at one intersection, each mapped either to Crosswalk
     /// or UnmarkedCrossing
     pub crosswalks: BTreeMap<TurnID, TurnType>,
+    /// Generate U-turns from the innermost lanes of every road, even where OSM doesn't tag them
+    pub allow_u_turns: bool,
+    /// Build a Dutch-style protected intersection for bikes
+    pub protected_corners: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -104,14 +152,15 @@ pub enum EditIntersectionControl {
 
 impl EditRoad {
     pub fn get_orig_from_osm(r: &Road, cfg: &MapConfig) -> EditRoad {
+        let lanes_ltr = get_lane_specs_ltr(&r.osm_tags, cfg);
         EditRoad {
-            lanes_ltr: get_lane_specs_ltr(&r.osm_tags, cfg),
+            hov_lanes: r.hov_lanes_from_osm(&lanes_ltr),
+            lanes_ltr,
             speed_limit: r.speed_limit_from_osm(),
             access_restrictions: r.access_restrictions_from_osm(),
             // TODO Port logic/existing_filters.rs here?
             modal_filter: None,
-            // TODO From crossing_nodes?
-            crossings: Vec::new(),
+            crossings: r.crossings_from_osm(),
             // TODO - review this. When editing turn restrictions, within the LTN tool we do not
             // use `get_orig_from_osm()`. The `EditRoad` is populated `map.get_r_edit()`.
             // Therefore we just create empty vecs here for now.
@@ -121,7 +170,7 @@ impl EditRoad {
         }
     }
 
-    fn diff(&self, other: &EditRoad) -> Vec<String> {
+    pub(crate) fn diff(&self, other: &EditRoad) -> Vec<String> {
         #![allow(clippy::comparison_chain)]
         let mut lt = 0;
         let mut dir = 0;
@@ -166,12 +215,15 @@ impl EditRoad {
         if self.crossings != other.crossings {
             changes.push("crossings".to_string());
         }
+        if self.hov_lanes != other.hov_lanes {
+            changes.push("HOV lanes".to_string());
+        }
         changes
     }
 }
 
 impl EditIntersection {
-    fn diff(&self, other: &EditIntersection) -> Vec<String> {
+    pub(crate) fn diff(&self, other: &EditIntersection) -> Vec<String> {
         let mut changes = Vec::new();
         // TODO Could get more specific about changes to stop signs, traffic signals, etc
         if self.control != other.control {
@@ -183,6 +235,12 @@ impl EditIntersection {
         if self.modal_filter != other.modal_filter {
             changes.push("modal filter".to_string());
         }
+        if self.allow_u_turns != other.allow_u_turns {
+            changes.push("U-turns".to_string());
+        }
+        if self.protected_corners != other.protected_corners {
+            changes.push("protected corners".to_string());
+        }
         changes
     }
 }
@@ -198,6 +256,10 @@ impl MapEdits {
             original_roads: BTreeMap::new(),
             original_intersections: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            original_route_boarding: BTreeMap::new(),
+            original_stop_boarding: BTreeMap::new(),
+            original_route_stops: BTreeMap::new(),
+            original_stop_closed: BTreeMap::new(),
         }
     }
 
@@ -237,15 +299,7 @@ impl MapEdits {
     /// they don't match the current map. If the resulting edits are totally empty, consider that a
     /// failure -- the edits likely don't cover this map at all.
     pub fn load_from_bytes(map: &Map, bytes: Vec<u8>) -> Result<MapEdits> {
-        let perma = match abstutil::from_json::<PermanentMapEdits>(&bytes) {
-            Ok(perma) => perma,
-            Err(_) => {
-                // The JSON format may have changed, so attempt backwards compatibility.
-                let contents = std::str::from_utf8(&bytes)?;
-                let value = serde_json::from_str(contents)?;
-                compat::upgrade(value, map)?
-            }
-        };
+        let (perma, _) = PermanentMapEdits::from_json_bytes(&bytes, map)?;
         let edits = perma.into_edits_permissive(map);
         if edits.commands.is_empty() {
             bail!("None of the edits apply to this map");
@@ -269,6 +323,10 @@ impl MapEdits {
         self.original_roads.clear();
         self.original_intersections.clear();
         self.changed_routes.clear();
+        self.original_route_boarding.clear();
+        self.original_stop_boarding.clear();
+        self.original_route_stops.clear();
+        self.original_stop_closed.clear();
 
         for cmd in &self.commands {
             match cmd {
@@ -285,6 +343,23 @@ impl MapEdits {
                 EditCmd::ChangeRouteSchedule { id, .. } => {
                     self.changed_routes.insert(*id);
                 }
+                EditCmd::ChangeRouteBoarding { id, old, .. } => {
+                    self.original_route_boarding.entry(*id).or_insert(*old);
+                }
+                EditCmd::ChangeStopBoarding { id, old, .. } => {
+                    self.original_stop_boarding.entry(*id).or_insert(*old);
+                }
+                EditCmd::ChangeRouteStops { id, old, .. } => {
+                    self.original_route_stops
+                        .entry(*id)
+                        .or_insert_with(|| old.clone());
+                }
+                EditCmd::ChangeStopClosed { id, old, .. } => {
+                    self.original_stop_closed.entry(*id).or_insert(*old);
+                }
+                EditCmd::ChangeCongestionCharge { .. }
+                | EditCmd::ChangeParkingPricing { .. }
+                | EditCmd::ChangeLoadingZones { .. } => {}
             }
         }
 
@@ -296,6 +371,14 @@ impl MapEdits {
             let r = map.get_tr(*br);
             r.spawn_times != r.orig_spawn_times
         });
+        self.original_route_boarding
+            .retain(|r, orig| map.get_tr(*r).boarding != *orig);
+        self.original_stop_boarding
+            .retain(|ts, orig| map.get_ts(*ts).boarding != *orig);
+        self.original_route_stops
+            .retain(|r, orig| map.get_tr(*r).stops != *orig);
+        self.original_stop_closed
+            .retain(|ts, orig| map.get_ts(*ts).closed != *orig);
     }
 
     /// Assumes update_derived has been called.
@@ -322,6 +405,53 @@ impl MapEdits {
                 old: r.orig_spawn_times.clone(),
             });
         }
+        for (r, old) in &self.original_route_boarding {
+            self.commands.push(EditCmd::ChangeRouteBoarding {
+                id: *r,
+                old: *old,
+                new: map.get_tr(*r).boarding,
+            });
+        }
+        for (ts, old) in &self.original_stop_boarding {
+            self.commands.push(EditCmd::ChangeStopBoarding {
+                id: *ts,
+                old: *old,
+                new: map.get_ts(*ts).boarding,
+            });
+        }
+        for (r, old) in &self.original_route_stops {
+            self.commands.push(EditCmd::ChangeRouteStops {
+                id: *r,
+                old: old.clone(),
+                new: map.get_tr(*r).stops.clone(),
+            });
+        }
+        for (ts, old) in &self.original_stop_closed {
+            self.commands.push(EditCmd::ChangeStopClosed {
+                id: *ts,
+                old: *old,
+                new: map.get_ts(*ts).closed,
+            });
+        }
+        // The basemap never has a congestion charge
+        if let Some(charge) = map.get_congestion_charge() {
+            self.commands.push(EditCmd::ChangeCongestionCharge {
+                old: None,
+                new: Some(charge.clone()),
+            });
+        }
+        if !map.get_parking_pricing().is_empty() {
+            self.commands.push(EditCmd::ChangeParkingPricing {
+                old: ParkingPricing::default(),
+                new: map.get_parking_pricing().clone(),
+            });
+        }
+        if !map.get_loading_zones().is_empty() {
+            self.commands.push(EditCmd::ChangeLoadingZones {
+                old: LoadingZones::default(),
+                new: map.get_loading_zones().clone(),
+            });
+        }
     }
 
     /// Pick apart changed_roads and figure out if an entire road was edited, or just a few lanes.
@@ -336,6 +466,7 @@ impl MapEdits {
                 || r.access_restrictions != orig.access_restrictions
                 || r.modal_filter != orig.modal_filter
                 || r.crossings != orig.crossings
+                || r.hov_lanes != orig.hov_lanes
                 // If a lane was added or deleted, figuring out if any were modified is kind of
                 // unclear -- just mark the entire road.
                 || r.lanes.len() != orig.lanes_ltr.len()
@@ -416,6 +547,52 @@ impl EditCmd {
             EditCmd::ChangeRouteSchedule { id, .. } => {
                 format!("reschedule route {}", map.get_tr(*id).short_name)
             }
+            EditCmd::ChangeRouteBoarding { id, .. } => {
+                format!("boarding on route {}", map.get_tr(*id).short_name)
+            }
+            EditCmd::ChangeStopBoarding { id, .. } => {
+                format!("boarding at stop {}", map.get_ts(*id).name)
+            }
+            EditCmd::ChangeRouteStops { id, old, new } => {
+                let skipped = old.iter().filter(|ts| !new.contains(ts)).count();
+                let added = new.iter().filter(|ts| !old.contains(ts)).count();
+                if skipped > 0 {
+                    details.push(format!("{} stops skipped", skipped));
+                }
+                if added > 0 {
+                    details.push(format!("{} stops added", added));
+                }
+                format!("detour route {}", map.get_tr(*id).short_name)
+            }
+            EditCmd::ChangeStopClosed { id, new, .. } => {
+                if *new {
+                    format!("close stop {}", map.get_ts(*id).name)
+                } else {
+                    format!("reopen stop {}", map.get_ts(*id).name)
+                }
+            }
+            EditCmd::ChangeCongestionCharge { new, .. } => {
+                if let Some(charge) = new {
+                    details.push(format!("{} roads in the cordon", charge.roads.len()));
+                    details.push(format!("peak charge {}", charge.peak_toll()));
+                    "congestion charge".to_string()
+                } else {
+                    "remove congestion charge".to_string()
+                }
+            }
+            EditCmd::ChangeParkingPricing { new, .. } => {
+                details.push(format!("{} priced blockfaces", new.blockfaces.len()));
+                details.push(format!("{} priced parking lots", new.lots.len()));
+                "parking pricing".to_string()
+            }
+            EditCmd::ChangeLoadingZones { new, .. } => {
+                details.push(format!(
+                    "{} loading zone spaces on {} curbs",
+                    new.spaces.values().sum::<usize>(),
+                    new.spaces.len()
+                ));
+                "loading zones".to_string()
+            }
         };
         (summary, details)
     }
@@ -455,6 +632,7 @@ impl Map {
             crossings: r.crossings.clone(),
             turn_restrictions: r.turn_restrictions.clone(),
             complicated_turn_restrictions: r.complicated_turn_restrictions.clone(),
+            hov_lanes: r.hov_lanes.clone(),
         }
     }
 
@@ -465,6 +643,94 @@ impl Map {
         EditCmd::ChangeRoad { r, old, new }
     }
 
+    /// Replace a slip lane with a footway as wide as the whole road, reclaiming the space for
+    /// pedestrians. Drivers have to turn at the bypassed intersection instead.
+    pub fn remove_slip_lane_cmd(&self, r: RoadID) -> EditCmd {
+        let width = self.get_r(r).get_width();
+        self.edit_road_cmd(r, |new| {
+            new.lanes_ltr = vec![LaneSpec {
+                lt: LaneType::Footway,
+                dir: Direction::Fwd,
+                width,
+                allowed_turns: Default::default(),
+            }];
+        })
+    }
+
+    /// After one side of a dual carriageway changed from `orig` to `new`, makes the same changes to
+    /// the rest of it. The sides may have a different number of lanes, so lane type and width
+    /// changes are matched up counting from the curb. If lanes were added or removed, the new
+    /// layout is only copied to segments that looked exactly like the original. Speed limits and
+    /// access restrictions are copied as they are.
+    pub fn edit_dual_carriageway_cmds(
+        &self,
+        r: RoadID,
+        orig: &EditRoad,
+        new: &EditRoad,
+    ) -> Vec<EditCmd> {
+        let curb_on_left = self.get_config().driving_side == DrivingSide::Left;
+        // Index of the lane this far from the curb, or the other way around
+        let from_curb = |len: usize, idx: usize, dir: Direction| {
+            if curb_on_left == (dir == Direction::Fwd) {
+                idx
+            } else {
+                len - 1 - idx
+            }
+        };
+        let orig_dir = LaneSpec::oneway_for_driving(&orig.lanes_ltr);
+
+        let mut cmds = Vec::new();
+        for other in self.dual_carriageway(r) {
+            if other == r {
+                continue;
+            }
+            let cmd = self.edit_road_cmd(other, |other_new| {
+                if orig.lanes_ltr == other_new.lanes_ltr {
+                    other_new.lanes_ltr = new.lanes_ltr.clone();
+                } else if orig.lanes_ltr.len() == new.lanes_ltr.len() {
+                    if let (Some(orig_dir), Some(other_dir)) =
+                        (orig_dir, LaneSpec::oneway_for_driving(&other_new.lanes_ltr))
+                    {
+                        let len = orig.lanes_ltr.len();
+                        let other_len = other_new.lanes_ltr.len();
+                        for (idx, (before, after)) in
+                            orig.lanes_ltr.iter().zip(new.lanes_ltr.iter()).enumerate()
+                        {
+                            if before.lt == after.lt && before.width == after.width {
+                                continue;
+                            }
+                            let offset = from_curb(len, idx, orig_dir);
+                            if offset >= other_len {
+                                continue;
+                            }
+                            let spec =
+                                &mut other_new.lanes_ltr[from_curb(other_len, offset, other_dir)];
+                            if spec.lt == before.lt {
+                                spec.lt = after.lt;
+                                spec.width = after.width;
+                            }
+                        }
+                    }
+                }
+                if orig.speed_limit != new.speed_limit {
+                    other_new.speed_limit = new.speed_limit;
+                }
+                if orig.access_restrictions != new.access_restrictions {
+                    other_new.access_restrictions = new.access_restrictions.clone();
+                }
+            });
+            if let EditCmd::ChangeRoad {
+                ref old, ref new, ..
+            } = cmd
+            {
+                if old != new {
+                    cmds.push(cmd);
+                }
+            }
+        }
+        cmds
+    }
+
     pub fn get_i_edit(&self, i: IntersectionID) -> EditIntersection {
         let i = self.get_i(i);
         let control = match i.control {
@@ -486,6 +752,8 @@ impl Map {
             control,
             modal_filter: i.modal_filter.clone(),
             crosswalks,
+            allow_u_turns: i.allow_u_turns,
+            protected_corners: i.protected_corners,
         }
     }
 
@@ -0,0 +1,310 @@
+//! Translate map edits back into OpenStreetMap terms, so mappers can upstream changes that've been
+//! verified on the ground. Only edits with a clear tagging equivalent are supported; everything
+//! else is listed so the mapper knows what to do by hand.
+
+use std::collections::{BTreeMap, HashMap};
+
+use osm2streets::RestrictionType;
+
+use crate::edits::EditRoad;
+use crate::{osm, CommonEndpoint, Direction, EditCmd, LaneSpec, LaneType, Map, RoadID, TurnType};
+
+/// Changes to make in OSM, derived from the current map edits
+#[derive(Debug, Default)]
+pub struct OsmChange {
+    /// Per way, tags to set, or to remove when the value is `None`
+    pub modify_ways: BTreeMap<osm::WayID, BTreeMap<String, Option<String>>>,
+    pub new_restrictions: Vec<NewTurnRestriction>,
+    /// Human-readable descriptions of edits that couldn't be translated
+    pub unsupported: Vec<String>,
+}
+
+/// A turn restriction relation to create
+#[derive(Debug)]
+pub struct NewTurnRestriction {
+    /// Like "no_left_turn" or "only_straight_on"
+    pub restriction: String,
+    pub from: osm::WayID,
+    pub via: osm::NodeID,
+    pub to: osm::WayID,
+}
+
+impl Map {
+    /// Express the current edits as tag changes on the original OSM ways. One OSM way is often
+    /// split into several roads here; the tags are changed only if every road from that way was
+    /// edited in the same way.
+    pub fn edits_to_osm_change(&self) -> OsmChange {
+        let mut result = OsmChange::default();
+
+        // Per way, the tag changes for each of its roads
+        let mut per_way: BTreeMap<osm::WayID, Vec<(RoadID, BTreeMap<String, Option<String>>)>> =
+            BTreeMap::new();
+        for r in self.all_roads() {
+            let tags = match self.edits.original_roads.get(&r.id) {
+                Some(orig) => self.road_tag_changes(r.id, orig, &mut result),
+                None => BTreeMap::new(),
+            };
+            per_way
+                .entry(r.orig_id.osm_way_id)
+                .or_default()
+                .push((r.id, tags));
+        }
+        for (way, roads) in per_way {
+            if roads.iter().all(|(_, tags)| tags.is_empty()) {
+                continue;
+            }
+            if roads.iter().all(|(_, tags)| *tags == roads[0].1) {
+                result.modify_ways.insert(way, roads[0].1.clone());
+            } else {
+                result.unsupported.push(format!(
+                    "{} was only partly changed, across roads {}. Split the way in OSM first.",
+                    way,
+                    roads
+                        .iter()
+                        .map(|(r, _)| r.0.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+        }
+
+        for i in self.edits.original_intersections.keys() {
+            result.unsupported.push(format!(
+                "Changes to intersection {}",
+                self.get_i(*i).orig_id
+            ));
+        }
+        for cmd in &self.edits.commands {
+            match cmd {
+                EditCmd::ChangeRoad { .. } | EditCmd::ChangeIntersection { .. } => {}
+                EditCmd::ChangeStopBoarding { .. }
+                | EditCmd::ChangeStopClosed { .. }
+                | EditCmd::ChangeRouteSchedule { .. }
+                | EditCmd::ChangeRouteBoarding { .. }
+                | EditCmd::ChangeRouteStops { .. }
+                | EditCmd::ChangeCongestionCharge { .. }
+                | EditCmd::ChangeParkingPricing { .. }
+                | EditCmd::ChangeLoadingZones { .. } => {
+                    result.unsupported.push(cmd.describe(self).0);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// The tags to change on one road's way. Turn restrictions and anything unsupported is
+    /// recorded directly in `result`.
+    fn road_tag_changes(
+        &self,
+        r: RoadID,
+        orig: &EditRoad,
+        result: &mut OsmChange,
+    ) -> BTreeMap<String, Option<String>> {
+        let road = self.get_r(r);
+        let current = self.get_r_edit(r);
+        let mut changes = BTreeMap::new();
+
+        if current.lanes_ltr != orig.lanes_ltr {
+            let old_tags = lanes_to_tags(&orig.lanes_ltr);
+            let new_tags = lanes_to_tags(&current.lanes_ltr);
+            for (k, v) in &new_tags {
+                if old_tags.get(k) != Some(v) {
+                    changes.insert(k.clone(), Some(v.clone()));
+                }
+            }
+            // Per-side tags are always written for both sides, so remove any shorthand that might
+            // contradict them
+            for prefix in ["cycleway", "busway", "parking:lane"] {
+                if changes
+                    .keys()
+                    .any(|k| k.starts_with(&format!("{}:", prefix)))
+                {
+                    changes.insert(prefix.to_string(), None);
+                    changes.insert(format!("{}:both", prefix), None);
+                }
+            }
+        }
+
+        if current.speed_limit != orig.speed_limit {
+            let mps = current.speed_limit.inner_meters_per_second();
+            let value = if road
+                .osm_tags
+                .get("maxspeed")
+                .map(|x| x.ends_with("mph"))
+                .unwrap_or(false)
+            {
+                format!("{} mph", (mps * 2.23694).round())
+            } else {
+                format!("{}", (mps * 3.6).round())
+            };
+            changes.insert("maxspeed".to_string(), Some(value));
+        }
+
+        for (restriction, to) in &current.turn_restrictions {
+            if orig
+                .turn_restrictions
+                .iter()
+                .any(|x| x.0 == *restriction && x.1 == *to)
+            {
+                continue;
+            }
+            match self.new_turn_restriction(r, restriction, *to) {
+                Some(x) => result.new_restrictions.push(x),
+                None => result
+                    .unsupported
+                    .push(format!("Turn restriction from road {} to {}", r.0, to.0)),
+            }
+        }
+        for (restriction, to) in &orig.turn_restrictions {
+            if !current
+                .turn_restrictions
+                .iter()
+                .any(|x| x.0 == *restriction && x.1 == *to)
+            {
+                // We don't know the relation ID to delete
+                result.unsupported.push(format!(
+                    "Removing the turn restriction from road {} to {}",
+                    r.0, to.0
+                ));
+            }
+        }
+
+        let mut other = Vec::new();
+        if current.access_restrictions != orig.access_restrictions {
+            other.push("access restrictions");
+        }
+        if current.modal_filter != orig.modal_filter {
+            other.push("a modal filter");
+        }
+        if current.crossings != orig.crossings {
+            other.push("crossings");
+        }
+        if current.complicated_turn_restrictions != orig.complicated_turn_restrictions {
+            other.push("complicated turn restrictions");
+        }
+        if current.hov_lanes != orig.hov_lanes {
+            other.push("HOV lanes");
+        }
+        for x in other {
+            result
+                .unsupported
+                .push(format!("Changes to {} on road {}", x, r.0));
+        }
+
+        changes
+    }
+
+    fn new_turn_restriction(
+        &self,
+        from: RoadID,
+        restriction: &RestrictionType,
+        to: RoadID,
+    ) -> Option<NewTurnRestriction> {
+        let r1 = self.get_r(from);
+        let r2 = self.get_r(to);
+        let i = match r1.common_endpoint(r2) {
+            CommonEndpoint::One(i) => i,
+            CommonEndpoint::Both | CommonEndpoint::None => {
+                return None;
+            }
+        };
+        let turn = match self.get_ban_turn_info(r1, r2, &HashMap::new()).0 {
+            TurnType::Straight => "straight_on",
+            TurnType::Left => "left_turn",
+            TurnType::Right => "right_turn",
+            TurnType::UTurn => "u_turn",
+            TurnType::Crosswalk | TurnType::SharedSidewalkCorner | TurnType::UnmarkedCrossing => {
+                return None;
+            }
+        };
+        let prefix = match restriction {
+            RestrictionType::BanTurns => "no",
+            RestrictionType::OnlyAllowTurns => "only",
+        };
+        Some(NewTurnRestriction {
+            restriction: format!("{}_{}", prefix, turn),
+            from: r1.orig_id.osm_way_id,
+            via: self.get_i(i).orig_id,
+            to: r2.orig_id.osm_way_id,
+        })
+    }
+}
+
+/// A simplified tagging of a road's lanes. Only the keys that differ between the old and new
+/// lanes are used, so it's fine if this doesn't round-trip exactly with what's in OSM.
+fn lanes_to_tags(lanes_ltr: &[LaneSpec]) -> BTreeMap<String, String> {
+    let mut tags = BTreeMap::new();
+
+    let mut fwd = 0;
+    let mut back = 0;
+    for spec in lanes_ltr {
+        if spec.lt == LaneType::Driving || spec.lt == LaneType::Bus {
+            if spec.dir == Direction::Fwd {
+                fwd += 1;
+            } else {
+                back += 1;
+            }
+        }
+    }
+    tags.insert("lanes".to_string(), (fwd + back).to_string());
+    if fwd > 0 && back > 0 {
+        tags.insert("oneway".to_string(), "no".to_string());
+        tags.insert("lanes:forward".to_string(), fwd.to_string());
+        tags.insert("lanes:backward".to_string(), back.to_string());
+    } else if back == 0 {
+        tags.insert("oneway".to_string(), "yes".to_string());
+    } else {
+        tags.insert("oneway".to_string(), "-1".to_string());
+    }
+
+    // Lanes in the left half of the road are on the left side of the way
+    let mut sidewalk_left = false;
+    let mut sidewalk_right = false;
+    for (side, half) in [
+        ("left", &lanes_ltr[..lanes_ltr.len() / 2]),
+        ("right", &lanes_ltr[lanes_ltr.len() / 2..]),
+    ] {
+        let has = |lt: LaneType| half.iter().any(|spec| spec.lt == lt);
+        let bike = if !has(LaneType::Biking) {
+            "no"
+        } else if half
+            .iter()
+            .any(|spec| matches!(spec.lt, LaneType::Buffer(_)))
+        {
+            "track"
+        } else {
+            "lane"
+        };
+        tags.insert(format!("cycleway:{}", side), bike.to_string());
+        tags.insert(
+            format!("busway:{}", side),
+            if has(LaneType::Bus) { "lane" } else { "no" }.to_string(),
+        );
+        tags.insert(
+            format!("parking:lane:{}", side),
+            if has(LaneType::Parking) {
+                "parallel"
+            } else {
+                "no"
+            }
+            .to_string(),
+        );
+        if has(LaneType::Sidewalk) {
+            if side == "left" {
+                sidewalk_left = true;
+            } else {
+                sidewalk_right = true;
+            }
+        }
+    }
+    let sidewalk = match (sidewalk_left, sidewalk_right) {
+        (true, true) => "both",
+        (true, false) => "left",
+        (false, true) => "right",
+        (false, false) => "no",
+    };
+    tags.insert("sidewalk".to_string(), sidewalk.to_string());
+
+    tags
+}
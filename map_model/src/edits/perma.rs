@@ -7,10 +7,13 @@ use abstio::MapName;
 use abstutil::{deserialize_btreemap, serialize_btreemap};
 use geom::Time;
 
+use super::migrate::{self, EditAnchors, MigrationReport};
 use super::perma_traffic_signal;
 use crate::edits::{EditCmd, EditIntersection, EditIntersectionControl, EditRoad, MapEdits};
 use crate::{
-    osm, ControlStopSign, DiagonalFilter, IntersectionID, Map, MovementID, OriginalRoad, TurnType,
+    osm, BoardingFeatures, CongestionCharge, ControlStopSign, DiagonalFilter, DirectedRoadID,
+    Direction, IntersectionID, LoadingZones, Map, MovementID, OriginalRoad, ParkingPolicy,
+    ParkingPricing, TransitStopID, TurnType,
 };
 
 // Manually change this to attempt to preserve edits after major OSM updates.
@@ -30,6 +33,10 @@ pub struct PermanentMapEdits {
     pub proposal_description: Vec<String>,
     /// The link is optional even for proposals
     pub proposal_link: Option<String>,
+    /// Where the edited objects were, to transfer edits after the map is re-imported. Edits saved
+    /// before these existed don't have them.
+    #[serde(default)]
+    pub anchors: EditAnchors,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -41,6 +48,10 @@ pub struct PermanentEditIntersection {
         deserialize_with = "deserialize_btreemap"
     )]
     crosswalks: BTreeMap<perma_traffic_signal::Turn, TurnType>,
+    #[serde(default)]
+    allow_u_turns: bool,
+    #[serde(default)]
+    protected_corners: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -56,6 +67,23 @@ pub enum PermanentEditIntersectionControl {
     Closed,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PermanentCongestionCharge {
+    roads: Vec<OriginalRoad>,
+    schedule: Vec<(Time, f64)>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PermanentParkingPricing {
+    blockfaces: Vec<(OriginalRoad, ParkingPolicy)>,
+    lots: Vec<(osm::OsmID, ParkingPolicy)>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PermanentLoadingZones {
+    spaces: Vec<(OriginalRoad, Direction, usize)>,
+}
+
 #[allow(clippy::enum_variant_names)]
 #[derive(Serialize, Deserialize, Clone)]
 pub enum PermanentEditCmd {
@@ -74,6 +102,39 @@ pub enum PermanentEditCmd {
         old: Vec<Time>,
         new: Vec<Time>,
     },
+    ChangeRouteBoarding {
+        gtfs_id: String,
+        old: BoardingFeatures,
+        new: BoardingFeatures,
+    },
+    ChangeStopBoarding {
+        gtfs_id: String,
+        old: BoardingFeatures,
+        new: BoardingFeatures,
+    },
+    /// The stops are also GTFS IDs
+    ChangeRouteStops {
+        gtfs_id: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    ChangeStopClosed {
+        gtfs_id: String,
+        old: bool,
+        new: bool,
+    },
+    ChangeCongestionCharge {
+        old: Option<PermanentCongestionCharge>,
+        new: Option<PermanentCongestionCharge>,
+    },
+    ChangeParkingPricing {
+        old: PermanentParkingPricing,
+        new: PermanentParkingPricing,
+    },
+    ChangeLoadingZones {
+        old: PermanentLoadingZones,
+        new: PermanentLoadingZones,
+    },
 }
 
 impl EditCmd {
@@ -96,6 +157,50 @@ impl EditCmd {
                     new: new.clone(),
                 }
             }
+            EditCmd::ChangeRouteBoarding { id, old, new } => {
+                PermanentEditCmd::ChangeRouteBoarding {
+                    gtfs_id: map.get_tr(*id).gtfs_id.clone(),
+                    old: *old,
+                    new: *new,
+                }
+            }
+            EditCmd::ChangeStopBoarding { id, old, new } => PermanentEditCmd::ChangeStopBoarding {
+                gtfs_id: map.get_ts(*id).gtfs_id.clone(),
+                old: *old,
+                new: *new,
+            },
+            EditCmd::ChangeRouteStops { id, old, new } => {
+                let to_gtfs = |stops: &Vec<TransitStopID>| {
+                    stops
+                        .iter()
+                        .map(|ts| map.get_ts(*ts).gtfs_id.clone())
+                        .collect()
+                };
+                PermanentEditCmd::ChangeRouteStops {
+                    gtfs_id: map.get_tr(*id).gtfs_id.clone(),
+                    old: to_gtfs(old),
+                    new: to_gtfs(new),
+                }
+            }
+            EditCmd::ChangeStopClosed { id, old, new } => PermanentEditCmd::ChangeStopClosed {
+                gtfs_id: map.get_ts(*id).gtfs_id.clone(),
+                old: *old,
+                new: *new,
+            },
+            EditCmd::ChangeCongestionCharge { old, new } => {
+                PermanentEditCmd::ChangeCongestionCharge {
+                    old: old.as_ref().map(|c| c.to_permanent(map)),
+                    new: new.as_ref().map(|c| c.to_permanent(map)),
+                }
+            }
+            EditCmd::ChangeParkingPricing { old, new } => PermanentEditCmd::ChangeParkingPricing {
+                old: old.to_permanent(map),
+                new: new.to_permanent(map),
+            },
+            EditCmd::ChangeLoadingZones { old, new } => PermanentEditCmd::ChangeLoadingZones {
+                old: old.to_permanent(map),
+                new: new.to_permanent(map),
+            },
         }
     }
 }
@@ -146,6 +251,59 @@ impl PermanentEditCmd {
                     .ok_or_else(|| anyhow!("can't find {}", gtfs_id))?;
                 Ok(EditCmd::ChangeRouteSchedule { id, old, new })
             }
+            PermanentEditCmd::ChangeRouteBoarding { gtfs_id, old, new } => {
+                let id = map
+                    .find_tr_by_gtfs(&gtfs_id)
+                    .ok_or_else(|| anyhow!("can't find {}", gtfs_id))?;
+                Ok(EditCmd::ChangeRouteBoarding { id, old, new })
+            }
+            PermanentEditCmd::ChangeStopBoarding { gtfs_id, old, new } => {
+                let id = map
+                    .find_ts_by_gtfs(&gtfs_id)
+                    .ok_or_else(|| anyhow!("can't find {}", gtfs_id))?;
+                Ok(EditCmd::ChangeStopBoarding { id, old, new })
+            }
+            PermanentEditCmd::ChangeRouteStops { gtfs_id, old, new } => {
+                let id = map
+                    .find_tr_by_gtfs(&gtfs_id)
+                    .ok_or_else(|| anyhow!("can't find {}", gtfs_id))?;
+                let from_gtfs = |stops: Vec<String>| {
+                    stops
+                        .into_iter()
+                        .map(|x| {
+                            map.find_ts_by_gtfs(&x)
+                                .ok_or_else(|| anyhow!("can't find stop {}", x))
+                        })
+                        .collect::<Result<Vec<_>>>()
+                };
+                Ok(EditCmd::ChangeRouteStops {
+                    id,
+                    old: from_gtfs(old)?,
+                    new: from_gtfs(new)?,
+                })
+            }
+            PermanentEditCmd::ChangeStopClosed { gtfs_id, old, new } => {
+                let id = map
+                    .find_ts_by_gtfs(&gtfs_id)
+                    .ok_or_else(|| anyhow!("can't find {}", gtfs_id))?;
+                Ok(EditCmd::ChangeStopClosed { id, old, new })
+            }
+            PermanentEditCmd::ChangeCongestionCharge { old, new } => {
+                Ok(EditCmd::ChangeCongestionCharge {
+                    old: old.map(|c| c.with_permanent(map)).transpose()?,
+                    new: new.map(|c| c.with_permanent(map)).transpose()?,
+                })
+            }
+            PermanentEditCmd::ChangeParkingPricing { old, new } => {
+                Ok(EditCmd::ChangeParkingPricing {
+                    old: old.with_permanent(map)?,
+                    new: new.with_permanent(map)?,
+                })
+            }
+            PermanentEditCmd::ChangeLoadingZones { old, new } => Ok(EditCmd::ChangeLoadingZones {
+                old: old.with_permanent(map)?,
+                new: new.with_permanent(map)?,
+            }),
         }
     }
 }
@@ -156,16 +314,31 @@ impl MapEdits {
         PermanentMapEdits {
             map_name: map.get_name().clone(),
             edits_name: self.edits_name.clone(),
-            // Increase this every time there's a schema change
-            version: 13,
+            version: PermanentMapEdits::CURRENT_VERSION,
             proposal_description: self.proposal_description.clone(),
             proposal_link: self.proposal_link.clone(),
             commands: self.commands.iter().map(|cmd| cmd.to_perma(map)).collect(),
+            anchors: EditAnchors::new(self, map),
         }
     }
 }
 
 impl PermanentMapEdits {
+    /// Increase this every time there's a schema change
+    pub const CURRENT_VERSION: usize = 13;
+
+    /// Parse edits written by any version of A/B Street, upgrading old formats. Also returns true
+    /// if the format had to be upgraded.
+    pub fn from_json_bytes(bytes: &[u8], map: &Map) -> Result<(PermanentMapEdits, bool)> {
+        if let Ok(perma) = abstutil::from_json::<PermanentMapEdits>(bytes) {
+            return Ok((perma, false));
+        }
+        // The JSON format may have changed, so attempt backwards compatibility.
+        let contents = std::str::from_utf8(bytes)?;
+        let value = serde_json::from_str(contents)?;
+        Ok((super::compat::upgrade(value, map)?, true))
+    }
+
     /// Transform permanent edits to MapEdits, looking up the map IDs by the hopefully stabler OSM
     /// IDs. Validate that the basemap hasn't changed in important ways.
     pub fn into_edits(self, map: &Map) -> Result<MapEdits> {
@@ -182,36 +355,71 @@ impl PermanentMapEdits {
             original_roads: BTreeMap::new(),
             original_intersections: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            original_route_boarding: BTreeMap::new(),
+            original_stop_boarding: BTreeMap::new(),
+            original_route_stops: BTreeMap::new(),
+            original_stop_closed: BTreeMap::new(),
         };
         edits.update_derived(map);
         Ok(edits)
     }
 
     /// Transform permanent edits to MapEdits, looking up the map IDs by the hopefully stabler OSM
-    /// IDs. Strip out commands that're broken, but log warnings.
+    /// IDs. Commands referring to roads or intersections that no longer exist are moved onto
+    /// whatever replaced them, if possible. Strip out commands that're broken, but log warnings.
     pub fn into_edits_permissive(self, map: &Map) -> MapEdits {
+        let (edits, report) = self.into_edits_migrating(map);
+        for msg in report.re_anchored {
+            info!("Migrating edits: {}", msg);
+        }
+        for err in report.failed {
+            warn!("Skipping broken command: {}", err);
+        }
+        edits
+    }
+
+    /// Like `into_edits_permissive`, but also reports which commands were moved and which
+    /// couldn't be transferred to this map.
+    pub fn into_edits_migrating(self, map: &Map) -> (MapEdits, MigrationReport) {
+        let mut report = MigrationReport::default();
+        let mut commands = Vec::new();
+        for cmd in self.commands {
+            let result = match cmd {
+                PermanentEditCmd::ChangeRoad { r, new, old }
+                    if map.find_r_by_osm_id(r).is_err() =>
+                {
+                    migrate::migrate_road(r, new, old, &self.anchors, map, &mut report)
+                }
+                PermanentEditCmd::ChangeIntersection { i, new, old }
+                    if map.find_i_by_osm_id(i).is_err() =>
+                {
+                    migrate_intersection(i, new, old, &self.anchors, map, &mut report)
+                        .map(|cmd| vec![cmd])
+                }
+                cmd => cmd.into_cmd(map).map(|cmd| vec![cmd]),
+            };
+            match result {
+                Ok(cmds) => commands.extend(cmds),
+                Err(err) => report.failed.push(err.to_string()),
+            }
+        }
+
         let mut edits = MapEdits {
             edits_name: self.edits_name,
             proposal_description: self.proposal_description,
             proposal_link: self.proposal_link,
-            commands: self
-                .commands
-                .into_iter()
-                .filter_map(|cmd| match cmd.into_cmd(map) {
-                    Ok(cmd) => Some(cmd),
-                    Err(err) => {
-                        warn!("Skipping broken command: {}", err);
-                        None
-                    }
-                })
-                .collect(),
+            commands,
 
             original_roads: BTreeMap::new(),
             original_intersections: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            original_route_boarding: BTreeMap::new(),
+            original_stop_boarding: BTreeMap::new(),
+            original_route_stops: BTreeMap::new(),
+            original_stop_closed: BTreeMap::new(),
         };
         edits.update_derived(map);
-        edits
+        (edits, report)
     }
 
     /// Get the human-friendly of these edits. If they have a description, the first line is the
@@ -225,6 +433,124 @@ impl PermanentMapEdits {
     }
 }
 
+/// Moves a `ChangeIntersection` command onto the intersection now at the same place
+fn migrate_intersection(
+    orig: osm::NodeID,
+    new: PermanentEditIntersection,
+    old: PermanentEditIntersection,
+    anchors: &EditAnchors,
+    map: &Map,
+    report: &mut MigrationReport,
+) -> Result<EditCmd> {
+    let id = anchors.find_intersection(orig, map)?;
+    let cmd = EditCmd::ChangeIntersection {
+        i: id,
+        new: new.with_permanent(id, map).with_context(|| {
+            format!(
+                "{} moved to {}, but the new edits don't fit",
+                orig,
+                map.get_i(id).orig_id
+            )
+        })?,
+        old: old.with_permanent(id, map).with_context(|| {
+            format!(
+                "{} moved to {}, but the old edits don't fit",
+                orig,
+                map.get_i(id).orig_id
+            )
+        })?,
+    };
+    report
+        .re_anchored
+        .push(format!("{} moved onto {}", orig, map.get_i(id).orig_id));
+    Ok(cmd)
+}
+
+impl CongestionCharge {
+    fn to_permanent(&self, map: &Map) -> PermanentCongestionCharge {
+        PermanentCongestionCharge {
+            roads: self.roads.iter().map(|r| map.get_r(*r).orig_id).collect(),
+            schedule: self.schedule.clone(),
+        }
+    }
+}
+
+impl PermanentCongestionCharge {
+    fn with_permanent(self, map: &Map) -> Result<CongestionCharge> {
+        Ok(CongestionCharge {
+            roads: self
+                .roads
+                .into_iter()
+                .map(|r| map.find_r_by_osm_id(r))
+                .collect::<Result<BTreeSet<_>>>()
+                .context("congestion charge cordon")?,
+            schedule: self.schedule,
+        })
+    }
+}
+
+impl ParkingPricing {
+    fn to_permanent(&self, map: &Map) -> PermanentParkingPricing {
+        PermanentParkingPricing {
+            blockfaces: self
+                .blockfaces
+                .iter()
+                .map(|(r, policy)| (map.get_r(*r).orig_id, *policy))
+                .collect(),
+            lots: self
+                .lots
+                .iter()
+                .map(|(pl, policy)| (map.get_pl(*pl).osm_id, *policy))
+                .collect(),
+        }
+    }
+}
+
+impl PermanentParkingPricing {
+    fn with_permanent(self, map: &Map) -> Result<ParkingPricing> {
+        let mut pricing = ParkingPricing::default();
+        for (r, policy) in self.blockfaces {
+            pricing
+                .blockfaces
+                .insert(map.find_r_by_osm_id(r).context("priced blockface")?, policy);
+        }
+        for (osm_id, policy) in self.lots {
+            let pl = map
+                .all_parking_lots()
+                .iter()
+                .find(|pl| pl.osm_id == osm_id)
+                .ok_or_else(|| anyhow!("can't find parking lot {}", osm_id))?;
+            pricing.lots.insert(pl.id, policy);
+        }
+        Ok(pricing)
+    }
+}
+
+impl LoadingZones {
+    fn to_permanent(&self, map: &Map) -> PermanentLoadingZones {
+        PermanentLoadingZones {
+            spaces: self
+                .spaces
+                .iter()
+                .map(|(dr, spaces)| (map.get_r(dr.road).orig_id, dr.dir, *spaces))
+                .collect(),
+        }
+    }
+}
+
+impl PermanentLoadingZones {
+    fn with_permanent(self, map: &Map) -> Result<LoadingZones> {
+        let mut zones = LoadingZones::default();
+        for (r, dir, spaces) in self.spaces {
+            let road = map
+                .find_r_by_osm_id(r)
+                .context("road with a loading zone")?;
+            zones.spaces.insert(DirectedRoadID { road, dir }, spaces);
+        }
+        Ok(zones)
+    }
+}
+
 impl EditIntersection {
     fn to_permanent(&self, map: &Map) -> PermanentEditIntersection {
         PermanentEditIntersection {
@@ -251,6 +577,8 @@ impl EditIntersection {
                 .iter()
                 .map(|(id, turn_type)| (id.to_movement(map).to_permanent(map), *turn_type))
                 .collect(),
+            allow_u_turns: self.allow_u_turns,
+            protected_corners: self.protected_corners,
         }
     }
 }
@@ -314,6 +642,8 @@ impl PermanentEditIntersection {
             // TODO Express as GeoJSON
             modal_filter: self.modal_filter.clone(),
             crosswalks,
+            allow_u_turns: self.allow_u_turns,
+            protected_corners: self.protected_corners,
         })
     }
 }
@@ -32,6 +32,35 @@ pub struct Plan {
     pub stages: Vec<Stage>,
     /// Relative to a central clock, delay the first stage by this many seconds.
     pub offset_seconds: usize,
+    /// If present, the signal responds to detected vehicles, instead of running each stage for a
+    /// fixed time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actuation: Option<Actuation>,
+    /// If present, buses and trains approaching the signal get an early or extended green.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transit_priority: Option<TransitPriority>,
+}
+
+/// How an actuated traffic signal responds to demand.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Actuation {
+    /// Every stage lasts at least this many seconds.
+    pub min_green_seconds: usize,
+    /// A stage ends after this many seconds if anybody else is waiting.
+    pub max_green_seconds: usize,
+    /// While vehicles are detected, the stage is extended by this many seconds at a time.
+    pub extension_seconds: usize,
+    /// Vehicles within this distance of the stop line are detected.
+    pub detector_length_meters: f64,
+}
+
+/// How a traffic signal gives priority to transit.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransitPriority {
+    /// Stages are never cut shorter than this many seconds for an approaching bus.
+    pub min_green_seconds: usize,
+    /// A green may be held this many seconds longer for an approaching bus.
+    pub max_extension_seconds: usize,
 }
 
 /// A traffic signal is in one stage at any time. The stage describes what movements are possible.
@@ -47,6 +76,9 @@ pub struct Stage {
     pub permitted_turns: BTreeSet<Turn>,
     /// The stage lasts this long before moving to the next one.
     pub stage_type: StageType,
+    /// Only bikes on bike lanes may move during this stage.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bikes_only: bool,
 }
 
 /// How long a stage lasts before moving to the next one.
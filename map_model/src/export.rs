@@ -0,0 +1,197 @@
+//! Export the whole map and its current edits as GeoJSON with attributes, so proposals can be
+//! opened in GIS tools like QGIS without running A/B Street.
+
+use std::collections::BTreeMap;
+
+use geom::PolyLine;
+
+use crate::{osm, Direction, EditCmd, LaneSpec, Map, TransitRouteID, TransitStopID};
+
+impl Map {
+    /// Every road, lane, intersection, and building, transformed to WGS84. Each feature has a
+    /// "layer" property saying what it is. Roads and intersections changed by the current edits
+    /// also get one feature in the "edit" layer, listing what's different from the original map.
+    /// Edited transit stops and routes are included too.
+    pub fn export_layers(&self) -> geojson::GeoJson {
+        let mut pairs = Vec::new();
+        let gps_bounds = Some(self.get_gps_bounds());
+
+        for r in self.all_roads() {
+            let mut props = serde_json::Map::new();
+            props.insert("layer".to_string(), "road".into());
+            props.insert("id".to_string(), r.id.0.into());
+            props.insert("osm_way_id".to_string(), r.orig_id.osm_way_id.0.into());
+            props.insert("name".to_string(), r.get_name(None).into());
+            if let Some(highway) = r.osm_tags.get(osm::HIGHWAY) {
+                props.insert("highway".to_string(), highway.clone().into());
+            }
+            props.insert("lanes".to_string(), describe_lanes(&r.lane_specs()).into());
+            props.insert(
+                "speed_limit_kmph".to_string(),
+                (r.speed_limit.inner_meters_per_second() * 3.6)
+                    .round()
+                    .into(),
+            );
+            props.insert("width_m".to_string(), r.get_width().inner_meters().into());
+            props.insert("zorder".to_string(), r.zorder.into());
+            props.insert("private".to_string(), r.is_private().into());
+            if let Some(ref filter) = r.modal_filter {
+                props.insert(
+                    "modal_filter".to_string(),
+                    format!("{:?}", filter.filter_type).into(),
+                );
+            }
+            props.insert(
+                "edited".to_string(),
+                self.edits.original_roads.contains_key(&r.id).into(),
+            );
+            pairs.push((r.center_pts.to_geojson(gps_bounds), props));
+        }
+
+        for l in self.all_lanes() {
+            let mut props = serde_json::Map::new();
+            props.insert("layer".to_string(), "lane".into());
+            props.insert("id".to_string(), l.id.to_string().into());
+            props.insert("road".to_string(), l.id.road.0.into());
+            props.insert("lane_type".to_string(), l.lane_type.describe().into());
+            props.insert("direction".to_string(), describe_direction(l.dir).into());
+            props.insert("width_m".to_string(), l.width.inner_meters().into());
+            pairs.push((l.get_thick_polygon().to_geojson(gps_bounds), props));
+        }
+
+        for i in self.all_intersections() {
+            let mut props = serde_json::Map::new();
+            props.insert("layer".to_string(), "intersection".into());
+            props.insert("id".to_string(), i.id.0.into());
+            props.insert("osm_node_id".to_string(), i.orig_id.to_string().into());
+            props.insert("kind".to_string(), format!("{:?}", i.kind).into());
+            props.insert("control".to_string(), format!("{:?}", i.control).into());
+            props.insert(
+                "edited".to_string(),
+                self.edits.original_intersections.contains_key(&i.id).into(),
+            );
+            pairs.push((i.polygon.to_geojson(gps_bounds), props));
+        }
+
+        for b in self.all_buildings() {
+            let mut props = serde_json::Map::new();
+            props.insert("layer".to_string(), "building".into());
+            props.insert("id".to_string(), b.id.0.into());
+            props.insert("osm_id".to_string(), b.orig_id.to_string().into());
+            props.insert("address".to_string(), b.address.clone().into());
+            if let Some(ref names) = b.name {
+                props.insert("name".to_string(), names.get(None).to_string().into());
+            }
+            props.insert(
+                "building_type".to_string(),
+                format!("{:?}", b.bldg_type).into(),
+            );
+            props.insert("levels".to_string(), b.levels.into());
+            props.insert(
+                "amenities".to_string(),
+                b.amenities
+                    .iter()
+                    .map(|a| a.amenity_type.clone())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+                    .into(),
+            );
+            pairs.push((b.polygon.to_geojson(gps_bounds), props));
+        }
+
+        // Net changes per road and intersection, compared to the map before any edits
+        for (r, orig) in &self.edits.original_roads {
+            let road = self.get_r(*r);
+            let current = self.get_r_edit(*r);
+            let mut props = serde_json::Map::new();
+            props.insert("layer".to_string(), "edit".into());
+            props.insert("object".to_string(), "road".into());
+            props.insert("id".to_string(), r.0.into());
+            props.insert("changes".to_string(), current.diff(orig).into());
+            props.insert(
+                "old_lanes".to_string(),
+                describe_lanes(&orig.lanes_ltr).into(),
+            );
+            props.insert(
+                "new_lanes".to_string(),
+                describe_lanes(&current.lanes_ltr).into(),
+            );
+            pairs.push((road.center_pts.to_geojson(gps_bounds), props));
+        }
+        for (i, orig) in &self.edits.original_intersections {
+            let mut props = serde_json::Map::new();
+            props.insert("layer".to_string(), "edit".into());
+            props.insert("object".to_string(), "intersection".into());
+            props.insert("id".to_string(), i.0.into());
+            props.insert("changes".to_string(), self.get_i_edit(*i).diff(orig).into());
+            pairs.push((self.get_i(*i).polygon.to_geojson(gps_bounds), props));
+        }
+
+        // Transit changes don't have a simple before and after, so just list the commands
+        let mut stop_changes: BTreeMap<TransitStopID, Vec<String>> = BTreeMap::new();
+        let mut route_changes: BTreeMap<TransitRouteID, Vec<String>> = BTreeMap::new();
+        for cmd in &self.edits.commands {
+            let summary = cmd.describe(self).0;
+            match cmd {
+                EditCmd::ChangeStopBoarding { id, .. } | EditCmd::ChangeStopClosed { id, .. } => {
+                    stop_changes.entry(*id).or_default().push(summary);
+                }
+                EditCmd::ChangeRouteSchedule { id, .. }
+                | EditCmd::ChangeRouteBoarding { id, .. }
+                | EditCmd::ChangeRouteStops { id, .. } => {
+                    route_changes.entry(*id).or_default().push(summary);
+                }
+                EditCmd::ChangeRoad { .. }
+                | EditCmd::ChangeIntersection { .. }
+                | EditCmd::ChangeCongestionCharge { .. }
+                | EditCmd::ChangeParkingPricing { .. }
+                | EditCmd::ChangeLoadingZones { .. } => {}
+            }
+        }
+        for (id, changes) in stop_changes {
+            let ts = self.get_ts(id);
+            let mut props = serde_json::Map::new();
+            props.insert("layer".to_string(), "edit".into());
+            props.insert("object".to_string(), "transit stop".into());
+            props.insert("name".to_string(), ts.name.clone().into());
+            props.insert("closed".to_string(), ts.closed.into());
+            props.insert("changes".to_string(), changes.into());
+            pairs.push((ts.sidewalk_pos.pt(self).to_geojson(gps_bounds), props));
+        }
+        for (id, changes) in route_changes {
+            let tr = self.get_tr(id);
+            let pts = tr
+                .stops
+                .iter()
+                .map(|ts| self.get_ts(*ts).driving_pos.pt(self))
+                .collect();
+            // Routes with fewer than two stops have no line to draw
+            if let Ok(pl) = PolyLine::deduping_new(pts) {
+                let mut props = serde_json::Map::new();
+                props.insert("layer".to_string(), "edit".into());
+                props.insert("object".to_string(), "transit route".into());
+                props.insert("name".to_string(), tr.short_name.clone().into());
+                props.insert("changes".to_string(), changes.into());
+                pairs.push((pl.to_geojson(gps_bounds), props));
+            }
+        }
+
+        geom::geometries_with_properties_to_geojson(pairs)
+    }
+}
+
+/// Like "sidewalk, parking back, driving back, driving fwd, sidewalk", from left to right
+fn describe_lanes(lanes_ltr: &[LaneSpec]) -> String {
+    lanes_ltr
+        .iter()
+        .map(|spec| format!("{} {}", spec.lt.short_name(), describe_direction(spec.dir)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn describe_direction(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Fwd => "fwd",
+        Direction::Back => "back",
+    }
+}
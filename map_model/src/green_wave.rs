@@ -0,0 +1,126 @@
+//! Coordinate traffic signals along a corridor, so a platoon released by one signal arrives at the
+//! next one just as it turns green.
+
+use anyhow::{bail, Result};
+
+use geom::{Duration, Speed};
+
+use crate::{
+    ControlTrafficSignal, EditCmd, EditIntersectionControl, IntersectionID, Map, MovementID,
+    PathConstraints, RoadID,
+};
+
+impl Map {
+    /// Calculates offsets for a string of traffic signals, in order of travel, so that vehicles
+    /// moving at `progression_speed` get a green at each one. The first signal keeps its offset.
+    /// Only the one direction is coordinated. All of the signals need the same cycle length.
+    ///
+    /// Returns the signals with their new offsets; `green_wave_edits` turns these into edits.
+    pub fn green_wave(
+        &self,
+        corridor: &[IntersectionID],
+        progression_speed: Speed,
+    ) -> Result<Vec<ControlTrafficSignal>> {
+        if corridor.len() < 2 {
+            bail!("A corridor needs at least two signals");
+        }
+        if progression_speed <= Speed::ZERO {
+            bail!("The progression speed must be positive");
+        }
+        let mut signals = Vec::new();
+        for i in corridor {
+            match self.maybe_get_traffic_signal(*i) {
+                Some(ts) => signals.push(ts.clone()),
+                None => bail!("{} isn't a traffic signal", i),
+            }
+        }
+        let cycle = signals[0].simple_cycle_duration();
+        if signals.iter().any(|ts| ts.simple_cycle_duration() != cycle) {
+            bail!("All signals in a green wave need the same cycle length");
+        }
+        if cycle == Duration::ZERO {
+            bail!("The signals have no stages");
+        }
+
+        // The roads between each pair of signals
+        let mut paths: Vec<Vec<RoadID>> = Vec::new();
+        for pair in corridor.windows(2) {
+            match self.simple_path_btwn_v2(pair[0], pair[1], PathConstraints::Car) {
+                Some((roads, _)) => paths.push(roads),
+                None => bail!("Can't drive from {} to {}", pair[0], pair[1]),
+            }
+        }
+
+        // When each signal's green for the corridor starts, relative to the start of its cycle
+        let mut green_starts = Vec::new();
+        for (idx, ts) in signals.iter().enumerate() {
+            let incoming = if idx == 0 {
+                None
+            } else {
+                paths[idx - 1].last().cloned()
+            };
+            let outgoing = paths.get(idx).and_then(|roads| roads.first().cloned());
+            green_starts.push(corridor_green_start(ts, incoming, outgoing)?);
+        }
+
+        let mut travel_time = Duration::ZERO;
+        for idx in 1..signals.len() {
+            for r in &paths[idx - 1] {
+                travel_time += self.get_r(*r).length() / progression_speed;
+            }
+            // The platoon leaves the first signal when its green starts, and should reach this
+            // signal when its green starts.
+            let offset = signals[0].offset.inner_seconds() + green_starts[idx].inner_seconds()
+                - green_starts[0].inner_seconds()
+                - travel_time.inner_seconds();
+            signals[idx].offset =
+                Duration::seconds(offset.rem_euclid(cycle.inner_seconds()).round());
+        }
+        Ok(signals)
+    }
+
+    /// Commands to apply the offsets calculated by `green_wave`
+    pub fn green_wave_edits(&self, signals: Vec<ControlTrafficSignal>) -> Vec<EditCmd> {
+        signals
+            .into_iter()
+            .map(|ts| {
+                self.edit_intersection_cmd(ts.id, |new| {
+                    new.control = EditIntersectionControl::TrafficSignal(ts.export(self));
+                })
+            })
+            .collect()
+    }
+}
+
+/// Finds the first stage serving vehicles moving along the corridor, preferring protected
+/// movements, and returns how far into the cycle it starts.
+fn corridor_green_start(
+    ts: &ControlTrafficSignal,
+    incoming: Option<RoadID>,
+    outgoing: Option<RoadID>,
+) -> Result<Duration> {
+    let along_corridor = |m: &MovementID| {
+        !m.crosswalk
+            && incoming.map(|r| m.from.road == r).unwrap_or(true)
+            && outgoing.map(|r| m.to.road == r).unwrap_or(true)
+    };
+    let mut idx = ts
+        .stages
+        .iter()
+        .position(|s| s.protected_movements.iter().any(along_corridor));
+    if idx.is_none() {
+        idx = ts
+            .stages
+            .iter()
+            .position(|s| s.yield_movements.iter().any(along_corridor));
+    }
+    let idx = match idx {
+        Some(idx) => idx,
+        None => bail!("No stage at {} serves the corridor", ts.id),
+    };
+    let mut start = Duration::ZERO;
+    for s in &ts.stages[0..idx] {
+        start += s.stage_type.simple_duration();
+    }
+    Ok(start)
+}
@@ -0,0 +1,101 @@
+//! Hazards like floods close roads for part of the day. Load their extent from polygons, close
+//! the roads they touch, and find the buildings cut off as a result, for resilience planning.
+
+use std::collections::BTreeSet;
+
+use anyhow::{Context, Result};
+
+use geom::{Duration, Polygon, Time};
+
+use crate::connectivity::find_scc;
+use crate::{BuildingID, LaneType, Map, MapEdits, PathConstraints, RoadID};
+
+/// An area that's impassable to vehicles for some time window
+#[derive(Clone, Debug)]
+pub struct Hazard {
+    pub polygon: Polygon,
+    pub start: Time,
+    pub end: Time,
+}
+
+impl Hazard {
+    /// Reads a GeoJSON file of polygons, like a flood extent. Each one may have `start` and `end`
+    /// properties, like "7:30", giving the time window. Without them, the hazard lasts all day.
+    pub fn load_geojson(map: &Map, path: &str) -> Result<Vec<Hazard>> {
+        let require_in_bounds = false;
+        let mut hazards = Vec::new();
+        for (polygon, props) in Polygon::from_geojson_bytes(
+            &abstio::slurp_file(path)?,
+            map.get_gps_bounds(),
+            require_in_bounds,
+        )? {
+            let start = match props.get("start") {
+                Some(x) => Time::parse(x).with_context(|| format!("bad start time {}", x))?,
+                None => Time::START_OF_DAY,
+            };
+            let end = match props.get("end") {
+                Some(x) => Time::parse(x).with_context(|| format!("bad end time {}", x))?,
+                None => Time::START_OF_DAY + Duration::hours(24),
+            };
+            hazards.push(Hazard {
+                polygon,
+                start,
+                end,
+            });
+        }
+        Ok(hazards)
+    }
+
+    pub fn is_active(&self, time: Time) -> bool {
+        time >= self.start && time < self.end
+    }
+}
+
+impl Map {
+    /// All roads touching a hazard that's active at this time
+    pub fn roads_closed_by_hazards(&self, hazards: &[Hazard], time: Time) -> BTreeSet<RoadID> {
+        let mut closed = BTreeSet::new();
+        for hazard in hazards {
+            if !hazard.is_active(time) {
+                continue;
+            }
+            for r in self.all_roads() {
+                if r.get_thick_polygon().intersects(&hazard.polygon) {
+                    closed.insert(r.id);
+                }
+            }
+        }
+        closed
+    }
+
+    /// Starting from some edits, also close every vehicle lane on these roads. Sidewalks stay
+    /// open, since buildings need them to exist.
+    pub fn close_roads_for_hazards(&self, base: &MapEdits, roads: &BTreeSet<RoadID>) -> MapEdits {
+        let mut edits = base.clone();
+        for r in roads {
+            let cmd = self.edit_road_cmd(*r, |new| {
+                for spec in &mut new.lanes_ltr {
+                    if !spec.lt.is_walkable() {
+                        spec.lt = LaneType::Construction;
+                    }
+                }
+            });
+            edits.commands.push(cmd);
+        }
+        edits
+    }
+
+    /// Buildings that can't be reached by car from most of the map, because their road is closed
+    /// or cut off. Some buildings are like this even without hazards, so compare against that.
+    pub fn stranded_buildings(&self) -> BTreeSet<BuildingID> {
+        let (main_component, _) = find_scc(self, PathConstraints::Car);
+        self.all_buildings()
+            .iter()
+            .filter(|b| match b.driving_connection(self) {
+                Some((pos, _)) => !main_component.contains(&pos.lane()),
+                None => true,
+            })
+            .map(|b| b.id)
+            .collect()
+    }
+}
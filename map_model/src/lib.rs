@@ -29,7 +29,7 @@ extern crate anyhow;
 extern crate log;
 
 use std::collections::BTreeMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, OnceLock, RwLock};
 
 use popgetter::CensusZone;
 use serde::{Deserialize, Serialize};
@@ -44,46 +44,80 @@ pub use osm2streets::{
     LaneType, MapConfig, NamePerLanguage, RestrictionType, NORMAL_LANE_THICKNESS,
     SIDEWALK_THICKNESS,
 };
-pub use raw_map::{Amenity, AmenityType, AreaType, CrossingType, ExtraPOI, ExtraPOIType};
+pub use raw_map::{
+    Amenity, AmenityType, AreaType, CrossingType, ExtraPOI, ExtraPOIType, LandUse, TrailType,
+};
 
 pub use crate::city::City;
+pub use crate::congestion_charge::CongestionCharge;
 pub use crate::edits::{
-    EditCmd, EditEffects, EditIntersection, EditIntersectionControl, EditRoad, MapEdits,
-    PermanentMapEdits,
+    EditAnchors, EditCmd, EditEffects, EditIntersection, EditIntersectionControl, EditRoad,
+    MapEdits, MigrationReport, NewTurnRestriction, OsmChange, PermanentEditCmd, PermanentMapEdits,
 };
 
+pub use crate::hazard::Hazard;
+pub use crate::loading_zones::LoadingZones;
 pub use crate::make::RawToMapOptions;
 pub use crate::objects::area::{Area, AreaID};
-pub use crate::objects::building::{Building, BuildingID, BuildingType, OffstreetParking};
+pub use crate::objects::building::{
+    Building, BuildingID, BuildingType, OffstreetParking, BUILDING_HEIGHT_PER_LEVEL,
+};
+pub use crate::objects::ferry::{
+    FerryRoute, FerryRouteID, FerryTerminal, FerryTerminalID, FERRY_CAPACITY, FERRY_DWELL_TIME,
+    FERRY_SPEED,
+};
 pub use crate::objects::intersection::{Intersection, IntersectionID};
 pub use crate::objects::lane::{CommonEndpoint, Lane, LaneID, PARKING_LOT_SPOT_LENGTH};
 pub use crate::objects::modal_filter::{DiagonalFilter, FilterType, RoadFilter};
 pub use crate::objects::movement::{CompressedMovementID, Movement, MovementID};
 pub use crate::objects::parking_lot::{ParkingLot, ParkingLotID};
 pub use crate::objects::road::{
-    Crossing, DirectedRoadID, OriginalRoad, Road, RoadID, RoadSideID, SideOfRoad,
+    Crossing, DirectedRoadID, HovLanes, OriginalRoad, Road, RoadID, RoadSideID, SideOfRoad,
+    DEFAULT_HOT_TOLL,
+};
+pub use crate::objects::site::{Site, SiteID};
+pub use crate::objects::stop_signs::{ControlDefaults, ControlStopSign, RoadWithStopSign};
+pub use crate::objects::traffic_signals::{
+    Actuation, ControlTrafficSignal, Stage, StageType, TransitPriority,
+};
+pub use crate::objects::transit::{
+    BoardingFeatures, TransitRoute, TransitRouteID, TransitStop, TransitStopID,
 };
-pub use crate::objects::stop_signs::{ControlStopSign, RoadWithStopSign};
-pub use crate::objects::traffic_signals::{ControlTrafficSignal, Stage, StageType};
-pub use crate::objects::transit::{TransitRoute, TransitRouteID, TransitStop, TransitStopID};
 pub use crate::objects::turn::{Turn, TurnID, TurnPriority, TurnType};
 pub use crate::objects::zone::{AccessRestrictions, Zone};
+pub use crate::parking_pricing::{ParkingPolicy, ParkingPricing};
+pub use crate::partition::Partitioning;
 pub use crate::pathfind::uber_turns::{IntersectionCluster, UberTurn};
 pub use crate::pathfind::{
     Path, PathConstraints, PathRequest, PathStep, PathStepV2, PathV2, Pathfinder, PathfinderCache,
     PathfinderCaching, RoutingParams,
 };
-pub use crate::traversable::{Position, Traversable, MAX_BIKE_SPEED, MAX_WALKING_SPEED};
+pub use crate::shade::{SunPosition, SUMMER_DAY_OF_YEAR};
+pub use crate::traffic_stress::TrafficStress;
+pub use crate::traversable::{
+    Position, Traversable, MAX_BIKE_SPEED, MAX_EBIKE_SPEED, MAX_WALKING_SPEED,
+};
+pub use crate::weather::Wind;
 pub use map::turn_type_from_angles;
 
 mod city;
+mod congestion_charge;
 pub mod connectivity;
 mod edits;
+mod export;
+mod green_wave;
+mod hazard;
+mod loading_zones;
 mod make;
 mod map;
 mod objects;
+mod parking_pricing;
+mod partition;
 mod pathfind;
+mod shade;
+mod traffic_stress;
 mod traversable;
+mod weather;
 
 // The map used by the simulation and UI. This struct is declared here so that the rest of the
 // crate can reach into private fields.
@@ -100,8 +134,11 @@ pub struct Map {
     )]
     transit_stops: BTreeMap<TransitStopID, TransitStop>,
     transit_routes: Vec<TransitRoute>,
+    ferry_terminals: Vec<FerryTerminal>,
+    ferry_routes: Vec<FerryRoute>,
     areas: Vec<Area>,
     parking_lots: Vec<ParkingLot>,
+    sites: Vec<Site>,
     boundary_polygon: Polygon,
 
     // Note that border nodes belong in neither!
@@ -120,6 +157,12 @@ pub struct Map {
 
     pathfinder: Pathfinder,
     pathfinder_dirty: bool,
+    /// When the pathfinder is stored in a separate file, `pathfinder` is empty, and the real one
+    /// is read from this path the first time something needs it
+    #[serde(skip_serializing, skip_deserializing)]
+    lazy_pathfinder_path: Option<String>,
+    #[serde(skip_serializing, skip_deserializing)]
+    lazy_pathfinder: OnceLock<Pathfinder>,
     routing_params: RoutingParams,
     // Not the source of truth, just cached.
     zones: Vec<Zone>,
@@ -132,6 +175,21 @@ pub struct Map {
     edits: MapEdits,
     #[serde(skip_serializing, skip_deserializing)]
     edits_generation: usize,
+    /// Only map edits set this
+    #[serde(skip_serializing, skip_deserializing)]
+    congestion_charge: Option<CongestionCharge>,
+    /// Only map edits set this
+    #[serde(skip_serializing, skip_deserializing)]
+    parking_pricing: ParkingPricing,
+    /// Only map edits set this
+    #[serde(skip_serializing, skip_deserializing)]
+    loading_zones: LoadingZones,
     #[serde(skip_serializing, skip_deserializing)]
     road_to_buildings: MultiMap<RoadID, BuildingID>,
+    // Not the source of truth, just cached.
+    #[serde(skip_serializing, skip_deserializing)]
+    has_hov_lanes: bool,
+    /// Not part of the map data; whoever loads the map sets this for a simulation
+    #[serde(skip_serializing, skip_deserializing)]
+    wind: Option<Wind>,
 }
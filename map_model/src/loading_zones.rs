@@ -0,0 +1,58 @@
+//! Loading zones set aside part of the curb for delivery vans to unload. Each space replaces one
+//! on-street parking spot at the end of the parking lane. Vans arriving when every space is taken
+//! double-park, blocking the lane. It's part of the map edits, so the congestion impact of
+//! double-parking can be compared against the same demand.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+
+use crate::{DirectedRoadID, LaneID, LaneType, Map};
+
+/// Most curbs don't have a loading zone.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct LoadingZones {
+    /// How many spaces are on the curb of each side of the road. The side needs a parking lane.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub spaces: BTreeMap<DirectedRoadID, usize>,
+}
+
+impl LoadingZones {
+    pub fn is_empty(&self) -> bool {
+        self.spaces.is_empty()
+    }
+
+    /// How many spaces fit on this side of the road. Each one takes up a parking spot.
+    pub fn max_spaces(map: &Map, dr: DirectedRoadID) -> usize {
+        map.get_r(dr.road)
+            .lanes
+            .iter()
+            .filter(|l| l.dir == dr.dir && l.lane_type == LaneType::Parking)
+            .map(|l| l.number_parking_spots(map.get_config()))
+            .sum()
+    }
+
+    /// How many parking spots of one lane the loading zone takes. If a side of the road has a few
+    /// parking lanes, the first ones fill up before the later.
+    pub fn spots_taken_from(&self, map: &Map, l: LaneID) -> usize {
+        let lane = map.get_l(l);
+        let dr = lane.get_directed_parent();
+        let mut remaining = self.spaces.get(&dr).cloned().unwrap_or(0);
+        for other in &map.get_r(dr.road).lanes {
+            if other.dir != dr.dir || other.lane_type != LaneType::Parking {
+                continue;
+            }
+            let taken = remaining.min(other.number_parking_spots(map.get_config()));
+            if other.id == l {
+                return taken;
+            }
+            remaining -= taken;
+        }
+        0
+    }
+}
@@ -7,10 +7,10 @@ use abstutil::{Tags, Timer};
 use geom::{Distance, HashablePt2D, Line};
 use raw_map::RawBuilding;
 
-use crate::make::{match_points_to_lanes, trim_path};
+use crate::make::{driveway_query_pt, match_points_to_lanes, trim_path};
 use crate::{
-    osm, Amenity, Building, BuildingID, BuildingType, LaneID, Map, NamePerLanguage,
-    OffstreetParking,
+    osm, Amenity, Building, BuildingID, BuildingType, LandUse, LaneID, Map, NamePerLanguage,
+    OffstreetParking, BUILDING_HEIGHT_PER_LEVEL,
 };
 
 /// Finalize importing of buildings, mostly by matching them to the nearest sidewalk.
@@ -21,14 +21,16 @@ pub fn make_all_buildings(
     timer: &mut Timer,
 ) -> Vec<Building> {
     timer.start("convert buildings");
-    let mut center_per_bldg: BTreeMap<osm::OsmID, HashablePt2D> = BTreeMap::new();
+    // The building's center, and the point to match to a sidewalk
+    let mut center_per_bldg: BTreeMap<osm::OsmID, (HashablePt2D, HashablePt2D)> = BTreeMap::new();
     let mut query: HashSet<HashablePt2D> = HashSet::new();
     timer.start_iter("get building center points", input.len());
     for (id, b) in input {
         timer.next();
-        let center = b.polygon.center().to_hashable();
-        center_per_bldg.insert(*id, center);
-        query.insert(center);
+        let center = b.polygon.center();
+        let query_pt = driveway_query_pt(map, center).to_hashable();
+        center_per_bldg.insert(*id, (center.to_hashable(), query_pt));
+        query.insert(query_pt);
     }
 
     let sidewalk_buffer = Distance::meters(7.5);
@@ -45,9 +47,9 @@ pub fn make_all_buildings(
 
     let mut results = Vec::new();
     timer.start_iter("match buildings to sidewalks", center_per_bldg.len());
-    for (orig_id, bldg_center) in center_per_bldg {
+    for (orig_id, (bldg_center, query_pt)) in center_per_bldg {
         timer.next();
-        if let Some(sidewalk_pos) = sidewalk_pts.get(&bldg_center) {
+        if let Some(sidewalk_pos) = sidewalk_pts.get(&query_pt) {
             let b = &input[&orig_id];
             let sidewalk_line = match Line::new(bldg_center.to_pt2d(), sidewalk_pos.pt(map)) {
                 Ok(l) => trim_path(&b.polygon, l),
@@ -63,17 +65,13 @@ pub fn make_all_buildings(
             let id = BuildingID(results.len());
 
             let mut rng = XorShiftRng::seed_from_u64(orig_id.inner_id() as u64);
-            // TODO is it worth using height or building:height as an alternative if not tagged?
-            let levels = b
-                .osm_tags
-                .get("building:levels")
-                .and_then(|x| x.parse::<f64>().ok())
-                .unwrap_or(1.0);
+            let (levels, height) = get_levels_and_height(&b.osm_tags);
 
             results.push(Building {
                 id,
                 polygon: b.polygon.clone(),
                 levels,
+                height,
                 address: get_address(&b.osm_tags, sidewalk_pos.lane(), map),
                 name: NamePerLanguage::new(&b.osm_tags),
                 orig_id,
@@ -90,13 +88,24 @@ pub fn make_all_buildings(
                         })
                         .collect()
                 },
-                bldg_type: classify_bldg(
-                    &b.osm_tags,
-                    &b.amenities,
-                    levels,
-                    b.polygon.area(),
-                    &mut rng,
-                ),
+                bldg_type: match b.land_use {
+                    Some(land_use) => classify_by_land_use(
+                        land_use,
+                        &b.osm_tags,
+                        &b.amenities,
+                        levels,
+                        b.polygon.area(),
+                        &mut rng,
+                    ),
+                    None => classify_bldg(
+                        &b.osm_tags,
+                        &b.amenities,
+                        levels,
+                        b.polygon.area(),
+                        &mut rng,
+                    ),
+                },
+                land_use: b.land_use,
                 parking: if let Some(n) = b.public_garage_name.clone() {
                     OffstreetParking::PublicGarage(n, b.num_parking_spots)
                 } else {
@@ -126,6 +135,44 @@ pub fn make_all_buildings(
     results
 }
 
+/// Use whichever of the number of levels and the height is tagged to estimate the other.
+fn get_levels_and_height(tags: &Tags) -> (f64, Distance) {
+    let levels = tags
+        .get("building:levels")
+        .and_then(|x| x.parse::<f64>().ok())
+        .filter(|x| *x > 0.0);
+    let height = tags
+        .get("height")
+        .or_else(|| tags.get("building:height"))
+        .and_then(|x| parse_height(x));
+    match (levels, height) {
+        (Some(levels), Some(height)) => (levels, height),
+        (Some(levels), None) => (levels, BUILDING_HEIGHT_PER_LEVEL * levels),
+        (None, Some(height)) => (
+            (height / BUILDING_HEIGHT_PER_LEVEL).round().max(1.0),
+            height,
+        ),
+        (None, None) => (1.0, BUILDING_HEIGHT_PER_LEVEL),
+    }
+}
+
+/// Heights are in meters by default, but may have units, like `12 m` or `40'`.
+fn parse_height(raw: &str) -> Option<Distance> {
+    let raw = raw.trim();
+    let (number, meters_per_unit) = if let Some(x) = raw.strip_suffix('m') {
+        (x, 1.0)
+    } else if let Some(x) = raw.strip_suffix("ft").or_else(|| raw.strip_suffix('\'')) {
+        (x, 0.3048)
+    } else {
+        (raw, 1.0)
+    };
+    let value = number.trim().parse::<f64>().ok()?;
+    if value <= 0.0 {
+        return None;
+    }
+    Some(Distance::meters(value * meters_per_unit))
+}
+
 // If the house number is missing, just omit it. (In the past, we showed "???" but this was a
 // confusing UX)
 fn get_address(tags: &Tags, sidewalk: LaneID, map: &Map) -> String {
@@ -139,6 +186,78 @@ fn get_address(tags: &Tags, sidewalk: LaneID, map: &Map) -> String {
     }
 }
 
+/// Official land use trumps OSM tags, but the tags still help size the building.
+fn classify_by_land_use(
+    land_use: LandUse,
+    tags: &Tags,
+    amenities: &[Amenity],
+    levels: f64,
+    ground_area_sq_meters: f64,
+    rng: &mut XorShiftRng,
+) -> BuildingType {
+    let area_sq_meters = levels * ground_area_sq_meters;
+    // Same densities as classify_bldg: 1 person per 10 square meters
+    let people = (area_sq_meters / 10.0) as usize;
+    match land_use {
+        LandUse::Residential => {
+            // Keep the OSM guess for houses and the like, but never count workers
+            let residents = match classify_bldg(tags, &[], levels, ground_area_sq_meters, rng) {
+                BuildingType::Residential { num_residents, .. } => num_residents,
+                BuildingType::ResidentialCommercial(residents, _) => residents,
+                // Tagged as an office or shop, but zoned residential
+                BuildingType::Commercial(_) => people,
+                // Sheds and garages aren't homes
+                BuildingType::Empty => {
+                    return BuildingType::Residential {
+                        num_residents: 0,
+                        num_housing_units: 0,
+                    };
+                }
+            };
+            BuildingType::Residential {
+                num_residents: residents,
+                num_housing_units: estimate_housing_units(tags, area_sq_meters),
+            }
+        }
+        LandUse::MixedUse => BuildingType::ResidentialCommercial(people / 2, people / 2),
+        LandUse::Commercial | LandUse::Retail | LandUse::Institutional => {
+            BuildingType::Commercial(people)
+        }
+        // Warehouses and factories have far fewer people per square meter
+        LandUse::Industrial => BuildingType::Commercial(people / 5),
+        LandUse::OpenSpace => {
+            if amenities.is_empty() {
+                BuildingType::Empty
+            } else {
+                BuildingType::Commercial(0)
+            }
+        }
+    }
+}
+
+/// Houses are one home. Anything else zoned residential is assumed to be split into flats of a
+/// typical size.
+fn estimate_housing_units(tags: &Tags, area_sq_meters: f64) -> usize {
+    // A modest flat, including its share of hallways and stairs
+    const SQ_METERS_PER_UNIT: f64 = 80.0;
+
+    if tags.is_any(
+        "building",
+        vec![
+            "house",
+            "detached",
+            "semidetached_house",
+            "farm",
+            "hut",
+            "static_caravan",
+            "cabin",
+        ],
+    ) {
+        return 1;
+    }
+    ((area_sq_meters / SQ_METERS_PER_UNIT).round() as usize).max(1)
+}
+
 fn classify_bldg(
     tags: &Tags,
     amenities: &[Amenity],
@@ -241,3 +360,36 @@ fn classify_bldg(
         num_housing_units: 1,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meters(raw: &str) -> Option<f64> {
+        parse_height(raw).map(|d| (d.inner_meters() * 100.0).round() / 100.0)
+    }
+
+    #[test]
+    fn test_parse_height() {
+        assert_eq!(meters("12"), Some(12.0));
+        assert_eq!(meters("12.5 m"), Some(12.5));
+        assert_eq!(meters(" 9m "), Some(9.0));
+        assert_eq!(meters("40'"), Some(12.19));
+        assert_eq!(meters("40 ft"), Some(12.19));
+        assert_eq!(meters("0"), None);
+        assert_eq!(meters("-3"), None);
+        assert_eq!(meters("tall"), None);
+    }
+
+    #[test]
+    fn test_estimate_housing_units() {
+        let tags = |building: &str| {
+            let mut map = BTreeMap::new();
+            map.insert("building".to_string(), building.to_string());
+            Tags::new(map)
+        };
+        assert_eq!(estimate_housing_units(&tags("house"), 400.0), 1);
+        assert_eq!(estimate_housing_units(&tags("apartments"), 4000.0), 50);
+        assert_eq!(estimate_housing_units(&tags("yes"), 20.0), 1);
+    }
+}
@@ -0,0 +1,93 @@
+//! OSM maps a street divided by a median as two separate oneway roads, one per direction. Detect
+//! these dual carriageways and link the sides together, so that both can be treated as one street,
+//! like when editing.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use geom::{Distance, FindClosest, PolyLine};
+
+use crate::{Direction, Map, RoadID};
+
+// The two sides of a dual carriageway are at most this far apart
+const MAX_SEPARATION: Distance = Distance::const_meters(40.0);
+// How close to exactly opposite the directions of travel must be
+const MAX_ANGLE_DIFFERENCE_DEGREES: f64 = 30.0;
+
+/// Finds pairs of roads on opposite sides of the same dual carriageway. Both must be oneway for
+/// driving in roughly opposite directions, have the same name, and run alongside each other.
+pub fn find_dual_carriageways(map: &Map) -> Vec<(RoadID, RoadID)> {
+    let mut closest: FindClosest<RoadID> = FindClosest::new();
+    let mut candidates = Vec::new();
+    for r in map.all_roads() {
+        if !r.is_driveable() || r.is_slip_lane() || r.osm_tags.get("name").is_none() {
+            continue;
+        }
+        if let Some(dir) = r.oneway_for_driving() {
+            closest.add(r.id, r.center_pts.points());
+            candidates.push((r.id, dir));
+        }
+    }
+    let travel_angle = |r: RoadID, dir: Direction| {
+        let pl = &map.get_r(r).center_pts;
+        let angle = pl.first_pt().angle_to(pl.last_pt());
+        if dir == Direction::Fwd {
+            angle
+        } else {
+            angle.opposite()
+        }
+    };
+
+    let mut pairs = BTreeSet::new();
+    for (r1, dir1) in &candidates {
+        let road1 = map.get_r(*r1);
+        let name = road1.osm_tags.get("name");
+        let angle1 = travel_angle(*r1, *dir1);
+        for pct in [0.25, 0.5, 0.75] {
+            let (pt, angle) = road1.center_pts.must_dist_along(pct * road1.length());
+            // Look straight across the median from this point
+            let probe = PolyLine::must_new(vec![
+                pt.project_away(MAX_SEPARATION, angle.rotate_degs(90.0)),
+                pt.project_away(MAX_SEPARATION, angle.rotate_degs(-90.0)),
+            ]);
+            for (r2, _, _) in closest.all_close_pts(pt, MAX_SEPARATION) {
+                let pair = ((*r1).min(r2), (*r1).max(r2));
+                if *r1 == r2 || pairs.contains(&pair) {
+                    continue;
+                }
+                let road2 = map.get_r(r2);
+                if road2.osm_tags.get("name") != name
+                    || probe.intersection(&road2.center_pts).is_none()
+                {
+                    continue;
+                }
+                let dir2 = road2.oneway_for_driving().unwrap();
+                if angle1
+                    .opposite()
+                    .approx_eq(travel_angle(r2, dir2), MAX_ANGLE_DIFFERENCE_DEGREES)
+                {
+                    pairs.insert(pair);
+                }
+            }
+        }
+    }
+    pairs.into_iter().collect()
+}
+
+impl Map {
+    /// All segments of the dual carriageway this road belongs to, on both sides and including
+    /// this road. If the road isn't divided, just returns the road itself.
+    pub fn dual_carriageway(&self, r: RoadID) -> BTreeSet<RoadID> {
+        let mut members = BTreeSet::new();
+        members.insert(r);
+        let mut queue = VecDeque::new();
+        queue.push_back(r);
+        while let Some(current) = queue.pop_front() {
+            for other in &self.get_r(current).opposite_carriageway {
+                if members.insert(*other) {
+                    queue.push_back(*other);
+                }
+            }
+        }
+        members
+    }
+}
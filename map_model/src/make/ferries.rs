@@ -0,0 +1,101 @@
+use std::collections::{HashMap, HashSet};
+
+use abstutil::Timer;
+use geom::{Distance, Duration, HashablePt2D, Time};
+use raw_map::RawMap;
+
+use crate::make::match_points_to_lanes;
+use crate::{FerryRoute, FerryRouteID, FerryTerminal, FerryTerminalID, Map};
+
+pub fn make_ferries(map: &mut Map, raw: &RawMap, timer: &mut Timer) {
+    // Terminals are on the shore, often at the end of a pier, so look much further away than for
+    // bus stops
+    let mut query: HashSet<HashablePt2D> = HashSet::new();
+    for route in &raw.ferry_routes {
+        for (_, pt) in &route.terminals {
+            query.insert(pt.to_hashable());
+        }
+    }
+    let sidewalk_pts = match_points_to_lanes(
+        map,
+        query,
+        |l| l.is_walkable(),
+        Distance::ZERO,
+        Distance::meters(100.0),
+        timer,
+    );
+
+    // Routes may share a terminal
+    let mut terminals: HashMap<HashablePt2D, FerryTerminalID> = HashMap::new();
+    for route in &raw.ferry_routes {
+        let mut stops: Vec<(Distance, FerryTerminalID)> = Vec::new();
+        for (name, pt) in &route.terminals {
+            let sidewalk_pos = match sidewalk_pts.get(&pt.to_hashable()) {
+                Some(pos) => *pos,
+                None => {
+                    warn!(
+                        "Ferry terminal {} on {} isn't near a sidewalk",
+                        name, route.name
+                    );
+                    continue;
+                }
+            };
+            let dist = match route.shape.dist_along_of_point(route.shape.project_pt(*pt)) {
+                Some((dist, _)) => dist,
+                None => {
+                    continue;
+                }
+            };
+            let id = *terminals.entry(pt.to_hashable()).or_insert_with(|| {
+                let id = FerryTerminalID(map.ferry_terminals.len());
+                map.ferry_terminals.push(FerryTerminal {
+                    id,
+                    name: name.clone(),
+                    pt: *pt,
+                    sidewalk_pos,
+                });
+                id
+            });
+            stops.push((dist, id));
+        }
+        // Relations don't always list terminals in order along the water
+        stops.sort_by_key(|(dist, _)| *dist);
+        stops.dedup_by_key(|(_, id)| *id);
+        let (dists, ids): (Vec<Distance>, Vec<FerryTerminalID>) = stops.into_iter().unzip();
+        if ids.len() < 2 {
+            warn!("Ferry route {} doesn't connect two terminals", route.name);
+            continue;
+        }
+
+        // Only keep the water between the first and last terminal
+        let path = match route
+            .shape
+            .maybe_exact_slice(dists[0], *dists.last().unwrap())
+        {
+            Ok(path) => path,
+            Err(err) => {
+                warn!("Ferry route {} has a bad path: {}", route.name, err);
+                continue;
+            }
+        };
+
+        // Without a timetable, run every 30 minutes
+        let spawn_times: Vec<Time> = if route.spawn_times.is_empty() {
+            (0..48)
+                .map(|i| Time::START_OF_DAY + (i as f64) * Duration::minutes(30))
+                .collect()
+        } else {
+            route.spawn_times.clone()
+        };
+
+        map.ferry_routes.push(FerryRoute {
+            id: FerryRouteID(map.ferry_routes.len()),
+            name: route.name.clone(),
+            osm_id: route.osm_id,
+            terminals: ids,
+            terminal_dists: dists.iter().map(|d| *d - dists[0]).collect(),
+            path,
+            spawn_times,
+        });
+    }
+}
@@ -2,8 +2,9 @@
 //! covers the RawMap->Map stage.
 
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, OnceLock, RwLock};
 
+use enumset::EnumSet;
 use structopt::StructOpt;
 
 use abstutil::{MultiMap, Tags, Timer};
@@ -11,19 +12,25 @@ use geom::{
     Distance, FindClosest, HashablePt2D, Line, PolyLine, Polygon, Pt2D, Speed, EPSILON_DIST,
 };
 use osm2streets::Transformation;
-use raw_map::RawMap;
+use raw_map::{BarrierType, RawMap};
 
 pub use self::parking_lots::snap_driveway;
+pub use self::sites::driveway_query_pt;
 use crate::pathfind::{CreateEngine, Pathfinder};
 use crate::{
     connectivity, osm, AccessRestrictions, Area, AreaID, ControlStopSign, ControlTrafficSignal,
-    Intersection, IntersectionControl, IntersectionID, IntersectionKind, Lane, LaneID, Map,
-    MapEdits, OriginalRoad, PathConstraints, Position, Road, RoadID, RoutingParams, Zone,
+    DrivingSide, FilterType, Intersection, IntersectionControl, IntersectionID, IntersectionKind,
+    Lane, LaneID, LoadingZones, Map, MapEdits, OriginalRoad, ParkingPricing, PathConstraints,
+    Position, Road, RoadFilter, RoadID, RoutingParams, Zone,
 };
 
 mod bridges;
 mod buildings;
+mod dual_carriageways;
+mod ferries;
 mod parking_lots;
+mod sites;
+mod slip_lanes;
 pub mod traffic_signals;
 pub mod transit;
 pub mod turns;
@@ -53,8 +60,11 @@ impl Map {
             buildings: Vec::new(),
             transit_stops: BTreeMap::new(),
             transit_routes: Vec::new(),
+            ferry_terminals: Vec::new(),
+            ferry_routes: Vec::new(),
             areas: Vec::new(),
             parking_lots: Vec::new(),
+            sites: Vec::new(),
             zones: Vec::new(),
             census_zones: raw.census_zones.clone(),
             extra_pois: raw.extra_pois.clone(),
@@ -67,11 +77,18 @@ impl Map {
             config: raw.streets.config.clone(),
             pathfinder: Pathfinder::empty(),
             pathfinder_dirty: false,
+            lazy_pathfinder_path: None,
+            lazy_pathfinder: OnceLock::new(),
             routing_params: RoutingParams::default(),
             name: raw.name.clone(),
             edits: MapEdits::new(),
             edits_generation: 0,
+            congestion_charge: None,
+            parking_pricing: ParkingPricing::default(),
+            loading_zones: LoadingZones::default(),
             road_to_buildings: MultiMap::new(),
+            has_hov_lanes: false,
+            wind: None,
         };
         map.edits = map.new_edits();
 
@@ -109,6 +126,9 @@ impl Map {
                 outgoing_lanes: Vec::new(),
                 roads: i.roads.iter().map(|id| road_id_mapping[id]).collect(),
                 modal_filter: None,
+                allow_u_turns: false,
+                protected_corners: false,
+                no_turn_on_red: BTreeSet::new(),
                 merged: !raw.streets.intersections[&i.id]
                     .trim_roads_for_merging
                     .is_empty(),
@@ -125,7 +145,7 @@ impl Map {
             let i2 = intersection_id_mapping[&r.dst_i];
 
             let extra = &raw.extra_road_data[&r.id];
-            let barrier_nodes = snap_nodes_to_line(&extra.barrier_nodes, &r.center_line);
+            let barriers = snap_nodes_with_data_to_line(&extra.barrier_nodes, &r.center_line);
             let crossing_nodes =
                 snap_nodes_with_data_to_line(&extra.crossing_nodes, &r.center_line);
 
@@ -189,16 +209,40 @@ impl Map {
                 percent_incline: extra.percent_incline,
                 crosswalk_forward: extra.crosswalk_forward,
                 crosswalk_backward: extra.crosswalk_backward,
+                trail: extra.trail,
                 transit_stops: BTreeSet::new(),
-                modal_filter: None,
-                barrier_nodes,
+                // Existing bollards and bus gates are modal filters
+                modal_filter: barriers.iter().find_map(|(dist, barrier)| match barrier {
+                    BarrierType::WalkCycleOnly => {
+                        Some(RoadFilter::new(*dist, FilterType::WalkCycleOnly))
+                    }
+                    BarrierType::BusGate => Some(RoadFilter::new(*dist, FilterType::BusGate)),
+                    BarrierType::PrivateGate => None,
+                }),
+                barrier_nodes: barriers
+                    .iter()
+                    .filter(|(_, barrier)| *barrier != BarrierType::PrivateGate)
+                    .map(|(dist, _)| *dist)
+                    .collect(),
                 crossing_nodes,
                 crossings: Vec::new(),
+                slip_lane_for: None,
+                opposite_carriageway: BTreeSet::new(),
+                hov_lanes: None,
             };
             road.speed_limit = road.speed_limit_from_osm();
             road.access_restrictions = road.access_restrictions_from_osm();
+            road.crossings = road.crossings_from_osm();
+            // Behind a private gate, the road is only for people going somewhere there
+            if barriers
+                .iter()
+                .any(|(_, barrier)| *barrier == BarrierType::PrivateGate)
+            {
+                road.access_restrictions.allow_through_traffic = EnumSet::new();
+            }
 
             road.recreate_lanes(r.lane_specs_ltr.clone());
+            road.hov_lanes = road.hov_lanes_from_osm(&r.lane_specs_ltr);
             for lane in &road.lanes {
                 map.intersections[lane.src_i.0].outgoing_lanes.push(lane.id);
                 map.intersections[lane.dst_i.0].incoming_lanes.push(lane.id);
@@ -206,6 +250,7 @@ impl Map {
 
             map.roads.push(road);
         }
+        map.recalculate_has_hov_lanes();
 
         for i in map.intersections.iter_mut() {
             if i.is_border() && i.roads.len() != 1 {
@@ -242,6 +287,24 @@ impl Map {
             }
         }
 
+        for (r, i) in slip_lanes::find_slip_lanes(&map) {
+            map.roads[r.0].slip_lane_for = Some(i);
+        }
+        for (r1, r2) in dual_carriageways::find_dual_carriageways(&map) {
+            map.roads[r1.0].opposite_carriageway.insert(r2);
+            map.roads[r2.0].opposite_carriageway.insert(r1);
+        }
+
+        for (raw_id, id) in &intersection_id_mapping {
+            let node_tags = raw.streets.intersections[raw_id]
+                .osm_ids
+                .iter()
+                .filter_map(|n| raw.red_turn_tags.get(n))
+                .collect();
+            let no_turn_on_red = find_no_turn_on_red(&map, *id, node_tags);
+            map.intersections[id.0].no_turn_on_red = no_turn_on_red;
+        }
+
         let mut all_turns = Vec::new();
         let mut connectivity_problems = 0;
         for i in &map.intersections {
@@ -280,8 +343,18 @@ impl Map {
         }
         timer.stop("find blackholes");
 
+        map.sites = sites::make_all_sites(&raw.sites, &map);
         map.buildings =
             buildings::make_all_buildings(&raw.buildings, &map, opts.keep_bldg_tags, timer);
+        for b in &map.buildings {
+            if let Some(site) = map
+                .sites
+                .iter_mut()
+                .find(|site| site.polygon.contains_pt(b.polygon.center()))
+            {
+                site.buildings.push(b.id);
+            }
+        }
 
         map.parking_lots = parking_lots::make_all_parking_lots(
             &raw.parking_lots,
@@ -321,11 +394,28 @@ impl Map {
                         error!("Traffic signal at {} downgraded to stop sign, because it has no movements -- probably roads under construction", i.orig_id);
                         stop_signs.insert(i.id, ControlStopSign::new(&map, i.id));
                     } else {
+                        let imported =
+                            raw.traffic_signal_programs
+                                .get(&i.orig_id)
+                                .and_then(|program| {
+                                    match traffic_signals::from_program(&map, i.id, program) {
+                                        Ok(ts) => Some(ts),
+                                        Err(err) => {
+                                            warn!(
+                                                "Can't use the signal program for {}: {}",
+                                                i.orig_id, err
+                                            );
+                                            None
+                                        }
+                                    }
+                                });
                         traffic_signals.insert(
                             i.id,
-                            ControlTrafficSignal::get_possible_policies(&map, i.id)
-                                .remove(0)
-                                .1,
+                            imported.unwrap_or_else(|| {
+                                ControlTrafficSignal::get_possible_policies(&map, i.id)
+                                    .remove(0)
+                                    .1
+                            }),
                         );
                     }
                 }
@@ -351,6 +441,7 @@ impl Map {
         timer.stop("setup pathfinding");
 
         transit::finalize_transit(&mut map, &raw, timer);
+        ferries::make_ferries(&mut map, &raw, timer);
         timer.start("setup pathfinding for people using transit");
         let mut pathfinder = std::mem::replace(&mut map.pathfinder, Pathfinder::empty());
         pathfinder.finalize_transit(&map, &engine);
@@ -433,18 +524,44 @@ pub fn trim_path(poly: &Polygon, path: Line) -> Line {
     path
 }
 
-// TODO Duplicate code with below. One caller needs data, the other doesn't and adding fake data is
-// annoying
-fn snap_nodes_to_line(pts: &[Pt2D], pl: &PolyLine) -> Vec<Distance> {
-    let mut results = Vec::new();
-    for pt in pts {
-        let projected = pl.project_pt(*pt);
-        // TODO Check distance isn't too crazy? Not sure why it would be
-        if let Some((dist, _)) = pl.dist_along_of_point(projected) {
-            results.push(dist);
+/// Which incoming roads ban turning on red? The jurisdiction sets a default, but `red_turn:right`
+/// (or `red_turn:left` where people drive on the left) on the intersection node or the incoming
+/// way overrides it.
+fn find_no_turn_on_red(map: &Map, i: IntersectionID, node_tags: Vec<&Tags>) -> BTreeSet<RoadID> {
+    let key = match map.config.driving_side {
+        DrivingSide::Right => "red_turn:right",
+        DrivingSide::Left => "red_turn:left",
+    };
+    let default = node_tags
+        .into_iter()
+        .find_map(|tags| parse_red_turn(tags, key))
+        .unwrap_or(map.config.turn_on_red);
+
+    let mut banned = BTreeSet::new();
+    for r in &map.get_i(i).roads {
+        let road = map.get_r(*r);
+        // Which direction along the way leads into this intersection?
+        let dir = if road.dst_i == i {
+            "forward"
+        } else {
+            "backward"
+        };
+        let allowed = parse_red_turn(&road.osm_tags, &format!("{}:{}", key, dir))
+            .or_else(|| parse_red_turn(&road.osm_tags, key))
+            .unwrap_or(default);
+        if !allowed {
+            banned.insert(*r);
         }
     }
-    results
+    banned
+}
+
+fn parse_red_turn(tags: &Tags, key: &str) -> Option<bool> {
+    match tags.get(key).map(|x| x.as_str()) {
+        Some("no") => Some(false),
+        Some("yes") => Some(true),
+        _ => None,
+    }
 }
 
 fn snap_nodes_with_data_to_line<T: Clone>(
@@ -0,0 +1,53 @@
+use geom::{PolyLine, Pt2D};
+use raw_map::RawSite;
+
+use crate::{Map, NamePerLanguage, Site, SiteID};
+
+/// Finds where each large site meets the public road network. Any road with exactly one end inside
+/// the site is an access road, and the access point is at its other end.
+pub fn make_all_sites(input: &[RawSite], map: &Map) -> Vec<Site> {
+    let mut results = Vec::new();
+    for raw in input {
+        let mut access_points = Vec::new();
+        for r in map.all_roads() {
+            let src_inside = raw.polygon.contains_pt(r.center_pts.first_pt());
+            let dst_inside = raw.polygon.contains_pt(r.center_pts.last_pt());
+            if src_inside && !dst_inside {
+                access_points.push((r.id, r.center_pts.last_pt()));
+            } else if !src_inside && dst_inside {
+                access_points.push((r.id, r.center_pts.first_pt()));
+            }
+        }
+        if access_points.is_empty() {
+            warn!(
+                "Nothing connects to the site {}, so its buildings will snap to the nearest sidewalk",
+                raw.osm_id
+            );
+        }
+
+        results.push(Site {
+            id: SiteID(results.len()),
+            polygon: raw.polygon.clone(),
+            name: NamePerLanguage::new(&raw.osm_tags),
+            osm_id: raw.osm_id,
+            aisles: raw
+                .aisles
+                .iter()
+                .filter_map(|(_, pts)| PolyLine::new(pts.clone()).ok())
+                .collect(),
+            access_points,
+            buildings: Vec::new(),
+        });
+    }
+    results
+}
+
+/// Buildings inside a site connect to the sidewalk closest to one of its access points, instead of
+/// whatever sidewalk happens to be nearest. Returns the point to snap to the sidewalk.
+pub fn driveway_query_pt(map: &Map, bldg_center: Pt2D) -> Pt2D {
+    map.all_sites()
+        .iter()
+        .find(|site| site.polygon.contains_pt(bldg_center))
+        .and_then(|site| site.closest_access_point(bldg_center))
+        .unwrap_or(bldg_center)
+}
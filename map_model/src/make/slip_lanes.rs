@@ -0,0 +1,69 @@
+//! Slip lanes (also called channelized turn lanes) let drivers make the easy turn without going
+//! through an intersection, by cutting across the corner. A triangular island usually separates
+//! them from the main intersection. They're fast for drivers and uncomfortable for pedestrians,
+//! so removing them is a common intervention.
+
+use geom::Distance;
+
+use crate::{turn_type_from_angles, Direction, DrivingSide, IntersectionID, Map, RoadID, TurnType};
+
+// Anything longer is probably a real street cutting a corner, not a slip lane
+const MAX_LENGTH: Distance = Distance::const_meters(100.0);
+
+/// Finds every slip lane, along with the intersection it bypasses.
+pub fn find_slip_lanes(map: &Map) -> Vec<(RoadID, IntersectionID)> {
+    let easy_turn = match map.get_config().driving_side {
+        DrivingSide::Right => TurnType::Right,
+        DrivingSide::Left => TurnType::Left,
+    };
+
+    let mut results = Vec::new();
+    for road in map.all_roads() {
+        if road.length() > MAX_LENGTH || road.is_light_rail() {
+            continue;
+        }
+        // Where does traffic enter and leave the slip lane?
+        let (from, to) = match road.oneway_for_driving() {
+            Some(Direction::Fwd) => (road.src_i, road.dst_i),
+            Some(Direction::Back) => (road.dst_i, road.src_i),
+            None => {
+                continue;
+            }
+        };
+
+        // The slip lane is one side of a triangle. The bypassed intersection is the third corner,
+        // connected to both ends by other driveable roads.
+        for r1 in &map.get_i(from).roads {
+            let r1 = map.get_r(*r1);
+            if r1.id == road.id || !r1.is_driveable() {
+                continue;
+            }
+            let corner = r1.other_endpt(from);
+            if corner == to {
+                continue;
+            }
+            let reaches_to = map.get_i(corner).roads.iter().any(|r2| {
+                let r2 = map.get_r(*r2);
+                r2.id != road.id
+                    && r2.id != r1.id
+                    && r2.is_driveable()
+                    && r2.other_endpt(corner) == to
+            });
+            if !reaches_to {
+                continue;
+            }
+
+            // Cutting the corner must be the easy turn, not the hard one
+            let from_pt = map.get_i(from).polygon.center();
+            let turn_type = turn_type_from_angles(
+                from_pt.angle_to(map.get_i(corner).polygon.center()),
+                from_pt.angle_to(map.get_i(to).polygon.center()),
+            );
+            if turn_type == easy_turn {
+                results.push((road.id, corner));
+                break;
+            }
+        }
+    }
+    results
+}
@@ -7,11 +7,15 @@
 
 use std::collections::{BTreeSet, HashSet};
 
+use anyhow::Result;
+
 use crate::{
-    ControlTrafficSignal, DrivingSide, Intersection, IntersectionCluster, IntersectionID, Map,
-    MapConfig, MovementID, RoadID, Stage, StageType, TurnPriority, TurnType,
+    osm, ControlTrafficSignal, DirectedRoadID, Direction, DrivingSide, Intersection,
+    IntersectionCluster, IntersectionID, Map, MapConfig, MovementID, RoadID, Stage, StageType,
+    TurnPriority, TurnType,
 };
 use geom::Duration;
+use raw_map::RawSignalProgram;
 
 mod lagging_green;
 
@@ -69,11 +73,95 @@ pub fn get_possible_policies(map: &Map, id: IntersectionID) -> Vec<(String, Cont
     results
 }
 
+/// Use a signal program from the source data. Movements the program doesn't mention become
+/// permitted whenever something else from the same road is protected, and crosswalks are
+/// protected whenever nothing conflicts. Fails if the result doesn't cover every movement.
+pub fn from_program(
+    map: &Map,
+    id: IntersectionID,
+    program: &RawSignalProgram,
+) -> Result<ControlTrafficSignal> {
+    let i = map.get_i(id);
+    let find_road = |way: osm::WayID, incoming: bool| -> Option<DirectedRoadID> {
+        let road = i
+            .roads
+            .iter()
+            .map(|r| map.get_r(*r))
+            .find(|r| r.orig_id.osm_way_id == way)?;
+        let dir = if (road.dst_i == id) == incoming {
+            Direction::Fwd
+        } else {
+            Direction::Back
+        };
+        Some(DirectedRoadID { road: road.id, dir })
+    };
+    let find_movement = |(from, to): &(osm::WayID, osm::WayID)| -> Option<MovementID> {
+        let m = MovementID {
+            from: find_road(*from, true)?,
+            to: find_road(*to, false)?,
+            parent: id,
+            crosswalk: false,
+        };
+        if i.movements.contains_key(&m) {
+            Some(m)
+        } else {
+            None
+        }
+    };
+
+    let mut ts = new(id);
+    ts.offset = program.offset;
+    for raw_stage in &program.stages {
+        let mut stage = Stage::new();
+        stage.stage_type = StageType::Fixed(raw_stage.duration);
+        for m in raw_stage.protected.iter().filter_map(find_movement) {
+            // Some sources mark conflicting movements as protected; only one can win
+            if stage.could_be_protected(m, i) {
+                stage.protected_movements.insert(m);
+            } else {
+                stage.yield_movements.insert(m);
+            }
+        }
+        for m in raw_stage.permitted.iter().filter_map(find_movement) {
+            if !stage.protected_movements.contains(&m) {
+                stage.yield_movements.insert(m);
+            }
+        }
+        ts.stages.push(stage);
+    }
+
+    for m in ts.missing_turns(i) {
+        if m.crosswalk {
+            for stage in &mut ts.stages {
+                // Don't change the timing to fit a crosswalk in
+                let mut lengthened = stage.clone();
+                lengthened.enforce_minimum_crosswalk_time(&i.movements[&m]);
+                if stage.could_be_protected(m, i) && lengthened.stage_type == stage.stage_type {
+                    stage.protected_movements.insert(m);
+                }
+            }
+        } else {
+            let has_green = |s: &Stage| s.protected_movements.iter().any(|x| x.from == m.from);
+            let any_green = ts.stages.iter().any(has_green);
+            for stage in &mut ts.stages {
+                if !any_green || has_green(stage) {
+                    stage.yield_movements.insert(m);
+                }
+            }
+        }
+    }
+
+    ts.validate(i)?;
+    Ok(ts)
+}
+
 fn new(id: IntersectionID) -> ControlTrafficSignal {
     ControlTrafficSignal {
         id,
         stages: Vec::new(),
         offset: Duration::ZERO,
+        actuation: None,
+        transit_priority: None,
     }
 }
 
@@ -351,7 +439,7 @@ fn make_stages(
 
                 // If turn on red is banned, ignore movements when the stage has
                 // no protected (green) movement from that road
-                if !map_config.turn_on_red
+                if i.no_turn_on_red.contains(&movement.id.from.road)
                     && !specs.iter().any(|(other_roads, _, other_protected)| {
                         *other_protected && other_roads.contains(&movement.id.from.road)
                     })
@@ -8,19 +8,32 @@ use raw_map::{RawMap, RawTransitRoute, RawTransitStop, RawTransitType};
 
 use crate::make::match_points_to_lanes;
 use crate::{
-    LaneID, Map, PathConstraints, Position, TransitRoute, TransitRouteID, TransitStop,
-    TransitStopID,
+    BoardingFeatures, LaneID, Map, PathConstraints, Position, TransitRoute, TransitRouteID,
+    TransitStop, TransitStopID,
 };
 
 pub fn finalize_transit(map: &mut Map, raw: &RawMap, timer: &mut Timer) {
+    // Stops served by trains snap to tracks, not to the road next to the sidewalk
+    let mut train_stops: HashSet<&String> = raw
+        .transit_routes
+        .iter()
+        .filter(|r| r.route_type == RawTransitType::Train)
+        .flat_map(|r| r.stops.iter())
+        .collect();
+
     // Snap stops to sidewalks and driving lanes, similar to buildings
-    let mut query: HashSet<HashablePt2D> = HashSet::new();
+    let mut bus_query: HashSet<HashablePt2D> = HashSet::new();
+    let mut train_query: HashSet<HashablePt2D> = HashSet::new();
     for stop in raw.transit_stops.values() {
-        query.insert(stop.position.to_hashable());
+        if train_stops.contains(&stop.gtfs_id) {
+            train_query.insert(stop.position.to_hashable());
+        } else {
+            bus_query.insert(stop.position.to_hashable());
+        }
     }
-    let sidewalk_pts = match_points_to_lanes(
+    let mut sidewalk_pts = match_points_to_lanes(
         map,
-        query,
+        bus_query,
         |l| l.is_walkable(),
         // Stops can be very close to intersections
         Distance::ZERO,
@@ -28,18 +41,72 @@ pub fn finalize_transit(map: &mut Map, raw: &RawMap, timer: &mut Timer) {
         Distance::meters(3.0),
         timer,
     );
+    // Tram stops are often in the middle of the street, and platforms for light rail might be
+    // separated from the nearest sidewalk
+    sidewalk_pts.extend(match_points_to_lanes(
+        map,
+        train_query.clone(),
+        |l| l.is_walkable(),
+        Distance::ZERO,
+        Distance::meters(30.0),
+        timer,
+    ));
+    let track_pts = match_points_to_lanes(
+        map,
+        train_query,
+        |l| l.is_light_rail(),
+        Distance::ZERO,
+        Distance::meters(15.0),
+        timer,
+    );
+
+    // Trams sharing a lane with cars don't have any track nearby. Run them on the driving lanes
+    // instead, like buses.
+    let street_running: HashSet<&String> = raw
+        .transit_routes
+        .iter()
+        .filter(|r| {
+            r.route_type == RawTransitType::Train
+                && !r.stops.iter().any(|gtfs_id| {
+                    raw.transit_stops
+                        .get(gtfs_id)
+                        .map(|stop| track_pts.contains_key(&stop.position.to_hashable()))
+                        .unwrap_or(false)
+                })
+        })
+        .map(|r| &r.gtfs_id)
+        .collect();
+    train_stops = raw
+        .transit_routes
+        .iter()
+        .filter(|r| r.route_type == RawTransitType::Train && !street_running.contains(&r.gtfs_id))
+        .flat_map(|r| r.stops.iter())
+        .collect();
 
     // Create all stops
     let mut gtfs_to_stop_id: HashMap<String, TransitStopID> = HashMap::new();
     for stop in raw.transit_stops.values() {
-        if let Err(err) = create_stop(stop, &sidewalk_pts, &mut gtfs_to_stop_id, map) {
+        let vehicle = if train_stops.contains(&stop.gtfs_id) {
+            PathConstraints::Train
+        } else {
+            PathConstraints::Bus
+        };
+        if let Err(err) = create_stop(
+            stop,
+            vehicle,
+            &sidewalk_pts,
+            &track_pts,
+            &mut gtfs_to_stop_id,
+            map,
+        ) {
             warn!("Couldn't create stop {}: {}", stop.gtfs_id, err);
         }
     }
 
     let snapper = BorderSnapper::new(map);
     for route in &raw.transit_routes {
-        if let Err(err) = create_route(route, map, &gtfs_to_stop_id, &snapper) {
+        let street_running = street_running.contains(&route.gtfs_id);
+        if let Err(err) = create_route(route, street_running, map, &gtfs_to_stop_id, &snapper) {
             warn!(
                 "Couldn't snap route {} ({}): {}",
                 route.gtfs_id, route.short_name, err
@@ -52,19 +119,28 @@ pub fn finalize_transit(map: &mut Map, raw: &RawMap, timer: &mut Timer) {
 
 fn create_stop(
     stop: &RawTransitStop,
+    vehicle: PathConstraints,
     sidewalk_pts: &HashMap<HashablePt2D, Position>,
+    track_pts: &HashMap<HashablePt2D, Position>,
     gtfs_to_stop_id: &mut HashMap<String, TransitStopID>,
     map: &mut Map,
 ) -> Result<()> {
-    // TODO We'll have to look up all routes referencing this stop and determine this
-    let vehicle = PathConstraints::Bus;
     if let Some(sidewalk_pos) = sidewalk_pts.get(&stop.position.to_hashable()) {
         let sidewalk_lane = sidewalk_pos.lane();
-        if let Some(driving_pos) = map
+        // Street-running trams share the road with the sidewalk. Otherwise, use the closest
+        // track.
+        let driving_pos = map
             .get_parent(sidewalk_lane)
             .find_closest_lane(sidewalk_lane, |l| vehicle.can_use(l, map))
             .map(|l| sidewalk_pos.equiv_pos(l, map))
-        {
+            .or_else(|| {
+                if vehicle == PathConstraints::Train {
+                    track_pts.get(&stop.position.to_hashable()).cloned()
+                } else {
+                    None
+                }
+            });
+        if let Some(driving_pos) = driving_pos {
             let road = sidewalk_lane.road;
             let id = TransitStopID {
                 road,
@@ -80,13 +156,15 @@ fn create_stop(
                     driving_pos,
                     sidewalk_pos: *sidewalk_pos,
                     is_train_stop: vehicle == PathConstraints::Train,
+                    boarding: BoardingFeatures::default(),
+                    closed: false,
                 },
             );
             gtfs_to_stop_id.insert(stop.gtfs_id.clone(), id);
             Ok(())
         } else {
             bail!(
-                "Couldn't find a lane for {:?} next to sidewalk {}",
+                "Couldn't find a lane for {:?} near sidewalk {}",
                 vehicle,
                 sidewalk_lane
             );
@@ -141,8 +219,10 @@ impl BorderSnapper {
     }
 }
 
+/// If `street_running`, a train route runs on driving lanes instead of tracks.
 fn create_route(
     route: &RawTransitRoute,
+    street_running: bool,
     map: &mut Map,
     gtfs_to_stop_id: &HashMap<String, TransitStopID>,
     snapper: &BorderSnapper,
@@ -157,6 +237,11 @@ fn create_route(
         bail!("No valid stops");
     }
     let border_snap_threshold = Distance::meters(30.0);
+    let vehicle = if route.route_type == RawTransitType::Train && !street_running {
+        PathConstraints::Train
+    } else {
+        PathConstraints::Bus
+    };
 
     let start = if map.boundary_polygon.contains_pt(route.shape.first_pt()) {
         map.get_ts(stops[0]).driving_pos.lane()
@@ -169,7 +254,7 @@ fn create_route(
             .get(0)
             .ok_or_else(|| anyhow!("couldn't find where shape enters map"))?;
         // Snap that to a border
-        let borders = if route.route_type == RawTransitType::Bus {
+        let borders = if vehicle == PathConstraints::Bus {
             &snapper.bus_incoming_borders
         } else {
             &snapper.train_incoming_borders
@@ -178,7 +263,7 @@ fn create_route(
             Some((l, _)) => l,
             None => bail!(
                 "Couldn't find a {:?} border near start {}",
-                route.route_type,
+                vehicle,
                 entry_pt
             ),
         }
@@ -195,7 +280,7 @@ fn create_route(
             .last()
             .ok_or_else(|| anyhow!("couldn't find where shape leaves map"))?;
         // Snap that to a border
-        let borders = if route.route_type == RawTransitType::Bus {
+        let borders = if vehicle == PathConstraints::Bus {
             &snapper.bus_outgoing_borders
         } else {
             &snapper.train_outgoing_borders
@@ -211,18 +296,18 @@ fn create_route(
                     lane
                 })
             }
-            None => bail!(
-                "Couldn't find a {:?} border near end {}",
-                route.route_type,
-                exit_pt
-            ),
+            None => bail!("Couldn't find a {:?} border near end {}", vehicle, exit_pt),
         }
     };
 
-    // TODO This'll come from the RawTransitRoute eventually. For now, every 30 minutes.
-    let spawn_times: Vec<Time> = (0..48)
-        .map(|i| Time::START_OF_DAY + (i as f64) * Duration::minutes(30))
-        .collect();
+    // Use the real schedule if GTFS has one. Otherwise, run every 30 minutes.
+    let spawn_times: Vec<Time> = if route.spawn_times.is_empty() {
+        (0..48)
+            .map(|i| Time::START_OF_DAY + (i as f64) * Duration::minutes(30))
+            .collect()
+    } else {
+        route.spawn_times.clone()
+    };
 
     let result = TransitRoute {
         id: TransitRouteID(map.transit_routes.len()),
@@ -232,12 +317,19 @@ fn create_route(
         stops,
         start,
         end_border,
-        route_type: match route.route_type {
-            RawTransitType::Bus => PathConstraints::Bus,
-            RawTransitType::Train => PathConstraints::Train,
-        },
+        route_type: vehicle,
         spawn_times: spawn_times.clone(),
         orig_spawn_times: spawn_times,
+        boarding: if route.route_type == RawTransitType::Train {
+            // Trains almost always have all of these
+            BoardingFeatures {
+                all_door_boarding: true,
+                off_board_fares: true,
+                level_boarding: true,
+            }
+        } else {
+            BoardingFeatures::default()
+        },
     };
 
     // Check that the paths are valid
@@ -6,8 +6,8 @@ use lyon::geom::{CubicBezierSegment, Point, QuadraticBezierSegment};
 use geom::{PolyLine, Pt2D};
 
 use crate::{
-    map::turn_type_from_road_geom, Intersection, Lane, LaneID, LaneType, Map, RoadID, Turn, TurnID,
-    TurnType,
+    map::turn_type_from_road_geom, Direction, DrivingSide, Intersection, Lane, LaneID, LaneType,
+    Map, RoadID, Turn, TurnID, TurnType,
 };
 
 /// Generate all driving and walking turns at an intersection, accounting for OSM turn restrictions.
@@ -28,18 +28,36 @@ pub fn make_all_turns(map: &Map, i: &Intersection) -> Vec<Turn> {
         .collect();
 
     // Try to use turn lane tags...
-    let filtered_turns: Vec<Turn> = all_turns
+    let lane_filtered_turns: Vec<Turn> = all_turns
         .clone()
         .into_iter()
-        .filter(|t| t.permitted_by_lane(map))
+        // U-turns permitted by edits override the turn lane tags
+        .filter(|t| t.permitted_by_lane(map) || (i.allow_u_turns && t.turn_type == TurnType::UTurn))
         .collect();
     // And remove merging left or right turns. If we wanted to remove the "lane-changing at
     // intersections" behavior, we could do this for TurnType::Straight too.
-    let filtered_turns = remove_merging_turns(map, filtered_turns, TurnType::Right);
-    let mut filtered_turns = remove_merging_turns(map, filtered_turns, TurnType::Left);
+    let filtered_turns = remove_merging_turns(map, lane_filtered_turns.clone(), TurnType::Right);
+    let filtered_turns = remove_merging_turns(map, filtered_turns, TurnType::Left);
+
+    // But then see how all of that filtering affects lane connectivity. Removing merging turns is
+    // just a heuristic, so before giving up on the turn lane tags entirely, try without it.
+    let mut filtered_turns = match verify_vehicle_connectivity(&filtered_turns, i, map) {
+        Ok(()) => filtered_turns,
+        Err(err) => match verify_vehicle_connectivity(&lane_filtered_turns, i, map) {
+            Ok(()) => {
+                warn!("Not removing merging turns. {}", err);
+                lane_filtered_turns
+            }
+            Err(err) => {
+                warn!("Not filtering turns. {}", err);
+                return all_turns;
+            }
+        },
+    };
+
     if i.merged {
         filtered_turns.retain(|turn| {
-            if turn.turn_type == TurnType::UTurn {
+            if turn.turn_type == TurnType::UTurn && !i.allow_u_turns {
                 let src_lane = map.get_l(turn.id.src);
                 // U-turns at divided highways are sometimes legal (and a common movement --
                 // https://www.openstreetmap.org/way/361443212), so let OSM turn:lanes override.
@@ -59,7 +77,7 @@ pub fn make_all_turns(map: &Map, i: &Intersection) -> Vec<Turn> {
         });
     }
 
-    // But then see how all of that filtering affects lane connectivity.
+    // Removing U-turns could break connectivity too
     match verify_vehicle_connectivity(&filtered_turns, i, map) {
         Ok(()) => filtered_turns,
         Err(err) => {
@@ -153,8 +171,9 @@ fn make_vehicle_turns(i: &Intersection, map: &Map) -> Vec<Turn> {
             if !dst.lane_type.is_for_moving_vehicles() {
                 continue;
             }
-            // Only allow U-turns at deadends
-            if src.id.road == dst.id.road && !is_deadend {
+            // Only allow U-turns at deadends, or where they're explicitly permitted
+            let is_u_turn = src.id.road == dst.id.road && !is_deadend;
+            if is_u_turn && !u_turn_permitted(src, dst, i, map) {
                 continue;
             }
             // Can't go between light rail and normal roads
@@ -169,10 +188,22 @@ fn make_vehicle_turns(i: &Intersection, map: &Map) -> Vec<Turn> {
                 continue;
             }
 
-            let turn_type = turn_type_from_lane_geom(src, dst, i, map);
-
-            let geom = curvey_turn(src, dst, i)
-                .unwrap_or_else(|_| PolyLine::must_new(vec![src.last_pt(), dst.first_pt()]));
+            let turn_type = if is_u_turn {
+                TurnType::UTurn
+            } else {
+                turn_type_from_lane_geom(src, dst, i, map)
+            };
+
+            let geom = if i.protected_corners
+                && src.is_biking()
+                && dst.is_biking()
+                && turn_type == far_side_turn(map)
+            {
+                two_stage_bike_turn(src, dst, i)
+            } else {
+                curvey_turn(src, dst, i)
+            }
+            .unwrap_or_else(|_| PolyLine::must_new(vec![src.last_pt(), dst.first_pt()]));
 
             turns.push(Turn {
                 id: TurnID {
@@ -189,6 +220,39 @@ fn make_vehicle_turns(i: &Intersection, map: &Map) -> Vec<Turn> {
     turns
 }
 
+/// U-turns away from deadends are legal when OSM tags them with turn:lanes, or when edits permit
+/// them at this intersection. Either way, only go between the innermost lanes, not from every lane
+/// to every other lane.
+fn u_turn_permitted(src: &Lane, dst: &Lane, i: &Intersection, map: &Map) -> bool {
+    if src.lane_type != dst.lane_type
+        || !is_innermost_lane(src, map)
+        || !is_innermost_lane(dst, map)
+    {
+        return false;
+    }
+    i.allow_u_turns
+        || src
+            .get_lane_level_turn_restrictions(map.get_r(src.id.road), false)
+            .map(|set| set.contains(&TurnType::UTurn))
+            .unwrap_or(false)
+}
+
+/// Is this the vehicle lane closest to the opposite direction of travel?
+fn is_innermost_lane(lane: &Lane, map: &Map) -> bool {
+    let road = map.get_r(lane.id.road);
+    // Lanes are ordered left-to-right. Which side is the center of the road?
+    let center_is_left =
+        (lane.dir == Direction::Fwd) == (map.get_config().driving_side == DrivingSide::Right);
+    let towards_center = if center_is_left {
+        &road.lanes[..lane.id.offset]
+    } else {
+        &road.lanes[lane.id.offset + 1..]
+    };
+    !towards_center
+        .iter()
+        .any(|l| l.dir == lane.dir && l.lane_type.is_for_moving_vehicles())
+}
+
 fn turn_type_from_lane_geom(src: &Lane, dst: &Lane, i: &Intersection, map: &Map) -> TurnType {
     turn_type_from_road_geom(
         map.get_r(src.id.road),
@@ -258,6 +322,31 @@ fn curvey_turn(src: &Lane, dst: &Lane, i: &Intersection) -> Result<PolyLine> {
     PolyLine::new(curve)
 }
 
+/// The turn that crosses oncoming traffic
+fn far_side_turn(map: &Map) -> TurnType {
+    if map.get_config().driving_side == DrivingSide::Right {
+        TurnType::Left
+    } else {
+        TurnType::Right
+    }
+}
+
+/// At protected intersections, bikes turning across traffic stay on the outside of the junction.
+/// They cross the first road to the far corner, then cross the second road, like a two-stage
+/// turn.
+fn two_stage_bike_turn(src: &Lane, dst: &Lane, i: &Intersection) -> Result<PolyLine> {
+    match src
+        .last_line()
+        .infinite()
+        .intersection(&dst.first_line().infinite())
+    {
+        Some(corner) if i.polygon.contains_pt(corner) => {
+            PolyLine::new(vec![src.last_pt(), corner, dst.first_pt()])
+        }
+        _ => curvey_turn(src, dst, i),
+    }
+}
+
 fn remove_merging_turns(map: &Map, input: Vec<Turn>, turn_type: TurnType) -> Vec<Turn> {
     let mut turns = Vec::new();
 
@@ -2,7 +2,7 @@
 
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
-    sync::{Arc, RwLock},
+    sync::{Arc, OnceLock, RwLock},
 };
 
 use anyhow::{Context, Result};
@@ -10,21 +10,26 @@ use petgraph::graphmap::{DiGraphMap, UnGraphMap};
 use popgetter::CensusZone;
 
 use abstio::{CityName, MapName};
-use abstutil::{prettyprint_usize, serialized_size_bytes, MultiMap, Tags, Timer};
+use abstutil::{
+    fnv1a, prettyprint_usize, serialized_size_bytes, to_binary, MultiMap, Tags, Timer, FNV_OFFSET,
+};
 use geom::{
     Angle, Bounds, Distance, Duration, FindClosest, GPSBounds, LonLat, PolyLine, Polygon, Pt2D,
     Ring, Time,
 };
 use raw_map::{RawBuilding, RawMap};
 
+use crate::pathfind::CreateEngine;
 use crate::{
     osm, AmenityType, Area, AreaID, AreaType, Building, BuildingID, BuildingType, CommonEndpoint,
-    CompressedMovementID, ControlStopSign, ControlTrafficSignal, DirectedRoadID, Direction,
-    DrivingSide, ExtraPOI, Intersection, IntersectionControl, IntersectionID, IntersectionKind,
-    Lane, LaneID, LaneType, Map, MapConfig, MapEdits, Movement, MovementID, OffstreetParking,
-    OriginalRoad, ParkingLot, ParkingLotID, Path, PathConstraints, PathRequest, PathV2, Pathfinder,
-    PathfinderCaching, Position, Road, RoadFilter, RoadID, RoutingParams, TransitRoute,
-    TransitRouteID, TransitStop, TransitStopID, Turn, TurnID, TurnType, Zone,
+    CompressedMovementID, CongestionCharge, ControlDefaults, ControlStopSign, ControlTrafficSignal,
+    DirectedRoadID, Direction, DrivingSide, ExtraPOI, FerryRoute, FerryRouteID, FerryTerminal,
+    FerryTerminalID, Intersection, IntersectionControl, IntersectionID, IntersectionKind, Lane,
+    LaneID, LaneType, LoadingZones, Map, MapConfig, MapEdits, Movement, MovementID,
+    OffstreetParking, OriginalRoad, ParkingLot, ParkingLotID, ParkingPricing, Path,
+    PathConstraints, PathRequest, PathV2, Pathfinder, PathfinderCaching, Position, Road,
+    RoadFilter, RoadID, RoutingParams, Site, SiteID, TransitRoute, TransitRouteID, TransitStop,
+    TransitStopID, Turn, TurnID, TurnType, Zone,
 };
 
 impl Map {
@@ -74,8 +79,16 @@ impl Map {
 
         self.edits = self.new_edits();
         self.recalculate_road_to_buildings();
+        self.recalculate_has_hov_lanes();
         self.recalculate_all_movements(timer);
 
+        // Maps saved with save_with_lazy_pathfinder have an empty pathfinder. Don't read the real
+        // one until something pathfinds, since lots of tools never do.
+        let pathfinder_path = self.name.pathfinder_path();
+        if cfg!(not(target_arch = "wasm32")) && abstio::file_exists(&pathfinder_path) {
+            self.lazy_pathfinder_path = Some(pathfinder_path);
+        }
+
         // Enable to work on shrinking map file sizes. Never run this on the web though --
         // trying to serialize fast_paths in wasm melts the browser, because the usize<->u32
         // translation there isn't meant to run on wasm.
@@ -106,6 +119,11 @@ impl Map {
                     self.parking_lots.len(),
                     serialized_size_bytes(&self.parking_lots),
                 ),
+                (
+                    "sites",
+                    self.sites.len(),
+                    serialized_size_bytes(&self.sites),
+                ),
                 (
                     "zones",
                     self.zones.len(),
@@ -121,7 +139,11 @@ impl Map {
                     self.extra_pois.len(),
                     serialized_size_bytes(&self.extra_pois),
                 ),
-                ("pathfinder", 1, serialized_size_bytes(&self.pathfinder)),
+                (
+                    "pathfinder",
+                    1,
+                    serialized_size_bytes(self.get_pathfinder()),
+                ),
             ];
             costs.sort_by_key(|(_, _, bytes)| *bytes);
             costs.reverse();
@@ -150,8 +172,11 @@ impl Map {
             buildings: Vec::new(),
             transit_stops: BTreeMap::new(),
             transit_routes: Vec::new(),
+            ferry_terminals: Vec::new(),
+            ferry_routes: Vec::new(),
             areas: Vec::new(),
             parking_lots: Vec::new(),
+            sites: Vec::new(),
             zones: Vec::new(),
             census_zones: Vec::new(),
             extra_pois: Vec::new(),
@@ -170,11 +195,18 @@ impl Map {
             config: MapConfig::default(),
             pathfinder: Pathfinder::empty(),
             pathfinder_dirty: false,
+            lazy_pathfinder_path: None,
+            lazy_pathfinder: OnceLock::new(),
             routing_params: RoutingParams::default(),
             name: MapName::blank(),
             edits: MapEdits::new(),
             edits_generation: 0,
+            congestion_charge: None,
+            parking_pricing: ParkingPricing::default(),
+            loading_zones: LoadingZones::default(),
             road_to_buildings: MultiMap::new(),
+            has_hov_lanes: false,
+            wind: None,
         }
     }
 
@@ -235,6 +267,7 @@ impl Map {
                 public_garage_name: None,
                 num_parking_spots: 0,
                 amenities: Vec::new(),
+                land_use: None,
             },
         );
 
@@ -283,6 +316,10 @@ impl Map {
         &self.parking_lots
     }
 
+    pub fn all_sites(&self) -> &Vec<Site> {
+        &self.sites
+    }
+
     pub fn all_zones(&self) -> &Vec<Zone> {
         &self.zones
     }
@@ -395,6 +432,10 @@ impl Map {
         &self.parking_lots[id.0]
     }
 
+    pub fn get_site(&self, id: SiteID) -> &Site {
+        &self.sites[id.0]
+    }
+
     pub fn get_stop_sign(&self, id: IntersectionID) -> &ControlStopSign {
         &self.stop_signs[&id]
     }
@@ -557,6 +598,22 @@ impl Map {
         self.transit_routes.iter().find(|r| r.long_name == name)
     }
 
+    pub fn get_ferry_terminal(&self, id: FerryTerminalID) -> &FerryTerminal {
+        &self.ferry_terminals[id.0]
+    }
+
+    pub fn all_ferry_terminals(&self) -> &Vec<FerryTerminal> {
+        &self.ferry_terminals
+    }
+
+    pub fn get_ferry_route(&self, id: FerryRouteID) -> &FerryRoute {
+        &self.ferry_routes[id.0]
+    }
+
+    pub fn all_ferry_routes(&self) -> &Vec<FerryRoute> {
+        &self.ferry_routes
+    }
+
     pub fn get_routes_serving_stop(&self, stop: TransitStopID) -> Vec<&TransitRoute> {
         let mut routes = Vec::new();
         for r in &self.transit_routes {
@@ -592,11 +649,59 @@ impl Map {
         result
     }
 
-    pub fn save(&self) {
+    pub fn save(&mut self) {
         assert!(self.edits.edits_name.starts_with("Untitled Proposal"));
         assert!(self.edits.commands.is_empty());
         assert!(!self.pathfinder_dirty);
+        // The pathfinder goes back in the map file
+        self.take_lazy_pathfinder();
         abstio::write_binary(self.name.path(), self);
+        // Don't let an old pathfinder from save_with_lazy_pathfinder replace the one just saved
+        abstio::delete_file(self.name.pathfinder_path());
+    }
+
+    /// Like `save`, but the pathfinder goes in a separate file, which is memory-mapped and read
+    /// only the first time something pathfinds. The contraction hierarchies are most of the file
+    /// for big maps, so loading the map itself gets much faster, and tools that never pathfind
+    /// never hold them in memory. Once read, the pathfinder takes as much memory as before. This
+    /// can't be used for maps loaded on web.
+    ///
+    /// The pathfinder file records a checksum of the map, so a stale one left behind by an older
+    /// map isn't used.
+    pub fn save_with_lazy_pathfinder(&mut self) {
+        assert!(self.edits.edits_name.starts_with("Untitled Proposal"));
+        assert!(self.edits.commands.is_empty());
+        assert!(!self.pathfinder_dirty);
+        self.take_lazy_pathfinder();
+        abstio::write_binary(
+            self.name.pathfinder_path(),
+            &(self.pathfinder_checksum(), &self.pathfinder),
+        );
+        let pathfinder = std::mem::replace(&mut self.pathfinder, Pathfinder::empty());
+        abstio::write_binary(self.name.path(), self);
+        self.pathfinder = pathfinder;
+    }
+
+    /// Summarizes everything the pathfinder is built from
+    fn pathfinder_checksum(&self) -> u64 {
+        fnv1a(
+            FNV_OFFSET,
+            &to_binary(&(
+                &self.roads,
+                &self.intersections,
+                &self.transit_routes,
+                &self.routing_params,
+            )),
+        )
+    }
+
+    /// If the pathfinder is stored separately, read it now and own it, so it can be modified.
+    pub(crate) fn take_lazy_pathfinder(&mut self) {
+        if self.lazy_pathfinder_path.is_some() {
+            self.get_pathfinder();
+            self.pathfinder = self.lazy_pathfinder.take().unwrap();
+            self.lazy_pathfinder_path = None;
+        }
     }
 
     /// Cars trying to park near this building should head for the driving lane returned here, then
@@ -658,7 +763,45 @@ impl Map {
     }
 
     pub fn get_pathfinder(&self) -> &Pathfinder {
-        &self.pathfinder
+        match self.lazy_pathfinder_path {
+            Some(ref path) => self.lazy_pathfinder.get_or_init(|| {
+                let mut timer = Timer::new("load pathfinder");
+                match abstio::maybe_read_binary_mmap::<(u64, Pathfinder)>(path.clone(), &mut timer)
+                {
+                    Ok((checksum, pathfinder)) if checksum == self.pathfinder_checksum() => {
+                        pathfinder
+                    }
+                    Ok(_) => {
+                        warn!(
+                            "{} is from a different version of the map, ignoring it",
+                            path
+                        );
+                        self.rebuild_pathfinder(&mut timer)
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Couldn't load the pathfinder for {}: {}",
+                            self.name.describe(),
+                            err
+                        );
+                        self.rebuild_pathfinder(&mut timer)
+                    }
+                }
+            }),
+            None => &self.pathfinder,
+        }
+    }
+
+    /// Slow, but only needed when the separate pathfinder file is unusable
+    fn rebuild_pathfinder(&self, timer: &mut Timer) -> Pathfinder {
+        let mut pathfinder = Pathfinder::new(
+            self,
+            self.routing_params().clone(),
+            &CreateEngine::CH,
+            timer,
+        );
+        pathfinder.finalize_transit(self, &CreateEngine::CH);
+        pathfinder
     }
 
     pub fn pathfind(&self, req: PathRequest) -> Result<Path> {
@@ -675,7 +818,7 @@ impl Map {
     }
     pub fn pathfind_v2(&self, req: PathRequest) -> Result<PathV2> {
         assert!(!self.pathfinder_dirty);
-        self.pathfinder
+        self.get_pathfinder()
             .pathfind(req.clone(), self)
             .ok_or_else(|| anyhow!("can't fulfill {}", req))
     }
@@ -686,17 +829,140 @@ impl Map {
         cache_custom: PathfinderCaching,
     ) -> Result<PathV2> {
         assert!(!self.pathfinder_dirty);
-        self.pathfinder
+        self.get_pathfinder()
             .pathfind_with_params(req.clone(), params, cache_custom, self)
             .ok_or_else(|| anyhow!("can't fulfill {}", req))
     }
+    /// Pathfinds for a vehicle with custom params, without building or caching a pathfinder.
+    /// This is slower per path, so only use it when the params change between calls.
+    pub fn pathfind_by_cost(&self, req: PathRequest, params: &RoutingParams) -> Result<Path> {
+        crate::pathfind::pathfind_by_cost(req.clone(), params, self)
+            .ok_or_else(|| anyhow!("can't fulfill {}", req))?
+            .into_v1(self)
+    }
     pub fn should_use_transit(
         &self,
         start: Position,
         end: Position,
     ) -> Option<(TransitStopID, Option<TransitStopID>, TransitRouteID)> {
         assert!(!self.pathfinder_dirty);
-        self.pathfinder.should_use_transit(self, start, end)
+        self.get_pathfinder().should_use_transit(self, start, end)
+    }
+
+    /// Would driving to one of these parking lots, then riding transit from there to `end`, make
+    /// sense? Only lots closer to `start` than to `end` are on the way; the closest of them with a
+    /// transit connection wins. Returns the lot, and the same as `should_use_transit` from there.
+    pub fn should_use_park_and_ride(
+        &self,
+        start: Pt2D,
+        end: Position,
+        lots: Vec<ParkingLotID>,
+    ) -> Option<(
+        ParkingLotID,
+        (TransitStopID, Option<TransitStopID>, TransitRouteID),
+    )> {
+        let end_pt = end.pt(self);
+        let mut candidates: Vec<(Distance, ParkingLotID)> = lots
+            .into_iter()
+            .filter_map(|pl| {
+                let pt = self.get_pl(pl).polygon.center();
+                let dist = pt.dist_to(start);
+                if dist < pt.dist_to(end_pt) {
+                    Some((dist, pl))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        candidates.sort_by_key(|(dist, _)| *dist);
+        // Each check pathfinds, so give up after a few
+        candidates.into_iter().take(5).find_map(|(_, pl)| {
+            let transit = self.should_use_transit(self.get_pl(pl).sidewalk_pos, end)?;
+            Some((pl, transit))
+        })
+    }
+
+    /// Would walking to a ferry terminal, crossing the water, and walking from the other terminal
+    /// beat just walking? Returns the route and the terminals to board and get off at.
+    pub fn should_use_ferry(
+        &self,
+        start: Position,
+        end: Position,
+    ) -> Option<(FerryRouteID, FerryTerminalID, FerryTerminalID)> {
+        if self.ferry_routes.is_empty() {
+            return None;
+        }
+        let walk_cost = |from: Position, to: Position| {
+            self.pathfind_v2(PathRequest::walking(from, to))
+                .ok()
+                .map(|p| p.get_cost())
+        };
+        // Nobody walks faster than this in a straight line
+        let walk_lower_bound = |from: Pt2D, to: Pt2D| from.dist_to(to) / crate::MAX_WALKING_SPEED;
+        let start_pt = start.pt(self);
+        let end_pt = end.pt(self);
+
+        // This is checked for every walking trip, and pathfinding to every terminal is slow. So
+        // first find the least each option could cost without pathfinding, and check the most
+        // promising first.
+        let mut options = Vec::new();
+        for route in &self.ferry_routes {
+            // On average, wait for half the time between ferries
+            let wait = Duration::hours(12) / (route.spawn_times.len() as f64);
+            for t1 in &route.terminals {
+                for t2 in &route.terminals {
+                    if t1 == t2 {
+                        continue;
+                    }
+                    let ride = wait + route.crossing_time(*t1, *t2).unwrap();
+                    let pt1 = self.get_ferry_terminal(*t1).sidewalk_pos.pt(self);
+                    let pt2 = self.get_ferry_terminal(*t2).sidewalk_pos.pt(self);
+                    let bound =
+                        walk_lower_bound(start_pt, pt1) + ride + walk_lower_bound(pt2, end_pt);
+                    options.push((bound, ride, (route.id, *t1, *t2)));
+                }
+            }
+        }
+        options.sort_by_key(|(bound, _, _)| *bound);
+
+        let direct = walk_cost(start, end);
+        // Many routes share terminals, so only pathfind once per terminal
+        let mut to_terminal: HashMap<FerryTerminalID, Option<Duration>> = HashMap::new();
+        let mut from_terminal: HashMap<FerryTerminalID, Option<Duration>> = HashMap::new();
+        let mut best: Option<(Duration, (FerryRouteID, FerryTerminalID, FerryTerminalID))> = None;
+        for (bound, ride, (route, t1, t2)) in options {
+            // Nothing after this can win either
+            if direct.map(|d| bound >= d).unwrap_or(false)
+                || best.map(|(c, _)| bound >= c).unwrap_or(false)
+            {
+                break;
+            }
+            let walk1 = match *to_terminal
+                .entry(t1)
+                .or_insert_with(|| walk_cost(start, self.get_ferry_terminal(t1).sidewalk_pos))
+            {
+                Some(cost) => cost,
+                None => {
+                    continue;
+                }
+            };
+            let walk2 = match *from_terminal
+                .entry(t2)
+                .or_insert_with(|| walk_cost(self.get_ferry_terminal(t2).sidewalk_pos, end))
+            {
+                Some(cost) => cost,
+                None => {
+                    continue;
+                }
+            };
+            let cost = walk1 + ride + walk2;
+            if direct.map(|d| cost < d).unwrap_or(true)
+                && best.map(|(c, _)| cost < c).unwrap_or(true)
+            {
+                best = Some((cost, (route, t1, t2)));
+            }
+        }
+        best.map(|(_, x)| x)
     }
 
     /// Return the cost of a single path, and also a mapping from every directed road to the cost
@@ -707,7 +973,7 @@ impl Map {
         req: PathRequest,
     ) -> Option<(Duration, HashMap<DirectedRoadID, Duration>)> {
         assert!(!self.pathfinder_dirty);
-        self.pathfinder.all_costs_from(req, self)
+        self.get_pathfinder().all_costs_from(req, self)
     }
 
     /// None for SharedSidewalkCorners and turns not belonging to traffic signals
@@ -793,6 +1059,15 @@ impl Map {
         None
     }
 
+    pub fn find_ts_by_gtfs(&self, gtfs_id: &str) -> Option<TransitStopID> {
+        for ts in self.all_transit_stops().values() {
+            if ts.gtfs_id == gtfs_id {
+                return Some(ts.id);
+            }
+        }
+        None
+    }
+
     // TODO Sort of a temporary hack
     pub fn hack_override_offstreet_spots(&mut self, spots_per_bldg: usize) {
         for b in &mut self.buildings {
@@ -857,6 +1132,23 @@ impl Map {
         &self.config
     }
 
+    pub fn get_congestion_charge(&self) -> Option<&CongestionCharge> {
+        self.congestion_charge.as_ref()
+    }
+
+    pub fn get_parking_pricing(&self) -> &ParkingPricing {
+        &self.parking_pricing
+    }
+
+    pub fn get_loading_zones(&self) -> &LoadingZones {
+        &self.loading_zones
+    }
+
+    /// How intersections without explicit control work in this part of the world
+    pub fn get_control_defaults(&self) -> ControlDefaults {
+        ControlDefaults::for_country(&self.config.country_code)
+    }
+
     /// Simple search along undirected roads. Expresses the result as a sequence of roads and a
     /// sequence of intersections.
     pub fn simple_path_btwn(
@@ -937,11 +1229,12 @@ impl Map {
         &self.routing_params
     }
 
-    /// Adjusts the routing params baked into the map by accounting for any modal filters created
-    /// since.
-    /// TODO It's weird that these don't already take effect!
+    /// Adjusts the routing params baked into the map by snapshotting the modal filters that exist
+    /// right now. Later changes to filters won't affect routing with these params, so the
+    /// resulting routes can be compared against routes after the changes.
     pub fn routing_params_respecting_modal_filters(&self) -> RoutingParams {
         let mut params = self.routing_params.clone();
+        params.respect_modal_filters = false;
         for r in &self.roads {
             if r.modal_filter.is_some() {
                 params.avoid_roads.insert(r.id);
@@ -969,6 +1262,15 @@ impl Map {
         self.road_to_buildings = mapping;
     }
 
+    /// True if any road has lanes reserved for high-occupancy vehicles
+    pub fn has_hov_lanes(&self) -> bool {
+        self.has_hov_lanes
+    }
+
+    pub(crate) fn recalculate_has_hov_lanes(&mut self) {
+        self.has_hov_lanes = self.roads.iter().any(|r| r.hov_lanes.is_some());
+    }
+
     pub(crate) fn recalculate_all_movements(&mut self, timer: &mut Timer) {
         let movements = timer.parallelize(
             "generate movements",
@@ -1023,6 +1325,7 @@ impl Map {
     /// Modifies the map in-place, removing parts not essential for the bike network tool.
     pub fn minify(&mut self, timer: &mut Timer) {
         // We only need the CHs for driving and biking, to support mode shift.
+        self.lazy_pathfinder_path = None;
         self.pathfinder = Pathfinder::new_limited(
             self,
             self.routing_params().clone(),
@@ -1044,6 +1347,7 @@ impl Map {
         self.buildings.clear();
 
         // We only need the CHs for driving.
+        self.lazy_pathfinder_path = None;
         self.pathfinder = Pathfinder::new_limited(
             self,
             self.routing_params().clone(),
@@ -6,7 +6,13 @@ use serde::{Deserialize, Serialize};
 use abstutil::{deserialize_usize, serialize_usize, Tags};
 use geom::{Distance, PolyLine, Polygon, Pt2D};
 
-use crate::{osm, Amenity, AmenityType, LaneID, Map, NamePerLanguage, PathConstraints, Position};
+use crate::{
+    osm, Amenity, AmenityType, LandUse, LaneID, Map, NamePerLanguage, PathConstraints, Position,
+};
+
+/// A rough guess, including the floor and ceiling. Used both to estimate heights and levels from
+/// each other, and to draw buildings.
+pub const BUILDING_HEIGHT_PER_LEVEL: Distance = Distance::const_meters(3.5);
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct BuildingID(
@@ -29,7 +35,10 @@ impl fmt::Display for BuildingID {
 pub struct Building {
     pub id: BuildingID,
     pub polygon: Polygon,
+    /// Tagged in OSM, or estimated from the height
     pub levels: f64,
+    /// Tagged in OSM, or estimated from the number of levels
+    pub height: Distance,
     pub address: String,
     pub name: Option<NamePerLanguage>,
     pub orig_id: osm::OsmID,
@@ -38,6 +47,9 @@ pub struct Building {
     pub label_center: Pt2D,
     pub amenities: Vec<Amenity>,
     pub bldg_type: BuildingType,
+    /// From official zoning data, if it was available when importing. `bldg_type` is derived
+    /// from this when present.
+    pub land_use: Option<LandUse>,
     pub parking: OffstreetParking,
     /// Depending on options while importing, these might be empty, to save file space.
     pub osm_tags: Tags,
@@ -78,6 +90,14 @@ impl BuildingType {
             BuildingType::Commercial(_) | BuildingType::Empty => false,
         }
     }
+
+    pub fn num_residents(&self) -> usize {
+        match self {
+            BuildingType::Residential { num_residents, .. } => *num_residents,
+            BuildingType::ResidentialCommercial(residents, _) => *residents,
+            BuildingType::Commercial(_) | BuildingType::Empty => 0,
+        }
+    }
 }
 
 impl Building {
@@ -85,6 +105,11 @@ impl Building {
         self.sidewalk_pos.lane()
     }
 
+    /// The total area of all levels, in square meters
+    pub fn floor_area(&self) -> f64 {
+        self.levels * self.polygon.area()
+    }
+
     /// The polyline goes from the building to the driving position
     // TODO Make this handle parking_blackhole
     pub fn driving_connection(&self, map: &Map) -> Option<(Position, PolyLine)> {
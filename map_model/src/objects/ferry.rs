@@ -0,0 +1,185 @@
+//! Ferries carry people across water on a fixed schedule. They don't use any lanes; each vessel
+//! just follows its route's path from terminal to terminal.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_usize, serialize_usize};
+use geom::{Angle, Distance, Duration, PolyLine, Pt2D, Speed, Time};
+
+use crate::{osm, Position};
+
+pub const FERRY_SPEED: Speed = Speed::const_meters_per_second(6.0);
+/// How long a ferry waits at each intermediate terminal
+pub const FERRY_DWELL_TIME: Duration = Duration::const_seconds(120.0);
+/// How many people fit on each ferry
+pub const FERRY_CAPACITY: usize = 150;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FerryTerminalID(
+    #[serde(
+        serialize_with = "serialize_usize",
+        deserialize_with = "deserialize_usize"
+    )]
+    pub usize,
+);
+
+impl fmt::Display for FerryTerminalID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ferry terminal #{}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FerryRouteID(
+    #[serde(
+        serialize_with = "serialize_usize",
+        deserialize_with = "deserialize_usize"
+    )]
+    pub usize,
+);
+
+impl fmt::Display for FerryRouteID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ferry route #{}", self.0)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FerryTerminal {
+    pub id: FerryTerminalID,
+    pub name: String,
+    /// Where the ferry docks
+    pub pt: Pt2D,
+    /// Where people wait to board
+    pub sidewalk_pos: Position,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FerryRoute {
+    pub id: FerryRouteID,
+    pub name: String,
+    pub osm_id: osm::OsmID,
+    /// In order along the path. Ferries run in both directions.
+    pub terminals: Vec<FerryTerminalID>,
+    /// The water path from the first terminal to the last
+    pub path: PolyLine,
+    /// How far along the path each terminal is
+    pub terminal_dists: Vec<Distance>,
+    /// Non-empty, times in order for one day when a ferry leaves the first terminal. Another ferry
+    /// leaves the last terminal at the same time, heading back.
+    pub spawn_times: Vec<Time>,
+}
+
+impl FerryRoute {
+    pub fn idx_of(&self, terminal: FerryTerminalID) -> Option<usize> {
+        self.terminals.iter().position(|t| *t == terminal)
+    }
+
+    /// How long after leaving its first terminal does a ferry heading in this direction reach
+    /// terminal `idx`?
+    fn time_to_reach(&self, idx: usize, forwards: bool) -> Duration {
+        let last = self.terminals.len() - 1;
+        let (dist, stops) = if forwards {
+            (self.terminal_dists[idx], idx)
+        } else {
+            (self.path.length() - self.terminal_dists[idx], last - idx)
+        };
+        dist / FERRY_SPEED + (stops as f64) * FERRY_DWELL_TIME
+    }
+
+    /// Riding from one terminal to another, when does the next ferry leave, and when does it
+    /// arrive? Also returns which of the day's ferries it is, as an index into `spawn_times`. None
+    /// if there are no more ferries today.
+    pub fn next_crossing(
+        &self,
+        from: FerryTerminalID,
+        to: FerryTerminalID,
+        now: Time,
+    ) -> Option<(usize, Time, Time)> {
+        let idx1 = self.idx_of(from)?;
+        let idx2 = self.idx_of(to)?;
+        let forwards = idx1 < idx2;
+        let offset = self.time_to_reach(idx1, forwards);
+        let ride = self.time_to_reach(idx2, forwards) - offset;
+        self.spawn_times
+            .iter()
+            .map(|t| *t + offset)
+            .enumerate()
+            .find(|(_, t)| *t >= now)
+            .map(|(idx, t)| (idx, t, t + ride))
+    }
+
+    /// Leg `i` of a route is the water between terminals `i` and `i + 1`. Riding from one terminal
+    /// to another, which direction does the ferry head, and which legs does it cover?
+    pub fn legs_between(
+        &self,
+        from: FerryTerminalID,
+        to: FerryTerminalID,
+    ) -> Option<(bool, std::ops::Range<usize>)> {
+        let idx1 = self.idx_of(from)?;
+        let idx2 = self.idx_of(to)?;
+        Some((idx1 < idx2, idx1.min(idx2)..idx1.max(idx2)))
+    }
+
+    /// How long the ride between two terminals takes, not counting the wait
+    pub fn crossing_time(&self, from: FerryTerminalID, to: FerryTerminalID) -> Option<Duration> {
+        let idx1 = self.idx_of(from)?;
+        let idx2 = self.idx_of(to)?;
+        let forwards = idx1 < idx2;
+        Some(self.time_to_reach(idx2, forwards) - self.time_to_reach(idx1, forwards))
+    }
+
+    /// The position of every ferry on the water right now
+    pub fn ferries_at(&self, now: Time) -> Vec<(Pt2D, Angle)> {
+        let last = self.terminals.len() - 1;
+        let total = self.time_to_reach(last, true);
+        let mut results = Vec::new();
+        for start in &self.spawn_times {
+            if now < *start || now > *start + total {
+                continue;
+            }
+            for forwards in [true, false] {
+                // Work out how far along the path it's travelled, pausing at each terminal
+                let mut dist = Distance::ZERO;
+                let mut remaining = now - *start;
+                for step in 1..=last {
+                    // terminal_dists is sorted
+                    let (idx1, idx2) = if forwards {
+                        (step - 1, step)
+                    } else {
+                        (last - step, last - step + 1)
+                    };
+                    let leg = self.terminal_dists[idx2] - self.terminal_dists[idx1];
+                    let leg_time = leg / FERRY_SPEED;
+                    if remaining < leg_time {
+                        dist += leg * (remaining / leg_time);
+                        break;
+                    }
+                    dist += leg;
+                    remaining = remaining - leg_time - FERRY_DWELL_TIME;
+                    if remaining <= Duration::ZERO {
+                        break;
+                    }
+                }
+                let dist_along = if forwards {
+                    dist
+                } else {
+                    self.path.length() - dist
+                };
+                if let Ok((pt, angle)) = self.path.dist_along(dist_along) {
+                    results.push((
+                        pt,
+                        if forwards {
+                            angle
+                        } else {
+                            angle.rotate_degs(180.0)
+                        },
+                    ));
+                }
+            }
+        }
+        results
+    }
+}
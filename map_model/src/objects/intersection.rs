@@ -4,12 +4,12 @@ use std::fmt;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_usize, serialize_usize};
-use geom::{Distance, Polygon};
+use geom::{Distance, Polygon, Pt2D, Ring};
 
 use crate::{
-    osm, CompressedMovementID, DiagonalFilter, DirectedRoadID, IntersectionControl,
+    osm, CompressedMovementID, DiagonalFilter, DirectedRoadID, DrivingSide, IntersectionControl,
     IntersectionKind, LaneID, Map, Movement, MovementID, PathConstraints, Road, RoadID, RoadSideID,
-    SideOfRoad, Turn, TurnID,
+    SideOfRoad, Turn, TurnID, TurnType,
 };
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -53,6 +53,16 @@ pub struct Intersection {
     pub roads: Vec<RoadID>,
 
     pub modal_filter: Option<DiagonalFilter>,
+    /// Have edits permitted U-turns here, even where OSM doesn't say they're legal? Otherwise,
+    /// they only happen at deadends and where turn:lanes tags them.
+    pub allow_u_turns: bool,
+    /// Have edits turned this into a Dutch-style protected intersection? Corner islands keep
+    /// turning vehicles away from bikes, and bikes turning across traffic cross one road, then the
+    /// other, with the crossings set back from the junction.
+    pub protected_corners: bool,
+    /// Incoming roads where turning on red is banned, from jurisdiction defaults or OSM tags. Only
+    /// matters for traffic signals.
+    pub no_turn_on_red: BTreeSet<RoadID>,
 
     /// Was a short road adjacent to this intersection merged?
     pub merged: bool,
@@ -154,6 +164,52 @@ impl Intersection {
             .unwrap()
     }
 
+    /// At protected intersections, the island on each corner separating bikes from vehicles turning
+    /// the same way. The bike path wraps around the outside of the island.
+    pub fn corner_islands(&self, map: &Map) -> Vec<Polygon> {
+        if !self.protected_corners {
+            return Vec::new();
+        }
+        let near_side_turn = if map.get_config().driving_side == DrivingSide::Right {
+            TurnType::Right
+        } else {
+            TurnType::Left
+        };
+        let mut corners = BTreeSet::new();
+        let mut islands = Vec::new();
+        for turn in &self.turns {
+            let src = map.get_l(turn.id.src);
+            let dst = map.get_l(turn.id.dst);
+            if turn.turn_type != near_side_turn || !src.is_biking() || !dst.is_biking() {
+                continue;
+            }
+            // Only one island per corner, even with multiple bike lanes
+            if !corners.insert((src.id.road, dst.id.road)) {
+                continue;
+            }
+            let corner = match src
+                .last_line()
+                .infinite()
+                .intersection(&dst.first_line().infinite())
+            {
+                Some(pt) if self.polygon.contains_pt(pt) => pt,
+                _ => continue,
+            };
+            // Point the island from the corner towards the middle of the junction, keeping clear
+            // of the bike lanes
+            let start = src.last_pt();
+            let end = dst.first_pt();
+            let middle = Pt2D::new((start.x() + end.x()) / 2.0, (start.y() + end.y()) / 2.0);
+            let tip = middle.project_away(corner.dist_to(middle), corner.angle_to(middle));
+            let start = start.project_away(src.width, start.angle_to(tip));
+            let end = end.project_away(dst.width, end.angle_to(tip));
+            if let Ok(ring) = Ring::new(vec![start, tip, end, start]) {
+                islands.push(ring.into_polygon());
+            }
+        }
+        islands
+    }
+
     // TODO Use osm2streets RoadEdge?
     // This skips the "interior" piece of any loop roads
     pub fn get_road_sides_sorted(&self, map: &Map) -> Vec<RoadSideID> {
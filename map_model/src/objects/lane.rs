@@ -259,17 +259,26 @@ impl Lane {
         };
         let parts: Vec<&str> = all.split('|').collect();
         // Verify the number of parts matches the road's lanes
-        let lanes: Vec<LaneID> = road
+        let mut lanes: Vec<LaneID> = road
             .children(self.dir)
             .into_iter()
             .filter(|(_, lt)| *lt == LaneType::Driving || *lt == LaneType::Bus)
             .map(|(id, _)| id)
             .collect();
+        if parts.len() != lanes.len() {
+            // Bus lanes are sometimes left out of turn:lanes and described separately
+            lanes = road
+                .children(self.dir)
+                .into_iter()
+                .filter(|(_, lt)| *lt == LaneType::Driving)
+                .map(|(id, _)| id)
+                .collect();
+        }
         if parts.len() != lanes.len() {
             warn!("{}'s turn restrictions don't match the lanes", road.orig_id);
             return None;
         }
-        // TODO More warnings if this fails
+        // A bus lane that isn't described has no restrictions
         let part = parts[lanes.iter().position(|l| *l == self.id)?];
 
         // TODO Probably the target lane should get marked as LaneType::Bus
@@ -1,11 +1,13 @@
 pub mod area;
 pub mod building;
+pub mod ferry;
 pub mod intersection;
 pub mod lane;
 pub mod modal_filter;
 pub mod movement;
 pub mod parking_lot;
 pub mod road;
+pub mod site;
 pub mod stop_signs;
 pub mod traffic_signals;
 pub mod transit;
@@ -6,8 +6,8 @@ use geom::{Distance, Line};
 
 use crate::{EditCmd, IntersectionID, Map, PathConstraints, RoadID};
 
-/// The type of a modal filter. Most of these don't have semantics yet; the variation is just for
-/// visual representation
+/// The type of a modal filter. Besides deciding who can pass, the variation is just for visual
+/// representation.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum FilterType {
     NoEntry,
@@ -16,6 +16,18 @@ pub enum FilterType {
     SchoolStreet,
 }
 
+impl FilterType {
+    /// Can this kind of traffic pass through the filter? School streets are treated as closed all
+    /// day.
+    pub fn allows(self, constraints: PathConstraints) -> bool {
+        match constraints {
+            PathConstraints::Pedestrian | PathConstraints::Bike | PathConstraints::Train => true,
+            PathConstraints::Bus => self == FilterType::BusGate,
+            PathConstraints::Car => false,
+        }
+    }
+}
+
 /// A filter placed somewhere along a road
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct RoadFilter {
@@ -10,7 +10,7 @@ use geom::{Distance, PolyLine, Polygon, Speed};
 
 use crate::{
     osm, AccessRestrictions, CommonEndpoint, CrossingType, Direction, DrivingSide, IntersectionID,
-    Lane, LaneID, LaneSpec, LaneType, Map, PathConstraints, RestrictionType, RoadFilter,
+    Lane, LaneID, LaneSpec, LaneType, Map, PathConstraints, RestrictionType, RoadFilter, TrailType,
     TransitStopID, Zone,
 };
 
@@ -193,6 +193,8 @@ pub struct Road {
     /// Is there a tagged crosswalk near each end of the road?
     pub crosswalk_forward: bool,
     pub crosswalk_backward: bool,
+    /// An off-road path or track, with bike lanes and a path for walking
+    pub trail: Option<TrailType>,
 
     /// Meaningless order
     pub transit_stops: BTreeSet<TransitStopID>,
@@ -207,6 +209,14 @@ pub struct Road {
     pub crossing_nodes: Vec<(Distance, CrossingType)>,
     /// Sorted by increasing distance
     pub crossings: Vec<Crossing>,
+    /// If this is a slip lane cutting the corner of an intersection, which one it bypasses. This
+    /// is detected during import and kept even if the slip lane is later edited away.
+    pub slip_lane_for: Option<IntersectionID>,
+    /// If this is one side of a dual carriageway, the roads running alongside it in the other
+    /// direction. Also detected during import; see `Map::dual_carriageway` for the whole thing.
+    pub opposite_carriageway: BTreeSet<RoadID>,
+    /// Some driving lanes may be reserved for high-occupancy vehicles
+    pub hov_lanes: Option<HovLanes>,
 }
 
 impl Road {
@@ -450,6 +460,10 @@ impl Road {
             )
     }
 
+    pub fn is_trail(&self) -> bool {
+        self.trail.is_some()
+    }
+
     pub fn is_service(&self) -> bool {
         self.osm_tags.is(osm::HIGHWAY, "service")
     }
@@ -470,6 +484,111 @@ impl Road {
         self.lanes.iter().any(|l| l.is_driving())
     }
 
+    /// Is this a slip lane that vehicles can still use?
+    pub fn is_slip_lane(&self) -> bool {
+        self.slip_lane_for.is_some() && self.is_driveable()
+    }
+
+    /// If this lane is reserved for high-occupancy vehicles, returns the restriction.
+    pub fn hov_restriction(&self, l: LaneID) -> Option<&HovLanes> {
+        self.hov_lanes
+            .as_ref()
+            .filter(|hov| hov.lanes.contains(&l.offset))
+    }
+
+    /// If every driving lane in one direction is reserved for high-occupancy vehicles, returns the
+    /// restriction. Vehicles that don't qualify can't use this direction at all, unless they pay
+    /// a toll.
+    pub fn hov_only(&self, dir: Direction) -> Option<&HovLanes> {
+        let hov = self.hov_lanes.as_ref()?;
+        let mut driving = self
+            .lanes
+            .iter()
+            .filter(|l| l.dir == dir && l.is_driving())
+            .peekable();
+        driving.peek()?;
+        if driving.all(|l| hov.lanes.contains(&l.id.offset)) {
+            Some(hov)
+        } else {
+            None
+        }
+    }
+
+    /// Reads `hov:lanes` (<https://wiki.openstreetmap.org/wiki/Key:hov>). Lanes also tagged in
+    /// `toll:lanes` are high-occupancy toll (HOT) lanes.
+    pub(crate) fn hov_lanes_from_osm(&self, lanes_ltr: &[LaneSpec]) -> Option<HovLanes> {
+        let mut lanes = BTreeSet::new();
+        let mut tolled = false;
+        for dir in [Direction::Fwd, Direction::Back] {
+            // Like turn:lanes, the plain tag describes forwards lanes
+            let suffix = if dir == Direction::Back {
+                ":backward"
+            } else if self.osm_tags.contains_key("hov:lanes:forward") {
+                ":forward"
+            } else {
+                ""
+            };
+            let hov = match self.osm_tags.get(&format!("hov:lanes{}", suffix)) {
+                Some(hov) => hov,
+                None => {
+                    continue;
+                }
+            };
+            // Like turn:lanes, these're listed from the driver's left to right
+            let mut offsets: Vec<usize> = (0..lanes_ltr.len())
+                .filter(|idx| {
+                    lanes_ltr[*idx].dir == dir
+                        && matches!(lanes_ltr[*idx].lt, LaneType::Driving | LaneType::Bus)
+                })
+                .collect();
+            if dir == Direction::Back {
+                offsets.reverse();
+            }
+            let parts: Vec<&str> = hov.split('|').collect();
+            if parts.len() != offsets.len() {
+                warn!("{}'s hov:lanes don't match the lanes", self.orig_id);
+                continue;
+            }
+            let tolls: Vec<String> = self
+                .osm_tags
+                .get(&format!("toll:lanes{}", suffix))
+                .map(|x| x.split('|').map(|x| x.to_string()).collect())
+                .unwrap_or_default();
+            for (idx, (offset, part)) in offsets.into_iter().zip(parts).enumerate() {
+                if (part == "designated" || part == "lane")
+                    && lanes_ltr[offset].lt == LaneType::Driving
+                {
+                    lanes.insert(offset);
+                    if tolls.get(idx).map(|x| x == "yes").unwrap_or(false) {
+                        tolled = true;
+                    }
+                }
+            }
+        }
+        if lanes.is_empty() {
+            return None;
+        }
+
+        Some(HovLanes {
+            lanes,
+            min_occupancy: self
+                .osm_tags
+                .get("hov:minimum")
+                .and_then(|x| x.parse::<usize>().ok())
+                .unwrap_or(2),
+            toll: if tolled {
+                Some(
+                    self.osm_tags
+                        .get("charge")
+                        .and_then(|x| x.split(' ').next()?.parse::<f64>().ok())
+                        .unwrap_or(DEFAULT_HOT_TOLL),
+                )
+            } else {
+                None
+            },
+        })
+    }
+
     pub fn common_endpoint(&self, other: &Road) -> CommonEndpoint {
         CommonEndpoint::new((self.src_i, self.dst_i), (other.src_i, other.dst_i))
     }
@@ -495,6 +614,20 @@ impl Road {
         self.access_restrictions != AccessRestrictions::new() && !self.is_light_rail()
     }
 
+    /// Every crossing mapped along this road, including mid-block ones
+    pub(crate) fn crossings_from_osm(&self) -> Vec<Crossing> {
+        let mut crossings: Vec<Crossing> = self
+            .crossing_nodes
+            .iter()
+            .map(|(dist, kind)| Crossing {
+                kind: *kind,
+                dist: *dist,
+            })
+            .collect();
+        crossings.sort_by_key(|c| c.dist);
+        crossings
+    }
+
     pub(crate) fn access_restrictions_from_osm(&self) -> AccessRestrictions {
         let allow_through_traffic = if self.osm_tags.is("access", "private") {
             EnumSet::new()
@@ -734,8 +867,79 @@ impl OriginalRoad {
     }
 }
 
+/// HOT lanes without a tagged charge use this toll
+pub const DEFAULT_HOT_TOLL: f64 = 2.0;
+
+/// Lanes reserved for high-occupancy vehicles (HOV). In high-occupancy toll (HOT) lanes, other
+/// vehicles may pay to use them.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct HovLanes {
+    /// Offsets of the reserved lanes. They must be driving lanes.
+    pub lanes: BTreeSet<usize>,
+    /// Vehicles carrying at least this many people qualify
+    pub min_occupancy: usize,
+    /// If present, vehicles that don't qualify pay this much (in the local currency) each time
+    /// they enter one of these lanes. Otherwise they can't use them at all.
+    pub toll: Option<f64>,
+}
+
+impl HovLanes {
+    pub fn qualifies(&self, occupancy: usize) -> bool {
+        occupancy >= self.min_occupancy
+    }
+
+    /// Call after inserting a lane at this offset, so the same lanes stay reserved.
+    pub fn lane_inserted(&mut self, idx: usize) {
+        self.lanes = self
+            .lanes
+            .iter()
+            .map(|x| if *x >= idx { x + 1 } else { *x })
+            .collect();
+    }
+
+    /// Call after removing the lane at this offset, so the same lanes stay reserved.
+    pub fn lane_removed(&mut self, idx: usize) {
+        self.lanes = self
+            .lanes
+            .iter()
+            .filter(|x| **x != idx)
+            .map(|x| if *x > idx { x - 1 } else { *x })
+            .collect();
+    }
+
+    /// Call after moving a lane from one offset to another. A reserved lane stays reserved.
+    pub fn lane_moved(&mut self, old_idx: usize, new_idx: usize) {
+        let reserved = self.lanes.contains(&old_idx);
+        self.lane_removed(old_idx);
+        self.lane_inserted(new_idx);
+        if reserved {
+            self.lanes.insert(new_idx);
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Crossing {
     pub kind: CrossingType,
     pub dist: Distance,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hov_lanes_follow_lane_edits() {
+        let mut hov = HovLanes {
+            lanes: vec![1, 3].into_iter().collect(),
+            min_occupancy: 2,
+            toll: None,
+        };
+        hov.lane_inserted(2);
+        assert_eq!(hov.lanes, vec![1, 4].into_iter().collect());
+        hov.lane_removed(1);
+        assert_eq!(hov.lanes, vec![3].into_iter().collect());
+        hov.lane_moved(3, 0);
+        assert_eq!(hov.lanes, vec![0].into_iter().collect());
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_usize, serialize_usize};
+use geom::{PolyLine, Polygon, Pt2D};
+
+use crate::{osm, BuildingID, NamePerLanguage, RoadID};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SiteID(
+    #[serde(
+        serialize_with = "serialize_usize",
+        deserialize_with = "deserialize_usize"
+    )]
+    pub usize,
+);
+
+impl fmt::Display for SiteID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Site #{}", self.0)
+    }
+}
+
+/// A large site like a hospital, campus, or mall. Its internal service roads aren't part of the
+/// road network; instead, all of its buildings connect to the public network at a few access
+/// points.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Site {
+    pub id: SiteID,
+    pub polygon: Polygon,
+    pub name: Option<NamePerLanguage>,
+    pub osm_id: osm::OsmID,
+    /// Service roads and parking aisles inside the site, just for drawing
+    pub aisles: Vec<PolyLine>,
+    /// Roads crossing the site's boundary, and where they meet the public network outside of it
+    pub access_points: Vec<(RoadID, Pt2D)>,
+    pub buildings: Vec<BuildingID>,
+}
+
+impl Site {
+    /// The access point closest to somewhere in the site, or None if nothing connects to the site
+    pub fn closest_access_point(&self, pt: Pt2D) -> Option<Pt2D> {
+        self.access_points
+            .iter()
+            .map(|(_, access)| *access)
+            .min_by_key(|access| access.dist_to(pt))
+    }
+}
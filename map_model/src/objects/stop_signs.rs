@@ -33,6 +33,74 @@ use crate::{
 // 6) Additionally, individual turns can be banned completely.
 //    - Even though letting players manipulate this could make parts of the map unreachable?
 
+/// Where OSM doesn't say how an intersection is controlled, the defaults depend on the country.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ControlDefaults {
+    /// Between roads of the same importance, do drivers yield to traffic from the right (as in
+    /// much of continental Europe), instead of everyone stopping (as in North America)?
+    pub priority_to_the_right: bool,
+    /// Are roundabouts common and consistently mapped? Then traffic already on one always has
+    /// priority, even over bigger roads entering it.
+    pub roundabouts_common: bool,
+}
+
+impl ControlDefaults {
+    /// `country_code` is ISO 3166-1 alpha-2, in either case
+    pub fn for_country(country_code: &str) -> ControlDefaults {
+        let country = country_code.to_lowercase();
+        ControlDefaults {
+            priority_to_the_right: matches!(
+                country.as_str(),
+                "at" | "be"
+                    | "bg"
+                    | "ch"
+                    | "cz"
+                    | "de"
+                    | "dk"
+                    | "ee"
+                    | "es"
+                    | "fi"
+                    | "fr"
+                    | "gr"
+                    | "hr"
+                    | "hu"
+                    | "it"
+                    | "lt"
+                    | "lu"
+                    | "lv"
+                    | "nl"
+                    | "no"
+                    | "pl"
+                    | "pt"
+                    | "ro"
+                    | "rs"
+                    | "se"
+                    | "si"
+                    | "sk"
+                    | "ua"
+            ),
+            roundabouts_common: matches!(
+                country.as_str(),
+                "au" | "be"
+                    | "ch"
+                    | "de"
+                    | "dk"
+                    | "es"
+                    | "fr"
+                    | "gb"
+                    | "ie"
+                    | "it"
+                    | "nl"
+                    | "no"
+                    | "nz"
+                    | "pl"
+                    | "pt"
+                    | "se"
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ControlStopSign {
     pub id: IntersectionID,
@@ -111,10 +179,12 @@ impl ControlStopSign {
 
         // Rank each road based on OSM highway type, and additionally:
         // - Treat cycleways as lower priority than local roads (sad but typical reality)
-        // - Prioritize roundabouts, so they clear out faster than people enter them
+        // - Prioritize roundabouts, so they clear out faster than people enter them. Where they're
+        //   common, this wins over the highway type.
         // - Treat on/off ramps with less priority than the main part of the highway
         // - Lower the priority of service roads
-        let mut rank: HashMap<RoadID, (osm::RoadRank, usize)> = HashMap::new();
+        let defaults = map.get_control_defaults();
+        let mut rank: HashMap<RoadID, (bool, osm::RoadRank, usize)> = HashMap::new();
         for r in ss.roads.keys() {
             let r = map.get_r(*r);
             // Lower number is lower priority
@@ -132,7 +202,9 @@ impl ControlStopSign {
             } else {
                 2
             };
-            rank.insert(r.id, (r.get_rank(), priority));
+            let circulating =
+                defaults.roundabouts_common && r.osm_tags.is("junction", "roundabout");
+            rank.insert(r.id, (circulating, r.get_rank(), priority));
         }
         let mut ranks = rank.values().cloned().collect::<Vec<_>>();
         ranks.sort();
@@ -140,8 +212,11 @@ impl ControlStopSign {
         // Highest rank is first
         ranks.reverse();
 
-        // If all roads have the same rank, all-way stop. Otherwise, everything stops except the
-        // highest-priority roads.
+        // If all roads have the same rank, all-way stop, unless drivers yield to the right here.
+        // Otherwise, everything stops except the highest-priority roads.
+        if ranks.len() == 1 && defaults.priority_to_the_right {
+            return ss;
+        }
         for (r, cfg) in ss.roads.iter_mut() {
             if ranks.len() == 1 || rank[r] != ranks[0] {
                 // Don't stop in the middle of something that's likely actually an intersection.
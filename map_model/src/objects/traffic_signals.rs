@@ -23,6 +23,58 @@ pub struct ControlTrafficSignal {
     pub id: IntersectionID,
     pub stages: Vec<Stage>,
     pub offset: Duration,
+    /// If present, the signal responds to detected vehicles instead of running each stage for its
+    /// fixed duration.
+    pub actuation: Option<Actuation>,
+    /// If present, buses and trains approaching the signal can get an early or extended green.
+    pub transit_priority: Option<TransitPriority>,
+}
+
+/// Actuated signals end a stage early when nobody's detected on its approaches, and skip stages
+/// that nobody's waiting for, so quiet side streets don't waste cycle time.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Actuation {
+    /// A stage always lasts at least this long, or longer if its crosswalks need more time
+    pub min_green: Duration,
+    /// A stage ends after this long if anybody else is waiting, even if more vehicles keep
+    /// arriving
+    pub max_green: Duration,
+    /// After the minimum green, the stage is extended by this much at a time while vehicles are
+    /// detected
+    pub extension: Duration,
+    /// Vehicles within this distance of the stop line are detected
+    pub detector_length: Distance,
+}
+
+impl Actuation {
+    /// Typical settings for an urban intersection
+    pub fn new() -> Actuation {
+        Actuation {
+            min_green: Duration::seconds(7.0),
+            max_green: Duration::seconds(45.0),
+            extension: Duration::seconds(3.0),
+            detector_length: Distance::meters(30.0),
+        }
+    }
+}
+
+/// Transit signal priority. When a bus or train enters the last lane before the signal, stages
+/// that don't serve it are cut short, or the current green is held until it gets through.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TransitPriority {
+    /// Stages are never cut shorter than this, or than their crosswalks need
+    pub min_green: Duration,
+    /// The green is held at most this much longer for an approaching bus
+    pub max_extension: Duration,
+}
+
+impl TransitPriority {
+    pub fn new() -> TransitPriority {
+        TransitPriority {
+            min_green: Duration::seconds(10.0),
+            max_extension: Duration::seconds(15.0),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -32,6 +84,10 @@ pub struct Stage {
     // TODO Not renaming this, because this is going to change radically in
     // https://github.com/a-b-street/abstreet/pull/298 anyway
     pub stage_type: StageType,
+    /// Only bikes coming from bike lanes may move during this stage, like the "simultaneous green"
+    /// for cyclists at Dutch intersections. Everything else waits.
+    #[serde(default)]
+    pub bikes_only: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -201,6 +257,36 @@ impl ControlTrafficSignal {
         self != &orig
     }
 
+    /// Adds a stage at the end where only bikes on bike lanes move, in every direction between
+    /// roads with bike lanes. Movements that can't be protected together yield. True is returned
+    /// if the stage was added.
+    pub fn add_bike_stage(&mut self, map: &Map) -> bool {
+        let i = map.get_i(self.id);
+        let mut bike_stage = Stage::new();
+        bike_stage.bikes_only = true;
+        bike_stage.stage_type = StageType::Fixed(Duration::seconds(15.0));
+        for m in i.movements.values() {
+            if m.turn_type.pedestrian_crossing()
+                || !m
+                    .members
+                    .iter()
+                    .any(|t| map.get_l(t.src).is_biking() && map.get_l(t.dst).is_biking())
+            {
+                continue;
+            }
+            if bike_stage.could_be_protected(m.id, i) {
+                bike_stage.edit_movement(m, TurnPriority::Protected);
+            } else {
+                bike_stage.edit_movement(m, TurnPriority::Yield);
+            }
+        }
+        if bike_stage.protected_movements.is_empty() || self.stages.contains(&bike_stage) {
+            return false;
+        }
+        self.stages.push(bike_stage);
+        true
+    }
+
     /// Modifies the fixed timing of all stages, applying either a major or minor duration,
     /// depending on the relative rank of the roads involved in the intersection. If this
     /// transformation couldn't be applied, returns an error. Even if an error is returned, the
@@ -271,6 +357,21 @@ impl ControlTrafficSignal {
         missing
     }
 
+    /// Would this movement be turning on red during a stage, from a road where that's banned?
+    /// Turning on red means nothing else from the same road has a green. Roads that never get a
+    /// green are exempt, or nobody could ever leave them.
+    pub fn is_banned_turn_on_red(&self, stage: usize, m: MovementID, i: &Intersection) -> bool {
+        if m.crosswalk || !i.no_turn_on_red.contains(&m.from.road) {
+            return false;
+        }
+        let has_green = |s: &Stage| {
+            s.protected_movements
+                .iter()
+                .any(|other| !other.crosswalk && other.from.road == m.from.road)
+        };
+        !has_green(&self.stages[stage]) && self.stages.iter().any(has_green)
+    }
+
     /// How long a full cycle of the signal lasts, assuming no actuated timings.
     pub fn simple_cycle_duration(&self) -> Duration {
         let mut total = Duration::ZERO;
@@ -288,6 +389,7 @@ impl Stage {
             yield_movements: BTreeSet::new(),
             // TODO Set a default
             stage_type: StageType::Fixed(Duration::seconds(30.0)),
+            bikes_only: false,
         }
     }
 
@@ -301,8 +403,12 @@ impl Stage {
         true
     }
 
-    pub fn get_priority_of_turn(&self, t: TurnID, i: &Intersection) -> TurnPriority {
-        self.get_priority_of_movement(i.turn_to_movement(t).0)
+    /// Unlike `get_priority_of_movement`, this handles bike-only stages.
+    pub fn get_priority_of_turn(&self, t: TurnID, map: &Map) -> TurnPriority {
+        if self.bikes_only && !map.get_l(t.src).is_biking() {
+            return TurnPriority::Banned;
+        }
+        self.get_priority_of_movement(map.get_i(t.parent).turn_to_movement(t).0)
     }
 
     pub fn get_priority_of_movement(&self, m: MovementID) -> TurnPriority {
@@ -397,9 +503,25 @@ impl ControlTrafficSignal {
                                 )
                             }
                         },
+                        bikes_only: s.bikes_only,
                     })
                     .collect(),
                 offset_seconds: self.offset.inner_seconds() as usize,
+                actuation: self
+                    .actuation
+                    .as_ref()
+                    .map(|a| perma_traffic_signal::Actuation {
+                        min_green_seconds: a.min_green.inner_seconds() as usize,
+                        max_green_seconds: a.max_green.inner_seconds() as usize,
+                        extension_seconds: a.extension.inner_seconds() as usize,
+                        detector_length_meters: a.detector_length.inner_meters(),
+                    }),
+                transit_priority: self.transit_priority.as_ref().map(|p| {
+                    perma_traffic_signal::TransitPriority {
+                        min_green_seconds: p.min_green.inner_seconds() as usize,
+                        max_extension_seconds: p.max_extension.inner_seconds() as usize,
+                    }
+                }),
             }],
         }
     }
@@ -452,6 +574,7 @@ impl ControlTrafficSignal {
                             )
                         }
                     },
+                    bikes_only: s.bikes_only,
                 });
             } else {
                 bail!("{}", errors.join("; "));
@@ -461,6 +584,16 @@ impl ControlTrafficSignal {
             id,
             stages,
             offset: Duration::seconds(plan.offset_seconds as f64),
+            actuation: plan.actuation.map(|a| Actuation {
+                min_green: Duration::seconds(a.min_green_seconds as f64),
+                max_green: Duration::seconds(a.max_green_seconds as f64),
+                extension: Duration::seconds(a.extension_seconds as f64),
+                detector_length: Distance::meters(a.detector_length_meters),
+            }),
+            transit_priority: plan.transit_priority.map(|p| TransitPriority {
+                min_green: Duration::seconds(p.min_green_seconds as f64),
+                max_extension: Duration::seconds(p.max_extension_seconds as f64),
+            }),
         };
         ts.validate(map.get_i(id))?;
         Ok(ts)
@@ -6,7 +6,7 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_usize, serialize_usize};
-use geom::Time;
+use geom::{Duration, Time};
 
 use crate::{LaneID, Map, Path, PathConstraints, PathRequest, Position, RoadID};
 
@@ -49,6 +49,11 @@ pub struct TransitStop {
     pub sidewalk_pos: Position,
     /// If false, only buses serve this stop
     pub is_train_stop: bool,
+    /// Applies to every route serving this stop
+    pub boarding: BoardingFeatures,
+    /// Temporarily closed, like during construction. Vehicles pass by without stopping, and
+    /// nobody plans to get on or off here.
+    pub closed: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -69,6 +74,59 @@ pub struct TransitRoute {
     /// Explicitly store whatever the original was, since this can't be reconstructed without side
     /// input.
     pub orig_spawn_times: Vec<Time>,
+    /// Applies at every stop along this route
+    pub boarding: BoardingFeatures,
+}
+
+/// Features that affect how long a transit vehicle dwells at a stop. Some belong to the stop (a
+/// raised platform) and some to the route (the fleet and fare policy); a feature applies if
+/// either has it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardingFeatures {
+    /// Passengers board through every door, not just the front one
+    pub all_door_boarding: bool,
+    /// Fares are paid before boarding, so nobody has to pay the driver
+    pub off_board_fares: bool,
+    /// The platform is level with the vehicle floor, so nobody has to climb steps or wait for a
+    /// ramp
+    pub level_boarding: bool,
+}
+
+impl BoardingFeatures {
+    pub fn union(self, other: BoardingFeatures) -> BoardingFeatures {
+        BoardingFeatures {
+            all_door_boarding: self.all_door_boarding || other.all_door_boarding,
+            off_board_fares: self.off_board_fares || other.off_board_fares,
+            level_boarding: self.level_boarding || other.level_boarding,
+        }
+    }
+
+    /// How long a vehicle stays at a stop, given how many people get on and off. Based roughly on
+    /// the Transit Capacity and Quality of Service Manual.
+    pub fn dwell_time(self, boarding: usize, alighting: usize) -> Duration {
+        let doors_open_and_close = Duration::seconds(5.0);
+        let mut per_boarding = if self.off_board_fares {
+            Duration::seconds(2.0)
+        } else {
+            Duration::seconds(3.5)
+        };
+        let mut per_alighting = Duration::seconds(2.0);
+        if self.level_boarding {
+            per_boarding = per_boarding * 0.75;
+            per_alighting = per_alighting * 0.75;
+        }
+        let on = (boarding as f64) * per_boarding;
+        let off = (alighting as f64) * per_alighting;
+
+        let passengers = if self.all_door_boarding {
+            // Both flows are spread over the doors, assuming a typical 3-door vehicle
+            (on + off) / 3.0
+        } else {
+            // Everybody boards at the front door, while people get off through the others
+            on.max(off)
+        };
+        doors_open_and_close + passengers
+    }
 }
 
 impl TransitRoute {
@@ -138,6 +196,21 @@ impl TransitRoute {
         Ok(paths)
     }
 
+    /// How often vehicles depart in the hour around this time, or None if there's no service then
+    pub fn headway_at(&self, time: Time) -> Option<Duration> {
+        let window = Duration::hours(1);
+        let num = self
+            .spawn_times
+            .iter()
+            .filter(|t| **t >= time - window / 2.0 && **t < time + window / 2.0)
+            .count();
+        if num == 0 {
+            None
+        } else {
+            Some(window / (num as f64))
+        }
+    }
+
     pub fn plural_noun(&self) -> &'static str {
         if self.route_type == PathConstraints::Bus {
             "buses"
@@ -146,3 +219,38 @@ impl TransitRoute {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(features: BoardingFeatures, boarding: usize, alighting: usize) -> f64 {
+        (features.dwell_time(boarding, alighting).inner_seconds() * 100.0).round() / 100.0
+    }
+
+    #[test]
+    fn test_dwell_time() {
+        // Most stops and routes don't have any features, so this is the usual case in the
+        // simulation
+        let none = BoardingFeatures::default();
+        assert_eq!(seconds(none, 0, 0), 5.0);
+        assert_eq!(seconds(none, 1, 1), 8.5);
+        // The front door is the bottleneck
+        assert_eq!(seconds(none, 4, 2), 19.0);
+        assert_eq!(seconds(none, 1, 6), 17.0);
+
+        let off_board_fares = BoardingFeatures {
+            off_board_fares: true,
+            ..Default::default()
+        };
+        assert_eq!(seconds(off_board_fares, 4, 2), 13.0);
+
+        let everything = BoardingFeatures {
+            all_door_boarding: true,
+            off_board_fares: true,
+            level_boarding: true,
+        };
+        assert_eq!(seconds(everything, 4, 2), 8.0);
+        assert_eq!(none.union(everything), everything);
+    }
+}
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use geom::{Angle, Line, PolyLine};
 
 use crate::{
-    DirectedRoadID, Direction, Intersection, IntersectionID, LaneID, Map, MovementID,
+    DirectedRoadID, Direction, DrivingSide, Intersection, IntersectionID, LaneID, Map, MovementID,
     PathConstraints, RestrictionType,
 };
 
@@ -102,6 +102,25 @@ impl Turn {
         self.geom.intersection(&other.geom).is_some()
     }
 
+    /// Does a vehicle making this turn cut across a cyclist going straight from the same road?
+    /// This is a "right hook" where people drive on the right, and a left hook otherwise. At
+    /// protected intersections, corner islands keep them apart, so it doesn't count.
+    pub fn hooks_across(&self, bike_turn: &Turn, map: &Map) -> bool {
+        let near_side_turn = if map.get_config().driving_side == DrivingSide::Right {
+            TurnType::Right
+        } else {
+            TurnType::Left
+        };
+        self.turn_type == near_side_turn
+            && bike_turn.turn_type == TurnType::Straight
+            && self.id.parent == bike_turn.id.parent
+            && self.id.src.road == bike_turn.id.src.road
+            && !map.get_l(self.id.src).is_biking()
+            && map.get_l(bike_turn.id.src).is_biking()
+            && !map.get_i(self.id.parent).protected_corners
+            && self.conflicts_with(bike_turn)
+    }
+
     // The relative angle of the turn, should be the angle from the src lane to the dst lane, but
     // instead uses the first and last lines of the turn geometry, which is currently not quite the
     // same angle as between the source and destination lanes
@@ -219,6 +238,19 @@ impl Turn {
         })
     }
 
+    /// Does this crosswalk cross a slip lane? Drivers there are usually looking the other way for
+    /// a gap in traffic, not for pedestrians.
+    pub fn is_crossing_slip_lane(&self, map: &Map) -> bool {
+        if !self.turn_type.pedestrian_crossing() {
+            return false;
+        }
+        map.get_i(self.id.parent).roads.iter().any(|r| {
+            let road = map.get_r(*r);
+            // The trimmed center line stops at the edge of the intersection, before the crosswalk
+            road.is_slip_lane() && self.geom.intersection(&road.untrimmed_center_pts).is_some()
+        })
+    }
+
     /// Is this turn legal, according to turn lane tagging?
     pub(crate) fn permitted_by_lane(&self, map: &Map) -> bool {
         if let Some(types) = map
@@ -0,0 +1,46 @@
+//! Parking can cost money and have a time limit, per blockface or parking lot. It's part of the
+//! map edits, so parking reform proposals can be compared against the same demand. The simulation
+//! weighs the price against how far drivers have to walk.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::Duration;
+
+use crate::{ParkingLotID, RoadID};
+
+/// The price and time limit for some parking
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ParkingPolicy {
+    /// What an hour of parking costs
+    pub hourly_price: f64,
+    /// Nobody can stay longer than this
+    pub max_stay: Option<Duration>,
+}
+
+impl ParkingPolicy {
+    /// How much staying this long costs, or None if it's over the time limit
+    pub fn cost_of_stay(&self, stay: Duration) -> Option<f64> {
+        if let Some(max) = self.max_stay {
+            if stay > max {
+                return None;
+            }
+        }
+        Some(self.hourly_price * stay.inner_seconds() / 3600.0)
+    }
+}
+
+/// Parking without a policy is free and unlimited.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ParkingPricing {
+    /// Covers all on-street parking along both sides of a road
+    pub blockfaces: BTreeMap<RoadID, ParkingPolicy>,
+    pub lots: BTreeMap<ParkingLotID, ParkingPolicy>,
+}
+
+impl ParkingPricing {
+    pub fn is_empty(&self) -> bool {
+        self.blockfaces.is_empty() && self.lots.is_empty()
+    }
+}
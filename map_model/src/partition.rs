@@ -0,0 +1,302 @@
+//! Split the map into balanced parts with few roads between them, as groundwork for running a huge
+//! region's simulation across multiple processes. Each process would own the intersections in one
+//! part, and agents crossing a boundary road get handed off to the neighbouring process.
+//!
+//! This is a simple take on METIS-style balanced partitioning: recursive bisection by growing a
+//! region from a peripheral intersection, followed by greedy boundary refinement.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{IntersectionID, LaneID, Map, RoadID};
+
+// Parts may be this much heavier than the ideal size, to allow moves that shrink the boundary
+const IMBALANCE_TOLERANCE: f64 = 0.05;
+const REFINEMENT_PASSES: usize = 10;
+
+/// Which part of the map owns each intersection
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Partitioning {
+    pub num_parts: usize,
+    /// Indexed by `IntersectionID`
+    part_per_intersection: Vec<usize>,
+    /// Roads whose endpoints are in different parts. Agents on these are handed off.
+    pub boundary_roads: BTreeSet<RoadID>,
+}
+
+impl Partitioning {
+    pub fn part_of(&self, i: IntersectionID) -> usize {
+        self.part_per_intersection[i.0]
+    }
+
+    pub fn intersections_in(&self, part: usize) -> Vec<IntersectionID> {
+        self.part_per_intersection
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| **p == part)
+            .map(|(idx, _)| IntersectionID(idx))
+            .collect()
+    }
+
+    /// If this lane leads from one part into another, returns (from, to) parts
+    pub fn lane_crosses(&self, map: &Map, l: LaneID) -> Option<(usize, usize)> {
+        let lane = map.get_l(l);
+        let from = self.part_of(lane.src_i);
+        let to = self.part_of(lane.dst_i);
+        if from == to {
+            None
+        } else {
+            Some((from, to))
+        }
+    }
+
+    /// The total weight of each part, and the number of lanes crossing boundaries
+    pub fn summarize(&self, map: &Map) -> (Vec<usize>, usize) {
+        let mut weights = vec![0; self.num_parts];
+        for i in map.all_intersections() {
+            weights[self.part_of(i.id)] += intersection_weight(map, i.id);
+        }
+        let cut = self
+            .boundary_roads
+            .iter()
+            .map(|r| road_weight(map, *r))
+            .sum();
+        (weights, cut)
+    }
+
+    /// Every intersection with a "part" property, and boundary roads in their own layer
+    pub fn to_geojson(&self, map: &Map) -> geojson::GeoJson {
+        let gps_bounds = Some(map.get_gps_bounds());
+        let mut pairs = Vec::new();
+        for i in map.all_intersections() {
+            let mut props = serde_json::Map::new();
+            props.insert("layer".to_string(), "intersection".into());
+            props.insert("id".to_string(), i.id.0.into());
+            props.insert("part".to_string(), self.part_of(i.id).into());
+            pairs.push((i.polygon.to_geojson(gps_bounds), props));
+        }
+        for r in &self.boundary_roads {
+            let road = map.get_r(*r);
+            let mut props = serde_json::Map::new();
+            props.insert("layer".to_string(), "boundary".into());
+            props.insert("id".to_string(), r.0.into());
+            props.insert("src_part".to_string(), self.part_of(road.src_i).into());
+            props.insert("dst_part".to_string(), self.part_of(road.dst_i).into());
+            pairs.push((road.center_pts.to_geojson(gps_bounds), props));
+        }
+        geom::geometries_with_properties_to_geojson(pairs)
+    }
+}
+
+impl Map {
+    /// Splits the map into `num_parts` pieces of roughly equal simulation work, cutting as few
+    /// lanes as possible. Each intersection is weighted by the lanes it has to manage.
+    pub fn partition(&self, num_parts: usize) -> Result<Partitioning> {
+        if num_parts == 0 {
+            bail!("Need at least one part");
+        }
+        if num_parts > self.intersections.len() {
+            bail!(
+                "Can't split {} intersections into {} parts",
+                self.intersections.len(),
+                num_parts
+            );
+        }
+
+        let mut part_per_intersection = vec![0; self.intersections.len()];
+        let all: BTreeSet<IntersectionID> = self.intersections.iter().map(|i| i.id).collect();
+        self.bisect(all, 0, num_parts, &mut part_per_intersection);
+        self.refine_partition(num_parts, &mut part_per_intersection);
+
+        let boundary_roads = self
+            .all_roads()
+            .iter()
+            .filter(|r| part_per_intersection[r.src_i.0] != part_per_intersection[r.dst_i.0])
+            .map(|r| r.id)
+            .collect();
+        Ok(Partitioning {
+            num_parts,
+            part_per_intersection,
+            boundary_roads,
+        })
+    }
+
+    fn bisect(
+        &self,
+        members: BTreeSet<IntersectionID>,
+        first_part: usize,
+        num_parts: usize,
+        part_per_intersection: &mut [usize],
+    ) {
+        if num_parts == 1 {
+            for i in members {
+                part_per_intersection[i.0] = first_part;
+            }
+            return;
+        }
+        let left_parts = num_parts / 2;
+        let total: usize = members.iter().map(|i| intersection_weight(self, *i)).sum();
+        let target = total * left_parts / num_parts;
+
+        let left = self.grow_region(&members, target);
+        let right = members.difference(&left).cloned().collect();
+        self.bisect(left, first_part, left_parts, part_per_intersection);
+        self.bisect(
+            right,
+            first_part + left_parts,
+            num_parts - left_parts,
+            part_per_intersection,
+        );
+    }
+
+    /// Grows a connected region from the edge of `members` until it reaches the target weight. If
+    /// the members are disconnected, continues from another piece.
+    fn grow_region(
+        &self,
+        members: &BTreeSet<IntersectionID>,
+        target: usize,
+    ) -> BTreeSet<IntersectionID> {
+        let mut region = BTreeSet::new();
+        let mut weight = 0;
+        while weight < target {
+            let start = match members.iter().find(|i| !region.contains(*i)) {
+                Some(i) => *i,
+                None => break,
+            };
+            // Two hops of breadth-first search find a roughly peripheral starting point
+            let start = self.farthest_from(
+                members,
+                &region,
+                self.farthest_from(members, &region, start),
+            );
+
+            let mut queue = VecDeque::new();
+            queue.push_back(start);
+            region.insert(start);
+            weight += intersection_weight(self, start);
+            while let Some(i) = queue.pop_front() {
+                if weight >= target {
+                    break;
+                }
+                for next in self.partition_neighbors(i) {
+                    if weight >= target {
+                        break;
+                    }
+                    if members.contains(&next) && region.insert(next) {
+                        weight += intersection_weight(self, next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        region
+    }
+
+    /// The last intersection reached by breadth-first search from `start`, staying within
+    /// `members` and outside `exclude`
+    fn farthest_from(
+        &self,
+        members: &BTreeSet<IntersectionID>,
+        exclude: &BTreeSet<IntersectionID>,
+        start: IntersectionID,
+    ) -> IntersectionID {
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start);
+        queue.push_back(start);
+        let mut last = start;
+        while let Some(i) = queue.pop_front() {
+            last = i;
+            for next in self.partition_neighbors(i) {
+                if members.contains(&next) && !exclude.contains(&next) && visited.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        last
+    }
+
+    /// Repeatedly moves intersections on a boundary to the neighbouring part they're most
+    /// connected to, when that shrinks the cut and keeps the parts balanced.
+    fn refine_partition(&self, num_parts: usize, part_per_intersection: &mut [usize]) {
+        let mut part_weights = vec![0; num_parts];
+        for i in &self.intersections {
+            part_weights[part_per_intersection[i.id.0]] += intersection_weight(self, i.id);
+        }
+        let ideal = part_weights.iter().sum::<usize>() as f64 / num_parts as f64;
+        let max_weight = (ideal * (1.0 + IMBALANCE_TOLERANCE)).ceil() as usize;
+        let min_weight = (ideal * (1.0 - IMBALANCE_TOLERANCE)).floor() as usize;
+
+        for _ in 0..REFINEMENT_PASSES {
+            let mut moved = false;
+            for i in &self.intersections {
+                let current = part_per_intersection[i.id.0];
+                // How many lanes connect this intersection to each part
+                let mut connections = vec![0; num_parts];
+                for r in &i.roads {
+                    let road = self.get_r(*r);
+                    let other = if road.src_i == i.id {
+                        road.dst_i
+                    } else {
+                        road.src_i
+                    };
+                    if other != i.id {
+                        connections[part_per_intersection[other.0]] += road_weight(self, *r);
+                    }
+                }
+                let weight = intersection_weight(self, i.id);
+                if part_weights[current] < min_weight + weight {
+                    continue;
+                }
+                let best = (0..num_parts)
+                    .filter(|p| *p != current && part_weights[*p] + weight <= max_weight)
+                    .max_by_key(|p| connections[*p]);
+                if let Some(best) = best {
+                    if connections[best] > connections[current] {
+                        part_per_intersection[i.id.0] = best;
+                        part_weights[current] -= weight;
+                        part_weights[best] += weight;
+                        moved = true;
+                    }
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+    }
+
+    fn partition_neighbors(&self, i: IntersectionID) -> Vec<IntersectionID> {
+        self.get_i(i)
+            .roads
+            .iter()
+            .map(|r| {
+                let road = self.get_r(*r);
+                if road.src_i == i {
+                    road.dst_i
+                } else {
+                    road.src_i
+                }
+            })
+            .filter(|other| *other != i)
+            .collect()
+    }
+}
+
+/// Roughly how much simulation work an intersection is: one, plus half the lanes of every road
+/// touching it, so each lane is counted once overall.
+fn intersection_weight(map: &Map, i: IntersectionID) -> usize {
+    let lanes: usize = map
+        .get_i(i)
+        .roads
+        .iter()
+        .map(|r| road_weight(map, *r))
+        .sum();
+    1 + lanes / 2
+}
+
+fn road_weight(map: &Map, r: RoadID) -> usize {
+    map.get_r(r).lanes.len()
+}
@@ -1,19 +1,20 @@
 //! Everything related to pathfinding through a map for different types of agents.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use enumset::EnumSetType;
 use serde::{Deserialize, Serialize};
 
+use abstutil::{deserialize_btreemap, serialize_btreemap};
 use geom::Duration;
 
 pub use self::engine::CreateEngine;
 pub use self::pathfinder::{Pathfinder, PathfinderCache, PathfinderCaching};
 pub use self::v1::{Path, PathRequest, PathStep};
 pub use self::v2::{PathStepV2, PathV2};
-pub use self::vehicles::vehicle_cost;
+pub use self::vehicles::{pathfind_by_cost, vehicle_cost};
 pub use self::walking::WalkingNode;
-use crate::{osm, Lane, LaneID, LaneType, Map, MovementID, Road, RoadID, TurnType};
+use crate::{osm, DirectedRoadID, Lane, LaneID, LaneType, Map, MovementID, Road, RoadID, TurnType};
 
 mod engine;
 mod node_map;
@@ -168,6 +169,17 @@ pub(crate) fn zone_cost(mvmnt: MovementID, constraints: PathConstraints, map: &M
 pub struct RoutingParams {
     // For all vehicles. This is added to the cost of a movement as an additional delay.
     pub unprotected_turn_penalty: Duration,
+    // For all vehicles. This is added to the cost of a U-turn, which is slow and awkward in
+    // traffic, so that routes only use them when the alternative is much longer.
+    pub u_turn_penalty: Duration,
+    // For cars. How many people are in the vehicle, to decide if HOV lanes can be used.
+    pub occupancy: usize,
+    // For cars that don't qualify for HOT lanes. Each unit of currency paid for a toll costs this
+    // much time.
+    pub toll_penalty: Duration,
+    // For cars. What entering the map's congestion charge cordon costs. If None, assume the peak
+    // charge, since the routes baked into the map don't know when anybody departs.
+    pub congestion_charge: Option<f64>,
 
     // For bike routing. Multiplied by the base cost, since spending more time on the wrong lane
     // type matters.
@@ -183,6 +195,9 @@ pub struct RoutingParams {
     pub avoid_steep_incline_penalty: f64,
     // If the road is `high_stress_for_bikes`, multiply by the base cost.
     pub avoid_high_stress: f64,
+    // Multiply the base cost by this for every level of `traffic_stress_for_bikes` above LTS1, so
+    // an LTS3 road costs this squared. 1 ignores traffic stress.
+    pub traffic_stress_penalty: f64,
 
     /// When crossing an arterial or highway road, multiply the base cost by this penalty. When
     /// greater than 1, this will encourage routes to use local roads more.
@@ -198,6 +213,29 @@ pub struct RoutingParams {
     /// Don't allow movements between these roads at all. Only affects vehicle routing, not
     /// pedestrian.
     pub avoid_movements_between: BTreeSet<(RoadID, RoadID)>,
+    /// Don't let vehicles pass through modal filters that block them, using the filters currently
+    /// on the map.
+    pub respect_modal_filters: bool,
+
+    /// For walking. Multiply the cost of sidewalks in full sun by this, scaled down by the
+    /// fraction of the sidewalk in `sidewalk_shade`. Greater than 1 prefers shaded routes on hot
+    /// days.
+    pub sun_exposure_penalty: f64,
+    /// The fraction of each sidewalk in shade, from `Map::sidewalk_shade`. Sidewalks missing here
+    /// are in full sun.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub sidewalk_shade: BTreeMap<LaneID, f64>,
+
+    /// For vehicles. Extra time expected to cross each road, on top of the freeflow time. The
+    /// simulation fills this in from live queues when rerouting vehicles stuck in traffic.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub road_delays: BTreeMap<DirectedRoadID, Duration>,
 }
 
 impl Default for RoutingParams {
@@ -206,6 +244,12 @@ impl Default for RoutingParams {
             // This is a total guess -- it really depends on the traffic patterns of the particular
             // road at the time we're routing.
             unprotected_turn_penalty: Duration::const_seconds(30.0),
+            // Also a guess
+            u_turn_penalty: Duration::const_seconds(20.0),
+            occupancy: 1,
+            // Roughly valuing time at 20/hour
+            toll_penalty: Duration::const_seconds(180.0),
+            congestion_charge: None,
 
             bike_lane_penalty: 1.0,
             bus_lane_penalty: 1.1,
@@ -213,12 +257,19 @@ impl Default for RoutingParams {
 
             avoid_steep_incline_penalty: 1.0,
             avoid_high_stress: 1.0,
+            traffic_stress_penalty: 1.0,
 
             main_road_penalty: 1.0,
 
             avoid_roads: BTreeSet::new(),
             avoid_movements_between: BTreeSet::new(),
             only_use_roads: BTreeSet::new(),
+            respect_modal_filters: true,
+
+            sun_exposure_penalty: 1.0,
+            sidewalk_shade: BTreeMap::new(),
+
+            road_delays: BTreeMap::new(),
         }
     }
 }
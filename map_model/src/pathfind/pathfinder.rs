@@ -1,12 +1,14 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 use thread_local::ThreadLocal;
 
 use abstutil::{Timer, VecMap};
-use geom::Duration;
+use geom::{Duration, Time};
 
+use crate::connectivity::FrequencyBasedTransit;
 use crate::pathfind::engine::CreateEngine;
 use crate::pathfind::vehicles::VehiclePathfinder;
 use crate::pathfind::walking::SidewalkPathfinder;
@@ -31,6 +33,9 @@ pub struct Pathfinder {
     // TODO VecMap is probably fast enough. RoutingParams is annoying to implement Hash.
     #[serde(skip_serializing, skip_deserializing)]
     cached_alternatives: ThreadLocal<RefCell<VecMap<(PathConstraints, RoutingParams), Pathfinder>>>,
+    // Planning tools ask for this repeatedly, with the same few departure times
+    #[serde(skip_serializing, skip_deserializing)]
+    cached_frequency_transit: ThreadLocal<RefCell<VecMap<Time, Arc<FrequencyBasedTransit>>>>,
 }
 
 /// When pathfinding with different `RoutingParams` is done, a temporary pathfinder must be
@@ -58,6 +63,7 @@ impl Clone for Pathfinder {
             walking_with_transit_graph: self.walking_with_transit_graph.clone(),
             params: self.params.clone(),
             cached_alternatives: ThreadLocal::new(),
+            cached_frequency_transit: ThreadLocal::new(),
         }
     }
 }
@@ -75,6 +81,7 @@ impl Pathfinder {
             walking_with_transit_graph: SidewalkPathfinder::empty(),
             params: RoutingParams::default(),
             cached_alternatives: ThreadLocal::new(),
+            cached_frequency_transit: ThreadLocal::new(),
         }
     }
 
@@ -115,7 +122,7 @@ impl Pathfinder {
         timer.stop("prepare pathfinding for trains");
 
         timer.start("prepare pathfinding for pedestrians");
-        let walking_graph = SidewalkPathfinder::new(map, None, engine);
+        let walking_graph = SidewalkPathfinder::new(map, None, &params, engine);
         timer.stop("prepare pathfinding for pedestrians");
 
         // Transit routes haven't been created yet, so defer this step
@@ -131,6 +138,7 @@ impl Pathfinder {
 
             params,
             cached_alternatives: ThreadLocal::new(),
+            cached_frequency_transit: ThreadLocal::new(),
         }
     }
 
@@ -169,7 +177,7 @@ impl Pathfinder {
             timer.start(format!("prepare pathfinding for just {:?}", constraints));
             match constraints {
                 PathConstraints::Pedestrian => {
-                    p.walking_graph = SidewalkPathfinder::new(map, None, &engine);
+                    p.walking_graph = SidewalkPathfinder::new(map, None, &params, &engine);
                 }
                 PathConstraints::Car => {
                     p.car_graph = VehiclePathfinder::new(map, constraints, &params, &engine);
@@ -191,8 +199,12 @@ impl Pathfinder {
     }
 
     pub(crate) fn finalize_transit(&mut self, map: &Map, engine: &CreateEngine) {
-        self.walking_with_transit_graph =
-            SidewalkPathfinder::new(map, Some((&self.bus_graph, &self.train_graph)), engine);
+        self.walking_with_transit_graph = SidewalkPathfinder::new(
+            map,
+            Some((&self.bus_graph, &self.train_graph)),
+            &self.params,
+            engine,
+        );
     }
 
     /// Finds a path from a start to an end for a certain type of agent.
@@ -297,6 +309,23 @@ impl Pathfinder {
             .should_use_transit(map, start, end)
     }
 
+    /// Transit schedules summarized for planning tools, departing around some time
+    pub(crate) fn frequency_based_transit(
+        &self,
+        map: &Map,
+        departure: Time,
+    ) -> Arc<FrequencyBasedTransit> {
+        let cache = self
+            .cached_frequency_transit
+            .get_or(|| RefCell::new(VecMap::new()));
+        if let Some(transit) = cache.borrow().get(&departure) {
+            return transit.clone();
+        }
+        let transit = Arc::new(FrequencyBasedTransit::new(map, departure));
+        cache.borrow_mut().push(departure, transit.clone());
+        transit
+    }
+
     pub(crate) fn apply_edits(&mut self, map: &Map, timer: &mut Timer) {
         timer.start("apply edits to car pathfinding");
         self.car_graph.apply_edits(map);
@@ -322,6 +351,17 @@ impl Pathfinder {
         self.walking_with_transit_graph
             .apply_edits(map, Some((&self.bus_graph, &self.train_graph)));
         timer.stop("apply edits to pedestrian using transit pathfinding");
+
+        // Stops may have closed, and riding between them may be slower now
+        self.cached_frequency_transit = ThreadLocal::new();
+    }
+
+    /// Only bikes care about the wind
+    pub(crate) fn apply_wind(&mut self, map: &Map, timer: &mut Timer) {
+        timer.start("apply wind to bike pathfinding");
+        self.bike_graph.apply_edits(map);
+        timer.stop("apply wind to bike pathfinding");
+        self.cached_alternatives = ThreadLocal::new();
     }
 }
 
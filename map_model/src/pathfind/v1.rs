@@ -312,6 +312,22 @@ impl Path {
         // TODO Maybe need to amend uber_turns?
     }
 
+    /// Keeps the current step, but replaces everything after it with the rest of `other`, which
+    /// must start on the same lane and end at the same place. Used to reroute a vehicle partway
+    /// through its trip.
+    pub fn reroute(&mut self, other: Path, map: &Map) {
+        assert!(self.currently_inside_ut.is_none());
+        assert_eq!(self.steps[0], other.steps[0]);
+        self.steps.truncate(1);
+        self.steps.extend(other.steps.into_iter().skip(1));
+        self.uber_turns = other.uber_turns;
+
+        self.total_length = self.crossed_so_far;
+        for step in &self.steps {
+            self.total_length += self.dist_crossed_from_step(map, step);
+        }
+    }
+
     pub fn is_upcoming_uber_turn_component(&self, t: TurnID) -> bool {
         self.uber_turns
             .front()
@@ -1,11 +1,11 @@
 //! Pathfinding for cars, bikes, buses, and trains using contraction hierarchies
 
-use std::collections::HashMap;
+use std::collections::{BinaryHeap, HashMap};
 
 use fast_paths::InputGraph;
 use serde::{Deserialize, Serialize};
 
-use abstutil::MultiMap;
+use abstutil::{MultiMap, PriorityQueueItem};
 use geom::Duration;
 
 use crate::pathfind::engine::{CreateEngine, PathfindEngine};
@@ -15,7 +15,7 @@ use crate::pathfind::zone_cost;
 use crate::pathfind::{round, unround};
 use crate::{
     osm, DirectedRoadID, Direction, LaneType, Map, MovementID, PathConstraints, PathRequest,
-    PathV2, Position, RoutingParams, Traversable,
+    PathV2, Position, RoutingParams, Traversable, TurnType,
 };
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -291,6 +291,73 @@ fn make_input_graph(
     input_graph
 }
 
+/// Runs Dijkstra's directly over `vehicle_cost`, without building a graph first. A
+/// `VehiclePathfinder` is much faster once built, but this suits params that change constantly,
+/// like live road delays. Uber-turns aren't considered. The first movement must be reachable from
+/// the starting lane, so a vehicle already on it can follow the path.
+pub fn pathfind_by_cost(req: PathRequest, params: &RoutingParams, map: &Map) -> Option<PathV2> {
+    let constraints = req.constraints;
+    let start = map.get_l(req.start.lane()).get_directed_parent();
+    let first_movements: Vec<DirectedRoadID> = map
+        .get_turns_from_lane(req.start.lane())
+        .into_iter()
+        .map(|t| map.get_l(t.id.dst).get_directed_parent())
+        .collect();
+    let end = map.get_l(req.end.lane()).get_directed_parent();
+
+    let mut queue: BinaryHeap<PriorityQueueItem<Duration, DirectedRoadID>> = BinaryHeap::new();
+    queue.push(PriorityQueueItem {
+        cost: Duration::ZERO,
+        value: start,
+    });
+    let mut best_cost: HashMap<DirectedRoadID, Duration> = HashMap::new();
+    best_cost.insert(start, Duration::ZERO);
+    let mut backrefs: HashMap<DirectedRoadID, DirectedRoadID> = HashMap::new();
+
+    while let Some(current) = queue.pop() {
+        if current.value == end {
+            let mut roads = vec![end];
+            while let Some(prev) = backrefs.get(roads.last().unwrap()) {
+                roads.push(*prev);
+            }
+            roads.reverse();
+            return Some(PathV2::from_roads(
+                roads,
+                req,
+                current.cost,
+                Vec::new(),
+                map,
+            ));
+        }
+        // Skip stale entries
+        if best_cost[&current.value] < current.cost {
+            continue;
+        }
+
+        for mvmnt in map.get_movements_for(current.value, constraints) {
+            if !params.only_use_roads.is_empty() && !params.only_use_roads.contains(&mvmnt.to.road)
+            {
+                continue;
+            }
+            if current.value == start && !first_movements.contains(&mvmnt.to) {
+                continue;
+            }
+            if let Some(cost) = vehicle_cost(mvmnt.from, mvmnt, constraints, params, map) {
+                let cost = current.cost + cost;
+                if best_cost.get(&mvmnt.to).map(|x| cost < *x).unwrap_or(true) {
+                    best_cost.insert(mvmnt.to, cost);
+                    backrefs.insert(mvmnt.to, current.value);
+                    queue.push(PriorityQueueItem {
+                        cost,
+                        value: mvmnt.to,
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
 /// This returns the pathfinding cost of crossing one road and turn, in units of time. It factors
 /// in the ideal time to cross the space and penalties for entering an access-restricted zone,
 /// taking an unprotected turn, or going up a steep hill for some vehicle types. If this returns
@@ -311,6 +378,20 @@ pub fn vehicle_cost(
     }
 
     let road = map.get_r(dr.road);
+    if params.respect_modal_filters {
+        if let Some(ref filter) = road.modal_filter {
+            if !filter.filter_type.allows(constraints) {
+                return None;
+            }
+        }
+        if let Some(ref filter) = map.get_i(mvmnt.parent).modal_filter {
+            if !filter.filter_type.allows(constraints)
+                && !filter.allows_turn(mvmnt.from.road, mvmnt.to.road)
+            {
+                return None;
+            }
+        }
+    }
     let movement = &map.get_i(mvmnt.parent).movements[&mvmnt];
     let max_speed = match constraints {
         PathConstraints::Car | PathConstraints::Bus | PathConstraints::Train => None,
@@ -372,11 +453,49 @@ pub fn vehicle_cost(
         multiplier *= params.avoid_high_stress;
     }
 
+    if constraints == PathConstraints::Bike
+        && (params.traffic_stress_penalty - 1.0).abs() > f64::EPSILON
+    {
+        if let Some(stress) = road.traffic_stress_for_bikes(map, dr.dir) {
+            multiplier *= params
+                .traffic_stress_penalty
+                .powi(stress.level() as i32 - 1);
+        }
+    }
+
     let mut extra = zone_cost(mvmnt, constraints, map);
     // Penalize unprotected turns at a stop sign from smaller to larger roads.
     if map.is_unprotected_turn(dr.road, mvmnt.to.road, movement.turn_type) {
         extra += params.unprotected_turn_penalty
     }
+    if movement.turn_type == TurnType::UTurn {
+        extra += params.u_turn_penalty;
+    }
+    if let Some(delay) = params.road_delays.get(&dr) {
+        extra += *delay;
+    }
+    if constraints == PathConstraints::Car {
+        if let Some(hov) = road.hov_only(dr.dir) {
+            if !hov.qualifies(params.occupancy) {
+                match hov.toll {
+                    Some(toll) => {
+                        extra += toll * params.toll_penalty;
+                    }
+                    None => {
+                        return None;
+                    }
+                }
+            }
+        }
+        if let Some(charge) = map.get_congestion_charge() {
+            if charge.charges_entering(dr.road, mvmnt.to.road) {
+                let toll = params
+                    .congestion_charge
+                    .unwrap_or_else(|| charge.peak_toll());
+                extra += toll * params.toll_penalty;
+            }
+        }
+    }
 
     if (params.main_road_penalty - 1.0).abs() > f64::EPSILON
         && road.get_rank() != osm::RoadRank::Local
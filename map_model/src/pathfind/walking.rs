@@ -14,7 +14,7 @@ use crate::pathfind::zone_cost;
 use crate::pathfind::{round, unround};
 use crate::{
     DirectedRoadID, IntersectionID, Map, PathConstraints, PathRequest, PathStep, PathStepV2,
-    PathV2, Position, TransitRoute, TransitRouteID, TransitStopID, TurnType,
+    PathV2, Position, RoutingParams, TransitRoute, TransitRouteID, TransitStopID, TurnType,
 };
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -22,6 +22,7 @@ pub struct SidewalkPathfinder {
     #[serde(deserialize_with = "deserialize_nodemap")]
     nodes: NodeMap<WalkingNode>,
     use_transit: bool,
+    params: RoutingParams,
     engine: PathfindEngine,
 }
 
@@ -59,6 +60,7 @@ impl SidewalkPathfinder {
         SidewalkPathfinder {
             nodes: NodeMap::new(),
             use_transit: false,
+            params: RoutingParams::default(),
             engine: PathfindEngine::Empty,
         }
     }
@@ -66,6 +68,7 @@ impl SidewalkPathfinder {
     pub fn new(
         map: &Map,
         use_transit: Option<(&VehiclePathfinder, &VehiclePathfinder)>,
+        params: &RoutingParams,
         engine: &CreateEngine,
     ) -> SidewalkPathfinder {
         let mut nodes = NodeMap::new();
@@ -89,12 +92,13 @@ impl SidewalkPathfinder {
             }
         }
 
-        let input_graph = make_input_graph(&nodes, use_transit, map);
+        let input_graph = make_input_graph(&nodes, use_transit, params, map);
         let engine = engine.create(input_graph);
 
         SidewalkPathfinder {
             nodes,
             use_transit: use_transit.is_some(),
+            params: params.clone(),
             engine,
         }
     }
@@ -108,7 +112,7 @@ impl SidewalkPathfinder {
             return;
         }
 
-        let input_graph = make_input_graph(&self.nodes, use_transit, map);
+        let input_graph = make_input_graph(&self.nodes, use_transit, &self.params, map);
         let engine = self.engine.reuse_ordering().create(input_graph);
         self.engine = engine;
     }
@@ -241,7 +245,7 @@ impl SidewalkPathfinder {
             self.engine.all_costs_from(start)
         } else {
             // The CH engine doesn't support this!
-            let input_graph = make_input_graph(&self.nodes, None, map);
+            let input_graph = make_input_graph(&self.nodes, None, &self.params, map);
             CreateEngine::Dijkstra
                 .create(input_graph)
                 .all_costs_from(start)
@@ -264,6 +268,7 @@ impl SidewalkPathfinder {
 fn make_input_graph(
     nodes: &NodeMap<WalkingNode>,
     use_transit: Option<(&VehiclePathfinder, &VehiclePathfinder)>,
+    params: &RoutingParams,
     map: &Map,
 ) -> InputGraph {
     let max_speed = Some(crate::MAX_WALKING_SPEED);
@@ -289,6 +294,8 @@ fn make_input_graph(
                 if l.is_shoulder() {
                     cost = 2.0 * cost;
                 }
+                let sun = 1.0 - params.sidewalk_shade.get(&l.id).cloned().unwrap_or(0.0);
+                cost = (1.0 + (params.sun_exposure_penalty - 1.0) * sun) * cost;
                 input_graph.add_edge(pair.0, pair.1, round(cost));
             }
         }
@@ -340,6 +347,10 @@ fn transit_input_graph(
     let max_speed = Some(crate::MAX_WALKING_SPEED);
     // Connect stops with both sidewalk endpoints, using the appropriate distance.
     for stop in map.all_transit_stops().values() {
+        // Riders can pass through a closed stop, but not get on or off there
+        if stop.closed {
+            continue;
+        }
         let ride_transit = nodes.get(WalkingNode::RideTransit(stop.id));
         let lane = map.get_l(stop.sidewalk_pos.lane());
         for (endpt, step) in [
@@ -0,0 +1,160 @@
+//! Shadows cast by buildings and street trees, for studying how comfortable it is to walk on hot
+//! days. The sun's position is calculated from the date and time of day; the time is treated as
+//! local solar time, so noon is when the sun is highest, ignoring time zones and daylight saving.
+
+use std::collections::BTreeMap;
+
+use geom::{Angle, Circle, Distance, LonLat, Polygon, Pt2D, QuadTree, Time};
+
+use crate::{ExtraPOIType, LaneID, Map};
+
+/// Around the summer solstice in the northern hemisphere, when shade matters most
+pub const SUMMER_DAY_OF_YEAR: usize = 172;
+
+// Sidewalks are checked for shade at points this far apart
+const SAMPLE_SPACING: Distance = Distance::const_meters(5.0);
+// When the sun is very low, shadows get absurdly long. Nearly everything's in shade by then
+// anyway.
+const MAX_SHADOW_LENGTH: Distance = Distance::const_meters(200.0);
+
+/// Where the sun is in the sky
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SunPosition {
+    /// The direction towards the sun, in map space
+    pub direction: Angle,
+    /// Degrees above the horizon. Negative at night.
+    pub elevation_degrees: f64,
+}
+
+impl SunPosition {
+    /// `day_of_year` starts at 1 for January 1st.
+    pub fn new(gps: LonLat, day_of_year: usize, time: Time) -> SunPosition {
+        let latitude = gps.y().to_radians();
+        let declination = (23.44_f64.to_radians())
+            * (2.0 * std::f64::consts::PI * (284.0 + day_of_year as f64) / 365.0).sin();
+        let solar_hours = time.inner_seconds() / 3600.0 % 24.0;
+        let hour_angle = (15.0 * (solar_hours - 12.0)).to_radians();
+
+        let sin_elevation = latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos();
+        let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+
+        // Clockwise from north
+        let cos_azimuth = (declination.sin() - elevation.sin() * latitude.sin())
+            / (elevation.cos() * latitude.cos());
+        let mut azimuth = cos_azimuth.clamp(-1.0, 1.0).acos().to_degrees();
+        if hour_angle > 0.0 {
+            azimuth = 360.0 - azimuth;
+        }
+
+        SunPosition {
+            // In map space, 0 degrees points east and Y points south
+            direction: Angle::degrees(azimuth - 90.0),
+            elevation_degrees: elevation.to_degrees(),
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.elevation_degrees > 0.0
+    }
+
+    /// How far the shadow of something this tall reaches
+    fn shadow_length(&self, height: Distance) -> Distance {
+        let length = height / self.elevation_degrees.to_radians().tan();
+        if length > MAX_SHADOW_LENGTH {
+            MAX_SHADOW_LENGTH
+        } else {
+            length
+        }
+    }
+}
+
+impl Map {
+    /// Where the sun is over the middle of the map
+    pub fn sun_position(&self, day_of_year: usize, time: Time) -> SunPosition {
+        SunPosition::new(
+            self.get_boundary_polygon()
+                .center()
+                .to_gps(self.get_gps_bounds()),
+            day_of_year,
+            time,
+        )
+    }
+
+    /// The shadows of every building and street tree. Empty when the sun is down.
+    pub fn shadows(&self, sun: &SunPosition) -> Vec<Polygon> {
+        let mut shadows = Vec::new();
+        if !sun.is_up() {
+            return shadows;
+        }
+        let away = sun.direction.opposite();
+
+        for b in self.all_buildings() {
+            let offset = Pt2D::new(0.0, 0.0).project_away(sun.shadow_length(b.height), away);
+            let moved = b.polygon.translate(offset.x(), offset.y());
+            // Sweep the footprint along the shadow. The hull overestimates shadows of concave
+            // buildings a little.
+            if let Ok(shadow) = Polygon::convex_hull(vec![b.polygon.clone(), moved]) {
+                shadows.push(shadow);
+            }
+        }
+
+        for poi in self.all_extra_pois() {
+            if let ExtraPOIType::Tree {
+                height,
+                crown_diameter,
+            } = poi.kind
+            {
+                // The crown is roughly a ball at the top of the tree
+                let center = poi
+                    .pt
+                    .project_away(sun.shadow_length(height - crown_diameter / 2.0), away);
+                shadows.push(Circle::new(center, crown_diameter / 2.0).to_polygon());
+            }
+        }
+
+        shadows
+    }
+
+    /// For every sidewalk and shoulder, the fraction of its length in shade, from 0 to 1. At
+    /// night, everything is shaded.
+    pub fn sidewalk_shade(&self, sun: &SunPosition) -> BTreeMap<LaneID, f64> {
+        let shadows = self.shadows(sun);
+        let quadtree = QuadTree::bulk_load(
+            shadows
+                .iter()
+                .enumerate()
+                .map(|(idx, p)| p.get_bounds().as_bbox(idx))
+                .collect(),
+        );
+
+        let mut result = BTreeMap::new();
+        for l in self.all_lanes() {
+            if !l.is_walkable() {
+                continue;
+            }
+            if !sun.is_up() {
+                result.insert(l.id, 1.0);
+                continue;
+            }
+            let num_samples = (l.length() / SAMPLE_SPACING).ceil().max(1.0) as usize;
+            let mut shaded = 0;
+            for idx in 0..num_samples {
+                let dist = l.length() * ((idx as f64 + 0.5) / (num_samples as f64));
+                let pt = match l.lane_center_pts.dist_along(dist) {
+                    Ok((pt, _)) => pt,
+                    Err(_) => continue,
+                };
+                if quadtree
+                    .query_bbox(Circle::new(pt, Distance::meters(0.1)).get_bounds())
+                    .into_iter()
+                    .any(|idx| shadows[idx].contains_pt(pt))
+                {
+                    shaded += 1;
+                }
+            }
+            result.insert(l.id, (shaded as f64) / (num_samples as f64));
+        }
+        result
+    }
+}
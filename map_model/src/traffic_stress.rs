@@ -0,0 +1,159 @@
+//! Level of Traffic Stress (LTS) for cycling, roughly following Mekuria, Furth and Nixon. Each
+//! direction of a road gets a score from 1 (comfortable for children) to 4 (only for the strong
+//! and fearless), based on the speed limit, how many lanes of traffic there are, and whether the
+//! cycle lane is separated from traffic or squeezed next to parked cars.
+
+use serde::{Deserialize, Serialize};
+
+use geom::Speed;
+
+use crate::{osm, BufferType, Direction, LaneType, Map, PathConstraints, Road};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TrafficStress {
+    /// Physically separated from traffic, or very quiet streets
+    LTS1,
+    /// Most adults will cycle here
+    LTS2,
+    /// Only confident cyclists
+    LTS3,
+    /// Mixing with fast or heavy traffic
+    LTS4,
+}
+
+impl TrafficStress {
+    pub fn all() -> Vec<TrafficStress> {
+        vec![
+            TrafficStress::LTS1,
+            TrafficStress::LTS2,
+            TrafficStress::LTS3,
+            TrafficStress::LTS4,
+        ]
+    }
+
+    /// From 1 to 4
+    pub fn level(self) -> usize {
+        match self {
+            TrafficStress::LTS1 => 1,
+            TrafficStress::LTS2 => 2,
+            TrafficStress::LTS3 => 3,
+            TrafficStress::LTS4 => 4,
+        }
+    }
+
+    fn from_level(level: usize) -> TrafficStress {
+        match level {
+            0 | 1 => TrafficStress::LTS1,
+            2 => TrafficStress::LTS2,
+            3 => TrafficStress::LTS3,
+            _ => TrafficStress::LTS4,
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            TrafficStress::LTS1 => "comfortable for everyone",
+            TrafficStress::LTS2 => "comfortable for most adults",
+            TrafficStress::LTS3 => "only for confident cyclists",
+            TrafficStress::LTS4 => "only for the strong and fearless",
+        }
+    }
+}
+
+impl Road {
+    /// How stressful cycling along one direction of this road is. None if bikes can't use it.
+    pub fn traffic_stress_for_bikes(&self, map: &Map, dir: Direction) -> Option<TrafficStress> {
+        if !self
+            .lanes
+            .iter()
+            .any(|l| PathConstraints::Bike.can_use(l, map))
+        {
+            return None;
+        }
+
+        let mut traffic_lanes = 0;
+        for l in &self.lanes {
+            if l.dir == dir && matches!(l.lane_type, LaneType::Driving | LaneType::Bus) {
+                traffic_lanes += 1;
+            }
+        }
+        // Paths and cycleways without any traffic
+        if self
+            .lanes
+            .iter()
+            .all(|l| !l.lane_type.is_for_moving_vehicles() || l.is_biking())
+        {
+            return Some(TrafficStress::LTS1);
+        }
+
+        let speed = self.speed_limit;
+        let idx = match self
+            .lanes
+            .iter()
+            .position(|l| l.dir == dir && l.lane_type == LaneType::Biking)
+        {
+            Some(idx) => idx,
+            None => {
+                return Some(mixed_traffic_stress(
+                    speed,
+                    traffic_lanes,
+                    self.get_rank() == osm::RoadRank::Local,
+                ));
+            }
+        };
+
+        let neighbors: Vec<LaneType> = [idx.checked_sub(1), Some(idx + 1)]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| self.lanes.get(i))
+            .map(|l| l.lane_type)
+            .collect();
+        if neighbors
+            .iter()
+            .any(|lt| matches!(lt, LaneType::Buffer(buffer) if *buffer != BufferType::Stripes))
+        {
+            return Some(TrafficStress::LTS1);
+        }
+
+        // A painted cycle lane
+        let mut level = if speed <= Speed::miles_per_hour(25.0) {
+            1
+        } else if speed <= Speed::miles_per_hour(35.0) {
+            2
+        } else {
+            3
+        };
+        // Car doors open into the cycle lane
+        if neighbors.contains(&LaneType::Parking) {
+            level += 1;
+        }
+        if traffic_lanes > 1 {
+            level += 1;
+        }
+        Some(TrafficStress::from_level(level))
+    }
+}
+
+/// Sharing the lane with cars
+fn mixed_traffic_stress(speed: Speed, traffic_lanes: usize, local: bool) -> TrafficStress {
+    let level = if speed <= Speed::miles_per_hour(25.0) {
+        if traffic_lanes > 1 {
+            3
+        } else if local {
+            1
+        } else {
+            2
+        }
+    } else if speed <= Speed::miles_per_hour(30.0) {
+        if traffic_lanes > 1 {
+            4
+        } else if local {
+            2
+        } else {
+            3
+        }
+    } else {
+        4
+    };
+    TrafficStress::from_level(level)
+}
@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt;
 
 use serde::{Deserialize, Serialize};
@@ -211,7 +213,13 @@ impl Traversable {
 
         let base = if constraints == PathConstraints::Bike {
             // We assume every bike has a max_speed defined.
-            bike_speed_on_incline(max_speed_on_flat_ground.unwrap(), percent_incline)
+            bike_speed_on_incline(
+                max_speed_on_flat_ground.unwrap(),
+                percent_incline,
+                map.get_wind()
+                    .map(|wind| wind.headwind(dr, map))
+                    .unwrap_or(0.0),
+            )
         } else if constraints == PathConstraints::Pedestrian {
             // We assume every pedestrian has a max_speed defined.
             walking_speed_on_incline(max_speed_on_flat_ground.unwrap(), percent_incline)
@@ -255,7 +263,59 @@ pub const MAX_BIKE_SPEED: Speed = Speed::const_meters_per_second(4.4704);
 // 3 mph
 pub const MAX_WALKING_SPEED: Speed = Speed::const_meters_per_second(1.34112);
 
-fn bike_speed_on_incline(max_speed: Speed, percent_incline: f64) -> Speed {
+/// Electric bikes stop assisting above 25km/h, so nobody rides faster than this on the flat.
+pub const MAX_EBIKE_SPEED: Speed = Speed::const_meters_per_second(6.944);
+
+// The physical model of a rider and their bike. These are roughly a commuter sitting upright on a
+// city bike.
+const RIDER_AND_BIKE_KG: f64 = 80.0;
+const ROLLING_RESISTANCE: f64 = 0.007;
+// Drag coefficient times frontal area, in square meters
+const DRAG_AREA: f64 = 0.45;
+// Kilograms per cubic meter
+const AIR_DENSITY: f64 = 1.2;
+const GRAVITY: f64 = 9.81;
+// People work harder climbing than cruising on the flat
+const CLIMBING_EFFORT: f64 = 2.5;
+// How much an electric motor helps
+const EBIKE_ASSIST_WATTS: f64 = 250.0;
+// When it gets too steep, people get off and push
+const PUSHING_SPEED: Speed = Speed::const_meters_per_second(1.0);
+
+// Nobody rides more than this many times faster downhill or with the wind behind them, than on
+// the flat in still air. This is the top of Valhalla's grade table.
+const MAX_DOWNHILL_FACTOR: f64 = 2.2;
+
+thread_local! {
+    // Keyed by (electric, percent incline, headwind), with the floats as bits
+    static PHYSICAL_SPEED_FACTORS: RefCell<HashMap<(bool, u64, u64), f64>> =
+        RefCell::new(HashMap::new());
+}
+
+/// How fast somebody who cruises at `max_speed` on flat ground in still air can ride along some
+/// incline into a headwind (in meters per second; negative for a tailwind).
+///
+/// Without wind, regular bikes use a table of speed factors per grade. When there's wind, or for
+/// anybody faster than MAX_BIKE_SPEED (who's on an electric bike), a physical model balances the
+/// rider's power against gravity, rolling resistance, and drag. That's only needed once per
+/// incline and headwind, so these factors are cached.
+fn bike_speed_on_incline(max_speed: Speed, percent_incline: f64, headwind: f64) -> Speed {
+    if headwind == 0.0 && max_speed <= MAX_BIKE_SPEED {
+        return graded_bike_speed_factor(percent_incline) * max_speed;
+    }
+
+    let electric = max_speed > MAX_BIKE_SPEED;
+    let key = (electric, percent_incline.to_bits(), headwind.to_bits());
+    let factor = PHYSICAL_SPEED_FACTORS.with(|cache| {
+        *cache
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| physical_bike_speed_factor(electric, percent_incline, headwind))
+    });
+    (factor * max_speed).max(PUSHING_SPEED.min(max_speed))
+}
+
+fn graded_bike_speed_factor(percent_incline: f64) -> f64 {
     // There doesn't seem to be a straightforward way of calculating how an "average" cyclist's
     // speed is affected by hills. http://www.kreuzotter.de/english/espeed.htm has lots of detail,
     // but we'd need to guess values like body size, type of bike, etc.
@@ -269,11 +329,9 @@ fn bike_speed_on_incline(max_speed: Speed, percent_incline: f64) -> Speed {
     // use calculates things.  This table comes from
     // https://github.com/valhalla/valhalla/blob/f899a940ccbd0bc986769197dec5bb9383014afb/src/sif/bicyclecost.cc#L139.
     // Valhalla is MIT licensed: https://github.com/valhalla/valhalla/blob/master/COPYING.
-
-    // TODO Could binary search or do something a bit faster here, but doesn't matter much
     let pct = percent_incline * 100.0;
-    for (grade, factor) in vec![
-        (-10.0, 2.2),
+    for (grade, factor) in [
+        (-10.0, MAX_DOWNHILL_FACTOR),
         (-8.0, 2.0),
         (-6.5, 1.9),
         (-5.0, 1.7),
@@ -290,12 +348,70 @@ fn bike_speed_on_incline(max_speed: Speed, percent_incline: f64) -> Speed {
         (13.0, 0.4),
     ] {
         if pct <= grade {
-            return factor * max_speed;
+            return factor;
         }
     }
     // The last pair is a factor of 0.3 for grades of 15%, but we'll use it for anything steeper
     // than 15%
-    0.3 * max_speed
+    0.3
+}
+
+/// The speed a rider on a regular or electric bike can sustain, relative to their top speed on the
+/// flat in still air. The rider's power output is worked out from that top speed; electric bikes
+/// add the motor's. See http://www.kreuzotter.de/english/espeed.htm for the physics.
+fn physical_bike_speed_factor(electric: bool, percent_incline: f64, headwind: f64) -> f64 {
+    let flat_speed = if electric {
+        MAX_EBIKE_SPEED
+    } else {
+        MAX_BIKE_SPEED
+    };
+    let mut human_watts = power_needed(MAX_BIKE_SPEED, 0.0, 0.0);
+    if percent_incline > 0.0 {
+        human_watts *= CLIMBING_EFFORT;
+    }
+    let watts = if electric {
+        human_watts + EBIKE_ASSIST_WATTS
+    } else {
+        human_watts
+    };
+
+    // Riders who can keep up their usual speed do so, and only go faster when gravity or the wind
+    // push them along without pedaling
+    let (target_watts, min_factor) = if power_needed(flat_speed, percent_incline, headwind) <= watts
+    {
+        (0.0, 1.0)
+    } else {
+        (watts, 0.0)
+    };
+
+    // Past the start, the power needed increases with speed, so narrow in on the speed using all
+    // of it
+    let mut low = 0.0;
+    let mut high = MAX_DOWNHILL_FACTOR * flat_speed.inner_meters_per_second();
+    if power_needed(Speed::meters_per_second(high), percent_incline, headwind) < target_watts {
+        return MAX_DOWNHILL_FACTOR;
+    }
+    for _ in 0..30 {
+        let mid = (low + high) / 2.0;
+        if power_needed(Speed::meters_per_second(mid), percent_incline, headwind) < target_watts {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low / flat_speed.inner_meters_per_second()).max(min_factor)
+}
+
+/// How many watts it takes to ride at some speed
+fn power_needed(speed: Speed, percent_incline: f64, headwind: f64) -> f64 {
+    let v = speed.inner_meters_per_second();
+    let angle = percent_incline.atan();
+    let weight = RIDER_AND_BIKE_KG * GRAVITY;
+    let air_speed = v + headwind;
+    let force = weight * angle.sin()
+        + weight * angle.cos() * ROLLING_RESISTANCE
+        + 0.5 * AIR_DENSITY * DRAG_AREA * air_speed * air_speed.abs();
+    v * force
 }
 
 fn walking_speed_on_incline(max_speed: Speed, percent_incline: f64) -> Speed {
@@ -325,15 +441,29 @@ mod tests {
         let base_speed = MAX_BIKE_SPEED;
         assert_approx_eq(
             Speed::miles_per_hour(10.0),
-            bike_speed_on_incline(base_speed, 0.0),
+            bike_speed_on_incline(base_speed, 0.0, 0.0),
         );
         assert_approx_eq(
             Speed::miles_per_hour(22.0),
-            bike_speed_on_incline(base_speed, -0.15),
+            bike_speed_on_incline(base_speed, -0.15, 0.0),
         );
         assert_approx_eq(
             Speed::miles_per_hour(3.0),
-            bike_speed_on_incline(base_speed, 0.15),
+            bike_speed_on_incline(base_speed, 0.15, 0.0),
+        );
+        assert_approx_eq(
+            Speed::miles_per_hour(5.37),
+            bike_speed_on_incline(base_speed, 0.0, 5.0),
+        );
+        // An electric bike barely notices a moderate hill
+        assert_approx_eq(
+            Speed::miles_per_hour(15.5),
+            bike_speed_on_incline(MAX_EBIKE_SPEED, 0.03, 0.0),
+        );
+        // Going down a steep hill is always fast
+        assert_approx_eq(
+            2.2 * MAX_EBIKE_SPEED,
+            bike_speed_on_incline(MAX_EBIKE_SPEED, -0.15, 0.0),
         );
     }
 
@@ -0,0 +1,55 @@
+//! Weather that affects how fast people can travel. For now, just a steady wind over the whole
+//! map, which slows down cyclists riding into it.
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::Timer;
+use geom::{Angle, Speed};
+
+use crate::{DirectedRoadID, Direction, Map, Pathfinder};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Wind {
+    pub speed: Speed,
+    /// The direction the wind blows towards, in map space
+    pub towards: Angle,
+}
+
+impl Wind {
+    /// How fast the wind blows against somebody travelling along a road, in meters per second.
+    /// Negative for a tailwind.
+    pub fn headwind(&self, dr: DirectedRoadID, map: &Map) -> f64 {
+        let pl = &map.get_r(dr.road).center_pts;
+        let (from, to) = if dr.dir == Direction::Fwd {
+            (pl.first_pt(), pl.last_pt())
+        } else {
+            (pl.last_pt(), pl.first_pt())
+        };
+        if from == to {
+            return 0.0;
+        }
+        let travel = from.angle_to(to);
+        -self.speed.inner_meters_per_second()
+            * (travel.normalized_radians() - self.towards.normalized_radians()).cos()
+    }
+}
+
+impl Map {
+    /// Cyclists cross the map slower into the wind, and route around it. Changing the wind
+    /// rebuilds the bike pathfinder, which is slow on big maps.
+    pub fn set_wind(&mut self, wind: Option<Wind>, timer: &mut Timer) {
+        if self.wind == wind {
+            return;
+        }
+        self.wind = wind;
+
+        self.take_lazy_pathfinder();
+        let mut pathfinder = std::mem::replace(&mut self.pathfinder, Pathfinder::empty());
+        pathfinder.apply_wind(self, timer);
+        self.pathfinder = pathfinder;
+    }
+
+    pub fn get_wind(&self) -> Option<Wind> {
+        self.wind
+    }
+}
@@ -3,7 +3,7 @@ use rand_xorshift::XorShiftRng;
 
 use geom::{Duration, Time};
 
-use crate::{Activity, CensusPerson, Config, PersonType, Schedule};
+use crate::{Activity, CensusPerson, Config, PersonType, Schedule, Tour};
 
 impl CensusPerson {
     pub fn generate_schedule(&self, _config: &Config, rng: &mut XorShiftRng) -> Schedule {
@@ -14,9 +14,10 @@ impl CensusPerson {
             PersonType::Worker
         };
 
-        // Fill out a list of activities and how long the person should do the activity before
-        // travelling to the next place.
+        // Fill out a list of tours. Each is a list of activities and how long the person should
+        // do the activity before travelling to the next place.
         let mut plan = Vec::new();
+        let mut tours = Vec::new();
         let start_time;
 
         match person_type {
@@ -41,6 +42,7 @@ impl CensusPerson {
                 }
                 // The last duration doesn't matter
                 plan.push((Activity::Home, hours(8)));
+                tours.push(plan);
             }
             PersonType::Worker => {
                 start_time = rand_time(rng, hours(6), hours(9));
@@ -53,25 +55,47 @@ impl CensusPerson {
                     rand_duration(rng, minutes(20), minutes(40)),
                 ));
                 plan.push((Activity::Work, hours(4)));
+                // Shopping on the way home
                 if rng.gen_bool(0.8) {
                     plan.push((Activity::Errands, rand_duration(rng, minutes(15), hours(1))));
                 }
-                // The last duration doesn't matter
-                plan.push((Activity::Home, hours(8)));
+                if rng.gen_bool(0.3) {
+                    // Head out again in the evening
+                    plan.push((Activity::Home, rand_duration(rng, hours(1), hours(2))));
+                    tours.push(plan);
+                    let evening = if rng.gen_bool(0.5) {
+                        Activity::Dinner
+                    } else {
+                        Activity::Entertainment
+                    };
+                    tours.push(vec![
+                        (evening, rand_duration(rng, hours(1), hours(3))),
+                        (Activity::Home, hours(8)),
+                    ]);
+                } else {
+                    // The last duration doesn't matter
+                    plan.push((Activity::Home, hours(8)));
+                    tours.push(plan);
+                }
             }
         }
 
-        let mut schedule = Vec::new();
         let mut now = start_time;
-        for (activity, duration) in plan {
-            schedule.push((now, activity));
-            // TODO We have to add in commute time here, but at this stage in the pipeline, we have
-            // no idea...
-            now += rand_duration(rng, Duration::minutes(30), Duration::hours(1));
-            now += duration;
-        }
         Schedule {
-            activities: schedule,
+            tours: tours
+                .into_iter()
+                .map(|plan| {
+                    let mut stops = Vec::new();
+                    for (activity, duration) in plan {
+                        stops.push((now, activity));
+                        // TODO We have to add in commute time here, but at this stage in the
+                        // pipeline, we have no idea...
+                        now += rand_duration(rng, Duration::minutes(30), Duration::hours(1));
+                        now += duration;
+                    }
+                    Tour { stops }
+                })
+                .collect(),
         }
     }
 }
@@ -13,20 +13,35 @@ pub fn assign_people_to_houses(
     rng: &mut XorShiftRng,
     _config: &Config,
 ) -> Vec<CensusPerson> {
-    let mut people = Vec::new();
+    let mut homes = Vec::new();
     for area in areas {
-        for (home, n) in distribute_population_to_homes(area.polygon, area.population, map, rng) {
-            for _ in 0..n {
-                people.push(CensusPerson {
-                    home,
-                    // TODO Making this up for now. We can either move this to Config or see if we
-                    // can extract it from the census. Also, not even sure which of these
-                    // attributes are useful later in the pipeline.
-                    age: rng.gen_range(5..95),
-                    employed: rng.gen_bool(0.7),
-                    owns_car: rng.gen_bool(0.5),
-                });
-            }
+        homes.extend(distribute_population_to_homes(
+            area.polygon,
+            area.population,
+            map,
+            rng,
+        ));
+    }
+    people_in_homes(homes, rng)
+}
+
+/// Create people living in each building.
+pub fn people_in_homes(
+    homes: Vec<(BuildingID, usize)>,
+    rng: &mut XorShiftRng,
+) -> Vec<CensusPerson> {
+    let mut people = Vec::new();
+    for (home, n) in homes {
+        for _ in 0..n {
+            people.push(CensusPerson {
+                home,
+                // TODO Making this up for now. We can either move this to Config or see if we
+                // can extract it from the census. Also, not even sure which of these
+                // attributes are useful later in the pipeline.
+                age: rng.gen_range(5..95),
+                employed: rng.gen_bool(0.7),
+                owns_car: rng.gen_bool(0.5),
+            });
         }
     }
     people
@@ -14,8 +14,8 @@
 //!    specific building on the map as their home, and assigning specific attributes based on the
 //!    census data's distribution.
 //! 3) For each CensusPerson, classify them into a PersonType, then generate a Schedule of
-//!    different Activities throughout the day.
-//! 4) Pick specific buildings to visit to satisfy the Schedule.
+//!    different Activities throughout the day, grouped into tours that start and end at home.
+//! 4) Pick specific buildings to visit to satisfy the Schedule, and a mode for each tour.
 
 #[macro_use]
 extern crate anyhow;
@@ -27,7 +27,7 @@ use rand_xorshift::XorShiftRng;
 use abstutil::Timer;
 use geom::{Distance, Time};
 use map_model::{BuildingID, Map};
-use synthpop::Scenario;
+use synthpop::{Scenario, TripPurpose};
 
 pub use self::distribute_people::distribute_population_to_homes;
 
@@ -63,10 +63,17 @@ pub enum PersonType {
     Worker,
 }
 
-/// A single person's daily schedule. It's assumed that someone always starts at home. And for most
-/// people, the last entry should probably be Activity::Home.
+/// A single person's daily schedule. It's assumed that someone always starts at home.
 pub struct Schedule {
-    pub activities: Vec<(Time, Activity)>,
+    pub tours: Vec<Tour>,
+}
+
+/// A chain of activities that leaves home and comes back, like home-work-shop-home. One vehicle is
+/// used for the whole tour, so a car driven to work gets driven home again. Activities repeated
+/// within a tour, like work before and after lunch, happen at the same place.
+pub struct Tour {
+    /// When to leave for each activity. The last one should be Activity::Home.
+    pub stops: Vec<(Time, Activity)>,
 }
 
 /// Different things people might do in the day. Maybe it's more clear to call this a
@@ -85,6 +92,21 @@ pub enum Activity {
     Work,
 }
 
+impl Activity {
+    pub fn trip_purpose(self) -> TripPurpose {
+        match self {
+            Activity::Breakfast | Activity::Lunch | Activity::Dinner => TripPurpose::Meal,
+            Activity::School => TripPurpose::School,
+            Activity::Entertainment => TripPurpose::Recreation,
+            Activity::Errands => TripPurpose::Shopping,
+            Activity::Financial => TripPurpose::PersonalBusiness,
+            Activity::Healthcare => TripPurpose::Medical,
+            Activity::Home => TripPurpose::Home,
+            Activity::Work => TripPurpose::Work,
+        }
+    }
+}
+
 /// Any arbitrarily chosen parameters needed should be put here, so they can be controlled from the
 /// UI or tuned for different cities.
 pub struct Config {
@@ -118,11 +140,36 @@ pub fn generate_scenario(
     let people = distribute_people::assign_people_to_houses(areas, map, rng, &config);
     timer.stop("assigning people to houses");
 
+    make_scenario(scenario_name, people, config, map, rng, &mut timer)
+}
+
+/// Like `generate_scenario`, but starting from how many people live in each building, worked out
+/// from some other source of population data.
+pub fn generate_scenario_for_homes(
+    scenario_name: &str,
+    homes: Vec<(BuildingID, usize)>,
+    config: Config,
+    map: &Map,
+    rng: &mut XorShiftRng,
+) -> Scenario {
+    let mut timer = Timer::new("building scenario");
+    let people = distribute_people::people_in_homes(homes, rng);
+    make_scenario(scenario_name, people, config, map, rng, &mut timer)
+}
+
+fn make_scenario(
+    scenario_name: &str,
+    people: Vec<CensusPerson>,
+    config: Config,
+    map: &Map,
+    rng: &mut XorShiftRng,
+    timer: &mut Timer,
+) -> Scenario {
     let mut scenario = Scenario::empty(map, scenario_name);
     timer.start("building people");
-    scenario.people.extend(make_person::make_people(
-        people, map, &mut timer, rng, &config,
-    ));
+    scenario
+        .people
+        .extend(make_person::make_people(people, map, timer, rng, &config));
     timer.stop("building people");
 
     timer.start("removing weird schedules");
@@ -5,10 +5,14 @@ use rand::Rng;
 use rand_xorshift::XorShiftRng;
 
 use abstutil::Timer;
-use map_model::{BuildingID, IntersectionID, Map, PathConstraints, PathRequest};
+use geom::Distance;
+use map_model::{BuildingID, IntersectionID, LandUse, Map, PathConstraints, PathRequest};
 use synthpop::{IndividTrip, PersonSpec, TripEndpoint, TripMode, TripPurpose};
 
-use crate::{Activity, CensusPerson, Config};
+use crate::{Activity, CensusPerson, Config, Tour};
+
+/// When picking somewhere to eat or shop, out of this many random candidates, go to the closest.
+const NEARBY_CANDIDATES: usize = 5;
 
 pub fn make_people(
     people: Vec<CensusPerson>,
@@ -135,9 +139,36 @@ impl PersonFactory {
             (Activity::Work, vec!["bank", "clinic"]),
         ];
 
-        // Find all buildings with a matching amenity
+        // Official land-use data, when it was imported, is better than amenities for finding
+        // workplaces and shops
+        let land_uses = vec![
+            (
+                Activity::Work,
+                vec![
+                    LandUse::Commercial,
+                    LandUse::Retail,
+                    LandUse::Industrial,
+                    LandUse::Institutional,
+                    LandUse::MixedUse,
+                ],
+            ),
+            (Activity::Errands, vec![LandUse::Retail, LandUse::MixedUse]),
+            (Activity::School, vec![LandUse::Institutional]),
+        ];
+
+        // Find all buildings with a matching amenity or land use
         let mut candidates: HashMap<Activity, Vec<BuildingID>> = HashMap::new();
         for b in map.all_buildings() {
+            if let Some(land_use) = b.land_use {
+                for (activity, categories) in &land_uses {
+                    if categories.contains(&land_use) {
+                        candidates
+                            .entry(*activity)
+                            .or_insert_with(Vec::new)
+                            .push(b.id);
+                    }
+                }
+            }
             for (activity, categories) in &categories {
                 for amenity in &b.amenities {
                     if categories.contains(&amenity.amenity_type.as_str()) {
@@ -155,20 +186,61 @@ impl PersonFactory {
     fn find_building_for_activity(
         &self,
         activity: Activity,
-        _start: TripEndpoint,
-        _map: &Map,
+        start: TripEndpoint,
+        map: &Map,
         rng: &mut XorShiftRng,
     ) -> Option<BuildingID> {
+        let buildings = self.activity_to_buildings.get(&activity)?;
+        // People commute far for work and school, so just pick a random one
+        if activity == Activity::Work || activity == Activity::School {
+            return buildings.choose(rng).cloned();
+        }
+
         // TODO If there are several choices of building that satisfy an activity, which one will
-        // someone choose? One simple approach could just calculate the difficulty of going from the
-        // previous location (starting from home) to that place, using some mode of travel. Then
-        // either pick the closest choice, or even better, randomize, but weight based on
-        // the cost of getting there. map.pathfind() may be helpful.
+        // someone choose? We could calculate the difficulty of going from the previous location
+        // to each place using some mode of travel, and weight based on the cost. For now, grab
+        // lunch or run errands near wherever the tour currently is, as the crow flies.
+        let pt = start.pt(map);
+        buildings
+            .choose_multiple(rng, NEARBY_CANDIDATES)
+            .min_by_key(|b| map.get_b(**b).polygon.center().dist_to(pt))
+            .cloned()
+    }
 
-        // For now, just pick a random one
-        self.activity_to_buildings
-            .get(&activity)
-            .and_then(|buildings| buildings.choose(rng).cloned())
+    /// Where each stop of the tour happens, or None if the tour can't happen at all. Work and
+    /// school are the same place all day.
+    fn find_tour_destinations(
+        &self,
+        tour: &Tour,
+        home: TripEndpoint,
+        anchors: &mut HashMap<Activity, TripEndpoint>,
+        map: &Map,
+        commuter_borders: &[IntersectionID],
+        rng: &mut XorShiftRng,
+    ) -> Option<Vec<TripEndpoint>> {
+        let mut destinations = Vec::new();
+        let mut current_location = home;
+        for (_, activity) in &tour.stops {
+            let goto = if *activity == Activity::Home {
+                home
+            } else if let Some(goto) = anchors.get(activity) {
+                *goto
+            } else if let Some(destination) =
+                self.find_building_for_activity(*activity, current_location, map, rng)
+            {
+                TripEndpoint::Building(destination)
+            } else {
+                // No buildings satisfy the activity. Just go somewhere off-map. If the map is
+                // broken without borders, don't crash, just skip the tour.
+                TripEndpoint::Border(*commuter_borders.choose(rng)?)
+            };
+            if *activity == Activity::Work || *activity == Activity::School {
+                anchors.insert(*activity, goto);
+            }
+            destinations.push(goto);
+            current_location = goto;
+        }
+        Some(destinations)
     }
 
     pub fn make_person(
@@ -186,40 +258,80 @@ impl PersonFactory {
             trips: Vec::new(),
         };
 
-        let mut current_location = TripEndpoint::Building(person.home);
-        for (departure_time, activity) in schedule.activities {
-            // TODO This field isn't that important; later we could map Activity to a TripPurpose
-            // better.
-            let purpose = TripPurpose::Shopping;
-
-            let goto = if let Some(destination) =
-                self.find_building_for_activity(activity, current_location, map, rng)
+        let home = TripEndpoint::Building(person.home);
+        let mut anchors = HashMap::new();
+        for tour in schedule.tours {
+            let destinations = if let Some(list) =
+                self.find_tour_destinations(&tour, home, &mut anchors, map, commuter_borders, rng)
             {
-                TripEndpoint::Building(destination)
-            } else if let Some(i) = commuter_borders.choose(rng) {
-                // No buildings satisfy the activity. Just go somewhere off-map.
-                TripEndpoint::Border(*i)
+                list
             } else {
-                // Broken map without borders. Don't crash, just skip the person
                 continue;
             };
 
-            let mode = pick_mode(current_location, goto, map, rng, config);
-            output.trips.push(IndividTrip::new(
-                departure_time,
-                purpose,
-                current_location,
-                goto,
-                mode,
-            ));
+            // Decide how to make the tour based on the trip to work or school, or otherwise the
+            // first stop
+            let primary = tour
+                .stops
+                .iter()
+                .position(|(_, activity)| {
+                    *activity == Activity::Work || *activity == Activity::School
+                })
+                .unwrap_or(0);
+            let tour_mode = pick_mode(home, destinations[primary], map, rng, config);
 
-            current_location = goto;
+            // Track any car or bike used, so it's brought back home
+            let mut vehicle_at = home;
+            let mut current_location = home;
+            for (idx, ((departure_time, activity), goto)) in
+                tour.stops.into_iter().zip(destinations.iter()).enumerate()
+            {
+                let goto = *goto;
+                let short_walk = walking_distance(current_location, goto, map)
+                    .map(|dist| dist < config.walk_for_distances_shorter_than)
+                    .unwrap_or(false);
+                let mode = if tour_mode != TripMode::Drive && tour_mode != TripMode::Bike {
+                    if short_walk {
+                        TripMode::Walk
+                    } else {
+                        tour_mode
+                    }
+                } else if vehicle_at != current_location {
+                    // Walking back to wherever the vehicle was left
+                    TripMode::Walk
+                } else if short_walk && destinations.get(idx + 1) == Some(&current_location) {
+                    // Like walking to lunch from work, leaving the car parked there
+                    TripMode::Walk
+                } else {
+                    vehicle_at = goto;
+                    tour_mode
+                };
+
+                output.trips.push(IndividTrip::new(
+                    departure_time,
+                    activity.trip_purpose(),
+                    current_location,
+                    goto,
+                    mode,
+                ));
+                current_location = goto;
+            }
         }
 
         output
     }
 }
 
+/// None if the endpoints aren't both buildings, or there's no path between them
+fn walking_distance(from: TripEndpoint, to: TripEndpoint, map: &Map) -> Option<Distance> {
+    if let (TripEndpoint::Building(b1), TripEndpoint::Building(b2)) = (from, to) {
+        let req = PathRequest::between_buildings(map, b1, b2, PathConstraints::Pedestrian)?;
+        map.pathfind(req).ok().map(|path| path.total_length())
+    } else {
+        None
+    }
+}
+
 fn pick_mode(
     from: TripEndpoint,
     to: TripEndpoint,
@@ -227,21 +339,11 @@ fn pick_mode(
     rng: &mut XorShiftRng,
     config: &Config,
 ) -> TripMode {
-    let (b1, b2) = match (from, to) {
-        (TripEndpoint::Building(b1), TripEndpoint::Building(b2)) => (b1, b2),
-        // TODO Always drive when going on or off-map?
-        _ => {
-            return TripMode::Drive;
-        }
-    };
-
     // Decide mode based on walking distance
-    let distance = if let Some(path) =
-        PathRequest::between_buildings(map, b1, b2, PathConstraints::Pedestrian)
-            .and_then(|req| map.pathfind(req).ok())
-    {
-        path.total_length()
+    let distance = if let Some(dist) = walking_distance(from, to, map) {
+        dist
     } else {
+        // TODO Always drive when going on or off-map?
         // If the buildings aren't connected, there was probably a bug importing the map. Just
         // fallback to driving. If the trip can't be started in the simulation, it'll show up as
         // cancelled with more details about the problem.
@@ -1,6 +1,7 @@
 //! This is a standalone pipeline for generating a Scenario, starting from origin-destination data
 //! (also called desire lines), which gives a count of commuters between two zones, breaking down
-//! by mode.
+//! by mode. A more general OD matrix of one-way trips, like the output of a travel demand model,
+//! can also be used.
 
 use std::collections::HashMap;
 
@@ -23,6 +24,18 @@ pub struct DesireLine {
     pub number_commuters: usize,
 }
 
+/// Some number of one-way trips from one zone to another (or the same zone) using some mode,
+/// departing sometime in a window. Unlike a DesireLine, nothing is assumed about a return trip.
+#[derive(Debug)]
+pub struct ZoneTrips {
+    pub origin_zone: String,
+    pub destination_zone: String,
+    pub mode: TripMode,
+    pub number_trips: usize,
+    pub depart_after: Time,
+    pub depart_before: Time,
+}
+
 // TODO Percentage of taking a lunch trip, when to do it, how far to venture out, what mode to
 // use...
 pub struct Options {
@@ -97,21 +110,8 @@ pub fn disaggregate(
         let home_zone = &zones[&desire.home_zone];
         let work_zone = &zones[&desire.work_zone];
 
-        // If both are remote, make sure the desire line intersects the map
-        if home_zone.is_remote() && work_zone.is_remote() {
-            if desire.home_zone == desire.work_zone {
-                continue;
-            }
-
-            if !map
-                .get_boundary_polygon()
-                .intersects_polyline(&PolyLine::must_new(vec![
-                    home_zone.center,
-                    work_zone.center,
-                ]))
-            {
-                continue;
-            }
+        if misses_map(map, home_zone, work_zone) {
+            continue;
         }
 
         for _ in 0..desire.number_commuters {
@@ -185,6 +185,92 @@ pub fn disaggregate(
     people
 }
 
+/// Generates a scenario from an origin-destination matrix. Each trip is taken by a different
+/// person, who doesn't return. Trips start and end at any home or workplace in the zone, or at a
+/// border, in the same way as `disaggregate`. Departure times are uniformly distributed over each
+/// window.
+pub fn disaggregate_trips(
+    map: &Map,
+    zones: HashMap<String, Polygon>,
+    matrix: Vec<ZoneTrips>,
+    include_zones: IncludeZonePolicy,
+    rng: &mut XorShiftRng,
+    timer: &mut Timer,
+) -> Vec<PersonSpec> {
+    let zones = create_zones(map, zones, include_zones, timer);
+
+    let mut people = Vec::new();
+    let mut skipped = 0;
+    timer.start_iter("create people per OD pair", matrix.len());
+    for entry in matrix {
+        timer.next();
+        let (origin, destination) = match (
+            zones.get(&entry.origin_zone),
+            zones.get(&entry.destination_zone),
+        ) {
+            (Some(o), Some(d)) => (o, d),
+            _ => {
+                skipped += entry.number_trips;
+                continue;
+            }
+        };
+        if misses_map(map, origin, destination) {
+            skipped += entry.number_trips;
+            continue;
+        }
+
+        for _ in 0..entry.number_trips {
+            if let (Some((from, _)), Some((_, to))) = (
+                origin.pick_any(entry.mode, map, rng),
+                destination.pick_any(entry.mode, map, rng),
+            ) {
+                if from == to {
+                    continue;
+                }
+                let departure = if entry.depart_before > entry.depart_after {
+                    entry.depart_after
+                        + Duration::seconds(rng.gen_range(
+                            0.0..(entry.depart_before - entry.depart_after).inner_seconds(),
+                        ))
+                } else {
+                    entry.depart_after
+                };
+                people.push(PersonSpec {
+                    orig_id: None,
+                    trips: vec![IndividTrip::new(
+                        departure,
+                        TripPurpose::Work,
+                        from,
+                        to,
+                        entry.mode,
+                    )],
+                });
+            }
+        }
+    }
+    info!(
+        "Created {} people. Skipped {} trips between zones that were filtered out or don't touch \
+         the map",
+        prettyprint_usize(people.len()),
+        prettyprint_usize(skipped)
+    );
+
+    people
+}
+
+/// If both zones are remote, trips between them only matter if the straight line between them
+/// crosses the map.
+fn misses_map(map: &Map, zone1: &Zone, zone2: &Zone) -> bool {
+    if !zone1.is_remote() || !zone2.is_remote() {
+        return false;
+    }
+    if zone1.center == zone2.center {
+        return true;
+    }
+    !map.get_boundary_polygon()
+        .intersects_polyline(&PolyLine::must_new(vec![zone1.center, zone2.center]))
+}
+
 struct Zone {
     polygon: Polygon,
     center: Pt2D,
@@ -349,6 +435,27 @@ impl Zone {
         self.pick_borders(mode, map, rng)
     }
 
+    /// Returns endpoints to (leave, goto), picking from both homes and workplaces.
+    fn pick_any(
+        &self,
+        mode: TripMode,
+        map: &Map,
+        rng: &mut XorShiftRng,
+    ) -> Option<(TripEndpoint, TripEndpoint)> {
+        if rng.gen_bool(self.pct_overlap) {
+            if let Ok((b, _)) = self
+                .homes
+                .iter()
+                .chain(self.workplaces.iter())
+                .collect::<Vec<_>>()
+                .choose_weighted(rng, |(_, n)| *n)
+            {
+                return Some((TripEndpoint::Building(*b), TripEndpoint::Building(*b)));
+            }
+        }
+        self.pick_borders(mode, map, rng)
+    }
+
     fn pick_borders(
         &self,
         mode: TripMode,
@@ -13,7 +13,7 @@ use abstutil::{
     deserialize_btreemap, deserialize_multimap, serialize_btreemap, serialize_multimap, MultiMap,
     Tags,
 };
-use geom::{Distance, PolyLine, Polygon, Pt2D};
+use geom::{Distance, Duration, PolyLine, Polygon, Pt2D, Time};
 
 pub use self::types::{Amenity, AmenityType, AreaType};
 
@@ -31,6 +31,7 @@ pub struct RawMap {
     pub areas: Vec<RawArea>,
     pub parking_lots: Vec<RawParkingLot>,
     pub parking_aisles: Vec<(osm::WayID, Vec<Pt2D>)>,
+    pub sites: Vec<RawSite>,
     pub transit_routes: Vec<RawTransitRoute>,
     pub census_zones: Vec<(Polygon, CensusZone)>,
     #[serde(
@@ -65,6 +66,21 @@ pub struct RawMap {
     )]
     pub elevation_per_intersection: BTreeMap<IntersectionID, Distance>,
     pub extra_pois: Vec<ExtraPOI>,
+    /// `red_turn:*` tags on nodes. These're interpreted by map_model, once it knows what roads
+    /// meet at each intersection.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub red_turn_tags: BTreeMap<osm::NodeID, Tags>,
+    pub ferry_routes: Vec<RawFerryRoute>,
+    /// Traffic signal timing from a source other than OSM, like a SUMO network. Intersections
+    /// without an entry get a generated signal.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub traffic_signal_programs: BTreeMap<osm::NodeID, RawSignalProgram>,
 }
 
 impl RawMap {
@@ -76,6 +92,7 @@ impl RawMap {
             areas: Vec::new(),
             parking_lots: Vec::new(),
             parking_aisles: Vec::new(),
+            sites: Vec::new(),
             transit_routes: Vec::new(),
             census_zones: Vec::new(),
             transit_stops: BTreeMap::new(),
@@ -84,6 +101,9 @@ impl RawMap {
             extra_road_data: BTreeMap::new(),
             elevation_per_intersection: BTreeMap::new(),
             extra_pois: Vec::new(),
+            red_turn_tags: BTreeMap::new(),
+            ferry_routes: Vec::new(),
+            traffic_signal_programs: BTreeMap::new(),
         }
     }
 
@@ -109,6 +129,70 @@ pub struct RawBuilding {
     pub public_garage_name: Option<String>,
     pub num_parking_spots: usize,
     pub amenities: Vec<Amenity>,
+    /// From official zoning or land-use data, if the importer was given any
+    pub land_use: Option<LandUse>,
+}
+
+/// A land-use category from municipal zoning or parcel data. Cities use all sorts of codes, so
+/// these are deliberately coarse.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LandUse {
+    Residential,
+    /// Homes above shops or offices
+    MixedUse,
+    /// Offices and services
+    Commercial,
+    Retail,
+    Industrial,
+    /// Schools, hospitals, government
+    Institutional,
+    /// Parks and other land without buildings people go to
+    OpenSpace,
+}
+
+impl LandUse {
+    /// Guess the category from a zoning code or description, like `R-2`, `NC3-40`, or
+    /// `Light Industrial`. Descriptive words are checked before the conventional code prefixes.
+    pub fn parse(raw: &str) -> Option<LandUse> {
+        let x = raw.trim().to_lowercase();
+        let keywords = [
+            ("mixed", LandUse::MixedUse),
+            ("resid", LandUse::Residential),
+            ("retail", LandUse::Retail),
+            ("shop", LandUse::Retail),
+            ("office", LandUse::Commercial),
+            ("commerc", LandUse::Commercial),
+            ("business", LandUse::Commercial),
+            ("industr", LandUse::Industrial),
+            ("manufactur", LandUse::Industrial),
+            ("institut", LandUse::Institutional),
+            ("school", LandUse::Institutional),
+            ("civic", LandUse::Institutional),
+            ("public", LandUse::Institutional),
+            ("park", LandUse::OpenSpace),
+            ("open space", LandUse::OpenSpace),
+            ("recreation", LandUse::OpenSpace),
+        ];
+        for (keyword, land_use) in keywords {
+            if x.contains(keyword) {
+                return Some(land_use);
+            }
+        }
+        if x.starts_with("mu") || x.starts_with("mx") {
+            return Some(LandUse::MixedUse);
+        }
+        // Neighborhood commercial zones usually allow housing above
+        if x.starts_with("nc") {
+            return Some(LandUse::MixedUse);
+        }
+        match x.chars().next()? {
+            'r' => Some(LandUse::Residential),
+            'c' | 'b' | 'o' => Some(LandUse::Commercial),
+            'm' | 'i' => Some(LandUse::Industrial),
+            'p' => Some(LandUse::OpenSpace),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -126,6 +210,16 @@ pub struct RawParkingLot {
     pub osm_tags: Tags,
 }
 
+/// A large site like a hospital, campus, or mall, with its own internal network of service roads
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RawSite {
+    pub osm_id: osm::OsmID,
+    pub polygon: Polygon,
+    pub osm_tags: Tags,
+    /// Service roads and parking aisles entirely inside the site
+    pub aisles: Vec<(osm::WayID, Vec<Pt2D>)>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RawTransitRoute {
     pub long_name: String,
@@ -136,7 +230,9 @@ pub struct RawTransitRoute {
     /// Entries into transit_stops
     pub stops: Vec<String>,
     pub route_type: RawTransitType,
-    // TODO Schedule
+    /// Sorted times for one typical weekday when a vehicle departs the first stop. If empty, the
+    /// schedule is unknown.
+    pub spawn_times: Vec<Time>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -153,6 +249,38 @@ pub struct RawTransitStop {
     pub name: String,
 }
 
+/// A fixed-time traffic signal. Movements are identified by the ways they go between, to survive
+/// roads being merged and split.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RawSignalProgram {
+    pub offset: Duration,
+    pub stages: Vec<RawSignalStage>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RawSignalStage {
+    pub duration: Duration,
+    /// (from way, to way)
+    pub protected: Vec<(osm::WayID, osm::WayID)>,
+    /// (from way, to way)
+    pub permitted: Vec<(osm::WayID, osm::WayID)>,
+}
+
+/// A ferry route scraped from OSM.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RawFerryRoute {
+    pub osm_id: osm::OsmID,
+    pub name: String,
+    /// Follows the water. This may begin and/or end outside the map boundary.
+    pub shape: PolyLine,
+    /// Where people board, in order along the shape. Only terminals within the map's boundary are
+    /// kept.
+    pub terminals: Vec<(String, Pt2D)>,
+    /// Sorted times for one typical weekday when a ferry departs the first terminal. If empty, the
+    /// schedule is unknown.
+    pub spawn_times: Vec<Time>,
+}
+
 /// Classifies pedestrian and cyclist crossings. Note lots of detail is missing.
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum CrossingType {
@@ -162,6 +290,28 @@ pub enum CrossingType {
     Unsignalized,
 }
 
+/// Classifies barrier nodes by what they let through.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BarrierType {
+    /// Bollards, planters, and the like. Only people walking and cycling can pass.
+    WalkCycleOnly,
+    /// Like `WalkCycleOnly`, but buses can pass too
+    BusGate,
+    /// A closed gate on a private road or driveway. Only people going somewhere behind it can
+    /// pass.
+    PrivateGate,
+}
+
+/// An off-road path imported for walking and cycling, from a `highway` tag osm2streets doesn't
+/// import as a road on its own
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TrailType {
+    /// `highway=path`
+    Path,
+    /// `highway=track`, often through farms and forests
+    Track,
+}
+
 /// Extra data associated with one Road
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExtraRoadData {
@@ -173,9 +323,11 @@ pub struct ExtraRoadData {
     // could be really hard. It might be better to split the road into two pieces to match the more
     // often used OSM style.
     /// Barrier nodes along this road's original center line.
-    pub barrier_nodes: Vec<Pt2D>,
+    pub barrier_nodes: Vec<(Pt2D, BarrierType)>,
     /// Crossing nodes along this road's original center line.
     pub crossing_nodes: Vec<(Pt2D, CrossingType)>,
+    /// Trails get their own lane layout: bike lanes and a path for walking
+    pub trail: Option<TrailType>,
 }
 
 impl ExtraRoadData {
@@ -187,6 +339,7 @@ impl ExtraRoadData {
             crosswalk_backward: true,
             barrier_nodes: Vec::new(),
             crossing_nodes: Vec::new(),
+            trail: None,
         }
     }
 }
@@ -202,4 +355,9 @@ pub struct ExtraPOI {
 pub enum ExtraPOIType {
     LondonUndergroundStation(String),
     NationalRailStation(String),
+    /// A street tree, which casts shade
+    Tree {
+        height: Distance,
+        crown_diameter: Distance,
+    },
 }
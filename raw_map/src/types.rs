@@ -16,7 +16,20 @@ pub struct Amenity {
 }
 
 /// Businesses are categorized into one of these types.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, EnumString, Display, EnumIter, Debug)]
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    EnumString,
+    Display,
+    EnumIter,
+    Debug,
+    Serialize,
+    Deserialize,
+)]
 pub enum AmenityType {
     Bank,
     Bar,
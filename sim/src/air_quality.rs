@@ -0,0 +1,140 @@
+//! A rough estimate of exposure to traffic pollution. Vehicles emit pollution along each road in
+//! proportion to the distance they drive, then it spreads out from the road following a Gaussian
+//! dispersion kernel. The result is an exposure index per building -- good for comparing proposals
+//! against each other, not for predicting absolute concentrations.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, FindClosest, Time};
+use map_model::{AmenityType, BuildingID, Map, RoadID};
+
+use crate::{AgentType, Analytics, FleetMix};
+
+// Emissions along a road are spread over points this far apart
+const SAMPLE_SPACING: Distance = Distance::const_meters(10.0);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmissionsModel {
+    /// Cars emit NOx depending on how fast they drive, like in the energy dashboard
+    pub fleet: FleetMix,
+    /// Grams of NOx each bus emits per kilometer. Trains are assumed to be electric, and nobody
+    /// else emits anything.
+    pub bus_grams_per_km: f64,
+    /// The standard deviation of the dispersion kernel. Nearly all pollution stays within 3 of
+    /// these from the road.
+    pub dispersion: Distance,
+}
+
+impl EmissionsModel {
+    /// The default fleet mix and a diesel bus, with no wind
+    pub fn default_model() -> EmissionsModel {
+        EmissionsModel {
+            fleet: FleetMix::default_mix(),
+            bus_grams_per_km: 6.0,
+            dispersion: Distance::meters(40.0),
+        }
+    }
+
+    /// Grams of NOx emitted along each road, counting vehicles that entered it up to the hour of
+    /// `now`.
+    pub fn road_emissions(
+        &self,
+        map: &Map,
+        analytics: &Analytics,
+        now: Time,
+    ) -> BTreeMap<RoadID, f64> {
+        let mut emissions: BTreeMap<RoadID, f64> = self
+            .fleet
+            .road_emissions(analytics, now)
+            .into_iter()
+            .map(|(r, e)| (r, e.nox_grams))
+            .collect();
+        for ((r, agent_type, hour), count) in &analytics.road_thruput.counts {
+            if *agent_type != AgentType::Bus || *hour > now.get_hours() {
+                continue;
+            }
+            let km = map.get_r(*r).length().inner_meters() / 1000.0;
+            *emissions.entry(*r).or_insert(0.0) += (*count as f64) * km * self.bus_grams_per_km;
+        }
+        emissions
+    }
+
+    /// Disperse road emissions onto buildings. Each building's exposure index is the sum of the
+    /// kernel from every sample point along nearby roads, in micrograms per square meter.
+    /// Buildings far from any emissions are omitted.
+    pub fn building_exposure(
+        &self,
+        map: &Map,
+        emissions: &BTreeMap<RoadID, f64>,
+    ) -> BTreeMap<BuildingID, f64> {
+        let mut closest = FindClosest::new();
+        for b in map.all_buildings() {
+            closest.add_polygon(b.id, &b.polygon);
+        }
+
+        let sigma = self.dispersion.inner_meters();
+        let normalize = 1.0 / (2.0 * std::f64::consts::PI * sigma * sigma);
+        let mut exposure = BTreeMap::new();
+        for (r, grams) in emissions {
+            let road = map.get_r(*r);
+            let num_samples = (road.length() / SAMPLE_SPACING).ceil().max(1.0);
+            let micrograms = 1e6 * grams / num_samples;
+            for idx in 0..(num_samples as usize) {
+                let dist = road.length() * ((idx as f64 + 0.5) / num_samples);
+                let pt = match road.center_pts.dist_along(dist) {
+                    Ok((pt, _)) => pt,
+                    Err(_) => continue,
+                };
+                for (b, _, d) in closest.all_close_pts(pt, self.dispersion * 3.0) {
+                    let d = d.inner_meters();
+                    *exposure.entry(b).or_insert(0.0) +=
+                        micrograms * normalize * (-d * d / (2.0 * sigma * sigma)).exp();
+                }
+            }
+        }
+        exposure
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExposureSummary {
+    /// The exposure index averaged over every resident
+    pub mean_per_resident: f64,
+    /// The exposure index averaged over buildings containing a school
+    pub mean_at_schools: f64,
+    pub num_schools: usize,
+}
+
+impl ExposureSummary {
+    pub fn new(map: &Map, exposure: &BTreeMap<BuildingID, f64>) -> ExposureSummary {
+        let mut residents = 0;
+        let mut resident_exposure = 0.0;
+        let mut num_schools = 0;
+        let mut school_exposure = 0.0;
+        for b in map.all_buildings() {
+            let value = exposure.get(&b.id).cloned().unwrap_or(0.0);
+            let n = b.bldg_type.num_residents();
+            residents += n;
+            resident_exposure += (n as f64) * value;
+            if b.has_amenity(AmenityType::School) {
+                num_schools += 1;
+                school_exposure += value;
+            }
+        }
+        ExposureSummary {
+            mean_per_resident: if residents == 0 {
+                0.0
+            } else {
+                resident_exposure / (residents as f64)
+            },
+            mean_at_schools: if num_schools == 0 {
+                0.0
+            } else {
+                school_exposure / (num_schools as f64)
+            },
+            num_schools,
+        }
+    }
+}
@@ -4,14 +4,25 @@ use std::fmt::Write;
 use serde::{Deserialize, Serialize};
 
 use abstutil::Counter;
-use geom::{Duration, Pt2D, Time};
+use geom::{Distance, Duration, Pt2D, Speed, Time};
 use map_model::{
-    CompressedMovementID, IntersectionID, LaneID, Map, MovementID, ParkingLotID, Path, PathRequest,
-    RoadID, TransitRouteID, TransitStopID, Traversable, TurnID,
+    BuildingID, CompressedMovementID, DirectedRoadID, IntersectionID, LaneID, Map, MovementID,
+    ParkingLotID, Path, PathRequest, RoadID, TransitRouteID, TransitStopID, Traversable, TurnID,
+    TurnType,
 };
 use synthpop::TripMode;
 
-use crate::{AgentID, AgentType, AlertLocation, CarID, Event, ParkingSpot, TripID, TripPhaseType};
+use crate::custom_metrics::CustomMetrics;
+use crate::{
+    AgentID, AgentType, AlertLocation, CarID, CustomMetric, CustomMetricResults, DrivingProfile,
+    DrivingTotals, Event, ParkingSpot, TripExertion, TripID, TripPhaseType,
+};
+
+// A cyclist and a vehicle turning across them passing through less than this far apart counts as
+// a hook conflict
+const HOOK_CONFLICT_WINDOW: Duration = Duration::const_seconds(2.0);
+/// `link_stats` groups traffic into time bins this long
+pub const LINK_STATS_BIN: Duration = Duration::const_seconds(900.0);
 
 /// As a simulation runs, different pieces emit Events. The Analytics object listens to these,
 /// organizing and storing some information from them. The UI queries Analytics to draw time-series
@@ -24,6 +35,9 @@ use crate::{AgentID, AgentType, AlertLocation, CarID, Event, ParkingSpot, TripID
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Analytics {
     pub road_thruput: TimeSeriesCount<RoadID>,
+    /// How many people move along each lane. Unlike road_thruput, a carpool counts everybody
+    /// inside, which matters for judging HOV lanes.
+    pub lane_person_thruput: TimeSeriesCount<LaneID>,
     pub intersection_thruput: TimeSeriesCount<IntersectionID>,
     // TODO For traffic signals, intersection_thruput could theoretically use this. But that
     // requires occasionally expensive or complicated summing or merging over all directions of an
@@ -58,12 +72,67 @@ pub struct Analytics {
     pub parking_lane_changes: BTreeMap<LaneID, Vec<(Time, bool)>>,
     pub parking_lot_changes: BTreeMap<ParkingLotID, Vec<(Time, bool)>>,
 
+    /// Tolls paid by vehicles that don't qualify for HOT lanes, or entering a congestion charge
+    /// cordon
+    pub tolls_paid: Vec<(Time, TripID, LaneID, f64)>,
+    /// Whenever a variable speed limit sign changes. None means the road's normal limit.
+    pub speed_limits_posted: Vec<(Time, DirectedRoadID, Option<Speed>)>,
+    /// Whenever a car stops at a pickup/dropoff zone, how many vehicles are queued behind it. A
+    /// long queue means the zone spills back onto the road.
+    pub pickup_dropoffs: Vec<(Time, BuildingID, usize)>,
+    /// Every time a ridehail vehicle stops, how far it drove, and whether it was carrying somebody
+    /// (true) or deadheading to a pickup (false)
+    pub ridehail_driving: Vec<(Time, CarID, Distance, bool)>,
+    /// Every time a delivery van stops to unload, how long it blocks the lane, how many vehicles
+    /// are queued behind it, and whether it double-parked (true) or used a loading zone (false)
+    pub delivery_stops: Vec<(Time, BuildingID, Duration, usize, bool)>,
+    /// When each emergency vehicle reached a building, and how long it took since being
+    /// dispatched. None means it couldn't get there at all.
+    pub emergency_responses: Vec<(Time, BuildingID, Option<Duration>)>,
+    /// Every time somebody takes a shared bike or e-scooter (true) or leaves one (false) outside
+    /// a building. The imbalance per building is how many vehicles would need rebalancing.
+    pub shared_vehicle_moves: Vec<(Time, BuildingID, bool)>,
+    /// Trips that wanted a shared vehicle, but found nothing close to where they started
+    pub no_shared_vehicle: Vec<(Time, BuildingID)>,
+    /// How far each trip moved by each mode, and how much it climbed. EnergyModel turns this into
+    /// fuel and calories.
+    pub trip_exertion: BTreeMap<TripID, TripExertion>,
+    /// How far each car trip drove, and how long it took. FleetMix turns this into emissions.
+    pub trip_driving: BTreeMap<TripID, DrivingProfile>,
+    /// Likewise for bike trips, to compare how fast people on e-bikes ride
+    pub trip_biking: BTreeMap<TripID, DrivingProfile>,
+    /// The lanes cars drove along each road, keyed by road and the hour they entered the lane
+    pub road_driving: BTreeMap<(RoadID, usize), DrivingTotals>,
+    /// How long buses and trains wait at each traffic signal they pass through
+    pub transit_signal_delays: Vec<(Time, CarID, IntersectionID, Duration)>,
+    /// Vehicle volumes and speeds along each road, keyed by road, vehicle type, and
+    /// `LINK_STATS_BIN` since midnight
+    pub link_stats: BTreeMap<(RoadID, AgentType, usize), LinkStats>,
+    /// Keyed by the name of each `CustomMetric`
+    pub custom_metric_results: BTreeMap<String, CustomMetricResults>,
+
     pub(crate) alerts: Vec<(Time, AlertLocation, String)>,
 
+    /// Per intersection and incoming road, the last cyclist going straight and the last vehicle
+    /// turning across the bike lane. Only needed to spot hook conflicts as they happen.
+    #[serde(skip_serializing, skip_deserializing)]
+    last_hook_movements: BTreeMap<(IntersectionID, RoadID), HookMovements>,
+    /// The lane each vehicle is currently on, and when it entered
+    #[serde(skip_serializing, skip_deserializing)]
+    vehicles_on_lanes: BTreeMap<CarID, (LaneID, Time)>,
+    #[serde(skip_serializing, skip_deserializing)]
+    custom_metrics: CustomMetrics,
+
     /// For benchmarking, we may want to disable collecting data.
     record_anything: bool,
 }
 
+#[derive(Clone, Default)]
+struct HookMovements {
+    bike: Option<(Time, TripID, TurnID)>,
+    vehicle: Option<(Time, TurnID)>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Problem {
     /// A vehicle waited >30s, or a pedestrian waited >15s.
@@ -72,10 +141,15 @@ pub enum Problem {
     ComplexIntersectionCrossing(IntersectionID),
     /// A pedestrian crossed an intersection with an Arterial street
     ArterialIntersectionCrossing(TurnID),
+    /// A pedestrian crossed a slip lane
+    SlipLaneCrossing(TurnID),
     /// Another vehicle wanted to over-take this cyclist somewhere on this lane or turn.
     OvertakeDesired(Traversable),
     /// Too many people are crossing the same sidewalk or crosswalk at the same time.
     PedestrianOvercrowding(Traversable),
+    /// A vehicle turned across this cyclist going straight through the turn, just before or
+    /// after them. This is a near miss with a "right hook" (or left hook, driving on the left).
+    HookConflict(TurnID),
 }
 
 impl Problem {
@@ -89,7 +163,9 @@ impl Problem {
             Problem::OvertakeDesired(on) | Problem::PedestrianOvercrowding(on) => {
                 on.get_polyline(map).middle()
             }
-            Problem::ArterialIntersectionCrossing(t) => map.get_t(*t).geom.middle(),
+            Problem::ArterialIntersectionCrossing(t)
+            | Problem::SlipLaneCrossing(t)
+            | Problem::HookConflict(t) => map.get_t(*t).geom.middle(),
         }
     }
 }
@@ -100,7 +176,9 @@ pub enum ProblemType {
     ComplexIntersectionCrossing,
     OvertakeDesired,
     ArterialIntersectionCrossing,
+    SlipLaneCrossing,
     PedestrianOvercrowding,
+    HookConflict,
 }
 
 impl From<&Problem> for ProblemType {
@@ -110,7 +188,9 @@ impl From<&Problem> for ProblemType {
             Problem::ComplexIntersectionCrossing(_) => Self::ComplexIntersectionCrossing,
             Problem::OvertakeDesired(_) => Self::OvertakeDesired,
             Problem::ArterialIntersectionCrossing(_) => Self::ArterialIntersectionCrossing,
+            Problem::SlipLaneCrossing(_) => Self::SlipLaneCrossing,
             Problem::PedestrianOvercrowding(_) => Self::PedestrianOvercrowding,
+            Problem::HookConflict(_) => Self::HookConflict,
         }
     }
 }
@@ -132,7 +212,9 @@ impl ProblemType {
             ProblemType::ComplexIntersectionCrossing,
             ProblemType::OvertakeDesired,
             ProblemType::ArterialIntersectionCrossing,
+            ProblemType::SlipLaneCrossing,
             ProblemType::PedestrianOvercrowding,
+            ProblemType::HookConflict,
         ]
     }
 
@@ -146,7 +228,9 @@ impl ProblemType {
             ProblemType::ArterialIntersectionCrossing => {
                 "where pedestrians cross arterial intersections"
             }
+            ProblemType::SlipLaneCrossing => "where pedestrians cross slip lanes",
             ProblemType::PedestrianOvercrowding => "where pedestrians are over-crowded",
+            ProblemType::HookConflict => "where cars turn across cyclists",
         }
     }
 }
@@ -155,6 +239,7 @@ impl Analytics {
     pub fn new(record_anything: bool) -> Analytics {
         Analytics {
             road_thruput: TimeSeriesCount::new(),
+            lane_person_thruput: TimeSeriesCount::new(),
             intersection_thruput: TimeSeriesCount::new(),
             traffic_signal_thruput: TimeSeriesCount::new(),
             demand: BTreeMap::new(),
@@ -168,7 +253,25 @@ impl Analytics {
             intersection_delays: BTreeMap::new(),
             parking_lane_changes: BTreeMap::new(),
             parking_lot_changes: BTreeMap::new(),
+            tolls_paid: Vec::new(),
+            speed_limits_posted: Vec::new(),
+            pickup_dropoffs: Vec::new(),
+            ridehail_driving: Vec::new(),
+            delivery_stops: Vec::new(),
+            emergency_responses: Vec::new(),
+            shared_vehicle_moves: Vec::new(),
+            no_shared_vehicle: Vec::new(),
+            trip_exertion: BTreeMap::new(),
+            trip_driving: BTreeMap::new(),
+            trip_biking: BTreeMap::new(),
+            road_driving: BTreeMap::new(),
+            transit_signal_delays: Vec::new(),
             alerts: Vec::new(),
+            link_stats: BTreeMap::new(),
+            last_hook_movements: BTreeMap::new(),
+            vehicles_on_lanes: BTreeMap::new(),
+            custom_metric_results: BTreeMap::new(),
+            custom_metrics: CustomMetrics::default(),
             record_anything,
         }
     }
@@ -178,14 +281,26 @@ impl Analytics {
             return;
         }
 
+        if !self.custom_metrics.metrics.is_empty() {
+            for metric in &mut self.custom_metrics.metrics {
+                metric.event(&ev, time, map);
+            }
+            self.update_custom_metric_results(time);
+        }
+
         // Throughput
-        if let Event::AgentEntersTraversable(a, _, to, passengers) = ev {
+        if let Event::AgentEntersTraversable(a, _, to, passengers, people) = ev {
             match to {
                 Traversable::Lane(l) => {
                     self.road_thruput.record(time, l.road, a.to_type(), 1);
                     if let Some(n) = passengers {
                         self.road_thruput
                             .record(time, l.road, AgentType::TransitRider, n);
+                        self.lane_person_thruput
+                            .record(time, l, AgentType::TransitRider, n);
+                    } else {
+                        self.lane_person_thruput
+                            .record(time, l, a.to_type(), people);
                     }
                 }
                 Traversable::Turn(t) => {
@@ -216,6 +331,40 @@ impl Analytics {
                 }
             };
         }
+        if let Event::AgentEntersTraversable(AgentID::Car(car), _, on, _, _) = ev {
+            self.record_link_stats(car, on, time, map);
+        }
+        if let Event::AgentEntersTraversable(a, Some(trip), on, _, _) = ev {
+            self.trip_exertion
+                .entry(trip)
+                .or_insert_with(TripExertion::new)
+                .record(a.to_type(), on, map);
+            if a.to_type() == AgentType::Car {
+                if let Some((r, dist, entered)) = self
+                    .trip_driving
+                    .entry(trip)
+                    .or_insert_with(DrivingProfile::new)
+                    .record(on, time, map)
+                {
+                    self.road_driving
+                        .entry((r, entered.get_hours()))
+                        .or_insert_with(DrivingTotals::new)
+                        .add(dist, time - entered);
+                }
+            }
+            if a.to_type() == AgentType::Bike {
+                self.trip_biking
+                    .entry(trip)
+                    .or_insert_with(DrivingProfile::new)
+                    .record(on, time, map);
+            }
+        }
+        if let Event::EBikeRideStarted(trip) = ev {
+            self.trip_exertion
+                .entry(trip)
+                .or_insert_with(TripExertion::new)
+                .ebike = true;
+        }
         match ev {
             Event::PersonLeavesMap(_, Some(a), i) => {
                 // Ignore cancelled trips
@@ -227,6 +376,39 @@ impl Analytics {
             _ => {}
         }
 
+        if let Event::TollPaid(trip, l, toll) = ev {
+            self.tolls_paid.push((time, trip, l, toll));
+        }
+        if let Event::SpeedLimitPosted(dr, speed) = ev {
+            self.speed_limits_posted.push((time, dr, speed));
+        }
+        if let Event::PickupDropoff(b, queued) = ev {
+            self.pickup_dropoffs.push((time, b, queued));
+        }
+        if let Event::RidehailDrove(car, dist, with_passenger) = ev {
+            self.ridehail_driving
+                .push((time, car, dist, with_passenger));
+        }
+        if let Event::DeliveryStop(b, blocked, queued, double_parked) = ev {
+            self.delivery_stops
+                .push((time, b, blocked, queued, double_parked));
+        }
+        if let Event::SharedVehicleTaken(b) = ev {
+            self.shared_vehicle_moves.push((time, b, true));
+        }
+        if let Event::SharedVehicleLeft(b) = ev {
+            self.shared_vehicle_moves.push((time, b, false));
+        }
+        if let Event::NoSharedVehicle(b) = ev {
+            self.no_shared_vehicle.push((time, b));
+        }
+        if let Event::EmergencyResponse(b, response_time) = ev {
+            self.emergency_responses.push((time, b, response_time));
+        }
+        if let Event::TransitSignalDelay(car, i, delay) = ev {
+            self.transit_signal_delays.push((time, car, i, delay));
+        }
+
         // Bus arrivals
         if let Event::BusArrivedAtStop(bus, route, stop) = ev {
             self.bus_arrivals.push((time, bus, route, stop));
@@ -320,7 +502,7 @@ impl Analytics {
         }
 
         // Safety metrics
-        if let Event::AgentEntersTraversable(a, Some(trip), Traversable::Turn(t), _) = ev {
+        if let Event::AgentEntersTraversable(a, Some(trip), Traversable::Turn(t), _, _) = ev {
             if a.to_type() == AgentType::Bike && map.get_i(t.parent).roads.len() > 4 {
                 // Defining a "large intersection" is tricky. If a road is split into two one-ways,
                 // should we count it as two roads? If we haven't consolidated some crazy
@@ -332,7 +514,7 @@ impl Analytics {
             }
         }
 
-        if let Event::AgentEntersTraversable(a, Some(trip), Traversable::Turn(t), _) = ev {
+        if let Event::AgentEntersTraversable(a, Some(trip), Traversable::Turn(t), _, _) = ev {
             let turn = map.get_t(t);
             if a.to_type() == AgentType::Pedestrian && turn.is_crossing_arterial_intersection(map) {
                 self.problems_per_trip
@@ -340,6 +522,50 @@ impl Analytics {
                     .or_insert_with(Vec::new)
                     .push((time, Problem::ArterialIntersectionCrossing(turn.id)));
             }
+            if a.to_type() == AgentType::Pedestrian && turn.is_crossing_slip_lane(map) {
+                self.problems_per_trip
+                    .entry(trip)
+                    .or_insert_with(Vec::new)
+                    .push((time, Problem::SlipLaneCrossing(turn.id)));
+            }
+        }
+
+        // A vehicle and cyclist using hooking turns close together in time is a surrogate for a
+        // crash, like a post-encroachment time under a threshold
+        if let Event::AgentEntersTraversable(a, maybe_trip, Traversable::Turn(t), _, _) = ev {
+            let turn = map.get_t(t);
+            let key = (t.parent, t.src.road);
+            match (a.to_type(), maybe_trip) {
+                (AgentType::Bike, Some(trip)) if turn.turn_type == TurnType::Straight => {
+                    let movements = self.last_hook_movements.entry(key).or_default();
+                    if let Some((t2, vehicle_turn)) = movements.vehicle {
+                        if time - t2 < HOOK_CONFLICT_WINDOW
+                            && map.get_t(vehicle_turn).hooks_across(turn, map)
+                        {
+                            self.problems_per_trip
+                                .entry(trip)
+                                .or_insert_with(Vec::new)
+                                .push((time, Problem::HookConflict(t)));
+                        }
+                    }
+                    movements.bike = Some((time, trip, t));
+                }
+                (AgentType::Car | AgentType::Bus, _) if turn.turn_type != TurnType::Straight => {
+                    let movements = self.last_hook_movements.entry(key).or_default();
+                    if let Some((t2, trip, bike_turn)) = movements.bike {
+                        if time - t2 < HOOK_CONFLICT_WINDOW
+                            && turn.hooks_across(map.get_t(bike_turn), map)
+                        {
+                            self.problems_per_trip
+                                .entry(trip)
+                                .or_insert_with(Vec::new)
+                                .push((time, Problem::HookConflict(bike_turn)));
+                        }
+                    }
+                    movements.vehicle = Some((time, t));
+                }
+                _ => {}
+            }
         }
 
         // TODO Kinda hacky, but these all consume the event, so kinda bundle em.
@@ -425,6 +651,114 @@ impl Analytics {
         results
     }
 
+    pub(crate) fn add_custom_metric(&mut self, metric: Box<dyn CustomMetric>) {
+        let name = metric.name();
+        if self.custom_metric_results.contains_key(&name) {
+            panic!("A custom metric called {} is already registered", name);
+        }
+        self.custom_metric_results.insert(
+            name,
+            CustomMetricResults {
+                hourly: Vec::new(),
+                latest: metric.values(),
+            },
+        );
+        self.custom_metrics.metrics.push(metric);
+    }
+
+    pub fn custom_metrics(&self) -> &Vec<Box<dyn CustomMetric>> {
+        &self.custom_metrics.metrics
+    }
+
+    // Asking every metric for its values after every event is too slow, so just copy them over
+    // once a minute, and at the end of every hour
+    fn update_custom_metric_results(&mut self, time: Time) {
+        let last = self.custom_metrics.last_update;
+        let new_hour = time.get_hours() != last.get_hours();
+        if !new_hour && time - last < Duration::minutes(1) {
+            return;
+        }
+        for metric in &self.custom_metrics.metrics {
+            let results = self.custom_metric_results.get_mut(&metric.name()).unwrap();
+            if new_hour {
+                // The latest values are from just before the hour ended
+                results.hourly.push((
+                    Time::START_OF_DAY + Duration::hours(last.get_hours() + 1),
+                    results.latest.clone(),
+                ));
+            }
+            results.latest = metric.values();
+        }
+        self.custom_metrics.last_update = time;
+    }
+
+    fn record_link_stats(&mut self, car: CarID, on: Traversable, time: Time, map: &Map) {
+        let bin = |t: Time| ((t - Time::START_OF_DAY) / LINK_STATS_BIN) as usize;
+        let agent_type = AgentID::Car(car).to_type();
+        match on {
+            Traversable::Turn(t) => {
+                // Only count vehicles driving the whole lane, not starting or stopping partway
+                if let Some((l, entered)) = self.vehicles_on_lanes.remove(&car) {
+                    if t.src == l {
+                        let lane = map.get_l(l);
+                        let mut free_flow_speed = map.get_r(l.road).speed_limit;
+                        if agent_type == AgentType::Bike {
+                            free_flow_speed = free_flow_speed.min(map_model::MAX_BIKE_SPEED);
+                        }
+                        let stats = self
+                            .link_stats
+                            .entry((l.road, agent_type, bin(entered)))
+                            .or_insert_with(LinkStats::new);
+                        stats.traversals += 1;
+                        stats.distance += lane.length();
+                        stats.travel_time += time - entered;
+                        stats.free_flow_time += lane.length() / free_flow_speed;
+                    }
+                }
+            }
+            Traversable::Lane(l) => {
+                self.link_stats
+                    .entry((l.road, agent_type, bin(time)))
+                    .or_insert_with(LinkStats::new)
+                    .volume += 1;
+                self.vehicles_on_lanes.insert(car, (l, time));
+            }
+        }
+    }
+
+    /// How far and fast people rode normal bikes and e-bikes, over the trips finishing
+    /// successfully by some time. Returns (normal bikes, e-bikes).
+    pub fn cycling_stats(&self, until: Time) -> (CyclingStats, CyclingStats) {
+        let none = CyclingStats {
+            trips: 0,
+            distance: Distance::ZERO,
+            riding_time: Duration::ZERO,
+        };
+        let mut bikes = none;
+        let mut ebikes = none;
+        for (t, id, _, maybe_dt) in &self.finished_trips {
+            if *t > until {
+                break;
+            }
+            if maybe_dt.is_none() {
+                continue;
+            }
+            let profile = match self.trip_biking.get(id) {
+                Some(profile) => profile,
+                None => continue,
+            };
+            let stats = if self.trip_exertion.get(id).map(|e| e.ebike).unwrap_or(false) {
+                &mut ebikes
+            } else {
+                &mut bikes
+            };
+            stats.trips += 1;
+            stats.distance += profile.totals.distance;
+            stats.riding_time += profile.totals.time;
+        }
+        (bikes, ebikes)
+    }
+
     /// If calling on prebaked Analytics, be careful to pass in an unedited map, to match how the
     /// simulation was originally run. Otherwise the paths may be nonsense.
     pub fn get_trip_phases(&self, trip: TripID, map: &Map) -> Vec<TripPhase> {
@@ -608,7 +942,9 @@ impl Analytics {
                             }
                         }
                     }
-                    Problem::ArterialIntersectionCrossing(t) => t.parent,
+                    Problem::ArterialIntersectionCrossing(t)
+                    | Problem::SlipLaneCrossing(t)
+                    | Problem::HookConflict(t) => t.parent,
                 };
                 if id == i {
                     raw_per_type
@@ -695,6 +1031,76 @@ impl Default for Analytics {
     }
 }
 
+/// The odometer of some group of bike trips. Like `DrivingProfile`, only whole lanes count.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CyclingStats {
+    pub trips: usize,
+    pub distance: Distance,
+    /// Includes waiting at intersections
+    pub riding_time: Duration,
+}
+
+impl CyclingStats {
+    pub fn average_speed(&self) -> Option<Speed> {
+        if self.riding_time == Duration::ZERO {
+            None
+        } else {
+            Some(Speed::meters_per_second(
+                self.distance.inner_meters() / self.riding_time.inner_seconds(),
+            ))
+        }
+    }
+}
+
+/// Vehicle traffic along one road during one time bin
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LinkStats {
+    /// Vehicles entering the road
+    pub volume: usize,
+    /// Vehicles driving the whole length of a lane. Speeds and delays only count these, grouped
+    /// by when they entered.
+    pub traversals: usize,
+    pub distance: Distance,
+    /// Includes waiting to turn at the end of the lane
+    pub travel_time: Duration,
+    /// How long the traversals would take at the speed limit, or the usual top speed for bikes
+    pub free_flow_time: Duration,
+}
+
+impl LinkStats {
+    fn new() -> LinkStats {
+        LinkStats {
+            volume: 0,
+            traversals: 0,
+            distance: Distance::ZERO,
+            travel_time: Duration::ZERO,
+            free_flow_time: Duration::ZERO,
+        }
+    }
+
+    pub fn average_speed(&self) -> Option<Speed> {
+        if self.travel_time == Duration::ZERO {
+            None
+        } else {
+            Some(Speed::meters_per_second(
+                self.distance.inner_meters() / self.travel_time.inner_seconds(),
+            ))
+        }
+    }
+
+    /// The average delay per traversal, compared to free-flow
+    pub fn average_delay(&self) -> Option<Duration> {
+        if self.traversals == 0 {
+            None
+        } else {
+            let delay = (self.travel_time - self.free_flow_time)
+                .inner_seconds()
+                .max(0.0);
+            Some(Duration::seconds(delay / (self.traversals as f64)))
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct TripPhase {
     pub start_time: Time,
@@ -852,6 +1258,7 @@ impl<X: Ord + Clone> TimeSeriesCount<X> {
 }
 
 /// A sliding window, used to count something over time
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SlidingWindow {
     times: VecDeque<Time>,
     window_size: Duration,
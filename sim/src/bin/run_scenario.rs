@@ -9,9 +9,30 @@ struct Args {
     /// Pressing Control+C will interrupt and savestate.
     #[structopt(long)]
     interruptible: bool,
+    /// Watch for gridlock, runaway queues, and people stuck for a long time, and savestate when
+    /// they first happen
+    #[structopt(long)]
+    watchdog: bool,
     /// How many hours to simulate.
     #[structopt(long)]
     hours: usize,
+    /// Save the simulation when it reaches these times, like 7:30. To resume later, pass the
+    /// savestate as the file to load. Use --checkpoint-every-minutes to save periodically.
+    #[structopt(long, parse(try_from_str = geom::Time::parse))]
+    checkpoint_at: Vec<geom::Time>,
+    /// Hash the simulation's state periodically and write the hashes to this JSON file. Compare
+    /// two of these files with `cli compare-determinism` to find where two runs diverged.
+    #[structopt(long)]
+    determinism_log: Option<String>,
+    /// How often to hash the simulation's state, for --determinism-log
+    #[structopt(long, default_value = "10")]
+    determinism_every_minutes: usize,
+    /// Also describe every event between this time and --determinism-detail-until, to find the
+    /// first event that differs between two runs
+    #[structopt(long, parse(try_from_str = geom::Time::parse))]
+    determinism_detail_from: Option<geom::Time>,
+    #[structopt(long, parse(try_from_str = geom::Time::parse))]
+    determinism_detail_until: Option<geom::Time>,
     #[structopt(flatten)]
     flags: sim::SimFlags,
 }
@@ -23,7 +44,22 @@ fn main() {
     let hours = geom::Duration::hours(args.hours);
     let (mut map, mut sim, _) = args
         .flags
-        .load_synchronously(&mut abstutil::Timer::new("setup"));
+        .load_synchronously(&mut abstutil::Timer::new("setup"))
+        .unwrap_or_else(|err| panic!("Can't set up the simulation: {}", err));
+    for t in args.checkpoint_at {
+        sim.schedule_checkpoint(t);
+    }
+    if args.determinism_log.is_some() {
+        let detail = match (args.determinism_detail_from, args.determinism_detail_until) {
+            (Some(from), Some(until)) => Some((from, until)),
+            (None, None) => None,
+            _ => panic!("Pass both --determinism-detail-from and --determinism-detail-until"),
+        };
+        sim.record_determinism(
+            geom::Duration::minutes(args.determinism_every_minutes),
+            detail,
+        );
+    }
 
     if args.interruptible {
         // Pressing ^C will savestate. This needs a more complex loop to check for the interrupt.
@@ -40,7 +76,7 @@ fn main() {
 
         let start = instant::Instant::now();
         let goal_time = geom::Time::START_OF_DAY + hours;
-        while running.load(Ordering::SeqCst) {
+        while running.load(Ordering::SeqCst) && sim.time() != goal_time {
             println!(
                 "After {}, the sim is at {}. {} live agents",
                 geom::Duration::realtime_elapsed(start),
@@ -53,14 +89,24 @@ fn main() {
                 geom::Duration::seconds(1.0),
                 &mut None,
             );
-            if sim.time() == goal_time {
-                return;
+        }
+        if sim.time() != goal_time {
+            println!("\n\nInterrupting at {}", sim.time());
+            sim.save();
+            for x in sim.describe_internal_stats() {
+                println!("{}", x);
             }
         }
-        println!("\n\nInterrupting at {}", sim.time());
-        sim.save();
-        for x in sim.describe_internal_stats() {
-            println!("{}", x);
+    } else if args.watchdog {
+        let anomalies = sim.timed_step_with_watchdog(
+            &map,
+            hours,
+            sim::Watchdog::new(),
+            &mut abstutil::Timer::new("run simulation"),
+        );
+        println!("{} anomalies detected", anomalies.len());
+        for (anomaly, path) in anomalies {
+            println!("- {}: see {}", anomaly, path);
         }
     } else {
         sim.timed_step(
@@ -70,4 +116,10 @@ fn main() {
             &mut abstutil::Timer::new("run simulation"),
         );
     }
+
+    if let Some(path) = args.determinism_log {
+        let log = sim.finish_determinism_log().unwrap();
+        println!("Wrote {} state hashes to {}", log.samples.len(), path);
+        abstio::write_json(path, &log);
+    }
 }
@@ -0,0 +1,126 @@
+//! An extension point for study-specific metrics, so they don't all have to be built into
+//! `Analytics`. Downstream code implements `CustomMetric`, registers it with
+//! `Sim::add_custom_metric`, and the metric sees every event. Its values are copied into
+//! `Analytics` as the sim runs, so they're saved with the rest of the results (including
+//! prebaked ones) and can be shown in a generic dashboard.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Polygon, Time};
+use map_model::{LaneID, Map, Traversable};
+
+use crate::{AgentType, Event};
+
+/// Accumulates something from simulation events. The sim may be shared across threads, so metrics
+/// must be too.
+pub trait CustomMetric: Send + Sync {
+    /// Must be unique among all metrics registered with one sim, and the same across runs, so
+    /// results can be compared against the baseline
+    fn name(&self) -> String;
+    fn event(&mut self, ev: &Event, time: Time, map: &Map);
+    /// Everything measured so far, as labelled values
+    fn values(&self) -> Vec<(String, f64)>;
+    /// `Analytics` is cloned sometimes, so metrics must be too
+    fn clone_box(&self) -> Box<dyn CustomMetric>;
+}
+
+/// What a custom metric measured, saved with the rest of `Analytics`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CustomMetricResults {
+    /// The values as of the end of each hour
+    pub hourly: Vec<(Time, Vec<(String, f64)>)>,
+    /// The values as of the last update, at most a minute of sim time ago
+    pub latest: Vec<(String, f64)>,
+}
+
+impl CustomMetricResults {
+    /// The value of one label as of some time, from the hourly values
+    pub fn value_at(&self, label: &str, time: Time) -> Option<f64> {
+        self.hourly
+            .iter()
+            .take_while(|(t, _)| *t <= time)
+            .last()
+            .and_then(|(_, values)| values.iter().find(|(l, _)| l == label))
+            .map(|(_, value)| *value)
+    }
+}
+
+/// The metrics registered with a sim. Not serialized; the results are.
+pub(crate) struct CustomMetrics {
+    pub metrics: Vec<Box<dyn CustomMetric>>,
+    pub last_update: Time,
+}
+
+impl Default for CustomMetrics {
+    fn default() -> CustomMetrics {
+        CustomMetrics {
+            metrics: Vec::new(),
+            last_update: Time::START_OF_DAY,
+        }
+    }
+}
+
+impl Clone for CustomMetrics {
+    fn clone(&self) -> CustomMetrics {
+        CustomMetrics {
+            metrics: self.metrics.iter().map(|m| m.clone_box()).collect(),
+            last_update: self.last_update,
+        }
+    }
+}
+
+/// An example metric: count everybody entering lanes inside a polygon, like a sensor covering
+/// part of the road
+#[derive(Clone)]
+pub struct SensorCount {
+    pub name: String,
+    lanes: BTreeSet<LaneID>,
+    counts: BTreeMap<AgentType, usize>,
+}
+
+impl SensorCount {
+    pub fn new(name: String, area: &Polygon, map: &Map) -> SensorCount {
+        let lanes = map
+            .all_lanes()
+            .filter(|l| area.contains_pt(l.lane_center_pts.middle()))
+            .map(|l| l.id)
+            .collect();
+        SensorCount {
+            name,
+            lanes,
+            counts: BTreeMap::new(),
+        }
+    }
+}
+
+impl CustomMetric for SensorCount {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn event(&mut self, ev: &Event, _: Time, _: &Map) {
+        if let Event::AgentEntersTraversable(a, _, Traversable::Lane(l), _, _) = ev {
+            if self.lanes.contains(l) {
+                *self.counts.entry(a.to_type()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    fn values(&self) -> Vec<(String, f64)> {
+        AgentType::all()
+            .into_iter()
+            .map(|agent_type| {
+                (
+                    agent_type.plural_noun().to_string(),
+                    self.counts.get(&agent_type).cloned().unwrap_or(0) as f64,
+                )
+            })
+            .collect()
+    }
+
+    fn clone_box(&self) -> Box<dyn CustomMetric> {
+        Box::new(self.clone())
+    }
+}
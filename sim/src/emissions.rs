@@ -0,0 +1,252 @@
+//! A rough estimate of the CO2, NOx and particulates each car trip emits. Analytics records how
+//! long each trip spends driving along every lane, so stop-and-go traffic can be told apart from
+//! cruising. Emissions per kilometer follow a U-shaped curve over the average speed on each lane,
+//! weighted by a mix of petrol, diesel, hybrid and electric cars. Like the energy model, this is
+//! for comparing proposals against each other, not for an official inventory.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, Time};
+use map_model::{LaneID, Map, RoadID, Traversable};
+
+use crate::{Analytics, TripID};
+
+/// Below this, the speed curve stops growing. Crawling in a queue is bad, but not infinitely so.
+const MIN_KMPH: f64 = 5.0;
+/// Emissions per kilometer are lowest around this speed
+const EFFICIENT_KMPH: f64 = 60.0;
+
+/// The sum over some lanes driven of how long they are, and how long driving them took
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DrivingTotals {
+    pub distance: Distance,
+    /// Includes waiting to turn at the end of each lane
+    pub time: Duration,
+    /// Kilometers driven, each scaled by how much more a car emits at the average speed along
+    /// that lane than at an efficient speed. This doesn't depend on the fleet mix.
+    pub weighted_km: f64,
+}
+
+impl DrivingTotals {
+    pub(crate) fn new() -> DrivingTotals {
+        DrivingTotals {
+            distance: Distance::ZERO,
+            time: Duration::ZERO,
+            weighted_km: 0.0,
+        }
+    }
+
+    pub(crate) fn add(&mut self, dist: Distance, dt: Duration) {
+        self.distance += dist;
+        self.time += dt;
+        let km = dist.inner_meters() / 1000.0;
+        let kmph = if dt == Duration::ZERO {
+            EFFICIENT_KMPH
+        } else {
+            km / (dt.inner_seconds() / 3600.0)
+        };
+        self.weighted_km += km * speed_factor(kmph);
+    }
+}
+
+/// How far one car or bike trip has driven so far, and how long it took. The last lane of a trip
+/// isn't counted, since the vehicle stops partway along it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DrivingProfile {
+    pub totals: DrivingTotals,
+    /// The lane currently being driven along, and when the vehicle entered it
+    current: Option<(LaneID, Time)>,
+}
+
+impl DrivingProfile {
+    pub(crate) fn new() -> DrivingProfile {
+        DrivingProfile {
+            totals: DrivingTotals::new(),
+            current: None,
+        }
+    }
+
+    /// Returns the lane just finished, if any, with its road, length, and when it was entered
+    pub(crate) fn record(
+        &mut self,
+        on: Traversable,
+        now: Time,
+        map: &Map,
+    ) -> Option<(RoadID, Distance, Time)> {
+        let mut finished = None;
+        if let Some((l, entered)) = self.current.take() {
+            let dist = map.get_l(l).length();
+            self.totals.add(dist, now - entered);
+            finished = Some((l.road, dist, entered));
+        }
+        if let Traversable::Lane(l) = on {
+            self.current = Some((l, now));
+        }
+        finished
+    }
+}
+
+/// Tailpipe (and for particulates, brake and tire wear) emissions of one kind of car, cruising at
+/// an efficient speed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Powertrain {
+    pub name: String,
+    /// What fraction of all cars are like this. The shares in a mix should add up to 1.
+    pub share: f64,
+    pub co2_grams_per_km: f64,
+    pub nox_grams_per_km: f64,
+    pub pm_grams_per_km: f64,
+}
+
+/// Nobody knows exactly which car each trip uses, so every trip emits the average of the mix
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FleetMix {
+    pub powertrains: Vec<Powertrain>,
+}
+
+impl FleetMix {
+    /// Roughly a European fleet in the early 2020s
+    pub fn default_mix() -> FleetMix {
+        FleetMix {
+            powertrains: vec![
+                Powertrain {
+                    name: "petrol".to_string(),
+                    share: 0.55,
+                    co2_grams_per_km: 170.0,
+                    nox_grams_per_km: 0.06,
+                    pm_grams_per_km: 0.03,
+                },
+                Powertrain {
+                    name: "diesel".to_string(),
+                    share: 0.3,
+                    co2_grams_per_km: 150.0,
+                    nox_grams_per_km: 0.5,
+                    pm_grams_per_km: 0.035,
+                },
+                Powertrain {
+                    name: "hybrid".to_string(),
+                    share: 0.1,
+                    co2_grams_per_km: 110.0,
+                    nox_grams_per_km: 0.02,
+                    pm_grams_per_km: 0.025,
+                },
+                Powertrain {
+                    name: "electric".to_string(),
+                    share: 0.05,
+                    co2_grams_per_km: 0.0,
+                    nox_grams_per_km: 0.0,
+                    pm_grams_per_km: 0.03,
+                },
+            ],
+        }
+    }
+
+    /// What an average car in the mix emits per kilometer, cruising at an efficient speed
+    pub fn average_per_km(&self) -> Emissions {
+        let mut total = Emissions::default();
+        for p in &self.powertrains {
+            total.add(&Emissions {
+                co2_grams: p.share * p.co2_grams_per_km,
+                nox_grams: p.share * p.nox_grams_per_km,
+                pm_grams: p.share * p.pm_grams_per_km,
+            });
+        }
+        total
+    }
+
+    /// What average cars emit driving some lanes
+    pub fn emissions(&self, totals: &DrivingTotals) -> Emissions {
+        self.average_per_km().scale(totals.weighted_km)
+    }
+
+    pub fn trip_emissions(&self, profile: &DrivingProfile) -> Emissions {
+        self.emissions(&profile.totals)
+    }
+
+    /// The emissions of every car trip finishing successfully by some time, in the order they
+    /// finished
+    pub fn finished_trips(
+        &self,
+        analytics: &Analytics,
+        until: Time,
+    ) -> Vec<(Time, TripID, Emissions)> {
+        let mut results = Vec::new();
+        for (t, id, _, maybe_dt) in &analytics.finished_trips {
+            if *t > until {
+                break;
+            }
+            if maybe_dt.is_none() {
+                continue;
+            }
+            if let Some(profile) = analytics.trip_driving.get(id) {
+                results.push((*t, *id, self.trip_emissions(profile)));
+            }
+        }
+        results
+    }
+
+    /// Everything emitted by car trips finishing by some time
+    pub fn total(&self, analytics: &Analytics, until: Time) -> Emissions {
+        let mut total = Emissions::default();
+        for (_, _, emissions) in self.finished_trips(analytics, until) {
+            total.add(&emissions);
+        }
+        total
+    }
+
+    /// Emissions along each road, from cars that entered it up to the hour of `until`
+    pub fn road_emissions(
+        &self,
+        analytics: &Analytics,
+        until: Time,
+    ) -> BTreeMap<RoadID, Emissions> {
+        let mut per_road: BTreeMap<RoadID, Emissions> = BTreeMap::new();
+        for ((r, hour), totals) in &analytics.road_driving {
+            if *hour > until.get_hours() {
+                continue;
+            }
+            per_road
+                .entry(*r)
+                .or_insert_with(Emissions::default)
+                .add(&self.emissions(totals));
+        }
+        per_road
+    }
+}
+
+/// How much more a car emits per kilometer at some average speed than at an efficient speed. This
+/// is roughly the shape of COPERT's curves: lots more in stop-and-go traffic, a bit more on the
+/// motorway. Particulates from brakes and tires don't really follow this, but they're lumped in.
+fn speed_factor(kmph: f64) -> f64 {
+    let curve = |v: f64| 0.5 + 15.0 / v + 0.00008 * v * v;
+    curve(kmph.max(MIN_KMPH)) / curve(EFFICIENT_KMPH)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Emissions {
+    pub co2_grams: f64,
+    pub nox_grams: f64,
+    pub pm_grams: f64,
+}
+
+impl Emissions {
+    pub fn add(&mut self, other: &Emissions) {
+        self.co2_grams += other.co2_grams;
+        self.nox_grams += other.nox_grams;
+        self.pm_grams += other.pm_grams;
+    }
+
+    pub fn scale(self, x: f64) -> Emissions {
+        Emissions {
+            co2_grams: x * self.co2_grams,
+            nox_grams: x * self.nox_grams,
+            pm_grams: x * self.pm_grams,
+        }
+    }
+
+    pub fn co2_kg(&self) -> f64 {
+        self.co2_grams / 1000.0
+    }
+}
@@ -0,0 +1,201 @@
+//! A rough estimate of the energy each trip uses: fuel for cars, and calories burned by people
+//! walking and cycling. Analytics records how far each trip moves by each mode and how much it
+//! climbs, then a model turns that into energy. Climbing costs extra, but going downhill doesn't
+//! give anything back. Like the air quality model, this is for comparing proposals against each
+//! other, not for predicting anybody's exact fuel bill.
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Time};
+use map_model::{IntersectionID, Map, Traversable};
+use synthpop::TripMode;
+
+use crate::{AgentType, Analytics, TripID};
+
+const GRAVITY: f64 = 9.81;
+const JOULES_PER_KWH: f64 = 3.6e6;
+const JOULES_PER_KCAL: f64 = 4184.0;
+const KWH_PER_LITER_OF_PETROL: f64 = 8.9;
+
+/// How far something moved along lanes, and how much higher it wound up along the way
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Exertion {
+    pub distance: Distance,
+    /// Only counts the uphill parts
+    pub climb: Distance,
+}
+
+/// What one trip did by each mode. A trip driving somewhere also walks to and from the car.
+/// Distance along turns isn't counted, and neither is riding transit.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TripExertion {
+    pub driving: Exertion,
+    pub biking: Exertion,
+    pub walking: Exertion,
+    /// Was the bike electric?
+    pub ebike: bool,
+    /// Pedestrians can walk either way along a sidewalk, so remember where they came from
+    last_intersection: Option<IntersectionID>,
+}
+
+impl TripExertion {
+    pub(crate) fn new() -> TripExertion {
+        let none = Exertion {
+            distance: Distance::ZERO,
+            climb: Distance::ZERO,
+        };
+        TripExertion {
+            driving: none,
+            biking: none,
+            walking: none,
+            ebike: false,
+            last_intersection: None,
+        }
+    }
+
+    pub(crate) fn record(&mut self, agent_type: AgentType, on: Traversable, map: &Map) {
+        let l = match on {
+            Traversable::Lane(l) => l,
+            Traversable::Turn(t) => {
+                self.last_intersection = Some(t.parent);
+                return;
+            }
+        };
+        let lane = map.get_l(l);
+        let (from, to) =
+            if agent_type == AgentType::Pedestrian && self.last_intersection == Some(lane.dst_i) {
+                (lane.dst_i, lane.src_i)
+            } else {
+                (lane.src_i, lane.dst_i)
+            };
+        let rise = map.get_i(to).elevation - map.get_i(from).elevation;
+        let exertion = match agent_type {
+            AgentType::Car => &mut self.driving,
+            AgentType::Bike => &mut self.biking,
+            AgentType::Pedestrian => &mut self.walking,
+            AgentType::Bus | AgentType::Train | AgentType::TransitRider => {
+                return;
+            }
+        };
+        exertion.distance += lane.length();
+        exertion.climb += rise.max(Distance::ZERO);
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnergyModel {
+    /// The fuel energy a car uses driving along flat ground
+    pub car_kwh_per_km: f64,
+    pub car_mass_kg: f64,
+    /// How much of the fuel's energy winds up lifting the car uphill
+    pub engine_efficiency: f64,
+    pub person_mass_kg: f64,
+    pub bike_mass_kg: f64,
+    /// Calories burned along flat ground, per kilogram of body weight
+    pub walking_kcal_per_kg_km: f64,
+    pub biking_kcal_per_kg_km: f64,
+    /// How much of the calories burned winds up lifting somebody uphill
+    pub muscle_efficiency: f64,
+    /// Riders on e-bikes only put in this share of the effort of a normal bike; the motor does
+    /// the rest
+    pub ebike_effort: f64,
+}
+
+impl EnergyModel {
+    /// Roughly a mid-size petrol car using 7L per 100km, and an adult riding a normal bike
+    pub fn default_model() -> EnergyModel {
+        EnergyModel {
+            car_kwh_per_km: 0.65,
+            car_mass_kg: 1500.0,
+            engine_efficiency: 0.25,
+            person_mass_kg: 70.0,
+            bike_mass_kg: 12.0,
+            walking_kcal_per_kg_km: 0.5,
+            biking_kcal_per_kg_km: 0.25,
+            muscle_efficiency: 0.25,
+            ebike_effort: 0.6,
+        }
+    }
+
+    pub fn trip_energy(&self, exertion: &TripExertion) -> TripEnergy {
+        let climbing = |mass: f64, climb: Distance, efficiency: f64| {
+            mass * GRAVITY * climb.inner_meters() / efficiency
+        };
+        let km = |e: &Exertion| e.distance.inner_meters() / 1000.0;
+
+        let vehicle_kwh = km(&exertion.driving) * self.car_kwh_per_km
+            + climbing(
+                self.car_mass_kg + self.person_mass_kg,
+                exertion.driving.climb,
+                self.engine_efficiency,
+            ) / JOULES_PER_KWH;
+        let walking_kcal =
+            km(&exertion.walking) * self.walking_kcal_per_kg_km * self.person_mass_kg
+                + climbing(
+                    self.person_mass_kg,
+                    exertion.walking.climb,
+                    self.muscle_efficiency,
+                ) / JOULES_PER_KCAL;
+        let mut biking_kcal =
+            km(&exertion.biking) * self.biking_kcal_per_kg_km * self.person_mass_kg
+                + climbing(
+                    self.person_mass_kg + self.bike_mass_kg,
+                    exertion.biking.climb,
+                    self.muscle_efficiency,
+                ) / JOULES_PER_KCAL;
+        if exertion.ebike {
+            biking_kcal *= self.ebike_effort;
+        }
+        TripEnergy {
+            vehicle_kwh,
+            walking_kcal,
+            biking_kcal,
+        }
+    }
+
+    /// The energy of every trip finishing successfully by some time, in the order they finished
+    pub fn finished_trips(
+        &self,
+        analytics: &Analytics,
+        until: Time,
+    ) -> Vec<(Time, TripID, TripMode, TripEnergy)> {
+        let mut results = Vec::new();
+        for (t, id, mode, maybe_dt) in &analytics.finished_trips {
+            if *t > until {
+                break;
+            }
+            if maybe_dt.is_none() {
+                continue;
+            }
+            if let Some(exertion) = analytics.trip_exertion.get(id) {
+                results.push((*t, *id, *mode, self.trip_energy(exertion)));
+            }
+        }
+        results
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TripEnergy {
+    /// Fuel burned in a car
+    pub vehicle_kwh: f64,
+    pub walking_kcal: f64,
+    pub biking_kcal: f64,
+}
+
+impl TripEnergy {
+    /// The vehicle's energy, as petrol
+    pub fn fuel_liters(&self) -> f64 {
+        self.vehicle_kwh / KWH_PER_LITER_OF_PETROL
+    }
+
+    pub fn kcal(&self) -> f64 {
+        self.walking_kcal + self.biking_kcal
+    }
+
+    pub fn add(&mut self, other: &TripEnergy) {
+        self.vehicle_kwh += other.vehicle_kwh;
+        self.walking_kcal += other.walking_kcal;
+        self.biking_kcal += other.biking_kcal;
+    }
+}
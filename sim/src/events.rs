@@ -1,9 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use geom::Duration;
+use geom::{Distance, Duration, Speed};
 use map_model::{
-    BuildingID, IntersectionID, LaneID, Map, Path, PathRequest, TransitRouteID, TransitStopID,
-    Traversable, TurnID,
+    BuildingID, DirectedRoadID, FerryRouteID, FerryTerminalID, IntersectionID, LaneID, Map, Path,
+    PathRequest, TransitRouteID, TransitStopID, Traversable, TurnID,
 };
 use synthpop::TripMode;
 
@@ -41,8 +41,37 @@ pub enum Event {
     ProblemEncountered(TripID, Problem),
 
     /// If the agent is a transit vehicle, then include a count of how many passengers are on
-    /// board.
-    AgentEntersTraversable(AgentID, Option<TripID>, Traversable, Option<usize>),
+    /// board. The last count is how many people the agent carries: a car's occupancy, a transit
+    /// vehicle's passengers, or 1 for a pedestrian or cyclist.
+    AgentEntersTraversable(AgentID, Option<TripID>, Traversable, Option<usize>, usize),
+
+    /// A vehicle paid this toll to enter a lane, either a HOT lane it doesn't qualify for or a lane
+    /// crossing into a congestion charge cordon
+    TollPaid(TripID, LaneID, f64),
+    /// A variable speed limit sign changed. None means the road's normal limit.
+    SpeedLimitPosted(DirectedRoadID, Option<Speed>),
+    /// A car started stopping at a pickup/dropoff zone, with this many vehicles queued behind it
+    PickupDropoff(BuildingID, usize),
+    /// A ridehail vehicle stopped after driving this far, either to drop off its passenger (true)
+    /// or to pick somebody up (false)
+    RidehailDrove(CarID, Distance, bool),
+    /// A delivery van stopped in front of a building and blocks its lane for this long, with this
+    /// many vehicles queued behind it. True if the van double-parked, false if it's just pulling
+    /// into a loading zone.
+    DeliveryStop(BuildingID, Duration, usize, bool),
+    /// An emergency vehicle reached a building this long after being dispatched, or None if it
+    /// couldn't get there at all
+    EmergencyResponse(BuildingID, Option<Duration>),
+    /// Somebody picked up a shared bike or e-scooter outside this building
+    SharedVehicleTaken(BuildingID),
+    /// Somebody left a shared bike or e-scooter outside this building
+    SharedVehicleLeft(BuildingID),
+    /// Somebody starting here found no shared vehicle close enough, so they walked instead
+    NoSharedVehicle(BuildingID),
+    /// Somebody started riding their own e-bike on this trip
+    EBikeRideStarted(TripID),
+    /// A bus or train waited this long at a traffic signal
+    TransitSignalDelay(CarID, IntersectionID, Duration),
     /// TripID, TurnID (Where the delay was encountered), Time spent waiting at that turn
     IntersectionDelayMeasured(TripID, TurnID, AgentID, Duration),
 
@@ -79,6 +108,13 @@ pub enum TripPhaseType {
     WaitingForBus(TransitRouteID, TransitStopID),
     /// What stop did they board at?
     RidingBus(TransitRouteID, TransitStopID, CarID),
+    WaitingForFerry(FerryRouteID, FerryTerminalID),
+    /// What terminal did they board at?
+    RidingFerry(FerryRouteID, FerryTerminalID),
+    WaitingForRidehail,
+    RidingRidehail(CarID),
+    /// A delivery van waits in a loading zone while the driver unloads
+    Unloading,
     Cancelled,
     Finished,
     DelayedStart,
@@ -97,6 +133,15 @@ impl TripPhaseType {
             TripPhaseType::RidingBus(r, _, _) => {
                 format!("Riding route {}", map.get_tr(r).long_name)
             }
+            TripPhaseType::WaitingForFerry(r, _) => {
+                format!("Waiting for the {} ferry", map.get_ferry_route(r).name)
+            }
+            TripPhaseType::RidingFerry(r, _) => {
+                format!("Riding the {} ferry", map.get_ferry_route(r).name)
+            }
+            TripPhaseType::WaitingForRidehail => "Waiting for a ridehail".to_string(),
+            TripPhaseType::RidingRidehail(car) => format!("Riding in {}", car),
+            TripPhaseType::Unloading => "Unloading in a loading zone".to_string(),
             TripPhaseType::Cancelled => "Trip was cancelled due to some bug".to_string(),
             TripPhaseType::Finished => "Trip finished".to_string(),
             TripPhaseType::DelayedStart => "Delayed by a previous trip taking too long".to_string(),
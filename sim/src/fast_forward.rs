@@ -0,0 +1,400 @@
+//! A much faster, coarser alternative to the normal simulation, for quick comparisons. Every lane
+//! uses the same link model as `SimOptions::mesoscopic`: vehicles queue in order, take a
+//! congestion-dependent time to cross, leave at the lane's exit capacity while fixed-time signals
+//! allow, and wait for room on the next lane. Nothing else is simulated in detail. Each trip
+//! follows one path, chosen when it starts. There's no parking, stop signs, or transit vehicles;
+//! transit riders just move along the walking path at an average transit speed.
+//!
+//! It uses the same `Scenario` and produces the same `Analytics` as the normal simulation,
+//! numbering people and trips the same way, so results feed into the same dashboards and
+//! comparisons. Only compare fast-forward results with other fast-forward results, though; travel
+//! times are systematically different from the detailed simulation.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+
+use rand_xorshift::XorShiftRng;
+
+use abstutil::Timer;
+use geom::{Distance, Duration, Speed, Time};
+use map_model::{LaneID, Map, Path, PathStep, MAX_BIKE_SPEED};
+use synthpop::{Scenario, TripEndpoint, TripMode};
+
+use crate::sim::rand_ped_speed;
+use crate::{
+    fixed_time_red_until, AgentID, Analytics, CarID, Event, LinkQueues, PedestrianID, PersonID,
+    TripID, TripPhaseType, VehicleType, BIKE_LENGTH, EXIT_HEADWAY,
+};
+
+/// The average car length in the normal simulation
+const CAR_LENGTH: Distance = Distance::const_meters(5.5);
+/// Including waiting and stops along the way
+const TRANSIT_SPEED: Speed = Speed::const_meters_per_second(5.0);
+
+pub struct FastForwardSim {
+    time: Time,
+    people: Vec<FastForwardPerson>,
+    /// When each person next needs to do something, by index into `people`. Entries that don't
+    /// match the person's `wake_at` are stale.
+    queue: BinaryHeap<Reverse<(Time, usize)>>,
+    links: LinkQueues,
+    /// The person driving each vehicle, by index into `people`
+    drivers: BTreeMap<CarID, usize>,
+    analytics: Analytics,
+}
+
+struct FastForwardPerson {
+    id: PersonID,
+    ped_speed: Speed,
+    trips: VecDeque<FastForwardTrip>,
+    current: Option<CurrentTrip>,
+    wake_at: Option<Time>,
+}
+
+struct FastForwardTrip {
+    id: TripID,
+    depart: Time,
+    mode: TripMode,
+    origin: TripEndpoint,
+    destination: TripEndpoint,
+}
+
+struct CurrentTrip {
+    id: TripID,
+    mode: TripMode,
+    agent: AgentID,
+    max_speed: Option<Speed>,
+    path: Path,
+    next_step: usize,
+    started: Time,
+    /// For vehicles, the lane they're on and when they reach the end of it
+    link: Option<(LaneID, Time)>,
+    blocked_time: Duration,
+}
+
+impl FastForwardSim {
+    /// People and trips are numbered like `Sim::instantiate` does, so results can be matched up
+    /// by trip. `rng` only picks walking speeds.
+    pub fn new(map: &Map, scenario: &Scenario, rng: &mut XorShiftRng) -> FastForwardSim {
+        let mut sim = FastForwardSim {
+            time: Time::START_OF_DAY,
+            people: Vec::new(),
+            queue: BinaryHeap::new(),
+            links: LinkQueues::new(),
+            drivers: BTreeMap::new(),
+            analytics: Analytics::new(true),
+        };
+        let mut num_trips = 0;
+        for (idx, p) in scenario.people.iter().enumerate() {
+            let mut trips = VecDeque::new();
+            for trip in &p.trips {
+                let id = TripID(num_trips);
+                num_trips += 1;
+                if trip.cancelled {
+                    sim.analytics
+                        .event(Event::TripCancelled(id, trip.mode), sim.time, map);
+                    continue;
+                }
+                trips.push_back(FastForwardTrip {
+                    id,
+                    depart: trip.depart,
+                    mode: trip.mode,
+                    origin: trip.origin,
+                    destination: trip.destination,
+                });
+            }
+            let depart = trips.front().map(|trip| trip.depart);
+            sim.people.push(FastForwardPerson {
+                id: PersonID(idx),
+                ped_speed: rand_ped_speed(rng),
+                trips,
+                current: None,
+                wake_at: None,
+            });
+            if let Some(depart) = depart {
+                sim.schedule(idx, depart);
+            }
+        }
+        sim
+    }
+
+    /// Simulate until this time, or until everybody's done
+    pub fn run_until(&mut self, map: &Map, end: Time, timer: &mut Timer) {
+        timer.start(format!("fast-forward simulation until {}", end));
+        while let Some(Reverse((time, idx))) = self.queue.peek().cloned() {
+            if time > end {
+                break;
+            }
+            self.queue.pop();
+            if self.people[idx].wake_at != Some(time) {
+                continue;
+            }
+            self.people[idx].wake_at = None;
+            self.time = time;
+            self.update_person(map, idx);
+        }
+        self.time = self.time.max(end);
+        timer.stop(format!("fast-forward simulation until {}", end));
+    }
+
+    pub fn time(&self) -> Time {
+        self.time
+    }
+
+    pub fn get_analytics(&self) -> &Analytics {
+        &self.analytics
+    }
+
+    /// Like `Sim::get_end_of_day`, always at least 24 hours
+    pub fn get_end_of_day(&self) -> Time {
+        self.people
+            .iter()
+            .filter_map(|p| p.trips.back().map(|t| t.depart))
+            .max()
+            .unwrap_or(Time::START_OF_DAY)
+            .max(Time::START_OF_DAY + Duration::hours(24))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Whatever the person's next update was, it's now at this time
+    fn schedule(&mut self, idx: usize, time: Time) {
+        self.people[idx].wake_at = Some(time);
+        self.queue.push(Reverse((time, idx)));
+    }
+
+    fn update_person(&mut self, map: &Map, idx: usize) {
+        let now = self.time;
+        if self.people[idx].current.is_none() {
+            match self.start_trip(map, idx) {
+                Some(next) => {
+                    self.schedule(idx, next);
+                    return;
+                }
+                None => {
+                    if self.people[idx].current.is_none() {
+                        // The trip couldn't start; try the next one
+                        if !self.people[idx].trips.is_empty() {
+                            self.schedule(idx, now);
+                        }
+                        return;
+                    }
+                }
+            }
+        }
+
+        let (agent, link, at_end, blocked_time) = {
+            let trip = self.people[idx].current.as_ref().unwrap();
+            (
+                trip.agent,
+                trip.link,
+                trip.next_step == trip.path.get_steps().len(),
+                trip.blocked_time,
+            )
+        };
+        if let (AgentID::Car(car), Some((l, ready))) = (agent, link) {
+            if now < ready {
+                self.schedule(idx, ready);
+                return;
+            }
+            if at_end {
+                // Parking or vanishing at a border doesn't wait for anything
+                if let Some(follower) = self.links.leave(l, car, now) {
+                    self.wake_follower(l, follower);
+                }
+                self.finish_trip(map, idx, blocked_time);
+                return;
+            }
+            if !self.try_leave_link(map, idx, car, l) {
+                return;
+            }
+        } else if at_end {
+            self.finish_trip(map, idx, blocked_time);
+            return;
+        }
+
+        // Move onto the next step, or for vehicles, past the next turn onto a lane
+        let trip = self.people[idx].current.as_mut().unwrap();
+        let constraints = trip.path.get_req().constraints;
+        let mut dt = Duration::ZERO;
+        loop {
+            let step = trip.path.get_steps()[trip.next_step];
+            trip.next_step += 1;
+            self.analytics.event(
+                Event::AgentEntersTraversable(
+                    trip.agent,
+                    Some(trip.id),
+                    step.as_traversable(),
+                    None,
+                    1,
+                ),
+                now,
+                map,
+            );
+            dt += trip.path.dist_crossed_from_step(map, &step)
+                / step.max_speed_along(trip.max_speed, constraints, map);
+            if let (AgentID::Car(car), PathStep::Lane(l)) = (trip.agent, step) {
+                let ready = self.links.enter(l, car, vehicle_length(car), dt, now);
+                trip.link = Some((l, ready));
+                self.schedule(idx, ready);
+                return;
+            }
+            if let AgentID::Pedestrian(_) = trip.agent {
+                break;
+            }
+        }
+        self.schedule(idx, now + dt);
+    }
+
+    /// The vehicle has reached the end of its lane, which isn't the last step. Returns true if it
+    /// can leave now; otherwise it'll be woken up later.
+    fn try_leave_link(&mut self, map: &Map, idx: usize, car: CarID, l: LaneID) -> bool {
+        let now = self.time;
+        let trip = self.people[idx].current.as_ref().unwrap();
+        let turn = trip.path.get_steps()[trip.next_step].as_turn();
+        let ready = trip.link.unwrap().1;
+
+        let retry = match self.links.earliest_exit(l, car) {
+            // The leader wakes us up
+            None => {
+                return false;
+            }
+            Some(t) if now < t => Some(t),
+            Some(_) => fixed_time_red_until(map, turn, now),
+        };
+        if let Some(t) = retry {
+            self.schedule(idx, t);
+            return false;
+        }
+        if !self
+            .links
+            .has_room(turn.dst, vehicle_length(car), Distance::ZERO, map)
+            && !self.links.is_stuck(l, now)
+        {
+            self.schedule(idx, now + EXIT_HEADWAY);
+            return false;
+        }
+
+        self.people[idx].current.as_mut().unwrap().blocked_time += now - ready;
+        if let Some(follower) = self.links.leave(l, car, now) {
+            self.wake_follower(l, follower);
+        }
+        true
+    }
+
+    fn wake_follower(&mut self, l: LaneID, follower: CarID) {
+        let time = self
+            .links
+            .earliest_exit(l, follower)
+            .unwrap()
+            .max(self.time);
+        self.schedule(self.drivers[&follower], time);
+    }
+
+    fn finish_trip(&mut self, map: &Map, idx: usize, blocked_time: Duration) {
+        let now = self.time;
+        let person = &mut self.people[idx];
+        let trip = person.current.take().unwrap();
+        if let AgentID::Car(car) = trip.agent {
+            self.drivers.remove(&car);
+        }
+        self.analytics.event(
+            Event::TripFinished {
+                trip: trip.id,
+                mode: trip.mode,
+                total_time: now - trip.started,
+                blocked_time,
+            },
+            now,
+            map,
+        );
+        if let Some(next) = person.trips.front() {
+            // Like the normal simulation, a person running late starts their next trip as soon
+            // as they can
+            let depart = next.depart.max(now);
+            self.schedule(idx, depart);
+        }
+    }
+
+    /// If the person's next trip isn't due yet, returns when it is. Otherwise starts it, or
+    /// cancels it if there's no path.
+    fn start_trip(&mut self, map: &Map, idx: usize) -> Option<Time> {
+        let now = self.time;
+        let person = &mut self.people[idx];
+        let spec = person.trips.front()?;
+        if spec.depart > now {
+            return Some(spec.depart);
+        }
+        let spec = person.trips.pop_front().unwrap();
+
+        let maybe_path = TripEndpoint::path_req(spec.origin, spec.destination, spec.mode, map)
+            .and_then(|req| map.pathfind(req).ok());
+        let path = match maybe_path {
+            Some(path) => path,
+            None => {
+                self.analytics
+                    .event(Event::TripCancelled(spec.id, spec.mode), now, map);
+                return None;
+            }
+        };
+
+        let (agent, max_speed, phase) = match spec.mode {
+            TripMode::Walk => (
+                AgentID::Pedestrian(PedestrianID(idx)),
+                Some(person.ped_speed),
+                TripPhaseType::Walking,
+            ),
+            TripMode::Transit => (
+                AgentID::Pedestrian(PedestrianID(idx)),
+                Some(TRANSIT_SPEED),
+                TripPhaseType::Walking,
+            ),
+            TripMode::Bike | TripMode::Micromobility => (
+                AgentID::Car(CarID {
+                    id: spec.id.0,
+                    vehicle_type: VehicleType::Bike,
+                }),
+                Some(MAX_BIKE_SPEED),
+                TripPhaseType::Biking,
+            ),
+            TripMode::Drive | TripMode::Ridehail | TripMode::ParkAndRide => (
+                AgentID::Car(CarID {
+                    id: spec.id.0,
+                    vehicle_type: VehicleType::Car,
+                }),
+                None,
+                TripPhaseType::Driving,
+            ),
+        };
+        self.analytics.event(
+            Event::TripPhaseStarting(spec.id, person.id, Some(path.get_req().clone()), phase),
+            now,
+            map,
+        );
+        person.current = Some(CurrentTrip {
+            id: spec.id,
+            mode: spec.mode,
+            agent,
+            max_speed,
+            path,
+            next_step: 0,
+            started: now,
+            link: None,
+            blocked_time: Duration::ZERO,
+        });
+        if let AgentID::Car(car) = agent {
+            self.drivers.insert(car, idx);
+        }
+        None
+    }
+}
+
+fn vehicle_length(car: CarID) -> Distance {
+    if car.vehicle_type == VehicleType::Bike {
+        BIKE_LENGTH
+    } else {
+        CAR_LENGTH
+    }
+}
@@ -0,0 +1,171 @@
+//! A health impact assessment of walking and cycling, following the WHO's Health Economic
+//! Assessment Tool (HEAT). Regular physical activity lowers the risk of dying early. Comparing how
+//! much each person walks and cycles in the baseline and a proposal estimates the deaths prevented
+//! per year, and what that's worth. Like HEAT, this only counts the benefit of physical activity,
+//! not changes in crash risk or air pollution, and it assumes the simulated day repeats
+//! throughout the year.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Speed, Time};
+
+use crate::{Analytics, PersonID, Sim, TripID};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HealthImpactModel {
+    /// The relative risk of dying for somebody doing `walking_reference_minutes` of walking per
+    /// week, compared to somebody not walking at all
+    pub walking_relative_risk: f64,
+    pub walking_reference_minutes: f64,
+    /// Walking beyond some point doesn't lower the risk any more
+    pub walking_max_risk_reduction: f64,
+    pub cycling_relative_risk: f64,
+    pub cycling_reference_minutes: f64,
+    pub cycling_max_risk_reduction: f64,
+    /// Used to turn distance into time spent active
+    pub walking_speed: Speed,
+    pub cycling_speed: Speed,
+    /// How many days a week people make the simulated trips
+    pub days_per_week: f64,
+    /// The fraction of people expected to die each year
+    pub annual_mortality_rate: f64,
+    /// The value society places on preventing one death, in any currency
+    pub value_of_statistical_life: f64,
+}
+
+impl HealthImpactModel {
+    /// HEAT's defaults for adults, with a European mortality rate and value of statistical life
+    pub fn default_model() -> HealthImpactModel {
+        HealthImpactModel {
+            walking_relative_risk: 0.89,
+            walking_reference_minutes: 168.0,
+            walking_max_risk_reduction: 0.30,
+            cycling_relative_risk: 0.90,
+            cycling_reference_minutes: 100.0,
+            cycling_max_risk_reduction: 0.45,
+            walking_speed: Speed::km_per_hour(4.8),
+            cycling_speed: Speed::km_per_hour(14.0),
+            days_per_week: 5.0,
+            annual_mortality_rate: 0.005,
+            value_of_statistical_life: 3_500_000.0,
+        }
+    }
+
+    /// Compares the walking and cycling everybody did in trips finished by some time, before and
+    /// after the proposal. The current simulation is the proposal.
+    pub fn assess(&self, sim: &Sim, baseline: &Analytics, until: Time) -> HealthImpact {
+        let before = activity_per_person(sim, baseline, until);
+        let after = activity_per_person(sim, sim.get_analytics(), until);
+
+        let mut impact = HealthImpact {
+            people: 0,
+            more_active: 0,
+            less_active: 0,
+            walking_minutes_before: 0.0,
+            walking_minutes_after: 0.0,
+            cycling_minutes_before: 0.0,
+            cycling_minutes_after: 0.0,
+            deaths_prevented_per_year: 0.0,
+            value_per_year: 0.0,
+        };
+        let nothing = (Distance::ZERO, Distance::ZERO);
+        let mut people: Vec<&PersonID> = before.keys().chain(after.keys()).collect();
+        people.sort();
+        people.dedup();
+        for person in people {
+            let (walk1, bike1) = self.weekly_minutes(*before.get(person).unwrap_or(&nothing));
+            let (walk2, bike2) = self.weekly_minutes(*after.get(person).unwrap_or(&nothing));
+            impact.people += 1;
+            impact.walking_minutes_before += walk1;
+            impact.walking_minutes_after += walk2;
+            impact.cycling_minutes_before += bike1;
+            impact.cycling_minutes_after += bike2;
+
+            let change = self.risk_reduction(walk2, bike2) - self.risk_reduction(walk1, bike1);
+            if change > 0.0 {
+                impact.more_active += 1;
+            } else if change < 0.0 {
+                impact.less_active += 1;
+            }
+            impact.deaths_prevented_per_year += change * self.annual_mortality_rate;
+        }
+        impact.value_per_year = impact.deaths_prevented_per_year * self.value_of_statistical_life;
+        impact
+    }
+
+    fn weekly_minutes(&self, (walked, biked): (Distance, Distance)) -> (f64, f64) {
+        (
+            (walked / self.walking_speed).inner_seconds() / 60.0 * self.days_per_week,
+            (biked / self.cycling_speed).inner_seconds() / 60.0 * self.days_per_week,
+        )
+    }
+
+    /// How much lower the risk of dying is for somebody walking and cycling this many minutes a
+    /// week. The risk falls linearly with activity, up to the cap.
+    fn risk_reduction(&self, walking_minutes: f64, cycling_minutes: f64) -> f64 {
+        let walking = ((1.0 - self.walking_relative_risk) * walking_minutes
+            / self.walking_reference_minutes)
+            .min(self.walking_max_risk_reduction);
+        let cycling = ((1.0 - self.cycling_relative_risk) * cycling_minutes
+            / self.cycling_reference_minutes)
+            .min(self.cycling_max_risk_reduction);
+        walking + cycling
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HealthImpact {
+    /// Everybody finishing a trip in the baseline or the proposal
+    pub people: usize,
+    /// How many people walk or cycle enough more (or less) to change their risk
+    pub more_active: usize,
+    pub less_active: usize,
+    /// Summed over everybody, per week
+    pub walking_minutes_before: f64,
+    pub walking_minutes_after: f64,
+    pub cycling_minutes_before: f64,
+    pub cycling_minutes_after: f64,
+    /// Negative if people become less active
+    pub deaths_prevented_per_year: f64,
+    pub value_per_year: f64,
+}
+
+/// How far each person walked and cycled in the trips they finished
+fn activity_per_person(
+    sim: &Sim,
+    analytics: &Analytics,
+    until: Time,
+) -> BTreeMap<PersonID, (Distance, Distance)> {
+    let mut per_person = BTreeMap::new();
+    for (t, id, _, maybe_dt) in &analytics.finished_trips {
+        if *t > until {
+            break;
+        }
+        if maybe_dt.is_none() {
+            continue;
+        }
+        if let Some((person, exertion)) = person_and_exertion(sim, analytics, *id) {
+            let entry = per_person
+                .entry(person)
+                .or_insert((Distance::ZERO, Distance::ZERO));
+            entry.0 += exertion.0;
+            entry.1 += exertion.1;
+        }
+    }
+    per_person
+}
+
+fn person_and_exertion(
+    sim: &Sim,
+    analytics: &Analytics,
+    id: TripID,
+) -> Option<(PersonID, (Distance, Distance))> {
+    let person = sim.trip_to_person(id)?;
+    let exertion = analytics.trip_exertion.get(&id)?;
+    Some((
+        person,
+        (exertion.walking.distance, exertion.biking.distance),
+    ))
+}
@@ -0,0 +1,90 @@
+//! Vehicle speeds along each lane at a fine resolution, to find exactly where traffic slows down.
+//! Per-road averages hide whether a queue starts at a traffic signal, a merge, or a crosswalk
+//! partway along the road. Every few seconds, the speed of each vehicle is recorded in a short
+//! bin along the lane it's on. Stopped vehicles count as a speed of 0.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, Speed, Time};
+use map_model::{LaneID, Map};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LaneSpeeds {
+    pub bin_length: Distance,
+    /// Sampling starts from when recording is first started
+    pub since: Time,
+    /// For each bin from the start of the lane, the sum of sampled speeds in meters per second
+    /// and the number of samples
+    pub bins: BTreeMap<LaneID, Vec<(f64, usize)>>,
+}
+
+impl LaneSpeeds {
+    pub(crate) const FREQUENCY: Duration = Duration::const_seconds(5.0);
+
+    pub(crate) fn new(bin_length: Distance, now: Time) -> LaneSpeeds {
+        LaneSpeeds {
+            bin_length,
+            since: now,
+            bins: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, l: LaneID, dist: Distance, speed: Speed, map: &Map) {
+        let bin_length = self.bin_length;
+        let bins = self.bins.entry(l).or_insert_with(|| {
+            let num_bins = (map.get_l(l).length() / bin_length).ceil().max(1.0) as usize;
+            vec![(0.0, 0); num_bins]
+        });
+        let idx = ((dist / bin_length) as usize).min(bins.len() - 1);
+        bins[idx].0 += speed.inner_meters_per_second();
+        bins[idx].1 += 1;
+    }
+
+    /// The average speed in each bin along the lane, if any vehicle was sampled there
+    pub fn average_speeds(&self, l: LaneID) -> Vec<Option<Speed>> {
+        match self.bins.get(&l) {
+            Some(bins) => bins
+                .iter()
+                .map(|(sum, n)| {
+                    if *n == 0 {
+                        None
+                    } else {
+                        Some(Speed::meters_per_second(*sum / (*n as f64)))
+                    }
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Finds the first stretch of the lane where vehicles average below some fraction of the
+    /// speed limit. Returns the distance along the lane where it starts and ends.
+    pub fn find_slowdown(
+        &self,
+        l: LaneID,
+        map: &Map,
+        fraction: f64,
+    ) -> Option<(Distance, Distance)> {
+        let limit = map.get_parent(l).speed_limit;
+        let length = map.get_l(l).length();
+        let mut start = None;
+        for (idx, speed) in self.average_speeds(l).into_iter().enumerate() {
+            let slow = speed
+                .map(|s| s.inner_meters_per_second() < fraction * limit.inner_meters_per_second())
+                .unwrap_or(false);
+            let dist = self.bin_length * (idx as f64);
+            match (start, slow) {
+                (None, true) => {
+                    start = Some(dist);
+                }
+                (Some(s), false) => {
+                    return Some((s, dist.min(length)));
+                }
+                _ => {}
+            }
+        }
+        start.map(|s| (s, length))
+    }
+}
@@ -24,8 +24,8 @@ use serde::{Deserialize, Serialize};
 use abstutil::{deserialize_usize, serialize_usize};
 use geom::{Distance, Speed, Time};
 use map_model::{
-    BuildingID, IntersectionID, LaneID, Map, ParkingLotID, Path, PathConstraints, Position,
-    TransitRouteID, TransitStopID,
+    BuildingID, FerryTerminalID, IntersectionID, LaneID, Map, ParkingLotID, Path, PathConstraints,
+    Position, TransitRouteID, TransitStopID,
 };
 use synthpop::TripEndpoint;
 
@@ -34,38 +34,69 @@ pub use crate::render::{
     UnzoomedAgent,
 };
 
-pub use self::analytics::{Analytics, Problem, ProblemType, SlidingWindow, TripPhase};
-pub(crate) use self::events::Event;
-pub use self::events::{AlertLocation, TripPhaseType};
+pub use self::air_quality::{EmissionsModel, ExposureSummary};
+pub use self::analytics::{
+    Analytics, CyclingStats, LinkStats, Problem, ProblemType, SlidingWindow, TripPhase,
+    LINK_STATS_BIN,
+};
+pub use self::custom_metrics::{CustomMetric, CustomMetricResults, SensorCount};
+pub use self::emissions::{DrivingProfile, DrivingTotals, Emissions, FleetMix, Powertrain};
+pub use self::energy::{EnergyModel, Exertion, TripEnergy, TripExertion};
+pub use self::events::{AlertLocation, Event, TripPhaseType};
+pub use self::fast_forward::FastForwardSim;
+pub use self::health::{HealthImpact, HealthImpactModel};
+pub use self::lane_speeds::LaneSpeeds;
 pub use self::make::SimFlags;
 pub(crate) use self::make::{StartTripArgs, TripSpec};
+pub use self::mechanics::{crowded_walking_speed, PickupDropoffZone, VariableSpeedLimits};
 pub(crate) use self::mechanics::{
-    DrivingSimState, IntersectionSimState, ParkingSim, ParkingSimState, WalkingSimState,
+    fixed_time_red_until, DrivingSimState, IntersectionSimState, LinkQueues, ParkingSim,
+    ParkingSimState, WalkingSimState, EXIT_HEADWAY,
 };
+pub(crate) use self::micromobility::MicromobilityFleet;
+pub use self::noise::{NoiseModel, NoiseSummary, VehicleNoise, BACKGROUND_DB};
 pub(crate) use self::pandemic::PandemicModel;
+pub use self::parallelism::ParallelismEstimate;
 pub use self::prebake::PrebakeSummary;
 pub(crate) use self::recorder::TrafficRecorder;
+pub use self::reliability::{Reliability, ReliabilityStats};
+pub(crate) use self::ridehail::RidehailFleet;
 pub(crate) use self::router::{ActionAtEnd, Router};
 pub(crate) use self::scheduler::{Command, Scheduler};
+pub use self::screenlines::{Screenline, ScreenlineCount, ScreenlineShape};
 pub use self::sim::{
-    count_parked_cars_per_bldg, rand_dist, AgentProperties, AlertHandler, DelayCause, Sim,
-    SimCallback, SimOptions,
+    count_parked_cars_per_bldg, rand_dist, AgentProperties, AlertHandler, Anomaly, BoundaryHandoff,
+    DelayCause, DeterminismLog, DeterminismSample, EmergencyCall, EmergencyVehicleType, Incident,
+    Sim, SimCallback, SimOptions, Watchdog,
 };
 pub(crate) use self::transit::TransitSimState;
 pub use self::trips::{CommutersVehiclesCounts, Person, PersonState, TripInfo, TripResult};
 pub(crate) use self::trips::{TripLeg, TripManager};
 pub use synthpop::make::{fork_rng, BorderSpawnOverTime, ScenarioGenerator, SpawnOverTime};
 
+mod air_quality;
 mod analytics;
+mod custom_metrics;
+mod emissions;
+mod energy;
 mod events;
+mod fast_forward;
+mod health;
+mod lane_speeds;
 mod make;
 mod mechanics;
+mod micromobility;
+mod noise;
 mod pandemic;
+mod parallelism;
 pub mod prebake;
 mod recorder;
+mod reliability;
 mod render;
+mod ridehail;
 mod router;
 mod scheduler;
+mod screenlines;
 mod sim;
 mod transit;
 mod trips;
@@ -321,6 +352,17 @@ pub struct Vehicle {
     pub max_speed: Option<Speed>,
 }
 
+impl Vehicle {
+    /// E-bikes are bikes that can sustain more than `map_model::MAX_BIKE_SPEED`
+    pub fn is_ebike(&self) -> bool {
+        self.vehicle_type == VehicleType::Bike
+            && self
+                .max_speed
+                .map(|speed| speed > map_model::MAX_BIKE_SPEED)
+                .unwrap_or(false)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct VehicleSpec {
     pub vehicle_type: VehicleType,
@@ -361,6 +403,8 @@ pub struct ParkedCar {
 pub(crate) enum DrivingGoal {
     ParkNear(BuildingID),
     Border(IntersectionID, LaneID),
+    /// Only cars use this
+    ParkInLot(ParkingLotID),
 }
 
 impl DrivingGoal {
@@ -382,6 +426,7 @@ impl DrivingGoal {
                 }
             },
             DrivingGoal::Border(_, l) => Some(Position::end(*l, map)),
+            DrivingGoal::ParkInLot(pl) => Some(map.get_pl(*pl).driving_pos),
         }
     }
 
@@ -397,6 +442,7 @@ impl DrivingGoal {
             DrivingGoal::Border(i, last_lane) => {
                 Router::end_at_border(owner, path, map.get_l(*last_lane).length(), *i)
             }
+            DrivingGoal::ParkInLot(pl) => Router::park_in_lot(owner, path, *pl),
         }
     }
 }
@@ -416,6 +462,7 @@ pub(crate) enum SidewalkPOI {
     DeferredParkingSpot,
     Building(BuildingID),
     TransitStop(TransitStopID),
+    FerryTerminal(FerryTerminalID),
     Border(IntersectionID),
     /// The bikeable position
     BikeRack(Position),
@@ -466,6 +513,13 @@ impl SidewalkSpot {
         }
     }
 
+    pub fn ferry_terminal(terminal: FerryTerminalID, map: &Map) -> SidewalkSpot {
+        SidewalkSpot {
+            sidewalk_pos: map.get_ferry_terminal(terminal).sidewalk_pos,
+            connection: SidewalkPOI::FerryTerminal(terminal),
+        }
+    }
+
     // Recall sidewalks are bidirectional.
     pub fn start_at_border(i: IntersectionID, map: &Map) -> Option<SidewalkSpot> {
         Some(SidewalkSpot {
@@ -571,7 +625,7 @@ pub(crate) struct CreateCar {
     pub vehicle: Vehicle,
     pub router: Router,
     pub maybe_parked_car: Option<ParkedCar>,
-    /// None for buses
+    /// None for buses, and ridehail vehicles on the way to a pickup
     pub trip_and_person: Option<(TripID, PersonID)>,
     pub maybe_route: Option<TransitRouteID>,
 }
@@ -69,8 +69,12 @@ impl SimFlags {
         XorShiftRng::seed_from_u64(self.rng_seed)
     }
 
-    /// Loads a map and simulation. Not appropriate for use in the UI or on web.
-    pub fn load_synchronously(&self, timer: &mut abstutil::Timer) -> (Map, Sim, XorShiftRng) {
+    /// Loads a map and simulation. Not appropriate for use in the UI or on web. Fails if any of
+    /// the input files named by the options are missing or broken.
+    pub fn load_synchronously(
+        &self,
+        timer: &mut abstutil::Timer,
+    ) -> Result<(Map, Sim, XorShiftRng)> {
         if self.load.is_empty() {
             panic!("You forgot to call initialize on SimFlags after parsing from structopt");
         }
@@ -82,9 +86,10 @@ impl SimFlags {
         if self.load.starts_with(&abstio::path_player("saves/")) {
             info!("Resuming from {}", self.load);
 
-            let sim: Sim = abstio::must_read_object(self.load.clone(), timer);
+            let sim = Sim::load_savestate(self.load.clone(), timer)?;
 
             let mut map = Map::load_synchronously(sim.map_name.path(), timer);
+            map.set_wind(opts.wind(), timer);
             match MapEdits::load_from_file(
                 &map,
                 abstio::path_edits(map.get_name(), &sim.edits_name),
@@ -95,17 +100,18 @@ impl SimFlags {
                     map.recalculate_pathfinding_after_edits(timer);
                 }
                 Err(err) => {
-                    panic!("Couldn't load edits \"{}\": {}", sim.edits_name, err);
+                    bail!("Couldn't load edits \"{}\": {}", sim.edits_name, err);
                 }
             }
 
-            (map, sim, rng)
+            Ok((map, sim, rng))
         } else if self.load.contains("/scenarios/") {
             info!("Seeding the simulation from scenario {}", self.load);
 
             let mut scenario: Scenario = abstio::must_read_object(self.load.clone(), timer);
 
-            let map = Map::load_synchronously(scenario.map_name.path(), timer);
+            let mut map = Map::load_synchronously(scenario.map_name.path(), timer);
+            map.set_wind(opts.wind(), timer);
 
             for m in &self.scenario_modifiers {
                 scenario = m.apply(&map, scenario, &mut rng);
@@ -115,21 +121,24 @@ impl SimFlags {
                 opts.run_name = scenario.scenario_name.clone();
             }
             let mut sim = Sim::new(&map, opts);
+            sim.load_input_files(&map, &self.opts, timer)?;
             sim.instantiate(&scenario, &map, &mut rng, timer);
 
-            (map, sim, rng)
+            Ok((map, sim, rng))
         } else if self.load.contains("/raw_maps/") || self.load.contains("/maps/") {
             info!("Loading map {}", self.load);
 
-            let map = Map::load_synchronously(self.load.clone(), timer);
+            let mut map = Map::load_synchronously(self.load.clone(), timer);
+            map.set_wind(opts.wind(), timer);
 
             timer.start("create sim");
-            let sim = Sim::new(&map, opts);
+            let mut sim = Sim::new(&map, opts);
+            sim.load_input_files(&map, &self.opts, timer)?;
             timer.stop("create sim");
 
-            (map, sim, rng)
+            Ok((map, sim, rng))
         } else {
-            panic!("Don't know how to load {}", self.load);
+            bail!("Don't know how to load {}", self.load);
         }
     }
 }
@@ -4,7 +4,10 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use map_model::{BuildingID, Map, PathConstraints, Position, TransitRouteID, TransitStopID};
+use map_model::{
+    BuildingID, FerryRouteID, FerryTerminalID, Map, PathConstraints, Position, TransitRouteID,
+    TransitStopID,
+};
 use synthpop::{TripEndpoint, TripMode};
 
 use crate::{CarID, DrivingGoal, SidewalkSpot, TripLeg, VehicleType, SPAWN_DIST};
@@ -55,6 +58,29 @@ pub(crate) enum TripSpec {
         stop1: TransitStopID,
         maybe_stop2: Option<TransitStopID>,
     },
+    UsingFerry {
+        start: SidewalkSpot,
+        goal: SidewalkSpot,
+        route: FerryRouteID,
+        terminal1: FerryTerminalID,
+        terminal2: FerryTerminalID,
+    },
+    UsingRidehail {
+        start: BuildingID,
+        goal: BuildingID,
+    },
+    /// Which shared vehicle to use isn't decided until the trip starts
+    UsingMicromobility {
+        start: BuildingID,
+        goal: BuildingID,
+    },
+    /// Whether to drive the whole way or only to a park-and-ride lot isn't decided until the trip
+    /// starts
+    UsingParkAndRide {
+        car: CarID,
+        start: BuildingID,
+        goal: BuildingID,
+    },
 }
 
 impl TripSpec {
@@ -115,6 +141,8 @@ impl TripSpec {
                         legs.push(TripLeg::Walk(SidewalkSpot::building(*b, map)));
                     }
                     DrivingGoal::Border(_, _) => {}
+                    // Only chosen once a park-and-ride trip starts
+                    DrivingGoal::ParkInLot(_) => unreachable!(),
                 }
             }
             TripSpec::JustWalking { start, goal, .. } => {
@@ -139,6 +167,7 @@ impl TripSpec {
                             goal,
                         })
                     }
+                    DrivingGoal::ParkInLot(_) => unreachable!(),
                 };
 
                 if let Some(start_spot) = SidewalkSpot::bike_rack(*start, map) {
@@ -168,6 +197,7 @@ impl TripSpec {
                             legs.push(TripLeg::Walk(SidewalkSpot::building(*b, map)));
                         }
                         DrivingGoal::Border(_, _) => {}
+                        DrivingGoal::ParkInLot(_) => unreachable!(),
                     }
                 } else if let Some(plan) = backup_plan {
                     info!("Can't start biking from {}. Walking instead", start);
@@ -201,6 +231,24 @@ impl TripSpec {
                     legs = vec![TripLeg::Walk(walk_to), TripLeg::RideBus(*route, None)];
                 }
             }
+            TripSpec::UsingFerry {
+                route,
+                terminal1,
+                terminal2,
+                goal,
+                ..
+            } => {
+                legs = vec![
+                    TripLeg::Walk(SidewalkSpot::ferry_terminal(*terminal1, map)),
+                    TripLeg::RideFerry(*route, *terminal1, *terminal2),
+                    TripLeg::Walk(goal.clone()),
+                ];
+            }
+            TripSpec::UsingRidehail { goal, .. } => {
+                legs.push(TripLeg::Ridehail(*goal));
+            }
+            // The legs depend on where shared vehicles are when the trip starts
+            TripSpec::UsingMicromobility { .. } | TripSpec::UsingParkAndRide { .. } => {}
         };
 
         (self, legs)
@@ -265,10 +313,11 @@ impl TripSpec {
                     },
                 }
             }
-            TripMode::Walk => TripSpec::JustWalking {
-                start: start_sidewalk_spot(from, map)?,
-                goal: end_sidewalk_spot(to, map)?,
-            },
+            TripMode::Walk => {
+                let start = start_sidewalk_spot(from, map)?;
+                let goal = end_sidewalk_spot(to, map)?;
+                walk_or_ferry(start, goal, map)
+            }
             TripMode::Transit => {
                 let start = start_sidewalk_spot(from, map)?;
                 let goal = end_sidewalk_spot(to, map)?;
@@ -285,13 +334,78 @@ impl TripSpec {
                 } else {
                     //warn!("{:?} not actually using transit, because pathfinding didn't find any
                     // useful route", trip);
-                    TripSpec::JustWalking { start, goal }
+                    walk_or_ferry(start, goal, map)
                 }
             }
+            TripMode::Ridehail => match (from, to) {
+                (TripEndpoint::Building(start), TripEndpoint::Building(goal))
+                    if start != goal
+                        && map.get_b(start).driving_connection(map).is_some()
+                        && map.get_b(goal).driving_connection(map).is_some() =>
+                {
+                    TripSpec::UsingRidehail { start, goal }
+                }
+                // Ridehail vehicles only pick up and drop off at buildings they can reach
+                _ => {
+                    info!(
+                        "Can't take a ridehail from {:?} to {:?}, walking instead",
+                        from, to
+                    );
+                    let start = start_sidewalk_spot(from, map)?;
+                    let goal = end_sidewalk_spot(to, map)?;
+                    walk_or_ferry(start, goal, map)
+                }
+            },
+            TripMode::Micromobility => match (from, to) {
+                (TripEndpoint::Building(start), TripEndpoint::Building(goal)) if start != goal => {
+                    TripSpec::UsingMicromobility { start, goal }
+                }
+                // Shared vehicles stay on the map
+                _ => {
+                    let start = start_sidewalk_spot(from, map)?;
+                    let goal = end_sidewalk_spot(to, map)?;
+                    walk_or_ferry(start, goal, map)
+                }
+            },
+            TripMode::ParkAndRide => match (from, to) {
+                (TripEndpoint::Building(start), TripEndpoint::Building(goal)) if start != goal => {
+                    TripSpec::UsingParkAndRide {
+                        car: use_vehicle.unwrap(),
+                        start,
+                        goal,
+                    }
+                }
+                // Cars from off-map just drive
+                _ => TripSpec::maybe_new(
+                    from,
+                    to,
+                    TripMode::Drive,
+                    use_vehicle,
+                    retry_if_no_room,
+                    map,
+                )?,
+            },
         })
     }
 }
 
+/// Take a ferry if it beats walking the long way around the water.
+fn walk_or_ferry(start: SidewalkSpot, goal: SidewalkSpot, map: &Map) -> TripSpec {
+    if let Some((route, terminal1, terminal2)) =
+        map.should_use_ferry(start.sidewalk_pos, goal.sidewalk_pos)
+    {
+        TripSpec::UsingFerry {
+            start,
+            goal,
+            route,
+            terminal1,
+            terminal2,
+        }
+    } else {
+        TripSpec::JustWalking { start, goal }
+    }
+}
+
 fn start_sidewalk_spot(endpt: TripEndpoint, map: &Map) -> Result<SidewalkSpot> {
     match endpt {
         TripEndpoint::Building(b) => Ok(SidewalkSpot::building(b, map)),
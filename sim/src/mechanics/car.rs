@@ -2,7 +2,7 @@ use std::collections::{BTreeSet, VecDeque};
 
 use serde::{Deserialize, Serialize};
 
-use geom::{Distance, Duration, PolyLine, Time, EPSILON_DIST};
+use geom::{Distance, Duration, PolyLine, Speed, Time, EPSILON_DIST};
 use map_model::{Direction, LaneID, Map, Traversable};
 
 use crate::{
@@ -10,13 +10,16 @@ use crate::{
     TimeInterval, TransitSimState, TripID, Vehicle, VehicleType,
 };
 
+/// How much faster than usual emergency vehicles drive on the way to a call
+const EMERGENCY_SPEEDING: f64 = 1.3;
+
 /// Represents a single vehicle. Note "car" is a misnomer; it could also be a bus or bike.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct Car {
     pub vehicle: Vehicle,
     pub state: CarState,
     pub router: Router,
-    /// None for buses
+    /// None for buses, and ridehail vehicles on the way to a pickup
     // TODO Can we scrap person here and use vehicle owner?
     pub trip_and_person: Option<(TripID, PersonID)>,
     pub started_at: Time,
@@ -29,6 +32,13 @@ pub(crate) struct Car {
     /// Since lane over-taking isn't implemented yet, a vehicle tends to be stuck behind a slow
     /// leader for a while. Avoid duplicate events.
     pub wants_to_overtake: BTreeSet<CarID>,
+
+    /// The variable speed limit shown when the vehicle entered its current road
+    pub posted_speed_limit: Option<Speed>,
+
+    /// The last place the driver reconsidered their route because of traffic, so they only do so
+    /// once per lane
+    pub rerouted_on: Option<Traversable>,
 }
 
 impl Car {
@@ -56,7 +66,7 @@ impl Car {
         start_time: Time,
         map: &Map,
     ) -> CarState {
-        let (speed, percent_incline) = self
+        let (mut speed, percent_incline) = self
             .router
             .get_path()
             .current_step()
@@ -65,6 +75,13 @@ impl Car {
                 self.vehicle.vehicle_type.to_constraints(),
                 map,
             );
+        if self.router.is_responding() {
+            speed = EMERGENCY_SPEEDING * speed;
+        } else if let (Some(limit), Traversable::Lane(_)) =
+            (self.posted_speed_limit, self.router.head())
+        {
+            speed = speed.min(limit);
+        }
         let dt = (dist_int.end - dist_int.start) / speed;
         CarState::Crossing {
             time_int: TimeInterval::new(start_time, start_time + dt),
@@ -367,6 +384,38 @@ impl CarState {
         }
     }
 
+    /// How fast the car is moving with the traffic. None when it's parking, unparking, or idling
+    /// at a transit stop.
+    pub fn current_speed(&self) -> Option<Speed> {
+        let (time_int, dist_int) = match self {
+            CarState::Crossing {
+                ref time_int,
+                ref dist_int,
+                ..
+            } => (time_int, dist_int),
+            CarState::ChangingLanes {
+                ref new_time,
+                ref new_dist,
+                ..
+            } => (new_time, new_dist),
+            CarState::Queued { .. } | CarState::WaitingToAdvance { .. } => {
+                return Some(Speed::ZERO);
+            }
+            CarState::Unparking { .. }
+            | CarState::Parking(_, _, _)
+            | CarState::IdlingAtStop(_, _) => {
+                return None;
+            }
+        };
+        let dt = (time_int.end - time_int.start).inner_seconds();
+        if dt == 0.0 {
+            return Some(Speed::ZERO);
+        }
+        Some(Speed::meters_per_second(
+            (dist_int.end - dist_int.start).inner_meters() / dt,
+        ))
+    }
+
     pub fn time_spent_waiting(&self, now: Time) -> Duration {
         match self {
             CarState::Queued { blocked_since, .. }
@@ -2,22 +2,37 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
 use serde::{Deserialize, Serialize};
 
-use abstutil::{deserialize_hashmap, serialize_hashmap, FixedMap, IndexableKey};
-use geom::{Distance, Duration, PolyLine, Time};
-use map_model::{DrivingSide, IntersectionID, LaneID, Map, Path, PathStep, Position, Traversable};
+use abstutil::{
+    deserialize_btreemap, deserialize_hashmap, fnv1a, serialize_btreemap, serialize_hashmap,
+    FixedMap, IndexableKey, FNV_OFFSET,
+};
+use geom::{Distance, Duration, PolyLine, Speed, Time};
+use map_model::{
+    BuildingID, DirectedRoadID, DrivingSide, IntersectionID, LaneID, Map, Path, PathStep, Position,
+    Traversable,
+};
 
 use crate::mechanics::car::{Car, CarState};
+use crate::mechanics::links::{LinkQueues, STUCK_TIME};
 use crate::mechanics::queue::{Queue, QueueEntry, Queued};
+use crate::mechanics::speed_limits::{SpeedLimitSigns, VariableSpeedLimits};
 use crate::sim::Ctx;
 use crate::{
     ActionAtEnd, AgentID, AgentProperties, CarID, CarStatus, Command, CreateCar, DelayCause,
     DistanceInterval, DrawCarInput, Event, IntersectionSimState, ParkedCar, ParkingSim,
-    ParkingSpot, PersonID, Problem, SimOptions, TimeInterval, TransitSimState, TripID, TripManager,
-    UnzoomedAgent, Vehicle, VehicleType, WalkingSimState, FOLLOWING_DISTANCE, MAX_CAR_LENGTH,
+    ParkingSpot, PersonID, PickupDropoffZone, Problem, SimOptions, TimeInterval, TransitSimState,
+    TripID, TripManager, UnzoomedAgent, Vehicle, VehicleType, WalkingSimState, EXIT_HEADWAY,
+    FOLLOWING_DISTANCE, MAX_CAR_LENGTH,
 };
 
-const TIME_TO_WAIT_AT_BUS_STOP: Duration = Duration::const_seconds(10.0);
 const TIME_TO_CHANGE_LANES: Duration = Duration::const_seconds(1.0);
+// Don't change lanes in front of a moving vehicle unless it'd take them at least this long to
+// close the gap at their usual speed.
+const MIN_GAP_TO_CHANGE_LANES: Duration = Duration::const_seconds(1.5);
+// When rerouting, assume each vehicle queued on a lane holds up the ones behind it this long
+const QUEUED_VEHICLE_DELAY: Duration = Duration::const_seconds(2.0);
+// Rerouting drivers all use the same road delays, recounted from the queues at most this often
+const ROAD_DELAYS_INTERVAL: Duration = Duration::const_seconds(30.0);
 
 // TODO Do something else.
 pub const BLIND_RETRY_TO_CREEP_FORWARDS: Duration = Duration::const_seconds(0.1);
@@ -40,11 +55,34 @@ pub(crate) struct DrivingSimState {
     )]
     queues: HashMap<Traversable, Queue>,
     events: Vec<Event>,
+    // Vehicles on lanes between two mesoscopic intersections are simulated with the link model
+    // instead, and aren't in cars or queues.
+    links: LinkQueues,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    link_cars: BTreeMap<CarID, Car>,
 
     waiting_to_spawn: BTreeMap<CarID, (Position, Option<PersonID>)>,
 
     recalc_lanechanging: bool,
     handle_uber_turns: bool,
+    reroute_fraction: f64,
+    reroute_after_delay: Duration,
+    // The delays from the last time live_road_delays recounted, and when that was
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    road_delays: BTreeMap<DirectedRoadID, Duration>,
+    road_delays_counted_at: Option<Time>,
+    speed_limit_signs: SpeedLimitSigns,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pickup_dropoff_zones: BTreeMap<BuildingID, PickupDropoffZone>,
 
     time_to_unpark_onstreet: Duration,
     time_to_park_onstreet: Duration,
@@ -59,8 +97,16 @@ impl DrivingSimState {
             cars: FixedMap::new(),
             queues: HashMap::new(),
             events: Vec::new(),
+            links: LinkQueues::new(),
+            link_cars: BTreeMap::new(),
             recalc_lanechanging: !opts.dont_recalc_lanechanging,
             handle_uber_turns: !opts.dont_handle_uber_turns,
+            reroute_fraction: opts.reroute_fraction,
+            reroute_after_delay: opts.reroute_after_delay,
+            road_delays: BTreeMap::new(),
+            road_delays_counted_at: None,
+            speed_limit_signs: SpeedLimitSigns::new(),
+            pickup_dropoff_zones: BTreeMap::new(),
             waiting_to_spawn: BTreeMap::new(),
 
             time_to_unpark_onstreet: Duration::seconds(10.0),
@@ -191,7 +237,15 @@ impl DrivingSimState {
                 total_blocked_time: Duration::ZERO,
                 trip_and_person: params.trip_and_person,
                 wants_to_overtake: BTreeSet::new(),
+                posted_speed_limit: None,
+                rerouted_on: None,
             };
+            // Ridehail vehicles heading to a pickup don't have a trip yet
+            if car.trip_and_person.is_some() || car.router.is_dropping_off() {
+                car.router.maybe_drop_off(&self.pickup_dropoff_zones);
+            }
+            self.read_speed_limit_sign(&mut car, now, ctx.map);
+            request_transit_priority(&car, now, ctx);
             let mut start_crossing = false;
             if let Some(p) = params.maybe_parked_car {
                 let delay = match p.spot {
@@ -234,13 +288,20 @@ impl DrivingSimState {
                 if car.router.last_step() {
                     match car.router.maybe_handle_end(
                         start_dist,
+                        now,
                         &car.vehicle,
                         ctx.parking,
                         ctx.map,
                         car.trip_and_person,
                         &mut self.events,
                     ) {
-                        None | Some(ActionAtEnd::GotoLaneEnd) => {}
+                        // If we're spawning along the curb of a pickup/dropoff zone, drive to the
+                        // end of it first
+                        None
+                        | Some(ActionAtEnd::GotoLaneEnd)
+                        | Some(ActionAtEnd::DropOff(_, _))
+                        | Some(ActionAtEnd::Deliver(_))
+                        | Some(ActionAtEnd::Respond(_, _)) => {}
                         x => {
                             panic!(
                                 "Car with one-step route {:?} had unexpected result from \
@@ -325,6 +386,14 @@ impl DrivingSimState {
         transit: &mut TransitSimState,
         walking: &mut WalkingSimState,
     ) {
+        if self.link_cars.contains_key(&id) {
+            self.update_car_in_link(id, now, ctx);
+            return;
+        }
+        if self.maybe_enter_link(id, now, ctx) {
+            return;
+        }
+
         let mut need_distances = {
             let car = &self.cars[&id];
             match car.state {
@@ -394,7 +463,7 @@ impl DrivingSimState {
                 if queue.is_car_at_front(car.vehicle.id) {
                     // Want to re-run, but no urgency about it happening immediately.
                     car.state = CarState::WaitingToAdvance { blocked_since: now };
-                    if self.recalc_lanechanging {
+                    if self.recalc_lanechanging && !approaching_mesoscopic(car, ctx) {
                         car.router.opportunistically_lanechange(
                             &self.queues,
                             ctx.map,
@@ -418,7 +487,13 @@ impl DrivingSimState {
                         ));
                     }
 
-                    if let Some(target_lane) = self.pick_overtaking_lane(car, ctx.map) {
+                    let target_lane = if approaching_mesoscopic(car, ctx) {
+                        // Nobody overtakes in the mesoscopic model
+                        None
+                    } else {
+                        self.pick_overtaking_lane(car, ctx.map)
+                    };
+                    if let Some(target_lane) = target_lane {
                         // We need the current position of the car to see if lane-changing is
                         // actually feasible right now, so record our intention and trigger
                         // update_car_with_distances.
@@ -471,6 +546,7 @@ impl DrivingSimState {
                     // end_dist.
                     car.router.maybe_handle_end(
                         front,
+                        now,
                         &car.vehicle,
                         ctx.parking,
                         ctx.map,
@@ -485,6 +561,26 @@ impl DrivingSimState {
             }
             CarState::WaitingToAdvance { blocked_since } => {
                 // 'car' is the leader.
+                if now - blocked_since >= self.reroute_after_delay
+                    && car.rerouted_on != Some(car.router.head())
+                    && self.reroutes_when_stuck(car.vehicle.id)
+                    && !approaching_mesoscopic(car, ctx)
+                {
+                    car.rerouted_on = Some(car.router.head());
+                    let old_next = car.router.next();
+                    self.update_road_delays(now, ctx.map);
+                    if car.router.reroute(&self.road_delays, ctx.map) {
+                        if let Traversable::Turn(t) = old_next {
+                            if car.router.next() != old_next {
+                                ctx.intersections
+                                    .cancel_request(AgentID::Car(car.vehicle.id), t);
+                            }
+                        }
+                        self.events
+                            .push(Event::PathAmended(car.router.get_path().clone()));
+                    }
+                }
+
                 let from = car.router.head();
                 let goto = car.router.next();
                 assert!(from != goto);
@@ -514,6 +610,15 @@ impl DrivingSimState {
                             now - blocked_since,
                         ));
                     }
+                    if car.vehicle.vehicle_type.is_transit()
+                        && ctx.map.maybe_get_traffic_signal(t.parent).is_some()
+                    {
+                        self.events.push(Event::TransitSignalDelay(
+                            car.vehicle.id,
+                            t.parent,
+                            now - blocked_since,
+                        ));
+                    }
                 }
 
                 {
@@ -525,6 +630,7 @@ impl DrivingSimState {
                 // way, until laggy_head is None.
 
                 let last_step = car.router.advance(
+                    now,
                     &car.vehicle,
                     ctx.parking,
                     ctx.map,
@@ -532,18 +638,22 @@ impl DrivingSimState {
                     &mut self.events,
                 );
                 car.total_blocked_time += now - blocked_since;
+                self.read_speed_limit_sign(car, now, ctx.map);
+                request_transit_priority(car, now, ctx);
                 car.state = car.crossing_state(Distance::ZERO, now, ctx.map);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                let passengers = if car.vehicle.vehicle_type.is_transit() {
+                    Some(transit.get_passengers(car.vehicle.id).len())
+                } else {
+                    None
+                };
                 self.events.push(Event::AgentEntersTraversable(
                     AgentID::Car(car.vehicle.id),
                     car.trip_and_person.map(|(t, _)| t),
                     goto,
-                    if car.vehicle.vehicle_type.is_transit() {
-                        Some(transit.get_passengers(car.vehicle.id).len())
-                    } else {
-                        None
-                    },
+                    passengers,
+                    passengers.unwrap_or_else(|| car.router.occupancy()),
                 ));
 
                 // Don't mark turn_finished until our back is out of the turn.
@@ -640,6 +750,7 @@ impl DrivingSimState {
 
                 match car.router.maybe_handle_end(
                     our_dist,
+                    now,
                     &car.vehicle,
                     ctx.parking,
                     ctx.map,
@@ -714,10 +825,12 @@ impl DrivingSimState {
                     }
                     Some(ActionAtEnd::BusAtStop) => {
                         car.total_blocked_time += now - blocked_since;
-                        if transit.bus_arrived_at_stop(now, car.vehicle.id, trips, walking, ctx) {
+                        if let Some(dwell_time) =
+                            transit.bus_arrived_at_stop(now, car.vehicle.id, trips, walking, ctx)
+                        {
                             car.state = CarState::IdlingAtStop(
                                 our_dist,
-                                TimeInterval::new(now, now + TIME_TO_WAIT_AT_BUS_STOP),
+                                TimeInterval::new(now, now + dwell_time),
                             );
                             ctx.scheduler
                                 .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
@@ -727,6 +840,48 @@ impl DrivingSimState {
                             false
                         }
                     }
+                    Some(ActionAtEnd::DropOff(b, dwell_time)) => {
+                        car.total_blocked_time += now - blocked_since;
+                        car.state = CarState::IdlingAtStop(
+                            our_dist,
+                            TimeInterval::new(now, now + dwell_time),
+                        );
+                        ctx.scheduler
+                            .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                        // Everyone behind is stuck, whether they're waiting for the curb or just
+                        // passing through
+                        self.events
+                            .push(Event::PickupDropoff(b, dists.len() - idx - 1));
+                        true
+                    }
+                    Some(ActionAtEnd::Deliver(b)) => {
+                        car.total_blocked_time += now - blocked_since;
+                        let (dwell_time, double_parked) = trips.van_reached_curb(
+                            now,
+                            car.vehicle.id,
+                            car.router.head().as_lane(),
+                            ctx.map,
+                        );
+                        car.state = CarState::IdlingAtStop(
+                            our_dist,
+                            TimeInterval::new(now, now + dwell_time),
+                        );
+                        ctx.scheduler
+                            .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                        self.events.push(Event::DeliveryStop(
+                            b,
+                            dwell_time,
+                            dists.len() - idx - 1,
+                            double_parked,
+                        ));
+                        true
+                    }
+                    Some(ActionAtEnd::Respond(b, dispatched)) => {
+                        car.total_blocked_time += now - blocked_since;
+                        self.events
+                            .push(Event::EmergencyResponse(b, Some(now - dispatched)));
+                        false
+                    }
                     None => {
                         ctx.scheduler.push(
                             now + BLIND_RETRY_TO_REACH_END_DIST,
@@ -772,6 +927,35 @@ impl DrivingSimState {
                 );
                 false
             }
+            CarState::IdlingAtStop(dist, _) if car.router.is_dropping_off() => {
+                let stopped_at = Position::new(car.router.head().as_lane(), dist);
+                if let Some((router, trip_and_person)) = trips.car_dropped_off(
+                    now,
+                    car.vehicle.clone(),
+                    stopped_at,
+                    car.total_blocked_time,
+                    car.router.get_path().total_length(),
+                    ctx,
+                ) {
+                    // A ridehail vehicle picked somebody up
+                    car.router = router;
+                    car.router.maybe_drop_off(&self.pickup_dropoff_zones);
+                    car.trip_and_person = Some(trip_and_person);
+                    car.total_blocked_time = Duration::ZERO;
+                    self.events
+                        .push(Event::PathAmended(car.router.get_path().clone()));
+                    car.state = car.crossing_state(dist, now, ctx.map);
+                    ctx.scheduler
+                        .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                    self.new_crossing_state(ctx, car);
+
+                    self.update_follower(idx, dists, now, ctx);
+
+                    true
+                } else {
+                    false
+                }
+            }
             CarState::IdlingAtStop(dist, _) => {
                 car.router = transit.bus_departed_from_stop(car.vehicle.id, ctx.map);
                 self.events
@@ -793,6 +977,15 @@ impl DrivingSimState {
     pub fn delete_car(&mut self, c: CarID, now: Time, ctx: &mut Ctx) -> Vehicle {
         self.waiting_to_spawn.remove(&c);
 
+        if let Some(car) = self.link_cars.remove(&c) {
+            let l = car.router.head().as_lane();
+            if let Some(follower) = self.links.leave(l, c, now) {
+                self.wake_link_follower(l, follower, now, ctx);
+            }
+            ctx.scheduler.cancel(Command::UpdateCar(c));
+            return car.vehicle;
+        }
+
         let dists = self.queues[&self.cars[&c].router.head()].get_car_positions(
             now,
             &self.cars,
@@ -1073,7 +1266,9 @@ impl DrivingSimState {
                                     // gets out of the way. So immediately promote them to
                                     // WaitingToAdvance.
                                     follower.state = CarState::WaitingToAdvance { blocked_since };
-                                    if self.recalc_lanechanging && ctx.handling_live_edits.is_none()
+                                    if self.recalc_lanechanging
+                                        && ctx.handling_live_edits.is_none()
+                                        && !approaching_mesoscopic(follower, ctx)
                                     {
                                         follower.router.opportunistically_lanechange(
                                             &self.queues,
@@ -1210,6 +1405,16 @@ impl DrivingSimState {
                 &self.queues,
             )
         {
+            if !self.follower_gap_is_acceptable(
+                front_target_queue - car.vehicle.length,
+                idx_in_target_queue,
+                target_lane,
+                now,
+                ctx.map,
+            ) {
+                return;
+            }
+
             // TODO Can downgrade this to an alert or debug once active work has settled down
             if false {
                 info!(
@@ -1248,10 +1453,68 @@ impl DrivingSimState {
         }
     }
 
+    /// Gap acceptance for changing lanes. get_idx_to_insert_car only checks that there's physical
+    /// room right now. If the vehicle that'd wind up behind us is moving, also insist it wouldn't
+    /// immediately have to brake hard.
+    fn follower_gap_is_acceptable(
+        &self,
+        our_back: Distance,
+        idx_in_target_queue: usize,
+        target_lane: LaneID,
+        now: Time,
+        map: &Map,
+    ) -> bool {
+        let dists = self.queues[&Traversable::Lane(target_lane)].get_car_positions(
+            now,
+            &self.cars,
+            &self.queues,
+        );
+        let (follower, follower_front) = match dists.get(idx_in_target_queue) {
+            Some(QueueEntry {
+                member: Queued::Vehicle(id),
+                front,
+                ..
+            }) => (&self.cars[id], *front),
+            // Blockages don't move, and the following distance was already checked
+            _ => {
+                return true;
+            }
+        };
+        if !matches!(
+            follower.state,
+            CarState::Crossing { .. } | CarState::ChangingLanes { .. }
+        ) {
+            return true;
+        }
+        let speed = PathStep::Lane(target_lane).max_speed_along(
+            follower.vehicle.max_speed,
+            follower.vehicle.vehicle_type.to_constraints(),
+            map,
+        );
+        (our_back - follower_front) / speed >= MIN_GAP_TO_CHANGE_LANES
+    }
+
     pub fn collect_events(&mut self) -> Vec<Event> {
         std::mem::take(&mut self.events)
     }
 
+    pub fn add_variable_speed_limits(&mut self, limits: VariableSpeedLimits) {
+        self.speed_limit_signs.add(limits);
+    }
+
+    pub fn remove_variable_speed_limit(&mut self, dr: DirectedRoadID) {
+        self.speed_limit_signs.remove(dr);
+    }
+
+    /// Only affects cars that start driving afterwards
+    pub fn add_pickup_dropoff_zone(&mut self, zone: PickupDropoffZone) {
+        self.pickup_dropoff_zones.insert(zone.building, zone);
+    }
+
+    pub fn remove_pickup_dropoff_zone(&mut self, b: BuildingID) {
+        self.pickup_dropoff_zones.remove(&b);
+    }
+
     pub fn handle_live_edits(&mut self, map: &Map) {
         // Calculate all queues that should exist now.
         let mut new_queues = HashSet::new();
@@ -1291,6 +1554,21 @@ impl DrivingSimState {
         }
     }
 
+    /// Vehicles read a variable speed limit sign as they enter its road, then keep to that limit
+    /// until they leave the road.
+    fn read_speed_limit_sign(&mut self, car: &mut Car, now: Time, map: &Map) {
+        if let Traversable::Lane(l) = car.router.head() {
+            let dr = map.get_l(l).get_directed_parent();
+            let (posted, changed) =
+                self.speed_limit_signs
+                    .vehicle_entering(now, dr, &self.queues, map);
+            if changed {
+                self.events.push(Event::SpeedLimitPosted(dr, posted));
+            }
+            car.posted_speed_limit = posted;
+        }
+    }
+
     fn new_crossing_state(&self, ctx: &mut Ctx, car: &Car) {
         if self.queues[&car.router.head()].is_car_at_front(car.vehicle.id) {
             if let Some(Traversable::Turn(turn)) = car.router.maybe_next() {
@@ -1304,6 +1582,255 @@ impl DrivingSimState {
     }
 }
 
+// The link model
+impl DrivingSimState {
+    /// Lanes between two mesoscopic intersections use the link model.
+    fn is_link(&self, l: LaneID, ctx: &Ctx) -> bool {
+        let lane = ctx.map.get_l(l);
+        self.queues.contains_key(&Traversable::Lane(l))
+            && ctx.intersections.is_mesoscopic(lane.src_i)
+            && ctx.intersections.is_mesoscopic(lane.dst_i)
+    }
+
+    /// A vehicle at the front of its lane about to turn onto a link leaves the detailed
+    /// simulation. Returns true if this handled the update, either by entering the link or by
+    /// waiting to.
+    fn maybe_enter_link(&mut self, id: CarID, now: Time, ctx: &mut Ctx) -> bool {
+        let (turn, blocked_since) = {
+            let car = &self.cars[&id];
+            let blocked_since = match car.state {
+                CarState::WaitingToAdvance { blocked_since } => blocked_since,
+                _ => {
+                    return false;
+                }
+            };
+            let turn = match car.router.maybe_next() {
+                Some(Traversable::Turn(t)) => t,
+                _ => {
+                    return false;
+                }
+            };
+            // Transit stops at the end of the trip and uber-turns still need the detailed
+            // simulation
+            let path = car.router.get_path();
+            if car.vehicle.vehicle_type.is_transit()
+                || path.get_steps().len() <= 3
+                || path.currently_inside_ut().is_some()
+                || path.about_to_start_ut().is_some()
+                || !self.is_link(turn.dst, ctx)
+            {
+                return false;
+            }
+            (turn, blocked_since)
+        };
+
+        let length = self.cars[&id].vehicle.length;
+        if !self.links.has_room(
+            turn.dst,
+            length,
+            self.queues[&Traversable::Lane(turn.dst)].reserved_length,
+            ctx.map,
+        ) && now - blocked_since < STUCK_TIME
+        {
+            ctx.scheduler
+                .update(now + EXIT_HEADWAY, Command::UpdateCar(id));
+            return true;
+        }
+        if !ctx.intersections.maybe_leave_for_link(
+            AgentID::Car(id),
+            turn,
+            now,
+            ctx.map,
+            ctx.scheduler,
+        ) {
+            return true;
+        }
+
+        let dists = self.queues[&self.cars[&id].router.head()].get_car_positions(
+            now,
+            &self.cars,
+            &self.queues,
+        );
+        let idx = dists
+            .iter()
+            .position(|entry| entry.member == Queued::Vehicle(id))
+            .unwrap();
+        let mut car = self.cars.remove(&id).unwrap();
+        self.delete_car_internal(&mut car, dists, idx, now, ctx);
+        car.total_blocked_time += now - blocked_since;
+        if let Some((trip, _)) = car.trip_and_person {
+            self.events.push(Event::IntersectionDelayMeasured(
+                trip,
+                turn,
+                AgentID::Car(id),
+                now - blocked_since,
+            ));
+        }
+        self.enter_link(car, now, ctx);
+        true
+    }
+
+    /// The vehicle's next steps are a turn and then a link.
+    fn enter_link(&mut self, mut car: Car, now: Time, ctx: &mut Ctx) {
+        let turn_time = self.advance_without_queues(&mut car, now, ctx);
+        let l = car.router.head().as_lane();
+        let lane_time = car
+            .crossing_state(Distance::ZERO, now, ctx.map)
+            .get_end_time()
+            - now;
+        let ready = self.links.enter(
+            l,
+            car.vehicle.id,
+            car.vehicle.length,
+            turn_time + lane_time,
+            now,
+        );
+        car.state = CarState::Crossing {
+            time_int: TimeInterval::new(now, ready),
+            dist_int: DistanceInterval::new_driving(Distance::ZERO, ctx.map.get_l(l).length()),
+            steep_uphill: false,
+        };
+        // The leader might already be waiting, so this isn't exactly when the vehicle can leave
+        ctx.scheduler
+            .update(ready, Command::UpdateCar(car.vehicle.id));
+        self.link_cars.insert(car.vehicle.id, car);
+    }
+
+    /// Moves the vehicle past its next turn onto the following lane, without going through any
+    /// queues. Returns how long the turn would've taken.
+    fn advance_without_queues(&mut self, car: &mut Car, now: Time, ctx: &mut Ctx) -> Duration {
+        let mut turn_time = Duration::ZERO;
+        for _ in 0..2 {
+            car.router.advance(
+                now,
+                &car.vehicle,
+                ctx.parking,
+                ctx.map,
+                car.trip_and_person,
+                &mut self.events,
+            );
+            if let Traversable::Turn(_) = car.router.head() {
+                turn_time = car
+                    .crossing_state(Distance::ZERO, now, ctx.map)
+                    .get_end_time()
+                    - now;
+            }
+            self.events.push(Event::AgentEntersTraversable(
+                AgentID::Car(car.vehicle.id),
+                car.trip_and_person.map(|(t, _)| t),
+                car.router.head(),
+                None,
+                car.router.occupancy(),
+            ));
+        }
+        self.read_speed_limit_sign(car, now, ctx.map);
+        turn_time
+    }
+
+    /// A vehicle in a link has reached the end of it, or was woken up by its leader leaving. It
+    /// leaves once it's at the front, the exit capacity and signal allow it, and the next lane has
+    /// room.
+    fn update_car_in_link(&mut self, id: CarID, now: Time, ctx: &mut Ctx) {
+        let mut car = self.link_cars.remove(&id).unwrap();
+        let from = car.router.head().as_lane();
+        let turn = car.router.next().as_turn();
+        if let CarState::Crossing { .. } = car.state {
+            if now >= car.state.get_end_time() {
+                car.state = CarState::WaitingToAdvance { blocked_since: now };
+            }
+        }
+
+        let retry = match self.links.earliest_exit(from, id) {
+            // The leader wakes everybody up
+            None => {
+                self.link_cars.insert(id, car);
+                return;
+            }
+            Some(t) if now < t => Some(t),
+            Some(_) => ctx.intersections.meso_red_until(turn, now, ctx.map),
+        };
+        if let Some(t) = retry {
+            ctx.scheduler.update(t, Command::UpdateCar(id));
+            self.link_cars.insert(id, car);
+            return;
+        }
+
+        // Where does the vehicle go next?
+        let next = Traversable::Lane(turn.dst);
+        let stays_in_links =
+            car.router.get_path().get_steps().len() > 3 && self.is_link(turn.dst, ctx);
+        let idx = if stays_in_links {
+            if !self.links.has_room(
+                turn.dst,
+                car.vehicle.length,
+                self.queues[&next].reserved_length,
+                ctx.map,
+            ) && !self.links.is_stuck(from, now)
+            {
+                None
+            } else {
+                Some(0)
+            }
+        } else if ctx
+            .intersections
+            .nobody_headed_towards(turn.dst, turn.parent)
+        {
+            self.queues[&next].get_idx_to_insert_car(
+                Distance::ZERO,
+                car.vehicle.length,
+                now,
+                &self.cars,
+                &self.queues,
+            )
+        } else {
+            None
+        };
+        let idx = match idx {
+            Some(idx) => idx,
+            None => {
+                ctx.scheduler
+                    .update(now + EXIT_HEADWAY, Command::UpdateCar(id));
+                self.link_cars.insert(id, car);
+                return;
+            }
+        };
+
+        if let CarState::WaitingToAdvance { blocked_since } = car.state {
+            car.total_blocked_time += now - blocked_since;
+        }
+        if let Some(follower) = self.links.leave(from, id, now) {
+            self.wake_link_follower(from, follower, now, ctx);
+        }
+
+        if stays_in_links {
+            self.enter_link(car, now, ctx);
+            return;
+        }
+        // Back to the detailed simulation. Just skip the time the turn would take.
+        self.advance_without_queues(&mut car, now, ctx);
+        car.state = car.crossing_state(Distance::ZERO, now, ctx.map);
+        ctx.scheduler
+            .push(car.state.get_end_time(), Command::UpdateCar(id));
+        self.queues
+            .get_mut(&next)
+            .unwrap()
+            .insert_car_at_idx(idx, &car);
+        self.new_crossing_state(ctx, &car);
+        self.cars.insert(id, car);
+    }
+
+    fn wake_link_follower(&self, l: LaneID, follower: CarID, now: Time, ctx: &mut Ctx) {
+        // If we're going to delete the follower soon, don't bother waking them up.
+        if let Some(ref deleting_agents) = ctx.handling_live_edits {
+            if deleting_agents.contains(&AgentID::Car(follower)) {
+                return;
+            }
+        }
+        let time = self.links.earliest_exit(l, follower).unwrap().max(now);
+        ctx.scheduler.update(time, Command::UpdateCar(follower));
+    }
+}
+
 // Queries
 impl DrivingSimState {
     /// Note the ordering of results is non-deterministic!
@@ -1329,32 +1856,142 @@ impl DrivingSimState {
                         },
                         person: car.trip_and_person.map(|(_, p)| p),
                         parking: car.is_parking(),
+                        // Sim fills this in for transit vehicles
+                        occupancy: car.router.occupancy(),
                     });
                 }
             }
         }
 
+        for (l, c, front) in self.link_positions(now, map) {
+            let car = &self.link_cars[&c];
+            result.push(UnzoomedAgent {
+                id: AgentID::Car(c),
+                pos: map.get_l(l).lane_center_pts.must_dist_along(front).0,
+                person: car.trip_and_person.map(|(_, p)| p),
+                parking: false,
+                occupancy: car.router.occupancy(),
+            });
+        }
+
         for (id, (pos, person)) in &self.waiting_to_spawn {
             result.push(UnzoomedAgent {
                 id: AgentID::Car(*id),
                 pos: pos.pt(map),
                 person: *person,
                 parking: false,
+                occupancy: 1,
             });
         }
 
         result
     }
 
+    /// The current speed of every vehicle moving with traffic along a lane, and where its front is
+    pub fn sample_lane_speeds(&self, now: Time) -> Vec<(LaneID, Distance, Speed)> {
+        let mut results = Vec::new();
+        for queue in self.queues.values() {
+            let l = match queue.id {
+                Traversable::Lane(l) => l,
+                Traversable::Turn(_) => {
+                    continue;
+                }
+            };
+            if queue.get_active_cars().is_empty() {
+                continue;
+            }
+            for entry in queue.get_car_positions(now, &self.cars, &self.queues) {
+                if let Queued::Vehicle(c) = entry.member {
+                    if let Some(speed) = self.cars[&c].state.current_speed() {
+                        results.push((l, entry.front, speed));
+                    }
+                }
+            }
+        }
+        // The queues are in a HashMap
+        results.sort_by_key(|(l, _, _)| *l);
+        results
+    }
+
+    /// For actuated traffic signals, the incoming lanes with a vehicle close enough to the stop
+    /// line to trip the detector. Empty for other intersections.
+    pub fn detect_vehicles(&self, now: Time, i: IntersectionID, map: &Map) -> BTreeSet<LaneID> {
+        let mut detected = BTreeSet::new();
+        let actuation = match map
+            .maybe_get_traffic_signal(i)
+            .and_then(|ts| ts.actuation.as_ref())
+        {
+            Some(x) => x,
+            None => {
+                return detected;
+            }
+        };
+        for l in &map.get_i(i).incoming_lanes {
+            let queue = match self.queues.get(&Traversable::Lane(*l)) {
+                Some(q) => q,
+                None => {
+                    continue;
+                }
+            };
+            // The first entry is the farthest along
+            if let Some(entry) = queue
+                .get_car_positions(now, &self.cars, &self.queues)
+                .into_iter()
+                .find(|entry| matches!(entry.member, Queued::Vehicle(_)))
+            {
+                if entry.front >= queue.geom_len - actuation.detector_length {
+                    detected.insert(*l);
+                }
+            }
+        }
+        detected
+    }
+
+    pub fn has_variable_speed_limit(&self, dr: DirectedRoadID) -> bool {
+        self.speed_limit_signs.has_sign(dr)
+    }
+
+    pub fn get_pickup_dropoff_zone(&self, b: BuildingID) -> Option<&PickupDropoffZone> {
+        self.pickup_dropoff_zones.get(&b)
+    }
+
+    pub fn get_posted_speed_limit(&self, dr: DirectedRoadID) -> Option<Speed> {
+        self.speed_limit_signs.get_posted(dr)
+    }
+
     pub fn does_car_exist(&self, id: CarID) -> bool {
         // Because of the shortcut IndexableKey takes with ignoring the VehicleType part of the ID,
         // we have to double-check that it matches!
         match self.cars.get(&id) {
             Some(car) => car.vehicle.id == id,
-            None => false,
+            None => self.link_cars.contains_key(&id),
         }
     }
 
+    /// Vehicles are either simulated in detail or in a link
+    fn get_car(&self, id: CarID) -> Option<&Car> {
+        match self.cars.get(&id) {
+            Some(car) if car.vehicle.id == id => Some(car),
+            _ => self.link_cars.get(&id),
+        }
+    }
+
+    /// Where the front of every vehicle in the link model appears to be
+    fn link_positions(&self, now: Time, map: &Map) -> Vec<(LaneID, CarID, Distance)> {
+        let lanes: BTreeSet<LaneID> = self
+            .link_cars
+            .values()
+            .map(|car| car.router.head().as_lane())
+            .collect();
+        let mut result = Vec::new();
+        for l in lanes {
+            for (c, front) in self.links.get_positions(l, now, map) {
+                result.push((l, c, front));
+            }
+        }
+        result
+    }
+
     /// Note the ordering of results is non-deterministic!
     pub fn get_all_draw_cars(
         &self,
@@ -1377,6 +2014,9 @@ impl DrivingSimState {
                     }),
             );
         }
+        for (_, c, front) in self.link_positions(now, map) {
+            result.push(self.link_cars[&c].get_draw_car(front, now, map, transit));
+        }
         result
     }
 
@@ -1388,7 +2028,7 @@ impl DrivingSimState {
         map: &Map,
         transit: &TransitSimState,
     ) -> Option<DrawCarInput> {
-        let car = self.cars.get(&id)?;
+        let car = self.get_car(id)?;
         self.get_draw_cars_on(now, car.router.head(), map, transit)
             .into_iter()
             .find(|d| d.id == id)
@@ -1401,7 +2041,7 @@ impl DrivingSimState {
         map: &Map,
         transit: &TransitSimState,
     ) -> Vec<DrawCarInput> {
-        match self.queues.get(&on) {
+        let mut result = match self.queues.get(&on) {
             Some(q) => q
                 .get_car_positions(now, &self.cars, &self.queues)
                 .into_iter()
@@ -1449,11 +2089,17 @@ impl DrivingSimState {
                 })
                 .collect(),
             None => Vec::new(),
+        };
+        if let Traversable::Lane(l) = on {
+            for (c, front) in self.links.get_positions(l, now, map) {
+                result.push(self.link_cars[&c].get_draw_car(front, now, map, transit));
+            }
         }
+        result
     }
 
     pub fn debug_car_json(&self, id: CarID) -> String {
-        if let Some(ref car) = self.cars.get(&id) {
+        if let Some(car) = self.get_car(id) {
             abstutil::to_json(car)
         } else {
             format!("{} is parked somewhere", id)
@@ -1461,7 +2107,7 @@ impl DrivingSimState {
     }
 
     pub fn debug_car_ui(&self, id: CarID) -> String {
-        if let Some(car) = self.cars.get(&id) {
+        if let Some(car) = self.get_car(id) {
             format!("{:?}", car.state)
         } else {
             format!("{} isn't in DrivingSimState", id)
@@ -1475,7 +2121,7 @@ impl DrivingSimState {
     }
 
     pub fn agent_properties(&self, id: CarID, now: Time) -> AgentProperties {
-        if let Some(car) = self.cars.get(&id) {
+        if let Some(car) = self.get_car(id) {
             let path = car.router.get_path();
             let time_spent_waiting = car.state.time_spent_waiting(now);
 
@@ -1519,28 +2165,33 @@ impl DrivingSimState {
     }
 
     pub fn get_path(&self, id: CarID) -> Option<&Path> {
-        let car = self.cars.get(&id)?;
+        let car = self.get_car(id)?;
         Some(car.router.get_path())
     }
     pub fn get_all_driving_paths(&self) -> Vec<&Path> {
         self.cars
             .values()
+            .chain(self.link_cars.values())
             .map(|car| car.router.get_path())
             .collect()
     }
 
     pub fn trace_route(&self, now: Time, id: CarID, map: &Map) -> Option<PolyLine> {
-        let car = self.cars.get(&id)?;
+        let car = self.get_car(id)?;
         let front = self.get_car_front(now, car);
         car.router.get_path().trace_from_start(map, front)
     }
 
     pub fn percent_along_route(&self, id: CarID) -> f64 {
-        self.cars[&id].router.get_path().percent_dist_crossed()
+        self.get_car(id)
+            .unwrap()
+            .router
+            .get_path()
+            .percent_dist_crossed()
     }
 
     pub fn get_owner_of_car(&self, id: CarID) -> Option<PersonID> {
-        let car = self.cars.get(&id)?;
+        let car = self.get_car(id)?;
         car.vehicle.owner
     }
 
@@ -1553,7 +2204,7 @@ impl DrivingSimState {
         spots: BTreeSet<ParkingSpot>,
     ) -> Vec<(AgentID, TripID)> {
         let mut affected = Vec::new();
-        for car in self.cars.values() {
+        for car in self.cars.values().chain(self.link_cars.values()) {
             if let Some(spot) = car.router.get_parking_spot_goal() {
                 if !spots.contains(spot) {
                     // Buses don't park
@@ -1591,7 +2242,7 @@ impl DrivingSimState {
     }
 
     pub fn all_waiting_people(&self, now: Time, delays: &mut BTreeMap<PersonID, Duration>) {
-        for c in self.cars.values() {
+        for c in self.cars.values().chain(self.link_cars.values()) {
             if let Some((_, person)) = c.trip_and_person {
                 let delay = c.state.time_spent_waiting(now);
                 if delay > Duration::ZERO {
@@ -1647,6 +2298,17 @@ impl DrivingSimState {
     }
 
     fn get_car_front(&self, now: Time, car: &Car) -> Distance {
+        if self.link_cars.contains_key(&car.vehicle.id) {
+            // Ignore what's ahead in the link
+            return match car.state {
+                CarState::Crossing {
+                    ref time_int,
+                    ref dist_int,
+                    ..
+                } => dist_int.lerp(time_int.percent_clamp_end(now)),
+                _ => self.queues[&car.router.head()].geom_len,
+            };
+        }
         self.queues[&car.router.head()]
             .get_car_positions(now, &self.cars, &self.queues)
             .into_iter()
@@ -1694,6 +2356,62 @@ impl DrivingSimState {
     }
 }
 
+impl DrivingSimState {
+    /// Only some drivers reconsider their route when stuck. Decide deterministically per driver,
+    /// so that re-running a scenario or loading a savestate doesn't change who reroutes.
+    fn reroutes_when_stuck(&self, car: CarID) -> bool {
+        if self.reroute_fraction <= 0.0 || car.vehicle_type.is_transit() {
+            return false;
+        }
+        // Car IDs are unique just by their number. Hash fixed-width bytes, so web and native agree.
+        let hash = fnv1a(FNV_OFFSET, &(car.id as u64).to_le_bytes());
+        (hash as f64 / u64::MAX as f64) < self.reroute_fraction
+    }
+
+    /// Recounts how long it'd take to get through the vehicles currently queued on each road,
+    /// averaged over its lanes, unless that was done recently
+    fn update_road_delays(&mut self, now: Time, map: &Map) {
+        if self
+            .road_delays_counted_at
+            .map(|t| now - t < ROAD_DELAYS_INTERVAL)
+            .unwrap_or(false)
+        {
+            return;
+        }
+        self.road_delays_counted_at = Some(now);
+
+        // Count in a BTreeMap, so the result doesn't depend on the order of the HashMap
+        let mut queued: BTreeMap<DirectedRoadID, (usize, usize)> = BTreeMap::new();
+        for queue in self.queues.values() {
+            if let Traversable::Lane(l) = queue.id {
+                let entry = queued
+                    .entry(map.get_l(l).get_directed_parent())
+                    .or_insert((0, 0));
+                entry.0 += queue.target_lane_penalty().0;
+                entry.1 += 1;
+            }
+        }
+        self.road_delays = queued
+            .into_iter()
+            .filter(|(_, (vehicles, _))| *vehicles > 0)
+            .map(|(dr, (vehicles, lanes))| {
+                (dr, QUEUED_VEHICLE_DELAY * (vehicles as f64 / lanes as f64))
+            })
+            .collect();
+    }
+}
+
+/// Vehicles approaching an intersection simulated mesoscopically just queue up in whatever lane
+/// their path picked. If they're then heading between two mesoscopic intersections, they enter
+/// the link model.
+fn approaching_mesoscopic(car: &Car, ctx: &Ctx) -> bool {
+    let i = match car.router.head() {
+        Traversable::Lane(l) => ctx.map.get_l(l).dst_i,
+        Traversable::Turn(t) => t.parent,
+    };
+    ctx.intersections.is_mesoscopic(i)
+}
+
 // This implementation relies on the fact that car IDs are unique just by their number. Vehicle
 // type is also in there, but during lookup, it'll be ignored!
 impl IndexableKey for CarID {
@@ -1701,3 +2419,16 @@ impl IndexableKey for CarID {
         self.id
     }
 }
+
+/// Buses and trains ask a traffic signal for priority as they enter the last lane before it.
+fn request_transit_priority(car: &Car, now: Time, ctx: &mut Ctx) {
+    if !car.vehicle.vehicle_type.is_transit() {
+        return;
+    }
+    if let (Traversable::Lane(_), Some(Traversable::Turn(t))) =
+        (car.router.head(), car.router.maybe_next())
+    {
+        ctx.intersections
+            .request_transit_priority(now, car.vehicle.id, t, ctx.map, ctx.scheduler);
+    }
+}
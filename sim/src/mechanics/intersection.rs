@@ -2,21 +2,34 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use serde::{Deserialize, Serialize};
 
-use abstutil::{deserialize_btreemap, prettyprint_usize, serialize_btreemap, FixedMap};
+use abstutil::{
+    deserialize_btreemap, fnv1a, prettyprint_usize, serialize_btreemap, FixedMap, FNV_OFFSET,
+};
 use geom::{Duration, Time};
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, Intersection, IntersectionID, LaneID, Map, StageType,
-    Traversable, TurnID, TurnPriority, TurnType, UberTurn,
+    turn_type_from_angles, Actuation, ControlStopSign, ControlTrafficSignal, Intersection,
+    IntersectionID, LaneID, Map, RoadID, Stage, StageType, TransitPriority, Traversable, TurnID,
+    TurnPriority, TurnType, UberTurn,
 };
 
 use crate::mechanics::car::{Car, CarState};
 use crate::mechanics::Queue;
 use crate::{
-    AgentID, AlertLocation, CarID, Command, DelayCause, Event, Scheduler, SimOptions, Speed,
+    AgentID, AgentType, AlertLocation, CarID, Command, DelayCause, Event, Scheduler, SimOptions,
+    Speed, EXIT_HEADWAY,
 };
 
 const WAIT_AT_STOP_SIGN: Duration = Duration::const_seconds(0.5);
 const WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL: Duration = Duration::const_seconds(0.2);
+// When zipper merging, don't wait on a vehicle from the other lane for longer than this; they might
+// be stuck for some other reason.
+const MAX_WAIT_TO_ZIPPER: Duration = Duration::const_seconds(5.0);
+// Where drivers yield to traffic from the right, a car waiting on every approach would deadlock.
+// Stop yielding after this long.
+const MAX_WAIT_FOR_PRIORITY_TO_THE_RIGHT: Duration = Duration::const_seconds(10.0);
+// Drivers turning across a bike lane stop yielding to cyclists after this long; the cyclist might
+// be stuck for some other reason.
+const MAX_WAIT_FOR_BIKES: Duration = Duration::const_seconds(10.0);
 
 /// Manages conflicts at intersections. When an agent has reached the end of a lane, they call
 /// maybe_start_turn to make a Request. Based on the intersection type (stop sign, traffic signal,
@@ -31,9 +44,17 @@ pub(crate) struct IntersectionSimState {
     state: BTreeMap<IntersectionID, State>,
     use_freeform_policy_everywhere: bool,
     dont_block_the_box: bool,
+    block_the_box_violation_rate: f64,
+    // Per-intersection overrides of dont_block_the_box
+    block_the_box_overrides: BTreeMap<IntersectionID, bool>,
     break_turn_conflict_cycles: bool,
     handle_uber_turns: bool,
     disable_turn_conflicts: bool,
+    zipper_merge: bool,
+    bike_crossing_time: Duration,
+    mesoscopic: bool,
+    // Per-intersection overrides of mesoscopic
+    mesoscopic_overrides: BTreeMap<IntersectionID, bool>,
     // (x, y) means x is blocked by y. It's a many-to-many relationship. TODO Better data
     // structure.
     blocked_by: BTreeSet<(CarID, CarID)>,
@@ -78,6 +99,28 @@ struct State {
     )]
     leader_eta: BTreeMap<LaneID, (Request, Time)>,
 
+    // Where lanes merge, remember which source lane most recently entered each destination lane,
+    // so vehicles from the two lanes can alternate.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    last_merge_src: BTreeMap<LaneID, LaneID>,
+
+    // In the mesoscopic model, when the last vehicle left each lane
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    last_discharge: BTreeMap<LaneID, Time>,
+
+    // Per incoming road, the last cyclist to start going straight across, and when
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    bike_crossings: BTreeMap<RoadID, (TurnID, Time)>,
+
     signal: Option<SignalState>,
 }
 
@@ -89,6 +132,19 @@ struct SignalState {
     stage_ends_at: Time,
     // The number of times a variable signal has been extended during the current stage.
     extensions_count: usize,
+    // The offset of the signal when this state began. If it's edited, the cycle is restarted.
+    offset: Duration,
+    // When the current stage began
+    stage_started_at: Time,
+    // Buses and trains that asked for priority, and the turn each will make. Only signals with
+    // transit priority use this.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    priority_requests: BTreeMap<CarID, TurnID>,
+    // Has the current stage already been held for a bus?
+    priority_extended: bool,
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Debug)]
@@ -104,9 +160,15 @@ impl IntersectionSimState {
             state: BTreeMap::new(),
             use_freeform_policy_everywhere: opts.use_freeform_policy_everywhere,
             dont_block_the_box: !opts.allow_block_the_box,
+            block_the_box_violation_rate: opts.block_the_box_violation_rate,
+            block_the_box_overrides: BTreeMap::new(),
             break_turn_conflict_cycles: !opts.dont_break_turn_conflict_cycles,
             handle_uber_turns: !opts.dont_handle_uber_turns,
             disable_turn_conflicts: opts.disable_turn_conflicts,
+            zipper_merge: !opts.dont_zipper_merge,
+            bike_crossing_time: opts.bike_crossing_time,
+            mesoscopic: opts.mesoscopic,
+            mesoscopic_overrides: BTreeMap::new(),
             blocked_by: BTreeSet::new(),
             events: Vec::new(),
 
@@ -127,6 +189,9 @@ impl IntersectionSimState {
                 uber_turn_neighbors: Vec::new(),
                 signal: None,
                 leader_eta: BTreeMap::new(),
+                last_merge_src: BTreeMap::new(),
+                last_discharge: BTreeMap::new(),
+                bike_crossings: BTreeMap::new(),
             };
             if i.is_traffic_signal() {
                 state.signal = Some(SignalState::new(i.id, Time::START_OF_DAY, map, scheduler));
@@ -196,6 +261,95 @@ impl IntersectionSimState {
     /// turn.
     pub fn vehicle_gone(&mut self, car: CarID) {
         self.blocked_by.retain(|(c1, c2)| *c1 != car && *c2 != car);
+        if car.vehicle_type.is_transit() {
+            for state in self.state.values_mut() {
+                if let Some(ref mut signal_state) = state.signal {
+                    signal_state.priority_requests.remove(&car);
+                }
+            }
+        }
+    }
+
+    /// A bus or train just entered the last lane before a traffic signal, and will make this
+    /// turn. If the signal gives transit priority, cut the current stage short when it doesn't
+    /// serve the bus.
+    pub fn request_transit_priority(
+        &mut self,
+        now: Time,
+        car: CarID,
+        turn: TurnID,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) {
+        let signal = match map.maybe_get_traffic_signal(turn.parent) {
+            Some(signal) => signal,
+            None => {
+                return;
+            }
+        };
+        let priority = match signal.transit_priority {
+            Some(ref priority) => priority,
+            None => {
+                return;
+            }
+        };
+        let signal_state = match self
+            .state
+            .get_mut(&turn.parent)
+            .and_then(|state| state.signal.as_mut())
+        {
+            Some(signal_state) => signal_state,
+            None => {
+                return;
+            }
+        };
+        signal_state.priority_requests.insert(car, turn);
+
+        let i = map.get_i(turn.parent);
+        let stage = &signal.stages[signal_state.current_stage];
+        if stage.get_priority_of_turn(turn, map) != TurnPriority::Banned {
+            // Already green. If it ends before the bus arrives, it'll be held then.
+            return;
+        }
+        let mut end = signal_state.stage_started_at + shortest_stage(stage, priority, i);
+        if end < now {
+            end = now;
+        }
+        if end < signal_state.stage_ends_at {
+            signal_state.stage_ends_at = end;
+            scheduler.update(end, Command::UpdateIntersection(turn.parent));
+        }
+    }
+
+    /// A bus or train started its turn, so it doesn't need priority anymore. If the green was
+    /// being held for it, release the hold.
+    fn transit_priority_served(
+        &mut self,
+        now: Time,
+        car: CarID,
+        i: IntersectionID,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) {
+        let signal_state = match self.state.get_mut(&i).and_then(|s| s.signal.as_mut()) {
+            Some(signal_state) => signal_state,
+            None => {
+                return;
+            }
+        };
+        if signal_state.priority_requests.remove(&car).is_none() {
+            return;
+        }
+        let signal = map.get_traffic_signal(i);
+        let stage = &signal.stages[signal_state.current_stage];
+        let still_needed = signal_state
+            .priority_requests
+            .values()
+            .any(|t| stage.get_priority_of_turn(*t, map) != TurnPriority::Banned);
+        if signal_state.priority_extended && !still_needed && now < signal_state.stage_ends_at {
+            signal_state.stage_ends_at = now;
+            scheduler.update(now, Command::UpdateIntersection(i));
+        }
     }
 
     pub fn agent_deleted_mid_turn(&mut self, agent: AgentID, turn: TurnID) {
@@ -226,7 +380,7 @@ impl IntersectionSimState {
         let mut protected = Vec::new();
         let mut yielding = Vec::new();
 
-        if self.use_freeform_policy_everywhere {
+        if self.use_freeform_policy_everywhere || self.is_mesoscopic(i) {
             for (req, _, _) in all {
                 protected.push(req);
             }
@@ -234,9 +388,8 @@ impl IntersectionSimState {
             let current_stage = self.state[&i].signal.as_ref().unwrap().current_stage;
             let stage = &signal.stages[current_stage];
             let reserved = &self.state[&i].reserved;
-            let i = map.get_i(i);
             for (req, _, _) in all {
-                match stage.get_priority_of_turn(req.turn, i) {
+                match stage.get_priority_of_turn(req.turn, map) {
                     TurnPriority::Protected => {
                         protected.push(req);
                     }
@@ -285,12 +438,15 @@ impl IntersectionSimState {
     }
 
     /// This is only triggered for traffic signals.
+    /// `detected` lists incoming lanes with a vehicle close to the stop line; only actuated
+    /// signals use it.
     pub fn update_intersection(
         &mut self,
         now: Time,
         id: IntersectionID,
         map: &Map,
         scheduler: &mut Scheduler,
+        detected: &BTreeSet<LaneID>,
     ) {
         let i = map.get_i(id);
 
@@ -325,52 +481,96 @@ impl IntersectionSimState {
             }
             false
         });
-        let duration: Duration;
+        let mut duration: Duration;
         // Switch to a new stage?
         assert_eq!(now, signal_state.stage_ends_at);
-        let old_stage = &signal.stages[signal_state.current_stage];
-        match old_stage.stage_type {
-            StageType::Fixed(_) => {
-                duration = advance(signal_state, signal, i, !ped_waiting);
-            }
-            StageType::Variable(min, delay, additional) => {
-                // test if anyone is waiting in current stage, and if so, extend the signal cycle.
-                // Filter out pedestrians, as they've had their chance and the delay
-                // could be short enough to keep them on the curb.
-                let delay = std::cmp::max(Duration::const_seconds(1.0), delay);
-                // Only extend for the fixed additional time
-                if signal_state.extensions_count as f64 * delay.inner_seconds()
-                    >= additional.inner_seconds()
-                {
-                    self.events.push(Event::Alert(
-                        AlertLocation::Intersection(id),
-                        format!(
-                            "exhausted a variable stage {},{},{},{}",
-                            min, delay, additional, signal_state.extensions_count
-                        ),
-                    ));
+        let old_idx = signal_state.current_stage;
+        let old_stage = &signal.stages[old_idx];
+        if let Some(ref actuation) = signal.actuation {
+            let has_demand = |stage: &Stage| {
+                detected.iter().any(|l| {
+                    let dr = map.get_l(*l).get_directed_parent();
+                    stage
+                        .protected_movements
+                        .iter()
+                        .chain(stage.yield_movements.iter())
+                        .any(|m| !m.crosswalk && m.from == dr)
+                }) || state.waiting.keys().any(|req| {
+                    matches!(req.agent, AgentID::Pedestrian(_))
+                        && stage.get_priority_of_turn(req.turn, map) == TurnPriority::Protected
+                })
+            };
+            duration = actuated_step(signal_state, signal, actuation, i, now, has_demand);
+        } else if let Some(priority) = signal.transit_priority.as_ref().filter(|_| {
+            !signal_state.priority_extended
+                && signal_state
+                    .priority_requests
+                    .values()
+                    .any(|t| old_stage.get_priority_of_turn(*t, map) != TurnPriority::Banned)
+        }) {
+            // Hold the green for an approaching bus. The hold ends early once it gets through.
+            signal_state.priority_extended = true;
+            duration = priority.max_extension;
+        } else {
+            match old_stage.stage_type {
+                StageType::Fixed(_) => {
                     duration = advance(signal_state, signal, i, !ped_waiting);
-                    signal_state.extensions_count = 0;
-                } else if state.waiting.keys().all(|req| {
-                    if let AgentID::Pedestrian(_) = req.agent {
-                        return true;
+                }
+                StageType::Variable(min, delay, additional) => {
+                    // test if anyone is waiting in current stage, and if so, extend the signal cycle.
+                    // Filter out pedestrians, as they've had their chance and the delay
+                    // could be short enough to keep them on the curb.
+                    let delay = std::cmp::max(Duration::const_seconds(1.0), delay);
+                    // Only extend for the fixed additional time
+                    if signal_state.extensions_count as f64 * delay.inner_seconds()
+                        >= additional.inner_seconds()
+                    {
+                        self.events.push(Event::Alert(
+                            AlertLocation::Intersection(id),
+                            format!(
+                                "exhausted a variable stage {},{},{},{}",
+                                min, delay, additional, signal_state.extensions_count
+                            ),
+                        ));
+                        duration = advance(signal_state, signal, i, !ped_waiting);
+                        signal_state.extensions_count = 0;
+                    } else if state.waiting.keys().all(|req| {
+                        if let AgentID::Pedestrian(_) = req.agent {
+                            return true;
+                        }
+                        // Should we only allow protected to extend or any not banned?
+                        // currently only the protected demand control extended.
+                        old_stage.get_priority_of_turn(req.turn, map) != TurnPriority::Protected
+                    }) {
+                        signal_state.extensions_count = 0;
+                        duration = advance(signal_state, signal, i, !ped_waiting);
+                    } else {
+                        signal_state.extensions_count += 1;
+                        duration = delay;
+                        self.events.push(Event::Alert(
+                            AlertLocation::Intersection(id),
+                            format!(
+                                "Extending a variable stage {},{},{},{}",
+                                min, delay, additional, signal_state.extensions_count
+                            ),
+                        ));
                     }
-                    // Should we only allow protected to extend or any not banned?
-                    // currently only the protected demand control extended.
-                    old_stage.get_priority_of_turn(req.turn, i) != TurnPriority::Protected
-                }) {
-                    signal_state.extensions_count = 0;
-                    duration = advance(signal_state, signal, i, !ped_waiting);
-                } else {
-                    signal_state.extensions_count += 1;
-                    duration = delay;
-                    self.events.push(Event::Alert(
-                        AlertLocation::Intersection(id),
-                        format!(
-                            "Extending a variable stage {},{},{},{}",
-                            min, delay, additional, signal_state.extensions_count
-                        ),
-                    ));
+                }
+            }
+        }
+        if signal_state.current_stage != old_idx {
+            signal_state.stage_started_at = now;
+            signal_state.priority_extended = false;
+            // Keep cutting stages short until a waiting bus gets its green
+            if let Some(ref priority) = signal.transit_priority {
+                let stage = &signal.stages[signal_state.current_stage];
+                if !signal_state.priority_requests.is_empty()
+                    && signal_state
+                        .priority_requests
+                        .values()
+                        .all(|t| stage.get_priority_of_turn(*t, map) == TurnPriority::Banned)
+                {
+                    duration = duration.min(shortest_stage(stage, priority, i));
                 }
             }
         }
@@ -439,7 +639,12 @@ impl IntersectionSimState {
 
         let shared_sidewalk_corner =
             map.get_t(req.turn).turn_type == TurnType::SharedSidewalkCorner;
+        let mesoscopic = self.is_mesoscopic(turn.parent);
 
+        let emergency = maybe_cars_and_queues
+            .as_ref()
+            .map(|(car, _, _)| car.router.is_responding())
+            .unwrap_or(false);
         let readonly_pair = maybe_cars_and_queues.as_ref().map(|(_, c, q)| (*c, &**q));
         let started_uber_turn = |state: &Self, car: &Car| {
             state.handle_uber_turns && car.router.get_path().currently_inside_ut().is_some()
@@ -448,6 +653,8 @@ impl IntersectionSimState {
         let allowed = if shared_sidewalk_corner {
             // SharedSidewalkCorner doesn't conflict with anything -- fastpath!
             true
+        } else if mesoscopic {
+            self.mesoscopic_policy(&req, now, map, scheduler)
         } else if !self.handle_accepted_conflicts(&req, map, readonly_pair, Some((now, scheduler)))
         {
             // It's never OK to perform a conflicting turn
@@ -469,6 +676,10 @@ impl IntersectionSimState {
                 }
             }
 
+            true
+        } else if emergency {
+            // Emergency vehicles preempt signals and don't wait at stop signs, as long as nothing
+            // conflicting is already in the intersection
             true
         } else if self.use_freeform_policy_everywhere {
             // If we made it this far, we don't conflict with an accepted turn
@@ -480,6 +691,10 @@ impl IntersectionSimState {
         } else {
             unreachable!()
         };
+        let allowed =
+            allowed && (mesoscopic || !self.must_wait_to_zipper(&req, now, map, scheduler));
+        let allowed = allowed
+            && (mesoscopic || emergency || !self.must_yield_to_bikes(&req, now, map, scheduler));
         if !allowed {
             if repeat_request {
                 self.not_allowed_requests += 1;
@@ -503,7 +718,7 @@ impl IntersectionSimState {
         }
 
         // Lock the entire uber-turn.
-        if self.handle_uber_turns {
+        if self.handle_uber_turns && !mesoscopic {
             if let Some(ut) = maybe_cars_and_queues
                 .as_ref()
                 .and_then(|(car, _, _)| car.router.get_path().about_to_start_ut())
@@ -535,13 +750,13 @@ impl IntersectionSimState {
             let inside_ut = self.handle_uber_turns
                 && (car.router.get_path().currently_inside_ut().is_some()
                     || car.router.get_path().about_to_start_ut().is_some());
+            // Everybody pulls aside to make room for emergency vehicles
+            let force_entry = !self.is_dont_block_the_box_enforced(turn.parent, map)
+                || inside_ut
+                || emergency
+                || self.violates_dont_block_the_box(car.vehicle.id, turn.parent);
             let queue = queues.get_mut(&Traversable::Lane(turn.dst)).unwrap();
-            if !queue.try_to_reserve_entry(
-                car,
-                !self.dont_block_the_box
-                    || allow_block_the_box(map.get_i(turn.parent))
-                    || inside_ut,
-            ) {
+            if !queue.try_to_reserve_entry(car, force_entry) {
                 let mut actually_did_reserve_entry = false;
                 if self.break_turn_conflict_cycles {
                     if let Some(c) = queue.laggy_head {
@@ -584,6 +799,18 @@ impl IntersectionSimState {
         // TODO For now, we're only interested in signals, and there's too much raw data to store
         // for stop signs too.
         let state = self.state.get_mut(&turn.parent).unwrap();
+        if self.zipper_merge && matches!(agent, AgentID::Car(_)) && is_merge(map, turn) {
+            state.last_merge_src.insert(turn.dst, turn.src);
+        }
+        if mesoscopic && matches!(agent, AgentID::Car(_)) {
+            state.last_discharge.insert(turn.src, now);
+        }
+        if agent.to_type() == AgentType::Bike
+            && map.get_t(turn).turn_type == TurnType::Straight
+            && map.get_l(turn.src).is_biking()
+        {
+            state.bike_crossings.insert(turn.src.road, (turn, now));
+        }
         state.waiting.remove(&req).unwrap();
         state.accepted.insert(req);
         if self.break_turn_conflict_cycles {
@@ -591,6 +818,11 @@ impl IntersectionSimState {
                 self.blocked_by.retain(|(c, _)| *c != car);
             }
         }
+        if let AgentID::Car(car) = agent {
+            if car.vehicle_type.is_transit() {
+                self.transit_priority_served(now, car, turn.parent, map, scheduler);
+            }
+        }
         true
     }
 
@@ -598,6 +830,18 @@ impl IntersectionSimState {
         std::mem::take(&mut self.events)
     }
 
+    pub fn set_dont_block_the_box(&mut self, i: IntersectionID, enforced: bool) {
+        self.block_the_box_overrides.insert(i, enforced);
+    }
+
+    pub fn reset_dont_block_the_box(&mut self, i: IntersectionID) {
+        self.block_the_box_overrides.remove(&i);
+    }
+
+    pub fn set_mesoscopic(&mut self, i: IntersectionID, mesoscopic: bool) {
+        self.mesoscopic_overrides.insert(i, mesoscopic);
+    }
+
     pub fn handle_live_edited_traffic_signals(
         &mut self,
         now: Time,
@@ -610,7 +854,12 @@ impl IntersectionSimState {
                 state.signal.as_mut(),
             ) {
                 (Some(ts), Some(signal_state)) => {
-                    if signal_state.current_stage >= ts.stages.len() {
+                    if signal_state.offset != ts.offset {
+                        // Coordinated signals must stay in phase with each other, so jump to
+                        // wherever the new offset puts the cycle.
+                        scheduler.cancel(Command::UpdateIntersection(state.id));
+                        *signal_state = SignalState::new(state.id, now, map, scheduler);
+                    } else if signal_state.current_stage >= ts.stages.len() {
                         // Just jump back to the first one. Shrug.
                         signal_state.current_stage = 0;
                         println!(
@@ -693,6 +942,62 @@ impl IntersectionSimState {
             .any(|req| req.turn.dst == lane)
     }
 
+    pub fn is_mesoscopic(&self, i: IntersectionID) -> bool {
+        self.mesoscopic_overrides
+            .get(&i)
+            .cloned()
+            .unwrap_or(self.mesoscopic)
+    }
+
+    /// At a mesoscopic intersection, if the traffic signal doesn't let this turn go now, returns
+    /// when the current stage ends. Yielding turns are allowed.
+    pub fn meso_red_until(&self, turn: TurnID, now: Time, map: &Map) -> Option<Time> {
+        let signal = map.maybe_get_traffic_signal(turn.parent)?;
+        let signal_state = self.state[&turn.parent].signal.as_ref()?;
+        let stage = &signal.stages[signal_state.current_stage];
+        if stage.get_priority_of_turn(turn, map) == TurnPriority::Banned {
+            Some(signal_state.stage_ends_at.max(now + EXIT_HEADWAY))
+        } else {
+            None
+        }
+    }
+
+    /// A vehicle wants to leave the detailed simulation, through a mesoscopic intersection into
+    /// the link model. Returns false if the signal or the exit capacity of its lane make it wait,
+    /// and schedules a retry.
+    pub fn maybe_leave_for_link(
+        &mut self,
+        agent: AgentID,
+        turn: TurnID,
+        now: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        if let Some(retry) = self.meso_red_until(turn, now, map) {
+            scheduler.update(retry, Command::update_agent(agent));
+            return false;
+        }
+        let state = self.state.get_mut(&turn.parent).unwrap();
+        if let Some(last) = state.last_discharge.get(&turn.src) {
+            if now < *last + EXIT_HEADWAY {
+                scheduler.update(*last + EXIT_HEADWAY, Command::update_agent(agent));
+                return false;
+            }
+        }
+        state.last_discharge.insert(turn.src, now);
+        state.leader_eta.remove(&turn.src);
+        // The vehicle may have asked for the turn before
+        self.cancel_request(agent, turn);
+        true
+    }
+
+    pub fn is_dont_block_the_box_enforced(&self, i: IntersectionID, map: &Map) -> bool {
+        if let Some(enforced) = self.block_the_box_overrides.get(&i) {
+            return *enforced;
+        }
+        self.dont_block_the_box && !allow_block_the_box(map.get_i(i))
+    }
+
     pub fn debug_json(&self, id: IntersectionID, map: &Map) -> String {
         let json1 = abstutil::to_json(&self.state[&id]);
         let json2 = if let Some(ref sign) = map.maybe_get_stop_sign(id) {
@@ -842,6 +1147,37 @@ impl IntersectionSimState {
 
 // Stuff to support maybe_start_turn
 impl IntersectionSimState {
+    /// Vehicles simulated in detail on the lanes around a mesoscopic intersection ignore conflicts
+    /// and right-of-way, but still obey the signal, and leave each lane at the same rate as the
+    /// link model lets them. There must be room in the target lane. Pedestrians just go.
+    fn mesoscopic_policy(
+        &mut self,
+        req: &Request,
+        now: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        if !matches!(req.agent, AgentID::Car(_)) {
+            return true;
+        }
+        // Stage changes wake up everybody waiting
+        if self.meso_red_until(req.turn, now, map).is_some() {
+            return false;
+        }
+        if let Some(last) = self.state[&req.turn.parent]
+            .last_discharge
+            .get(&req.turn.src)
+        {
+            let next = *last + EXIT_HEADWAY;
+            if now < next {
+                // A turn finishing may've already woken up this agent
+                scheduler.update(next, Command::update_agent(req.agent));
+                return false;
+            }
+        }
+        true
+    }
+
     fn stop_sign_policy(
         &mut self,
         req: &Request,
@@ -865,6 +1201,19 @@ impl IntersectionSimState {
             return false;
         }
 
+        if our_priority == TurnPriority::Protected
+            && !req.agent.is_pedestrian()
+            && now < our_time + MAX_WAIT_FOR_PRIORITY_TO_THE_RIGHT
+            && map.get_control_defaults().priority_to_the_right
+            && self.traffic_from_the_right(req, map, sign)
+        {
+            scheduler.update(
+                our_time + MAX_WAIT_FOR_PRIORITY_TO_THE_RIGHT,
+                Command::update_agent(req.agent),
+            );
+            return false;
+        }
+
         // Once upon a time, we'd make sure that this request doesn't conflict with another in
         // self.waiting:
         // 1) Higher-ranking turns get to go first.
@@ -915,6 +1264,124 @@ impl IntersectionSimState {
         true
     }
 
+    /// Is a vehicle with the same priority waiting to make a conflicting turn from the road to our
+    /// right?
+    fn traffic_from_the_right(&self, req: &Request, map: &Map, sign: &ControlStopSign) -> bool {
+        let our_turn = map.get_t(req.turn);
+        let our_angle = map.get_l(req.turn.src).last_line().angle();
+        self.state[&req.turn.parent].waiting.keys().any(|other| {
+            // If they're coming from our right, they're heading to our left
+            !other.agent.is_pedestrian()
+                && other.turn.src.road != req.turn.src.road
+                && sign.get_priority(other.turn, map) == TurnPriority::Protected
+                && our_turn.conflicts_with(map.get_t(other.turn))
+                && turn_type_from_angles(our_angle, map.get_l(other.turn.src).last_line().angle())
+                    == TurnType::Left
+        })
+    }
+
+    /// Some drivers ignore "don't block the box". Decide deterministically per driver and
+    /// intersection, so that re-running a scenario or loading a savestate doesn't change who
+    /// violates.
+    fn violates_dont_block_the_box(&self, car: CarID, i: IntersectionID) -> bool {
+        if self.block_the_box_violation_rate <= 0.0 {
+            return false;
+        }
+        // Car IDs are unique just by their number. Hash fixed-width bytes, so web and native agree.
+        let hash = fnv1a(FNV_OFFSET, &(car.id as u64).to_le_bytes());
+        let hash = fnv1a(hash, &(i.0 as u64).to_le_bytes());
+        (hash as f64 / u64::MAX as f64) < self.block_the_box_violation_rate
+    }
+
+    /// Where two lanes from the same road merge into one, vehicles should alternate, instead of
+    /// the lane that happens to reach the merge first getting to go repeatedly. If the previous
+    /// vehicle to merge came from our lane and somebody from another lane is waiting, let them go
+    /// first.
+    fn must_wait_to_zipper(
+        &self,
+        req: &Request,
+        now: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        if !self.zipper_merge || !matches!(req.agent, AgentID::Car(_)) || !is_merge(map, req.turn) {
+            return false;
+        }
+        let state = &self.state[&req.turn.parent];
+        if state.last_merge_src.get(&req.turn.dst) != Some(&req.turn.src) {
+            return false;
+        }
+        let (our_time, _) = state.waiting[req];
+        if now >= our_time + MAX_WAIT_TO_ZIPPER {
+            return false;
+        }
+        let other_lane_waiting = state.waiting.keys().any(|other| {
+            matches!(other.agent, AgentID::Car(_))
+                && other.turn.dst == req.turn.dst
+                && other.turn.src != req.turn.src
+                && other.turn.src.road == req.turn.src.road
+        });
+        if other_lane_waiting {
+            // Normally the other vehicle finishing its turn wakes us up, but in case they're
+            // stuck, retry anyway.
+            scheduler.push(
+                our_time + MAX_WAIT_TO_ZIPPER,
+                Command::update_agent(req.agent),
+            );
+        }
+        other_lane_waiting
+    }
+
+    /// Drivers turning across a bike lane yield to cyclists going straight: to anybody waiting to
+    /// cross, and to anybody who started crossing less than bike_crossing_time ago.
+    fn must_yield_to_bikes(
+        &self,
+        req: &Request,
+        now: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        if req.agent.to_type() != AgentType::Car {
+            return false;
+        }
+        let turn = map.get_t(req.turn);
+        let state = &self.state[&req.turn.parent];
+        let (our_time, _) = state.waiting[req];
+        if now >= our_time + MAX_WAIT_FOR_BIKES {
+            return false;
+        }
+
+        if let Some((bike_turn, started)) = state.bike_crossings.get(&req.turn.src.road) {
+            let clear_at = *started + self.bike_crossing_time;
+            if now < clear_at && turn.hooks_across(map.get_t(*bike_turn), map) {
+                scheduler.push(clear_at, Command::update_agent(req.agent));
+                return true;
+            }
+        }
+
+        let current_stage = map
+            .maybe_get_traffic_signal(state.id)
+            .zip(state.signal.as_ref())
+            .map(|(signal, signal_state)| &signal.stages[signal_state.current_stage]);
+        let bike_waiting = state.waiting.keys().any(|other| {
+            other.agent.to_type() == AgentType::Bike
+                && turn.hooks_across(map.get_t(other.turn), map)
+                // Nobody yields to a cyclist held at a red light
+                && current_stage
+                    .map(|stage| stage.get_priority_of_turn(other.turn, map) != TurnPriority::Banned)
+                    .unwrap_or(true)
+        });
+        if bike_waiting {
+            // Normally the cyclist finishing their turn wakes us up, but in case they're stuck,
+            // retry anyway.
+            scheduler.push(
+                our_time + MAX_WAIT_FOR_BIKES,
+                Command::update_agent(req.agent),
+            );
+        }
+        bike_waiting
+    }
+
     fn traffic_signal_policy(
         &mut self,
         req: &Request,
@@ -934,10 +1401,22 @@ impl IntersectionSimState {
         let (our_time, _) = state.waiting[req];
 
         // Can't go at all this stage.
-        let our_priority = stage.get_priority_of_turn(req.turn, map.get_i(state.id));
+        let i = map.get_i(state.id);
+        let our_priority = stage.get_priority_of_turn(req.turn, map);
         if our_priority == TurnPriority::Banned {
             return false;
         }
+        // Signal timing might have been edited or imported without knowing about local turn on red
+        // restrictions, so enforce them here too
+        if our_priority == TurnPriority::Yield
+            && signal.is_banned_turn_on_red(
+                signal_state.current_stage,
+                i.turn_to_movement(req.turn).0,
+                i,
+            )
+        {
+            return false;
+        }
 
         if our_priority == TurnPriority::Yield
             && now < our_time + WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL
@@ -1109,9 +1588,14 @@ impl SignalState {
             current_stage: 0,
             stage_ends_at: now,
             extensions_count: 0,
+            offset: Duration::ZERO,
+            stage_started_at: now,
+            priority_requests: BTreeMap::new(),
+            priority_extended: false,
         };
 
         let signal = map.get_traffic_signal(id);
+        state.offset = signal.offset;
         // What stage are we starting with?
         let mut offset = (now - Time::START_OF_DAY) + signal.offset;
         loop {
@@ -1126,6 +1610,7 @@ impl SignalState {
                 }
             } else {
                 state.stage_ends_at = now + dt - offset;
+                state.stage_started_at = now - offset;
                 break;
             }
         }
@@ -1134,6 +1619,65 @@ impl SignalState {
     }
 }
 
+/// Decides whether an actuated signal extends the current stage or moves on, and returns how long
+/// until the next decision. `has_demand` says if anybody is detected waiting for a stage.
+fn actuated_step<F: Fn(&Stage) -> bool>(
+    signal_state: &mut SignalState,
+    signal: &ControlTrafficSignal,
+    actuation: &Actuation,
+    i: &Intersection,
+    now: Time,
+    has_demand: F,
+) -> Duration {
+    let current = signal_state.current_stage;
+    let elapsed = now - signal_state.stage_started_at;
+    if elapsed < actuation.min_green {
+        return actuation.min_green - elapsed;
+    }
+    let num_stages = signal.stages.len();
+    // The next stage somebody's waiting for, skipping the ones nobody needs
+    let next = (1..num_stages)
+        .map(|offset| (current + offset) % num_stages)
+        .find(|idx| has_demand(&signal.stages[*idx]));
+
+    let keep_current = match next {
+        // Nobody else is waiting, so rest here
+        None => true,
+        // Gap out if nobody's arriving, or max out
+        Some(_) => has_demand(&signal.stages[current]) && elapsed < actuation.max_green,
+    };
+    if keep_current {
+        let mut dt = actuation.extension;
+        if next.is_some() && elapsed + dt > actuation.max_green {
+            dt = actuation.max_green - elapsed;
+        }
+        return dt.max(Duration::seconds(0.1));
+    }
+
+    signal_state.current_stage = next.unwrap();
+    let stage = &signal.stages[signal_state.current_stage];
+    match stage.max_crosswalk_time(i) {
+        Some(crosswalk) => actuation.min_green.max(crosswalk),
+        None => actuation.min_green,
+    }
+}
+
+/// How short transit priority may cut a stage
+fn shortest_stage(stage: &Stage, priority: &TransitPriority, i: &Intersection) -> Duration {
+    match stage.max_crosswalk_time(i) {
+        Some(crosswalk) => priority.min_green.max(crosswalk),
+        None => priority.min_green,
+    }
+}
+
+/// Do vehicles from multiple lanes of one road use this turn's destination lane? This is how lane
+/// drops are represented.
+fn is_merge(map: &Map, turn: TurnID) -> bool {
+    map.get_turns_to_lane(turn.dst)
+        .into_iter()
+        .any(|t| t.id.src != turn.src && t.id.src.road == turn.src.road)
+}
+
 fn allow_block_the_box(i: &Intersection) -> bool {
     // Degenerate intersections are often just artifacts of how roads are split up in OSM. Allow
     // vehicles to get stuck in them, since the only possible thing they could block is pedestrians
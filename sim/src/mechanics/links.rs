@@ -0,0 +1,194 @@
+//! A queue-based link model, used between intersections simulated with `SimOptions::mesoscopic`
+//! and everywhere by `FastForwardSim`. Vehicles on a lane (a "link") don't follow the vehicle
+//! ahead; they just wait in order. Each vehicle can leave once it's had time to cross the lane at
+//! its free-flow speed, slowed by the BPR volume-delay function of recent traffic, and once it's at
+//! the front. The lane then only lets one vehicle out every few seconds (its exit capacity), only
+//! while the traffic signal allows the movement, and only when the next lane has room for it (its
+//! storage capacity).
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Distance, Duration, Time};
+use map_model::{LaneID, Map, TurnID, TurnPriority};
+
+use crate::{CarID, SlidingWindow, FOLLOWING_DISTANCE};
+
+/// Each lane lets at most one vehicle out this often. This is a saturation flow of 1800 vehicles
+/// per hour per lane.
+pub const EXIT_HEADWAY: Duration = Duration::const_seconds(2.0);
+/// Parameters of the Bureau of Public Roads volume-delay function
+const BPR_ALPHA: f64 = 0.15;
+const BPR_BETA: i32 = 4;
+/// Like MATSim's stuck time: a vehicle that's been waiting at the front this long moves on even
+/// if the next lane is full, so gridlock eventually resolves.
+pub const STUCK_TIME: Duration = Duration::const_seconds(60.0);
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(crate) struct LinkQueues {
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    links: BTreeMap<LaneID, Link>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Link {
+    /// In the order they entered
+    vehicles: VecDeque<LinkVehicle>,
+    /// The sum of each vehicle's length, plus following distance
+    occupied: Distance,
+    /// For the volume-delay function
+    recent_entries: SlidingWindow,
+    last_exit: Option<Time>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct LinkVehicle {
+    car: CarID,
+    length: Distance,
+    entered: Time,
+    /// When the vehicle reaches the end of the lane, if nothing's in the way
+    ready: Time,
+}
+
+impl LinkQueues {
+    pub fn new() -> LinkQueues {
+        LinkQueues::default()
+    }
+
+    /// Is there room for a vehicle this long on the lane? `elsewhere` is space already taken by
+    /// vehicles simulated in detail. An empty lane always has room, so that very short lanes don't
+    /// stop everything.
+    pub fn has_room(&self, l: LaneID, length: Distance, elsewhere: Distance, map: &Map) -> bool {
+        let occupied = self
+            .links
+            .get(&l)
+            .map(|link| link.occupied)
+            .unwrap_or(Distance::ZERO)
+            + elsewhere;
+        occupied == Distance::ZERO
+            || occupied + length + FOLLOWING_DISTANCE <= map.get_l(l).length()
+    }
+
+    /// Returns when the vehicle reaches the end of the lane, if nothing's in the way. `free_flow`
+    /// is how long that takes with no other traffic.
+    pub fn enter(
+        &mut self,
+        l: LaneID,
+        car: CarID,
+        length: Distance,
+        free_flow: Duration,
+        now: Time,
+    ) -> Time {
+        let link = self.links.entry(l).or_insert_with(|| Link {
+            vehicles: VecDeque::new(),
+            occupied: Distance::ZERO,
+            recent_entries: SlidingWindow::new(Duration::hours(1)),
+            last_exit: None,
+        });
+        let volume = link.recent_entries.add(now) as f64;
+        let capacity = Duration::hours(1) / EXIT_HEADWAY;
+        let ready = now + free_flow * (1.0 + BPR_ALPHA * (volume / capacity).powi(BPR_BETA));
+        link.occupied += length + FOLLOWING_DISTANCE;
+        link.vehicles.push_back(LinkVehicle {
+            car,
+            length,
+            entered: now,
+            ready,
+        });
+        ready
+    }
+
+    /// None if somebody's ahead of the vehicle; they'll leave first. Otherwise, the earliest the
+    /// vehicle can leave, given the exit capacity.
+    pub fn earliest_exit(&self, l: LaneID, car: CarID) -> Option<Time> {
+        let link = &self.links[&l];
+        let front = link.vehicles.front()?;
+        if front.car != car {
+            return None;
+        }
+        Some(match link.last_exit {
+            Some(t) => front.ready.max(t + EXIT_HEADWAY),
+            None => front.ready,
+        })
+    }
+
+    /// Has the vehicle at the front waited long enough to go, even if the next lane is full?
+    pub fn is_stuck(&self, l: LaneID, now: Time) -> bool {
+        self.links[&l]
+            .vehicles
+            .front()
+            .map(|v| now - v.ready >= STUCK_TIME)
+            .unwrap_or(false)
+    }
+
+    /// Removes a vehicle from the lane. It's usually at the front, but might be deleted abruptly
+    /// from anywhere. Returns the vehicle now at the front, which may be able to leave soon.
+    pub fn leave(&mut self, l: LaneID, car: CarID, now: Time) -> Option<CarID> {
+        let link = self.links.get_mut(&l).unwrap();
+        let idx = link.vehicles.iter().position(|v| v.car == car).unwrap();
+        let vehicle = link.vehicles.remove(idx).unwrap();
+        link.occupied -= vehicle.length + FOLLOWING_DISTANCE;
+        if idx == 0 {
+            link.last_exit = Some(now);
+            link.vehicles.front().map(|v| v.car)
+        } else {
+            None
+        }
+    }
+
+    /// Where the front of each vehicle on the lane appears to be: as far as it's had time to
+    /// travel, but behind everybody ahead.
+    pub fn get_positions(&self, l: LaneID, now: Time, map: &Map) -> Vec<(CarID, Distance)> {
+        let link = match self.links.get(&l) {
+            Some(link) => link,
+            None => {
+                return Vec::new();
+            }
+        };
+        let lane_len = map.get_l(l).length();
+        let mut limit = lane_len;
+        let mut result = Vec::new();
+        for v in &link.vehicles {
+            let progress = if now >= v.ready {
+                1.0
+            } else {
+                (now - v.entered) / (v.ready - v.entered)
+            };
+            let front = (lane_len * progress).min(limit).max(Distance::ZERO);
+            result.push((v.car, front));
+            limit = front - v.length - FOLLOWING_DISTANCE;
+        }
+        result
+    }
+}
+
+/// If a fixed-time traffic signal doesn't let this turn go now, returns when its current stage
+/// ends. Variable stages are treated as lasting their minimum duration.
+pub fn fixed_time_red_until(map: &Map, turn: TurnID, now: Time) -> Option<Time> {
+    let signal = map.maybe_get_traffic_signal(turn.parent)?;
+    let cycle = signal.stages.iter().fold(Duration::ZERO, |sum, stage| {
+        sum + stage.stage_type.simple_duration()
+    });
+    if cycle == Duration::ZERO {
+        return None;
+    }
+    let since_start = now - Time::START_OF_DAY - signal.offset;
+    let cycle_secs = cycle.inner_seconds();
+    let mut into_cycle = since_start.inner_seconds().rem_euclid(cycle_secs);
+    for stage in &signal.stages {
+        let duration = stage.stage_type.simple_duration().inner_seconds();
+        if into_cycle < duration {
+            if stage.get_priority_of_turn(turn, map) == TurnPriority::Banned {
+                return Some(now + Duration::seconds(duration - into_cycle));
+            }
+            return None;
+        }
+        into_cycle -= duration;
+    }
+    None
+}
@@ -1,12 +1,21 @@
 pub(crate) use self::driving::DrivingSimState;
 pub(crate) use self::intersection::IntersectionSimState;
-pub(crate) use self::parking::{ParkingSim, ParkingSimState};
+pub(crate) use self::links::{fixed_time_red_until, LinkQueues, EXIT_HEADWAY};
+pub(crate) use self::parking::{
+    parking_price, ParkingSim, ParkingSimState, WALKING_DISTANCE_PER_PARKING_PRICE,
+};
+pub use self::pudo::PickupDropoffZone;
 pub(crate) use self::queue::Queue;
+pub use self::speed_limits::VariableSpeedLimits;
+pub use self::walking::crowded_walking_speed;
 pub(crate) use self::walking::WalkingSimState;
 
 mod car;
 mod driving;
 mod intersection;
+mod links;
 mod parking;
+mod pudo;
 mod queue;
+mod speed_limits;
 mod walking;
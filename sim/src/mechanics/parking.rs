@@ -9,7 +9,7 @@ use abstutil::{
     deserialize_btreemap, deserialize_multimap, serialize_btreemap, serialize_multimap, MultiMap,
     Timer,
 };
-use geom::{Distance, PolyLine, Pt2D};
+use geom::{Distance, Duration, PolyLine, Pt2D};
 use map_model::{
     BuildingID, Lane, LaneID, LaneType, Map, OffstreetParking, ParkingLotID, PathConstraints,
     PathStep, Position, Traversable, TurnID,
@@ -17,6 +17,11 @@ use map_model::{
 
 use crate::{CarID, CarStatus, DrawCarInput, Event, ParkedCar, ParkingSpot, PersonID, Vehicle};
 
+/// Drivers would walk this much farther to save one unit of money on parking
+pub const WALKING_DISTANCE_PER_PARKING_PRICE: Distance = Distance::const_meters(100.0);
+/// Somebody not leaving again pays for a whole day of parking
+const STAY_FOR_REST_OF_SIMULATION: Duration = Duration::const_seconds(24.0 * 3600.0);
+
 /// Manages the state of parked cars. There are two implementations:
 /// - NormalParkingSimState allows only one vehicle per ParkingSpot defined in the map
 /// - InfiniteParkingSimState pretends every building has infinite capacity, and onstreet parking is
@@ -64,12 +69,15 @@ pub trait ParkingSim {
     /// them there, producing some nice, realistic churn if there's too much contention. But
     /// the implementation has some internal jitter between different vehicles, to discourage
     /// everybody near one spot from all competing for it.
+    /// Spots with a time limit shorter than the `stay` are skipped, and the price of the rest is
+    /// weighed against how far away they are, like `parking_price` describes.
     /// Note the first PathStep is the turn after start, NOT PathStep::Lane(start).
     fn path_to_free_parking_spot(
         &self,
         start: LaneID,
         vehicle: &Vehicle,
         target: BuildingID,
+        stay: Option<Duration>,
         map: &Map,
     ) -> Option<(Vec<PathStep>, ParkingSpot, Position)>;
     fn collect_events(&mut self) -> Vec<Event>;
@@ -211,6 +219,25 @@ impl NormalParkingSimState {
 
         sim
     }
+
+    /// The best free spot along a lane that allows a stay this long, weighing its price against
+    /// how far along the lane it is. Spots closest to the start of the lane are closest to where
+    /// the driver came from.
+    fn cheapest_free_spot(
+        &self,
+        l: LaneID,
+        vehicle: &Vehicle,
+        target: BuildingID,
+        stay: Option<Duration>,
+        map: &Map,
+    ) -> Option<(ParkingSpot, Position, f64)> {
+        self.get_all_free_spots(Position::start(l), vehicle, target, map)
+            .into_iter()
+            .filter_map(|(spot, pos)| Some((spot, pos, parking_price(map, spot, stay)?)))
+            .min_by_key(|(_, pos, price)| {
+                pos.dist_along() + *price * WALKING_DISTANCE_PER_PARKING_PRICE
+            })
+    }
 }
 
 impl ParkingSim for NormalParkingSimState {
@@ -563,14 +590,16 @@ impl ParkingSim for NormalParkingSimState {
         start: LaneID,
         vehicle: &Vehicle,
         target: BuildingID,
+        stay: Option<Duration>,
         map: &Map,
     ) -> Option<(Vec<PathStep>, ParkingSpot, Position)> {
         let mut backrefs: HashMap<LaneID, TurnID> = HashMap::new();
         // Don't travel far.
         // This is a max-heap, so negate all distances. Tie breaker is lane ID, arbitrary but
-        // deterministic.
-        let mut queue: BinaryHeap<(Distance, LaneID)> = BinaryHeap::new();
-        queue.push((Distance::ZERO, start));
+        // deterministic. The flag means the lane is known to have a spot, and the cost includes
+        // its price; otherwise, the lane still needs to be checked.
+        let mut queue: BinaryHeap<(Distance, LaneID, bool)> = BinaryHeap::new();
+        queue.push((Distance::ZERO, start, false));
 
         // We need a source of randomness between different cars, but it needs to be deterministic
         // across repeated runs of the exact same simulation. This also shouldn't be the same
@@ -580,17 +609,23 @@ impl ParkingSim for NormalParkingSimState {
             XorShiftRng::seed_from_u64((vehicle.id.id + start.encode_u32() as usize) as u64);
 
         while !queue.is_empty() {
-            let (dist_so_far, current) = queue.pop().unwrap();
+            let (dist_so_far, current, checked) = queue.pop().unwrap();
             // If the current lane has a spot open, we wouldn't be asking. This can happen if a spot
             // opens up on the 'start' lane, but behind the car.
             if current != start {
-                // Pick the closest to the start of the lane, since that's closest to where we came
-                // from
-                if let Some((spot, pos)) = self
-                    .get_all_free_spots(Position::start(current), vehicle, target, map)
-                    .into_iter()
-                    .min_by_key(|(_, pos)| pos.dist_along())
+                if let Some((spot, pos, price)) =
+                    self.cheapest_free_spot(current, vehicle, target, stay, map)
                 {
+                    if !checked {
+                        // There might be a cheaper spot a little farther away
+                        queue.push((
+                            dist_so_far - price * WALKING_DISTANCE_PER_PARKING_PRICE,
+                            current,
+                            true,
+                        ));
+                        continue;
+                    }
+
                     let mut steps = vec![PathStep::Lane(current)];
                     let mut current = current;
                     loop {
@@ -608,6 +643,9 @@ impl ParkingSim for NormalParkingSimState {
                 }
             }
             for turn in map.get_turns_for(current, PathConstraints::Car) {
+                if blocked_by_modal_filter(map, start, turn.id) {
+                    continue;
+                }
                 if let Entry::Vacant(e) = backrefs.entry(turn.id.dst) {
                     let dist_this_step = turn.geom.length() + map.get_l(current).length();
                     // When vehicles search away from the first lane for a spot, don't all go in
@@ -620,7 +658,7 @@ impl ParkingSim for NormalParkingSimState {
                     let jitter = rng.gen_range(0.1..0.9);
                     e.insert(turn.id);
                     // Remember, keep things negative
-                    queue.push((dist_so_far - jitter * dist_this_step, turn.id.dst));
+                    queue.push((dist_so_far - jitter * dist_this_step, turn.id.dst, false));
                 }
             }
         }
@@ -690,11 +728,14 @@ impl ParkingLane {
             return None;
         };
 
+        // Loading zones take over the spots at the end of the lane
+        let num_spots = lane.number_parking_spots(map.get_config())
+            - map.get_loading_zones().spots_taken_from(map, lane.id);
         Some(ParkingLane {
             parking_lane: lane.id,
             driving_lane,
             sidewalk,
-            spot_dist_along: (0..lane.number_parking_spots(map.get_config()))
+            spot_dist_along: (0..num_spots)
                 .map(|idx| map.get_config().street_parking_spot_length * (2.0 + idx as f64))
                 .collect(),
         })
@@ -989,6 +1030,7 @@ impl ParkingSim for InfiniteParkingSimState {
         start: LaneID,
         vehicle: &Vehicle,
         target: BuildingID,
+        _: Option<Duration>,
         map: &Map,
     ) -> Option<(Vec<PathStep>, ParkingSpot, Position)> {
         // TODO This impl is copied from NormalParkingSimState. Instead, we already know the
@@ -1029,6 +1071,9 @@ impl ParkingSim for InfiniteParkingSimState {
                 }
             }
             for turn in map.get_turns_for(current, PathConstraints::Car) {
+                if blocked_by_modal_filter(map, start, turn.id) {
+                    continue;
+                }
                 if let Entry::Vacant(e) = backrefs.entry(turn.id.dst) {
                     let dist_this_step = turn.geom.length() + map.get_l(current).length();
                     e.insert(turn.id);
@@ -1070,3 +1115,45 @@ impl ParkingSim for InfiniteParkingSimState {
         cars
     }
 }
+
+/// Cars cruising for parking can't drive through modal filters. They can still leave the lane they
+/// start on.
+fn blocked_by_modal_filter(map: &Map, start: LaneID, turn: TurnID) -> bool {
+    if turn.src != start {
+        if let Some(ref filter) = map.get_r(turn.src.road).modal_filter {
+            if !filter.filter_type.allows(PathConstraints::Car) {
+                return true;
+            }
+        }
+    }
+    if let Some(ref filter) = map.get_i(turn.parent).modal_filter {
+        if !filter.filter_type.allows(PathConstraints::Car)
+            && !filter.allows_turn(turn.src.road, turn.dst.road)
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// What parking in a spot costs, or None if the stay breaks the time limit. A stay of None means
+/// the rest of the simulation.
+pub fn parking_price(map: &Map, spot: ParkingSpot, stay: Option<Duration>) -> Option<f64> {
+    let pricing = map.get_parking_pricing();
+    let policy = match spot {
+        ParkingSpot::Onstreet(l, _) => pricing.blockfaces.get(&l.road),
+        ParkingSpot::Lot(pl, _) => pricing.lots.get(&pl),
+        ParkingSpot::Offstreet(_, _) => None,
+    };
+    match (policy, stay) {
+        (None, _) => Some(0.0),
+        (Some(policy), Some(stay)) => policy.cost_of_stay(stay),
+        (Some(policy), None) => {
+            if policy.max_stay.is_some() {
+                None
+            } else {
+                policy.cost_of_stay(STAY_FOR_REST_OF_SIMULATION)
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Pickup and dropoff (PUDO) at busy destinations, like schools at bell time or stations. Instead
+//! of parking, cars arriving there stop along the curb in front of the building to let their
+//! passenger out. Once the curb is full, more cars queue in the lane behind it, blocking through
+//! traffic.
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration};
+use map_model::BuildingID;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PickupDropoffZone {
+    pub building: BuildingID,
+    /// How much of the driving lane in front of the building cars can stop along. Several cars
+    /// can let passengers out at once if this is long enough.
+    pub curb_length: Distance,
+    /// How long each car stops
+    pub dwell_time: Duration,
+}
+
+impl PickupDropoffZone {
+    /// Room for about 3 cars, each stopping for under a minute
+    pub fn new(building: BuildingID) -> PickupDropoffZone {
+        PickupDropoffZone {
+            building,
+            curb_length: Distance::meters(20.0),
+            dwell_time: Duration::seconds(45.0),
+        }
+    }
+}
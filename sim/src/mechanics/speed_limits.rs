@@ -0,0 +1,149 @@
+//! Variable speed limit signs lower the posted limit along a corridor when traffic downstream gets
+//! dense, like on smart motorways. Vehicles approach a queue more gradually, instead of racing up
+//! to it and braking.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Duration, Speed, Time};
+use map_model::{DirectedRoadID, Map, Traversable};
+
+use crate::mechanics::Queue;
+
+/// Signs along a corridor, all following the same policy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VariableSpeedLimits {
+    /// In order of travel. Each sign reacts to the density on the next road; the last sign reacts
+    /// to the density on its own road.
+    pub corridor: Vec<DirectedRoadID>,
+    /// Sorted by increasing density. Once the number of vehicles per kilometer per lane downstream
+    /// reaches the density, the sign posts the limit. Below the first density, the road's normal
+    /// limit applies.
+    pub thresholds: Vec<(f64, Speed)>,
+    /// How often the signs can change
+    pub update_interval: Duration,
+}
+
+impl VariableSpeedLimits {
+    /// A typical policy for an arterial, stepping down to 30, 25, and 20 mph as traffic builds
+    pub fn default_policy(corridor: Vec<DirectedRoadID>) -> VariableSpeedLimits {
+        VariableSpeedLimits {
+            corridor,
+            thresholds: vec![
+                (20.0, Speed::miles_per_hour(30.0)),
+                (35.0, Speed::miles_per_hour(25.0)),
+                (50.0, Speed::miles_per_hour(20.0)),
+            ],
+            update_interval: Duration::minutes(1),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Sign {
+    downstream: DirectedRoadID,
+    thresholds: Vec<(f64, Speed)>,
+    update_interval: Duration,
+    /// None means the normal limit
+    posted: Option<Speed>,
+    last_update: Option<Time>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct SpeedLimitSigns {
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    signs: BTreeMap<DirectedRoadID, Sign>,
+}
+
+impl SpeedLimitSigns {
+    pub fn new() -> SpeedLimitSigns {
+        SpeedLimitSigns {
+            signs: BTreeMap::new(),
+        }
+    }
+
+    /// Replaces any existing signs on the same roads
+    pub fn add(&mut self, limits: VariableSpeedLimits) {
+        for (idx, dr) in limits.corridor.iter().enumerate() {
+            self.signs.insert(
+                *dr,
+                Sign {
+                    downstream: limits.corridor.get(idx + 1).cloned().unwrap_or(*dr),
+                    thresholds: limits.thresholds.clone(),
+                    update_interval: limits.update_interval,
+                    posted: None,
+                    last_update: None,
+                },
+            );
+        }
+    }
+
+    pub fn remove(&mut self, dr: DirectedRoadID) {
+        self.signs.remove(&dr);
+    }
+
+    pub fn has_sign(&self, dr: DirectedRoadID) -> bool {
+        self.signs.contains_key(&dr)
+    }
+
+    /// The limit currently shown, or None if there's no sign or it shows the normal limit
+    pub fn get_posted(&self, dr: DirectedRoadID) -> Option<Speed> {
+        self.signs.get(&dr).and_then(|sign| sign.posted)
+    }
+
+    /// A vehicle is entering a road. Update its sign if it's time, and return the posted limit and
+    /// whether it just changed.
+    pub fn vehicle_entering(
+        &mut self,
+        now: Time,
+        dr: DirectedRoadID,
+        queues: &HashMap<Traversable, Queue>,
+        map: &Map,
+    ) -> (Option<Speed>, bool) {
+        let sign = match self.signs.get_mut(&dr) {
+            Some(sign) => sign,
+            None => {
+                return (None, false);
+            }
+        };
+        if let Some(t) = sign.last_update {
+            if now - t < sign.update_interval {
+                return (sign.posted, false);
+            }
+        }
+        sign.last_update = Some(now);
+
+        let density = density(sign.downstream, queues, map);
+        let posted = sign
+            .thresholds
+            .iter()
+            .rev()
+            .find(|(threshold, _)| density >= *threshold)
+            .map(|(_, speed)| *speed);
+        let changed = posted != sign.posted;
+        sign.posted = posted;
+        (posted, changed)
+    }
+}
+
+/// Vehicles per kilometer per lane
+fn density(dr: DirectedRoadID, queues: &HashMap<Traversable, Queue>, map: &Map) -> f64 {
+    let road = map.get_r(dr.road);
+    let mut vehicles = 0;
+    let mut lanes = 0;
+    for l in road.lanes.iter().filter(|l| l.dir == dr.dir) {
+        if let Some(queue) = queues.get(&Traversable::Lane(l.id)) {
+            vehicles += queue.get_active_cars().len();
+            lanes += 1;
+        }
+    }
+    if lanes == 0 {
+        return 0.0;
+    }
+    (vehicles as f64) / (road.length().inner_meters() / 1000.0 * (lanes as f64))
+}
@@ -5,8 +5,8 @@ use serde::{Deserialize, Serialize};
 use abstutil::{deserialize_multimap, serialize_multimap, FixedMap, IndexableKey, MultiMap};
 use geom::{Distance, Duration, Line, PolyLine, Speed, Time};
 use map_model::{
-    BuildingID, DrivingSide, IntersectionID, Map, ParkingLotID, Path, PathConstraints, PathStep,
-    RoadID, TransitRouteID, Traversable,
+    BuildingID, DrivingSide, FerryRouteID, IntersectionID, Map, ParkingLotID, Path,
+    PathConstraints, PathStep, RoadID, TransitRouteID, Traversable,
 };
 
 use crate::sim::Ctx;
@@ -193,6 +193,23 @@ impl WalkingSimState {
                                 self.peds.remove(&id);
                             }
                         }
+                        SidewalkPOI::FerryTerminal(terminal) => {
+                            if let Some((route, depart)) = trips.ped_reached_ferry_terminal(
+                                now,
+                                ped.id,
+                                terminal,
+                                ped.total_blocked_time,
+                                ped.path.total_length(),
+                                ctx,
+                            ) {
+                                ped.state = PedState::WaitingForFerry(route, now);
+                                ctx.scheduler.push(depart, Command::UpdatePed(ped.id));
+                            } else {
+                                self.peds_per_traversable
+                                    .remove(ped.path.current_step().as_traversable(), ped.id);
+                                self.peds.remove(&id);
+                            }
+                        }
                         SidewalkPOI::Border(i) => {
                             self.peds_per_traversable
                                 .remove(ped.path.current_step().as_traversable(), ped.id);
@@ -346,6 +363,18 @@ impl WalkingSimState {
                     .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
             }
             PedState::WaitingForBus(_, _) => unreachable!(),
+            PedState::WaitingForFerry(_, blocked_since) => {
+                // The ferry is leaving
+                let blocked_time = now - blocked_since;
+                if let Some(next) = trips.ped_boarding_ferry(now, id, blocked_time, ctx) {
+                    // It's full
+                    ctx.scheduler.push(next, Command::UpdatePed(id));
+                    return;
+                }
+                self.peds_per_traversable
+                    .remove(ped.path.current_step().as_traversable(), ped.id);
+                self.peds.remove(&id);
+            }
         }
     }
 
@@ -413,7 +442,8 @@ impl WalkingSimState {
             | PedState::EnteringBuilding(_, _)
             | PedState::EnteringParkingLot(_, _)
             | PedState::StartingToBike(_, _, _)
-            | PedState::WaitingForBus(_, _) => {
+            | PedState::WaitingForBus(_, _)
+            | PedState::WaitingForFerry(_, _) => {
                 p.path.dist_crossed_from_step(map, &p.path.current_step())
             }
         };
@@ -453,6 +483,7 @@ impl WalkingSimState {
                 pos: ped.get_draw_ped(now, map).pos,
                 person: Some(ped.person),
                 parking: false,
+                occupancy: 1,
             });
         }
 
@@ -508,7 +539,8 @@ impl WalkingSimState {
                 }
                 PedState::StartingToBike(_, _, _)
                 | PedState::FinishingBiking(_, _, _)
-                | PedState::WaitingForBus(_, _) => {
+                | PedState::WaitingForBus(_, _)
+                | PedState::WaitingForFerry(_, _) => {
                     // The backwards half of the sidewalk is closer to the road.
                     backwards.push((*id, dist));
                 }
@@ -602,7 +634,7 @@ impl WalkingSimState {
                 SidewalkPOI::ParkingSpot(_) | SidewalkPOI::DeferredParkingSpot => {
                     cnts.walking_to_from_car += 1;
                 }
-                SidewalkPOI::TransitStop(_) => {
+                SidewalkPOI::TransitStop(_) | SidewalkPOI::FerryTerminal(_) => {
                     cnts.walking_to_from_transit += 1;
                 }
                 SidewalkPOI::BikeRack(_) => {
@@ -612,7 +644,7 @@ impl WalkingSimState {
                     SidewalkPOI::ParkingSpot(_) | SidewalkPOI::DeferredParkingSpot => {
                         cnts.walking_to_from_car += 1;
                     }
-                    SidewalkPOI::TransitStop(_) => {
+                    SidewalkPOI::TransitStop(_) | SidewalkPOI::FerryTerminal(_) => {
                         cnts.walking_to_from_transit += 1;
                     }
                     SidewalkPOI::BikeRack(_) => {
@@ -698,7 +730,7 @@ impl Pedestrian {
             self.path.current_step().as_traversable(),
             peds_per_traversable,
         );
-        if speed_penalty != 1.0 {
+        if speed_penalty < crowded_walking_speed(CROWDED_DENSITY) {
             events.push(Event::ProblemEncountered(
                 self.trip,
                 Problem::PedestrianOvercrowding(self.path.current_step().as_traversable()),
@@ -738,7 +770,9 @@ impl Pedestrian {
             }
             PedState::StartingToBike(ref spot, _, _) => spot.sidewalk_pos.dist_along(),
             PedState::FinishingBiking(ref spot, _, _) => spot.sidewalk_pos.dist_along(),
-            PedState::WaitingForBus(_, _) => self.goal.sidewalk_pos.dist_along(),
+            PedState::WaitingForBus(_, _) | PedState::WaitingForFerry(_, _) => {
+                self.goal.sidewalk_pos.dist_along()
+            }
         }
     }
 
@@ -843,7 +877,7 @@ impl Pedestrian {
                     .unwrap_or_else(|_| line.pt1()),
                 line.angle(),
             ),
-            PedState::WaitingForBus(_, _) => {
+            PedState::WaitingForBus(_, _) | PedState::WaitingForFerry(_, _) => {
                 let (pt, angle) = self.goal.sidewalk_pos.pt_and_angle(map);
                 // Stand on the far side of the sidewalk (by the bus stop), facing the road
                 (
@@ -915,6 +949,7 @@ impl Pedestrian {
             Some(self.trip),
             self.path.current_step().as_traversable(),
             None,
+            1,
         ));
         true
     }
@@ -936,6 +971,8 @@ enum PedState {
     StartingToBike(SidewalkSpot, Line, TimeInterval),
     FinishingBiking(SidewalkSpot, Line, TimeInterval),
     WaitingForBus(TransitRouteID, Time),
+    /// The Time is blocked_since. The pedestrian is woken up when the ferry departs.
+    WaitingForFerry(FerryRouteID, Time),
 }
 
 impl PedState {
@@ -949,14 +986,15 @@ impl PedState {
             PedState::EnteringParkingLot(_, ref time_int) => time_int.end,
             PedState::StartingToBike(_, _, ref time_int) => time_int.end,
             PedState::FinishingBiking(_, _, ref time_int) => time_int.end,
-            PedState::WaitingForBus(_, _) => unreachable!(),
+            PedState::WaitingForBus(_, _) | PedState::WaitingForFerry(_, _) => unreachable!(),
         }
     }
 
     fn time_spent_waiting(&self, now: Time) -> Duration {
         match self {
             PedState::WaitingToTurn(_, blocked_since)
-            | PedState::WaitingForBus(_, blocked_since) => now - *blocked_since,
+            | PedState::WaitingForBus(_, blocked_since)
+            | PedState::WaitingForFerry(_, blocked_since) => now - *blocked_since,
             _ => Duration::ZERO,
         }
     }
@@ -1013,6 +1051,27 @@ impl IndexableKey for PedestrianID {
     }
 }
 
+/// People per square meter where nobody can move anymore
+const JAM_DENSITY: f64 = 5.4;
+/// Based on eyeballing images from https://www.gkstill.com/Support/crowd-density/CrowdDensity-1.html,
+/// past this many people per square meter, a sidewalk counts as overcrowded.
+const CROWDED_DENSITY: f64 = 1.5;
+/// Even in a jammed crowd, people shuffle forwards eventually. This also keeps a full sidewalk
+/// from trapping somebody forever.
+const MIN_SPEED_FACTOR: f64 = 0.05;
+
+/// Returns a number in (0, 1] to multiply walking speed by, given the density of people on a
+/// sidewalk or crosswalk. This is Weidmann's speed-density relationship: people walk freely below
+/// about half a person per square meter, slow down to about 60% of their speed at 1.5, and barely
+/// move approaching the jam density.
+pub fn crowded_walking_speed(people_per_sq_m: f64) -> f64 {
+    if people_per_sq_m <= 0.0 {
+        return 1.0;
+    }
+    let factor = 1.0 - (-1.913 * (1.0 / people_per_sq_m - 1.0 / JAM_DENSITY)).exp();
+    factor.max(MIN_SPEED_FACTOR).min(1.0)
+}
+
 /// Returns a number in (0, 1] to multiply speed by to account for current crowdedness.
 ///
 /// We could get really fancy here and slow people down only when they're part of a crowd, or
@@ -1024,17 +1083,10 @@ fn crowdedness_penalty(
     traversable: Traversable,
     peds_per_traversable: &MultiMap<Traversable, PedestrianID>,
 ) -> f64 {
+    // Don't count the person entering; on short crosswalks, one person alone would look crowded
     let num_people = peds_per_traversable.get(traversable).len();
     // Assume everyone's equally spread out
-    let people_per_sq_m = (num_people as f64) / area(map, traversable);
-    // Based on eyeballing images from
-    // https://www.gkstill.com/Support/crowd-density/CrowdDensity-1.html, let's use a fixed
-    // threshold of 1.5 people per square meter as "crowded" and slow them down by half.
-    if people_per_sq_m < 1.5 {
-        // Plenty of room, no penalty
-        return 1.0;
-    }
-    0.5
+    crowded_walking_speed((num_people as f64) / area(map, traversable))
 }
 
 // In m^2
@@ -1050,3 +1102,25 @@ fn area(map: &Map, traversable: Traversable) -> f64 {
     };
     width.inner_meters() * len.inner_meters()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crowded_walking_speed() {
+        assert_eq!(crowded_walking_speed(0.0), 1.0);
+        assert!(crowded_walking_speed(0.3) > 0.99);
+        assert!((crowded_walking_speed(CROWDED_DENSITY) - 0.6).abs() < 0.01);
+        assert_eq!(crowded_walking_speed(JAM_DENSITY), MIN_SPEED_FACTOR);
+        assert_eq!(crowded_walking_speed(10.0), MIN_SPEED_FACTOR);
+
+        // Speed never goes up as the crowd grows
+        let mut last = 1.0;
+        for step in 1..100 {
+            let speed = crowded_walking_speed(0.1 * (step as f64));
+            assert!(speed <= last);
+            last = speed;
+        }
+    }
+}
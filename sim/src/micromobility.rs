@@ -0,0 +1,156 @@
+//! Shared bikes and e-scooters wait on the sidewalk outside buildings. When a trip starts, it
+//! reserves the closest one, walks there, rides it, and leaves it outside the destination -- or,
+//! for vehicles from a docked system, at the station with a free dock closest to the destination.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::Distance;
+use map_model::{BuildingID, Map};
+use synthpop::MicromobilityStation;
+
+use crate::{CarID, Vehicle};
+
+/// Nobody walks further than this, as the crow flies, to pick up a vehicle
+const MAX_WALK_TO_VEHICLE: Distance = Distance::const_meters(800.0);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct MicromobilityFleet {
+    /// How many vehicles fit at each station with docks
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    docks: BTreeMap<BuildingID, usize>,
+    /// Vehicles nobody's reserved, and where they are
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    available: BTreeMap<BuildingID, Vec<Vehicle>>,
+    /// Vehicles that have to be returned to a dock
+    docked_vehicles: BTreeSet<CarID>,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    rentals: BTreeMap<CarID, Rental>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Rental {
+    vehicle: Vehicle,
+    from: BuildingID,
+    to: BuildingID,
+    picked_up: bool,
+}
+
+impl MicromobilityFleet {
+    pub fn new() -> MicromobilityFleet {
+        MicromobilityFleet {
+            docks: BTreeMap::new(),
+            available: BTreeMap::new(),
+            docked_vehicles: BTreeSet::new(),
+            rentals: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_vehicle(&mut self, vehicle: Vehicle, station: &MicromobilityStation) {
+        if let Some(docks) = station.docks {
+            self.docks.insert(station.building, docks);
+            self.docked_vehicles.insert(vehicle.id);
+        }
+        self.available
+            .entry(station.building)
+            .or_insert_with(Vec::new)
+            .push(vehicle);
+    }
+
+    /// Reserves the vehicle closest to the start, and decides where it'll be left. Returns the
+    /// vehicle, where to pick it up, and where to leave it.
+    pub fn rent(
+        &mut self,
+        start: BuildingID,
+        goal: BuildingID,
+        map: &Map,
+    ) -> Option<(CarID, BuildingID, BuildingID)> {
+        let start_pt = map.get_b(start).label_center;
+        let (from, _) = self
+            .available
+            .iter()
+            .filter(|(_, vehicles)| !vehicles.is_empty())
+            .map(|(b, _)| (*b, map.get_b(*b).label_center.dist_to(start_pt)))
+            .filter(|(_, dist)| *dist <= MAX_WALK_TO_VEHICLE)
+            .min_by_key(|(_, dist)| *dist)?;
+        let vehicle = self.available.get_mut(&from).unwrap().pop().unwrap();
+        let to = if self.docked_vehicles.contains(&vehicle.id) {
+            self.closest_free_dock(goal, map).unwrap_or(goal)
+        } else {
+            goal
+        };
+        let car = vehicle.id;
+        self.rentals.insert(
+            car,
+            Rental {
+                vehicle,
+                from,
+                to,
+                picked_up: false,
+            },
+        );
+        Some((car, from, to))
+    }
+
+    /// Counting vehicles already on the way there
+    fn closest_free_dock(&self, goal: BuildingID, map: &Map) -> Option<BuildingID> {
+        let goal_pt = map.get_b(goal).label_center;
+        self.docks
+            .iter()
+            .filter(|(b, capacity)| {
+                let parked = self.available.get(b).map(|v| v.len()).unwrap_or(0);
+                let incoming = self.rentals.values().filter(|r| r.to == **b).count();
+                parked + incoming < **capacity
+            })
+            .min_by_key(|(b, _)| map.get_b(**b).label_center.dist_to(goal_pt))
+            .map(|(b, _)| *b)
+    }
+
+    pub fn is_rented(&self, car: CarID) -> bool {
+        self.rentals.contains_key(&car)
+    }
+
+    /// The rider reached the vehicle and starts riding. Returns where they picked it up.
+    pub fn pick_up(&mut self, car: CarID) -> (Vehicle, BuildingID) {
+        let rental = self.rentals.get_mut(&car).unwrap();
+        rental.picked_up = true;
+        (rental.vehicle.clone(), rental.from)
+    }
+
+    /// Returns where the vehicle was left
+    pub fn drop_off(&mut self, car: CarID) -> BuildingID {
+        let rental = self.rentals.remove(&car).unwrap();
+        self.available
+            .entry(rental.to)
+            .or_insert_with(Vec::new)
+            .push(rental.vehicle);
+        rental.to
+    }
+
+    /// The trip was cancelled. A vehicle nobody picked up yet stays where it was; otherwise it's
+    /// warped to where the rider was going to leave it.
+    pub fn cancel_rental(&mut self, car: CarID) {
+        if let Some(rental) = self.rentals.remove(&car) {
+            let at = if rental.picked_up {
+                rental.to
+            } else {
+                rental.from
+            };
+            self.available
+                .entry(at)
+                .or_insert_with(Vec::new)
+                .push(rental.vehicle);
+        }
+    }
+}
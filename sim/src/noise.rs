@@ -0,0 +1,240 @@
+//! A rough estimate of road traffic noise, loosely following the shape of CNOSSOS-EU. Each vehicle
+//! makes rolling and propulsion noise depending on its speed, and traffic along a road acts like a
+//! line of sources, as loud as the hourly volume and the simulated speed on that road. Sound then
+//! spreads out hemispherically to buildings nearby. There's no ground or air absorption and
+//! buildings don't shield each other, so this is for comparing proposals against each other, not
+//! for an official noise map.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, FindClosest, Time};
+use map_model::{BuildingID, Map, RoadID};
+
+use crate::{AgentType, Analytics};
+
+// Noise along a road is emitted from points this far apart
+const SAMPLE_SPACING: Distance = Distance::const_meters(10.0);
+// Ignore roads further than this from a building
+const MAX_DISTANCE: Distance = Distance::const_meters(200.0);
+// Buildings right next to a road still have some distance to the middle of the traffic
+const MIN_DISTANCE: Distance = Distance::const_meters(5.0);
+// The coefficients are only meaningful above this
+const MIN_KMPH: f64 = 20.0;
+/// Roughly how quiet a building with no road nearby is, in dB(A)
+pub const BACKGROUND_DB: f64 = 35.0;
+
+/// How loud one kind of vehicle is, in dB(A) of sound power. Rolling noise grows with the
+/// logarithm of speed, and propulsion noise linearly, relative to 70km/h.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VehicleNoise {
+    pub rolling_db: f64,
+    pub rolling_per_log_speed: f64,
+    pub propulsion_db: f64,
+    pub propulsion_per_speed: f64,
+}
+
+impl VehicleNoise {
+    /// The sound power of one vehicle at some speed
+    pub fn sound_power(&self, kmph: f64) -> f64 {
+        let v = kmph.max(MIN_KMPH);
+        let rolling = self.rolling_db + self.rolling_per_log_speed * (v / 70.0).log10();
+        let propulsion = self.propulsion_db + self.propulsion_per_speed * (v - 70.0) / 70.0;
+        energetic_sum(vec![rolling, propulsion])
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NoiseModel {
+    pub car: VehicleNoise,
+    /// Buses are the only heavy vehicles simulated. Trains are ignored.
+    pub bus: VehicleNoise,
+}
+
+impl NoiseModel {
+    /// Roughly an average car and a diesel bus on ordinary asphalt
+    pub fn default_model() -> NoiseModel {
+        NoiseModel {
+            car: VehicleNoise {
+                rolling_db: 95.0,
+                rolling_per_log_speed: 30.0,
+                propulsion_db: 88.0,
+                propulsion_per_speed: 4.0,
+            },
+            bus: VehicleNoise {
+                rolling_db: 100.0,
+                rolling_per_log_speed: 30.0,
+                propulsion_db: 101.0,
+                propulsion_per_speed: 3.0,
+            },
+        }
+    }
+
+    /// The average speed cars drove along each road. Unlike volumes, this covers every trip
+    /// recorded so far, regardless of time.
+    pub fn road_speeds(analytics: &Analytics) -> BTreeMap<RoadID, f64> {
+        let mut totals: BTreeMap<RoadID, (Distance, Duration)> = BTreeMap::new();
+        for ((r, _), driving) in &analytics.road_driving {
+            let entry = totals.entry(*r).or_insert((Distance::ZERO, Duration::ZERO));
+            entry.0 += driving.distance;
+            entry.1 += driving.time;
+        }
+        totals
+            .into_iter()
+            .filter(|(_, (_, dt))| *dt > Duration::ZERO)
+            .map(|(r, (dist, dt))| {
+                (
+                    r,
+                    (dist.inner_meters() / 1000.0) / (dt.inner_seconds() / 3600.0),
+                )
+            })
+            .collect()
+    }
+
+    /// The sound power per meter of each road, in dB(A), averaged over every hour since midnight.
+    /// Roads without traffic are omitted.
+    pub fn road_emissions(
+        &self,
+        map: &Map,
+        analytics: &Analytics,
+        now: Time,
+    ) -> BTreeMap<RoadID, f64> {
+        let hours = ((now - Time::START_OF_DAY).inner_seconds() / 3600.0).max(1.0);
+        let speeds = NoiseModel::road_speeds(analytics);
+
+        // The energy per meter, summed over vehicle types
+        let mut energy: BTreeMap<RoadID, f64> = BTreeMap::new();
+        for ((r, agent_type, hour), count) in &analytics.road_thruput.counts {
+            if *hour > now.get_hours() {
+                continue;
+            }
+            let vehicle = match agent_type {
+                AgentType::Car => &self.car,
+                AgentType::Bus => &self.bus,
+                AgentType::Train
+                | AgentType::Bike
+                | AgentType::Pedestrian
+                | AgentType::TransitRider => {
+                    continue;
+                }
+            };
+            let road = map.get_r(*r);
+            let speed_limit_kmph = road.speed_limit.inner_meters_per_second() * 3.6;
+            let kmph = speeds
+                .get(r)
+                .cloned()
+                .unwrap_or(speed_limit_kmph)
+                .min(speed_limit_kmph)
+                .max(MIN_KMPH);
+            // On average, this many vehicles are on each meter of the road at once
+            let per_meter = (*count as f64) / hours / (1000.0 * kmph);
+            *energy.entry(*r).or_insert(0.0) += per_meter * to_energy(vehicle.sound_power(kmph));
+        }
+        energy
+            .into_iter()
+            .filter(|(_, e)| *e > 0.0)
+            .map(|(r, e)| (r, to_db(e)))
+            .collect()
+    }
+
+    /// How loud it is right beside a road, 10 meters from the traffic, in dB(A)
+    pub fn level_beside_road(emission_per_meter: f64) -> f64 {
+        // An infinitely long line source, radiating into a half-space
+        emission_per_meter - 10.0 * (std::f64::consts::PI * 10.0).log10()
+    }
+
+    /// Propagate road noise to the facade of buildings nearby, in dB(A). Buildings far from any
+    /// traffic are omitted.
+    pub fn building_exposure(
+        &self,
+        map: &Map,
+        emissions: &BTreeMap<RoadID, f64>,
+    ) -> BTreeMap<BuildingID, f64> {
+        let mut closest = FindClosest::new();
+        for b in map.all_buildings() {
+            closest.add_polygon(b.id, &b.polygon);
+        }
+
+        let mut energy: BTreeMap<BuildingID, f64> = BTreeMap::new();
+        for (r, emission_per_meter) in emissions {
+            let road = map.get_r(*r);
+            let num_samples = (road.length() / SAMPLE_SPACING).ceil().max(1.0);
+            let sample_length = road.length().inner_meters() / num_samples;
+            let sample_energy = to_energy(*emission_per_meter) * sample_length;
+            for idx in 0..(num_samples as usize) {
+                let dist = road.length() * ((idx as f64 + 0.5) / num_samples);
+                let pt = match road.center_pts.dist_along(dist) {
+                    Ok((pt, _)) => pt,
+                    Err(_) => continue,
+                };
+                for (b, _, d) in closest.all_close_pts(pt, MAX_DISTANCE) {
+                    let d = d.max(MIN_DISTANCE).inner_meters();
+                    *energy.entry(b).or_insert(0.0) +=
+                        sample_energy / (2.0 * std::f64::consts::PI * d * d);
+                }
+            }
+        }
+        energy
+            .into_iter()
+            .map(|(b, e)| (b, to_db(e + to_energy(BACKGROUND_DB))))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseSummary {
+    /// The noise level at home, averaged over every resident
+    pub mean_per_resident: f64,
+    pub residents: usize,
+    /// How many people live where it's louder than 55 dB(A), where annoyance starts
+    pub residents_above_55: usize,
+    /// How many people live where it's louder than 65 dB(A), where health effects start
+    pub residents_above_65: usize,
+}
+
+impl NoiseSummary {
+    pub fn new(map: &Map, exposure: &BTreeMap<BuildingID, f64>) -> NoiseSummary {
+        let mut residents = 0;
+        let mut total = 0.0;
+        let mut residents_above_55 = 0;
+        let mut residents_above_65 = 0;
+        for b in map.all_buildings() {
+            let n = b.bldg_type.num_residents();
+            if n == 0 {
+                continue;
+            }
+            let level = exposure.get(&b.id).cloned().unwrap_or(BACKGROUND_DB);
+            residents += n;
+            total += (n as f64) * level;
+            if level > 55.0 {
+                residents_above_55 += n;
+            }
+            if level > 65.0 {
+                residents_above_65 += n;
+            }
+        }
+        NoiseSummary {
+            mean_per_resident: if residents == 0 {
+                0.0
+            } else {
+                total / (residents as f64)
+            },
+            residents,
+            residents_above_55,
+            residents_above_65,
+        }
+    }
+}
+
+fn to_energy(db: f64) -> f64 {
+    10.0_f64.powf(db / 10.0)
+}
+
+fn to_db(energy: f64) -> f64 {
+    10.0 * energy.log10()
+}
+
+fn energetic_sum(levels: Vec<f64>) -> f64 {
+    to_db(levels.into_iter().map(to_energy).sum())
+}
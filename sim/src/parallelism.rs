@@ -0,0 +1,168 @@
+//! Estimates how much running the simulation in parallel could gain. Nothing here actually runs
+//! in parallel; the sim is still a single discrete-event loop. Every agent shares one `Scheduler`,
+//! and the driving, walking, and intersection states are each one structure for the whole map.
+//! Running the parts of a `Partitioning` concurrently means splitting all of those per part and
+//! exchanging agents crossing boundaries, like `BoundaryHandoff` describes, while keeping the
+//! order of simultaneous events fixed so results stay deterministic. Vehicles also check for room
+//! on the next lane before they leave one, so a part would need to know the state of its
+//! neighbors' boundary lanes, not just receive agents from them. That's a big change to every
+//! mechanic, so for now, this measures whether a conservative parallel scheduler would be worth
+//! it on a real scenario.
+//!
+//! Under conservative synchronization, each part can safely run ahead of the others for the
+//! "lookahead" -- the shortest time anything could take to cross a boundary road. Parts process
+//! one window at a time in parallel, then exchange agents crossing boundaries. If boundary roads
+//! are short or the work is concentrated in one part, there's little to gain.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use geom::{Duration, Time};
+use map_model::{Map, Partitioning, Traversable};
+
+use crate::{AgentID, CustomMetric, Event};
+
+/// Measures how a scenario's work would be spread across the parts of the map, using events as a
+/// proxy for work. Register it with `Sim::add_custom_metric` before running.
+///
+/// In each lookahead window, the parts would run in parallel, so the window takes as long as its
+/// busiest part. Agents crossing a boundary have to be exchanged afterwards, which is counted as
+/// serial work. The estimated speedup is all of the work over that critical path. It's an upper
+/// bound; it ignores the cost of synchronizing at all, which is large for short windows.
+#[derive(Clone)]
+pub struct ParallelismEstimate {
+    partitioning: Arc<Partitioning>,
+    lookahead: Option<Duration>,
+    current_window: usize,
+    /// Events per part in the current window
+    current_counts: Vec<usize>,
+    current_crossings: usize,
+    /// The last part each agent was seen in
+    last_part: BTreeMap<AgentID, usize>,
+
+    total_events: usize,
+    total_crossings: usize,
+    critical_path: usize,
+}
+
+impl ParallelismEstimate {
+    pub fn new(map: &Map, partitioning: Arc<Partitioning>) -> ParallelismEstimate {
+        ParallelismEstimate {
+            lookahead: ParallelismEstimate::lookahead(map, &partitioning),
+            current_window: 0,
+            current_counts: vec![0; partitioning.num_parts],
+            current_crossings: 0,
+            last_part: BTreeMap::new(),
+            total_events: 0,
+            total_crossings: 0,
+            critical_path: 0,
+            partitioning,
+        }
+    }
+
+    /// The fastest anything could cross a boundary road, at the speed limit. None if there's only
+    /// one part.
+    pub fn lookahead(map: &Map, partitioning: &Partitioning) -> Option<Duration> {
+        partitioning
+            .boundary_roads
+            .iter()
+            .map(|r| {
+                let road = map.get_r(*r);
+                road.length() / road.speed_limit
+            })
+            .min()
+    }
+
+    fn window(&self, time: Time) -> usize {
+        match self.lookahead {
+            Some(dt) if dt > Duration::ZERO => {
+                ((time - Time::START_OF_DAY) / dt).floor().max(0.0) as usize
+            }
+            // With only one part, or a boundary crossed instantly, there's nothing to gain
+            _ => 0,
+        }
+    }
+
+    fn finish_window(&mut self) {
+        self.critical_path +=
+            self.current_counts.iter().max().cloned().unwrap_or(0) + self.current_crossings;
+        self.current_counts.iter_mut().for_each(|x| *x = 0);
+        self.current_crossings = 0;
+    }
+}
+
+impl CustomMetric for ParallelismEstimate {
+    fn name(&self) -> String {
+        format!("parallelism across {} parts", self.partitioning.num_parts)
+    }
+
+    fn event(&mut self, ev: &Event, time: Time, map: &Map) {
+        // A lane belongs to the part where it starts, so agents cross into another part when
+        // they reach the end of a boundary road and enter the next intersection. Events that
+        // can't be placed somewhere are ignored.
+        let (part, agent) = match ev {
+            Event::AgentEntersTraversable(a, _, Traversable::Lane(l), _, _) => {
+                (self.partitioning.part_of(map.get_l(*l).src_i), Some(*a))
+            }
+            Event::AgentEntersTraversable(a, _, Traversable::Turn(t), _, _) => {
+                (self.partitioning.part_of(t.parent), Some(*a))
+            }
+            Event::IntersectionDelayMeasured(_, t, _, _) => {
+                (self.partitioning.part_of(t.parent), None)
+            }
+            Event::PersonEntersBuilding(_, b) | Event::PersonLeavesBuilding(_, b) => (
+                self.partitioning
+                    .part_of(map.get_l(map.get_b(*b).sidewalk()).src_i),
+                None,
+            ),
+            Event::PersonEntersMap(_, _, i) | Event::PersonLeavesMap(_, _, i) => {
+                (self.partitioning.part_of(*i), None)
+            }
+            _ => {
+                return;
+            }
+        };
+
+        let window = self.window(time);
+        if window != self.current_window {
+            self.finish_window();
+            self.current_window = window;
+        }
+        self.current_counts[part] += 1;
+        self.total_events += 1;
+
+        if let Some(agent) = agent {
+            if let Some(prev) = self.last_part.insert(agent, part) {
+                if prev != part {
+                    self.current_crossings += 1;
+                    self.total_crossings += 1;
+                }
+            }
+        }
+    }
+
+    fn values(&self) -> Vec<(String, f64)> {
+        let critical_path = self.critical_path
+            + self.current_counts.iter().max().cloned().unwrap_or(0)
+            + self.current_crossings;
+        vec![
+            ("events".to_string(), self.total_events as f64),
+            (
+                "boundary crossings".to_string(),
+                self.total_crossings as f64,
+            ),
+            (
+                "estimated speedup".to_string(),
+                if critical_path == 0 {
+                    1.0
+                } else {
+                    (self.total_events as f64) / (critical_path as f64)
+                },
+            ),
+        ]
+    }
+
+    fn clone_box(&self) -> Box<dyn CustomMetric> {
+        Box::new(self.clone())
+    }
+}
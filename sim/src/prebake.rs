@@ -1,6 +1,6 @@
 use serde::Serialize;
 
-use crate::{AlertHandler, Sim, SimFlags, SimOptions};
+use crate::{AlertHandler, Analytics, Sim, SimFlags, SimOptions};
 use abstutil::{prettyprint_usize, Timer};
 use geom::{Duration, Time};
 use map_model::Map;
@@ -64,11 +64,16 @@ pub struct PrebakeSummary {
 
 impl PrebakeSummary {
     pub fn new(sim: &Sim, scenario: &Scenario) -> Self {
+        Self::from_analytics(sim.get_analytics(), scenario)
+    }
+
+    /// For results that didn't come from a normal `Sim`, like a `FastForwardSim`
+    pub fn from_analytics(analytics: &Analytics, scenario: &Scenario) -> Self {
         let mut finished_trips = 0;
         let mut cancelled_trips = 0;
         // Use f64 seconds, since a serialized Duration has a low cap.
         let mut total_trip_duration_seconds = 0.0;
-        for (_, _, _, maybe_duration) in &sim.get_analytics().finished_trips {
+        for (_, _, _, maybe_duration) in &analytics.finished_trips {
             if let Some(dt) = maybe_duration {
                 finished_trips += 1;
                 total_trip_duration_seconds += dt.inner_seconds();
@@ -28,7 +28,7 @@ impl TrafficRecorder {
     }
 
     pub fn handle_event(&mut self, time: Time, ev: &Event, map: &Map, driving: &DrivingSimState) {
-        if let Event::AgentEntersTraversable(AgentID::Car(car), Some(trip), on, _) = ev {
+        if let Event::AgentEntersTraversable(AgentID::Car(car), Some(trip), on, _, _) = ev {
             self.on_car_enters_traversable(time, *car, *trip, *on, map, driving);
         }
     }
@@ -110,6 +110,9 @@ impl TrafficRecorder {
                 })
                 .collect::<Vec<_>>(),
             only_seed_buses: None,
+            micromobility: Vec::new(),
+            park_and_ride: BTreeSet::new(),
+            ebike_share: 0.0,
         }
         .save();
     }
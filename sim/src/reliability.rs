@@ -0,0 +1,172 @@
+//! How reliable travel times are, not just how long they take on average. One simulation run is
+//! one possible day; small changes to when people leave or where they park can make the same trip
+//! much slower on another day. This runs a scenario several times with different random seeds and
+//! slightly shifted departure times, then summarizes the spread of travel times per origin and
+//! destination, and per road.
+//!
+//! The usual measures are the 95th percentile travel time, the planning time index (95th
+//! percentile over the free-flow time; how much time to budget to arrive on time 19 days out of
+//! 20), and the buffer time index (the extra time over the average needed to arrive on time).
+
+use std::collections::BTreeMap;
+
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+use abstio::MapName;
+use abstutil::Timer;
+use geom::{Duration, Time};
+use map_model::{Map, RoadID};
+use synthpop::{Scenario, ScenarioModifier, TripEndpoint, TripMode};
+
+use crate::{AgentType, AlertHandler, Sim, SimFlags, SimOptions};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Reliability {
+    pub map_name: MapName,
+    pub scenario_name: String,
+    pub edits_name: String,
+    pub runs: usize,
+    /// Trips grouped by where they start and end, and how
+    pub od_pairs: BTreeMap<(TripEndpoint, TripEndpoint, TripMode), ReliabilityStats>,
+    /// Each sample is the average time for vehicles to drive along the road during one 15 minute
+    /// bin of one run. Only cars count, since buses stop along the way and bikes have a different
+    /// top speed.
+    pub roads: BTreeMap<RoadID, ReliabilityStats>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReliabilityStats {
+    pub samples: Vec<Duration>,
+    /// The best-case time without any traffic or intersection delays, if it could be calculated
+    pub free_flow: Option<Duration>,
+}
+
+impl Reliability {
+    /// Runs the scenario `runs` times. Each run uses a different RNG seed, and shifts each
+    /// person's departures by up to `jitter` earlier or later. The map should already have any
+    /// edits applied.
+    pub fn measure(
+        map: &Map,
+        scenario: &Scenario,
+        runs: usize,
+        jitter: Duration,
+        timer: &mut Timer,
+    ) -> Reliability {
+        let mut result = Reliability {
+            map_name: map.get_name().clone(),
+            scenario_name: scenario.scenario_name.clone(),
+            edits_name: map.get_edits().edits_name.clone(),
+            runs,
+            od_pairs: BTreeMap::new(),
+            roads: BTreeMap::new(),
+        };
+
+        for run in 0..runs {
+            timer.start(format!("reliability run {}/{}", run + 1, runs));
+            let seed = SimFlags::RNG_SEED + (run as u64);
+            let mut rng = XorShiftRng::seed_from_u64(seed);
+            // The first run is the same as prebaking, so it matches the usual results
+            let shifted = if run == 0 {
+                scenario.clone()
+            } else {
+                ScenarioModifier::DepartureNoise(jitter).apply(map, scenario.clone(), &mut rng)
+            };
+
+            let mut opts = SimOptions::new("reliability");
+            opts.alerts = AlertHandler::Silence;
+            let mut sim = Sim::new(map, opts);
+            sim.instantiate(&shifted, map, &mut rng, timer);
+            sim.timed_step(
+                map,
+                sim.get_end_of_day() - Time::START_OF_DAY + Duration::hours(3),
+                &mut None,
+                timer,
+            );
+            result.record_run(&sim, map);
+            timer.stop(format!("reliability run {}/{}", run + 1, runs));
+        }
+        result
+    }
+
+    fn record_run(&mut self, sim: &Sim, map: &Map) {
+        for (_, id, mode, maybe_dt) in &sim.get_analytics().finished_trips {
+            let dt = match maybe_dt {
+                Some(dt) => *dt,
+                None => {
+                    continue;
+                }
+            };
+            let info = sim.trip_info(*id);
+            self.od_pairs
+                .entry((info.start, info.end, *mode))
+                .or_insert_with(|| ReliabilityStats {
+                    samples: Vec::new(),
+                    // Only calculated the first time this OD pair is seen, since it needs
+                    // pathfinding
+                    free_flow: sim.get_trip_time_lower_bound(map, *id).ok(),
+                })
+                .samples
+                .push(dt);
+        }
+
+        for ((r, agent_type, _), stats) in &sim.get_analytics().link_stats {
+            if *agent_type != AgentType::Car || stats.traversals == 0 {
+                continue;
+            }
+            let n = stats.traversals as f64;
+            self.roads
+                .entry(*r)
+                .or_insert_with(|| ReliabilityStats {
+                    samples: Vec::new(),
+                    free_flow: Some(Duration::seconds(stats.free_flow_time.inner_seconds() / n)),
+                })
+                .samples
+                .push(Duration::seconds(stats.travel_time.inner_seconds() / n));
+        }
+    }
+}
+
+impl ReliabilityStats {
+    pub fn mean(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        let total: f64 = self.samples.iter().map(|dt| dt.inner_seconds()).sum();
+        Duration::seconds(total / (self.samples.len() as f64))
+    }
+
+    /// Uses the nearest-rank method. `pct` is from 0 to 100.
+    pub fn percentile(&self, pct: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        let rank = ((pct / 100.0) * (sorted.len() as f64)).ceil() as usize;
+        sorted[rank.max(1).min(sorted.len()) - 1]
+    }
+
+    pub fn p95(&self) -> Duration {
+        self.percentile(95.0)
+    }
+
+    /// The 95th percentile time over the free-flow time
+    pub fn planning_time_index(&self) -> Option<f64> {
+        let free_flow = self.free_flow?;
+        if free_flow == Duration::ZERO || self.samples.is_empty() {
+            return None;
+        }
+        Some(self.p95() / free_flow)
+    }
+
+    /// The extra time over the average to budget for, as a fraction of the average
+    pub fn buffer_time_index(&self) -> Option<f64> {
+        let mean = self.mean();
+        if mean == Duration::ZERO {
+            return None;
+        }
+        Some((self.p95() - mean) / mean)
+    }
+}
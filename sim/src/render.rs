@@ -72,4 +72,7 @@ pub struct UnzoomedAgent {
     /// True only for cars currently looking for parking. I don't want this struct to grow, but
     /// this is important enough to call out here.
     pub parking: bool,
+    /// How many people the agent carries: a car's occupancy, a transit vehicle's passengers, or
+    /// just 1 for anybody else
+    pub occupancy: usize,
 }
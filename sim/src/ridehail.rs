@@ -0,0 +1,102 @@
+//! Ridehail vehicles get dispatched to pick somebody up, drive them to their destination, and then
+//! wait wherever they dropped them off for the next request. Driving empty to a pickup is
+//! deadheading. Both ends of the ride happen at the curb, like a pickup/dropoff zone, so busy
+//! destinations can congest.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::Pt2D;
+use map_model::{Map, Position};
+
+use crate::{CarID, TripID, Vehicle};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RidehailFleet {
+    size: usize,
+    /// The vehicles aren't created until the first request, so simulations without any ridehail
+    /// trips number their cars the same way as before.
+    created: bool,
+    /// Vehicles waiting for a request, off the road wherever they last stopped
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    idle: BTreeMap<CarID, (Vehicle, Position)>,
+    /// Vehicles on the way to pick somebody up, or carrying them
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    assigned: BTreeMap<CarID, TripID>,
+    /// Requests nobody's been sent to yet, oldest first
+    waiting: VecDeque<TripID>,
+}
+
+impl RidehailFleet {
+    pub fn new(size: usize) -> RidehailFleet {
+        RidehailFleet {
+            size,
+            created: false,
+            idle: BTreeMap::new(),
+            assigned: BTreeMap::new(),
+            waiting: VecDeque::new(),
+        }
+    }
+
+    /// How many vehicles to create, if that hasn't happened yet. Only returns something once.
+    pub fn size_to_create(&mut self) -> Option<usize> {
+        if self.created {
+            return None;
+        }
+        self.created = true;
+        Some(self.size)
+    }
+
+    pub fn owns(&self, car: CarID) -> bool {
+        self.idle.contains_key(&car) || self.assigned.contains_key(&car)
+    }
+
+    pub fn add_idle(&mut self, vehicle: Vehicle, pos: Position) {
+        self.idle.insert(vehicle.id, (vehicle, pos));
+    }
+
+    /// Removes the idle vehicle closest to a point, as the crow flies
+    pub fn take_closest(&mut self, pt: Pt2D, map: &Map) -> Option<(Vehicle, Position)> {
+        let car = self
+            .idle
+            .iter()
+            .min_by_key(|(_, (_, pos))| pos.pt(map).dist_to(pt))
+            .map(|(car, _)| *car)?;
+        self.idle.remove(&car)
+    }
+
+    pub fn assign(&mut self, car: CarID, trip: TripID) {
+        self.assigned.insert(car, trip);
+    }
+
+    pub fn unassign(&mut self, car: CarID) -> TripID {
+        self.assigned.remove(&car).unwrap()
+    }
+
+    pub fn vehicle_for(&self, trip: TripID) -> Option<CarID> {
+        self.assigned
+            .iter()
+            .find(|(_, t)| **t == trip)
+            .map(|(car, _)| *car)
+    }
+
+    pub fn enqueue(&mut self, trip: TripID) {
+        self.waiting.push_back(trip);
+    }
+
+    pub fn next_request(&mut self) -> Option<TripID> {
+        self.waiting.pop_front()
+    }
+
+    pub fn forget_request(&mut self, trip: TripID) {
+        self.waiting.retain(|t| *t != trip);
+    }
+}
@@ -1,28 +1,37 @@
 //! For vehicles only, not pedestrians. Follows a Path from map_model, but can opportunistically
 //! lane-change to avoid a slow lane, can can handle re-planning to look for available parking.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
 
-use geom::Distance;
+use geom::{Distance, Duration, Time};
 use map_model::{
-    BuildingID, IntersectionID, LaneID, Map, Path, PathConstraints, PathRequest, PathStep,
-    Position, Traversable, Turn, TurnID,
+    BuildingID, DirectedRoadID, IntersectionID, LaneID, Map, ParkingLotID, Path, PathConstraints,
+    PathRequest, PathStep, Position, RoutingParams, Traversable, Turn, TurnID,
 };
 
-use crate::mechanics::Queue;
+use crate::mechanics::{parking_price, Queue, WALKING_DISTANCE_PER_PARKING_PRICE};
 use crate::{
-    AlertLocation, CarID, Event, ParkingSim, ParkingSimState, ParkingSpot, PersonID, SidewalkSpot,
-    TripID, TripPhaseType, Vehicle, VehicleType,
+    AlertLocation, CarID, Event, ParkingSim, ParkingSimState, ParkingSpot, PersonID,
+    PickupDropoffZone, SidewalkSpot, TripID, TripPhaseType, Vehicle, VehicleType,
 };
 
+// Vehicles that don't qualify for a HOT lane will pay to use it only if this many fewer vehicles
+// are queued there
+const HOT_LANE_QUEUE_THRESHOLD: usize = 5;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub(crate) struct Router {
     /// Front is always the current step
     path: Path,
     goal: Goal,
     owner: CarID,
+    /// How many people are in the vehicle, to decide if HOV lanes can be used
+    occupancy: usize,
+    /// When the driver leaves again after parking, to figure out what parking costs and if it's
+    /// within a time limit. None means they stay for the rest of the simulation.
+    next_departure: Option<Time>,
 }
 
 #[derive(Debug)]
@@ -33,6 +42,13 @@ pub(crate) enum ActionAtEnd {
     StopBiking(SidewalkSpot),
     BusAtStop,
     GiveUpOnParking,
+    /// Stop along the curb in front of the building for this long
+    DropOff(BuildingID, Duration),
+    /// Stop in front of the building to unload. Whether the van pulls into a loading zone or
+    /// double-parks depends on what's free when it arrives.
+    Deliver(BuildingID),
+    /// An emergency vehicle reached the building, after being dispatched at this time
+    Respond(BuildingID, Time),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -55,6 +71,29 @@ enum Goal {
     FollowTransitRoute {
         end_dist: Distance,
     },
+    /// Stop anywhere between the two distances along the last driving lane, instead of parking
+    DropOffAtBuilding {
+        target: BuildingID,
+        curb_start: Distance,
+        end_dist: Distance,
+        dwell_time: Duration,
+    },
+    /// Stop in the lane in front of the building to unload
+    Deliver {
+        target: BuildingID,
+        end_dist: Distance,
+    },
+    /// Take any free spot in one particular lot
+    ParkInLot {
+        lot: ParkingLotID,
+        end_dist: Distance,
+    },
+    /// An emergency vehicle stops in front of the building and stays at the scene
+    Respond {
+        target: BuildingID,
+        dispatched: Time,
+        end_dist: Distance,
+    },
 }
 
 impl Router {
@@ -68,6 +107,8 @@ impl Router {
             path,
             goal: Goal::EndAtBorder { end_dist, i },
             owner,
+            occupancy: 1,
+            next_departure: None,
         }
     }
 
@@ -81,6 +122,8 @@ impl Router {
                 started_looking: false,
             },
             owner,
+            occupancy: 1,
+            next_departure: None,
         }
     }
 
@@ -89,6 +132,8 @@ impl Router {
             goal: Goal::BikeThenStop { goal },
             path,
             owner,
+            occupancy: 1,
+            next_departure: None,
         }
     }
 
@@ -99,9 +144,109 @@ impl Router {
             },
             path,
             owner,
+            occupancy: 1,
+            next_departure: None,
+        }
+    }
+
+    /// Ridehail vehicles always stop at the curb in front of the building, whether or not there's
+    /// a pickup/dropoff zone there.
+    pub fn stop_at_curb(owner: CarID, path: Path, target: BuildingID) -> Router {
+        let zone = PickupDropoffZone::new(target);
+        let end_dist = path.get_req().end.dist_along();
+        Router {
+            goal: Goal::DropOffAtBuilding {
+                target,
+                curb_start: (end_dist - zone.curb_length).max(Distance::ZERO),
+                end_dist,
+                dwell_time: zone.dwell_time,
+            },
+            path,
+            owner,
+            occupancy: 1,
+            next_departure: None,
+        }
+    }
+
+    /// Delivery vans stop right in front of the building instead of parking
+    pub fn deliver(owner: CarID, path: Path, target: BuildingID) -> Router {
+        Router {
+            goal: Goal::Deliver {
+                target,
+                end_dist: path.get_req().end.dist_along(),
+            },
+            path,
+            owner,
+            occupancy: 1,
+            next_departure: None,
+        }
+    }
+
+    /// Park-and-ride trips have to leave the car in one lot, not whatever's closest
+    pub fn park_in_lot(owner: CarID, path: Path, lot: ParkingLotID) -> Router {
+        Router {
+            goal: Goal::ParkInLot {
+                lot,
+                end_dist: path.get_req().end.dist_along(),
+            },
+            path,
+            owner,
+            occupancy: 1,
+            next_departure: None,
+        }
+    }
+
+    pub fn respond(owner: CarID, path: Path, target: BuildingID, dispatched: Time) -> Router {
+        Router {
+            goal: Goal::Respond {
+                target,
+                dispatched,
+                end_dist: path.get_req().end.dist_along(),
+            },
+            path,
+            owner,
+            occupancy: 1,
+            next_departure: None,
         }
     }
 
+    /// If the car was going to park near a building with a pickup/dropoff zone, just stop along
+    /// the curb instead. Cars already stopping at the curb use the zone's size and dwell time.
+    pub fn maybe_drop_off(&mut self, zones: &BTreeMap<BuildingID, PickupDropoffZone>) {
+        let target = match self.goal {
+            Goal::ParkNearBuilding {
+                target, spot: None, ..
+            }
+            | Goal::DropOffAtBuilding { target, .. } => target,
+            _ => {
+                return;
+            }
+        };
+        if let Some(zone) = zones.get(&target) {
+            let end_dist = self.path.get_req().end.dist_along();
+            self.goal = Goal::DropOffAtBuilding {
+                target,
+                curb_start: (end_dist - zone.curb_length).max(Distance::ZERO),
+                end_dist,
+                dwell_time: zone.dwell_time,
+            };
+        }
+    }
+
+    pub fn with_occupancy(mut self, occupancy: usize) -> Router {
+        self.occupancy = occupancy;
+        self
+    }
+
+    pub fn occupancy(&self) -> usize {
+        self.occupancy
+    }
+
+    pub fn with_next_departure(mut self, next_departure: Option<Time>) -> Router {
+        self.next_departure = next_departure;
+        self
+    }
+
     pub fn head(&self) -> Traversable {
         self.path.current_step().as_traversable()
     }
@@ -130,6 +275,10 @@ impl Router {
             } => stuck_end_dist.unwrap_or_else(|| spot.unwrap().1),
             Goal::BikeThenStop { ref goal } => goal.sidewalk_pos.dist_along(),
             Goal::FollowTransitRoute { end_dist } => end_dist,
+            Goal::DropOffAtBuilding { end_dist, .. }
+            | Goal::Deliver { end_dist, .. }
+            | Goal::ParkInLot { end_dist, .. }
+            | Goal::Respond { end_dist, .. } => end_dist,
         }
     }
 
@@ -140,6 +289,7 @@ impl Router {
     /// Returns the step just finished
     pub fn advance(
         &mut self,
+        now: Time,
         vehicle: &Vehicle,
         parking: &ParkingSimState,
         map: &Map,
@@ -151,6 +301,7 @@ impl Router {
             // Do this to trigger the side-effect of looking for parking.
             self.maybe_handle_end(
                 Distance::ZERO,
+                now,
                 vehicle,
                 parking,
                 map,
@@ -168,6 +319,29 @@ impl Router {
                     vehicle.id, l, lane.lane_type
                 );
             }
+
+            // Vehicles that don't qualify for a HOT lane pay to use it
+            if let Some(hov) = map.get_r(l.road).hov_restriction(l) {
+                if let (Some(toll), Some((trip, _))) = (hov.toll, trip_and_person) {
+                    if vehicle.vehicle_type == VehicleType::Car && !hov.qualifies(self.occupancy) {
+                        events.push(Event::TollPaid(trip, l, toll));
+                    }
+                }
+            }
+
+            // Cars crossing into a congestion charge cordon pay at the current rate
+            if let (Some(charge), Traversable::Turn(t), Some((trip, _))) =
+                (map.get_congestion_charge(), prev, trip_and_person)
+            {
+                if vehicle.vehicle_type == VehicleType::Car
+                    && charge.charges_entering(t.src.road, l.road)
+                {
+                    let toll = charge.toll_at(now);
+                    if toll > 0.0 {
+                        events.push(Event::TollPaid(trip, l, toll));
+                    }
+                }
+            }
         }
 
         prev
@@ -178,6 +352,7 @@ impl Router {
     pub fn maybe_handle_end(
         &mut self,
         front: Distance,
+        now: Time,
         vehicle: &Vehicle,
         parking: &ParkingSimState,
         map: &Map,
@@ -222,26 +397,27 @@ impl Router {
                         target,
                         map,
                     );
-                    let best =
-                        if let Some((driving_pos, _)) = map.get_b(target).driving_connection(map) {
-                            if driving_pos.lane() == current_lane {
-                                let target_dist = driving_pos.dist_along();
-                                // Closest to the building
-                                candidates
-                                    .into_iter()
-                                    .min_by_key(|(_, pos)| (pos.dist_along() - target_dist).abs())
-                            } else {
-                                // Closest to the road endpoint, I guess
-                                candidates
-                                    .into_iter()
-                                    .min_by_key(|(_, pos)| pos.dist_along())
-                            }
-                        } else {
-                            // Closest to the road endpoint, I guess
-                            candidates
-                                .into_iter()
-                                .min_by_key(|(_, pos)| pos.dist_along())
-                        };
+                    let target_dist = map
+                        .get_b(target)
+                        .driving_connection(map)
+                        .filter(|(driving_pos, _)| driving_pos.lane() == current_lane)
+                        .map(|(driving_pos, _)| driving_pos.dist_along());
+                    let stay = self.next_departure.map(|t| (t - now).max(Duration::ZERO));
+                    // Skip spots with a time limit that's too short, and weigh the price against
+                    // how far the spot is
+                    let best = candidates
+                        .into_iter()
+                        .filter_map(|(spot, pos)| {
+                            let price = parking_price(map, spot, stay)?;
+                            // Closest to the building, or else to the road endpoint, I guess
+                            let walk = match target_dist {
+                                Some(dist) => (pos.dist_along() - dist).abs(),
+                                None => pos.dist_along(),
+                            };
+                            Some((spot, pos, walk + price * WALKING_DISTANCE_PER_PARKING_PRICE))
+                        })
+                        .min_by_key(|(_, _, cost)| *cost)
+                        .map(|(spot, pos, _)| (spot, pos));
                     if let Some((new_spot, new_pos)) = best {
                         if let Some((t, p)) = trip_and_person {
                             events.push(Event::TripPhaseStarting(
@@ -259,8 +435,8 @@ impl Router {
                         assert!(new_pos.dist_along() >= front);
                         *spot = Some((new_spot, new_pos.dist_along()));
                     } else {
-                        if let Some((new_path_steps, new_spot, new_pos)) =
-                            parking.path_to_free_parking_spot(current_lane, vehicle, target, map)
+                        if let Some((new_path_steps, new_spot, new_pos)) = parking
+                            .path_to_free_parking_spot(current_lane, vehicle, target, stay, map)
                         {
                             assert!(!new_path_steps.is_empty());
                             for step in new_path_steps {
@@ -318,6 +494,53 @@ impl Router {
                     None
                 }
             }
+            Goal::DropOffAtBuilding {
+                target,
+                curb_start,
+                end_dist,
+                dwell_time,
+            } => {
+                // Cars queued behind others stopping at the curb can stop there too, as long as
+                // they've reached it
+                if front >= curb_start && front <= end_dist {
+                    Some(ActionAtEnd::DropOff(target, dwell_time))
+                } else {
+                    None
+                }
+            }
+            Goal::Deliver { target, end_dist } => {
+                if end_dist == front {
+                    Some(ActionAtEnd::Deliver(target))
+                } else {
+                    None
+                }
+            }
+            Goal::ParkInLot { lot, end_dist } => {
+                if end_dist != front {
+                    return None;
+                }
+                if let Some(spot) = parking.get_free_lot_spots(lot).into_iter().next() {
+                    return Some(ActionAtEnd::StartParking(spot));
+                }
+                if let Some((_, p)) = trip_and_person {
+                    events.push(Event::Alert(
+                        AlertLocation::Person(p),
+                        format!("{} found {} full", vehicle.id, lot),
+                    ));
+                }
+                Some(ActionAtEnd::GiveUpOnParking)
+            }
+            Goal::Respond {
+                target,
+                dispatched,
+                end_dist,
+            } => {
+                if end_dist == front {
+                    Some(ActionAtEnd::Respond(target, dispatched))
+                } else {
+                    None
+                }
+            }
         }
     }
 
@@ -378,7 +601,23 @@ impl Router {
                     slow_lane = 0;
                 }
 
-                (lt, bike, slow_lane, vehicles + lc)
+                // 0) Does a car qualify for an HOV lane? If not, only use it when it's the only
+                //    way to go, or to pay a toll and skip a real queue.
+                let mut hov = 0;
+                let mut toll = 0;
+                if let Some(restriction) = map.get_r(lane.road).hov_restriction(lane) {
+                    if self.owner.vehicle_type == VehicleType::Car
+                        && !restriction.qualifies(self.occupancy)
+                    {
+                        if restriction.toll.is_some() {
+                            toll = HOT_LANE_QUEUE_THRESHOLD;
+                        } else {
+                            hov = 1;
+                        }
+                    }
+                }
+
+                (hov, lt, bike, slow_lane, vehicles + lc + toll)
             };
 
             // Look for other candidates, and assign a cost to each.
@@ -452,6 +691,70 @@ impl Router {
         }
     }
 
+    /// Reconsiders the rest of the route from the end of the current lane, adding `road_delays` to
+    /// the usual costs. Returns true if the route changed.
+    pub fn reroute(&mut self, road_delays: &BTreeMap<DirectedRoadID, Duration>, map: &Map) -> bool {
+        match self.goal {
+            // Transit follows a fixed route, and somebody already circling for parking has
+            // amended their path and no longer ends at the original request
+            Goal::FollowTransitRoute { .. }
+            | Goal::ParkNearBuilding {
+                started_looking: true,
+                ..
+            } => {
+                return false;
+            }
+            _ => {}
+        }
+        if self.last_step() || self.path.currently_inside_ut().is_some() {
+            return false;
+        }
+        let current = match self.head() {
+            Traversable::Lane(l) => l,
+            Traversable::Turn(_) => {
+                return false;
+            }
+        };
+        let end = self.path.get_req().end;
+        if current == end.lane() {
+            return false;
+        }
+
+        let params = RoutingParams {
+            occupancy: self.occupancy,
+            road_delays: road_delays.clone(),
+            ..map.routing_params().clone()
+        };
+        let req = PathRequest::vehicle(
+            Position::end(current, map),
+            end,
+            self.owner.vehicle_type.to_constraints(),
+        );
+        // The delays change constantly, so don't build a pathfinder for them
+        match map.pathfind_by_cost(req, &params) {
+            Ok(path) => {
+                // The path must continue from where the vehicle is now
+                if path.get_steps().front() != Some(&PathStep::Lane(current)) {
+                    return false;
+                }
+                if path
+                    .get_steps()
+                    .iter()
+                    .skip(1)
+                    .eq(self.path.get_steps().iter().skip(1))
+                {
+                    return false;
+                }
+                self.path.reroute(path, map);
+                true
+            }
+            Err(err) => {
+                warn!("{} can't reroute: {}", self.owner, err);
+                false
+            }
+        }
+    }
+
     pub fn can_lanechange(&self, from: LaneID, to: LaneID, map: &Map) -> bool {
         let steps = self.path.get_steps();
         if steps.len() < 3 {
@@ -492,6 +795,19 @@ impl Router {
         }
     }
 
+    pub fn is_dropping_off(&self) -> bool {
+        matches!(
+            self.goal,
+            Goal::DropOffAtBuilding { .. } | Goal::Deliver { .. }
+        )
+    }
+
+    /// Emergency vehicles on the way to a call may speed, preempt signals, and squeeze into full
+    /// lanes
+    pub fn is_responding(&self) -> bool {
+        matches!(self.goal, Goal::Respond { .. })
+    }
+
     pub fn get_parking_spot_goal(&self) -> Option<&ParkingSpot> {
         match self.goal {
             Goal::ParkNearBuilding { ref spot, .. } => spot.as_ref().map(|(s, _)| s),
@@ -3,7 +3,7 @@ use std::collections::{BinaryHeap, HashMap};
 
 use serde::{Deserialize, Serialize};
 
-use abstutil::{Counter, PriorityQueueItem};
+use abstutil::{deserialize_hashmap, serialize_hashmap, Counter, PriorityQueueItem};
 use geom::{Duration, Histogram, Time};
 use map_model::{IntersectionID, TransitRouteID};
 
@@ -26,6 +26,16 @@ pub(crate) enum Command {
     Pandemic(pandemic::Cmd),
     /// The Time is redundant, just used to dedupe commands
     StartBus(TransitRouteID, Time),
+    /// An incident starts or ends. The Time is redundant, just used to dedupe commands
+    UpdateIncident(usize, Time),
+    /// A delivery van in a loading zone is done unloading
+    FinishUnloading(TripID),
+    /// Send out a fire engine or ambulance for some emergency call
+    DispatchEmergency(usize),
+    /// Record the speed of every vehicle along its lane
+    SampleLaneSpeeds,
+    /// Save the whole simulation. The Time is redundant, just used to dedupe commands
+    Checkpoint(Time),
 }
 
 impl Command {
@@ -49,6 +59,11 @@ impl Command {
             Command::Callback(_) => CommandType::Callback,
             Command::Pandemic(ref p) => CommandType::Pandemic(p.clone()),
             Command::StartBus(r, t) => CommandType::StartBus(*r, *t),
+            Command::UpdateIncident(idx, t) => CommandType::Incident(*idx, *t),
+            Command::FinishUnloading(id) => CommandType::FinishUnloading(*id),
+            Command::DispatchEmergency(idx) => CommandType::DispatchEmergency(*idx),
+            Command::SampleLaneSpeeds => CommandType::SampleLaneSpeeds,
+            Command::Checkpoint(t) => CommandType::Checkpoint(*t),
         }
     }
 
@@ -64,6 +79,11 @@ impl Command {
             Command::Callback(_) => SimpleCommandType::Callback,
             Command::Pandemic(_) => SimpleCommandType::Pandemic,
             Command::StartBus(_, _) => SimpleCommandType::StartBus,
+            Command::UpdateIncident(_, _) => SimpleCommandType::Incident,
+            Command::FinishUnloading(_) => SimpleCommandType::FinishUnloading,
+            Command::DispatchEmergency(_) => SimpleCommandType::DispatchEmergency,
+            Command::SampleLaneSpeeds => SimpleCommandType::SampleLaneSpeeds,
+            Command::Checkpoint(_) => SimpleCommandType::Checkpoint,
         }
     }
 }
@@ -80,6 +100,11 @@ enum CommandType {
     Callback,
     Pandemic(pandemic::Cmd),
     StartBus(TransitRouteID, Time),
+    Incident(usize, Time),
+    FinishUnloading(TripID),
+    DispatchEmergency(usize),
+    SampleLaneSpeeds,
+    Checkpoint(Time),
 }
 
 /// A more compressed form of CommandType, just used for keeping stats on event processing.
@@ -93,6 +118,11 @@ enum SimpleCommandType {
     Callback,
     Pandemic,
     StartBus,
+    Incident,
+    FinishUnloading,
+    DispatchEmergency,
+    SampleLaneSpeeds,
+    Checkpoint,
 }
 
 /// The priority queue driving the discrete event simulation. Different pieces of the simulation
@@ -101,6 +131,11 @@ enum SimpleCommandType {
 #[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Scheduler {
     items: BinaryHeap<PriorityQueueItem<Time, CommandType>>,
+    // Serialized in a fixed order, so savestates and determinism hashes are stable
+    #[serde(
+        serialize_with = "serialize_hashmap",
+        deserialize_with = "deserialize_hashmap"
+    )]
     queued_commands: HashMap<CommandType, (Command, Time)>,
 
     latest_time: Time,
@@ -0,0 +1,176 @@
+//! Screenline and cordon counts, the way agencies validate a model against real traffic counts
+//! and compare alternatives. A screenline is drawn across some roads and counts everybody
+//! crossing it in each direction. A cordon is drawn around an area and counts everybody entering
+//! and leaving.
+//!
+//! Vehicles count when they enter a lane crossing the line, even if they started or finished
+//! partway along it, without actually reaching the line. Which way a pedestrian walks along a
+//! sidewalk is only known after they cross an intersection, so somebody leaving a building
+//! counts from the next sidewalk on.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Angle, PolyLine, Polygon, Time};
+use map_model::{Direction, Map, RoadID, Traversable};
+
+use crate::{AgentType, Event};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ScreenlineShape {
+    Line(PolyLine),
+    Cordon(Polygon),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScreenlineCount {
+    pub agents: usize,
+    /// Everybody inside a vehicle, or riding transit. The drivers of buses and trains don't
+    /// count.
+    pub people: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Screenline {
+    pub name: String,
+    pub shape: ScreenlineShape,
+    /// The roads crossing the line. True if travelling forwards along the road crosses in the
+    /// first direction.
+    pub roads: BTreeMap<RoadID, bool>,
+    /// Like "northbound" or "inbound", then the opposite
+    pub direction_names: (String, String),
+    /// Keyed by whether agents crossed in the first direction, their type, and the hour
+    pub counts: BTreeMap<(bool, AgentType, usize), ScreenlineCount>,
+    /// Counting starts from when the screenline is added
+    pub since: Time,
+}
+
+impl Screenline {
+    /// Count traffic across a line. The first direction is from the right of the line, as drawn,
+    /// to its left.
+    pub fn along(name: String, line: PolyLine, map: &Map, now: Time) -> Screenline {
+        let mut roads = BTreeMap::new();
+        let mut normals = Vec::new();
+        for road in map.all_roads() {
+            let pt = match road.center_pts.intersection(&line) {
+                Some((pt, _)) => pt,
+                None => continue,
+            };
+            let (line_angle, road_angle) = match (
+                line.dist_along_of_point(pt),
+                road.center_pts.dist_along_of_point(pt),
+            ) {
+                (Some((_, a1)), Some((_, a2))) => (a1, a2),
+                _ => continue,
+            };
+            // Map space has Y pointing down, so this points to the left of the line
+            let normal = line_angle.rotate_degs(-90.0);
+            roads.insert(
+                road.id,
+                (road_angle.normalized_radians() - normal.normalized_radians()).cos() > 0.0,
+            );
+            normals.push(normal);
+        }
+        let first = normals
+            .first()
+            .cloned()
+            .unwrap_or_else(|| line.first_line().angle().rotate_degs(-90.0));
+        Screenline {
+            name,
+            shape: ScreenlineShape::Line(line),
+            roads,
+            direction_names: (
+                format!("{}bound", compass(first)),
+                format!("{}bound", compass(first.opposite())),
+            ),
+            counts: BTreeMap::new(),
+            since: now,
+        }
+    }
+
+    /// Count traffic entering and leaving an area
+    pub fn cordon(name: String, area: Polygon, map: &Map, now: Time) -> Screenline {
+        let mut roads = BTreeMap::new();
+        for road in map.all_roads() {
+            let src_inside = area.contains_pt(road.center_pts.first_pt());
+            let dst_inside = area.contains_pt(road.center_pts.last_pt());
+            if src_inside != dst_inside {
+                roads.insert(road.id, dst_inside);
+            }
+        }
+        Screenline {
+            name,
+            shape: ScreenlineShape::Cordon(area),
+            roads,
+            direction_names: ("inbound".to_string(), "outbound".to_string()),
+            counts: BTreeMap::new(),
+            since: now,
+        }
+    }
+
+    pub fn direction_name(&self, first: bool) -> &str {
+        if first {
+            &self.direction_names.0
+        } else {
+            &self.direction_names.1
+        }
+    }
+
+    pub fn total(&self, first: bool) -> ScreenlineCount {
+        let mut total = ScreenlineCount::default();
+        for ((dir, _, _), count) in &self.counts {
+            if *dir == first {
+                total.agents += count.agents;
+                total.people += count.people;
+            }
+        }
+        total
+    }
+
+    pub(crate) fn handle_event(&mut self, time: Time, ev: &Event, map: &Map) {
+        if let Event::AgentEntersTraversable(a, _, on, _, people) = ev {
+            let agent_type = a.to_type();
+            let (r, dir) = match (agent_type, on) {
+                (AgentType::Pedestrian, Traversable::Turn(t)) => {
+                    let road = map.get_r(t.dst.road);
+                    (
+                        road.id,
+                        if road.src_i == t.parent {
+                            Direction::Fwd
+                        } else {
+                            Direction::Back
+                        },
+                    )
+                }
+                (AgentType::Pedestrian, Traversable::Lane(_)) | (_, Traversable::Turn(_)) => {
+                    return;
+                }
+                (_, Traversable::Lane(l)) => (l.road, map.get_l(*l).dir),
+            };
+            if let Some(fwd_first) = self.roads.get(&r) {
+                let first = *fwd_first == (dir == Direction::Fwd);
+                let count = self
+                    .counts
+                    .entry((first, agent_type, time.get_hours()))
+                    .or_default();
+                count.agents += 1;
+                count.people += *people;
+            }
+        }
+    }
+}
+
+fn compass(angle: Angle) -> &'static str {
+    // Map space has Y pointing down, so 90 degrees is south
+    let deg = angle.normalized_degrees();
+    if (45.0..135.0).contains(&deg) {
+        "south"
+    } else if (135.0..225.0).contains(&deg) {
+        "west"
+    } else if (225.0..315.0).contains(&deg) {
+        "north"
+    } else {
+        "east"
+    }
+}
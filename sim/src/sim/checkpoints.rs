@@ -0,0 +1,101 @@
+//! Checkpoints are savestates written automatically as the simulation runs, either periodically or
+//! at times chosen ahead of time. Long simulations can be resumed from the latest one after a
+//! crash or in another session, by loading it like any other savestate.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Duration, Time};
+
+use crate::{Command, Sim};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Checkpoints {
+    every: Option<Duration>,
+    /// Only this many periodic checkpoints are kept on disk. Checkpoints at chosen times are
+    /// never deleted.
+    keep: usize,
+    next_periodic: Option<Time>,
+    /// Every time with a `Command::Checkpoint` scheduled, to avoid duplicates
+    pending: BTreeSet<Time>,
+    /// Periodic checkpoints written so far, oldest first
+    written: VecDeque<String>,
+}
+
+impl Checkpoints {
+    pub fn new(every: Option<Duration>, keep: usize) -> Checkpoints {
+        // Otherwise the simulation would never advance past the first checkpoint
+        if let Some(x) = every {
+            if x <= Duration::ZERO {
+                warn!("Ignoring a checkpoint interval of {}", x);
+            }
+        }
+        Checkpoints {
+            every: every.filter(|x| *x > Duration::ZERO),
+            keep: keep.max(1),
+            next_periodic: None,
+            pending: BTreeSet::new(),
+            written: VecDeque::new(),
+        }
+    }
+}
+
+impl Sim {
+    /// Starts periodic checkpoints, if they're enabled in `SimOptions`. Called when the sim is
+    /// created.
+    pub(crate) fn start_periodic_checkpoints(&mut self) {
+        if let Some(every) = self.checkpoints.every {
+            let t = self.time + every;
+            self.checkpoints.next_periodic = Some(t);
+            self.schedule_checkpoint(t);
+        }
+    }
+
+    /// Save the whole simulation when it reaches this time. The checkpoint is kept, even if
+    /// periodic checkpoints are being pruned.
+    pub fn schedule_checkpoint(&mut self, time: Time) {
+        if time < self.time || !self.checkpoints.pending.insert(time) {
+            return;
+        }
+        self.scheduler.push(time, Command::Checkpoint(time));
+    }
+
+    /// Times with a checkpoint scheduled, including the next periodic one
+    pub fn get_scheduled_checkpoints(&self) -> &BTreeSet<Time> {
+        &self.checkpoints.pending
+    }
+
+    pub(crate) fn handle_checkpoint(&mut self, time: Time) {
+        self.checkpoints.pending.remove(&time);
+        let periodic = self.checkpoints.next_periodic == Some(time);
+        // Schedule the next checkpoint first, so it's part of this savestate and resuming from it
+        // continues checkpointing
+        if periodic {
+            let next = time + self.checkpoints.every.unwrap();
+            self.checkpoints.next_periodic = Some(next);
+            self.schedule_checkpoint(next);
+        }
+
+        let path = self.save();
+        info!("Checkpointed the simulation at {} to {}", self.time, path);
+        if periodic {
+            self.checkpoints.written.push_back(path);
+            while self.checkpoints.written.len() > self.checkpoints.keep {
+                let old = self.checkpoints.written.pop_front().unwrap();
+                abstio::delete_file(old);
+            }
+        }
+    }
+
+    /// The most recent savestate for this map, edits, and run, whether it's a checkpoint or saved
+    /// manually. Resume from it with `Sim::load_savestate`, or by passing the path as the file to
+    /// load on the command line.
+    pub fn find_latest_savestate(&self) -> Option<String> {
+        // Like find_previous_savestate, this relies on the filenames sorting by time
+        abstio::list_dir(self.save_dir())
+            .into_iter()
+            .filter(|path| path.ends_with(".bin"))
+            .max()
+    }
+}
@@ -0,0 +1,210 @@
+//! The simulation is meant to be deterministic: the same map, scenario, and RNG seed should always
+//! produce the same results, on any platform. When prebaked results or savestates shared between
+//! machines don't match, it's hard to tell where things went wrong. While recording, the sim
+//! hashes its entire state every few minutes of simulated time, plus every event since the last
+//! sample. Comparing two logs finds the first interval where the runs diverged. Recording the
+//! events in that interval in detail, then comparing again, finds the first different event.
+//!
+//! Both runs need the same run name, since it's part of the sim's state.
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{fnv1a, FNV_OFFSET};
+use geom::{Duration, Time};
+
+use crate::{Event, Sim};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeterminismLog {
+    pub every: Duration,
+    pub samples: Vec<DeterminismSample>,
+    /// Every event from this time range is described, to pinpoint a divergence
+    pub detail: Option<(Time, Time)>,
+    pub detail_events: Vec<(Time, String)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeterminismSample {
+    pub time: Time,
+    /// A hash of the sim's state as of this time
+    pub state: u64,
+    /// A hash of every event since the previous sample
+    pub events: u64,
+    pub num_events: usize,
+}
+
+#[derive(Clone)]
+pub(crate) struct DeterminismRecorder {
+    log: DeterminismLog,
+    next_sample: Time,
+    events_hash: u64,
+    num_events: usize,
+}
+
+impl DeterminismLog {
+    /// Describes the first place where the two runs differ, or None if they match as far as both
+    /// of them go
+    pub fn compare(&self, other: &DeterminismLog) -> Option<String> {
+        if self.every != other.every {
+            return Some(format!(
+                "The logs sampled at different frequencies ({} and {}), so they can't be compared",
+                self.every, other.every
+            ));
+        }
+        let mut prev_time = Time::START_OF_DAY;
+        for (s1, s2) in self.samples.iter().zip(other.samples.iter()) {
+            if s1 == s2 {
+                prev_time = s1.time;
+                continue;
+            }
+            if s1.time != s2.time {
+                return Some(format!(
+                    "The logs were sampled at different times ({} and {}), so they can't be \
+                     compared",
+                    s1.time, s2.time
+                ));
+            }
+            if s1.events == s2.events && s1.num_events == s2.num_events {
+                return Some(format!(
+                    "The same events happened between {} and {}, but the sim state differs at {}. \
+                     Something changed without producing an event, like a random number or a \
+                     floating point calculation.",
+                    prev_time, s1.time, s1.time
+                ));
+            }
+
+            let mut msg = format!(
+                "The events diverged between {} and {} ({} and {} events).",
+                prev_time, s1.time, s1.num_events, s2.num_events
+            );
+            match (
+                self.detail_events_between(prev_time, s1.time),
+                other.detail_events_between(prev_time, s1.time),
+            ) {
+                (Some(events1), Some(events2)) => {
+                    let first_diff = events1
+                        .iter()
+                        .zip(events2.iter())
+                        .position(|(ev1, ev2)| ev1 != ev2)
+                        .unwrap_or_else(|| events1.len().min(events2.len()));
+                    let describe = |events: &[&(Time, String)]| match events.get(first_diff) {
+                        Some((time, ev)) => format!("{}: {}", time, ev),
+                        None => "no more events".to_string(),
+                    };
+                    msg.push_str(&format!(
+                        " The first different event is #{} in this interval.\nFirst run: {}\n\
+                         Second run: {}",
+                        first_diff + 1,
+                        describe(&events1),
+                        describe(&events2)
+                    ));
+                }
+                _ => {
+                    msg.push_str(&format!(
+                        " To find the first different event, record both runs again with details \
+                         from {} to {}.",
+                        prev_time, s1.time
+                    ));
+                }
+            }
+            return Some(msg);
+        }
+        None
+    }
+
+    /// Only if the whole time range was recorded in detail
+    fn detail_events_between(&self, start: Time, end: Time) -> Option<Vec<&(Time, String)>> {
+        let (from, until) = self.detail?;
+        if from > start || until < end {
+            return None;
+        }
+        // Events at exactly a sample's time might belong to either interval, so include both ends
+        Some(
+            self.detail_events
+                .iter()
+                .filter(|(t, _)| *t >= start && *t <= end)
+                .collect(),
+        )
+    }
+}
+
+impl DeterminismRecorder {
+    fn record_event(&mut self, time: Time, ev: &Event) {
+        let description = format!("{:?}", ev);
+        self.events_hash = fnv1a(self.events_hash, description.as_bytes());
+        self.num_events += 1;
+        if let Some((from, until)) = self.log.detail {
+            if time >= from && time <= until {
+                self.log.detail_events.push((time, description));
+            }
+        }
+    }
+
+    fn sample(&mut self, time: Time, state: u64) {
+        self.log.samples.push(DeterminismSample {
+            time,
+            state,
+            events: self.events_hash,
+            num_events: self.num_events,
+        });
+        self.events_hash = FNV_OFFSET;
+        self.num_events = 0;
+    }
+}
+
+impl Sim {
+    /// Start hashing the sim's state this often, for comparing with another run. Events during
+    /// `detail` are also described in full. Get the results with `finish_determinism_log`.
+    pub fn record_determinism(&mut self, every: Duration, detail: Option<(Time, Time)>) {
+        assert!(every > Duration::ZERO);
+        self.determinism = Some(DeterminismRecorder {
+            log: DeterminismLog {
+                every,
+                samples: Vec::new(),
+                detail,
+                detail_events: Vec::new(),
+            },
+            next_sample: self.time + every,
+            events_hash: FNV_OFFSET,
+            num_events: 0,
+        });
+    }
+
+    /// Stops recording, and returns the log, including a last sample for now
+    pub fn finish_determinism_log(&mut self) -> Option<DeterminismLog> {
+        let mut recorder = self.determinism.take()?;
+        let state = self.hash_state();
+        recorder.sample(self.time, state);
+        Some(recorder.log)
+    }
+
+    /// Called before handling a command at `next_time`
+    pub(crate) fn maybe_sample_determinism(&mut self, next_time: Time) {
+        match self.determinism {
+            Some(ref recorder) if next_time > recorder.next_sample => {}
+            _ => {
+                return;
+            }
+        }
+        // Nothing's happened since the last command, so this is the state at every sample time
+        // skipped over
+        let mut recorder = self.determinism.take().unwrap();
+        let state = self.hash_state();
+        while next_time > recorder.next_sample {
+            let time = recorder.next_sample;
+            recorder.sample(time, state);
+            recorder.next_sample = time + recorder.log.every;
+        }
+        self.determinism = Some(recorder);
+    }
+
+    pub(crate) fn record_determinism_event(&mut self, ev: &Event) {
+        if let Some(ref mut recorder) = self.determinism {
+            recorder.record_event(self.time, ev);
+        }
+    }
+
+    fn hash_state(&self) -> u64 {
+        fnv1a(FNV_OFFSET, &abstutil::to_binary(self))
+    }
+}
@@ -0,0 +1,140 @@
+//! Fire engines and ambulances drive from their station to an emergency. On the way, they go
+//! faster than the speed limit, preempt traffic signals and skip stop signs, and squeeze into full
+//! lanes, as if everybody else pulled aside. How long they take to arrive shows how edits like
+//! modal filters affect emergency response times.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Time};
+use map_model::{BuildingID, Map, PathConstraints, PathRequest, Position};
+
+use crate::{
+    AlertLocation, CarID, Command, CreateCar, Event, Router, Sim, VehicleSpec, VehicleType,
+};
+
+const FIRE_ENGINE_LENGTH: Distance = Distance::const_meters(9.0);
+const AMBULANCE_LENGTH: Distance = Distance::const_meters(6.5);
+
+/// An emergency vehicle sent from a station to some building
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct EmergencyCall {
+    /// Like "kitchen fire"
+    pub description: String,
+    pub time: Time,
+    pub vehicle: EmergencyVehicleType,
+    /// Where the vehicle waits, like a fire station or ambulance depot
+    pub station: BuildingID,
+    pub incident: BuildingID,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EmergencyVehicleType {
+    FireEngine,
+    Ambulance,
+}
+
+impl Sim {
+    pub fn schedule_emergency_call(&mut self, map: &Map, call: EmergencyCall) -> Result<()> {
+        if call.time < self.time {
+            bail!("{} already happened", call.description);
+        }
+        for b in [call.station, call.incident] {
+            if b.0 >= map.all_buildings().len() {
+                bail!("{} doesn't exist", b);
+            }
+            if map.get_b(b).driving_connection(map).is_none() {
+                bail!("{} for {} isn't reachable by car", b, call.description);
+            }
+        }
+        if call.station == call.incident {
+            bail!("{} is at the station", call.description);
+        }
+
+        let idx = self.emergency_calls.len();
+        self.scheduler
+            .push(call.time, Command::DispatchEmergency(idx));
+        self.emergency_calls.push(call);
+        Ok(())
+    }
+
+    pub fn get_emergency_calls(&self) -> &Vec<EmergencyCall> {
+        &self.emergency_calls
+    }
+
+    /// Returns events if the vehicle can't even set off
+    pub(crate) fn dispatch_emergency(&mut self, idx: usize, map: &Map) -> Vec<Event> {
+        let call = &self.emergency_calls[idx];
+        let length = match call.vehicle {
+            EmergencyVehicleType::FireEngine => FIRE_ENGINE_LENGTH,
+            EmergencyVehicleType::Ambulance => AMBULANCE_LENGTH,
+        };
+        // Edits since the call was scheduled may have cut off either building
+        let (start, end) = match (
+            map.get_b(call.station).driving_connection(map),
+            map.get_b(call.incident).driving_connection(map),
+        ) {
+            (Some((start, _)), Some((end, _))) => (start, end),
+            _ => {
+                return vec![
+                    Event::EmergencyResponse(call.incident, None),
+                    Event::Alert(
+                        AlertLocation::Building(call.incident),
+                        format!(
+                            "Nobody can reach {}: the station or the incident isn't reachable by \
+                             car anymore",
+                            call.description
+                        ),
+                    ),
+                ];
+            }
+        };
+        // Spawning needs room for the whole vehicle behind its front
+        let start = Position::new(
+            start.lane(),
+            start
+                .dist_along()
+                .max(length)
+                .min(map.get_l(start.lane()).length()),
+        );
+        let path = match map.pathfind(PathRequest::vehicle(start, end, PathConstraints::Car)) {
+            Ok(path) => path,
+            Err(err) => {
+                return vec![
+                    Event::EmergencyResponse(call.incident, None),
+                    Event::Alert(
+                        AlertLocation::Building(call.incident),
+                        format!("Nobody can reach {}: {}", call.description, err),
+                    ),
+                ];
+            }
+        };
+
+        let vehicle = VehicleSpec {
+            vehicle_type: VehicleType::Car,
+            length,
+            max_speed: None,
+        }
+        .make(
+            CarID {
+                id: self.trips.new_car_id(),
+                vehicle_type: VehicleType::Car,
+            },
+            None,
+        );
+        self.scheduler.push(
+            self.time,
+            Command::SpawnCar(
+                CreateCar {
+                    router: Router::respond(vehicle.id, path, call.incident, self.time),
+                    vehicle,
+                    maybe_parked_car: None,
+                    trip_and_person: None,
+                    maybe_route: None,
+                },
+                true,
+            ),
+        );
+        Vec::new()
+    }
+}
@@ -0,0 +1,75 @@
+//! Groundwork for splitting one huge simulation across processes, each simulating one part of a
+//! `Partitioning`. When an agent is on a lane leading into another part, the process that owns it
+//! describes it with a `BoundaryHandoff`. Once the agent reaches the end of that lane, the sending
+//! process removes it and the receiving process continues the trip along `remaining_path`.
+
+use serde::{Deserialize, Serialize};
+
+use geom::Time;
+use map_model::{IntersectionID, LaneID, Map, Partitioning, PathStep};
+use synthpop::{TripEndpoint, TripMode};
+
+use crate::{AgentID, PersonID, Sim, TripID};
+
+/// An agent about to cross from one part of the map into another
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BoundaryHandoff {
+    /// When the handoff was described, not when the agent will cross
+    pub time: Time,
+    pub agent: AgentID,
+    pub person: PersonID,
+    pub trip: TripID,
+    pub mode: TripMode,
+    pub from_part: usize,
+    pub to_part: usize,
+    /// The agent is on this lane now
+    pub lane: LaneID,
+    /// The agent enters the next part here
+    pub crossing: IntersectionID,
+    /// Everything after the current lane
+    pub remaining_path: Vec<PathStep>,
+    pub destination: TripEndpoint,
+}
+
+impl Sim {
+    /// Every active agent currently on a lane that leads from one part into another
+    pub fn boundary_handoffs(
+        &self,
+        map: &Map,
+        partitioning: &Partitioning,
+    ) -> Vec<BoundaryHandoff> {
+        let mut handoffs = Vec::new();
+        for (agent, trip) in self.trips.active_agents_and_trips() {
+            let path = match self.get_path(*agent) {
+                Some(path) => path,
+                None => continue,
+            };
+            // Pedestrians may walk against the direction of a sidewalk
+            let (lane, src_i, dst_i) = match path.current_step() {
+                PathStep::Lane(l) => (l, map.get_l(l).src_i, map.get_l(l).dst_i),
+                PathStep::ContraflowLane(l) => (l, map.get_l(l).dst_i, map.get_l(l).src_i),
+                PathStep::Turn(_) | PathStep::ContraflowTurn(_) => continue,
+            };
+            let from_part = partitioning.part_of(src_i);
+            let to_part = partitioning.part_of(dst_i);
+            if from_part == to_part {
+                continue;
+            }
+            let info = self.trips.trip_info(*trip);
+            handoffs.push(BoundaryHandoff {
+                time: self.time,
+                agent: *agent,
+                person: self.trips.trip_to_person(*trip).unwrap(),
+                trip: *trip,
+                mode: info.mode,
+                from_part,
+                to_part,
+                lane,
+                crossing: dst_i,
+                remaining_path: path.get_steps().iter().skip(1).cloned().collect(),
+                destination: info.end,
+            });
+        }
+        handoffs
+    }
+}
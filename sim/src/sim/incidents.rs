@@ -0,0 +1,151 @@
+//! Incidents like a crash, a parade, or emergency construction close roads or lanes for part of a
+//! simulation, so people can study how resilient the network is. The sim can't edit the map
+//! itself, so it halts whenever an incident starts or ends. Whoever's running the sim then applies
+//! `Sim::edits_for_incidents` to the map and calls `handle_live_edits`, which reroutes or cancels
+//! the affected trips.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use geom::Time;
+use map_model::{EditCmd, EditRoad, LaneID, LaneType, Map, MapEdits, RoadID};
+
+use crate::{Command, Sim};
+
+/// Roads and lanes closed during some time window
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Incident {
+    /// Like "crash on the bridge"
+    pub description: String,
+    pub start: Time,
+    pub end: Time,
+    /// Every vehicle lane on these roads closes. Sidewalks stay open.
+    #[serde(default)]
+    pub roads: BTreeSet<RoadID>,
+    /// Just these lanes close
+    #[serde(default)]
+    pub lanes: BTreeSet<LaneID>,
+}
+
+impl Incident {
+    pub fn is_active(&self, time: Time) -> bool {
+        time >= self.start && time < self.end
+    }
+}
+
+impl Sim {
+    /// Schedules roads or lanes to close and reopen later. The incident may have already started.
+    pub fn schedule_incident(&mut self, map: &Map, incident: Incident) -> Result<()> {
+        if incident.end <= incident.start {
+            bail!("{} ends before it starts", incident.description);
+        }
+        if incident.end <= self.time {
+            bail!("{} is already over", incident.description);
+        }
+        if incident.roads.is_empty() && incident.lanes.is_empty() {
+            bail!("{} doesn't close anything", incident.description);
+        }
+        for r in &incident.roads {
+            if r.0 >= map.all_roads().len() {
+                bail!("{} doesn't exist", r);
+            }
+        }
+        for l in &incident.lanes {
+            if map.maybe_get_l(*l).is_none() {
+                bail!("{} doesn't exist", l);
+            }
+        }
+
+        let idx = self.incidents.len();
+        self.scheduler.push(
+            incident.start.max(self.time),
+            Command::UpdateIncident(idx, incident.start),
+        );
+        self.scheduler
+            .push(incident.end, Command::UpdateIncident(idx, incident.end));
+        self.incidents.push(incident);
+        Ok(())
+    }
+
+    pub fn get_incidents(&self) -> &Vec<Incident> {
+        &self.incidents
+    }
+
+    /// If an incident started or ended since the last call, returns edits that close everything
+    /// closed right now. The previous closures are replaced; any other edits, including ones made
+    /// while an incident was active, are kept.
+    pub fn edits_for_incidents(&mut self, map: &Map) -> Option<MapEdits> {
+        if !self.incidents_changed {
+            return None;
+        }
+        self.incidents_changed = false;
+
+        let mut edits = map.get_edits().clone();
+        let previous_closures = std::mem::take(&mut self.incident_closures);
+        let is_previous_closure = |cmd: &EditCmd| match cmd {
+            EditCmd::ChangeRoad { r, old, new } => previous_closures
+                .iter()
+                .any(|(r2, old2, new2)| r == r2 && old == old2 && new == new2),
+            _ => false,
+        };
+        // How each road looked before any incident. If somebody edited a closed road afterwards,
+        // their edit wins.
+        let mut orig_states: BTreeMap<RoadID, EditRoad> = BTreeMap::new();
+        let mut seen: BTreeSet<RoadID> = BTreeSet::new();
+        for cmd in edits.commands.iter().rev() {
+            if let EditCmd::ChangeRoad { r, old, .. } = cmd {
+                if seen.insert(*r) && is_previous_closure(cmd) {
+                    orig_states.insert(*r, old.clone());
+                }
+            }
+        }
+        edits.commands.retain(|cmd| !is_previous_closure(cmd));
+        let orig_state = |r: RoadID| {
+            orig_states
+                .get(&r)
+                .cloned()
+                .unwrap_or_else(|| map.get_r_edit(r))
+        };
+
+        // For each road, which lanes are closed, or None for all of them
+        let mut closed: BTreeMap<RoadID, Option<BTreeSet<usize>>> = BTreeMap::new();
+        for incident in &self.incidents {
+            if !incident.is_active(self.time) {
+                continue;
+            }
+            for r in &incident.roads {
+                closed.insert(*r, None);
+            }
+            for l in &incident.lanes {
+                if let Some(offsets) = closed
+                    .entry(l.road)
+                    .or_insert_with(|| Some(BTreeSet::new()))
+                {
+                    offsets.insert(l.offset);
+                }
+            }
+        }
+
+        for (r, offsets) in closed {
+            let old = orig_state(r);
+            let mut new = old.clone();
+            close_lanes(&mut new, offsets);
+            self.incident_closures.push((r, old.clone(), new.clone()));
+            edits.commands.push(EditCmd::ChangeRoad { r, old, new });
+        }
+        Some(edits)
+    }
+}
+
+fn close_lanes(road: &mut EditRoad, offsets: Option<BTreeSet<usize>>) {
+    for (idx, spec) in road.lanes_ltr.iter_mut().enumerate() {
+        if spec.lt.is_walkable() {
+            continue;
+        }
+        if offsets.as_ref().map(|x| x.contains(&idx)).unwrap_or(true) {
+            spec.lt = LaneType::Construction;
+        }
+    }
+}
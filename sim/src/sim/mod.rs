@@ -11,26 +11,42 @@ use structopt::StructOpt;
 
 use abstio::{CityName, MapName};
 use abstutil::{prettyprint_usize, serialized_size_bytes, Timer};
-use geom::{Distance, Duration, Speed, Time};
+use geom::{Angle, Distance, Duration, Polygon, Speed, Time};
 use map_model::{
-    BuildingID, IntersectionID, LaneID, Map, ParkingLotID, Path, PathConstraints, PathRequest,
-    Position, TransitRoute, Traversable,
+    BuildingID, DirectedRoadID, EditRoad, IntersectionCluster, IntersectionID, LaneID, Map,
+    ParkingLotID, Path, PathConstraints, PathRequest, Position, RoadID, TransitRoute, Traversable,
+    Wind,
 };
 use synthpop::OrigPersonID;
 
+pub use self::emergency::{EmergencyCall, EmergencyVehicleType};
+pub use self::handoff::BoundaryHandoff;
+pub use self::incidents::Incident;
 pub use self::queries::{AgentProperties, DelayCause};
 // TODO Super weird for both of these to wind up here
+use self::checkpoints::Checkpoints;
+use self::determinism::DeterminismRecorder;
+pub use self::determinism::{DeterminismLog, DeterminismSample};
+pub(crate) use self::scenario::rand_ped_speed;
 pub use self::scenario::{count_parked_cars_per_bldg, rand_dist};
+pub use self::watchdog::{Anomaly, Watchdog};
 use crate::{
     AgentID, AlertLocation, Analytics, CarID, Command, CreateCar, DrivingSimState, Event,
-    IntersectionSimState, PandemicModel, ParkedCar, ParkingSim, ParkingSimState, ParkingSpot,
-    Person, PersonID, Router, Scheduler, SidewalkPOI, SidewalkSpot, StartTripArgs, TrafficRecorder,
-    TransitSimState, TripID, TripInfo, TripManager, TripPhaseType, Vehicle, VehicleSpec,
-    VehicleType, WalkingSimState, BUS_LENGTH, LIGHT_RAIL_LENGTH, MIN_CAR_LENGTH,
+    IntersectionSimState, LaneSpeeds, PandemicModel, ParkedCar, ParkingSim, ParkingSimState,
+    ParkingSpot, Person, PersonID, PickupDropoffZone, Router, Scheduler, Screenline, SidewalkPOI,
+    SidewalkSpot, StartTripArgs, TrafficRecorder, TransitSimState, TripID, TripInfo, TripManager,
+    TripPhaseType, VariableSpeedLimits, Vehicle, VehicleSpec, VehicleType, WalkingSimState,
+    BUS_LENGTH, LIGHT_RAIL_LENGTH, MIN_CAR_LENGTH,
 };
 
+mod checkpoints;
+mod determinism;
+mod emergency;
+mod handoff;
+mod incidents;
 mod queries;
 mod scenario;
+mod watchdog;
 
 // TODO Do something else.
 const BLIND_RETRY_TO_SPAWN: Duration = Duration::const_seconds(5.0);
@@ -62,9 +78,29 @@ pub struct Sim {
     // This is created interactively, and there's no reason to preserve one for savestates.
     #[serde(skip_serializing, skip_deserializing)]
     recorder: Option<TrafficRecorder>,
+    screenlines: Vec<Screenline>,
+    lane_speeds: Option<LaneSpeeds>,
 
     #[serde(skip_serializing, skip_deserializing)]
     alerts: AlertHandler,
+
+    incidents: Vec<Incident>,
+    /// An incident started or ended, and the map hasn't been updated yet
+    incidents_changed: bool,
+    /// The road edits (old and new state) currently closing things for incidents
+    incident_closures: Vec<(RoadID, EditRoad, EditRoad)>,
+    emergency_calls: Vec<EmergencyCall>,
+    /// The fraction of bikes that are electric, used when instantiating scenarios
+    ebike_share: f64,
+    checkpoints: Checkpoints,
+    uncompressed_savestates: bool,
+    #[serde(skip_serializing, skip_deserializing)]
+    determinism: Option<DeterminismRecorder>,
+    #[serde(skip_serializing, skip_deserializing)]
+    streamed_events: Option<Vec<(Time, Event)>>,
+    /// Only for monitoring, so this starts over when a savestate is loaded
+    #[serde(skip_serializing, skip_deserializing)]
+    num_events: usize,
 }
 
 pub(crate) struct Ctx<'a> {
@@ -92,6 +128,10 @@ pub struct SimOptions {
     /// they'll get stuck blocking the intersection.
     #[structopt(long)]
     pub allow_block_the_box: bool,
+    /// The fraction of drivers who ignore "don't block the box" and enter an intersection even
+    /// when their target lane is full. Between 0 and 1.
+    #[structopt(long, default_value = "0.0")]
+    pub block_the_box_violation_rate: f64,
     /// Normally as a vehicle follows a route, it opportunistically make small changes to use a different lane,
     /// based on some score of "least-loaded" lane. Disable this default behavior.
     #[structopt(long)]
@@ -105,6 +145,10 @@ pub struct SimOptions {
     /// red lights after starting.
     #[structopt(long)]
     pub dont_handle_uber_turns: bool,
+    /// Normally where two lanes merge into one, vehicles from each lane take turns entering.
+    /// Disable this, so the merge is first-come, first-served.
+    #[structopt(long)]
+    pub dont_zipper_merge: bool,
     /// Enable an experimental SEIR pandemic model. This requires an RNG seed, which can be the
     /// same or different from the one used for the rest of the simulation.
     #[structopt(long, parse(try_from_str = parse_rng))]
@@ -127,6 +171,69 @@ pub struct SimOptions {
     /// quickly.
     #[structopt(long)]
     pub skip_analytics: bool,
+    /// Simulate vehicles everywhere with a cheaper mesoscopic model. Lanes between two mesoscopic
+    /// intersections become queues with a travel time, an exit capacity, and a storage capacity,
+    /// and the vehicles on them aren't updated individually. Intersections ignore conflicts and
+    /// right-of-way, but still obey traffic signals, and vehicles don't change lanes. Individual
+    /// areas can be switched to or from this model later.
+    #[structopt(long)]
+    pub mesoscopic: bool,
+    /// A GeoJSON file with polygons. Only traffic inside these areas is simulated microscopically;
+    /// the rest of the map uses the mesoscopic model.
+    #[structopt(long)]
+    pub micro_focus: Option<String>,
+    /// A JSON file with a list of corridors to put variable speed limit signs on
+    #[structopt(long)]
+    pub variable_speed_limits: Option<String>,
+    /// A JSON file with a list of buildings where cars stop at the curb to pick up or drop off
+    /// passengers, instead of parking
+    #[structopt(long)]
+    pub pickup_dropoff_zones: Option<String>,
+    /// A JSON file with a list of incidents closing roads or lanes for part of the simulation.
+    /// Whoever runs the simulation has to apply the resulting map edits.
+    #[structopt(long)]
+    pub incidents: Option<String>,
+    /// A JSON file with a list of emergency calls, each sending a fire engine or ambulance from a
+    /// station to some building
+    #[structopt(long)]
+    pub emergency_calls: Option<String>,
+    /// The fraction of drivers who reconsider their route when stuck in traffic, using the live
+    /// length of queues as extra costs. Between 0 and 1; 0 disables rerouting.
+    #[structopt(long, default_value = "0.0")]
+    pub reroute_fraction: f64,
+    /// How many seconds a driver waits at the front of a queue before reconsidering their route.
+    #[structopt(long, parse(try_from_str = parse_seconds), default_value = "60")]
+    pub reroute_after_delay: Duration,
+    /// How many vehicles serve ridehail trips. They're spread around the map when the first
+    /// ridehail trip starts.
+    #[structopt(long, default_value = "50")]
+    pub ridehail_fleet_size: usize,
+    /// How many seconds a cyclist going straight takes to clear the path of vehicles turning
+    /// across the bike lane. Drivers wait this long after a cyclist starts crossing before turning.
+    #[structopt(long, parse(try_from_str = parse_seconds), default_value = "3")]
+    pub bike_crossing_time: Duration,
+    /// The fraction of bikes that are electric, between 0 and 1. Electric bikes cruise faster and
+    /// barely slow down on hills. If this is 0, the scenario's own share is used.
+    #[structopt(long, default_value = "0.0")]
+    pub ebike_share: f64,
+    /// A steady wind over the whole map, in miles per hour. Cyclists riding into it slow down.
+    #[structopt(long, default_value = "0.0")]
+    pub wind_mph: f64,
+    /// The direction the wind blows towards, in degrees. 0 is east and 90 is south.
+    #[structopt(long, default_value = "0.0")]
+    pub wind_towards_degrees: f64,
+    /// Automatically save the simulation every this many minutes of simulated time, so a crash
+    /// doesn't lose everything. Resume by loading the latest savestate.
+    #[structopt(long = "checkpoint-every-minutes", parse(try_from_str = parse_minutes))]
+    pub checkpoint_every: Option<Duration>,
+    /// How many of the periodic checkpoints to keep on disk. Older ones are deleted.
+    #[structopt(long, default_value = "3")]
+    pub keep_checkpoints: usize,
+    /// Savestates are normally compressed, which makes them several times smaller and faster to
+    /// write and load for big maps. Turn this off to save uncompressed files, which can be
+    /// loaded on web.
+    #[structopt(long)]
+    pub uncompressed_savestates: bool,
 }
 
 impl SimOptions {
@@ -135,16 +242,45 @@ impl SimOptions {
             run_name: run_name.to_string(),
             use_freeform_policy_everywhere: false,
             allow_block_the_box: false,
+            block_the_box_violation_rate: 0.0,
             dont_recalc_lanechanging: false,
             dont_break_turn_conflict_cycles: false,
             dont_handle_uber_turns: false,
+            dont_zipper_merge: false,
             enable_pandemic_model: None,
             alerts: AlertHandler::Print,
             infinite_parking: false,
             disable_turn_conflicts: false,
             skip_analytics: false,
+            mesoscopic: false,
+            micro_focus: None,
+            variable_speed_limits: None,
+            pickup_dropoff_zones: None,
+            incidents: None,
+            emergency_calls: None,
+            reroute_fraction: 0.0,
+            reroute_after_delay: Duration::minutes(1),
+            ridehail_fleet_size: 50,
+            bike_crossing_time: Duration::seconds(3.0),
+            ebike_share: 0.0,
+            wind_mph: 0.0,
+            wind_towards_degrees: 0.0,
+            checkpoint_every: None,
+            keep_checkpoints: 3,
+            uncompressed_savestates: false,
         }
     }
+
+    /// The wind to set on the map, with `Map::set_wind`, before simulating
+    pub fn wind(&self) -> Option<Wind> {
+        if self.wind_mph <= 0.0 {
+            return None;
+        }
+        Some(Wind {
+            speed: Speed::miles_per_hour(self.wind_mph),
+            towards: Angle::degrees(self.wind_towards_degrees),
+        })
+    }
 }
 
 impl Default for SimOptions {
@@ -153,6 +289,30 @@ impl Default for SimOptions {
     }
 }
 
+fn load_focus_areas(path: &str, map: &Map) -> Result<Vec<Polygon>> {
+    let require_in_bounds = false;
+    Ok(Polygon::from_geojson_bytes(
+        &abstio::slurp_file(path)?,
+        map.get_gps_bounds(),
+        require_in_bounds,
+    )?
+    .into_iter()
+    .map(|(polygon, _)| polygon)
+    .collect())
+}
+
+fn parse_seconds(x: &str) -> Result<Duration> {
+    Ok(Duration::seconds(x.parse::<f64>()?))
+}
+
+fn parse_minutes(x: &str) -> Result<Duration> {
+    let minutes = x.parse::<usize>()?;
+    if minutes == 0 {
+        bail!("must be at least 1 minute");
+    }
+    Ok(Duration::minutes(minutes))
+}
+
 fn parse_rng(x: &str) -> Result<XorShiftRng> {
     let seed: u64 = x.parse()?;
     Ok(XorShiftRng::seed_from_u64(seed))
@@ -206,13 +366,13 @@ impl Sim {
             opts.allow_block_the_box = true;
         }
 
-        Sim {
+        let mut sim = Sim {
             driving: DrivingSimState::new(map, &opts),
             parking: ParkingSimState::new(map, opts.infinite_parking, &mut timer),
             walking: WalkingSimState::new(),
             intersections: IntersectionSimState::new(map, &mut scheduler, &opts),
             transit: TransitSimState::new(map),
-            trips: TripManager::new(),
+            trips: TripManager::new(opts.ridehail_fleet_size),
             pandemic: opts.enable_pandemic_model.map(PandemicModel::new),
             scheduler,
             time: Time::START_OF_DAY,
@@ -226,7 +386,70 @@ impl Sim {
 
             analytics: Analytics::new(!opts.skip_analytics),
             recorder: None,
+            screenlines: Vec::new(),
+            lane_speeds: None,
+
+            incidents: Vec::new(),
+            incidents_changed: false,
+            incident_closures: Vec::new(),
+            emergency_calls: Vec::new(),
+            ebike_share: opts.ebike_share.clamp(0.0, 1.0),
+            checkpoints: Checkpoints::new(opts.checkpoint_every, opts.keep_checkpoints),
+            uncompressed_savestates: opts.uncompressed_savestates,
+            determinism: None,
+            streamed_events: None,
+            num_events: 0,
+        };
+        sim.start_periodic_checkpoints();
+        sim
+    }
+
+    /// `Sim::new` doesn't read any of the files named by `SimOptions`. `SimFlags` calls this
+    /// afterwards; anything else creating a simulation from user-supplied options should too.
+    pub fn load_input_files(
+        &mut self,
+        map: &Map,
+        opts: &SimOptions,
+        timer: &mut Timer,
+    ) -> Result<()> {
+        if let Some(ref path) = opts.micro_focus {
+            let areas = load_focus_areas(path, map)
+                .map_err(|err| anyhow!("Can't load micro_focus areas from {}: {}", path, err))?;
+            self.set_microscopic_focus(map, &areas);
+        }
+        if let Some(ref path) = opts.variable_speed_limits {
+            let corridors: Vec<VariableSpeedLimits> = abstio::maybe_read_json(path.clone(), timer)
+                .map_err(|err| {
+                    anyhow!("Can't load variable_speed_limits from {}: {}", path, err)
+                })?;
+            for limits in corridors {
+                self.add_variable_speed_limits(limits);
+            }
+        }
+        if let Some(ref path) = opts.pickup_dropoff_zones {
+            let zones: Vec<PickupDropoffZone> = abstio::maybe_read_json(path.clone(), timer)
+                .map_err(|err| anyhow!("Can't load pickup_dropoff_zones from {}: {}", path, err))?;
+            for zone in zones {
+                self.add_pickup_dropoff_zone(zone);
+            }
         }
+        if let Some(ref path) = opts.incidents {
+            let list: Vec<Incident> = abstio::maybe_read_json(path.clone(), timer)
+                .map_err(|err| anyhow!("Can't load incidents from {}: {}", path, err))?;
+            for incident in list {
+                self.schedule_incident(map, incident)
+                    .map_err(|err| anyhow!("Bad incident in {}: {}", path, err))?;
+            }
+        }
+        if let Some(ref path) = opts.emergency_calls {
+            let list: Vec<EmergencyCall> = abstio::maybe_read_json(path.clone(), timer)
+                .map_err(|err| anyhow!("Can't load emergency_calls from {}: {}", path, err))?;
+            for call in list {
+                self.schedule_emergency_call(map, call)
+                    .map_err(|err| anyhow!("Bad emergency call in {}: {}", path, err))?;
+            }
+        }
+        Ok(())
     }
 
     pub(crate) fn spawn_trips(
@@ -302,7 +525,7 @@ impl Sim {
         } else {
             let (_, spot, _) =
                 self.parking
-                    .path_to_free_parking_spot(driving_lane, &vehicle, b, map)?;
+                    .path_to_free_parking_spot(driving_lane, &vehicle, b, None, map)?;
             spot
         };
 
@@ -431,6 +654,7 @@ impl Sim {
         cmd: Command,
         maybe_cb: &mut Option<Box<dyn SimCallback>>,
     ) -> bool {
+        self.maybe_sample_determinism(time);
         self.time = time;
         let mut events = Vec::new();
         let mut halt = false;
@@ -470,13 +694,23 @@ impl Sim {
                     }
                 }
                 if !ok {
-                    self.trips.cancel_trip(
-                        self.time,
-                        create_car.trip_and_person.unwrap().0,
-                        "path is no longer valid after map edits".to_string(),
-                        Some(create_car.vehicle),
-                        &mut ctx,
-                    );
+                    if let Some((trip, _)) = create_car.trip_and_person {
+                        self.trips.cancel_trip(
+                            self.time,
+                            trip,
+                            "path is no longer valid after map edits".to_string(),
+                            Some(create_car.vehicle),
+                            &mut ctx,
+                        );
+                    } else {
+                        let from = create_car.router.get_path().get_req().start;
+                        self.trips.ridehail_dispatch_failed(
+                            self.time,
+                            create_car.vehicle,
+                            from,
+                            &mut ctx,
+                        );
+                    }
                 } else {
                     // create_car contains a Path, which is expensive to clone. We need different
                     // parts of create_car after attempting start_car_on_lane.
@@ -615,8 +849,14 @@ impl Sim {
                 );
             }
             Command::UpdateIntersection(i) => {
-                self.intersections
-                    .update_intersection(self.time, i, map, &mut self.scheduler);
+                let detected = self.driving.detect_vehicles(self.time, i, map);
+                self.intersections.update_intersection(
+                    self.time,
+                    i,
+                    map,
+                    &mut self.scheduler,
+                    &detected,
+                );
             }
             Command::Callback(frequency) => {
                 self.scheduler
@@ -634,6 +874,29 @@ impl Sim {
             Command::StartBus(r, _) => {
                 self.start_bus(map.get_tr(r), map);
             }
+            Command::UpdateIncident(_, _) => {
+                // Stop, so the map can be updated before anything else happens
+                self.incidents_changed = true;
+                halt = true;
+            }
+            Command::FinishUnloading(trip) => {
+                self.trips.finish_unloading(self.time, trip, &mut ctx);
+            }
+            Command::DispatchEmergency(idx) => {
+                events.extend(self.dispatch_emergency(idx, map));
+            }
+            Command::SampleLaneSpeeds => {
+                if let Some(ref mut lane_speeds) = self.lane_speeds {
+                    for (l, dist, speed) in self.driving.sample_lane_speeds(self.time) {
+                        lane_speeds.record(l, dist, speed, map);
+                    }
+                    self.scheduler
+                        .push(self.time + LaneSpeeds::FREQUENCY, Command::SampleLaneSpeeds);
+                }
+            }
+            Command::Checkpoint(time) => {
+                self.handle_checkpoint(time);
+            }
         }
 
         // Record events at precisely the time they occur.
@@ -649,6 +912,7 @@ impl Sim {
         events.extend(self.walking.collect_events());
         events.extend(self.intersections.collect_events());
         events.extend(self.parking.collect_events());
+        self.num_events += events.len();
         for ev in events {
             if let Some(ref mut m) = self.pandemic {
                 m.handle_event(self.time, &ev, &mut self.scheduler);
@@ -656,6 +920,14 @@ impl Sim {
             if let Some(ref mut r) = self.recorder {
                 r.handle_event(self.time, &ev, map, &self.driving);
             }
+            for s in &mut self.screenlines {
+                s.handle_event(self.time, &ev, map);
+            }
+
+            self.record_determinism_event(&ev);
+            if let Some(ref mut list) = self.streamed_events {
+                list.push((self.time, ev.clone()));
+            }
 
             self.analytics.event(ev, self.time, map);
         }
@@ -811,7 +1083,11 @@ impl Sim {
         }
 
         let path = self.save_path(self.time);
-        abstio::write_binary(path.clone(), self);
+        if self.uncompressed_savestates {
+            abstio::write_binary(path.clone(), self);
+        } else {
+            abstio::write_compressed_binary(path.clone(), self);
+        }
 
         path
     }
@@ -824,8 +1100,11 @@ impl Sim {
         abstio::find_next_file(self.save_path(base_time))
     }
 
+    /// Savestates are bincode, optionally compressed. A zero-copy format like rkyv would load
+    /// faster still, but every type in the sim, map_model, and geom would have to support it,
+    /// including many from other crates.
     pub fn load_savestate(path: String, timer: &mut Timer) -> Result<Sim> {
-        abstio::maybe_read_binary(path, timer)
+        abstio::maybe_read_binary_mmap(path, timer)
     }
 }
 
@@ -1007,6 +1286,137 @@ impl Sim {
         self.scheduler
             .cancel(Command::Callback(Duration::seconds(1.0)));
     }
+
+    /// Start or stop keeping a copy of every event, for following the simulation from outside.
+    /// The caller has to regularly drain them with `take_streamed_events`.
+    pub fn stream_events(&mut self, enabled: bool) {
+        if !enabled {
+            self.streamed_events = None;
+        } else if self.streamed_events.is_none() {
+            self.streamed_events = Some(Vec::new());
+        }
+    }
+
+    /// Every event since the last call, if `stream_events` is enabled
+    pub fn take_streamed_events(&mut self) -> Vec<(Time, Event)> {
+        self.streamed_events
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+}
+
+// Intersection rules
+impl Sim {
+    /// Override whether drivers at one intersection must wait for space in their target lane
+    /// before entering, regardless of the global setting. Useful for testing box junctions.
+    pub fn set_dont_block_the_box(&mut self, i: IntersectionID, enforced: bool) {
+        self.intersections.set_dont_block_the_box(i, enforced);
+    }
+
+    /// Forget any override from `set_dont_block_the_box`.
+    pub fn reset_dont_block_the_box(&mut self, i: IntersectionID) {
+        self.intersections.reset_dont_block_the_box(i);
+    }
+
+    /// Are drivers at this intersection required to wait for space in their target lane before
+    /// entering?
+    pub fn is_dont_block_the_box_enforced(&self, i: IntersectionID, map: &Map) -> bool {
+        self.intersections.is_dont_block_the_box_enforced(i, map)
+    }
+
+    /// Override whether vehicles through some intersections use the mesoscopic model, regardless
+    /// of the global setting.
+    pub fn set_mesoscopic(&mut self, intersections: Vec<IntersectionID>, mesoscopic: bool) {
+        for i in intersections {
+            self.intersections.set_mesoscopic(i, mesoscopic);
+        }
+    }
+
+    /// Override the model used for every intersection inside an area.
+    pub fn set_mesoscopic_area(&mut self, map: &Map, area: &Polygon, mesoscopic: bool) {
+        let intersections = map
+            .all_intersections()
+            .iter()
+            .filter(|i| area.contains_pt(i.polygon.center()))
+            .map(|i| i.id)
+            .collect();
+        self.set_mesoscopic(intersections, mesoscopic);
+    }
+
+    /// Simulate only the focus areas microscopically, and the rest of the map mesoscopically.
+    /// Vehicles switch models when they reach an intersection of the other kind. So that nobody
+    /// switches partway through an uber-turn, intersection clusters are kept in one model; if any
+    /// member is in focus, the whole cluster is.
+    pub fn set_microscopic_focus(&mut self, map: &Map, areas: &[Polygon]) {
+        let mut micro: BTreeSet<IntersectionID> = map
+            .all_intersections()
+            .iter()
+            .filter(|i| {
+                areas
+                    .iter()
+                    .any(|area| area.contains_pt(i.polygon.center()))
+            })
+            .map(|i| i.id)
+            .collect();
+        for cluster in IntersectionCluster::find_all(map) {
+            if cluster.members.iter().any(|i| micro.contains(i)) {
+                micro.extend(cluster.members);
+            }
+        }
+
+        let (micro, meso): (Vec<IntersectionID>, Vec<IntersectionID>) = map
+            .all_intersections()
+            .iter()
+            .map(|i| i.id)
+            .partition(|i| micro.contains(i));
+        self.set_mesoscopic(micro, false);
+        self.set_mesoscopic(meso, true);
+    }
+
+    /// Is traffic through this intersection simulated with the mesoscopic model?
+    pub fn is_mesoscopic(&self, i: IntersectionID) -> bool {
+        self.intersections.is_mesoscopic(i)
+    }
+}
+
+// Variable speed limits
+impl Sim {
+    /// Put up variable speed limit signs along a corridor. Vehicles entering one of these roads
+    /// afterwards keep to whatever the sign shows.
+    pub fn add_variable_speed_limits(&mut self, limits: VariableSpeedLimits) {
+        self.driving.add_variable_speed_limits(limits);
+    }
+
+    pub fn remove_variable_speed_limit(&mut self, dr: DirectedRoadID) {
+        self.driving.remove_variable_speed_limit(dr);
+    }
+
+    pub fn has_variable_speed_limit(&self, dr: DirectedRoadID) -> bool {
+        self.driving.has_variable_speed_limit(dr)
+    }
+
+    /// None if there's no sign, or it shows the road's normal limit
+    pub fn get_posted_speed_limit(&self, dr: DirectedRoadID) -> Option<Speed> {
+        self.driving.get_posted_speed_limit(dr)
+    }
+}
+
+// Pickup/dropoff zones
+impl Sim {
+    /// Cars heading to this building afterwards stop along the curb in front of it, instead of
+    /// parking. Replaces any existing zone there.
+    pub fn add_pickup_dropoff_zone(&mut self, zone: PickupDropoffZone) {
+        self.driving.add_pickup_dropoff_zone(zone);
+    }
+
+    pub fn remove_pickup_dropoff_zone(&mut self, b: BuildingID) {
+        self.driving.remove_pickup_dropoff_zone(b);
+    }
+
+    pub fn get_pickup_dropoff_zone(&self, b: BuildingID) -> Option<&PickupDropoffZone> {
+        self.driving.get_pickup_dropoff_zone(b)
+    }
 }
 
 // Recording traffic
@@ -1025,6 +1435,51 @@ impl Sim {
     }
 }
 
+// Counting traffic across screenlines
+impl Sim {
+    /// The screenline only counts traffic from now on
+    pub fn add_screenline(&mut self, screenline: Screenline) {
+        self.screenlines.push(screenline);
+    }
+
+    pub fn get_screenlines(&self) -> &Vec<Screenline> {
+        &self.screenlines
+    }
+
+    pub fn clear_screenlines(&mut self) {
+        self.screenlines.clear();
+    }
+}
+
+// Study-specific metrics
+impl Sim {
+    /// The metric sees events from now on. Its results are saved in `Analytics`.
+    pub fn add_custom_metric(&mut self, metric: Box<dyn crate::CustomMetric>) {
+        self.analytics.add_custom_metric(metric);
+    }
+}
+
+// Sampling speeds along lanes
+impl Sim {
+    /// Starts recording vehicle speeds in bins of this length along every lane. Does nothing if
+    /// already recording.
+    pub fn record_lane_speeds(&mut self, bin_length: Distance) {
+        if self.lane_speeds.is_none() {
+            self.lane_speeds = Some(LaneSpeeds::new(bin_length, self.time));
+            self.scheduler.push(self.time, Command::SampleLaneSpeeds);
+        }
+    }
+
+    pub fn get_lane_speeds(&self) -> Option<&LaneSpeeds> {
+        self.lane_speeds.as_ref()
+    }
+
+    pub fn stop_recording_lane_speeds(&mut self) {
+        self.lane_speeds = None;
+        self.scheduler.cancel(Command::SampleLaneSpeeds);
+    }
+}
+
 // Managing highlighted people
 impl Sim {
     pub fn set_highlighted_people(&mut self, people: BTreeSet<PersonID>) {
@@ -1,14 +1,14 @@
 //! All sorts of read-only queries about a simulation
 
 use anyhow::Result;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 
 use abstutil::Counter;
-use geom::{Distance, Duration, PolyLine, Pt2D, Time};
+use geom::{Angle, Distance, Duration, PolyLine, Pt2D, Time};
 use map_model::{
-    BuildingID, IntersectionID, Lane, LaneID, Map, Path, Position, RoadID, TransitRouteID,
-    TransitStopID, Traversable, TurnID,
+    BuildingID, FerryRouteID, IntersectionID, Lane, LaneID, Map, Path, Position, RoadID,
+    TransitRouteID, TransitStopID, Traversable, TurnID,
 };
 use synthpop::{OrigPersonID, Scenario, TripMode};
 
@@ -427,7 +427,10 @@ impl Sim {
                 let max_speed = match info.mode {
                     TripMode::Walk | TripMode::Transit => Some(person.ped_speed),
                     // TODO We should really search the vehicles and grab it from there
-                    TripMode::Drive => None,
+                    TripMode::Drive
+                    | TripMode::Ridehail
+                    | TripMode::Micromobility
+                    | TripMode::ParkAndRide => None,
                     // Assume just one bike
                     TripMode::Bike => {
                         person
@@ -469,6 +472,11 @@ impl Sim {
         self.step_count
     }
 
+    /// How many events have happened since the sim was created or loaded
+    pub fn num_events(&self) -> usize {
+        self.num_events
+    }
+
     pub fn get_draw_car(&self, id: CarID, map: &Map) -> Option<DrawCarInput> {
         self.parking.get_draw_car(id, map).or_else(|| {
             self.driving
@@ -519,9 +527,27 @@ impl Sim {
     /// rendering ones don't.
     pub fn get_unzoomed_agents(&self, map: &Map) -> Vec<UnzoomedAgent> {
         let mut result = self.driving.get_unzoomed_agents(self.time, map);
+        for agent in &mut result {
+            if let AgentID::Car(car) = agent.id {
+                if car.vehicle_type.is_transit() {
+                    agent.occupancy = self.transit.get_passengers(car).len();
+                }
+            }
+        }
         result.extend(self.walking.get_unzoomed_agents(self.time, map));
         result
     }
+    /// Ferries aren't agents; they just follow their route's schedule.
+    pub fn get_ferries(&self, map: &Map) -> Vec<(FerryRouteID, Pt2D, Angle)> {
+        let mut results = Vec::new();
+        for route in map.all_ferry_routes() {
+            for (pt, angle) in route.ferries_at(self.time) {
+                results.push((route.id, pt, angle));
+            }
+        }
+        results
+    }
+
     pub fn get_unzoomed_transit_riders(&self, map: &Map) -> Vec<UnzoomedAgent> {
         self.transit
             .get_unzoomed_transit_riders(self.time, &self.driving, map)
@@ -540,7 +566,7 @@ pub struct AgentProperties {
 
 /// Why is an agent delayed? If there are multiple reasons, arbitrarily pick one -- ie, somebody
 /// could be blocked by two conflicting turns.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub enum DelayCause {
     /// Queued behind someone, or someone's doing a conflicting turn, or someone's eating up space
     /// in a target queue
@@ -57,6 +57,21 @@ impl Sim {
         timer.start_iter("trips for People", scenario.people.len());
         let mut parked_cars: Vec<(Vehicle, BuildingID)> = Vec::new();
         let mut schedule_trips = Vec::new();
+        // The sim options override the scenario
+        let ebike_share = if self.ebike_share > 0.0 {
+            self.ebike_share
+        } else {
+            scenario.ebike_share.clamp(0.0, 1.0)
+        };
+        // Don't consume anything from the main RNG, so the rest of instantiation is the same as
+        // before carpools were modelled
+        let mut occupancy_rng = XorShiftRng::seed_from_u64(rng.clone().gen());
+        // Likewise for electric bikes, with a different seed
+        let mut ebike_rng = {
+            let mut fork = rng.clone();
+            fork.gen::<u64>();
+            XorShiftRng::seed_from_u64(fork.gen())
+        };
         for p in &scenario.people {
             timer.next();
 
@@ -64,8 +79,15 @@ impl Sim {
                 panic!("{}", err);
             }
 
-            let (vehicle_specs, cars_initially_parked_at, vehicle_foreach_trip) =
+            let (mut vehicle_specs, cars_initially_parked_at, vehicle_foreach_trip) =
                 get_vehicles(p, rng);
+            if ebike_share > 0.0 {
+                for spec in &mut vehicle_specs {
+                    if spec.vehicle_type == VehicleType::Bike && ebike_rng.gen_bool(ebike_share) {
+                        *spec = rand_ebike(&mut ebike_rng);
+                    }
+                }
+            }
             let person = self.new_person(p.orig_id, rand_ped_speed(rng), vehicle_specs);
             for (idx, b) in cars_initially_parked_at {
                 parked_cars.push((person.vehicles[idx].clone(), b));
@@ -85,6 +107,11 @@ impl Sim {
                         } else {
                             None
                         },
+                        occupancy: if trip.mode == TripMode::Drive {
+                            rand_occupancy(&mut occupancy_rng)
+                        } else {
+                            1
+                        },
                     },
                     StartTripArgs {
                         retry_if_no_room,
@@ -94,6 +121,9 @@ impl Sim {
             }
         }
 
+        self.trips.add_micromobility(&scenario.micromobility);
+        self.trips.add_park_and_ride(&scenario.park_and_ride);
+
         // parked_cars is stable over map edits, so don't fork.
         parked_cars.shuffle(rng);
         seed_parked_cars(parked_cars, self, map, rng, timer);
@@ -103,6 +133,13 @@ impl Sim {
     }
 }
 
+/// Overnight, a car stays wherever its owner's last trip with it ended. Pick cars to use for
+/// each trip and where they're parked in the morning by replaying the day, starting each new day
+/// with cars where they were left the night before, until nothing changes. A day that leaves a car
+/// somewhere else never settles, so give up after this many. Those cars get driven back overnight
+/// to where they're first needed, rather than buying another car every day.
+const MAX_DAYS: usize = 10;
+
 fn get_vehicles(
     person: &PersonSpec,
     rng: &mut XorShiftRng,
@@ -111,18 +148,80 @@ fn get_vehicles(
     Vec<(usize, BuildingID)>,
     Vec<Option<usize>>,
 ) {
+    let mut day = replay_day(person, Vec::new(), rng);
+    for _ in 1..MAX_DAYS {
+        if day.is_stable() {
+            break;
+        }
+        let overnight = day
+            .end_locations
+            .iter()
+            .map(|(idx, parked_at)| (day.vehicle_specs[*idx].clone(), *parked_at))
+            .collect();
+        day = replay_day(person, overnight, rng);
+    }
+
+    let cars_initially_parked_at = day
+        .start_locations
+        .iter()
+        .filter_map(|(idx, parked_at)| parked_at.map(|b| (*idx, b)))
+        .collect();
+    (
+        day.vehicle_specs,
+        cars_initially_parked_at,
+        day.vehicle_foreach_trip,
+    )
+}
+
+struct DayOfVehicles {
+    vehicle_specs: Vec<VehicleSpec>,
+    vehicle_foreach_trip: Vec<Option<usize>>,
+    /// For each indexed car, is it parked somewhere before the first trip, or off-map?
+    start_locations: Vec<(usize, Option<BuildingID>)>,
+    /// And after the last trip. A car left at a park-and-ride lot counts as off-map.
+    end_locations: Vec<(usize, Option<BuildingID>)>,
+}
+
+impl DayOfVehicles {
+    fn is_stable(&self) -> bool {
+        let mut start = self.start_locations.clone();
+        let mut end = self.end_locations.clone();
+        start.sort();
+        end.sort();
+        start == end
+    }
+}
+
+/// Starting with cars parked overnight, decide which vehicles each trip uses. When nothing is in
+/// the right spot, move a car that isn't used before then, or otherwise create a new one.
+fn replay_day(
+    person: &PersonSpec,
+    overnight: Vec<(VehicleSpec, Option<BuildingID>)>,
+    rng: &mut XorShiftRng,
+) -> DayOfVehicles {
     let mut vehicle_specs = Vec::new();
-    let mut cars_initially_parked_at = Vec::new();
+    let mut start_locations = Vec::new();
     let mut vehicle_foreach_trip = Vec::new();
 
     let mut bike_idx = None;
     // For each indexed car, is it parked somewhere, or off-map?
     let mut car_locations: Vec<(usize, Option<BuildingID>)> = Vec::new();
+    for (spec, parked_at) in overnight {
+        start_locations.push((vehicle_specs.len(), parked_at));
+        car_locations.push((vehicle_specs.len(), parked_at));
+        vehicle_specs.push(spec);
+    }
+    // A car left at some park-and-ride lot, which one only decided when the trip starts
+    let mut left_at_park_and_ride = None;
+    let mut used_cars: HashSet<usize> = HashSet::new();
 
     // TODO If the trip is cancelled, this should be affected...
     for trip in &person.trips {
         let use_for_trip = match trip.mode {
-            TripMode::Walk | TripMode::Transit => None,
+            // Ridehail vehicles belong to the fleet, not the passenger, and so do shared bikes
+            TripMode::Walk | TripMode::Transit | TripMode::Ridehail | TripMode::Micromobility => {
+                None
+            }
             TripMode::Bike => {
                 if bike_idx.is_none() {
                     bike_idx = Some(vehicle_specs.len());
@@ -130,37 +229,60 @@ fn get_vehicles(
                 }
                 bike_idx
             }
-            TripMode::Drive => {
+            TripMode::Drive | TripMode::ParkAndRide => {
                 let need_parked_at = match trip.origin {
                     TripEndpoint::Building(b) => Some(b),
                     _ => None,
                 };
+                // Heading back from a park-and-ride, use the car left at the lot
+                let from_lot = if trip.mode == TripMode::ParkAndRide {
+                    left_at_park_and_ride.take()
+                } else {
+                    None
+                };
 
                 // Any available cars in the right spot?
-                let idx = if let Some(idx) = car_locations
+                let idx = if let Some(idx) = from_lot {
+                    idx
+                } else if let Some(idx) = car_locations
                     .iter()
                     .find(|(_, parked_at)| *parked_at == need_parked_at)
                     .map(|(idx, _)| *idx)
                 {
                     idx
+                } else if let Some(idx) = car_locations
+                    .iter()
+                    .map(|(idx, _)| *idx)
+                    .find(|idx| !used_cars.contains(idx))
+                {
+                    // Nobody's used this car since last night, so drive it over then
+                    start_locations
+                        .iter_mut()
+                        .find(|(i, _)| *i == idx)
+                        .unwrap()
+                        .1 = need_parked_at;
+                    idx
                 } else {
                     // Need a new car, starting in the right spot
                     let idx = vehicle_specs.len();
                     vehicle_specs.push(rand_car(rng));
-                    if let Some(b) = need_parked_at {
-                        cars_initially_parked_at.push((idx, b));
-                    }
+                    start_locations.push((idx, need_parked_at));
                     idx
                 };
 
                 // Where does this car wind up?
+                used_cars.insert(idx);
                 car_locations.retain(|(i, _)| idx != *i);
-                match trip.destination {
-                    TripEndpoint::Building(b) => {
-                        car_locations.push((idx, Some(b)));
-                    }
-                    TripEndpoint::Border(_) | TripEndpoint::SuddenlyAppear(_) => {
-                        car_locations.push((idx, None));
+                if trip.mode == TripMode::ParkAndRide && from_lot.is_none() {
+                    left_at_park_and_ride = Some(idx);
+                } else {
+                    match trip.destination {
+                        TripEndpoint::Building(b) => {
+                            car_locations.push((idx, Some(b)));
+                        }
+                        TripEndpoint::Border(_) | TripEndpoint::SuddenlyAppear(_) => {
+                            car_locations.push((idx, None));
+                        }
                     }
                 }
 
@@ -170,6 +292,10 @@ fn get_vehicles(
         vehicle_foreach_trip.push(use_for_trip);
     }
 
+    if let Some(idx) = left_at_park_and_ride {
+        car_locations.push((idx, None));
+    }
+
     // For debugging
     if false {
         let mut n = vehicle_specs.len();
@@ -181,11 +307,12 @@ fn get_vehicles(
         }
     }
 
-    (
+    DayOfVehicles {
         vehicle_specs,
-        cars_initially_parked_at,
         vehicle_foreach_trip,
-    )
+        start_locations,
+        end_locations: car_locations,
+    }
 }
 
 fn rand_car(rng: &mut XorShiftRng) -> VehicleSpec {
@@ -210,6 +337,19 @@ fn rand_bike(rng: &mut XorShiftRng) -> VehicleSpec {
     }
 }
 
+fn rand_ebike(rng: &mut XorShiftRng) -> VehicleSpec {
+    let max_speed = Some(rand_speed(
+        rng,
+        Speed::km_per_hour(20.0),
+        map_model::MAX_EBIKE_SPEED,
+    ));
+    VehicleSpec {
+        vehicle_type: VehicleType::Bike,
+        length: BIKE_LENGTH,
+        max_speed,
+    }
+}
+
 pub fn rand_dist(rng: &mut XorShiftRng, low: Distance, high: Distance) -> Distance {
     assert!(high > low);
     Distance::meters(rng.gen_range(low.inner_meters()..high.inner_meters()))
@@ -222,6 +362,20 @@ fn rand_speed(rng: &mut XorShiftRng, low: Speed, high: Speed) -> Speed {
     )
 }
 
+/// How many people ride in a car, including the driver. Most cars carry only the driver.
+fn rand_occupancy(rng: &mut XorShiftRng) -> usize {
+    let x: f64 = rng.gen();
+    if x < 0.75 {
+        1
+    } else if x < 0.93 {
+        2
+    } else if x < 0.98 {
+        3
+    } else {
+        4
+    }
+}
+
 pub fn rand_ped_speed(rng: &mut XorShiftRng) -> Speed {
     rand_speed(
         rng,
@@ -0,0 +1,204 @@
+//! A watchdog for long headless runs, like prebaking or benchmarking. Rare failures like gridlock
+//! usually only show up hours into a simulation, and by the time the run finishes, the evidence is
+//! gone. The watchdog periodically looks for trouble, and whenever something new goes wrong, saves
+//! the sim at that moment, so it can be loaded in the UI and debugged later.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use abstutil::prettyprint_usize;
+use geom::{Duration, Time};
+use map_model::{IntersectionID, Map};
+
+use crate::{PersonID, Sim, SimCallback};
+
+/// Detects anomalies during a simulation. Run with `Sim::timed_step_with_watchdog`.
+pub struct Watchdog {
+    /// How often to check
+    pub frequency: Duration,
+    /// An intersection with somebody waiting this long is assumed to be gridlocked
+    pub gridlock_threshold: Duration,
+    /// Anybody waiting this long without moving is stuck
+    pub stuck_threshold: Duration,
+    /// If the number of people waiting grows on this many checks in a row, and at least doubles,
+    /// queues are growing out of control
+    pub queue_growth_checks: usize,
+
+    waiting_samples: Vec<usize>,
+    // Don't report the same problem every time it's checked. Once something's okay again, it can
+    // be reported again.
+    active: BTreeSet<AnomalyKey>,
+    pending: Vec<Anomaly>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Anomaly {
+    Gridlock {
+        intersection: IntersectionID,
+        since: Time,
+    },
+    RunawayQueues {
+        waiting_before: usize,
+        waiting_now: usize,
+    },
+    StuckPerson {
+        person: PersonID,
+        waiting: Duration,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum AnomalyKey {
+    Gridlock(IntersectionID),
+    RunawayQueues,
+    StuckPerson(PersonID),
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Anomaly::Gridlock {
+                intersection,
+                since,
+            } => write!(f, "{} has been gridlocked since {}", intersection, since),
+            Anomaly::RunawayQueues {
+                waiting_before,
+                waiting_now,
+            } => write!(
+                f,
+                "Queues are growing out of control, with {} people waiting, up from {}",
+                prettyprint_usize(*waiting_now),
+                prettyprint_usize(*waiting_before)
+            ),
+            Anomaly::StuckPerson { person, waiting } => {
+                write!(f, "{} has been stuck for {}", person, waiting)
+            }
+        }
+    }
+}
+
+impl Watchdog {
+    pub fn new() -> Watchdog {
+        Watchdog {
+            frequency: Duration::minutes(1),
+            gridlock_threshold: Duration::minutes(30),
+            stuck_threshold: Duration::hours(1),
+            queue_growth_checks: 15,
+
+            waiting_samples: Vec::new(),
+            active: BTreeSet::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns new anomalies since the last check
+    fn check(&mut self, sim: &Sim) -> Vec<Anomaly> {
+        let mut found = Vec::new();
+
+        for (i, since) in sim.delayed_intersections(self.gridlock_threshold) {
+            found.push((
+                AnomalyKey::Gridlock(i),
+                Anomaly::Gridlock {
+                    intersection: i,
+                    since,
+                },
+            ));
+        }
+
+        let waiting = sim.all_waiting_people();
+        for (person, delay) in &waiting {
+            if *delay >= self.stuck_threshold {
+                found.push((
+                    AnomalyKey::StuckPerson(*person),
+                    Anomaly::StuckPerson {
+                        person: *person,
+                        waiting: *delay,
+                    },
+                ));
+            }
+        }
+
+        self.waiting_samples.push(waiting.len());
+        if self.waiting_samples.len() > self.queue_growth_checks + 1 {
+            self.waiting_samples.remove(0);
+        }
+        if self.waiting_samples.len() == self.queue_growth_checks + 1
+            && self
+                .waiting_samples
+                .windows(2)
+                .all(|pair| pair[0] < pair[1])
+            && self.waiting_samples[self.queue_growth_checks] >= 2 * self.waiting_samples[0]
+        {
+            found.push((
+                AnomalyKey::RunawayQueues,
+                Anomaly::RunawayQueues {
+                    waiting_before: self.waiting_samples[0],
+                    waiting_now: waiting.len(),
+                },
+            ));
+        }
+
+        let still_active: BTreeSet<AnomalyKey> = found.iter().map(|(key, _)| *key).collect();
+        let new = found
+            .into_iter()
+            .filter(|(key, _)| !self.active.contains(key))
+            .map(|(_, anomaly)| anomaly)
+            .collect();
+        self.active = still_active;
+        new
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Watchdog {
+        Watchdog::new()
+    }
+}
+
+impl SimCallback for Watchdog {
+    fn run(&mut self, sim: &Sim, _: &Map) -> bool {
+        let new = self.check(sim);
+        self.pending.extend(new);
+        !self.pending.is_empty()
+    }
+}
+
+impl Sim {
+    /// Like `timed_step`, but whenever the watchdog finds a new anomaly, saves the sim and logs
+    /// where. Returns every anomaly, with the path to the savestate from that moment.
+    pub fn timed_step_with_watchdog(
+        &mut self,
+        map: &Map,
+        dt: Duration,
+        watchdog: Watchdog,
+        timer: &mut abstutil::Timer,
+    ) -> Vec<(Anomaly, String)> {
+        let end_time = self.time + dt;
+        self.set_periodic_callback(watchdog.frequency);
+        let mut maybe_cb: Option<Box<dyn SimCallback>> = Some(Box::new(watchdog));
+
+        let mut results = Vec::new();
+        while self.time < end_time {
+            self.timed_step(map, end_time - self.time, &mut maybe_cb, timer);
+            let anomalies: Vec<Anomaly> = maybe_cb
+                .as_mut()
+                .unwrap()
+                .downcast_mut::<Watchdog>()
+                .unwrap()
+                .pending
+                .drain(..)
+                .collect();
+            if anomalies.is_empty() {
+                // Halted for some other reason, like an alert
+                break;
+            }
+            let path = self.save();
+            for anomaly in anomalies {
+                warn!("At {}: {}. Saved the sim to {}", self.time, anomaly, path);
+                results.push((anomaly, path.clone()));
+            }
+        }
+        self.unset_periodic_callback();
+        results
+    }
+}
@@ -3,7 +3,7 @@ use std::collections::{BTreeMap, BTreeSet};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, serialize_btreemap};
-use geom::Time;
+use geom::{Duration, Time};
 use map_model::{Map, Path, TransitRoute, TransitRouteID, TransitStopID};
 
 use crate::sim::Ctx;
@@ -119,8 +119,8 @@ impl TransitSimState {
         );
     }
 
-    /// If true, the bus is idling. If false, the bus actually arrived at a border and should now
-    /// vanish.
+    /// Returns how long the bus dwells at the stop, or None if the bus actually arrived at a
+    /// border and should now vanish.
     ///
     /// TODO Misnomer -- callback from Router::follow_bus_route
     pub fn bus_arrived_at_stop(
@@ -130,7 +130,7 @@ impl TransitSimState {
         trips: &mut TripManager,
         walking: &mut WalkingSimState,
         ctx: &mut Ctx,
-    ) -> bool {
+    ) -> Option<Duration> {
         let bus = self.buses.get_mut(&id).unwrap();
         match bus.state {
             BusState::DrivingToStop(stop_idx) => {
@@ -141,8 +141,11 @@ impl TransitSimState {
 
                 // Deboard existing passengers.
                 let mut still_riding = Vec::new();
+                let mut num_alighting = 0;
+                let mut num_boarding = 0;
                 for (person, maybe_stop2) in bus.passengers.drain(..) {
                     if Some(stop1) == maybe_stop2 {
+                        num_alighting += 1;
                         trips.person_left_bus(now, person, bus.car, ctx);
                         self.events.push(Event::PassengerAlightsTransit(
                             person, bus.car, bus.route, stop1,
@@ -159,6 +162,7 @@ impl TransitSimState {
                     self.peds_waiting.remove(&stop1).unwrap()
                 {
                     if bus.route == route {
+                        num_boarding += 1;
                         let (trip, person) = trips.ped_boarded_bus(
                             now,
                             ped,
@@ -189,7 +193,17 @@ impl TransitSimState {
                     }
                 }
                 self.peds_waiting.insert(stop1, still_waiting);
-                true
+
+                // Nobody plans to use a closed stop, so vehicles pass right by
+                if ctx.map.get_ts(stop1).closed && num_boarding == 0 && num_alighting == 0 {
+                    return Some(Duration::ZERO);
+                }
+                let features = ctx
+                    .map
+                    .get_tr(bus.route)
+                    .boarding
+                    .union(ctx.map.get_ts(stop1).boarding);
+                Some(features.dwell_time(num_boarding, num_alighting))
             }
             BusState::DrivingOffMap => {
                 self.routes
@@ -208,7 +222,7 @@ impl TransitSimState {
                     trips.transit_rider_reached_border(now, person, id, ctx);
                 }
                 bus.state = BusState::Finished;
-                false
+                None
             }
             BusState::AtStop(_) | BusState::Finished => unreachable!(),
         }
@@ -373,6 +387,7 @@ impl TransitSimState {
                     pos,
                     person: Some(*person),
                     parking: false,
+                    occupancy: 1,
                 });
             }
         }
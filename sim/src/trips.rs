@@ -1,25 +1,34 @@
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, serialize_btreemap, Counter};
+use anyhow::Result;
 use geom::{Distance, Duration, Speed, Time};
+
 use map_model::{
-    BuildingID, IntersectionID, Map, PathConstraints, PathRequest, Position, TransitRouteID,
-    TransitStopID,
+    BuildingID, DirectedRoadID, FerryRouteID, FerryTerminalID, IntersectionID, LaneID, Map,
+    ParkingLotID, Path, PathConstraints, PathRequest, PathfinderCaching, Position, TransitRouteID,
+    TransitStopID, FERRY_CAPACITY,
 };
 use synthpop::{
-    IndividTrip, OrigPersonID, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose,
+    IndividTrip, MicromobilityStation, OrigPersonID, PersonSpec, Scenario, SharedVehicleType,
+    TripEndpoint, TripMode, TripPurpose,
 };
 
 use crate::sim::Ctx;
 use crate::{
     AgentID, AgentType, AlertLocation, CarID, Command, CreateCar, CreatePedestrian, DrivingGoal,
-    Event, ParkedCar, ParkingSim, ParkingSpot, PedestrianID, PersonID, SidewalkPOI, SidewalkSpot,
-    StartTripArgs, TransitSimState, TripID, TripPhaseType, TripSpec, Vehicle, VehicleSpec,
-    VehicleType, WalkingSimState,
+    Event, MicromobilityFleet, ParkedCar, ParkingSim, ParkingSimState, ParkingSpot, PedestrianID,
+    PersonID, RidehailFleet, Router, SidewalkPOI, SidewalkSpot, StartTripArgs, TransitSimState,
+    TripID, TripPhaseType, TripSpec, Vehicle, VehicleSpec, VehicleType, WalkingSimState,
+    BIKE_LENGTH, MIN_CAR_LENGTH,
 };
 
+const TIME_TO_PULL_INTO_LOADING_ZONE: Duration = Duration::const_seconds(10.0);
+// Only for delivery stops without a next stop to space them out
+const DEFAULT_UNLOAD_TIME: Duration = Duration::const_seconds(120.0);
+
 /// Manages people, each of which executes some trips through the day. Each trip is further broken
 /// down into legs -- for example, a driving trip might start with somebody walking to their car,
 /// driving somewhere, parking, and then walking to their final destination.
@@ -39,19 +48,51 @@ pub(crate) struct TripManager {
     unfinished_trips: usize,
 
     car_id_counter: usize,
+    ridehail: RidehailFleet,
+    micromobility: MicromobilityFleet,
+    park_and_ride: BTreeSet<ParkingLotID>,
+    /// How many loading zone spaces on each side of a road are taken
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    loading_zones_in_use: BTreeMap<DirectedRoadID, usize>,
+    unloading: BTreeMap<TripID, UnloadingVan>,
+    /// How many people are aboard each ferry over each leg of its route. Keyed by the route, which
+    /// of the day's ferries it is, whether it's heading forwards, and the leg.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    ferry_loads: BTreeMap<(FerryRouteID, usize, bool, usize), usize>,
 
     events: Vec<Event>,
 }
 
+/// A delivery van that found a free space in a loading zone
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct UnloadingVan {
+    side: DirectedRoadID,
+    done_at: Time,
+    /// Set once the van has pulled out of the lane
+    vehicle: Option<Vehicle>,
+}
+
 // Initialization
 impl TripManager {
-    pub fn new() -> TripManager {
+    pub fn new(ridehail_fleet_size: usize) -> TripManager {
         TripManager {
             trips: Vec::new(),
             people: Vec::new(),
             active_trip_mode: BTreeMap::new(),
             unfinished_trips: 0,
             car_id_counter: 0,
+            ridehail: RidehailFleet::new(ridehail_fleet_size),
+            micromobility: MicromobilityFleet::new(),
+            park_and_ride: BTreeSet::new(),
+            loading_zones_in_use: BTreeMap::new(),
+            unloading: BTreeMap::new(),
+            ferry_loads: BTreeMap::new(),
             events: Vec::new(),
         }
     }
@@ -95,6 +136,34 @@ impl TripManager {
         id
     }
 
+    /// Places shared bikes and e-scooters at the start of the day
+    pub fn add_micromobility(&mut self, stations: &[MicromobilityStation]) {
+        for station in stations {
+            for _ in 0..station.vehicles {
+                let vehicle = VehicleSpec {
+                    vehicle_type: VehicleType::Bike,
+                    length: BIKE_LENGTH,
+                    max_speed: Some(match station.vehicle_type {
+                        SharedVehicleType::Bike => Speed::miles_per_hour(9.0),
+                        SharedVehicleType::EScooter => Speed::miles_per_hour(12.4),
+                    }),
+                }
+                .make(
+                    CarID {
+                        id: self.new_car_id(),
+                        vehicle_type: VehicleType::Bike,
+                    },
+                    None,
+                );
+                self.micromobility.add_vehicle(vehicle, station);
+            }
+        }
+    }
+
+    pub fn add_park_and_ride(&mut self, lots: &BTreeSet<ParkingLotID>) {
+        self.park_and_ride.extend(lots.iter().cloned());
+    }
+
     pub fn new_trip(&mut self, person: PersonID, info: TripInfo) -> TripID {
         let id = TripID(self.trips.len());
         let trip = Trip {
@@ -207,10 +276,14 @@ impl TripManager {
                     constraints,
                 );
                 let person = person.id;
+                let occupancy = self.trips[trip.0].info.occupancy;
 
-                match ctx.map.pathfind(req) {
+                match pathfind_for_car(ctx.map, req, occupancy, now) {
                     Ok(path) => {
-                        let router = goal.make_router(vehicle.id, path, ctx.map);
+                        let router = self
+                            .make_drive_router(trip, &goal, vehicle.id, path, ctx.map)
+                            .with_occupancy(occupancy)
+                            .with_next_departure(self.next_departure(person, trip));
                         ctx.scheduler.push(
                             now,
                             Command::SpawnCar(
@@ -382,7 +455,7 @@ impl TripManager {
                     );
                 }
             }
-            TripSpec::UsingTransit { start, stop1, .. } => {
+            TripSpec::UsingTransit { start, .. } | TripSpec::UsingFerry { start, .. } => {
                 assert_eq!(
                     person.state,
                     match start.connection {
@@ -410,7 +483,11 @@ impl TripManager {
                 );
                 person.state = PersonState::Trip(trip);
 
-                let walk_to = SidewalkSpot::bus_stop(stop1, ctx.map);
+                // Walk to wherever they board
+                let walk_to = match self.trips[trip.0].legs[0] {
+                    TripLeg::Walk(ref spot) => spot.clone(),
+                    _ => unreachable!(),
+                };
                 let req = PathRequest::walking(start.sidewalk_pos, walk_to.sidewalk_pos);
                 match ctx.map.pathfind(req) {
                     Ok(path) => {
@@ -432,6 +509,29 @@ impl TripManager {
                     }
                 }
             }
+            TripSpec::UsingRidehail { start, .. } => {
+                assert_eq!(person.state, PersonState::Inside(start));
+                person.state = PersonState::Trip(trip);
+
+                // They wait inside until the vehicle arrives
+                self.events.push(Event::TripPhaseStarting(
+                    trip,
+                    person.id,
+                    None,
+                    TripPhaseType::WaitingForRidehail,
+                ));
+                self.request_ridehail(now, trip, ctx);
+            }
+            TripSpec::UsingMicromobility { start, goal } => {
+                assert_eq!(person.state, PersonState::Inside(start));
+                person.state = PersonState::Trip(trip);
+                self.start_micromobility(now, trip, start, goal, ctx);
+            }
+            TripSpec::UsingParkAndRide { car, start, goal } => {
+                assert_eq!(person.state, PersonState::Inside(start));
+                person.state = PersonState::Trip(trip);
+                self.start_park_and_ride(now, trip, car, start, goal, ctx);
+            }
         }
     }
 
@@ -466,7 +566,7 @@ impl TripManager {
         trip.total_distance += distance_crossed;
 
         match trip.legs.pop_front() {
-            Some(TripLeg::Drive(c, DrivingGoal::ParkNear(_))) => {
+            Some(TripLeg::Drive(c, DrivingGoal::ParkNear(_) | DrivingGoal::ParkInLot(_))) => {
                 assert_eq!(car, c);
             }
             _ => unreachable!(),
@@ -546,10 +646,14 @@ impl TripManager {
         };
 
         let person = trip.person;
+        let occupancy = trip.info.occupancy;
         let trip = trip.id;
-        match ctx.map.pathfind(req) {
+        match pathfind_for_car(ctx.map, req, occupancy, now) {
             Ok(path) => {
-                let router = drive_to.make_router(parked_car.vehicle.id, path, ctx.map);
+                let router = self
+                    .make_drive_router(trip, &drive_to, parked_car.vehicle.id, path, ctx.map)
+                    .with_occupancy(occupancy)
+                    .with_next_departure(self.next_departure(person, trip));
                 ctx.scheduler.push(
                     now,
                     Command::SpawnCar(
@@ -586,6 +690,7 @@ impl TripManager {
         trip.assert_walking_leg(spot.clone());
         let (bike, drive_to) = match trip.legs[0] {
             TripLeg::Drive(bike, ref to) => (bike, to.clone()),
+            TripLeg::RideShared(bike, b) => (bike, DrivingGoal::ParkNear(b)),
             _ => unreachable!(),
         };
         let driving_pos = match spot.connection {
@@ -621,15 +726,21 @@ impl TripManager {
         };
         match maybe_router {
             Ok(router) => {
+                let vehicle = if self.micromobility.is_rented(bike) {
+                    let (vehicle, from) = self.micromobility.pick_up(bike);
+                    self.events.push(Event::SharedVehicleTaken(from));
+                    vehicle
+                } else {
+                    let vehicle = self.people[trip.person.0].get_vehicle(bike);
+                    if vehicle.is_ebike() {
+                        self.events.push(Event::EBikeRideStarted(trip.id));
+                    }
+                    vehicle
+                };
                 ctx.scheduler.push(
                     now,
                     Command::SpawnCar(
-                        CreateCar::for_appearing(
-                            self.people[trip.person.0].get_vehicle(bike),
-                            router,
-                            trip.id,
-                            trip.person,
-                        ),
+                        CreateCar::for_appearing(vehicle, router, trip.id, trip.person),
                         true,
                     ),
                 );
@@ -662,6 +773,11 @@ impl TripManager {
             Some(TripLeg::Drive(c, DrivingGoal::ParkNear(_))) => {
                 assert_eq!(c, bike);
             }
+            Some(TripLeg::RideShared(c, _)) => {
+                assert_eq!(c, bike);
+                let b = self.micromobility.drop_off(bike);
+                self.events.push(Event::SharedVehicleLeft(b));
+            }
             _ => unreachable!(),
         };
 
@@ -775,6 +891,134 @@ impl TripManager {
         (trip.id, trip.person)
     }
 
+    /// Returns the route and when the next ferry departs. If there are no more ferries today, the
+    /// trip is cancelled, and the pedestrian should be removed.
+    pub fn ped_reached_ferry_terminal(
+        &mut self,
+        now: Time,
+        ped: PedestrianID,
+        terminal: FerryTerminalID,
+        blocked_time: Duration,
+        distance_crossed: Distance,
+        ctx: &mut Ctx,
+    ) -> Option<(FerryRouteID, Time)> {
+        let id = self.active_trip_mode[&AgentID::Pedestrian(ped)];
+        let trip = &mut self.trips[id.0];
+        trip.total_blocked_time += blocked_time;
+        trip.total_distance += distance_crossed;
+
+        trip.assert_walking_leg(SidewalkSpot::ferry_terminal(terminal, ctx.map));
+        let (route, terminal2) = match trip.legs[0] {
+            TripLeg::RideFerry(route, t1, t2) => {
+                assert_eq!(t1, terminal);
+                (route, t2)
+            }
+            _ => unreachable!(),
+        };
+        match ctx
+            .map
+            .get_ferry_route(route)
+            .next_crossing(terminal, terminal2, now)
+        {
+            Some((_, depart, _)) => {
+                self.events.push(Event::TripPhaseStarting(
+                    trip.id,
+                    trip.person,
+                    None,
+                    TripPhaseType::WaitingForFerry(route, terminal),
+                ));
+                Some((route, depart))
+            }
+            None => {
+                self.active_trip_mode
+                    .remove(&AgentID::Pedestrian(ped))
+                    .unwrap();
+                self.cancel_trip(
+                    now,
+                    id,
+                    format!("no more ferries today from {}", terminal),
+                    None,
+                    ctx,
+                );
+                None
+            }
+        }
+    }
+
+    /// The ferry a pedestrian is waiting for is leaving. If it's full, returns when the next one
+    /// leaves, and they should keep waiting. Otherwise, they board it, or their trip is cancelled
+    /// if there are no more ferries today. Either way, the caller should remove them from the
+    /// walking simulation. They'll reappear at the other terminal when the ferry arrives.
+    pub fn ped_boarding_ferry(
+        &mut self,
+        now: Time,
+        ped: PedestrianID,
+        blocked_time: Duration,
+        ctx: &mut Ctx,
+    ) -> Option<Time> {
+        let id = self.active_trip_mode[&AgentID::Pedestrian(ped)];
+        let (route, terminal1, terminal2) = match self.trips[id.0].legs[0] {
+            TripLeg::RideFerry(route, t1, t2) => (route, t1, t2),
+            _ => unreachable!(),
+        };
+        let ferry_route = ctx.map.get_ferry_route(route);
+        let (vessel, _, arrive) = ferry_route
+            .next_crossing(terminal1, terminal2, now)
+            .unwrap();
+        let (forwards, legs) = ferry_route.legs_between(terminal1, terminal2).unwrap();
+        let full = legs.clone().any(|leg| {
+            self.ferry_loads
+                .get(&(route, vessel, forwards, leg))
+                .map(|load| *load >= FERRY_CAPACITY)
+                .unwrap_or(false)
+        });
+        if full {
+            if let Some((_, depart, _)) =
+                ferry_route.next_crossing(terminal1, terminal2, now + Duration::EPSILON)
+            {
+                return Some(depart);
+            }
+            self.active_trip_mode
+                .remove(&AgentID::Pedestrian(ped))
+                .unwrap();
+            self.trips[id.0].total_blocked_time += blocked_time;
+            self.cancel_trip(
+                now,
+                id,
+                format!("the last ferry from {} today is full", terminal1),
+                None,
+                ctx,
+            );
+            return None;
+        }
+        for leg in legs {
+            *self
+                .ferry_loads
+                .entry((route, vessel, forwards, leg))
+                .or_insert(0) += 1;
+        }
+
+        self.active_trip_mode
+            .remove(&AgentID::Pedestrian(ped))
+            .unwrap();
+        let trip = &mut self.trips[id.0];
+        trip.total_blocked_time += blocked_time;
+        trip.legs.pop_front();
+        self.events.push(Event::TripPhaseStarting(
+            trip.id,
+            trip.person,
+            None,
+            TripPhaseType::RidingFerry(route, terminal1),
+        ));
+        self.spawn_ped(
+            arrive,
+            id,
+            SidewalkSpot::ferry_terminal(terminal2, ctx.map),
+            ctx,
+        );
+        None
+    }
+
     // TODO Need to characterize delay the bus experienced
     pub fn person_left_bus(&mut self, now: Time, person: PersonID, bus: CarID, ctx: &mut Ctx) {
         let trip = &mut self.trips[self
@@ -894,6 +1138,100 @@ impl TripManager {
         self.trip_finished(now, id, ctx);
     }
 
+    /// The car stopped at a pickup/dropoff zone and the passenger went inside. The driver leaving
+    /// afterwards isn't simulated; the car is just moved to free parking nearby, so the person can
+    /// use it for later trips.
+    ///
+    /// Ridehail vehicles stop at the curb too. If one just picked somebody up, returns where it
+    /// drives them next.
+    pub fn car_dropped_off(
+        &mut self,
+        now: Time,
+        vehicle: Vehicle,
+        stopped_at: Position,
+        blocked_time: Duration,
+        distance_crossed: Distance,
+        ctx: &mut Ctx,
+    ) -> Option<(Router, (TripID, PersonID))> {
+        if self.ridehail.owns(vehicle.id) {
+            return self.ridehail_stopped(
+                now,
+                vehicle,
+                stopped_at,
+                blocked_time,
+                distance_crossed,
+                ctx,
+            );
+        }
+
+        let trip = &mut self.trips[self
+            .active_trip_mode
+            .remove(&AgentID::Car(vehicle.id))
+            .unwrap()
+            .0];
+        trip.total_blocked_time += blocked_time;
+        trip.total_distance += distance_crossed;
+        let id = trip.id;
+
+        if let Some(unloading) = self.unloading.get_mut(&id) {
+            // The van pulled into a loading zone, and the driver's still unloading
+            unloading.vehicle = Some(vehicle);
+            ctx.scheduler
+                .push(unloading.done_at, Command::FinishUnloading(id));
+            self.events.push(Event::TripPhaseStarting(
+                id,
+                self.trips[id.0].person,
+                None,
+                TripPhaseType::Unloading,
+            ));
+            return None;
+        }
+        self.finish_at_curb(now, id, vehicle, ctx);
+        None
+    }
+
+    /// A delivery van's finished unloading in a loading zone, freeing up its space
+    pub fn finish_unloading(&mut self, now: Time, id: TripID, ctx: &mut Ctx) {
+        // The trip might've been cancelled meanwhile
+        if let Some(unloading) = self.unloading.remove(&id) {
+            self.free_loading_zone_space(unloading.side);
+            self.finish_at_curb(now, id, unloading.vehicle.unwrap(), ctx);
+        }
+    }
+
+    /// After stopping at the curb instead of parking, the person goes inside and the car parks
+    /// somewhere nearby for later.
+    fn finish_at_curb(&mut self, now: Time, id: TripID, vehicle: Vehicle, ctx: &mut Ctx) {
+        let trip = &mut self.trips[id.0];
+        let b = match trip.legs.pop_front() {
+            Some(TripLeg::Drive(c, DrivingGoal::ParkNear(b))) => {
+                assert_eq!(vehicle.id, c);
+                b
+            }
+            _ => unreachable!(),
+        };
+        // No need to walk from a parking spot
+        match trip.legs.pop_front() {
+            Some(TripLeg::Walk(_)) => {}
+            _ => unreachable!(),
+        }
+        assert!(trip.legs.is_empty());
+
+        let person = trip.person;
+        self.people[person.0].state = PersonState::Inside(b);
+        self.events.push(Event::PersonEntersBuilding(person, b));
+        if warp_car_near(now, vehicle, b, ctx).is_none() {
+            self.events.push(Event::Alert(
+                AlertLocation::Person(person),
+                format!(
+                    "{} was dropped off at {}, but there's nowhere to park the car later",
+                    person, b
+                ),
+            ));
+        }
+        self.trip_finished(now, id, ctx);
+    }
+
     fn trip_finished(&mut self, now: Time, id: TripID, ctx: &mut Ctx) {
         let trip = &mut self.trips[id.0];
         assert!(trip.legs.is_empty());
@@ -931,10 +1269,32 @@ impl TripManager {
 
     fn spawn_ped(&mut self, now: Time, id: TripID, start: SidewalkSpot, ctx: &mut Ctx) {
         let trip = &self.trips[id.0];
-        let walk_to = match trip.legs[0] {
+        let mut walk_to = match trip.legs[0] {
             TripLeg::Walk(ref to) => to.clone(),
             _ => unreachable!(),
         };
+        // Walking back to a car left somewhere earlier
+        if walk_to.connection == SidewalkPOI::DeferredParkingSpot {
+            let car = match trip.legs[1] {
+                TripLeg::Drive(car, _) => car,
+                _ => unreachable!(),
+            };
+            match ctx.parking.lookup_parked_car(car) {
+                Some(parked_car) => {
+                    walk_to = SidewalkSpot::parking_spot(parked_car.spot, ctx.map, ctx.parking);
+                }
+                None => {
+                    self.cancel_trip(
+                        now,
+                        id,
+                        format!("should have {} parked somewhere, but it's unavailable", car),
+                        None,
+                        ctx,
+                    );
+                    return;
+                }
+            }
+        }
 
         let req = PathRequest::walking(start.sidewalk_pos, walk_to.sidewalk_pos);
         match ctx.map.pathfind(req) {
@@ -960,6 +1320,426 @@ impl TripManager {
     }
 }
 
+// Ridehail
+impl TripManager {
+    /// Sends the closest free ridehail vehicle to the start of the trip, or waits for one to free
+    /// up
+    fn request_ridehail(&mut self, now: Time, trip: TripID, ctx: &mut Ctx) {
+        if let Some(size) = self.ridehail.size_to_create() {
+            self.create_ridehail_fleet(size, ctx.map);
+        }
+        let pickup = match self.trips[trip.0].info.start {
+            TripEndpoint::Building(b) => b,
+            _ => unreachable!(),
+        };
+        match self
+            .ridehail
+            .take_closest(ctx.map.get_b(pickup).label_center, ctx.map)
+        {
+            Some((vehicle, pos)) => {
+                self.dispatch_ridehail(now, vehicle, pos, trip, ctx);
+            }
+            None => {
+                self.ridehail.enqueue(trip);
+            }
+        }
+    }
+
+    /// Spreads the vehicles evenly among buildings, in no particular order
+    fn create_ridehail_fleet(&mut self, size: usize, map: &Map) {
+        let start_positions: Vec<Position> = map
+            .all_buildings()
+            .iter()
+            .filter_map(|b| b.driving_connection(map).map(|(pos, _)| pos))
+            .collect();
+        if size == 0 || start_positions.is_empty() {
+            return;
+        }
+        let step = (start_positions.len() / size).max(1);
+        for pos in start_positions.into_iter().step_by(step).take(size) {
+            let vehicle = VehicleSpec {
+                vehicle_type: VehicleType::Car,
+                length: MIN_CAR_LENGTH,
+                max_speed: None,
+            }
+            .make(
+                CarID {
+                    id: self.new_car_id(),
+                    vehicle_type: VehicleType::Car,
+                },
+                None,
+            );
+            self.ridehail.add_idle(vehicle, pos);
+        }
+    }
+
+    fn dispatch_ridehail(
+        &mut self,
+        now: Time,
+        vehicle: Vehicle,
+        from: Position,
+        trip: TripID,
+        ctx: &mut Ctx,
+    ) {
+        let pickup = match self.trips[trip.0].info.start {
+            TripEndpoint::Building(b) => b,
+            _ => unreachable!(),
+        };
+        let req = PathRequest::vehicle(
+            from,
+            DrivingGoal::ParkNear(pickup)
+                .goal_pos(PathConstraints::Car, ctx.map)
+                .unwrap(),
+            PathConstraints::Car,
+        );
+        match pathfind_for_car(ctx.map, req, 1, now) {
+            Ok(path) => {
+                self.ridehail.assign(vehicle.id, trip);
+                ctx.scheduler.push(
+                    now,
+                    Command::SpawnCar(
+                        CreateCar {
+                            router: Router::stop_at_curb(vehicle.id, path, pickup),
+                            vehicle,
+                            maybe_parked_car: None,
+                            trip_and_person: None,
+                            maybe_route: None,
+                        },
+                        true,
+                    ),
+                );
+            }
+            Err(err) => {
+                let car = vehicle.id;
+                self.ridehail.add_idle(vehicle, from);
+                self.cancel_trip(
+                    now,
+                    trip,
+                    format!("ridehail {} can't reach the pickup: {}", car, err),
+                    None,
+                    ctx,
+                );
+            }
+        }
+    }
+
+    /// A ridehail vehicle can take another request. The oldest waiting one gets it; otherwise the
+    /// vehicle waits here.
+    fn ridehail_vehicle_free(&mut self, now: Time, vehicle: Vehicle, pos: Position, ctx: &mut Ctx) {
+        // Spawning later needs room for the whole vehicle behind its front
+        let pos = Position::new(
+            pos.lane(),
+            pos.dist_along()
+                .max(vehicle.length)
+                .min(ctx.map.get_l(pos.lane()).length()),
+        );
+        match self.ridehail.next_request() {
+            Some(trip) => {
+                self.dispatch_ridehail(now, vehicle, pos, trip, ctx);
+            }
+            None => {
+                self.ridehail.add_idle(vehicle, pos);
+            }
+        }
+    }
+
+    /// A ridehail vehicle finished dwelling at the curb, either after dropping off its passenger,
+    /// or picking somebody up. In the second case, returns the route to the destination.
+    fn ridehail_stopped(
+        &mut self,
+        now: Time,
+        vehicle: Vehicle,
+        stopped_at: Position,
+        blocked_time: Duration,
+        distance_crossed: Distance,
+        ctx: &mut Ctx,
+    ) -> Option<(Router, (TripID, PersonID))> {
+        let id = self.ridehail.unassign(vehicle.id);
+
+        if self.active_trip_mode.get(&AgentID::Car(vehicle.id)) == Some(&id) {
+            self.active_trip_mode.remove(&AgentID::Car(vehicle.id));
+            self.events
+                .push(Event::RidehailDrove(vehicle.id, distance_crossed, true));
+            let trip = &mut self.trips[id.0];
+            trip.total_blocked_time += blocked_time;
+            trip.total_distance += distance_crossed;
+            let b = match trip.legs.pop_front() {
+                Some(TripLeg::Ridehail(b)) => b,
+                _ => unreachable!(),
+            };
+            assert!(trip.legs.is_empty());
+
+            let person = trip.person;
+            self.people[person.0].state = PersonState::Inside(b);
+            self.events.push(Event::PersonEntersBuilding(person, b));
+            self.trip_finished(now, id, ctx);
+            self.ridehail_vehicle_free(now, vehicle, stopped_at, ctx);
+            return None;
+        }
+
+        self.events
+            .push(Event::RidehailDrove(vehicle.id, distance_crossed, false));
+        let trip = &self.trips[id.0];
+        if trip.info.cancellation_reason.is_some() {
+            // The trip was cancelled while the vehicle was on the way
+            self.ridehail_vehicle_free(now, vehicle, stopped_at, ctx);
+            return None;
+        }
+        let (start, goal) = match (trip.info.start, &trip.legs[0]) {
+            (TripEndpoint::Building(start), TripLeg::Ridehail(goal)) => (start, *goal),
+            _ => unreachable!(),
+        };
+        let person = trip.person;
+        // The driver counts too
+        let occupancy = trip.info.occupancy + 1;
+        let req = PathRequest::vehicle(
+            stopped_at,
+            DrivingGoal::ParkNear(goal)
+                .goal_pos(PathConstraints::Car, ctx.map)
+                .unwrap(),
+            PathConstraints::Car,
+        );
+        match pathfind_for_car(ctx.map, req.clone(), occupancy, now) {
+            Ok(path) => {
+                self.ridehail.assign(vehicle.id, id);
+                self.agent_starting_trip_leg(AgentID::Car(vehicle.id), id);
+                self.events.push(Event::PersonLeavesBuilding(person, start));
+                self.events.push(Event::TripPhaseStarting(
+                    id,
+                    person,
+                    Some(req),
+                    TripPhaseType::RidingRidehail(vehicle.id),
+                ));
+                let router = Router::stop_at_curb(vehicle.id, path, goal).with_occupancy(occupancy);
+                Some((router, (id, person)))
+            }
+            Err(err) => {
+                self.cancel_trip(now, id, err.to_string(), None, ctx);
+                self.ridehail_vehicle_free(now, vehicle, stopped_at, ctx);
+                None
+            }
+        }
+    }
+
+    /// The path of a ridehail vehicle heading to a pickup broke before it could start driving.
+    /// Dispatch again.
+    pub fn ridehail_dispatch_failed(
+        &mut self,
+        now: Time,
+        vehicle: Vehicle,
+        from: Position,
+        ctx: &mut Ctx,
+    ) {
+        let trip = self.ridehail.unassign(vehicle.id);
+        self.ridehail.add_idle(vehicle, from);
+        if self.trips[trip.0].info.cancellation_reason.is_none() {
+            self.request_ridehail(now, trip, ctx);
+        }
+    }
+}
+
+// Deliveries
+impl TripManager {
+    /// Delivery vans stop right in front of each building instead of parking
+    fn make_drive_router(
+        &self,
+        trip: TripID,
+        goal: &DrivingGoal,
+        vehicle: CarID,
+        path: Path,
+        map: &Map,
+    ) -> Router {
+        match goal {
+            DrivingGoal::ParkNear(b)
+                if vehicle.vehicle_type == VehicleType::Car
+                    && matches!(self.trips[trip.0].info.purpose, TripPurpose::Delivery) =>
+            {
+                Router::deliver(vehicle, path, *b)
+            }
+            _ => goal.make_router(vehicle, path, map),
+        }
+    }
+
+    /// A delivery van reached the curb in front of its stop. If the loading zone on that side of
+    /// the road has a free space, the van pulls into it, and the driver unloads there. Otherwise
+    /// the van double-parks, blocking the lane the whole time. Returns how long the van blocks the
+    /// lane, and whether it double-parked.
+    pub fn van_reached_curb(
+        &mut self,
+        now: Time,
+        car: CarID,
+        lane: LaneID,
+        map: &Map,
+    ) -> (Duration, bool) {
+        let trip = self.active_trip_mode[&AgentID::Car(car)];
+        let unload_time = self.unload_time(trip);
+        let side = map.get_l(lane).get_directed_parent();
+        let capacity = map
+            .get_loading_zones()
+            .spaces
+            .get(&side)
+            .cloned()
+            .unwrap_or(0);
+        let in_use = self.loading_zones_in_use.entry(side).or_insert(0);
+        if *in_use < capacity {
+            *in_use += 1;
+            self.unloading.insert(
+                trip,
+                UnloadingVan {
+                    side,
+                    done_at: now + unload_time.max(TIME_TO_PULL_INTO_LOADING_ZONE),
+                    vehicle: None,
+                },
+            );
+            (TIME_TO_PULL_INTO_LOADING_ZONE, false)
+        } else {
+            (unload_time, true)
+        }
+    }
+
+    /// Scenarios space out a van's stops by how long each one takes to unload
+    fn unload_time(&self, id: TripID) -> Duration {
+        let trip = &self.trips[id.0];
+        self.next_departure(trip.person, id)
+            .map(|t| t - trip.info.departure)
+            .unwrap_or(DEFAULT_UNLOAD_TIME)
+    }
+
+    fn free_loading_zone_space(&mut self, side: DirectedRoadID) {
+        if let Some(in_use) = self.loading_zones_in_use.get_mut(&side) {
+            *in_use -= 1;
+            if *in_use == 0 {
+                self.loading_zones_in_use.remove(&side);
+            }
+        }
+    }
+}
+
+// Micromobility
+impl TripManager {
+    /// Walk to the closest shared vehicle, or walk the whole way if there's nothing nearby
+    fn start_micromobility(
+        &mut self,
+        now: Time,
+        trip: TripID,
+        start: BuildingID,
+        goal: BuildingID,
+        ctx: &mut Ctx,
+    ) {
+        let mut legs = Vec::new();
+        let rental = self.micromobility.rent(start, goal, ctx.map);
+        if rental.is_none() {
+            self.events.push(Event::NoSharedVehicle(start));
+        }
+        if let Some((car, from, to)) = rental {
+            match (
+                SidewalkSpot::bike_rack(from, ctx.map),
+                SidewalkSpot::bike_rack(to, ctx.map),
+            ) {
+                // Riding along the same sidewalk is silly
+                (Some(pick_up), Some(leave))
+                    if pick_up.sidewalk_pos.lane() != leave.sidewalk_pos.lane() =>
+                {
+                    legs.push(TripLeg::Walk(pick_up));
+                    legs.push(TripLeg::RideShared(car, to));
+                }
+                _ => {
+                    self.micromobility.cancel_rental(car);
+                }
+            }
+        }
+        legs.push(TripLeg::Walk(SidewalkSpot::building(goal, ctx.map)));
+        self.trips[trip.0].legs.extend(legs);
+        self.spawn_ped(now, trip, SidewalkSpot::building(start, ctx.map), ctx);
+    }
+}
+
+// Park-and-ride
+impl TripManager {
+    /// Decides the legs of a park-and-ride trip. Heading out, people drive to a lot with room and
+    /// ride transit from there; heading back with their car in a lot, they ride transit to the
+    /// lot and drive home. When transit doesn't help, they just drive.
+    fn start_park_and_ride(
+        &mut self,
+        now: Time,
+        trip: TripID,
+        car: CarID,
+        start: BuildingID,
+        goal: BuildingID,
+        ctx: &mut Ctx,
+    ) {
+        let start_spot = SidewalkSpot::building(start, ctx.map);
+        let goal_spot = SidewalkSpot::building(goal, ctx.map);
+        let mut legs = None;
+
+        let parked_in = ctx
+            .parking
+            .lookup_parked_car(car)
+            .and_then(|parked_car| match parked_car.spot {
+                ParkingSpot::Lot(pl, _) if self.park_and_ride.contains(&pl) => Some(pl),
+                _ => None,
+            });
+        if let Some(pl) = parked_in {
+            if let Some((stop1, Some(stop2), route)) = ctx
+                .map
+                .should_use_transit(start_spot.sidewalk_pos, ctx.map.get_pl(pl).sidewalk_pos)
+            {
+                legs = Some(vec![
+                    TripLeg::Walk(SidewalkSpot::bus_stop(stop1, ctx.map)),
+                    TripLeg::RideBus(route, Some(stop2)),
+                    TripLeg::Walk(SidewalkSpot::deferred_parking_spot()),
+                    TripLeg::Drive(car, DrivingGoal::ParkNear(goal)),
+                    TripLeg::Walk(goal_spot.clone()),
+                ]);
+            }
+        } else {
+            let lots: Vec<ParkingLotID> = self
+                .park_and_ride
+                .iter()
+                .filter(|pl| self.park_and_ride_has_room(**pl, ctx.parking))
+                .cloned()
+                .collect();
+            if let Some((pl, (stop1, Some(stop2), route))) = ctx.map.should_use_park_and_ride(
+                ctx.map.get_b(start).label_center,
+                goal_spot.sidewalk_pos,
+                lots,
+            ) {
+                legs = Some(vec![
+                    TripLeg::Walk(SidewalkSpot::deferred_parking_spot()),
+                    TripLeg::Drive(car, DrivingGoal::ParkInLot(pl)),
+                    TripLeg::Walk(SidewalkSpot::bus_stop(stop1, ctx.map)),
+                    TripLeg::RideBus(route, Some(stop2)),
+                    TripLeg::Walk(goal_spot.clone()),
+                ]);
+            }
+        }
+
+        let legs = legs.unwrap_or_else(|| {
+            vec![
+                TripLeg::Walk(SidewalkSpot::deferred_parking_spot()),
+                TripLeg::Drive(car, DrivingGoal::ParkNear(goal)),
+                TripLeg::Walk(goal_spot),
+            ]
+        });
+        self.trips[trip.0].legs.extend(legs);
+        self.spawn_ped(now, trip, start_spot, ctx);
+    }
+
+    /// Counting cars already on the way there
+    fn park_and_ride_has_room(&self, pl: ParkingLotID, parking: &ParkingSimState) -> bool {
+        let incoming = self
+            .active_trip_mode
+            .values()
+            .filter(|t| {
+                self.trips[t.0].legs.iter().take(2).any(
+                    |leg| matches!(leg, TripLeg::Drive(_, DrivingGoal::ParkInLot(x)) if *x == pl),
+                )
+            })
+            .count();
+        parking.get_free_lot_spots(pl).len() > incoming
+    }
+}
+
 // Cancelling trips
 impl TripManager {
     /// Cancel a trip before it's started. The person will stay where they are.
@@ -978,9 +1758,17 @@ impl TripManager {
         now: Time,
         id: TripID,
         reason: String,
-        abandoned_vehicle: Option<Vehicle>,
+        mut abandoned_vehicle: Option<Vehicle>,
         ctx: &mut Ctx,
     ) {
+        // A van stopping at a loading zone frees its space
+        if let Some(unloading) = self.unloading.remove(&id) {
+            self.free_loading_zone_space(unloading.side);
+            if unloading.vehicle.is_some() {
+                abandoned_vehicle = unloading.vehicle;
+            }
+        }
+
         let trip = &mut self.trips[id.0];
         self.unfinished_trips -= 1;
         trip.info.cancellation_reason = Some(reason);
@@ -988,6 +1776,26 @@ impl TripManager {
             .push(Event::TripCancelled(trip.id, trip.info.mode));
         let person = trip.person;
 
+        // Somebody waiting for a ridehail is still inside
+        if let Some(TripLeg::Ridehail(_)) = trip.legs.front() {
+            self.ridehail.forget_request(id);
+            let picked_up = self
+                .ridehail
+                .vehicle_for(id)
+                .map(|car| self.active_trip_mode.get(&AgentID::Car(car)) == Some(&id))
+                .unwrap_or(false);
+            if let (false, TripEndpoint::Building(b)) = (picked_up, trip.info.start) {
+                self.events.push(Event::PersonLeavesBuilding(person, b));
+            }
+        }
+
+        // Shared vehicles go back to the fleet
+        for leg in &trip.legs {
+            if let TripLeg::RideShared(car, _) = leg {
+                self.micromobility.cancel_rental(*car);
+            }
+        }
+
         // Maintain consistentency for anyone listening to events
         if let PersonState::Inside(b) = self.people[person.0].state {
             self.events.push(Event::PersonLeavesBuilding(person, b));
@@ -1008,7 +1816,17 @@ impl TripManager {
 
         // Don't forget the car!
         if let Some(vehicle) = abandoned_vehicle {
-            if vehicle.vehicle_type == VehicleType::Car {
+            if self.ridehail.owns(vehicle.id) {
+                // The ridehail vehicle is warped along with its passenger and goes back into
+                // service there
+                self.ridehail.unassign(vehicle.id);
+                if let TripEndpoint::Building(b) = trip.info.end {
+                    let pos = DrivingGoal::ParkNear(b)
+                        .goal_pos(PathConstraints::Car, ctx.map)
+                        .unwrap();
+                    self.ridehail_vehicle_free(now, vehicle, pos, ctx);
+                }
+            } else if vehicle.vehicle_type == VehicleType::Car {
                 // First remove the parked car, if needed. Maybe the trip was cancelled while the
                 // car was parked in the starting building.
                 if let Some(parked_car) = ctx.parking.lookup_parked_car(vehicle.id).cloned() {
@@ -1016,20 +1834,7 @@ impl TripManager {
                 }
 
                 if let TripEndpoint::Building(b) = trip.info.end {
-                    let driving_lane = ctx.map.find_driving_lane_near_building(b);
-                    if let Some(spot) = ctx
-                        .parking
-                        .get_all_free_spots(Position::start(driving_lane), &vehicle, b, ctx.map)
-                        // TODO Could pick something closer, but meh, cancelled trips are bugs
-                        // anyway
-                        .get(0)
-                        .map(|(spot, _)| *spot)
-                        .or_else(|| {
-                            ctx.parking
-                                .path_to_free_parking_spot(driving_lane, &vehicle, b, ctx.map)
-                                .map(|(_, spot, _)| spot)
-                        })
-                    {
+                    if let Some(spot) = warp_car_near(now, vehicle, b, ctx) {
                         self.events.push(Event::Alert(
                             AlertLocation::Person(person),
                             format!(
@@ -1037,12 +1842,6 @@ impl TripManager {
                                 person, spot
                             ),
                         ));
-                        ctx.parking.reserve_spot(spot, vehicle.id);
-                        ctx.parking.add_parked_car(ParkedCar {
-                            vehicle,
-                            spot,
-                            parked_since: now,
-                        });
                     } else {
                         self.events.push(Event::Alert(
                             AlertLocation::Person(person),
@@ -1057,7 +1856,7 @@ impl TripManager {
         } else {
             // If the trip was cancelled because we'e totally out of parking, don't forget to clean
             // this up.
-            if let TripLeg::Drive(c, _) = &trip.legs[0] {
+            if let TripLeg::Drive(c, _) | TripLeg::RideShared(c, _) = &trip.legs[0] {
                 if let Some(t) = self.active_trip_mode.remove(&AgentID::Car(*c)) {
                     assert_eq!(t, trip.id);
                 }
@@ -1102,9 +1901,17 @@ impl TripManager {
 
         let person = &self.people[trip.person.0];
         let a = match &trip.legs[0] {
-            TripLeg::Walk(_) => AgentID::Pedestrian(person.ped),
-            TripLeg::Drive(c, _) => AgentID::Car(*c),
+            // While waiting for a ferry, they're still a pedestrian
+            TripLeg::Walk(_) | TripLeg::RideFerry(_, _, _) => AgentID::Pedestrian(person.ped),
+            TripLeg::Drive(c, _) | TripLeg::RideShared(c, _) => AgentID::Car(*c),
             TripLeg::RideBus(_, _) => AgentID::BusPassenger(person.id, person.on_bus.unwrap()),
+            TripLeg::Ridehail(_) => match self.ridehail.vehicle_for(id) {
+                Some(car) => AgentID::Car(car),
+                // Still waiting for a vehicle
+                None => {
+                    return TripResult::ModeChange;
+                }
+            },
         };
         if self.active_trip_mode.get(&a) == Some(&id) {
             TripResult::Ok(a)
@@ -1215,6 +2022,13 @@ impl TripManager {
         self.unfinished_trips == 0
     }
 
+    /// When the person starts their trip after this one, if they have one
+    fn next_departure(&self, person: PersonID, trip: TripID) -> Option<Time> {
+        let trips = &self.people[person.0].trips;
+        let idx = trips.iter().position(|t| *t == trip)?;
+        trips.get(idx + 1).map(|t| self.trips[t.0].info.departure)
+    }
+
     pub fn trip_info(&self, id: TripID) -> TripInfo {
         self.trips[id.0].info.clone()
     }
@@ -1265,8 +2079,10 @@ impl TripManager {
                     // We can make some assumptions here.
                     let agent_type = match t.info.mode {
                         TripMode::Walk => AgentType::Pedestrian,
-                        TripMode::Bike => AgentType::Bike,
-                        TripMode::Drive => AgentType::Car,
+                        TripMode::Bike | TripMode::Micromobility => AgentType::Bike,
+                        TripMode::Drive | TripMode::Ridehail | TripMode::ParkAndRide => {
+                            AgentType::Car
+                        }
                         // TODO Not true for long. People will be able to spawn at borders already
                         // on a bus.
                         TripMode::Transit => AgentType::Pedestrian,
@@ -1330,6 +2146,9 @@ pub struct TripInfo {
     /// Did a ScenarioModifier apply to this?
     pub modified: bool,
     pub cancellation_reason: Option<String>,
+    /// How many people ride in the vehicle, including the driver. Only meaningful for driving
+    /// trips.
+    pub occupancy: usize,
 }
 
 impl Trip {
@@ -1352,6 +2171,12 @@ pub(crate) enum TripLeg {
     Drive(CarID, DrivingGoal),
     /// Maybe get off at a stop, maybe ride off-map
     RideBus(TransitRouteID, Option<TransitStopID>),
+    /// Board at the first terminal, get off at the second
+    RideFerry(FerryRouteID, FerryTerminalID, FerryTerminalID),
+    /// Get picked up at the trip's start and dropped off at this building
+    Ridehail(BuildingID),
+    /// Ride a shared bike or e-scooter, leaving it outside this building
+    RideShared(CarID, BuildingID),
 }
 
 pub enum TripResult<T> {
@@ -1428,3 +2253,51 @@ pub struct CommutersVehiclesCounts {
     pub bus_riders: usize,
     pub train_riders: usize,
 }
+
+/// Instantly park a car somewhere free near a building, returning the spot. Nobody knows when the
+/// car will be needed again, so it can't go in a spot with a time limit.
+fn warp_car_near(now: Time, vehicle: Vehicle, b: BuildingID, ctx: &mut Ctx) -> Option<ParkingSpot> {
+    let driving_lane = ctx.map.find_driving_lane_near_building(b);
+    let spot = ctx
+        .parking
+        .get_all_free_spots(Position::start(driving_lane), &vehicle, b, ctx.map)
+        // TODO Could pick something closer, but meh
+        .get(0)
+        .map(|(spot, _)| *spot)
+        .or_else(|| {
+            ctx.parking
+                .path_to_free_parking_spot(driving_lane, &vehicle, b, None, ctx.map)
+                .map(|(_, spot, _)| spot)
+        })?;
+    ctx.parking.reserve_spot(spot, vehicle.id);
+    ctx.parking.add_parked_car(ParkedCar {
+        vehicle,
+        spot,
+        parked_since: now,
+    });
+    Some(spot)
+}
+
+/// The main pathfinder assumes one person per car, so it won't use roads where every lane is
+/// reserved for high-occupancy vehicles. Carpools need to route with their real occupancy. It also
+/// assumes the peak congestion charge, so drivers leaving off-peak route with the current charge.
+fn pathfind_for_car(map: &Map, req: PathRequest, occupancy: usize, now: Time) -> Result<Path> {
+    if req.constraints != PathConstraints::Car {
+        return map.pathfind(req);
+    }
+    let mut params = map.routing_params().clone();
+    if occupancy > 1 && map.has_hov_lanes() {
+        params.occupancy = occupancy;
+    }
+    if let Some(charge) = map.get_congestion_charge() {
+        let toll = charge.toll_at(now);
+        if toll != charge.peak_toll() {
+            params.congestion_charge = Some(toll);
+        }
+    }
+    if &params == map.routing_params() {
+        map.pathfind(req)
+    } else {
+        map.pathfind_with_params(req, &params, PathfinderCaching::CacheDijkstra)
+    }
+}
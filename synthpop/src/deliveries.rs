@@ -0,0 +1,196 @@
+//! Home deliveries by van. Pickup points, like parcel lockers or shops, can absorb some of the
+//! parcels for nearby households. The van then makes one consolidated stop there, and customers
+//! walk over to collect their parcels. Compare the number of van stops with and without pickup
+//! points to see if a locker network actually reduces van traffic.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+use abstutil::prettyprint_usize;
+use geom::{Distance, Duration, Time};
+use map_model::{BuildingID, BuildingType, Map};
+
+use crate::{IndividTrip, PersonSpec, TripEndpoint, TripMode, TripPurpose};
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DeliveryDemand {
+    /// The average number of parcels each household receives per day
+    pub parcels_per_household: f64,
+    /// Vans start and end each round here, usually a border near the real depot
+    pub depot: TripEndpoint,
+    /// How many stops a van makes before returning to the depot
+    pub stops_per_round: usize,
+    /// When the vans leave the depot
+    pub depart: Time,
+    /// How long a van takes to unload at each stop, not counting driving. The van waits at the
+    /// curb this long, in a loading zone if there's a free space, or double-parked otherwise.
+    pub time_per_stop: Duration,
+    pub pickup_points: Vec<PickupPoint>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PickupPoint {
+    pub building: BuildingID,
+    /// Households within this straight-line distance may use the pickup point
+    pub catchment: Distance,
+    /// What fraction of parcels for households in the catchment are sent here instead
+    pub share: f64,
+    /// The maximum number of parcels stored here per day
+    pub capacity: usize,
+}
+
+impl DeliveryDemand {
+    /// Create one person for each van round, and one for each household walking to collect their
+    /// parcels from a pickup point in the evening.
+    pub fn generate(&self, map: &Map, rng: &mut XorShiftRng) -> Vec<PersonSpec> {
+        let mut home_stops: BTreeSet<BuildingID> = BTreeSet::new();
+        // Per pickup point, the households collecting something there
+        let mut pickups: BTreeMap<BuildingID, BTreeSet<BuildingID>> = BTreeMap::new();
+        let mut remaining_capacity: Vec<usize> =
+            self.pickup_points.iter().map(|p| p.capacity).collect();
+        let mut num_parcels = 0;
+        let mut num_at_pickups = 0;
+
+        for b in map.all_buildings() {
+            let households = match b.bldg_type {
+                BuildingType::Residential {
+                    num_housing_units, ..
+                } => num_housing_units,
+                BuildingType::ResidentialCommercial(residents, _) => (residents / 2).max(1),
+                BuildingType::Commercial(_) | BuildingType::Empty => 0,
+            };
+            let expected = (households as f64) * self.parcels_per_household;
+            let parcels = expected.floor() as usize + usize::from(rng.gen_bool(expected.fract()));
+
+            // The closest pickup point in range
+            let pickup = self
+                .pickup_points
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, p)| {
+                    let dist = map
+                        .get_b(p.building)
+                        .polygon
+                        .center()
+                        .dist_to(b.polygon.center());
+                    if dist <= p.catchment {
+                        Some((idx, dist))
+                    } else {
+                        None
+                    }
+                })
+                .min_by_key(|(_, dist)| *dist)
+                .map(|(idx, _)| idx);
+
+            for _ in 0..parcels {
+                num_parcels += 1;
+                if let Some(idx) = pickup {
+                    let point = &self.pickup_points[idx];
+                    if remaining_capacity[idx] > 0 && rng.gen_bool(point.share) {
+                        remaining_capacity[idx] -= 1;
+                        num_at_pickups += 1;
+                        pickups.entry(point.building).or_default().insert(b.id);
+                        continue;
+                    }
+                }
+                home_stops.insert(b.id);
+            }
+        }
+
+        let mut stops: Vec<BuildingID> = home_stops.iter().cloned().collect();
+        stops.extend(pickups.keys().cloned());
+        let rounds = self.plan_rounds(map, stops);
+        info!(
+            "{} parcels, with {} at pickup points. {} vans make {} stops, plus {} trips to collect parcels",
+            prettyprint_usize(num_parcels),
+            prettyprint_usize(num_at_pickups),
+            prettyprint_usize(rounds.len()),
+            prettyprint_usize(home_stops.len() + pickups.len()),
+            prettyprint_usize(pickups.values().map(|x| x.len()).sum())
+        );
+
+        let mut people = Vec::new();
+        for round in rounds {
+            let mut trips = Vec::new();
+            let mut from = self.depot;
+            let mut depart = self.depart;
+            for b in round {
+                let to = TripEndpoint::Building(b);
+                trips.push(IndividTrip::new(
+                    depart,
+                    TripPurpose::Delivery,
+                    from,
+                    to,
+                    TripMode::Drive,
+                ));
+                from = to;
+                // The next trip can't start before this one finishes, so this just adds the time
+                // unloading. The simulation uses the gap between departures as the time to
+                // unload.
+                depart += self.time_per_stop;
+            }
+            trips.push(IndividTrip::new(
+                depart,
+                TripPurpose::Work,
+                from,
+                self.depot,
+                TripMode::Drive,
+            ));
+            people.push(PersonSpec {
+                orig_id: None,
+                trips,
+            });
+        }
+
+        for (pickup, households) in pickups {
+            for home in households {
+                let depart = Time::START_OF_DAY
+                    + Duration::hours(17)
+                    + Duration::seconds(rng.gen_range(0.0..3.0 * 3600.0));
+                people.push(PersonSpec {
+                    orig_id: None,
+                    trips: vec![
+                        IndividTrip::new(
+                            depart,
+                            TripPurpose::Shopping,
+                            TripEndpoint::Building(home),
+                            TripEndpoint::Building(pickup),
+                            TripMode::Walk,
+                        ),
+                        IndividTrip::new(
+                            depart + Duration::minutes(2),
+                            TripPurpose::Home,
+                            TripEndpoint::Building(pickup),
+                            TripEndpoint::Building(home),
+                            TripMode::Walk,
+                        ),
+                    ],
+                });
+            }
+        }
+
+        people
+    }
+
+    /// Split stops into rounds by sweeping around the depot, so each van serves one wedge of the
+    /// map.
+    fn plan_rounds(&self, map: &Map, mut stops: Vec<BuildingID>) -> Vec<Vec<BuildingID>> {
+        let depot = self.depot.pt(map);
+        stops.sort_by_key(|b| {
+            let angle = depot.angle_to(map.get_b(*b).polygon.center());
+            (angle.normalized_degrees() * 100.0) as usize
+        });
+        stops
+            .chunks(self.stops_per_round.max(1))
+            .map(|chunk| {
+                // Within each wedge, visit stops from nearest to farthest
+                let mut round = chunk.to_vec();
+                round.sort_by_key(|b| map.get_b(*b).polygon.center().dist_to(depot));
+                round
+            })
+            .collect()
+    }
+}
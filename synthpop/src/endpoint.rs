@@ -37,9 +37,11 @@ impl TripEndpoint {
         let end = to.pos(mode, false, map)?;
         Some(match mode {
             TripMode::Walk | TripMode::Transit => PathRequest::walking(start, end),
-            TripMode::Bike => PathRequest::vehicle(start, end, PathConstraints::Bike),
+            TripMode::Bike | TripMode::Micromobility => {
+                PathRequest::vehicle(start, end, PathConstraints::Bike)
+            }
             // Only cars leaving from a building might turn out from the driveway in a special way
-            TripMode::Drive => {
+            TripMode::Drive | TripMode::Ridehail | TripMode::ParkAndRide => {
                 if matches!(from, TripEndpoint::Building(_)) {
                     PathRequest::leave_from_driveway(start, end, PathConstraints::Car, map)
                 } else {
@@ -52,7 +54,11 @@ impl TripEndpoint {
     fn pos(self, mode: TripMode, from: bool, map: &Map) -> Option<Position> {
         match mode {
             TripMode::Walk | TripMode::Transit => self.sidewalk_pos(map, from),
-            TripMode::Drive | TripMode::Bike => {
+            TripMode::Drive
+            | TripMode::Bike
+            | TripMode::Ridehail
+            | TripMode::Micromobility
+            | TripMode::ParkAndRide => {
                 let constraints = mode.to_constraints();
                 if from {
                     match self {
@@ -0,0 +1,282 @@
+//! Long-distance trips start or end somewhere outside the map. Describe how many of them cross each
+//! border intersection, hour by hour and by mode, then turn those volumes into people.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+use geom::{Duration, PolyLine, Time};
+use map_model::{IntersectionID, Map};
+
+use crate::{
+    ExternalPerson, ExternalTripEndpoint, IndividTrip, MapBorders, PersonSpec, TripEndpoint,
+    TripMode, TripPurpose,
+};
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct GatewayDemand {
+    pub gateways: Vec<Gateway>,
+    /// What fraction of trips entering the map pass straight through, leaving by another gateway
+    /// for the same mode instead of ending at a building
+    pub through_traffic: f64,
+}
+
+/// Traffic for one mode and purpose crossing one border intersection.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Gateway {
+    pub border: IntersectionID,
+    pub mode: TripMode,
+    /// Why people entering here are travelling. Through traffic may leave by a gateway with any
+    /// purpose.
+    #[serde(default = "default_purpose")]
+    pub purpose: TripPurpose,
+    /// For each hour of the day starting at midnight, how many trips enter the map here
+    pub inbound_per_hour: Vec<usize>,
+    /// For each hour of the day starting at midnight, how many trips leave the map here. This
+    /// includes through traffic.
+    pub outbound_per_hour: Vec<usize>,
+}
+
+impl GatewayDemand {
+    /// Derive gateway volumes from the trips of a regional travel demand model. Endpoints outside
+    /// the map boundary are snapped to the nearest border, like `ExternalPerson::import` does.
+    /// Trips entirely inside the map, or between two points outside whose straight line misses
+    /// the map, are ignored.
+    pub fn from_regional_model(map: &Map, people: &[ExternalPerson]) -> GatewayDemand {
+        let borders = MapBorders::new(map);
+        let boundary = map.get_boundary_polygon();
+        let gps_bounds = map.get_gps_bounds();
+        let misses_map = |origin: &ExternalTripEndpoint, destination: &ExternalTripEndpoint| {
+            if let (ExternalTripEndpoint::Position(pt1), ExternalTripEndpoint::Position(pt2)) =
+                (origin, destination)
+            {
+                let pt1 = pt1.to_pt(gps_bounds);
+                let pt2 = pt2.to_pt(gps_bounds);
+                if boundary.contains_pt(pt1) || boundary.contains_pt(pt2) {
+                    return false;
+                }
+                return match PolyLine::new(vec![pt1, pt2]) {
+                    Ok(line) => !boundary.intersects_polyline(&line),
+                    // Both ends are the same point outside the map
+                    Err(_) => true,
+                };
+            }
+            false
+        };
+        // None if the endpoint is inside the map
+        let snap = |endpt: &ExternalTripEndpoint, is_origin: bool, mode: TripMode| match endpt {
+            ExternalTripEndpoint::TripEndpoint(TripEndpoint::Border(i)) => Some(*i),
+            ExternalTripEndpoint::TripEndpoint(_) => None,
+            ExternalTripEndpoint::Position(gps) => {
+                if boundary.contains_pt(gps.to_pt(map.get_gps_bounds())) {
+                    return None;
+                }
+                let (incoming, outgoing) = borders.for_mode(mode);
+                let candidates = if is_origin { incoming } else { outgoing };
+                candidates
+                    .iter()
+                    .min_by_key(|border| border.gps_pos.fast_dist(*gps))
+                    .map(|border| border.i)
+            }
+        };
+
+        let mut demand = GatewayDemand {
+            gateways: Vec::new(),
+            through_traffic: 0.0,
+        };
+        let mut num_inbound = 0;
+        let mut num_through = 0;
+        for person in people {
+            for trip in &person.trips {
+                if trip.departure < Time::START_OF_DAY {
+                    continue;
+                }
+                if misses_map(&trip.origin, &trip.destination) {
+                    continue;
+                }
+                let hour = (trip.departure.inner_seconds() / 3600.0) as usize;
+                let from = snap(&trip.origin, true, trip.mode);
+                let to = snap(&trip.destination, false, trip.mode);
+                if let Some(i) = from {
+                    demand.gateway(i, trip.mode, trip.purpose).add(hour, true);
+                    num_inbound += 1;
+                    if to.is_some() {
+                        num_through += 1;
+                    }
+                }
+                if let Some(i) = to {
+                    demand.gateway(i, trip.mode, trip.purpose).add(hour, false);
+                }
+            }
+        }
+        if num_inbound > 0 {
+            demand.through_traffic = (num_through as f64) / (num_inbound as f64);
+        }
+        demand
+    }
+
+    fn gateway(
+        &mut self,
+        border: IntersectionID,
+        mode: TripMode,
+        purpose: TripPurpose,
+    ) -> &mut Gateway {
+        let idx = match self
+            .gateways
+            .iter()
+            .position(|g| g.border == border && g.mode == mode && g.purpose == purpose)
+        {
+            Some(idx) => idx,
+            None => {
+                self.gateways.push(Gateway {
+                    border,
+                    mode,
+                    purpose,
+                    inbound_per_hour: Vec::new(),
+                    outbound_per_hour: Vec::new(),
+                });
+                self.gateways.len() - 1
+            }
+        };
+        &mut self.gateways[idx]
+    }
+
+    /// Create one person for every trip crossing a gateway. Trips that don't pass through start
+    /// or end at a random building, or are skipped if the map has none.
+    pub fn generate(&self, map: &Map, rng: &mut XorShiftRng) -> Vec<PersonSpec> {
+        // This may be written by hand
+        let through_traffic = if self.through_traffic.is_nan() {
+            0.0
+        } else {
+            self.through_traffic.clamp(0.0, 1.0)
+        };
+        if through_traffic != self.through_traffic {
+            warn!(
+                "through_traffic must be between 0 and 1, not {}. Using {}",
+                self.through_traffic, through_traffic
+            );
+        }
+        // Through traffic uses up some of each gateway's outbound volume
+        let mut remaining_outbound: Vec<Vec<usize>> = self
+            .gateways
+            .iter()
+            .map(|g| g.outbound_per_hour.clone())
+            .collect();
+        let mut people = Vec::new();
+        if map.all_buildings().is_empty() {
+            warn!("The map has no buildings, so only trips passing through are generated");
+        }
+
+        for gateway in &self.gateways {
+            for (hour, num) in gateway.inbound_per_hour.iter().enumerate() {
+                for _ in 0..*num {
+                    let depart = rand_time_in_hour(rng, hour);
+                    let mut goal = None;
+                    if rng.gen_bool(through_traffic) {
+                        goal = self.pick_exit(gateway, hour, &mut remaining_outbound, rng);
+                    }
+                    let goal = match goal.or_else(|| {
+                        map.all_buildings()
+                            .choose(rng)
+                            .map(|b| TripEndpoint::Building(b.id))
+                    }) {
+                        Some(goal) => goal,
+                        None => continue,
+                    };
+                    people.push(person(
+                        depart,
+                        TripEndpoint::Border(gateway.border),
+                        goal,
+                        gateway.mode,
+                        gateway.purpose,
+                    ));
+                }
+            }
+        }
+
+        for (gateway, outbound) in self.gateways.iter().zip(remaining_outbound) {
+            for (hour, num) in outbound.into_iter().enumerate() {
+                for _ in 0..num {
+                    let depart = rand_time_in_hour(rng, hour);
+                    let from = match map.all_buildings().choose(rng) {
+                        Some(b) => TripEndpoint::Building(b.id),
+                        None => continue,
+                    };
+                    people.push(person(
+                        depart,
+                        from,
+                        TripEndpoint::Border(gateway.border),
+                        gateway.mode,
+                        gateway.purpose,
+                    ));
+                }
+            }
+        }
+
+        people
+    }
+
+    /// Pick another gateway to leave through, weighted by its outbound volume that hour.
+    fn pick_exit(
+        &self,
+        entrance: &Gateway,
+        hour: usize,
+        remaining_outbound: &mut [Vec<usize>],
+        rng: &mut XorShiftRng,
+    ) -> Option<TripEndpoint> {
+        let choices: Vec<(usize, usize)> = self
+            .gateways
+            .iter()
+            .enumerate()
+            .filter(|(_, g)| g.mode == entrance.mode && g.border != entrance.border)
+            .filter_map(|(idx, _)| {
+                let num = remaining_outbound[idx].get(hour).cloned().unwrap_or(0);
+                if num > 0 {
+                    Some((idx, num))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let (idx, _) = *choices.choose_weighted(rng, |(_, num)| *num).ok()?;
+        remaining_outbound[idx][hour] -= 1;
+        Some(TripEndpoint::Border(self.gateways[idx].border))
+    }
+}
+
+impl Gateway {
+    fn add(&mut self, hour: usize, inbound: bool) {
+        let counts = if inbound {
+            &mut self.inbound_per_hour
+        } else {
+            &mut self.outbound_per_hour
+        };
+        if counts.len() <= hour {
+            counts.resize(hour + 1, 0);
+        }
+        counts[hour] += 1;
+    }
+}
+
+fn person(
+    depart: Time,
+    from: TripEndpoint,
+    to: TripEndpoint,
+    mode: TripMode,
+    purpose: TripPurpose,
+) -> PersonSpec {
+    PersonSpec {
+        orig_id: None,
+        trips: vec![IndividTrip::new(depart, purpose, from, to, mode)],
+    }
+}
+
+// Gateways written before purposes were tracked were all treated as commuting
+fn default_purpose() -> TripPurpose {
+    TripPurpose::Work
+}
+
+fn rand_time_in_hour(rng: &mut XorShiftRng, hour: usize) -> Time {
+    Time::START_OF_DAY + Duration::hours(hour) + Duration::seconds(rng.gen_range(0.0..3600.0))
+}
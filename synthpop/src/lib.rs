@@ -17,17 +17,25 @@ use map_model::PathConstraints;
 
 pub use self::borders::{MapBorder, MapBorders};
 pub use self::counts::TrafficCounts;
+pub use self::deliveries::{DeliveryDemand, PickupPoint};
 pub use self::endpoint::TripEndpoint;
 pub use self::external::{ExternalPerson, ExternalTrip, ExternalTripEndpoint};
-pub use self::modifier::ScenarioModifier;
+pub use self::gateways::{Gateway, GatewayDemand};
+pub use self::micromobility::{MicromobilityStation, SharedVehicleType};
+pub use self::modifier::{ScenarioModifier, WorkplaceFilter};
+pub use self::park_and_ride::park_and_ride_candidates;
 pub use self::scenario::{IndividTrip, PersonSpec, Scenario, TripPurpose};
 
 mod borders;
 mod counts;
+mod deliveries;
 mod endpoint;
 mod external;
+mod gateways;
 pub mod make;
+mod micromobility;
 mod modifier;
+mod park_and_ride;
 mod scenario;
 
 /// How does a trip primarily happen?
@@ -40,6 +48,12 @@ pub enum TripMode {
     Bike,
     Transit,
     Drive,
+    /// Get picked up by a ridehail vehicle and dropped off at the destination
+    Ridehail,
+    /// Walk to a shared bike or e-scooter, ride it, and leave it near the destination
+    Micromobility,
+    /// Drive to a park-and-ride lot, then take transit. The trip back home does the reverse.
+    ParkAndRide,
 }
 
 impl TripMode {
@@ -49,6 +63,9 @@ impl TripMode {
             TripMode::Bike,
             TripMode::Transit,
             TripMode::Drive,
+            TripMode::Ridehail,
+            TripMode::Micromobility,
+            TripMode::ParkAndRide,
         ]
     }
 
@@ -58,6 +75,9 @@ impl TripMode {
             TripMode::Bike => "bike",
             TripMode::Transit => "use transit",
             TripMode::Drive => "drive",
+            TripMode::Ridehail => "take a ridehail",
+            TripMode::Micromobility => "ride a shared bike or scooter",
+            TripMode::ParkAndRide => "park and ride",
         }
     }
 
@@ -68,6 +88,9 @@ impl TripMode {
             TripMode::Bike => "biking",
             TripMode::Transit => "using transit",
             TripMode::Drive => "driving",
+            TripMode::Ridehail => "riding in a ridehail",
+            TripMode::Micromobility => "riding a shared bike or scooter",
+            TripMode::ParkAndRide => "parking and riding",
         }
     }
 
@@ -77,16 +100,19 @@ impl TripMode {
             TripMode::Bike => "Bike",
             TripMode::Transit => "Bus",
             TripMode::Drive => "Car",
+            TripMode::Ridehail => "Ridehail",
+            TripMode::Micromobility => "Shared bike",
+            TripMode::ParkAndRide => "Park and ride",
         }
     }
 
     pub fn to_constraints(self) -> PathConstraints {
         match self {
             TripMode::Walk => PathConstraints::Pedestrian,
-            TripMode::Bike => PathConstraints::Bike,
+            TripMode::Bike | TripMode::Micromobility => PathConstraints::Bike,
             // TODO WRONG
             TripMode::Transit => PathConstraints::Bus,
-            TripMode::Drive => PathConstraints::Car,
+            TripMode::Drive | TripMode::Ridehail | TripMode::ParkAndRide => PathConstraints::Car,
         }
     }
 
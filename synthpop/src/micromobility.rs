@@ -0,0 +1,27 @@
+//! Shared bikes and e-scooters. Docked systems have stations, and a vehicle taken from a dock has
+//! to be returned to a station with a free dock. Free-floating vehicles are left outside the
+//! rider's destination. Either way, vehicles start the day wherever the scenario places them and
+//! only move when somebody rides them -- nobody rebalances them. Comparing pickups and drop-offs
+//! per place shows where a rebalancing crew would be needed.
+
+use serde::{Deserialize, Serialize};
+
+use map_model::BuildingID;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SharedVehicleType {
+    Bike,
+    /// Limited to 20km/h
+    EScooter,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MicromobilityStation {
+    /// Vehicles are picked up and left on the sidewalk in front of this building
+    pub building: BuildingID,
+    pub vehicle_type: SharedVehicleType,
+    /// How many vehicles are here at the start of the day
+    pub vehicles: usize,
+    /// How many vehicles fit in the docks here. None means the vehicles are free-floating.
+    pub docks: Option<usize>,
+}
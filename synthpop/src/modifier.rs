@@ -8,9 +8,9 @@ use serde::{Deserialize, Serialize};
 
 use abstutil::Timer;
 use geom::{Duration, Time};
-use map_model::Map;
+use map_model::{AmenityType, BuildingID, LandUse, Map};
 
-use crate::{Scenario, TripMode};
+use crate::{IndividTrip, Scenario, TripEndpoint, TripMode, TripPurpose};
 
 /// Transforms an existing Scenario before instantiating it.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
@@ -29,6 +29,85 @@ pub enum ScenarioModifier {
     },
     /// Scenario name
     AddExtraTrips(String),
+    /// Some people don't go to work, like during a pandemic or with a work-from-home policy. Only
+    /// trips with a work purpose to a matching workplace count.
+    WorkFromHome {
+        pct_ppl: usize,
+        workplaces: WorkplaceFilter,
+        /// If true, the trips to and from work are cancelled, as if something happened at the last
+        /// minute. Otherwise, the person just stays home and skips those trips.
+        cancel: bool,
+    },
+    /// Some people who'd have to pay the map's congestion charge switch all of their driving
+    /// trips to another mode. Only people driving into the cordon while it's charged count.
+    AvoidCongestionCharge {
+        pct_ppl: usize,
+        to_mode: TripMode,
+    },
+    /// Some of the bikes people own are electric
+    ElectricBikes {
+        pct_bikes: usize,
+    },
+    /// Shift each person's departures by up to this much earlier or later, all by the same amount
+    /// so their trips stay in order. Used to vary otherwise deterministic runs.
+    DepartureNoise(Duration),
+    /// Randomly chosen people switch all of their trips to one mode, picked in proportion to how
+    /// often each mode is used in the scenario. Only affects people who only travel between
+    /// buildings, since not every border handles every mode.
+    ModeNoise {
+        pct_ppl: usize,
+    },
+}
+
+/// Which workplaces are affected by `ScenarioModifier::WorkFromHome`?
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub enum WorkplaceFilter {
+    /// Includes workplaces off the map
+    Anywhere,
+    /// Workplaces in one of these buildings, like everywhere in some district
+    Buildings(BTreeSet<BuildingID>),
+    /// Workplaces with at least one business of these types
+    Sectors(BTreeSet<AmenityType>),
+    /// Workplaces zoned for one of these uses
+    LandUse(BTreeSet<LandUse>),
+}
+
+impl WorkplaceFilter {
+    fn matches(&self, endpt: TripEndpoint, map: &Map) -> bool {
+        if *self == WorkplaceFilter::Anywhere {
+            return true;
+        }
+        let b = match endpt {
+            TripEndpoint::Building(b) => map.get_b(b),
+            _ => {
+                return false;
+            }
+        };
+        match self {
+            WorkplaceFilter::Anywhere => true,
+            WorkplaceFilter::Buildings(list) => list.contains(&b.id),
+            WorkplaceFilter::Sectors(sectors) => sectors.iter().any(|t| b.has_amenity(*t)),
+            WorkplaceFilter::LandUse(uses) => {
+                b.land_use.map(|u| uses.contains(&u)).unwrap_or(false)
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            WorkplaceFilter::Anywhere => "anywhere".to_string(),
+            WorkplaceFilter::Buildings(list) => format!("{} buildings", list.len()),
+            WorkplaceFilter::Sectors(sectors) => format!(
+                "{} businesses",
+                sectors
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            WorkplaceFilter::LandUse(uses) => format!("{:?} zoning", uses),
+        }
+    }
 }
 
 impl ScenarioModifier {
@@ -96,6 +175,121 @@ impl ScenarioModifier {
                 }
                 s
             }
+            ScenarioModifier::WorkFromHome {
+                pct_ppl,
+                workplaces,
+                cancel,
+            } => {
+                let mut commuters = 0;
+                for person in &mut s.people {
+                    // Only count people who actually commute to one of the workplaces, so the
+                    // percentage applies to them
+                    if !person.trips.iter().any(|trip| {
+                        trip.purpose == TripPurpose::Work
+                            && workplaces.matches(trip.destination, map)
+                    }) {
+                        continue;
+                    }
+                    // Stable as the percentage increases, like ChangeMode
+                    commuters += 1;
+                    if (commuters - 1) % 100 >= *pct_ppl {
+                        continue;
+                    }
+                    skip_work_tours(&mut person.trips, workplaces, *cancel, map);
+                }
+                // People who only commuted have nothing left to do
+                s.people.retain(|p| !p.trips.is_empty());
+                s
+            }
+            ScenarioModifier::AvoidCongestionCharge { pct_ppl, to_mode } => {
+                let charge = match map.get_congestion_charge() {
+                    Some(charge) => charge,
+                    None => {
+                        return s;
+                    }
+                };
+                let in_cordon = |endpt: TripEndpoint| match endpt {
+                    TripEndpoint::Building(b) => {
+                        charge.roads.contains(&map.get_b(b).sidewalk().road)
+                    }
+                    TripEndpoint::SuddenlyAppear(pos) => charge.roads.contains(&pos.lane().road),
+                    TripEndpoint::Border(_) => false,
+                };
+                let mut charged_drivers = 0;
+                for person in &mut s.people {
+                    if !person.trips.iter().any(|trip| {
+                        trip.mode == TripMode::Drive
+                            && !trip.cancelled
+                            && charge.toll_at(trip.depart) > 0.0
+                            && !in_cordon(trip.origin)
+                            && in_cordon(trip.destination)
+                    }) {
+                        continue;
+                    }
+                    // Stable as the percentage increases, like ChangeMode
+                    charged_drivers += 1;
+                    if (charged_drivers - 1) % 100 >= *pct_ppl {
+                        continue;
+                    }
+                    // Switch the whole day, so nobody leaves their car behind
+                    for trip in &mut person.trips {
+                        if trip.mode == TripMode::Drive {
+                            trip.mode = *to_mode;
+                            trip.modified = true;
+                        }
+                    }
+                }
+                s
+            }
+            ScenarioModifier::ElectricBikes { pct_bikes } => {
+                s.ebike_share = (*pct_bikes as f64 / 100.0).min(1.0);
+                s
+            }
+            ScenarioModifier::DepartureNoise(noise) => {
+                if *noise == Duration::ZERO {
+                    return s;
+                }
+                for person in &mut s.people {
+                    // + or - noise
+                    let offset =
+                        Duration::seconds(rng.gen_range((0.0)..=(2.0 * noise.inner_seconds())))
+                            - *noise;
+                    for trip in &mut person.trips {
+                        trip.depart = trip.depart.clamped_sub(offset);
+                        trip.modified = true;
+                    }
+                }
+                s
+            }
+            ScenarioModifier::ModeNoise { pct_ppl } => {
+                let modes: Vec<TripMode> = s
+                    .people
+                    .iter()
+                    .flat_map(|p| p.trips.iter().map(|t| t.mode))
+                    .collect();
+                if modes.is_empty() {
+                    return s;
+                }
+                for person in &mut s.people {
+                    if !person.trips.iter().all(|t| {
+                        matches!(t.origin, TripEndpoint::Building(_))
+                            && matches!(t.destination, TripEndpoint::Building(_))
+                    }) {
+                        continue;
+                    }
+                    if rng.gen_range(0..100) >= *pct_ppl {
+                        continue;
+                    }
+                    let mode = modes[rng.gen_range(0..modes.len())];
+                    for trip in &mut person.trips {
+                        if trip.mode != mode {
+                            trip.mode = mode;
+                            trip.modified = true;
+                        }
+                    }
+                }
+                s
+            }
         }
     }
 
@@ -124,6 +318,81 @@ impl ScenarioModifier {
                 to_mode.map(|m| m.verb())
             ),
             ScenarioModifier::AddExtraTrips(name) => format!("Add extra trips from {}", name),
+            ScenarioModifier::WorkFromHome {
+                pct_ppl,
+                workplaces,
+                cancel,
+            } => format!(
+                "{}% of people working {} {}",
+                pct_ppl,
+                workplaces.describe(),
+                if *cancel {
+                    "cancel their commute"
+                } else {
+                    "work from home"
+                }
+            ),
+            ScenarioModifier::AvoidCongestionCharge { pct_ppl, to_mode } => format!(
+                "{}% of people paying the congestion charge {} instead",
+                pct_ppl,
+                to_mode.verb()
+            ),
+            ScenarioModifier::ElectricBikes { pct_bikes } => {
+                format!("{}% of people's bikes are electric", pct_bikes)
+            }
+            ScenarioModifier::DepartureNoise(noise) => {
+                format!("+/- {} noise on each person's departures", noise)
+            }
+            ScenarioModifier::ModeNoise { pct_ppl } => {
+                format!("{}% of people switch to a random mode", pct_ppl)
+            }
+        }
+    }
+}
+
+/// Remove each trip to a matching workplace, and everything until the person returns to where
+/// they started that trip. If they never return, remove the rest of the day.
+fn skip_work_tours(
+    trips: &mut Vec<IndividTrip>,
+    workplaces: &WorkplaceFilter,
+    cancel: bool,
+    map: &Map,
+) {
+    let mut skip = vec![false; trips.len()];
+    let mut idx = 0;
+    while idx < trips.len() {
+        let trip = &trips[idx];
+        if trip.cancelled
+            || trip.purpose != TripPurpose::Work
+            || !workplaces.matches(trip.destination, map)
+        {
+            idx += 1;
+            continue;
+        }
+        let start = trip.origin;
+        let mut end = idx;
+        while end < trips.len() {
+            skip[end] = true;
+            if trips[end].destination == start {
+                break;
+            }
+            end += 1;
+        }
+        idx = end + 1;
+    }
+
+    if cancel {
+        for (trip, skip) in trips.iter_mut().zip(skip) {
+            if skip {
+                trip.modified = true;
+                trip.cancelled = true;
+            }
+        }
+    } else {
+        let mut iter = skip.into_iter();
+        trips.retain(|_| !iter.next().unwrap());
+        for trip in trips {
+            trip.modified = true;
         }
     }
 }
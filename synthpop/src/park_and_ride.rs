@@ -0,0 +1,33 @@
+//! Park-and-ride lots are big parking lots near a transit stop, where people commuting from far
+//! away leave their car and finish the trip on transit.
+
+use std::collections::BTreeSet;
+
+use geom::Distance;
+use map_model::{Map, ParkingLotID};
+
+/// Lots smaller than this are probably just for some shop
+const MIN_CAPACITY: usize = 50;
+/// As the crow flies, from the lot's sidewalk to the stop
+const MAX_WALK_TO_STOP: Distance = Distance::const_meters(400.0);
+
+/// Marks every big parking lot within a short walk of an open transit stop as a park-and-ride lot.
+pub fn park_and_ride_candidates(map: &Map) -> BTreeSet<ParkingLotID> {
+    let stops: Vec<_> = map
+        .all_transit_stops()
+        .values()
+        .filter(|stop| !stop.closed)
+        .map(|stop| stop.sidewalk_pos.pt(map))
+        .collect();
+    map.all_parking_lots()
+        .iter()
+        .filter(|pl| pl.capacity() >= MIN_CAPACITY)
+        .filter(|pl| {
+            let pt = pl.sidewalk_pos.pt(map);
+            stops
+                .iter()
+                .any(|stop| stop.dist_to(pt) <= MAX_WALK_TO_STOP)
+        })
+        .map(|pl| pl.id)
+        .collect()
+}
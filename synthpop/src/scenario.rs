@@ -7,9 +7,9 @@ use serde::{Deserialize, Serialize};
 use abstio::{CityName, MapName};
 use abstutil::prettyprint_usize;
 use geom::Time;
-use map_model::Map;
+use map_model::{Map, ParkingLotID};
 
-use crate::{OrigPersonID, TripEndpoint, TripMode};
+use crate::{MicromobilityStation, OrigPersonID, TripEndpoint, TripMode};
 
 /// A Scenario describes all the input to a simulation. Usually a scenario covers one day.
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -20,6 +20,15 @@ pub struct Scenario {
     pub people: Vec<PersonSpec>,
     /// None means seed all buses. Otherwise the route name must be present here.
     pub only_seed_buses: Option<BTreeSet<String>>,
+    /// Shared bikes and e-scooters, for trips using `TripMode::Micromobility`
+    #[serde(default)]
+    pub micromobility: Vec<MicromobilityStation>,
+    /// Parking lots where trips using `TripMode::ParkAndRide` can leave their car
+    #[serde(default)]
+    pub park_and_ride: BTreeSet<ParkingLotID>,
+    /// The fraction of people's own bikes that are electric, between 0 and 1
+    #[serde(default)]
+    pub ebike_share: f64,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -65,7 +74,7 @@ impl IndividTrip {
 }
 
 /// Lifted from Seattle's Soundcast model, but seems general enough to use anyhere.
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum TripPurpose {
     Home,
     Work,
@@ -78,6 +87,8 @@ pub enum TripPurpose {
     Recreation,
     Medical,
     ParkAndRideTransfer,
+    /// A van stopping to unload. It stops at the curb instead of parking.
+    Delivery,
 }
 
 impl fmt::Display for TripPurpose {
@@ -98,6 +109,7 @@ impl fmt::Display for TripPurpose {
                 TripPurpose::Recreation => "recreation",
                 TripPurpose::Medical => "medical",
                 TripPurpose::ParkAndRideTransfer => "park-and-ride transfer",
+                TripPurpose::Delivery => "delivery",
             }
         )
     }
@@ -117,6 +129,9 @@ impl Scenario {
             map_name: map.get_name().clone(),
             people: Vec::new(),
             only_seed_buses: Some(BTreeSet::new()),
+            micromobility: Vec::new(),
+            park_and_ride: BTreeSet::new(),
+            ebike_share: 0.0,
         }
     }
 
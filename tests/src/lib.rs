@@ -16,7 +16,8 @@ pub fn import_map(path: String) -> Map {
         clip,
         convert_osm::Options::default(),
         &mut timer,
-    );
+    )
+    .unwrap();
     Map::create_from_raw(raw, map_model::RawToMapOptions::default(), &mut timer)
 }
 
@@ -56,7 +56,7 @@ fn test_map_importer() -> Result<()> {
         "turn_restriction_ltn_boundary",
     ] {
         // TODO It's kind of a hack to reference the crate's directory relative to the data dir.
-        let map = import_map(abstio::path(format!("../tests/input/{}.osm", name)));
+        let mut map = import_map(abstio::path(format!("../tests/input/{}.osm", name)));
         // Enable to debug the result with the normal GUI
         if false {
             map.save();
@@ -235,7 +235,7 @@ fn test_lane_changing(map: &Map) -> Result<()> {
     }
     // Enable to manually watch the scenario
     if false {
-        map.save();
+        map.clone().save();
         scenario.save();
     }
 
@@ -31,7 +31,9 @@ fn main() {
     sim_flags.initialize();
 
     let mut timer = Timer::throwaway();
-    let (mut map, mut sim, mut rng) = sim_flags.load_synchronously(&mut timer);
+    let (mut map, mut sim, mut rng) = sim_flags
+        .load_synchronously(&mut timer)
+        .unwrap_or_else(|err| panic!("Can't set up the simulation: {}", err));
 
     // Set the edits name up-front, so that the savestates get named reasonably too.
     {
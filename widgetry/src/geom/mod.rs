@@ -1,3 +1,5 @@
+use anyhow::Result;
+
 use geom::{Angle, Bounds, GPSBounds, Polygon, Pt2D, Tessellation};
 
 use crate::{
@@ -168,6 +170,12 @@ impl GeomBatch {
         svg::load_svg_from_bytes_uncached(raw).unwrap().0
     }
 
+    /// Like `load_svg_bytes_uncached`, but for SVGs that might be malformed, like ones downloaded
+    /// from somewhere.
+    pub fn parse_svg_bytes(raw: &[u8]) -> Result<GeomBatch> {
+        Ok(svg::load_svg_from_bytes_uncached(raw)?.0)
+    }
+
     /// Transforms all colors in a batch.
     pub fn color(mut self, transformation: RewriteColor) -> GeomBatch {
         for (fancy, _, _) in &mut self.list {